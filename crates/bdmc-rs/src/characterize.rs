@@ -0,0 +1,270 @@
+use crate::controller::CloseLoopController;
+use log::{debug, error, info, warn};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Interval between velocity samples during a characterization step
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(5);
+/// Measured speeds at or below this magnitude are treated as standstill
+const STANDSTILL: f64 = 0.5;
+
+/// Parameters for `CloseLoopController::characterize_motors`
+#[derive(Clone, Debug)]
+pub struct CharacterizeConfig {
+    /// Commanded speeds to step each motor through
+    pub speeds_to_test: Vec<f64>,
+    /// How long to average velocity samples at each step
+    pub dwell: Duration,
+    /// How long to wait after each command before sampling
+    pub settle: Duration,
+}
+
+impl Default for CharacterizeConfig {
+    fn default() -> Self {
+        Self {
+            speeds_to_test: vec![500.0, 1000.0, 2000.0, 3000.0, 4000.0],
+            dwell: Duration::from_millis(200),
+            settle: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Linear motor response with deadband, in wire units
+///
+/// For commands beyond the deadband, `|velocity| = gain * (|command| - deadband)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MotorModel {
+    pub code_sign: i32,
+    pub deadband: f64,
+    pub gain: f64,
+    /// Goodness of the linear fit, 1.0 being a perfect line
+    pub r_squared: f64,
+}
+
+impl MotorModel {
+    /// Command needed to reach `velocity`
+    pub fn command_for(&self, velocity: f64) -> f64 {
+        if velocity == 0.0 || self.gain <= 0.0 {
+            return 0.0;
+        }
+        velocity.signum() * (velocity.abs() / self.gain + self.deadband)
+    }
+}
+
+impl CloseLoopController {
+    /// Step each motor through the test speeds and fit a deadband + gain model
+    ///
+    /// Motors are driven one at a time with all others held at zero, and every motor
+    /// is commanded to zero between steps and before returning, including on error.
+    pub fn characterize_motors(
+        &mut self,
+        config: CharacterizeConfig,
+    ) -> Result<Vec<MotorModel>, Box<dyn std::error::Error>> {
+        info!(
+            "Characterizing {} motors at speeds {:?}",
+            self.motor_infos().len(),
+            config.speeds_to_test
+        );
+
+        let result = self.run_characterization(&config);
+
+        let zeros = vec![0.0; self.motor_infos().len()];
+        if let Err(e) = self.set_motors_speed(&zeros) {
+            error!("Failed to stop motors after characterization: {}", e);
+            if result.is_ok() {
+                return Err(e);
+            }
+        }
+        result
+    }
+
+    fn run_characterization(
+        &mut self,
+        config: &CharacterizeConfig,
+    ) -> Result<Vec<MotorModel>, Box<dyn std::error::Error>> {
+        let motor_infos = self.motor_infos().clone();
+        let mut models = Vec::with_capacity(motor_infos.len());
+
+        for (index, info) in motor_infos.iter().enumerate() {
+            let mut samples = Vec::with_capacity(config.speeds_to_test.len());
+
+            for &speed in &config.speeds_to_test {
+                let mut speeds = vec![0.0; motor_infos.len()];
+                speeds[index] = speed;
+                self.set_motors_speed(&speeds)?;
+                thread::sleep(config.settle);
+
+                let measured = self.sample_velocity(info.code_sign, config.dwell)?;
+                // The wire command carries the motor direction; compare in wire units.
+                let command = (speed * info.direction as f64) as i32 as f64;
+                debug!(
+                    "Motor {} command {} measured {:.1}",
+                    info.code_sign, command, measured
+                );
+                samples.push((command.abs(), measured.abs()));
+
+                self.set_motors_speed(&vec![0.0; motor_infos.len()])?;
+            }
+
+            let model = fit_model(info.code_sign, &samples)?;
+            info!(
+                "Motor {} model: gain {:.4}, deadband {:.1}, r² {:.4}",
+                model.code_sign, model.gain, model.deadband, model.r_squared
+            );
+            models.push(model);
+        }
+
+        Ok(models)
+    }
+
+    /// Average measured velocity over `dwell`, taking at least one sample
+    fn sample_velocity(
+        &mut self,
+        code_sign: i32,
+        dwell: Duration,
+    ) -> Result<f64, Box<dyn std::error::Error>> {
+        let start = Instant::now();
+        let mut total = 0.0;
+        let mut count = 0;
+        loop {
+            total += self.query_velocity(code_sign)?;
+            count += 1;
+            if start.elapsed() >= dwell {
+                break;
+            }
+            thread::sleep(SAMPLE_INTERVAL);
+        }
+        Ok(total / count as f64)
+    }
+}
+
+/// Least-squares fit of `|velocity| = gain * |command| - gain * deadband`
+fn fit_model(
+    code_sign: i32,
+    samples: &[(f64, f64)],
+) -> Result<MotorModel, Box<dyn std::error::Error>> {
+    let moving: Vec<(f64, f64)> = samples
+        .iter()
+        .copied()
+        .filter(|&(_, measured)| measured > STANDSTILL)
+        .collect();
+    if moving.len() < 2 {
+        warn!(
+            "Motor {} moved at only {} of {} test speeds",
+            code_sign,
+            moving.len(),
+            samples.len()
+        );
+        return Err(format!(
+            "Not enough samples above the deadband to fit motor {}",
+            code_sign
+        )
+        .into());
+    }
+
+    let n = moving.len() as f64;
+    let mean_x = moving.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = moving.iter().map(|p| p.1).sum::<f64>() / n;
+    let sxx: f64 = moving.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    let sxy: f64 = moving.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    if sxx == 0.0 {
+        return Err(format!("Motor {} test speeds must differ to fit a model", code_sign).into());
+    }
+
+    let gain = sxy / sxx;
+    let intercept = mean_y - gain * mean_x;
+    let ss_tot: f64 = moving.iter().map(|p| (p.1 - mean_y).powi(2)).sum();
+    let ss_res: f64 = moving
+        .iter()
+        .map(|p| (p.1 - (gain * p.0 + intercept)).powi(2))
+        .sum();
+    let r_squared = if ss_tot == 0.0 {
+        1.0
+    } else {
+        1.0 - ss_res / ss_tot
+    };
+
+    Ok(MotorModel {
+        code_sign,
+        deadband: if gain > 0.0 {
+            (-intercept / gain).max(0.0)
+        } else {
+            0.0
+        },
+        gain,
+        r_squared,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::MotorInfo;
+    use crate::sim::{SimConfig, SimulatedDriver};
+
+    #[test]
+    fn test_characterize_recovers_sim_response() {
+        let driver = SimulatedDriver::new(
+            &[1, 2],
+            SimConfig {
+                time_constant: Duration::from_millis(2),
+                ..SimConfig::default()
+            },
+        );
+        let handle = driver.handle();
+        handle.set_response(1, 0.8, 100.0);
+        handle.set_response(2, 1.2, 250.0);
+
+        let infos = vec![MotorInfo::new(1, 1), MotorInfo::new(2, -1)];
+        let mut controller = CloseLoopController::new(Some(infos), None, None, None).unwrap();
+        controller.attach_serial(Box::new(driver));
+
+        let models = controller
+            .characterize_motors(CharacterizeConfig {
+                speeds_to_test: vec![50.0, 400.0, 800.0, 1200.0, 1600.0],
+                dwell: Duration::from_millis(5),
+                settle: Duration::from_millis(30),
+            })
+            .unwrap();
+
+        assert!((models[0].gain - 0.8).abs() < 0.01);
+        assert!((models[0].deadband - 100.0).abs() < 3.0);
+        assert!((models[1].gain - 1.2).abs() < 0.01);
+        assert!((models[1].deadband - 250.0).abs() < 3.0);
+        assert!(models.iter().all(|m| m.r_squared > 0.999));
+
+        // Every motor is left stopped.
+        assert_eq!(handle.motor(1).unwrap().target, 0.0);
+        assert_eq!(handle.motor(2).unwrap().target, 0.0);
+
+        controller.apply_feedforward(&models[0]);
+        let command = models[0].command_for(480.0);
+        assert!((command - (480.0 / 0.8 + 100.0)).abs() < 5.0);
+    }
+
+    #[test]
+    fn test_characterize_stops_motors_on_error() {
+        let driver = SimulatedDriver::new(
+            &[1],
+            SimConfig {
+                time_constant: Duration::ZERO,
+                ..SimConfig::default()
+            },
+        );
+        let handle = driver.handle();
+        // Never moves: nothing to fit.
+        handle.set_response(1, 1.0, 10_000.0);
+
+        let mut controller =
+            CloseLoopController::new(Some(vec![MotorInfo::new(1, 1)]), None, None, None).unwrap();
+        controller.attach_serial(Box::new(driver));
+
+        let result = controller.characterize_motors(CharacterizeConfig {
+            speeds_to_test: vec![300.0, 600.0],
+            dwell: Duration::ZERO,
+            settle: Duration::ZERO,
+        });
+        assert!(result.is_err());
+        assert_eq!(handle.motor(1).unwrap().target, 0.0);
+    }
+}
@@ -1,12 +1,44 @@
+use crate::characterize::MotorModel;
+use crate::wear::{WearConfig, WearReport, WearSection, WearTracker};
 use log::{debug, error, info, trace, warn};
-use serialport::{DataBits, Parity, SerialPort, StopBits};
-use std::collections::HashMap;
+use serialport::{ClearBuffer, DataBits, Parity, SerialPort, StopBits};
+use std::collections::{HashMap, VecDeque};
 use std::thread;
 use std::time::{Duration, Instant};
 
 pub type Context = HashMap<String, serde_json::Value>;
 pub type Direction = i8; // 1 or -1
 
+/// Outcome of a safety interlock check, ordered from least to most restrictive
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InterlockVerdict {
+    /// Let the command through unchanged
+    Allow,
+    /// Replace the command with an explicit zero speed for every motor
+    ZeroSpeed(String),
+    /// Reject the command without sending anything
+    ///
+    /// Only motion is vetoed: all-zero commands still go through, so the
+    /// motors can always be stopped.
+    Block(String),
+}
+
+impl InterlockVerdict {
+    fn severity(&self) -> u8 {
+        match self {
+            InterlockVerdict::Allow => 0,
+            InterlockVerdict::ZeroSpeed(_) => 1,
+            InterlockVerdict::Block(_) => 2,
+        }
+    }
+}
+
+/// A safety check run against the context before every speed command
+pub type Interlock = Box<dyn Fn(&Context) -> InterlockVerdict + Send + Sync>;
+
+/// Maximum number of speed setpoints kept in the controller history
+pub const HISTORY_CAPACITY: usize = 32;
+
 /// Serial configuration for the motor controller
 #[derive(Clone, Debug)]
 pub struct SerialConfig {
@@ -87,6 +119,12 @@ pub const CLASSIC_MIS: [MotorInfo; 4] = [
 /// 3. **Motor Control**:
 ///    - `set_motors_speed`: Sets the speed of each motor based on a provided list of speeds, ensuring consistency with the provided `MotorInfo`.
 ///    - `send_cmd`: Sends a command to the hardware.
+///    - `revert_speeds`: Re-issues a previously accepted setpoint from a bounded history.
+///    - `emergency_stop`: Stops all motors and bars reverting to setpoints issued before the stop.
+///    - `add_interlock`: Registers a named safety check that can zero or block every speed command.
+///    - `query_velocity`: Reads back a motor's measured velocity.
+///    - `characterize_motors` / `apply_feedforward`: Fits per-motor deadband and gain and compensates for them in `set_motors_velocity`.
+///    - `enable_wear_metrics`: Accumulates per-motor on-time and speed integrals and persists them to the context file.
 ///
 /// 4. **Delay Functions**:
 ///    - `delay`: Introduces a simple delay for a specified duration.
@@ -98,6 +136,12 @@ pub struct CloseLoopController {
     motor_infos: Vec<MotorInfo>,
     context: Context,
     config: SerialConfig,
+    history: VecDeque<(Instant, Vec<i32>)>,
+    estop_at: Option<Instant>,
+    interlocks: Vec<(String, Interlock)>,
+    interlock_trips: HashMap<String, usize>,
+    feedforward: HashMap<i32, MotorModel>,
+    wear: Option<WearTracker>,
 }
 
 impl CloseLoopController {
@@ -141,6 +185,12 @@ impl CloseLoopController {
             motor_infos,
             context,
             config,
+            history: VecDeque::with_capacity(HISTORY_CAPACITY),
+            estop_at: None,
+            interlocks: Vec::new(),
+            interlock_trips: HashMap::new(),
+            feedforward: HashMap::new(),
+            wear: None,
         };
 
         if let Some(port_name) = port {
@@ -206,6 +256,13 @@ impl CloseLoopController {
         self
     }
 
+    /// Attach an already opened serial port, replacing any current one
+    pub fn attach_serial(&mut self, serial: Box<dyn SerialPort>) -> &mut Self {
+        info!("Attaching serial port: {:?}", serial.name());
+        self.serial = Some(serial);
+        self
+    }
+
     /// Get reference to the serial port
     pub fn serial(&self) -> Option<&dyn SerialPort> {
         self.serial.as_deref()
@@ -264,6 +321,27 @@ impl CloseLoopController {
             return Err("Length of speeds must equal the number of motors".into());
         }
 
+        // Stopping is always safe, so interlocks only vet commands that move.
+        let stopping = speeds.iter().all(|&speed| speed == 0.0);
+        let verdict = if stopping {
+            InterlockVerdict::Allow
+        } else {
+            self.check_interlocks()
+        };
+        let zeros;
+        let speeds = match verdict {
+            InterlockVerdict::Allow => speeds,
+            InterlockVerdict::ZeroSpeed(reason) => {
+                warn!("Interlock forcing zero speed: {}", reason);
+                zeros = vec![0.0; speeds.len()];
+                &zeros
+            }
+            InterlockVerdict::Block(reason) => {
+                error!("Interlock blocked motor speed command: {}", reason);
+                return Err(format!("Motor command blocked by interlock: {}", reason).into());
+            }
+        };
+
         if let Some(ref mut serial) = self.serial {
             let mut command = String::new();
             for (motor_info, &speed) in self.motor_infos.iter().zip(speeds.iter()) {
@@ -280,6 +358,32 @@ impl CloseLoopController {
                 Ok(_) => {
                     info!("Motor speeds set successfully");
                     trace!("Command sent: {}", command.trim());
+                    // Record the values that produce exactly the bytes just sent.
+                    let accepted = self
+                        .motor_infos
+                        .iter()
+                        .zip(speeds.iter())
+                        .map(|(info, &speed)| {
+                            (speed * info.direction as f64) as i32 * info.direction as i32
+                        })
+                        .collect();
+                    let now = Instant::now();
+                    if let Some(wear) = self.wear.as_mut() {
+                        let commanded = self
+                            .motor_infos
+                            .iter()
+                            .zip(&accepted)
+                            .map(|(info, &speed)| (info.code_sign, speed as f64))
+                            .collect();
+                        wear.record_command(now, commanded);
+                        if let Err(e) = wear.maybe_persist(now) {
+                            warn!("Failed to save wear metrics: {}", e);
+                        }
+                    }
+                    if self.history.len() == HISTORY_CAPACITY {
+                        self.history.pop_front();
+                    }
+                    self.history.push_back((now, accepted));
                 }
                 Err(e) => {
                     error!("Failed to send motor speed command: {}", e);
@@ -293,6 +397,196 @@ impl CloseLoopController {
         Ok(self)
     }
 
+    /// Register a named interlock, replacing any existing one with the same name
+    ///
+    /// Interlocks run on every `set_motors_speed` call and must be cheap.
+    pub fn add_interlock(&mut self, name: &str, interlock: Interlock) -> &mut Self {
+        info!("Adding interlock: {}", name);
+        self.remove_interlock(name);
+        self.interlocks.push((name.to_string(), interlock));
+        self
+    }
+
+    /// Remove an interlock by name, returning whether it existed
+    pub fn remove_interlock(&mut self, name: &str) -> bool {
+        let count = self.interlocks.len();
+        self.interlocks.retain(|(existing, _)| existing != name);
+        let removed = self.interlocks.len() != count;
+        if removed {
+            info!("Removed interlock: {}", name);
+        }
+        removed
+    }
+
+    /// Number of times each interlock has zeroed or blocked a command
+    pub fn interlock_trips(&self) -> &HashMap<String, usize> {
+        &self.interlock_trips
+    }
+
+    /// Evaluate all interlocks and return the most restrictive verdict
+    fn check_interlocks(&mut self) -> InterlockVerdict {
+        let mut verdict = InterlockVerdict::Allow;
+        for (name, interlock) in &self.interlocks {
+            let current = interlock(&self.context);
+            if current != InterlockVerdict::Allow {
+                debug!("Interlock {} tripped: {:?}", name, current);
+                *self.interlock_trips.entry(name.clone()).or_insert(0) += 1;
+            }
+            if current.severity() > verdict.severity() {
+                verdict = current;
+            }
+        }
+        verdict
+    }
+
+    /// Get the last `n` accepted speed setpoints, oldest first
+    pub fn history(&self, n: usize) -> Vec<(Instant, Vec<i32>)> {
+        let skip = self.history.len().saturating_sub(n);
+        self.history.iter().skip(skip).cloned().collect()
+    }
+
+    /// Re-issue the setpoint accepted `steps_back` commands before the current one
+    ///
+    /// The setpoint goes through `set_motors_speed` again and is recorded as a
+    /// new history entry. Reverting to a setpoint issued before the last
+    /// `emergency_stop` is refused.
+    pub fn revert_speeds(
+        &mut self,
+        steps_back: usize,
+    ) -> Result<&mut Self, Box<dyn std::error::Error>> {
+        debug!("Reverting motor speeds {} steps back", steps_back);
+
+        let Some(index) = self.history.len().checked_sub(steps_back + 1) else {
+            error!(
+                "Cannot revert {} steps back, only {} setpoints in history",
+                steps_back,
+                self.history.len()
+            );
+            return Err("Not enough speed history to revert".into());
+        };
+
+        let (issued_at, speeds) = self.history[index].clone();
+        if self.estop_at.is_some_and(|estop_at| issued_at <= estop_at) {
+            warn!("Refusing to revert to a setpoint issued before an emergency stop");
+            return Err("Cannot revert across an emergency stop".into());
+        }
+
+        info!("Reverting motor speeds to {:?}", speeds);
+        let speeds: Vec<f64> = speeds.iter().map(|&speed| speed as f64).collect();
+        self.set_motors_speed(&speeds)
+    }
+
+    /// Stop all motors immediately
+    ///
+    /// Setpoints accepted before the stop can no longer be reverted to.
+    pub fn emergency_stop(&mut self) -> Result<&mut Self, Box<dyn std::error::Error>> {
+        warn!("Emergency stop requested");
+        let now = Instant::now();
+        self.estop_at = Some(now);
+        if let Some(wear) = self.wear.as_mut() {
+            wear.record_emergency_stop(now);
+        }
+        self.send_cmd(crate::cmds::FULL_STOP)
+    }
+
+    /// Start accumulating wear metrics, loading persisted totals from the context file
+    ///
+    /// Missing or corrupted persisted data starts fresh with a warning. A context file
+    /// that can't be read or isn't a JSON object is never overwritten: saves fail until it is fixed.
+    pub fn enable_wear_metrics(&mut self, config: WearConfig) -> &mut Self {
+        info!("Enabling wear metrics in {}", config.path.display());
+        self.wear = Some(WearTracker::load(config, Instant::now()));
+        self
+    }
+
+    /// Wear totals and since-last-reset values, if wear metrics are enabled
+    pub fn wear_report(&self) -> Option<WearReport> {
+        self.wear.as_ref().map(|wear| wear.report(Instant::now()))
+    }
+
+    /// Clear since-last-reset wear counters; lifetime totals are kept
+    pub fn reset_wear(&mut self, section: WearSection) -> &mut Self {
+        match self.wear.as_mut() {
+            Some(wear) => wear.reset(Instant::now(), section),
+            None => warn!("Attempted to reset wear counters but wear metrics are disabled"),
+        }
+        self
+    }
+
+    /// Save wear metrics to the context file now
+    pub fn persist_wear(&mut self) -> Result<&mut Self, Box<dyn std::error::Error>> {
+        if let Some(wear) = self.wear.as_mut() {
+            wear.persist(Instant::now())?;
+        }
+        Ok(self)
+    }
+
+    /// Query a motor's measured velocity with the `GN` command
+    pub fn query_velocity(&mut self, code_sign: i32) -> Result<f64, Box<dyn std::error::Error>> {
+        let Some(ref mut serial) = self.serial else {
+            error!("Attempted to query velocity but no serial port is open");
+            return Err("No serial port is open".into());
+        };
+
+        serial.clear(ClearBuffer::Input)?;
+        serial.write_all(format!("{}GN\r", code_sign).as_bytes())?;
+
+        let mut reply = Vec::new();
+        let mut byte = [0u8; 1];
+        while reply.last() != Some(&b'\n') {
+            serial.read_exact(&mut byte)?;
+            reply.push(byte[0]);
+        }
+
+        let text = String::from_utf8_lossy(&reply);
+        trace!("Motor {} velocity reply: {:?}", code_sign, text);
+        text.trim().parse::<f64>().map_err(|e| {
+            error!(
+                "Malformed velocity reply from motor {}: {:?}",
+                code_sign, text
+            );
+            format!("Malformed velocity reply {:?}: {}", text.trim(), e).into()
+        })
+    }
+
+    /// Compensate subsequent `set_motors_velocity` requests for a motor's deadband and gain
+    pub fn apply_feedforward(&mut self, model: &MotorModel) -> &mut Self {
+        info!(
+            "Applying feedforward to motor {}: gain {:.4}, deadband {:.1}",
+            model.code_sign, model.gain, model.deadband
+        );
+        self.feedforward.insert(model.code_sign, model.clone());
+        self
+    }
+
+    /// Set motor speeds in measured velocity units, compensated by any applied feedforward models
+    pub fn set_motors_velocity(
+        &mut self,
+        velocities: &[f64],
+    ) -> Result<&mut Self, Box<dyn std::error::Error>> {
+        if velocities.len() != self.motor_infos.len() {
+            error!(
+                "Velocity array length ({}) does not match motor count ({})",
+                velocities.len(),
+                self.motor_infos.len()
+            );
+            return Err("Length of velocities must equal the number of motors".into());
+        }
+
+        let speeds: Vec<f64> = self
+            .motor_infos
+            .iter()
+            .zip(velocities.iter())
+            .map(
+                |(info, &velocity)| match self.feedforward.get(&info.code_sign) {
+                    Some(model) => model.command_for(velocity),
+                    None => velocity,
+                },
+            )
+            .collect();
+        self.set_motors_speed(&speeds)
+    }
+
     /// Send a command to the serial port
     pub fn send_cmd(&mut self, cmd: &[u8]) -> Result<&mut Self, Box<dyn std::error::Error>> {
         debug!("Sending command: {:?}", String::from_utf8_lossy(cmd));
@@ -407,3 +701,262 @@ impl CloseLoopController {
         self
     }
 }
+
+impl Drop for CloseLoopController {
+    fn drop(&mut self) {
+        if let Some(wear) = self.wear.as_mut()
+            && let Err(e) = wear.persist(Instant::now())
+        {
+            error!("Failed to save wear metrics on drop: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serialport::{ClearBuffer, FlowControl};
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    /// Serial port stand-in that records every written byte.
+    struct RecordingPort {
+        written: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl io::Read for RecordingPort {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Ok(0)
+        }
+    }
+
+    impl io::Write for RecordingPort {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SerialPort for RecordingPort {
+        fn name(&self) -> Option<String> {
+            Some("recording".into())
+        }
+        fn baud_rate(&self) -> serialport::Result<u32> {
+            Ok(115200)
+        }
+        fn data_bits(&self) -> serialport::Result<DataBits> {
+            Ok(DataBits::Eight)
+        }
+        fn flow_control(&self) -> serialport::Result<FlowControl> {
+            Ok(FlowControl::None)
+        }
+        fn parity(&self) -> serialport::Result<Parity> {
+            Ok(Parity::None)
+        }
+        fn stop_bits(&self) -> serialport::Result<StopBits> {
+            Ok(StopBits::One)
+        }
+        fn timeout(&self) -> Duration {
+            Duration::from_secs(1)
+        }
+        fn set_baud_rate(&mut self, _: u32) -> serialport::Result<()> {
+            Ok(())
+        }
+        fn set_data_bits(&mut self, _: DataBits) -> serialport::Result<()> {
+            Ok(())
+        }
+        fn set_flow_control(&mut self, _: FlowControl) -> serialport::Result<()> {
+            Ok(())
+        }
+        fn set_parity(&mut self, _: Parity) -> serialport::Result<()> {
+            Ok(())
+        }
+        fn set_stop_bits(&mut self, _: StopBits) -> serialport::Result<()> {
+            Ok(())
+        }
+        fn set_timeout(&mut self, _: Duration) -> serialport::Result<()> {
+            Ok(())
+        }
+        fn write_request_to_send(&mut self, _: bool) -> serialport::Result<()> {
+            Ok(())
+        }
+        fn write_data_terminal_ready(&mut self, _: bool) -> serialport::Result<()> {
+            Ok(())
+        }
+        fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+            Ok(true)
+        }
+        fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+            Ok(true)
+        }
+        fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+            Ok(false)
+        }
+        fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+            Ok(true)
+        }
+        fn bytes_to_read(&self) -> serialport::Result<u32> {
+            Ok(0)
+        }
+        fn bytes_to_write(&self) -> serialport::Result<u32> {
+            Ok(0)
+        }
+        fn clear(&self, _: ClearBuffer) -> serialport::Result<()> {
+            Ok(())
+        }
+        fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+            Ok(Box::new(RecordingPort {
+                written: Arc::clone(&self.written),
+            }))
+        }
+        fn set_break(&self) -> serialport::Result<()> {
+            Ok(())
+        }
+        fn clear_break(&self) -> serialport::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn recording_controller() -> (CloseLoopController, Arc<Mutex<Vec<u8>>>) {
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let infos = vec![MotorInfo::new(1, 1), MotorInfo::new(2, -1)];
+        let mut controller = CloseLoopController::new(Some(infos), None, None, None).unwrap();
+        controller.attach_serial(Box::new(RecordingPort {
+            written: Arc::clone(&written),
+        }));
+        (controller, written)
+    }
+
+    fn take(written: &Arc<Mutex<Vec<u8>>>) -> Vec<u8> {
+        std::mem::take(&mut *written.lock().unwrap())
+    }
+
+    #[test]
+    fn test_revert_reissues_exact_bytes() {
+        let (mut controller, written) = recording_controller();
+
+        controller.set_motors_speed(&[100.7, -50.2]).unwrap();
+        let original = take(&written);
+        controller.set_motors_speed(&[900.0, 900.0]).unwrap();
+        take(&written);
+
+        controller.revert_speeds(1).unwrap();
+        assert_eq!(take(&written), original);
+
+        let history = controller.history(3);
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].1, history[2].1);
+    }
+
+    #[test]
+    fn test_revert_refused_across_estop() {
+        let (mut controller, written) = recording_controller();
+
+        controller.set_motors_speed(&[100.0, 100.0]).unwrap();
+        controller.emergency_stop().unwrap();
+        assert!(take(&written).ends_with(crate::cmds::FULL_STOP));
+
+        assert!(controller.revert_speeds(0).is_err());
+        assert!(take(&written).is_empty());
+
+        controller.set_motors_speed(&[20.0, 20.0]).unwrap();
+        controller.set_motors_speed(&[30.0, 30.0]).unwrap();
+        assert!(controller.revert_speeds(1).is_ok());
+        assert!(controller.revert_speeds(3).is_err());
+    }
+
+    #[test]
+    fn test_interlock_verdicts() {
+        let (mut controller, written) = recording_controller();
+        controller.add_interlock(
+            "lid",
+            Box::new(|ctx: &Context| match ctx.get("lid_open") {
+                Some(serde_json::Value::Bool(true)) => {
+                    InterlockVerdict::ZeroSpeed("lid open".into())
+                }
+                _ => InterlockVerdict::Allow,
+            }),
+        );
+        controller.add_interlock(
+            "battery",
+            Box::new(
+                |ctx: &Context| match ctx.get("battery").and_then(|v| v.as_f64()) {
+                    Some(volts) if volts < 10.0 => InterlockVerdict::Block("battery low".into()),
+                    _ => InterlockVerdict::Allow,
+                },
+            ),
+        );
+
+        controller.set_motors_speed(&[100.0, 100.0]).unwrap();
+        assert_eq!(take(&written), b"1v100\r2v-100\r");
+
+        controller
+            .context_mut()
+            .insert("lid_open".into(), serde_json::Value::Bool(true));
+        controller.set_motors_speed(&[100.0, 100.0]).unwrap();
+        assert_eq!(take(&written), b"1v0\r2v0\r");
+
+        // Block wins over ZeroSpeed and sends nothing.
+        controller
+            .context_mut()
+            .insert("battery".into(), serde_json::json!(9.5));
+        assert!(controller.set_motors_speed(&[100.0, 100.0]).is_err());
+        assert!(take(&written).is_empty());
+        assert_eq!(controller.interlock_trips()["lid"], 2);
+        assert_eq!(controller.interlock_trips()["battery"], 1);
+
+        // A zero command still reaches the wire while blocked, without tripping.
+        controller.set_motors_speed(&[0.0, 0.0]).unwrap();
+        assert_eq!(take(&written), b"1v0\r2v0\r");
+        assert_eq!(controller.interlock_trips()["battery"], 1);
+
+        // Emergency stop is never vetoed.
+        controller.emergency_stop().unwrap();
+        assert_eq!(take(&written), crate::cmds::FULL_STOP);
+
+        assert!(controller.remove_interlock("battery"));
+        assert!(controller.remove_interlock("lid"));
+        assert!(!controller.remove_interlock("lid"));
+        controller.set_motors_speed(&[50.0, 50.0]).unwrap();
+        assert_eq!(take(&written), b"1v50\r2v-50\r");
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let (mut controller, _written) = recording_controller();
+        for i in 0..(HISTORY_CAPACITY + 5) {
+            controller.set_motors_speed(&[i as f64, 0.0]).unwrap();
+        }
+        assert_eq!(controller.history(usize::MAX).len(), HISTORY_CAPACITY);
+        assert_eq!(
+            controller.history(1)[0].1,
+            vec![(HISTORY_CAPACITY + 4) as i32, 0]
+        );
+    }
+
+    #[test]
+    fn test_velocity_applies_feedforward() {
+        let (mut controller, written) = recording_controller();
+        controller.apply_feedforward(&MotorModel {
+            code_sign: 1,
+            deadband: 50.0,
+            gain: 2.0,
+            r_squared: 1.0,
+        });
+
+        // Motor 1 is compensated; motor 2 has no model and passes through, reversed.
+        controller.set_motors_velocity(&[300.0, 120.0]).unwrap();
+        assert_eq!(take(&written), b"1v200\r2v-120\r");
+        controller.set_motors_velocity(&[-100.0, 0.0]).unwrap();
+        assert_eq!(take(&written), b"1v-100\r2v0\r");
+        controller.set_motors_velocity(&[0.0, -40.0]).unwrap();
+        assert_eq!(take(&written), b"1v0\r2v40\r");
+
+        assert!(controller.set_motors_velocity(&[300.0]).is_err());
+        assert!(take(&written).is_empty());
+    }
+}
@@ -1,3 +1,7 @@
+pub mod characterize;
 pub mod cmds;
 pub mod controller;
 pub mod ports;
+pub mod sim;
+pub mod straight;
+pub mod wear;
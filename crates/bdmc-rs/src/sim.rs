@@ -0,0 +1,484 @@
+use log::{debug, trace, warn};
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Configuration of the simulated motor model
+#[derive(Clone, Debug)]
+pub struct SimConfig {
+    /// First-order time constant of the velocity response
+    pub time_constant: Duration,
+    /// Absolute speed limit applied to every setpoint
+    pub max_speed: f64,
+    /// Steady-state velocity per commanded unit beyond the deadband
+    pub gain: f64,
+    /// Commanded magnitude below which a motor does not move
+    pub deadband: f64,
+    /// Delay before a query reply becomes readable
+    pub latency: Duration,
+    /// Advance simulated time with the wall clock; when false, time only moves via `SimHandle::advance`
+    pub realtime: bool,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            time_constant: Duration::from_millis(100),
+            max_speed: 8000.0,
+            gain: 1.0,
+            deadband: 0.0,
+            latency: Duration::ZERO,
+            realtime: true,
+        }
+    }
+}
+
+/// State of a single simulated motor
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MotorState {
+    /// Last commanded speed, after the `max_speed` clamp
+    pub target: f64,
+    pub velocity: f64,
+    pub position: f64,
+    /// Injected fault code; a faulted motor coasts down to zero and reports the code on `OST`
+    pub fault: Option<u32>,
+}
+
+struct SimState {
+    config: SimConfig,
+    motors: BTreeMap<i32, MotorState>,
+    responses: BTreeMap<i32, (f64, f64)>,
+    pending: Vec<u8>,
+    replies: VecDeque<(f64, Vec<u8>)>,
+    now: f64,
+    last_real: Instant,
+}
+
+impl SimState {
+    fn sync_clock(&mut self) {
+        if self.config.realtime {
+            let real_now = Instant::now();
+            let dt = real_now.duration_since(self.last_real).as_secs_f64();
+            self.last_real = real_now;
+            self.step(dt);
+        }
+    }
+
+    fn step(&mut self, dt: f64) {
+        if dt <= 0.0 {
+            return;
+        }
+        let tau = self.config.time_constant.as_secs_f64();
+        for (code, motor) in self.motors.iter_mut() {
+            let (gain, deadband) = self
+                .responses
+                .get(code)
+                .copied()
+                .unwrap_or((self.config.gain, self.config.deadband));
+            let target = if motor.fault.is_some() || motor.target.abs() <= deadband {
+                0.0
+            } else {
+                motor.target.signum() * gain * (motor.target.abs() - deadband)
+            };
+            if tau <= 0.0 {
+                motor.velocity = target;
+                motor.position += target * dt;
+                continue;
+            }
+            // Exact solution of dv/dt = (target - v) / tau over dt.
+            let decay = (-dt / tau).exp();
+            let initial_error = motor.velocity - target;
+            motor.position += target * dt + initial_error * tau * (1.0 - decay);
+            motor.velocity = target + initial_error * decay;
+        }
+        self.now += dt;
+    }
+
+    fn handle_bytes(&mut self, buf: &[u8]) {
+        self.pending.extend_from_slice(buf);
+        while let Some(end) = self.pending.iter().position(|&b| b == b'\r') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line[..line.len() - 1]).into_owned();
+            self.handle_command(line.trim());
+        }
+    }
+
+    fn handle_command(&mut self, cmd: &str) {
+        trace!("Simulated driver received: {:?}", cmd);
+        let split = cmd.find(|c: char| !c.is_ascii_digit()).unwrap_or(cmd.len());
+        let (node, body) = cmd.split_at(split);
+        let node: Option<i32> = node.parse().ok();
+
+        if body == "RESET" {
+            debug!("Simulated driver reset");
+            for motor in self.motors.values_mut() {
+                *motor = MotorState::default();
+            }
+            return;
+        }
+
+        if let Some(speed) = body.strip_prefix('v') {
+            let Ok(speed) = speed.parse::<f64>() else {
+                warn!(
+                    "Simulated driver ignoring malformed speed command: {:?}",
+                    cmd
+                );
+                return;
+            };
+            let target = speed.clamp(-self.config.max_speed, self.config.max_speed);
+            match node {
+                Some(node) => match self.motors.get_mut(&node) {
+                    Some(motor) => motor.target = target,
+                    None => warn!("Simulated driver has no motor {}", node),
+                },
+                None => self.motors.values_mut().for_each(|m| m.target = target),
+            }
+            return;
+        }
+
+        let reply = match (node.and_then(|n| self.motors.get(&n)), body) {
+            (Some(motor), "GN") => Some(motor.velocity.round() as i64),
+            (Some(motor), "POS") => Some(motor.position.round() as i64),
+            (Some(motor), "OST") => Some(motor.fault.unwrap_or(0) as i64),
+            (None, "GN" | "POS" | "OST") => {
+                warn!("Simulated driver query without a known node: {:?}", cmd);
+                None
+            }
+            // Configuration commands (ADL, NPOFF, EEPSAVE, ...) are accepted silently.
+            _ => None,
+        };
+
+        if let Some(value) = reply {
+            let ready_at = self.now + self.config.latency.as_secs_f64();
+            self.replies
+                .push_back((ready_at, format!("{}\r\n", value).into_bytes()));
+        }
+    }
+
+    fn ready_bytes(&self) -> usize {
+        self.replies
+            .iter()
+            .take_while(|(ready_at, _)| *ready_at <= self.now)
+            .map(|(_, bytes)| bytes.len())
+            .sum()
+    }
+}
+
+/// A serial port stand-in that simulates motors behind the BDMC wire protocol.
+///
+/// Accepts the same `"{code_sign}v{speed}\r"` commands `CloseLoopController` sends,
+/// models each motor as a first-order velocity system with a gain and deadband, integrates position, and answers
+/// `{code_sign}GN` (velocity), `{code_sign}POS` (position) and `{code_sign}OST` (fault code) queries.
+///
+/// Attach it with `CloseLoopController::attach_serial` and inspect or drive the model
+/// through the `SimHandle` returned by `handle`.
+pub struct SimulatedDriver {
+    state: Arc<Mutex<SimState>>,
+    timeout: Duration,
+}
+
+/// Shared handle for inspecting the simulation and injecting faults
+#[derive(Clone)]
+pub struct SimHandle {
+    state: Arc<Mutex<SimState>>,
+}
+
+impl SimulatedDriver {
+    /// Create a simulated driver with one motor per code sign
+    pub fn new(code_signs: &[i32], config: SimConfig) -> Self {
+        debug!(
+            "Creating simulated driver for motors {:?} with {:?}",
+            code_signs, config
+        );
+        let motors = code_signs
+            .iter()
+            .map(|&code| (code, MotorState::default()))
+            .collect();
+        Self {
+            state: Arc::new(Mutex::new(SimState {
+                config,
+                motors,
+                responses: BTreeMap::new(),
+                pending: Vec::new(),
+                replies: VecDeque::new(),
+                now: 0.0,
+                last_real: Instant::now(),
+            })),
+            timeout: Duration::from_secs(2),
+        }
+    }
+
+    /// Get a handle sharing this driver's simulation state
+    pub fn handle(&self) -> SimHandle {
+        SimHandle {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl SimHandle {
+    /// Advance simulated time (the only clock when `SimConfig::realtime` is false)
+    pub fn advance(&self, dt: Duration) {
+        self.state.lock().unwrap().step(dt.as_secs_f64());
+    }
+
+    /// Current state of a motor
+    pub fn motor(&self, code_sign: i32) -> Option<MotorState> {
+        let mut state = self.state.lock().unwrap();
+        state.sync_clock();
+        state.motors.get(&code_sign).cloned()
+    }
+
+    /// Inject a fault into a motor, or clear it with `None`
+    pub fn set_fault(&self, code_sign: i32, fault: Option<u32>) {
+        if let Some(motor) = self.state.lock().unwrap().motors.get_mut(&code_sign) {
+            debug!("Simulated motor {} fault set to {:?}", code_sign, fault);
+            motor.fault = fault;
+        }
+    }
+
+    /// Override the gain and deadband of a single motor
+    pub fn set_response(&self, code_sign: i32, gain: f64, deadband: f64) {
+        debug!(
+            "Simulated motor {} response set to gain {} deadband {}",
+            code_sign, gain, deadband
+        );
+        self.state
+            .lock()
+            .unwrap()
+            .responses
+            .insert(code_sign, (gain, deadband));
+    }
+
+    /// Change the reply latency
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().config.latency = latency;
+    }
+
+    /// Elapsed simulated time
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_secs_f64(self.state.lock().unwrap().now)
+    }
+}
+
+impl io::Write for SimulatedDriver {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        state.sync_clock();
+        state.handle_bytes(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl io::Read for SimulatedDriver {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let mut guard = self.state.lock().unwrap();
+            let state = &mut *guard;
+            state.sync_clock();
+
+            if state.ready_bytes() > 0 {
+                let mut count = 0;
+                while count < buf.len() {
+                    let Some((ready_at, bytes)) = state.replies.front_mut() else {
+                        break;
+                    };
+                    if *ready_at > state.now {
+                        break;
+                    }
+                    let n = bytes.len().min(buf.len() - count);
+                    buf[count..count + n].copy_from_slice(&bytes[..n]);
+                    bytes.drain(..n);
+                    count += n;
+                    if bytes.is_empty() {
+                        state.replies.pop_front();
+                    }
+                }
+                return Ok(count);
+            }
+
+            // Only the realtime clock can make a pending reply ready while we wait.
+            if !state.config.realtime || state.replies.is_empty() || Instant::now() >= deadline {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "Simulated driver read timed out",
+                ));
+            }
+            drop(guard);
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
+
+impl SerialPort for SimulatedDriver {
+    fn name(&self) -> Option<String> {
+        Some("simulated".into())
+    }
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        Ok(115200)
+    }
+    fn data_bits(&self) -> serialport::Result<DataBits> {
+        Ok(DataBits::Eight)
+    }
+    fn flow_control(&self) -> serialport::Result<FlowControl> {
+        Ok(FlowControl::None)
+    }
+    fn parity(&self) -> serialport::Result<Parity> {
+        Ok(Parity::None)
+    }
+    fn stop_bits(&self) -> serialport::Result<StopBits> {
+        Ok(StopBits::One)
+    }
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+    fn set_baud_rate(&mut self, _: u32) -> serialport::Result<()> {
+        Ok(())
+    }
+    fn set_data_bits(&mut self, _: DataBits) -> serialport::Result<()> {
+        Ok(())
+    }
+    fn set_flow_control(&mut self, _: FlowControl) -> serialport::Result<()> {
+        Ok(())
+    }
+    fn set_parity(&mut self, _: Parity) -> serialport::Result<()> {
+        Ok(())
+    }
+    fn set_stop_bits(&mut self, _: StopBits) -> serialport::Result<()> {
+        Ok(())
+    }
+    fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()> {
+        self.timeout = timeout;
+        Ok(())
+    }
+    fn write_request_to_send(&mut self, _: bool) -> serialport::Result<()> {
+        Ok(())
+    }
+    fn write_data_terminal_ready(&mut self, _: bool) -> serialport::Result<()> {
+        Ok(())
+    }
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        Ok(true)
+    }
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        Ok(true)
+    }
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        Ok(true)
+    }
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        let mut state = self.state.lock().unwrap();
+        state.sync_clock();
+        Ok(state.ready_bytes() as u32)
+    }
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        Ok(0)
+    }
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        match buffer_to_clear {
+            ClearBuffer::Input => state.replies.clear(),
+            ClearBuffer::Output => state.pending.clear(),
+            ClearBuffer::All => {
+                state.replies.clear();
+                state.pending.clear();
+            }
+        }
+        Ok(())
+    }
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        Ok(Box::new(SimulatedDriver {
+            state: Arc::clone(&self.state),
+            timeout: self.timeout,
+        }))
+    }
+    fn set_break(&self) -> serialport::Result<()> {
+        Ok(())
+    }
+    fn clear_break(&self) -> serialport::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::{CLASSIC_MIS, CloseLoopController};
+    use std::io::{Read, Write};
+
+    fn manual(time_constant_ms: u64) -> SimConfig {
+        SimConfig {
+            time_constant: Duration::from_millis(time_constant_ms),
+            realtime: false,
+            ..SimConfig::default()
+        }
+    }
+
+    fn query(driver: &mut SimulatedDriver, cmd: &str) -> i64 {
+        driver.write_all(cmd.as_bytes()).unwrap();
+        let mut buf = [0u8; 32];
+        let n = driver.read(&mut buf).unwrap();
+        String::from_utf8_lossy(&buf[..n]).trim().parse().unwrap()
+    }
+
+    #[test]
+    fn test_step_response_matches_time_constant() {
+        let driver = SimulatedDriver::new(&[1], manual(200));
+        let handle = driver.handle();
+        let mut controller =
+            CloseLoopController::new(Some(vec![CLASSIC_MIS[0].clone()]), None, None, None).unwrap();
+        controller.attach_serial(Box::new(driver));
+        controller.set_motors_speed(&[1000.0]).unwrap();
+
+        // After one time constant a first-order system reaches 1 - 1/e of the step.
+        for _ in 0..20 {
+            handle.advance(Duration::from_millis(10));
+        }
+        let velocity = handle.motor(1).unwrap().velocity;
+        assert!((velocity - 1000.0 * (1.0 - (-1.0f64).exp())).abs() < 1e-6);
+
+        handle.advance(Duration::from_secs(2));
+        assert!((handle.motor(1).unwrap().velocity - 1000.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_queries_report_velocity_position_and_fault() {
+        let mut driver = SimulatedDriver::new(&[1, 2], manual(0));
+        let handle = driver.handle();
+
+        driver.write_all(b"1v500\r2v-9000\r").unwrap();
+        handle.advance(Duration::from_secs(2));
+        assert_eq!(query(&mut driver, "1GN\r"), 500);
+        assert_eq!(query(&mut driver, "1POS\r"), 1000);
+        assert_eq!(query(&mut driver, "2GN\r"), -8000);
+
+        handle.set_fault(2, Some(4));
+        handle.advance(Duration::from_millis(1));
+        assert_eq!(query(&mut driver, "2OST\r"), 4);
+        assert_eq!(query(&mut driver, "2GN\r"), 0);
+    }
+
+    #[test]
+    fn test_latency_delays_replies() {
+        let mut driver = SimulatedDriver::new(&[1], manual(0));
+        let handle = driver.handle();
+        handle.set_latency(Duration::from_millis(50));
+
+        driver.write_all(b"1GN\r").unwrap();
+        let mut buf = [0u8; 8];
+        assert!(driver.read(&mut buf).is_err());
+        handle.advance(Duration::from_millis(50));
+        assert_eq!(driver.read(&mut buf).unwrap(), 3);
+    }
+}
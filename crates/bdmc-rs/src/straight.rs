@@ -0,0 +1,204 @@
+use crate::controller::CloseLoopController;
+use log::{debug, info};
+use std::thread;
+use std::time::Duration;
+
+/// Context key under which `drive_straight` publishes the applied correction
+pub const STRAIGHT_CORRECTION_KEY: &str = "straight_correction";
+
+/// Feedback used by `drive_straight` to hold a straight line
+pub enum StraightSource {
+    /// Difference between the measured left and right side velocities
+    Velocity,
+    /// External heading error, positive when the robot has drifted counter-clockwise (left)
+    Heading(Box<dyn Fn() -> f64 + Send + Sync>),
+}
+
+/// Parameters for `CloseLoopController::drive_straight`
+pub struct StraightConfig {
+    /// Proportional gain from the feedback error to the speed correction
+    pub kp: f64,
+    /// Largest correction applied to either side, in speed units
+    pub max_correction: f64,
+    /// Feedback source
+    pub source: StraightSource,
+    /// Time between correction updates
+    pub interval: Duration,
+}
+
+impl Default for StraightConfig {
+    fn default() -> Self {
+        Self {
+            kp: 0.5,
+            max_correction: 300.0,
+            source: StraightSource::Velocity,
+            interval: Duration::from_millis(20),
+        }
+    }
+}
+
+impl CloseLoopController {
+    /// Drive both sides at `speed`, correcting for drift until `breaker` returns true
+    ///
+    /// Motors in the first half of the motor infos form the left side, the rest the right.
+    /// A positive correction slows the left side and speeds up the right by the same amount;
+    /// it is published in the context under `STRAIGHT_CORRECTION_KEY` on every update.
+    /// Commands go through `set_motors_speed`, so interlocks apply. The last corrected
+    /// command stays in effect when the breaker fires.
+    pub fn drive_straight<F>(
+        &mut self,
+        speed: f64,
+        config: StraightConfig,
+        mut breaker: F,
+    ) -> Result<&mut Self, Box<dyn std::error::Error>>
+    where
+        F: FnMut() -> bool,
+    {
+        info!(
+            "Driving straight at {} with kp {}, max correction {}",
+            speed, config.kp, config.max_correction
+        );
+
+        let motor_count = self.motor_infos().len();
+        let left_count = motor_count / 2;
+        let mut correction = 0.0;
+
+        loop {
+            let speeds: Vec<f64> = (0..motor_count)
+                .map(|i| {
+                    if i < left_count {
+                        speed - correction
+                    } else {
+                        speed + correction
+                    }
+                })
+                .collect();
+            self.set_motors_speed(&speeds)?;
+            self.context_mut().insert(
+                STRAIGHT_CORRECTION_KEY.to_string(),
+                serde_json::json!(correction),
+            );
+
+            if breaker() {
+                debug!("Straight drive breaker fired");
+                break;
+            }
+            thread::sleep(config.interval);
+
+            let error = match &config.source {
+                StraightSource::Velocity => {
+                    let (left, right) = self.side_velocities(left_count)?;
+                    left - right
+                }
+                StraightSource::Heading(heading) => -heading(),
+            };
+            correction = (config.kp * error).clamp(-config.max_correction, config.max_correction);
+            debug!(
+                "Straight drive error {:.1}, correction {:.1}",
+                error, correction
+            );
+        }
+
+        Ok(self)
+    }
+
+    /// Mean measured velocity of the left and right sides, in commanded direction
+    fn side_velocities(
+        &mut self,
+        left_count: usize,
+    ) -> Result<(f64, f64), Box<dyn std::error::Error>> {
+        let motor_infos = self.motor_infos().clone();
+        let (mut left, mut right) = (0.0, 0.0);
+        for (i, info) in motor_infos.iter().enumerate() {
+            let velocity = self.query_velocity(info.code_sign)? * info.direction as f64;
+            if i < left_count {
+                left += velocity;
+            } else {
+                right += velocity;
+            }
+        }
+        Ok((
+            left / left_count.max(1) as f64,
+            right / (motor_infos.len() - left_count).max(1) as f64,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::CLASSIC_MIS;
+    use crate::sim::{SimConfig, SimulatedDriver};
+    use std::time::Instant;
+
+    fn asymmetric_controller() -> (CloseLoopController, crate::sim::SimHandle) {
+        let driver = SimulatedDriver::new(
+            &[1, 2, 3, 4],
+            SimConfig {
+                time_constant: Duration::from_millis(30),
+                ..SimConfig::default()
+            },
+        );
+        let handle = driver.handle();
+        // The right side loses 20% under load.
+        handle.set_response(3, 0.8, 0.0);
+        handle.set_response(4, 0.8, 0.0);
+
+        let mut controller =
+            CloseLoopController::new(Some(CLASSIC_MIS.to_vec()), None, None, None).unwrap();
+        controller.attach_serial(Box::new(driver));
+        (controller, handle)
+    }
+
+    #[test]
+    fn test_velocity_correction_reduces_side_difference() {
+        let (mut controller, handle) = asymmetric_controller();
+        let start = Instant::now();
+        controller
+            .drive_straight(
+                1000.0,
+                StraightConfig {
+                    kp: 0.5,
+                    interval: Duration::from_millis(10),
+                    ..StraightConfig::default()
+                },
+                || start.elapsed() >= Duration::from_millis(400),
+            )
+            .unwrap();
+
+        // Uncorrected the sides would differ by 200.
+        let left = handle.motor(1).unwrap().velocity;
+        let right = handle.motor(3).unwrap().velocity;
+        assert!((left - right).abs() < 130.0, "left {left}, right {right}");
+
+        let correction = controller.context()[STRAIGHT_CORRECTION_KEY]
+            .as_f64()
+            .unwrap();
+        assert!(correction > 0.0);
+    }
+
+    #[test]
+    fn test_heading_correction_is_clamped() {
+        let (mut controller, handle) = asymmetric_controller();
+        let mut updates = 0;
+        controller
+            .drive_straight(
+                500.0,
+                StraightConfig {
+                    kp: 1000.0,
+                    max_correction: 100.0,
+                    source: StraightSource::Heading(Box::new(|| 1.0)),
+                    interval: Duration::ZERO,
+                },
+                || {
+                    updates += 1;
+                    updates > 2
+                },
+            )
+            .unwrap();
+
+        // Drifted left: slow the right side, speed up the left, within the limit.
+        assert_eq!(handle.motor(1).unwrap().target, 600.0);
+        assert_eq!(handle.motor(3).unwrap().target, 400.0);
+    }
+}
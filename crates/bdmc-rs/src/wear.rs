@@ -0,0 +1,356 @@
+use log::{debug, info, warn};
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Where and how often wear metrics are persisted
+#[derive(Clone, Debug)]
+pub struct WearConfig {
+    /// JSON context file holding the wear section; other top-level keys are preserved
+    pub path: PathBuf,
+    /// Top-level key the wear totals are stored under
+    pub section: String,
+    /// Minimum time between periodic saves triggered by motor commands
+    pub persist_interval: Duration,
+}
+
+impl Default for WearConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("context.json"),
+            section: "wear".to_string(),
+            persist_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Accumulated wear counters
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WearTotals {
+    /// Seconds each motor spent with a nonzero command, by code sign
+    pub motor_on_seconds: BTreeMap<i32, f64>,
+    /// Integral of |commanded speed| over time, by code sign (speed units × seconds)
+    pub speed_seconds: BTreeMap<i32, f64>,
+    /// Number of emergency stops
+    pub emergency_stops: u64,
+}
+
+/// Lifetime wear totals together with the values since the last reset
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WearReport {
+    pub total: WearTotals,
+    pub since_reset: WearTotals,
+}
+
+/// Which since-last-reset counters `reset_wear` clears
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WearSection {
+    /// Every since-last-reset counter
+    All,
+    /// The motor-on time and speed integral of one motor, by code sign
+    Motor(i32),
+    /// The emergency stop count
+    EmergencyStops,
+}
+
+impl WearTotals {
+    fn add_motor(&mut self, code_sign: i32, speed: f64, dt: f64) {
+        if speed == 0.0 {
+            return;
+        }
+        *self.motor_on_seconds.entry(code_sign).or_insert(0.0) += dt;
+        *self.speed_seconds.entry(code_sign).or_insert(0.0) += speed.abs() * dt;
+    }
+
+    fn to_json(&self) -> Value {
+        let per_motor = |map: &BTreeMap<i32, f64>| -> Value {
+            map.iter()
+                .map(|(code, value)| (code.to_string(), json!(value)))
+                .collect::<Map<String, Value>>()
+                .into()
+        };
+        json!({
+            "motor_on_seconds": per_motor(&self.motor_on_seconds),
+            "speed_seconds": per_motor(&self.speed_seconds),
+            "emergency_stops": self.emergency_stops,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let per_motor = |key: &str| -> Option<BTreeMap<i32, f64>> {
+            value
+                .get(key)?
+                .as_object()?
+                .iter()
+                .map(|(code, value)| Some((code.parse().ok()?, value.as_f64()?)))
+                .collect()
+        };
+        Some(Self {
+            motor_on_seconds: per_motor("motor_on_seconds")?,
+            speed_seconds: per_motor("speed_seconds")?,
+            emergency_stops: value.get("emergency_stops")?.as_u64()?,
+        })
+    }
+}
+
+/// Integrates commanded speeds over time and persists the totals
+pub(crate) struct WearTracker {
+    config: WearConfig,
+    report: WearReport,
+    current: Option<(Instant, Vec<(i32, f64)>)>,
+    last_persist: Instant,
+}
+
+impl WearTracker {
+    /// Load persisted totals, starting fresh if the file or section is missing or corrupted
+    pub(crate) fn load(config: WearConfig, now: Instant) -> Self {
+        let report = match fs::read_to_string(&config.path) {
+            Ok(text) => {
+                let section = serde_json::from_str::<Value>(&text)
+                    .ok()
+                    .and_then(|root| root.get(&config.section).cloned());
+                let parsed = section.as_ref().and_then(|section| {
+                    Some(WearReport {
+                        total: WearTotals::from_json(section.get("total")?)?,
+                        since_reset: WearTotals::from_json(section.get("since_reset")?)?,
+                    })
+                });
+                match (section, parsed) {
+                    (_, Some(report)) => {
+                        info!("Loaded wear metrics from {}", config.path.display());
+                        report
+                    }
+                    (None, None) => {
+                        info!(
+                            "No wear section {:?} in {}, starting fresh",
+                            config.section,
+                            config.path.display()
+                        );
+                        WearReport::default()
+                    }
+                    (Some(_), None) => {
+                        warn!(
+                            "Corrupted wear section in {}, starting fresh",
+                            config.path.display()
+                        );
+                        WearReport::default()
+                    }
+                }
+            }
+            Err(e) => {
+                warn!(
+                    "Could not read wear metrics from {}: {}, starting fresh",
+                    config.path.display(),
+                    e
+                );
+                WearReport::default()
+            }
+        };
+
+        Self {
+            config,
+            report,
+            current: None,
+            last_persist: now,
+        }
+    }
+
+    /// Integrate the speeds in effect since the last update up to `now`
+    fn accumulate(&mut self, now: Instant) {
+        let Some((since, speeds)) = self.current.as_mut() else {
+            return;
+        };
+        let dt = now.saturating_duration_since(*since).as_secs_f64();
+        for &(code_sign, speed) in speeds.iter() {
+            self.report.total.add_motor(code_sign, speed, dt);
+            self.report.since_reset.add_motor(code_sign, speed, dt);
+        }
+        *since = now;
+    }
+
+    /// Record newly commanded speeds, by code sign
+    pub(crate) fn record_command(&mut self, now: Instant, speeds: Vec<(i32, f64)>) {
+        self.accumulate(now);
+        self.current = Some((now, speeds));
+    }
+
+    /// Record an emergency stop; all motors count as stopped from `now`
+    pub(crate) fn record_emergency_stop(&mut self, now: Instant) {
+        self.accumulate(now);
+        if let Some((_, speeds)) = self.current.as_mut() {
+            speeds.iter_mut().for_each(|(_, speed)| *speed = 0.0);
+        }
+        self.report.total.emergency_stops += 1;
+        self.report.since_reset.emergency_stops += 1;
+    }
+
+    /// Totals including the command currently in effect
+    pub(crate) fn report(&self, now: Instant) -> WearReport {
+        let mut report = self.report.clone();
+        if let Some((since, speeds)) = &self.current {
+            let dt = now.saturating_duration_since(*since).as_secs_f64();
+            for &(code_sign, speed) in speeds {
+                report.total.add_motor(code_sign, speed, dt);
+                report.since_reset.add_motor(code_sign, speed, dt);
+            }
+        }
+        report
+    }
+
+    pub(crate) fn reset(&mut self, now: Instant, section: WearSection) {
+        self.accumulate(now);
+        let since_reset = &mut self.report.since_reset;
+        match section {
+            WearSection::All => *since_reset = WearTotals::default(),
+            WearSection::Motor(code_sign) => {
+                since_reset.motor_on_seconds.remove(&code_sign);
+                since_reset.speed_seconds.remove(&code_sign);
+            }
+            WearSection::EmergencyStops => since_reset.emergency_stops = 0,
+        }
+        info!("Wear counters reset: {:?}", section);
+    }
+
+    /// Persist if the configured interval has passed since the last save
+    pub(crate) fn maybe_persist(&mut self, now: Instant) -> Result<(), Box<dyn std::error::Error>> {
+        if now.saturating_duration_since(self.last_persist) >= self.config.persist_interval {
+            self.persist(now)?;
+        }
+        Ok(())
+    }
+
+    /// Write the wear section into the context file atomically
+    ///
+    /// A missing file is created. An unreadable file or one that isn't a JSON
+    /// object is an error and is left untouched.
+    pub(crate) fn persist(&mut self, now: Instant) -> Result<(), Box<dyn std::error::Error>> {
+        self.accumulate(now);
+
+        let mut root = match fs::read_to_string(&self.config.path) {
+            Ok(text) => match serde_json::from_str::<Value>(&text) {
+                Ok(Value::Object(map)) => map,
+                _ => {
+                    // Other keys belong to the rest of the app: never clobber them.
+                    warn!(
+                        "Context file {} is not a JSON object, not saving wear metrics",
+                        self.config.path.display()
+                    );
+                    return Err(format!(
+                        "context file {} is not a JSON object",
+                        self.config.path.display()
+                    )
+                    .into());
+                }
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Map::new(),
+            Err(e) => return Err(e.into()),
+        };
+        root.insert(
+            self.config.section.clone(),
+            json!({
+                "total": self.report.total.to_json(),
+                "since_reset": self.report.since_reset.to_json(),
+            }),
+        );
+
+        // Write beside the target and rename so a crash never leaves a torn file.
+        let tmp_path = self.config.path.with_extension("tmp");
+        fs::write(
+            &tmp_path,
+            serde_json::to_string_pretty(&Value::Object(root))?,
+        )?;
+        fs::rename(&tmp_path, &self.config.path)?;
+
+        self.last_persist = now;
+        debug!("Wear metrics saved to {}", self.config.path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_config(name: &str) -> WearConfig {
+        let path =
+            std::env::temp_dir().join(format!("bdmc-wear-{}-{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        WearConfig {
+            path,
+            persist_interval: Duration::from_secs(10),
+            ..WearConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_accumulates_scripted_run() {
+        let start = Instant::now();
+        let at = |secs: f64| start + Duration::from_secs_f64(secs);
+        let mut tracker = WearTracker::load(temp_config("scripted"), start);
+
+        tracker.record_command(at(0.0), vec![(1, 1000.0), (2, -500.0)]);
+        tracker.record_command(at(2.0), vec![(1, 0.0), (2, 500.0)]);
+        tracker.record_emergency_stop(at(3.0));
+        tracker.reset(at(4.0), WearSection::Motor(2));
+        tracker.record_command(at(5.0), vec![(1, 200.0), (2, 200.0)]);
+
+        let report = tracker.report(at(6.0));
+        assert_eq!(report.total.motor_on_seconds[&1], 3.0);
+        assert_eq!(report.total.speed_seconds[&1], 2200.0);
+        assert_eq!(report.total.motor_on_seconds[&2], 4.0);
+        assert_eq!(report.total.speed_seconds[&2], 1700.0);
+        assert_eq!(report.total.emergency_stops, 1);
+        assert_eq!(report.since_reset.motor_on_seconds[&2], 1.0);
+        assert_eq!(report.since_reset.emergency_stops, 1);
+    }
+
+    #[test]
+    fn test_persistence_round_trip() {
+        let config = temp_config("round-trip");
+        fs::write(&config.path, r#"{"IsAligned": true}"#).unwrap();
+        let start = Instant::now();
+
+        let mut tracker = WearTracker::load(config.clone(), start);
+        tracker.record_command(start, vec![(1, 100.0)]);
+        tracker.record_emergency_stop(start + Duration::from_secs(5));
+        tracker
+            .maybe_persist(start + Duration::from_secs(5))
+            .unwrap();
+        // Interval not yet elapsed: the file is untouched.
+        assert_eq!(
+            fs::read_to_string(&config.path).unwrap(),
+            r#"{"IsAligned": true}"#
+        );
+        tracker
+            .maybe_persist(start + Duration::from_secs(10))
+            .unwrap();
+
+        let root: Value = serde_json::from_str(&fs::read_to_string(&config.path).unwrap()).unwrap();
+        assert_eq!(root["IsAligned"], json!(true));
+
+        let reloaded = WearTracker::load(config.clone(), start);
+        assert_eq!(reloaded.report, tracker.report);
+        assert_eq!(reloaded.report.total.speed_seconds[&1], 500.0);
+        fs::remove_file(&config.path).unwrap();
+    }
+
+    #[test]
+    fn test_corrupted_file_starts_fresh() {
+        let config = temp_config("corrupted");
+        fs::write(&config.path, r#"{"wear": {"total": 3}}"#).unwrap();
+        let tracker = WearTracker::load(config.clone(), Instant::now());
+        assert_eq!(tracker.report, WearReport::default());
+
+        fs::write(&config.path, "not json").unwrap();
+        let mut tracker = WearTracker::load(config.clone(), Instant::now());
+        assert_eq!(tracker.report, WearReport::default());
+
+        // Saving must not overwrite a file it can't merge into.
+        assert!(tracker.persist(Instant::now()).is_err());
+        assert_eq!(fs::read_to_string(&config.path).unwrap(), "not json");
+        fs::remove_file(&config.path).unwrap();
+    }
+}
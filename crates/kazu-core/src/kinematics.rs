@@ -0,0 +1,166 @@
+use crate::movement::{FixedAxis, MovementConfig, TurnDirection};
+
+/// Per-wheel speeds in `[front_left, rear_left, front_right, rear_right]` order.
+pub type WheelSpeeds = [i32; 4];
+
+/// Scale a single speed, truncating toward zero.
+pub fn scale_speed(speed: i32, multiplier: f64) -> i32 {
+    (speed as f64 * multiplier) as i32
+}
+
+/// Scale every wheel speed, truncating toward zero.
+pub fn scale_speeds(speeds: WheelSpeeds, multiplier: f64) -> WheelSpeeds {
+    speeds.map(|speed| scale_speed(speed, multiplier))
+}
+
+/// `(left, right)` speeds for an in-place turn.
+pub fn turn_speeds(direction: TurnDirection, speed: i32) -> (i32, i32) {
+    match direction {
+        TurnDirection::Left => (-speed, speed),
+        TurnDirection::Right => (speed, -speed),
+    }
+}
+
+impl MovementConfig {
+    /// `(left, right)` speeds for an arc of `radius` with the outer side at `outer_speed`.
+    ///
+    /// `radius` is measured to the inner track. The function is total:
+    /// - a radius of 0, a negative radius or NaN stops the inner track,
+    ///   pivoting about it;
+    /// - an infinite radius drives both tracks at `outer_speed`.
+    ///
+    /// Negative and NaN radii are usually input mistakes; callers taking
+    /// radii from users should reject them first, as
+    /// `mentabotix_rs::MovingState::differential` does.
+    pub fn differential_speeds(
+        &self,
+        direction: TurnDirection,
+        radius: f64,
+        outer_speed: i32,
+    ) -> (i32, i32) {
+        let inner_speed = if radius == f64::INFINITY {
+            outer_speed
+        } else if radius > 0.0 {
+            (radius / (radius + self.track_width) * outer_speed as f64) as i32
+        } else {
+            0
+        };
+        match direction {
+            TurnDirection::Left => (inner_speed, outer_speed),
+            TurnDirection::Right => (outer_speed, inner_speed),
+        }
+    }
+
+    /// Seconds to sweep `angle_deg` on the arc driven by `differential_speeds`.
+    ///
+    /// The chassis turns at the outer track's velocity over `radius +
+    /// track_width`. Infinite when `outer_speed` is 0.
+    pub fn arc_duration(&self, radius: f64, outer_speed: i32, angle_deg: f64) -> f64 {
+        let outer_velocity = (outer_speed as f64 * self.speed_to_velocity).abs();
+        let angular = outer_velocity / (radius.max(0.0) + self.track_width);
+        angle_deg.abs().to_radians() / angular
+    }
+
+    /// Wheel speeds for a drift pivoting around `fixed_axis`.
+    pub fn drift_speeds(&self, fixed_axis: FixedAxis, speed: i32) -> WheelSpeeds {
+        let diagonal_speed = scale_speed(speed, self.diagonal_multiplier);
+        match fixed_axis {
+            FixedAxis::FrontLeft => [0, speed, diagonal_speed, speed],
+            FixedAxis::RearLeft => [speed, 0, speed, diagonal_speed],
+            FixedAxis::RearRight => [diagonal_speed, speed, 0, speed],
+            FixedAxis::FrontRight => [speed, diagonal_speed, speed, 0],
+        }
+    }
+
+    /// Chassis `(linear, angular)` velocity from left/right track velocities.
+    ///
+    /// Angular velocity is positive counter-clockwise, in radians per unit
+    /// time when the track velocities share units with `track_width`.
+    pub fn chassis_velocity(&self, left: f64, right: f64) -> (f64, f64) {
+        ((left + right) / 2.0, (right - left) / self.track_width)
+    }
+}
+
+/// Planar robot pose used for dead reckoning.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pose {
+    pub x: f64,
+    pub y: f64,
+    /// Heading in radians, counter-clockwise from the x axis.
+    pub heading: f64,
+}
+
+impl Pose {
+    /// Advance the pose by `dt` at constant chassis velocity.
+    ///
+    /// Uses the midpoint heading, which is exact for straight lines and
+    /// in-place turns and second-order accurate for arcs.
+    pub fn integrate(&mut self, linear: f64, angular: f64, dt: f64) {
+        let mid_heading = self.heading + angular * dt / 2.0;
+        self.x += linear * dt * libm::cos(mid_heading);
+        self.y += linear * dt * libm::sin(mid_heading);
+        self.heading += angular * dt;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drift_speeds() {
+        let config = MovementConfig::default();
+        assert_eq!(
+            config.drift_speeds(FixedAxis::FrontLeft, 100),
+            [0, 100, 153, 100]
+        );
+    }
+
+    #[test]
+    fn test_zero_radius_pivots_on_inner_track() {
+        let config = MovementConfig::default();
+        assert_eq!(
+            config.differential_speeds(TurnDirection::Left, 0.0, 200),
+            (0, 200)
+        );
+        assert_eq!(
+            config.differential_speeds(TurnDirection::Right, -50.0, 200),
+            (200, 0)
+        );
+        assert_eq!(
+            config.differential_speeds(TurnDirection::Left, f64::NAN, 200),
+            (0, 200)
+        );
+        assert_eq!(
+            config.differential_speeds(TurnDirection::Left, f64::INFINITY, 200),
+            (200, 200)
+        );
+    }
+
+    #[test]
+    fn test_arc_duration() {
+        let config = MovementConfig {
+            speed_to_velocity: 0.5,
+            ..MovementConfig::default()
+        };
+        // The outer track runs at 100 on a 200 radius: 0.5 rad/s.
+        let duration = config.arc_duration(100.0, 200, 90.0);
+        assert!((duration - core::f64::consts::PI).abs() < 1e-9);
+        assert!(config.arc_duration(100.0, 0, 90.0).is_infinite());
+    }
+
+    #[test]
+    fn test_pose_integrates_full_circle() {
+        let config = MovementConfig::default();
+        let (linear, angular) = config.chassis_velocity(50.0, 150.0);
+        let mut pose = Pose::default();
+        let period = 2.0 * core::f64::consts::PI / angular;
+        let steps = 1000;
+        for _ in 0..steps {
+            pose.integrate(linear, angular, period / steps as f64);
+        }
+        assert!(pose.x.abs() < 1e-6 && pose.y.abs() < 1e-6);
+        assert!((pose.heading - 2.0 * core::f64::consts::PI).abs() < 1e-9);
+    }
+}
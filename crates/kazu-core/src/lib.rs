@@ -0,0 +1,17 @@
+//! Pure movement math shared by the host-side crates and bare-metal targets.
+//!
+//! Everything here is `no_std`. Setpoint generation that returns a `Vec`
+//! needs the `alloc` feature (on by default); serde derives are behind the
+//! `serde` feature.
+#![cfg_attr(not(test), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+pub mod kinematics;
+pub mod movement;
+pub mod ramp;
+
+pub use kinematics::{Pose, WheelSpeeds, scale_speed, scale_speeds, turn_speeds};
+pub use movement::{FixedAxis, MovementConfig, TurnDirection};
+pub use ramp::RampProfile;
@@ -0,0 +1,44 @@
+/// Configuration for movement calculations.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct MovementConfig {
+    /// The width of the track (distance between wheels with same axis).
+    pub track_width: f64,
+    /// The multiplier for diagonal speeds (designed for drift movement).
+    pub diagonal_multiplier: f64,
+    /// Linear velocity of a track per unit of motor speed, in `track_width`
+    /// units per second.
+    pub speed_to_velocity: f64,
+    /// The largest speed magnitude the motor driver accepts.
+    pub max_speed: i32,
+}
+
+impl Default for MovementConfig {
+    fn default() -> Self {
+        Self {
+            track_width: 100.0,
+            diagonal_multiplier: 1.53,
+            speed_to_velocity: 1.0,
+            max_speed: 10000,
+        }
+    }
+}
+
+/// Turn direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TurnDirection {
+    Left,
+    Right,
+}
+
+/// Fixed axis for drift movement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FixedAxis {
+    FrontLeft,
+    RearLeft,
+    RearRight,
+    FrontRight,
+}
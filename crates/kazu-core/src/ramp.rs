@@ -0,0 +1,295 @@
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+/// Most steps [`RampProfile::setpoints`] splits a ramp into; longer ramps
+/// get wider steps instead of more of them.
+pub const MAX_SETPOINT_STEPS: usize = 10_000;
+
+/// Speed shaping profile used when ramping between two speeds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RampProfile {
+    /// Constant slope over the requested duration.
+    Linear,
+    /// Trapezoidal acceleration profile (S-curve velocity).
+    ///
+    /// `max_accel` is in speed units per second, `max_jerk` in speed units
+    /// per second squared. When the speed change is smaller than
+    /// `max_accel² / max_jerk` there is no room for the constant-acceleration
+    /// phase, and the profile degrades to a linear ramp at `max_accel`.
+    ///
+    /// Bounds that are not finite and positive, or so small that the ramp
+    /// time overflows, have no S-curve to follow: the profile falls back to
+    /// `Linear` over the requested duration.
+    SCurve { max_accel: f64, max_jerk: f64 },
+}
+
+impl RampProfile {
+    /// Total ramp time for a speed change of `delta` (absolute, speed units).
+    ///
+    /// `Linear` always takes the requested `duration`; `SCurve` derives its
+    /// own duration from the accel/jerk bounds.
+    pub fn ramp_duration(&self, delta: f64, duration: f64) -> f64 {
+        match self.effective(delta) {
+            RampProfile::Linear => duration,
+            RampProfile::SCurve {
+                max_accel,
+                max_jerk,
+            } => {
+                if delta <= 0.0 {
+                    0.0
+                } else if Self::is_degenerate(delta, max_accel, max_jerk) {
+                    delta / max_accel
+                } else {
+                    delta / max_accel + max_accel / max_jerk
+                }
+            }
+        }
+    }
+
+    /// Generate `(time, speed)` setpoints from `start_speed` to `end_speed`.
+    ///
+    /// Setpoints are spaced evenly at most `interval` seconds apart, or wider
+    /// when that would take more than [`MAX_SETPOINT_STEPS`] steps. The first
+    /// one is `start_speed` at `t = 0` and the last one is exactly `end_speed`;
+    /// a ramp time that is not finite jumps straight to `end_speed`.
+    #[cfg(feature = "alloc")]
+    pub fn setpoints(
+        &self,
+        start_speed: i32,
+        end_speed: i32,
+        duration: f64,
+        interval: f64,
+    ) -> Vec<(f64, i32)> {
+        let deviation = (end_speed - start_speed) as f64;
+        let total = self.ramp_duration(deviation.abs(), duration);
+        let total = if total.is_finite() {
+            total.max(0.0)
+        } else {
+            0.0
+        };
+        if total == 0.0 || deviation == 0.0 {
+            return vec![(0.0, start_speed), (total, end_speed)];
+        }
+
+        let step_count =
+            (libm::ceil(total / interval.max(1e-6)).max(1.0) as usize).min(MAX_SETPOINT_STEPS);
+        let step = total / step_count as f64;
+
+        (0..=step_count)
+            .map(|i| {
+                let t = if i == step_count {
+                    total
+                } else {
+                    i as f64 * step
+                };
+                let speed = if i == step_count {
+                    end_speed
+                } else {
+                    self.speed_at(start_speed, end_speed, duration, t)
+                };
+                (t, speed)
+            })
+            .collect()
+    }
+
+    /// Speed `t` seconds into the ramp from `start_speed` to `end_speed`.
+    ///
+    /// Allocation-free counterpart of [`RampProfile::setpoints`] for callers
+    /// that sample the profile on their own clock.
+    pub fn speed_at(&self, start_speed: i32, end_speed: i32, duration: f64, t: f64) -> i32 {
+        let deviation = (end_speed - start_speed) as f64;
+        let total = self.ramp_duration(deviation.abs(), duration);
+        if t >= total || deviation == 0.0 {
+            return if t >= total { end_speed } else { start_speed };
+        }
+        let progress = self.progress_at(t, deviation.abs(), total);
+        start_speed + libm::round(deviation * progress) as i32
+    }
+
+    /// Fraction of the speed change reached at time `t` (0.0–1.0).
+    fn progress_at(&self, t: f64, delta: f64, total: f64) -> f64 {
+        match self.effective(delta) {
+            RampProfile::Linear => (t / total).clamp(0.0, 1.0),
+            RampProfile::SCurve {
+                max_accel,
+                max_jerk,
+            } => {
+                if Self::is_degenerate(delta, max_accel, max_jerk) {
+                    return (t / total).clamp(0.0, 1.0);
+                }
+                Self::scurve_velocity(t, delta, max_accel, max_jerk, total) / delta
+            }
+        }
+    }
+
+    /// S-curve speed change reached at `t` for a full (non-degenerate) profile.
+    fn scurve_velocity(t: f64, delta: f64, max_accel: f64, max_jerk: f64, total: f64) -> f64 {
+        let jerk_time = max_accel / max_jerk;
+        if t <= 0.0 {
+            0.0
+        } else if t < jerk_time {
+            max_jerk * t * t / 2.0
+        } else if t < total - jerk_time {
+            max_jerk * jerk_time * jerk_time / 2.0 + max_accel * (t - jerk_time)
+        } else if t < total {
+            let remaining = total - t;
+            delta - max_jerk * remaining * remaining / 2.0
+        } else {
+            delta
+        }
+    }
+
+    /// The profile followed for a speed change of `delta`: `Linear` in place
+    /// of an `SCurve` without usable bounds.
+    fn effective(&self, delta: f64) -> RampProfile {
+        let usable = |bound: f64| bound.is_finite() && bound > 0.0;
+        match *self {
+            RampProfile::SCurve {
+                max_accel,
+                max_jerk,
+            } if !(usable(max_accel)
+                && usable(max_jerk)
+                && (delta / max_accel + max_accel / max_jerk).is_finite()) =>
+            {
+                RampProfile::Linear
+            }
+            profile => profile,
+        }
+    }
+
+    fn is_degenerate(delta: f64, max_accel: f64, max_jerk: f64) -> bool {
+        max_accel <= 0.0 || max_jerk <= 0.0 || delta < max_accel * max_accel / max_jerk
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear_setpoints() {
+        let points = RampProfile::Linear.setpoints(0, 100, 1.0, 0.25);
+        let speeds: Vec<i32> = points.iter().map(|&(_, s)| s).collect();
+        assert_eq!(speeds, vec![0, 25, 50, 75, 100]);
+        assert_eq!(points.last().unwrap().0, 1.0);
+    }
+
+    #[test]
+    fn test_scurve_respects_bounds() {
+        let (max_accel, max_jerk) = (2000.0, 10000.0);
+        let profile = RampProfile::SCurve {
+            max_accel,
+            max_jerk,
+        };
+        for (start, end) in [(0, 10000), (8000, -2000)] {
+            let points = profile.setpoints(start, end, 0.0, 0.05);
+            assert_eq!(points.last().unwrap().1, end);
+            let dt = points[1].0 - points[0].0;
+            assert!(
+                points
+                    .windows(2)
+                    .all(|w| (w[1].0 - w[0].0 - dt).abs() < 1e-9)
+            );
+
+            // Setpoints are rounded to whole units, so each speed difference
+            // may be off by one unit.
+            let accel_tolerance = 1.0 / dt;
+            let jerk_tolerance = 2.0 / (dt * dt);
+            let accels: Vec<f64> = points
+                .windows(2)
+                .map(|w| (w[1].1 - w[0].1) as f64 / dt)
+                .collect();
+            for a in &accels {
+                assert!(
+                    a.abs() <= max_accel + accel_tolerance,
+                    "accel {} exceeds bound",
+                    a
+                );
+            }
+            assert!(
+                accels
+                    .iter()
+                    .any(|a| a.abs() >= max_accel - accel_tolerance)
+            );
+            for w in accels.windows(2) {
+                let jerk = (w[1] - w[0]).abs() / dt;
+                assert!(
+                    jerk <= max_jerk + jerk_tolerance,
+                    "jerk {} exceeds bound",
+                    jerk
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_scurve_reaches_target_downward() {
+        let profile = RampProfile::SCurve {
+            max_accel: 1000.0,
+            max_jerk: 5000.0,
+        };
+        let points = profile.setpoints(800, -200, 0.0, 0.02);
+        assert_eq!(points.first().unwrap().1, 800);
+        assert_eq!(points.last().unwrap().1, -200);
+        assert!(points.windows(2).all(|w| w[1].1 <= w[0].1));
+    }
+
+    #[test]
+    fn test_scurve_degenerate_falls_back_to_linear() {
+        // 100 < 1000² / 5000 = 200 → no constant-accel phase fits.
+        let profile = RampProfile::SCurve {
+            max_accel: 1000.0,
+            max_jerk: 5000.0,
+        };
+        assert!((profile.ramp_duration(100.0, 0.0) - 0.1).abs() < 1e-9);
+        let speeds: Vec<i32> = profile
+            .setpoints(0, 100, 0.0, 0.025)
+            .iter()
+            .map(|&(_, s)| s)
+            .collect();
+        assert_eq!(speeds, vec![0, 25, 50, 75, 100]);
+    }
+
+    #[test]
+    fn test_scurve_invalid_bounds_fall_back_to_linear() {
+        for (max_accel, max_jerk) in [
+            (0.0, 5000.0),
+            (-1000.0, 5000.0),
+            (f64::NAN, 5000.0),
+            (f64::INFINITY, 5000.0),
+            (1000.0, 0.0),
+            (1000.0, -5000.0),
+            (1000.0, f64::NAN),
+            // Positive, but the ramp time overflows
+            (1e-310, 5000.0),
+        ] {
+            let profile = RampProfile::SCurve {
+                max_accel,
+                max_jerk,
+            };
+            assert_eq!(profile.ramp_duration(100.0, 1.0), 1.0);
+            let speeds: Vec<i32> = profile
+                .setpoints(0, 100, 1.0, 0.25)
+                .iter()
+                .map(|&(_, s)| s)
+                .collect();
+            assert_eq!(speeds, vec![0, 25, 50, 75, 100], "{max_accel}, {max_jerk}");
+            assert_eq!(profile.speed_at(0, 100, 1.0, 0.5), 50);
+        }
+    }
+
+    #[test]
+    fn test_long_ramps_are_capped() {
+        // A valid but tiny acceleration makes a ramp of 1e11 seconds
+        let profile = RampProfile::SCurve {
+            max_accel: 1e-9,
+            max_jerk: 1.0,
+        };
+        let points = profile.setpoints(0, 100, 0.0, 0.1);
+        assert_eq!(points.len(), MAX_SETPOINT_STEPS + 1);
+        assert_eq!(points.last().unwrap().1, 100);
+        let points = RampProfile::Linear.setpoints(0, 100, f64::INFINITY, 0.1);
+        assert_eq!(points, vec![(0.0, 0), (0.0, 100)]);
+    }
+}
@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::{Botix, BotixError, StructureError};
+use crate::controller::MotorController;
+use crate::state::{MovingState, SpeedPattern};
+use crate::transition::{BreakerFn, BreakerResult, MovingTransition};
+
+/// A state as stored in a behavior file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSpec {
+    /// ID the state had when saved; only links transitions within the file.
+    pub id: usize,
+    /// Concrete speeds of the state.
+    pub pattern: SpeedPattern,
+    /// Whether this is a wait state (see `MovingState::wait`).
+    #[serde(default)]
+    pub wait: bool,
+    /// See `MovingState::with_max_total_duration`.
+    #[serde(default)]
+    pub max_total_duration: Option<f64>,
+}
+
+/// A transition as stored in a behavior file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransitionSpec {
+    pub duration: f64,
+    pub check_interval: f64,
+    /// IDs of `StateSpec`s the transition leaves from.
+    pub from_states: Vec<usize>,
+    /// Branches as `(breaker result, StateSpec ID)` pairs.
+    pub to_states: Vec<(BreakerResult, usize)>,
+    /// Name of the breaker in the registry passed to `Botix::load_behavior`.
+    #[serde(default)]
+    pub breaker: Option<String>,
+    #[serde(default)]
+    pub keyed: bool,
+    #[serde(default)]
+    pub fallback: bool,
+    #[serde(default)]
+    pub expected_keys: Option<Vec<String>>,
+}
+
+/// Contents of a behavior file: a Botix graph without its closures.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BehaviorSpec {
+    pub states: Vec<StateSpec>,
+    pub transitions: Vec<TransitionSpec>,
+    #[serde(default)]
+    pub allow_cycles: bool,
+}
+
+impl<C> Botix<C> {
+    /// Describe the graph as a `BehaviorSpec`, states and transitions in ID
+    /// order.
+    ///
+    /// Returns `BotixError::Behavior` for anything a file can't hold: dynamic
+    /// speeds, state controllers, hooks, and breakers not set with
+    /// `MovingTransition::with_named_breaker`.
+    pub fn to_behavior_spec(&self) -> Result<BehaviorSpec, BotixError> {
+        let mut state_ids: Vec<usize> = self.states.keys().copied().collect();
+        state_ids.sort_unstable();
+        let mut states = Vec::with_capacity(state_ids.len());
+        for id in state_ids {
+            let state = &self.states[&id];
+            let unsaved = if state.is_dynamic() {
+                Some("dynamic speeds")
+            } else if state.controller().is_some() {
+                Some("a state controller")
+            } else if !state.before_entering().is_empty() || !state.after_exiting().is_empty() {
+                Some("hooks")
+            } else {
+                None
+            };
+            if let Some(what) = unsaved {
+                return Err(BotixError::Behavior(format!(
+                    "State {} has {}, which can't be saved",
+                    id, what
+                )));
+            }
+            states.push(StateSpec {
+                id,
+                pattern: state.speed_pattern().clone(),
+                wait: state.is_wait(),
+                max_total_duration: state.max_total_duration(),
+            });
+        }
+
+        let mut trans_ids: Vec<usize> = self.transitions.keys().copied().collect();
+        trans_ids.sort_unstable();
+        let mut transitions = Vec::with_capacity(trans_ids.len());
+        for id in trans_ids {
+            let trans = &self.transitions[&id];
+            if trans.breaker.is_some() && trans.breaker_name.is_none() {
+                return Err(BotixError::Behavior(format!(
+                    "Transition {} has an unnamed breaker, which can't be saved",
+                    id
+                )));
+            }
+            let mut to_states: Vec<(BreakerResult, usize)> = trans
+                .to_states
+                .iter()
+                .map(|(key, &to)| (key.clone(), to))
+                .collect();
+            to_states.sort_by_key(|(key, to)| (*to, key.to_string()));
+            transitions.push(TransitionSpec {
+                duration: trans.duration,
+                check_interval: trans.check_interval,
+                from_states: trans.from_states.clone(),
+                to_states,
+                breaker: trans.breaker_name.clone(),
+                keyed: trans.keyed,
+                fallback: trans.fallback,
+                expected_keys: trans.expected_keys.clone(),
+            });
+        }
+
+        Ok(BehaviorSpec {
+            states,
+            transitions,
+            allow_cycles: self.allow_cycles,
+        })
+    }
+
+    /// Save the graph as a JSON behavior file; see [`Botix::to_behavior_spec`].
+    pub fn save_behavior(&self, path: &Path) -> Result<(), BotixError> {
+        let json = serde_json::to_string_pretty(&self.to_behavior_spec()?)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+impl<C: MotorController> Botix<C> {
+    /// Build a graph from a `BehaviorSpec`, with fresh state and transition
+    /// IDs and the same topology.
+    ///
+    /// Breakers are looked up by name in `breakers`; an unknown name is
+    /// `BotixError::UnknownBreaker`. A negative duration is
+    /// `BotixError::InvalidMovement`. The graph is then checked as in
+    /// [`Botix::build_full`], its structure errors returned as
+    /// `BotixError::InvalidStructure`.
+    pub fn from_behavior_spec(
+        controller: C,
+        spec: BehaviorSpec,
+        breakers: &HashMap<String, BreakerFn>,
+    ) -> Result<Self, BotixError> {
+        let source = spec.clone();
+        let mut ids: HashMap<usize, usize> = HashMap::new();
+        let mut states = Vec::with_capacity(spec.states.len());
+        for state_spec in spec.states {
+            let mut state = if state_spec.wait {
+                MovingState::wait()
+            } else {
+                MovingState::new(state_spec.pattern)
+            };
+            if let Some(secs) = state_spec.max_total_duration {
+                state = state.with_max_total_duration(secs);
+            }
+            if ids.insert(state_spec.id, state.id()).is_some() {
+                return Err(BotixError::Behavior(format!(
+                    "Duplicate state ID: {}",
+                    state_spec.id
+                )));
+            }
+            states.push(state);
+        }
+        let state_id = |id: usize| {
+            ids.get(&id)
+                .copied()
+                .ok_or_else(|| BotixError::Behavior(format!("Unknown state ID: {}", id)))
+        };
+
+        let mut transitions = Vec::with_capacity(spec.transitions.len());
+        for trans_spec in spec.transitions {
+            let mut trans = MovingTransition::new(trans_spec.duration)
+                .map_err(|e| BotixError::InvalidMovement(e.to_string()))?
+                .with_check_interval(trans_spec.check_interval);
+            if let Some(name) = trans_spec.breaker {
+                let Some(breaker) = breakers.get(&name) else {
+                    let mut available: Vec<String> = breakers.keys().cloned().collect();
+                    available.sort_unstable();
+                    return Err(BotixError::UnknownBreaker { name, available });
+                };
+                trans = trans.with_named_breaker(name, breaker.clone());
+            }
+            for from in trans_spec.from_states {
+                trans = trans.with_from_state(state_id(from)?);
+            }
+            for (key, to) in trans_spec.to_states {
+                trans = trans.with_to_state(key, state_id(to)?);
+            }
+            trans.keyed = trans_spec.keyed;
+            trans.fallback = trans_spec.fallback;
+            trans.expected_keys = trans_spec.expected_keys;
+            transitions.push(trans);
+        }
+
+        let mut botix =
+            Self::build_full(controller, states, transitions).map_err(|e| match e
+                .downcast::<StructureError>()
+            {
+                Ok(e) => BotixError::InvalidStructure(vec![*e]),
+                Err(e) => BotixError::Behavior(e.to_string()),
+            })?;
+        botix.allow_cycles = spec.allow_cycles;
+        botix.spec = Some(source);
+        Ok(botix)
+    }
+
+    /// Load a graph saved with [`Botix::save_behavior`]; see
+    /// [`Botix::from_behavior_spec`].
+    pub fn load_behavior(
+        controller: C,
+        path: &Path,
+        breakers: &HashMap<String, BreakerFn>,
+    ) -> Result<Self, BotixError> {
+        let json = fs::read_to_string(path)?;
+        let spec: BehaviorSpec = serde_json::from_str(&json)?;
+        Self::from_behavior_spec(controller, spec, breakers)
+    }
+}
@@ -0,0 +1,269 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::state::{ArrowStyle, MovingState, PatternType};
+use crate::transition::{BreakerResult, MovingTransition};
+
+use super::{Botix, BotixError};
+
+/// A drawn node: a state ID and its label.
+pub(crate) struct Node {
+    pub(crate) id: usize,
+    pub(crate) label: String,
+    pub(crate) wait: bool,
+}
+
+/// A drawn edge: one from-state → to-state pair of a transition.
+pub(crate) struct Edge {
+    from: usize,
+    to: usize,
+    label: String,
+}
+
+/// Edges of every transition, sorted by transition ID, then by the
+/// destination. Labels hold the duration, prefixed by the branch key when the
+/// transition branches.
+pub(crate) fn transition_edges<'a>(
+    transitions: impl IntoIterator<Item = &'a MovingTransition>,
+) -> Vec<Edge> {
+    let mut transitions: Vec<&MovingTransition> = transitions.into_iter().collect();
+    transitions.sort_unstable_by_key(|t| t.id());
+    let mut edges = Vec::new();
+    for t in transitions {
+        let mut branches: Vec<(&BreakerResult, usize)> = t
+            .to_states
+            .iter()
+            .map(|(key, &to_id)| (key, to_id))
+            .collect();
+        branches.sort_by_key(|&(key, to_id)| (to_id, key.to_string()));
+        for &from in &t.from_states {
+            for &(key, to) in &branches {
+                let label = if t.is_branching() {
+                    format!("[{}] {:.3}s", key, t.duration)
+                } else if t.has_breaker() {
+                    format!("{:.3}s [breaker]", t.duration)
+                } else {
+                    format!("{:.3}s", t.duration)
+                };
+                edges.push(Edge { from, to, label });
+            }
+        }
+    }
+    edges
+}
+
+/// PlantUML state diagram text for `nodes` and `edges`, with `starts` hanging
+/// off the initial node and `ends` leading to the final node. Callers sort
+/// their input by ID.
+pub(crate) fn render_plantuml(
+    nodes: &[Node],
+    edges: &[Edge],
+    starts: &[usize],
+    ends: &[usize],
+    arrow_style: ArrowStyle,
+) -> String {
+    let arrow = arrow_style.as_str();
+    let mut out = String::from("@startuml\n");
+    for sid in starts {
+        let _ = writeln!(out, "[*] {} s{}", arrow, sid);
+    }
+    out.push('\n');
+
+    // Wait states get a distinct fill so they stand out from halts.
+    for node in nodes {
+        let _ = write!(out, "state \"{}\" as s{}", node.label, node.id);
+        if node.wait {
+            out.push_str(" #LightYellow");
+        }
+        out.push('\n');
+    }
+    out.push('\n');
+
+    for edge in edges {
+        let _ = writeln!(
+            out,
+            "s{} {} s{} : {}",
+            edge.from, arrow, edge.to, edge.label
+        );
+    }
+    out.push('\n');
+
+    for sid in ends {
+        let _ = writeln!(out, "s{} {} [*]", sid, arrow);
+    }
+    out.push_str("@enduml\n");
+    out
+}
+
+impl<C> Botix<C> {
+    /// States sorted by ID, so exported text is stable across runs.
+    fn sorted_states(&self) -> Vec<&MovingState> {
+        let mut states: Vec<&MovingState> = self.states.values().collect();
+        states.sort_unstable_by_key(|s| s.id());
+        states
+    }
+
+    /// See [`transition_edges`].
+    fn edges(&self) -> Vec<Edge> {
+        transition_edges(self.transitions.values())
+    }
+
+    /// Render the graph as PlantUML state diagram text.
+    ///
+    /// Each state becomes a node labelled with its `Display` output. Every
+    /// from-state → to-state pair of a transition becomes an edge labelled
+    /// with the transition duration, prefixed by the branch key when the
+    /// transition branches. The start state hangs off the initial node and
+    /// end states lead to the final node. Output is sorted by ID, so it is
+    /// stable across runs.
+    pub fn to_plantuml_string(&self, arrow_style: ArrowStyle) -> String {
+        let nodes: Vec<Node> = self
+            .sorted_states()
+            .into_iter()
+            .map(|state| Node {
+                id: state.id(),
+                label: state.to_string(),
+                wait: state.is_wait(),
+            })
+            .collect();
+        let mut end_states: Vec<usize> = self.end_states().into_iter().collect();
+        end_states.sort_unstable();
+        render_plantuml(
+            &nodes,
+            &self.edges(),
+            &[self.start_state],
+            &end_states,
+            arrow_style,
+        )
+    }
+
+    /// Render the graph as Graphviz DOT text.
+    ///
+    /// Same nodes and edges as [`Botix::to_plantuml_string`]. Node shape and
+    /// fill follow the state's `PatternType`, wait states are filled yellow,
+    /// and the start state is drawn with a bold outline.
+    pub fn to_dot_string(&self) -> String {
+        let mut out = String::from("digraph botix {\n    node [style=filled];\n\n");
+        for state in self.sorted_states() {
+            let (shape, fill) = match state.pattern_type() {
+                PatternType::Full => ("box", "lightblue"),
+                PatternType::LeftRight => ("ellipse", "lightgreen"),
+                PatternType::Individual => ("hexagon", "lightsalmon"),
+            };
+            let fill = if state.is_wait() { "lightyellow" } else { fill };
+            let _ = write!(
+                out,
+                "    s{} [label=\"{}\", shape={}, fillcolor={}",
+                state.id(),
+                escape_dot(&state.to_string()),
+                shape,
+                fill
+            );
+            if state.id() == self.start_state {
+                out.push_str(", style=\"filled,bold\", penwidth=2");
+            }
+            out.push_str("];\n");
+        }
+        out.push('\n');
+        for edge in self.edges() {
+            let _ = writeln!(
+                out,
+                "    s{} -> s{} [label=\"{}\"];",
+                edge.from,
+                edge.to,
+                escape_dot(&edge.label)
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Render the graph as a Mermaid flowchart.
+    ///
+    /// Same nodes and edges as [`Botix::to_plantuml_string`]. Node shape and
+    /// class follow the state's `PatternType`, wait states get the `wait`
+    /// class, and the start state additionally the `start` class.
+    pub fn to_mermaid_string(&self) -> String {
+        let mut out = String::from("flowchart TD\n");
+        for state in self.sorted_states() {
+            let label = escape_mermaid(&state.to_string());
+            let (node, class) = match state.pattern_type() {
+                PatternType::Full => (format!("[\"{}\"]", label), "full"),
+                PatternType::LeftRight => (format!("([\"{}\"])", label), "leftRight"),
+                PatternType::Individual => (format!("{{{{\"{}\"}}}}", label), "individual"),
+            };
+            let class = if state.is_wait() { "wait" } else { class };
+            let _ = writeln!(out, "    s{}{}:::{}", state.id(), node, class);
+        }
+        for edge in self.edges() {
+            let _ = writeln!(
+                out,
+                "    s{} -->|\"{}\"| s{}",
+                edge.from,
+                escape_mermaid(&edge.label),
+                edge.to
+            );
+        }
+        out.push_str(
+            "    classDef full fill:#add8e6\n    \
+             classDef leftRight fill:#90ee90\n    \
+             classDef individual fill:#ffa07a\n    \
+             classDef wait fill:#ffffe0\n    \
+             classDef start stroke-width:3px\n",
+        );
+        let _ = writeln!(out, "    class s{} start", self.start_state);
+        out
+    }
+
+    /// Write the graph to a `.puml` file; see [`Botix::to_plantuml_string`].
+    pub fn export_structure(&self, path: &Path, arrow_style: ArrowStyle) -> Result<(), BotixError> {
+        std::fs::write(path, self.to_plantuml_string(arrow_style))?;
+        Ok(())
+    }
+}
+
+/// Escape text for a double-quoted DOT string.
+fn escape_dot(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape text for a double-quoted Mermaid label using entity codes.
+fn escape_mermaid(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '#' => escaped.push_str("#35;"),
+            '"' => escaped.push_str("#quot;"),
+            '<' => escaped.push_str("#lt;"),
+            '>' => escaped.push_str("#gt;"),
+            '\n' => escaped.push_str("<br>"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_labels() {
+        assert_eq!(escape_dot(r#"say "hi" \ bye"#), r#"say \"hi\" \\ bye"#);
+        assert_eq!(
+            escape_mermaid(r#"a "b" <c> #1"#),
+            "a #quot;b#quot; #lt;c#gt; #35;1"
+        );
+    }
+}
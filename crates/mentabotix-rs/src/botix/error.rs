@@ -0,0 +1,105 @@
+use std::fmt;
+
+use super::StructureError;
+use crate::transition::BreakerResult;
+
+/// Errors raised while running a Botix graph.
+#[derive(Debug)]
+pub enum BotixError {
+    /// A state ID missing from the state registry.
+    UnknownState(usize),
+    /// A transition ID missing from the transition registry.
+    UnknownTransition(usize),
+    /// The breaker returned a result with no matching `to_states` entry
+    /// and the transition has no `Placeholder` branch to fall back to.
+    NoMatchingBranch {
+        transition: usize,
+        result: BreakerResult,
+    },
+    /// Sending speeds to the controller failed.
+    Controller(Box<dyn std::error::Error>),
+    /// Writing an exported file failed.
+    Io(std::io::Error),
+    /// The graph failed `Botix::ensure_structure_validity`.
+    InvalidStructure(Vec<StructureError>),
+    /// A movement parameter out of range, such as a negative arc radius.
+    InvalidMovement(String),
+    /// A behavior file names a breaker missing from the registry.
+    UnknownBreaker {
+        name: String,
+        available: Vec<String>,
+    },
+    /// A graph can't be saved to, or rebuilt from, a behavior file.
+    Behavior(String),
+    /// A behavior file isn't valid JSON for a `BehaviorSpec`.
+    Json(serde_json::Error),
+    /// The graph can't be reloaded while `Botix::run` executes it.
+    Running,
+}
+
+impl fmt::Display for BotixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BotixError::UnknownState(id) => write!(f, "State {} not found in registry", id),
+            BotixError::UnknownTransition(id) => {
+                write!(f, "Transition {} not found in registry", id)
+            }
+            BotixError::NoMatchingBranch { transition, result } => write!(
+                f,
+                "Transition {}: no matching to_state for breaker result {:?}",
+                transition, result
+            ),
+            BotixError::Controller(e) => write!(f, "Controller error: {}", e),
+            BotixError::Io(e) => write!(f, "I/O error: {}", e),
+            BotixError::InvalidStructure(errors) => {
+                write!(f, "Invalid graph structure: ")?;
+                for (i, e) in errors.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", e)?;
+                }
+                Ok(())
+            }
+            BotixError::InvalidMovement(msg) => write!(f, "Invalid movement: {}", msg),
+            BotixError::UnknownBreaker { name, available } => write!(
+                f,
+                "Unknown breaker '{}', available breakers: [{}]",
+                name,
+                available.join(", ")
+            ),
+            BotixError::Behavior(msg) => write!(f, "Behavior file error: {}", msg),
+            BotixError::Json(e) => write!(f, "Behavior file JSON error: {}", e),
+            BotixError::Running => write!(f, "Cannot reload the graph while it is running"),
+        }
+    }
+}
+
+impl std::error::Error for BotixError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BotixError::Controller(e) => Some(e.as_ref()),
+            BotixError::Io(e) => Some(e),
+            BotixError::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for BotixError {
+    fn from(e: std::io::Error) -> Self {
+        BotixError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for BotixError {
+    fn from(e: serde_json::Error) -> Self {
+        BotixError::Json(e)
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for BotixError {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        BotixError::Controller(e)
+    }
+}
@@ -5,7 +5,7 @@ use crate::transition::MovingTransition;
 
 use super::Botix;
 
-impl Botix {
+impl<C> Botix<C> {
     /// Compute the set of states reachable from `start` via forward edges.
     pub(crate) fn compute_reachable_set(
         states: &HashMap<usize, MovingState>,
@@ -37,17 +37,6 @@ impl Botix {
         visited
     }
 
-    /// Validate the graph structure.
-    pub fn validate(&self) -> Result<(), String> {
-        if self.states.is_empty() {
-            return Err("No states in graph".into());
-        }
-        if self.transitions.is_empty() {
-            return Err("No transitions in graph".into());
-        }
-        Ok(())
-    }
-
     /// Find loops in the graph via DFS.
     pub fn find_loops(&self) -> Vec<Vec<usize>> {
         let mut loops = Vec::new();
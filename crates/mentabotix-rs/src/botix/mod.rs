@@ -1,20 +1,44 @@
 use bdmc_rs::controller::CloseLoopController;
 use std::collections::{HashMap, HashSet};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, mpsc};
+use std::thread;
 use std::time::{Duration, Instant};
 
-use crate::state::MovingState;
+use crate::controller::MotorController;
+use crate::state::{ClampPolicy, MovingState, StateController, movement_config};
 use crate::transition::{BreakerResult, MovingTransition};
 
+mod behavior;
+pub(crate) mod diagram;
+mod error;
 mod graph;
+mod path;
+mod reload;
+mod report;
+mod simulate;
+mod structure;
+
+pub use behavior::{BehaviorSpec, StateSpec, TransitionSpec};
+pub use error::BotixError;
+pub use path::PathAssumptions;
+pub use reload::{ReloadReport, SpecWatcher, WatchStatus, watch_spec};
+pub use report::{DeadlineLevel, ExecutionReport, ExitReason, HookTimeout, StateVisit};
+pub use simulate::{SimulationTrace, TraceEntry};
+pub use structure::StructureError;
 
 /// Main Botix struct for managing states and transitions.
 ///
 /// Stores states and transitions in registries keyed by their IDs.
 /// The graph is built from a flat list of transitions via `build_full()`,
 /// which validates structure and computes adjacency maps.
-pub struct Botix {
+///
+/// Generic over the [`MotorController`] it drives, `CloseLoopController` by
+/// default; tests can run a graph against a `MockController`.
+pub struct Botix<C = CloseLoopController> {
     /// The bot's controller.
-    controller: CloseLoopController,
+    controller: C,
     /// State registry: state_id → MovingState.
     states: HashMap<usize, MovingState>,
     /// Transition registry: transition_id → MovingTransition.
@@ -25,9 +49,92 @@ pub struct Botix {
     incoming_edges: HashMap<usize, Vec<usize>>,
     /// The unique start state ID.
     start_state: usize,
+    /// Last speeds sent to the controller (used to skip redundant wait re-sends).
+    last_sent: Option<[i32; 4]>,
+    /// Whether `ensure_structure_validity` accepts cycles.
+    allow_cycles: bool,
+    /// How speeds beyond `max_speed` are clamped.
+    clamp_policy: ClampPolicy,
+    /// Largest absolute speed sent to the controller.
+    max_speed: i32,
+    /// Whether `run` logs a line per transition taken.
+    log_transitions: bool,
+    /// Set while `run` is executing the graph.
+    running: Arc<AtomicBool>,
+    /// Behavior file contents the graph was built from, if any.
+    spec: Option<BehaviorSpec>,
+    /// Longest time a single hook may run (seconds).
+    hook_timeout: Option<f64>,
+    /// What a hook timeout does to the run.
+    hook_policy: HookPolicy,
+    /// Longest time a whole run may take (seconds).
+    run_deadline: Option<f64>,
 }
 
-impl Botix {
+/// What `Botix::run` does when a hook outlives `Botix::set_hook_timeout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HookPolicy {
+    /// Record the timeout in the report and carry on with the run.
+    #[default]
+    Continue,
+    /// Stop the motors and end the run with
+    /// `ExitReason::DeadlineExceeded(DeadlineLevel::Hook)`.
+    Abort,
+}
+
+/// Clears the running flag when a run ends, however it ends.
+struct RunningGuard(Arc<AtomicBool>);
+
+impl RunningGuard {
+    fn set(flag: &Arc<AtomicBool>) -> Self {
+        flag.store(true, Ordering::Release);
+        RunningGuard(flag.clone())
+    }
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// The transition taken out of a state by `run_state`.
+struct TakenTransition {
+    transition: usize,
+    result: BreakerResult,
+    branch: BreakerResult,
+    next: usize,
+}
+
+/// How `run_state` left a state.
+enum StateOutcome {
+    Next(TakenTransition),
+    End,
+    Deadline(DeadlineLevel),
+}
+
+/// The earliest of a state's and the run's deadlines.
+#[derive(Clone, Copy)]
+struct Deadline {
+    at: Instant,
+    level: DeadlineLevel,
+}
+
+impl Deadline {
+    fn remaining(&self) -> Duration {
+        self.at.saturating_duration_since(Instant::now())
+    }
+}
+
+/// Per-hook limits passed to `run_hooks`.
+struct HookLimits {
+    timeout: Option<Duration>,
+    policy: HookPolicy,
+    deadline: Option<Deadline>,
+    started: Instant,
+}
+
+impl<C: MotorController> Botix<C> {
     /// Build a Botix graph from controller, states, and transitions.
     ///
     /// Validates:
@@ -35,8 +142,12 @@ impl Botix {
     /// - Exactly one start state (indegree 0).
     /// - All states are reachable from the start state.
     /// - All referenced state IDs exist in the state registry.
+    ///
+    /// Problems that don't prevent indexing the graph, such as cycles or
+    /// mismatched breaker keys, are left to
+    /// [`Botix::ensure_structure_validity`].
     pub fn build_full(
-        controller: CloseLoopController,
+        controller: C,
         states: Vec<MovingState>,
         transitions: Vec<MovingTransition>,
     ) -> Result<Self, Box<dyn std::error::Error>> {
@@ -45,6 +156,7 @@ impl Botix {
         let mut incoming_edges: HashMap<usize, Vec<usize>> = HashMap::new();
         let mut trans_map: HashMap<usize, MovingTransition> = HashMap::new();
         let mut state_forward_count: HashMap<usize, usize> = HashMap::new();
+        let mut transition_ids: HashSet<usize> = HashSet::new();
 
         // Index states.
         for state in states {
@@ -59,8 +171,8 @@ impl Botix {
         // First pass: read adjacency info from transitions (by reference).
         for t in &transitions {
             let tid = t.id();
-            if trans_map.contains_key(&tid) {
-                return Err(format!("Duplicate transition ID: {}", tid).into());
+            if !transition_ids.insert(tid) {
+                return Err(StructureError::DuplicateTransition(tid).into());
             }
 
             // Validate all referenced state IDs exist.
@@ -104,12 +216,9 @@ impl Botix {
             .collect();
 
         if start_candidates.len() != 1 {
-            return Err(format!(
-                "Must have exactly one start state (indegree 0), found {}: {:?}",
-                start_candidates.len(),
-                start_candidates
-            )
-            .into());
+            let mut start_candidates = start_candidates;
+            start_candidates.sort_unstable();
+            return Err(StructureError::StartStates(start_candidates).into());
         }
 
         let start_state = start_candidates[0];
@@ -123,13 +232,10 @@ impl Botix {
         let reachable =
             Self::compute_reachable_set(&state_map, &forward_edge, &trans_map, start_state);
         let all_ids: HashSet<usize> = state_map.keys().copied().collect();
-        let unreachable: Vec<usize> = all_ids.difference(&reachable).copied().collect();
+        let mut unreachable: Vec<usize> = all_ids.difference(&reachable).copied().collect();
         if !unreachable.is_empty() {
-            return Err(format!(
-                "States not reachable from start state {}: {:?}",
-                start_state, unreachable
-            )
-            .into());
+            unreachable.sort_unstable();
+            return Err(StructureError::Unreachable(unreachable).into());
         }
 
         Ok(Self {
@@ -139,185 +245,377 @@ impl Botix {
             forward_edge,
             incoming_edges,
             start_state,
+            last_sent: None,
+            allow_cycles: false,
+            clamp_policy: ClampPolicy::default(),
+            max_speed: movement_config().max_speed,
+            log_transitions: false,
+            running: Arc::new(AtomicBool::new(false)),
+            spec: None,
+            hook_timeout: None,
+            hook_policy: HookPolicy::default(),
+            run_deadline: None,
         })
     }
 
     /// Execute the state machine directly — no JIT, no codegen.
     ///
-    /// Walks the graph starting from `start_state`, calling controller methods,
-    /// hooks, and evaluating breakers at each step. Loops until an end state
-    /// is reached (no forward edge).
+    /// Same as [`Botix::run`], without the report.
     pub fn execute(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.run()?;
+        Ok(())
+    }
+
+    /// Run the state machine from the start state until an end state is
+    /// reached (no forward edge).
+    ///
+    /// Each state calls its `before_entering` hooks, sends its speeds to the
+    /// controller (clamped to the speed limit, see [`Botix::set_max_speed`]
+    /// and [`Botix::set_clamp_policy`]), then waits out its outgoing transition: for `duration`
+    /// seconds, polling the breaker at `check_interval`. The breaker result
+    /// picks the next state (see [`MovingTransition::next_state`]) and the
+    /// state's `after_exiting` hooks run as it is left. The end state runs
+    /// its `after_exiting` hooks right after sending its speeds. A panicking
+    /// hook is logged and skipped; the run carries on.
+    ///
+    /// Three deadlines can cut a run short: a hook outliving
+    /// [`Botix::set_hook_timeout`] under `HookPolicy::Abort`, a state
+    /// outliving [`MovingState::with_max_total_duration`], and the whole run
+    /// outliving [`Botix::set_run_deadline`]. The motors are then stopped and
+    /// the report ends with the state the deadline ran out in, its exit
+    /// `ExitReason::DeadlineExceeded` naming the level.
+    ///
+    /// Returns the visited states with their timestamps and the transitions
+    /// that left them, or
+    /// `BotixError::InvalidStructure` without moving if the graph fails
+    /// [`Botix::ensure_structure_validity`]. Motors are stopped if the run
+    /// fails part way.
+    pub fn run(&mut self) -> Result<ExecutionReport, BotixError> {
+        self.ensure_structure_validity()?;
+        let _running = RunningGuard::set(&self.running);
+        let started = Instant::now();
+        let mut visits = Vec::new();
+        let mut hook_timeouts = Vec::new();
         let mut current = self.start_state;
+        self.last_sent = None;
 
         loop {
-            let outcome = self.execute_one_state(current)?;
-
-            match outcome {
-                TransitionOutcome::NextState(next) => current = next,
-                TransitionOutcome::End => break,
+            let entered = started.elapsed();
+            let outcome = match self.run_state(current, started, &mut hook_timeouts) {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    self.controller.stop();
+                    self.last_sent = Some([0; 4]);
+                    return Err(e);
+                }
+            };
+            let exited = started.elapsed();
+            let wait = self.states.get(&current).is_some_and(MovingState::is_wait);
+            let end_visit = |exit| StateVisit {
+                state_id: current,
+                entered,
+                exited,
+                transition: None,
+                result: None,
+                branch: None,
+                exit,
+                wait,
+            };
+            let taken = match outcome {
+                StateOutcome::Next(taken) => taken,
+                StateOutcome::End => {
+                    visits.push(end_visit(None));
+                    return Ok(ExecutionReport {
+                        started,
+                        visits,
+                        hook_timeouts,
+                    });
+                }
+                StateOutcome::Deadline(level) => {
+                    self.controller.stop();
+                    self.last_sent = Some([0; 4]);
+                    log::warn!(
+                        "{} deadline exceeded in state {} after {:.3}s, motors stopped",
+                        level,
+                        current,
+                        exited.as_secs_f64()
+                    );
+                    visits.push(end_visit(Some(ExitReason::DeadlineExceeded(level))));
+                    return Ok(ExecutionReport {
+                        started,
+                        visits,
+                        hook_timeouts,
+                    });
+                }
+            };
+            let exit = if taken.result == BreakerResult::Placeholder {
+                ExitReason::DurationElapsed
+            } else {
+                ExitReason::BreakerFired
+            };
+            if self.log_transitions {
+                log::info!(
+                    "State {} -> {} via transition {} after {:.3}s: {}, branch {}",
+                    current,
+                    taken.next,
+                    taken.transition,
+                    exited.saturating_sub(entered).as_secs_f64(),
+                    exit,
+                    taken.branch
+                );
             }
+            visits.push(StateVisit {
+                state_id: current,
+                entered,
+                exited,
+                transition: Some(taken.transition),
+                result: Some(taken.result),
+                branch: Some(taken.branch),
+                exit: Some(exit),
+                wait,
+            });
+            current = taken.next;
         }
-
-        Ok(())
     }
 
-    /// Execute a single state and its forward transition.
-    /// Returns the outcome (next state or end).
-    fn execute_one_state(
+    /// Run a single state and its forward transition.
+    ///
+    /// Returns the transition taken to the next state, or the deadline that
+    /// ran out first. Timed out hooks are added to `hook_timeouts`.
+    fn run_state(
         &mut self,
         state_id: usize,
-    ) -> Result<TransitionOutcome, Box<dyn std::error::Error>> {
-        // We need to access both states and transitions. Since execute()
-        // takes &mut self, we can't borrow self.states and self.transitions
-        // simultaneously. We use indices to avoid the borrow conflict.
-
-        // Call before_entering hooks.
-        if let Some(state) = self.states.get(&state_id) {
-            for hook in state.before_entering() {
-                hook();
-            }
+        started: Instant,
+        hook_timeouts: &mut Vec<HookTimeout>,
+    ) -> Result<StateOutcome, BotixError> {
+        let state = self
+            .states
+            .get(&state_id)
+            .ok_or(BotixError::UnknownState(state_id))?;
+        let state_deadline = state.max_total_duration().map(|secs| Deadline {
+            at: Instant::now() + seconds(secs),
+            level: DeadlineLevel::State,
+        });
+        let run_deadline = self.run_deadline.map(|secs| Deadline {
+            at: started + seconds(secs),
+            level: DeadlineLevel::Run,
+        });
+        let deadline = match (state_deadline, run_deadline) {
+            (Some(s), Some(r)) => Some(if r.at < s.at { r } else { s }),
+            (s, r) => s.or(r),
+        };
+        let limits = HookLimits {
+            timeout: self.hook_timeout.map(seconds),
+            policy: self.hook_policy,
+            deadline,
+            started,
+        };
+        if let Some(level) = run_hooks(
+            state_id,
+            "before_entering",
+            state.before_entering(),
+            &limits,
+            hook_timeouts,
+        ) {
+            return Ok(StateOutcome::Deadline(level));
         }
 
-        // Resolve and set speeds.
-        let speeds = {
-            let state = self
-                .states
-                .get(&state_id)
-                .ok_or_else(|| format!("State {} not found in registry", state_id))?;
-            state.resolve_speeds(self.controller.context())
-        };
-        let speeds_f64: Vec<f64> = speeds.iter().map(|&s| s as f64).collect();
-        self.controller.set_motors_speed(&speeds_f64)?;
+        // Resolve and set speeds. Wait states skip the send when the motors
+        // are already known to be at zero.
+        let speeds = self.limit_speeds(state_id, state.resolve_speeds(self.controller.context()));
+        if !(state.is_wait() && self.last_sent == Some([0; 4])) {
+            self.controller.set_motors_speed(speeds)?;
+            self.last_sent = Some(speeds);
+        }
 
-        // Call after_exiting hooks.
-        if let Some(state) = self.states.get(&state_id) {
-            for hook in state.after_exiting() {
-                hook();
+        let next = match self.forward_edge.get(&state_id).copied() {
+            None => StateOutcome::End,
+            Some(trans_id) => {
+                let Some(result) = self.wait_transition(state_id, trans_id, deadline)? else {
+                    // Only a deadline cuts a wait short
+                    let level = deadline.map_or(DeadlineLevel::State, |d| d.level);
+                    return Ok(StateOutcome::Deadline(level));
+                };
+                let trans = self
+                    .transitions
+                    .get(&trans_id)
+                    .ok_or(BotixError::UnknownTransition(trans_id))?;
+                let (branch, next) =
+                    trans
+                        .branch(&result)
+                        .ok_or_else(|| BotixError::NoMatchingBranch {
+                            transition: trans_id,
+                            result: result.clone(),
+                        })?;
+                StateOutcome::Next(TakenTransition {
+                    transition: trans_id,
+                    branch: branch.clone(),
+                    result,
+                    next,
+                })
             }
+        };
+
+        if let Some(state) = self.states.get(&state_id)
+            && let Some(level) = run_hooks(
+                state_id,
+                "after_exiting",
+                state.after_exiting(),
+                &limits,
+                hook_timeouts,
+            )
+        {
+            return Ok(StateOutcome::Deadline(level));
         }
 
-        // Determine next state.
-        match self.forward_edge.get(&state_id) {
-            None => Ok(TransitionOutcome::End),
-            Some(&trans_id) => {
-                // Clone the necessary info to avoid borrow issues with breaker closures.
-                let duration;
-                let check_interval;
-                let to_states: HashMap<BreakerResult, usize>;
-                let has_breaker: bool;
-
-                {
-                    let trans = self
-                        .transitions
-                        .get(&trans_id)
-                        .ok_or_else(|| format!("Transition {} not found in registry", trans_id))?;
-                    duration = trans.duration;
-                    check_interval = trans.check_interval;
-                    to_states = trans.to_states.clone();
-                    has_breaker = trans.breaker.is_some();
-                }
+        Ok(next)
+    }
 
-                if !has_breaker {
-                    // Simple delay.
-                    std::thread::sleep(Duration::from_secs_f64(duration));
-                    let next = to_states
-                        .values()
-                        .next()
-                        .copied()
-                        .ok_or_else(|| format!("Transition {} has no to_states", trans_id))?;
-                    Ok(TransitionOutcome::NextState(next))
-                } else {
-                    // Poll breaker until duration expires or non-placeholder result.
-                    let start = Instant::now();
-                    let max_dur = Duration::from_secs_f64(duration);
-                    let check_dur = Duration::from_secs_f64(check_interval.max(0.001));
-
-                    // We call the breaker directly through the stored reference.
-                    let _breaker_fn = self
-                        .transitions
-                        .get(&trans_id)
-                        .and_then(|t| t.breaker.as_ref())
-                        .ok_or_else(|| format!("Breaker not found for transition {}", trans_id))?;
-
-                    // We need to call the breaker, but we can't hold a reference
-                    // while also needing to access other fields. Since breaker is
-                    // Fn() (not FnMut), we can call it through the reference.
-                    // But we can't hold the immutable borrow of self.transitions
-                    // across the loop while also calling the breaker.
-
-                    // Strategy: extract the breaker result through repeated calls.
-                    // Since the breaker is behind a shared reference in the HashMap,
-                    // and we need to call it multiple times, we use a different approach:
-                    // we call the breaker, drop the borrow, sleep, repeat.
-
-                    let last_result;
-                    loop {
-                        let result = {
-                            let trans = self.transitions.get(&trans_id).unwrap();
-                            trans.breaker.as_ref().unwrap()()
-                        };
-                        if result != BreakerResult::Placeholder {
-                            last_result = result;
-                            break;
-                        }
-                        if start.elapsed() >= max_dur {
-                            last_result = result;
-                            break;
-                        }
-                        let remaining = max_dur.saturating_sub(start.elapsed());
-                        std::thread::sleep(check_dur.min(remaining));
-                    }
-
-                    let next = to_states.get(&last_result).copied().ok_or_else(|| {
-                        format!(
-                            "Transition {}: no matching to_state for breaker result {:?}",
-                            trans_id, last_result
-                        )
-                    })?;
-                    Ok(TransitionOutcome::NextState(next))
+    /// Wait out the transition leaving `state_id`, re-evaluating the state's
+    /// controller at the check interval.
+    ///
+    /// Returns the first non-Placeholder breaker result, or `Placeholder` if
+    /// the duration elapses without a break. Returns `None` if `deadline`
+    /// comes before the end of the duration and passes without a break.
+    fn wait_transition(
+        &mut self,
+        state_id: usize,
+        trans_id: usize,
+        deadline: Option<Deadline>,
+    ) -> Result<Option<BreakerResult>, BotixError> {
+        let (duration, check_interval, breaker) = {
+            let trans = self
+                .transitions
+                .get(&trans_id)
+                .ok_or(BotixError::UnknownTransition(trans_id))?;
+            (trans.duration, trans.check_interval, trans.breaker.clone())
+        };
+        let cut = deadline
+            .map(|d| d.remaining().as_secs_f64())
+            .filter(|&remaining| remaining < duration);
+        let duration = cut.unwrap_or(duration);
+        let cut_short = |result: BreakerResult| {
+            (cut.is_none() || result != BreakerResult::Placeholder).then_some(result)
+        };
+        let state_controller = self
+            .states
+            .get(&state_id)
+            .and_then(|s| s.controller().cloned());
+
+        let Some(state_controller) = state_controller else {
+            return Ok(cut_short(match &breaker {
+                Some(breaker) => wait_with_breaker(duration, check_interval, breaker.as_ref()),
+                None => {
+                    std::thread::sleep(seconds(duration));
+                    BreakerResult::Placeholder
                 }
+            }));
+        };
+
+        let start = Instant::now();
+        let max_dur = seconds(duration);
+        let check_dur = seconds(check_interval.max(0.001));
+        let mut last_tick = Instant::now();
+        loop {
+            let result = breaker
+                .as_ref()
+                .map_or(BreakerResult::Placeholder, |breaker| breaker());
+            if result != BreakerResult::Placeholder || start.elapsed() >= max_dur {
+                return Ok(cut_short(result));
             }
+            let remaining = max_dur.saturating_sub(start.elapsed());
+            std::thread::sleep(check_dur.min(remaining));
+            self.tick_state_controller(state_id, &state_controller, &mut last_tick)?;
         }
     }
 
-    /// Wait for `duration` seconds, polling `breaker` at `check_interval`.
-    /// Returns the first non-Placeholder breaker result, or the last result
-    /// if the duration elapses without a break.
-    pub fn wait_with_breaker(
-        duration_sec: f64,
-        check_interval: f64,
-        breaker: &(dyn Fn() -> BreakerResult + Send + Sync),
-    ) -> BreakerResult {
-        let start = Instant::now();
-        let max_duration = Duration::from_secs_f64(duration_sec);
-        let check_dur = Duration::from_secs_f64(check_interval.max(0.001));
+    /// Evaluate a state's controller and send the resulting speeds.
+    fn tick_state_controller(
+        &mut self,
+        state_id: usize,
+        state_controller: &StateController,
+        last_tick: &mut Instant,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let dt = last_tick.elapsed().as_secs_f64();
+        *last_tick = Instant::now();
+        let speeds = {
+            let ctx = self.controller.context();
+            state_controller(ctx, dt).resolve_speeds(ctx)
+        };
+        let speeds = self.limit_speeds(state_id, speeds);
+        self.controller.set_motors_speed(speeds)?;
+        self.last_sent = Some(speeds);
+        Ok(())
+    }
 
-        // Initial check.
-        let mut last_result = breaker();
-        if last_result != BreakerResult::Placeholder {
-            return last_result;
+    /// Bring a state's speeds within `max_speed`, logging any change.
+    fn limit_speeds(&self, state_id: usize, speeds: [i32; 4]) -> [i32; 4] {
+        let limited = self.clamp_policy.apply(speeds, self.max_speed);
+        if limited != speeds {
+            log::warn!(
+                "State {} speeds {:?} exceed the speed limit, clamped to {:?}",
+                state_id,
+                speeds,
+                limited
+            );
         }
+        limited
+    }
 
-        while start.elapsed() < max_duration {
-            let remaining = max_duration.saturating_sub(start.elapsed());
-            std::thread::sleep(check_dur.min(remaining));
-            last_result = breaker();
-            if last_result != BreakerResult::Placeholder {
-                return last_result;
-            }
-        }
+    /// Log a line per transition taken during `run`, at info level, to
+    /// follow a run live. Off by default.
+    pub fn set_log_transitions(&mut self, enabled: bool) -> &mut Self {
+        self.log_transitions = enabled;
+        self
+    }
+
+    /// Set how speeds beyond the speed limit are clamped before they are
+    /// sent. Defaults to `ClampPolicy::Saturate`.
+    pub fn set_clamp_policy(&mut self, policy: ClampPolicy) -> &mut Self {
+        self.clamp_policy = policy;
+        self
+    }
 
-        last_result
+    /// Set the largest absolute speed sent to the controller. Defaults to
+    /// the `max_speed` of `movement_config()` when the graph is built.
+    pub fn set_max_speed(&mut self, max_speed: i32) -> &mut Self {
+        self.max_speed = max_speed;
+        self
+    }
+
+    /// Give up on a hook after `timeout` seconds, or wait for hooks however
+    /// long they take with `None`, the default.
+    ///
+    /// A timed out hook is left running on its helper thread and recorded in
+    /// `ExecutionReport::hook_timeouts`; `policy` decides whether the run
+    /// carries on. With a timeout or a deadline set, every hook runs on a
+    /// thread of its own.
+    pub fn set_hook_timeout(&mut self, timeout: Option<f64>, policy: HookPolicy) -> &mut Self {
+        self.hook_timeout = timeout;
+        self.hook_policy = policy;
+        self
+    }
+
+    /// Abort a run that takes longer than `deadline` seconds, counted from
+    /// the start of `run`. No deadline by default.
+    pub fn set_run_deadline(&mut self, deadline: Option<f64>) -> &mut Self {
+        self.run_deadline = deadline;
+        self
+    }
+
+    /// Whether `run` is executing the graph, e.g. on another thread holding
+    /// the graph's lock.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
     }
 
     /// Get a reference to the controller.
-    pub fn controller(&self) -> &CloseLoopController {
+    pub fn controller(&self) -> &C {
         &self.controller
     }
 
     /// Get a mutable reference to the controller.
-    pub fn controller_mut(&mut self) -> &mut CloseLoopController {
+    pub fn controller_mut(&mut self) -> &mut C {
         &mut self.controller
     }
 
@@ -347,15 +645,124 @@ impl Botix {
     }
 }
 
-enum TransitionOutcome {
-    NextState(usize),
-    End,
+impl Botix {
+    /// Wait for `duration` seconds, polling `breaker` at `check_interval`.
+    /// Returns the first non-Placeholder breaker result, or the last result
+    /// if the duration elapses without a break.
+    pub fn wait_with_breaker(
+        duration_sec: f64,
+        check_interval: f64,
+        breaker: &(dyn Fn() -> BreakerResult + Send + Sync),
+    ) -> BreakerResult {
+        wait_with_breaker(duration_sec, check_interval, breaker)
+    }
+}
+
+/// See [`Botix::wait_with_breaker`]; shared by all controller types.
+fn wait_with_breaker(
+    duration_sec: f64,
+    check_interval: f64,
+    breaker: &(dyn Fn() -> BreakerResult + Send + Sync),
+) -> BreakerResult {
+    let start = Instant::now();
+    let max_duration = seconds(duration_sec);
+    let check_dur = seconds(check_interval.max(0.001));
+
+    // Initial check.
+    let mut last_result = breaker();
+    if last_result != BreakerResult::Placeholder {
+        return last_result;
+    }
+
+    while start.elapsed() < max_duration {
+        let remaining = max_duration.saturating_sub(start.elapsed());
+        std::thread::sleep(check_dur.min(remaining));
+        last_result = breaker();
+        if last_result != BreakerResult::Placeholder {
+            return last_result;
+        }
+    }
+
+    last_result
+}
+
+/// Call state hooks in order, logging panics instead of aborting the run.
+///
+/// With a hook timeout or a deadline, each hook runs on a helper thread that
+/// is left behind if the hook outlives either. Returns the deadline that ran
+/// out, or `DeadlineLevel::Hook` for a timeout under `HookPolicy::Abort`.
+fn run_hooks(
+    state_id: usize,
+    kind: &'static str,
+    hooks: &[Arc<dyn Fn() + Send + Sync>],
+    limits: &HookLimits,
+    hook_timeouts: &mut Vec<HookTimeout>,
+) -> Option<DeadlineLevel> {
+    for (index, hook) in hooks.iter().enumerate() {
+        let remaining = limits.deadline.map(|d| d.remaining());
+        let wait = match (limits.timeout, remaining) {
+            (None, None) => {
+                call_hook(state_id, kind, hook.as_ref());
+                continue;
+            }
+            (timeout, remaining) => timeout.into_iter().chain(remaining).min()?,
+        };
+        let (done_tx, done_rx) = mpsc::channel();
+        let helper = hook.clone();
+        thread::spawn(move || {
+            call_hook(state_id, kind, helper.as_ref());
+            let _ = done_tx.send(());
+        });
+        if done_rx.recv_timeout(wait) != Err(mpsc::RecvTimeoutError::Timeout) {
+            continue;
+        }
+        // The earlier of the two limits is the one that ran out
+        if let Some(deadline) = limits.deadline
+            && remaining.is_some_and(|r| limits.timeout.is_none_or(|t| r < t))
+        {
+            return Some(deadline.level);
+        }
+        log::warn!(
+            "{} hook {} of state {} timed out after {:.3}s",
+            kind,
+            index,
+            state_id,
+            wait.as_secs_f64()
+        );
+        hook_timeouts.push(HookTimeout {
+            state_id,
+            kind,
+            index,
+            at: limits.started.elapsed(),
+        });
+        if limits.policy == HookPolicy::Abort {
+            return Some(DeadlineLevel::Hook);
+        }
+    }
+    None
+}
+
+/// Call one hook, logging a panic instead of unwinding.
+fn call_hook(state_id: usize, kind: &str, hook: &(dyn Fn() + Send + Sync)) {
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(hook)) {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        log::error!("{} hook of state {} panicked: {}", kind, state_id, message);
+    }
+}
+
+/// Convert seconds to a `Duration`, saturating for unbounded durations.
+fn seconds(secs: f64) -> Duration {
+    Duration::try_from_secs_f64(secs.max(0.0)).unwrap_or(Duration::MAX)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::state::MovingState;
+    use crate::state::{ArrowStyle, MovementConfig, MovingState, SpeedPattern, TurnDirection};
     use crate::transition::{BreakerResult, MovingTransition};
 
     fn make_linear_chain() -> (Vec<MovingState>, Vec<MovingTransition>) {
@@ -393,6 +800,84 @@ mod tests {
         assert_eq!(botix.transition_count(), 2);
     }
 
+    /// A→{B, C}, B→D, C→D, with `key` picking B or C.
+    fn make_diamond(key: bool) -> (Botix, [usize; 4]) {
+        let a = MovingState::straight(100);
+        let b = MovingState::turn(TurnDirection::Left, 100);
+        let c = MovingState::turn(TurnDirection::Right, 100);
+        let d = MovingState::halt();
+        let ids = [a.id(), b.id(), c.id(), d.id()];
+
+        let transitions = vec![
+            MovingTransition::new(1.0)
+                .unwrap()
+                .with_bool_breaker(move || key)
+                .with_from_state(ids[0])
+                .with_to_state(true, ids[1])
+                .with_to_state(false, ids[2]),
+            MovingTransition::new(0.01)
+                .unwrap()
+                .with_from_state(ids[1])
+                .with_single_to_state(ids[3]),
+            MovingTransition::new(0.01)
+                .unwrap()
+                .with_from_state(ids[2])
+                .with_single_to_state(ids[3]),
+        ];
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        let botix = Botix::build_full(controller, vec![a, b, c, d], transitions).unwrap();
+        (botix, ids)
+    }
+
+    #[test]
+    fn test_build_diamond_shares_end_state() {
+        let (mut botix, [a, _, c, d]) = make_diamond(false);
+        assert_eq!(botix.start_states(), HashSet::from([a]));
+        assert_eq!(botix.end_states(), HashSet::from([d]));
+        assert_eq!(botix.start_state_id(), a);
+        // Two paths into D are not a loop.
+        assert!(botix.find_loops().is_empty());
+        assert_eq!(botix.run().unwrap().state_ids(), [a, c, d]);
+
+        let (mut botix, [a, b, _, d]) = make_diamond(true);
+        assert_eq!(botix.run().unwrap().state_ids(), [a, b, d]);
+    }
+
+    #[test]
+    fn test_cloned_state_keeps_identity() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let entered = Arc::new(AtomicUsize::new(0));
+        let shared = MovingState::halt().with_before_entering({
+            let entered = Arc::clone(&entered);
+            move || {
+                entered.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        let copy = shared.clone();
+        assert_eq!(copy.id(), shared.id());
+        assert!(copy == shared);
+
+        // Both branches lead to the one registered copy of the shared state.
+        let s0 = MovingState::straight(100);
+        let t0 = MovingTransition::new(0.01)
+            .unwrap()
+            .with_from_state(s0.id())
+            .with_to_state(true, shared.id())
+            .with_single_to_state(copy.id());
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        let mut botix = Botix::build_full(controller, vec![s0, copy], vec![t0]).unwrap();
+        assert_eq!(botix.end_states(), HashSet::from([shared.id()]));
+        botix.run().unwrap();
+        assert_eq!(entered.load(Ordering::SeqCst), 1);
+
+        // Registering the same state twice is still an error.
+        let s0 = MovingState::straight(100);
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        assert!(Botix::build_full(controller, vec![s0.clone(), s0], vec![]).is_err());
+    }
+
     #[test]
     fn test_build_duplicate_from_state() {
         let s0 = MovingState::straight(100);
@@ -442,6 +927,207 @@ mod tests {
         assert_eq!(result, BreakerResult::Placeholder);
     }
 
+    #[test]
+    fn test_run_reports_visits_and_hooks() {
+        use std::sync::{Arc, Mutex};
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let log = |event: &'static str| {
+            let events = Arc::clone(&events);
+            move || events.lock().unwrap().push(event)
+        };
+        let s0 = MovingState::straight(100)
+            .with_before_entering(log("enter s0"))
+            .with_after_exiting(log("exit s0"));
+        let s1 = MovingState::halt()
+            .with_before_entering(log("enter s1"))
+            .with_after_exiting(log("exit s1"));
+        let s2 = MovingState::straight(-100);
+        let s3 = MovingState::halt().with_before_entering(log("enter s3"));
+        let (s0_id, s1_id, s2_id, s3_id) = (s0.id(), s1.id(), s2.id(), s3.id());
+
+        let t0 = MovingTransition::new(0.02)
+            .unwrap()
+            .with_from_state(s0_id)
+            .with_single_to_state(s1_id);
+        let t1 = MovingTransition::new(1.0)
+            .unwrap()
+            .with_breaker({
+                let breaker = log("break s1");
+                move || {
+                    breaker();
+                    BreakerResult::Int(2)
+                }
+            })
+            .with_from_state(s1_id)
+            .with_to_state(1, s2_id)
+            .with_to_state(2, s3_id);
+
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        let mut botix = Botix::build_full(controller, vec![s0, s1, s2, s3], vec![t0, t1]).unwrap();
+        let report = botix.run().unwrap();
+
+        assert_eq!(report.state_ids(), [s0_id, s1_id, s3_id]);
+        assert!(report.visits[0].duration() >= Duration::from_millis(20));
+        assert_eq!(report.visits[0].result, Some(BreakerResult::Placeholder));
+        assert_eq!(report.visits[1].result, Some(BreakerResult::Int(2)));
+        assert_eq!(report.visits[2].result, None);
+        assert!(report.visits[1].entered >= report.visits[0].exited);
+        assert_eq!(report.total_duration(), report.visits[2].exited);
+        // Exit hooks run once the transition has fired, not on entry.
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                "enter s0", "exit s0", "enter s1", "break s1", "exit s1", "enter s3"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hook_panics_are_contained() {
+        use std::sync::{Arc, Mutex};
+
+        let order = Arc::new(Mutex::new(Vec::<String>::new()));
+        let record = |event: String| {
+            let order = Arc::clone(&order);
+            move || order.lock().unwrap().push(event.clone())
+        };
+        let mut s0 = MovingState::straight(100);
+        let s0_id = s0.id();
+        s0.add_before_entering(record(format!("before {s0_id} #1")))
+            .add_before_entering(|| panic!("hook failure"))
+            .add_before_entering(record(format!("before {s0_id} #2")))
+            .add_after_exiting(record(format!("after {s0_id}")));
+        let s1 = MovingState::halt();
+        let s1_id = s1.id();
+        let s1 = s1.with_before_entering(record(format!("before {s1_id}")));
+        let t0 = MovingTransition::new(0.01)
+            .unwrap()
+            .with_from_state(s0_id)
+            .with_single_to_state(s1_id);
+
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        let mut botix = Botix::build_full(controller, vec![s0, s1], vec![t0]).unwrap();
+        assert_eq!(botix.run().unwrap().state_ids(), [s0_id, s1_id]);
+        assert_eq!(
+            *order.lock().unwrap(),
+            [
+                format!("before {s0_id} #1"),
+                format!("before {s0_id} #2"),
+                format!("after {s0_id}"),
+                format!("before {s1_id}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_no_matching_branch() {
+        let s0 = MovingState::straight(100);
+        let s1 = MovingState::halt();
+        let s2 = MovingState::straight(-100);
+        let t0 = MovingTransition::new(1.0)
+            .unwrap()
+            .with_breaker(|| BreakerResult::Int(3))
+            .with_from_state(s0.id())
+            .with_to_state(1, s1.id())
+            .with_to_state(2, s2.id());
+        let t0_id = t0.id();
+
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        let mut botix = Botix::build_full(controller, vec![s0, s1, s2], vec![t0]).unwrap();
+        match botix.run() {
+            Err(BotixError::NoMatchingBranch { transition, result }) => {
+                assert_eq!(transition, t0_id);
+                assert_eq!(result, BreakerResult::Int(3));
+            }
+            other => panic!(
+                "expected NoMatchingBranch, got {:?}",
+                other.map(|r| r.visits)
+            ),
+        }
+    }
+
+    #[test]
+    fn test_run_bool_breaker() {
+        let s0 = MovingState::straight(100);
+        let s1 = MovingState::halt();
+        let s2 = MovingState::straight(-100);
+        let (s0_id, s1_id, s2_id) = (s0.id(), s1.id(), s2.id());
+        let t0 = MovingTransition::new(1.0)
+            .unwrap()
+            .with_bool_breaker(|| true)
+            .with_from_state(s0_id)
+            .with_to_state(false, s1_id)
+            .with_to_state(true, s2_id);
+
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        let mut botix = Botix::build_full(controller, vec![s0, s1, s2], vec![t0]).unwrap();
+        let report = botix.run().unwrap();
+        assert_eq!(report.state_ids(), [s0_id, s2_id]);
+        assert!(report.total_duration() < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_run_keyed_breaker() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let keyed = |key: &'static str| {
+            let s0 = MovingState::straight(100);
+            let left = MovingState::turn(TurnDirection::Left, 100);
+            let right = MovingState::turn(TurnDirection::Right, 100);
+            let timeout = MovingState::halt();
+            let ids = [s0.id(), left.id(), right.id(), timeout.id()];
+            // No decision for the first polls, then the key.
+            let polls = Arc::new(AtomicUsize::new(0));
+            let t0 = MovingTransition::new(1.0)
+                .unwrap()
+                .with_check_interval(0.005)
+                .with_keyed_breaker(move || {
+                    (polls.fetch_add(1, Ordering::SeqCst) >= 3).then(|| key.to_string())
+                })
+                .with_expected_keys(["left", "right"])
+                .with_from_state(ids[0])
+                .with_to_state("left", ids[1])
+                .with_to_state("right", ids[2])
+                .with_single_to_state(ids[3]);
+            let controller = CloseLoopController::new(None, None, None, None).unwrap();
+            let botix =
+                Botix::build_full(controller, vec![s0, left, right, timeout], vec![t0]).unwrap();
+            (botix, ids)
+        };
+
+        let (mut botix, ids) = keyed("right");
+        let report = botix.run().unwrap();
+        assert_eq!(report.state_ids(), [ids[0], ids[2]]);
+        assert_eq!(report.visits[0].result, Some(BreakerResult::from("right")));
+        assert!(report.visits[0].duration() >= Duration::from_millis(15));
+
+        let (mut botix, _) = keyed("up");
+        assert!(matches!(
+            botix.run(),
+            Err(BotixError::NoMatchingBranch { .. })
+        ));
+
+        // Declared keys must match the branches.
+        let s0 = MovingState::straight(100);
+        let s1 = MovingState::halt();
+        let t0 = MovingTransition::new(1.0)
+            .unwrap()
+            .with_keyed_breaker(|| None)
+            .with_expected_keys(["left", "right"])
+            .with_from_state(s0.id())
+            .with_to_state("left", s1.id());
+        let t0_id = t0.id();
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        let mut botix = Botix::build_full(controller, vec![s0, s1], vec![t0]).unwrap();
+        assert!(matches!(
+            botix.run(),
+            Err(BotixError::InvalidStructure(errors))
+                if matches!(errors[..], [StructureError::InvalidKeys { transition, .. }] if transition == t0_id)
+        ));
+    }
+
     #[test]
     fn test_find_loops_no_loop() {
         let (states, transitions) = make_linear_chain();
@@ -451,6 +1137,307 @@ mod tests {
         assert!(loops.is_empty(), "Expected no loops, got {:?}", loops);
     }
 
+    #[test]
+    fn test_wait_until_breaker_exit() {
+        let mut comp = crate::composer::MovingChainComposer::new();
+        comp.wait_until(|| true, 5.0).add_state(MovingState::halt());
+        let (states, transitions) = comp.export();
+
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        let mut botix = Botix::build_full(controller, states, transitions).unwrap();
+        let start = Instant::now();
+        let report = botix.run().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        let waits: Vec<(bool, Option<ExitReason>)> =
+            report.visits.iter().map(|v| (v.wait, v.exit)).collect();
+        assert_eq!(
+            waits,
+            [(true, Some(ExitReason::BreakerFired)), (false, None)]
+        );
+        assert!(report.summary().lines().nth(1).unwrap().contains(" wait "));
+    }
+
+    #[test]
+    fn test_wait_until_timeout_exit() {
+        let mut comp = crate::composer::MovingChainComposer::new();
+        comp.wait_until(|| false, 0.05)
+            .add_state(MovingState::halt());
+        let (states, transitions) = comp.export();
+
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        let mut botix = Botix::build_full(controller, states, transitions).unwrap();
+        let start = Instant::now();
+        let report = botix.run().unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert!(report.visits[0].wait);
+        assert_eq!(report.visits[0].exit, Some(ExitReason::DurationElapsed));
+        assert_eq!(report.to_json()["visits"][0]["wait"], true);
+    }
+
+    #[test]
+    fn test_validate_infinite_wait() {
+        let s0 = MovingState::wait();
+        let s1 = MovingState::halt();
+        let t0 = MovingTransition::new(f64::INFINITY)
+            .unwrap()
+            .with_from_state(s0.id())
+            .with_single_to_state(s1.id());
+
+        let s0_id = s0.id();
+
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        let mut botix = Botix::build_full(controller, vec![s0, s1], vec![t0]).unwrap();
+        assert_eq!(
+            botix.ensure_structure_validity(),
+            Err(vec![StructureError::InfiniteWait(s0_id)])
+        );
+        // The executor refuses to start instead of waiting forever.
+        assert!(matches!(
+            botix.run(),
+            Err(BotixError::InvalidStructure(errors)) if errors.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_ensure_structure_validity() {
+        let s0 = MovingState::straight(100);
+        let s1 = MovingState::straight(200);
+        let s2 = MovingState::straight(300);
+        let s3 = MovingState::halt();
+        let [s0_id, s1_id, s2_id, s3_id] = [s0.id(), s1.id(), s2.id(), s3.id()];
+        let t0 = MovingTransition::new(0.01)
+            .unwrap()
+            .with_from_state(s0_id)
+            .with_single_to_state(s1_id);
+        // s1 → s2 → back to s1, or on to s3.
+        let t1 = MovingTransition::new(0.01)
+            .unwrap()
+            .with_from_state(s1_id)
+            .with_single_to_state(s2_id);
+        let t2 = MovingTransition::new(0.01)
+            .unwrap()
+            .with_bool_breaker(|| true)
+            .with_from_state(s2_id)
+            .with_to_state(false, s1_id)
+            .with_to_state(true, s3_id);
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        let mut botix =
+            Botix::build_full(controller, vec![s0, s1, s2, s3], vec![t0, t1, t2]).unwrap();
+
+        assert_eq!(
+            botix.ensure_structure_validity(),
+            Err(vec![StructureError::Cycle(vec![s1_id, s2_id])])
+        );
+        assert!(matches!(botix.run(), Err(BotixError::InvalidStructure(_))));
+        botix.set_allow_cycles(true);
+        assert_eq!(botix.ensure_structure_validity(), Ok(()));
+        assert_eq!(
+            botix.run().unwrap().state_ids(),
+            [s0_id, s1_id, s2_id, s3_id]
+        );
+
+        // Every problem is reported, not only the first.
+        let s0 = MovingState::straight(100);
+        let s1 = MovingState::halt();
+        let t0 = MovingTransition::new(0.01)
+            .unwrap()
+            .with_from_state(s0.id())
+            .with_single_to_state(s1.id());
+        let dangling = MovingTransition::new(0.01)
+            .unwrap()
+            .with_from_state(s1.id());
+        let orphan = MovingTransition::new(0.01)
+            .unwrap()
+            .with_keyed_breaker(|| None)
+            .with_to_state(true, s1.id());
+        let (dangling_id, orphan_id) = (dangling.id(), orphan.id());
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        let botix =
+            Botix::build_full(controller, vec![s0, s1], vec![t0, dangling, orphan]).unwrap();
+        let errors = botix.ensure_structure_validity().unwrap_err();
+        assert_eq!(errors.len(), 3, "{errors:?}");
+        assert_eq!(errors[0], StructureError::EmptyToStates(dangling_id));
+        assert_eq!(errors[1], StructureError::EmptyFromStates(orphan_id));
+        assert!(matches!(
+            errors[2],
+            StructureError::InvalidKeys { transition, .. } if transition == orphan_id
+        ));
+    }
+
+    #[test]
+    fn test_render_path_svg_l_route() {
+        let config = MovementConfig::default();
+        // In-place turn: angular = 2 * 100 / track_width = 2 rad/s → 90° in π/4 s.
+        let s0 = MovingState::straight(1000);
+        let s1 = MovingState::turn(TurnDirection::Left, 100);
+        let s2 = MovingState::straight(1000);
+        let s3 = MovingState::halt();
+        let (s0_id, s1_id, s2_id, s3_id) = (s0.id(), s1.id(), s2.id(), s3.id());
+
+        let transitions = vec![
+            MovingTransition::new(1.0)
+                .unwrap()
+                .with_from_state(s0_id)
+                .with_single_to_state(s1_id),
+            MovingTransition::new(std::f64::consts::FRAC_PI_4)
+                .unwrap()
+                .with_from_state(s1_id)
+                .with_single_to_state(s2_id),
+            MovingTransition::new(1.0)
+                .unwrap()
+                .with_from_state(s2_id)
+                .with_single_to_state(s3_id),
+        ];
+
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        let botix = Botix::build_full(controller, vec![s0, s1, s2, s3], transitions).unwrap();
+        let svg = botix.render_path_svg(&config, PathAssumptions::default());
+        assert_eq!(svg, include_str!("../../testdata/l_route.svg"));
+    }
+
+    #[test]
+    fn test_render_path_svg_notes_uncovered_branch() {
+        let s0 = MovingState::straight(500);
+        let s1 = MovingState::halt();
+        let s2 = MovingState::straight(-500);
+        let (s0_id, s1_id, s2_id) = (s0.id(), s1.id(), s2.id());
+        let t0 = MovingTransition::new(1.0)
+            .unwrap()
+            .with_breaker(|| BreakerResult::Bool(true))
+            .with_from_state(s0_id)
+            .with_to_state(BreakerResult::Placeholder, s1_id)
+            .with_to_state(BreakerResult::Bool(true), s2_id);
+        let t0_id = t0.id();
+
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        let botix = Botix::build_full(controller, vec![s0, s1, s2], vec![t0]).unwrap();
+        let config = MovementConfig::default();
+
+        let svg = botix.render_path_svg(&config, PathAssumptions::default());
+        assert!(svg.contains("branch not covered by assumptions"));
+        assert!(svg.contains(">halt</text>"));
+
+        let mut assumptions = PathAssumptions::default();
+        assumptions
+            .outcomes
+            .insert(t0_id, BreakerResult::Bool(true));
+        let svg = botix.render_path_svg(&config, assumptions);
+        assert!(!svg.contains("note:"));
+        assert!(svg.contains(">straight(-500)</text>"));
+    }
+
+    #[test]
+    fn test_to_plantuml_string() {
+        let a = MovingState::straight(100);
+        let b = MovingState::wait();
+        let c = MovingState::turn(TurnDirection::Left, 50);
+        let d = MovingState::halt();
+        let [a_id, b_id, c_id, d_id] = [a.id(), b.id(), c.id(), d.id()];
+        let t0 = MovingTransition::new(1.0)
+            .unwrap()
+            .with_bool_breaker(|| true)
+            .with_from_state(a_id)
+            .with_to_state(true, b_id)
+            .with_to_state(false, c_id);
+        // One transition leaving two states renders as two edges.
+        let t1 = MovingTransition::new(0.5)
+            .unwrap()
+            .with_from_state(b_id)
+            .with_from_state(c_id)
+            .with_single_to_state(d_id);
+
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        let botix = Botix::build_full(controller, vec![a, b, c, d], vec![t0, t1]).unwrap();
+        let expected = format!(
+            "@startuml\n\
+             [*] --> s{a_id}\n\
+             \n\
+             state \"State{a_id}(100)\" as s{a_id}\n\
+             state \"State{b_id}(wait)\" as s{b_id} #LightYellow\n\
+             state \"State{c_id}(-50, 50)\" as s{c_id}\n\
+             state \"State{d_id}(0)\" as s{d_id}\n\
+             \n\
+             s{a_id} --> s{b_id} : [true] 1.000s\n\
+             s{a_id} --> s{c_id} : [false] 1.000s\n\
+             s{b_id} --> s{d_id} : 0.500s\n\
+             s{c_id} --> s{d_id} : 0.500s\n\
+             \n\
+             s{d_id} --> [*]\n\
+             @enduml\n"
+        );
+        assert_eq!(botix.to_plantuml_string(ArrowStyle::Down), expected);
+        assert!(
+            botix
+                .to_plantuml_string(ArrowStyle::Right)
+                .contains(&format!("[*] -right-> s{a_id}"))
+        );
+
+        let path = std::env::temp_dir().join(format!("botix-{}.puml", std::process::id()));
+        botix.export_structure(&path, ArrowStyle::Down).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), expected);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// A straight start branching to a turn or a drift.
+    fn make_three_states() -> (Botix, [usize; 3]) {
+        let a = MovingState::straight(100);
+        let b = MovingState::turn(TurnDirection::Left, 50);
+        let c = MovingState::new(SpeedPattern::Individual {
+            front_left: 10,
+            rear_left: 20,
+            front_right: 30,
+            rear_right: 40,
+        });
+        let ids = [a.id(), b.id(), c.id()];
+        let t0 = MovingTransition::new(1.0)
+            .unwrap()
+            .with_bool_breaker(|| true)
+            .with_from_state(ids[0])
+            .with_to_state(true, ids[1])
+            .with_to_state(false, ids[2]);
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        let botix = Botix::build_full(controller, vec![a, b, c], vec![t0]).unwrap();
+        (botix, ids)
+    }
+
+    #[test]
+    fn test_to_dot_string() {
+        let (botix, [a, b, c]) = make_three_states();
+        let expected = format!(
+            "digraph botix {{\n    \
+             node [style=filled];\n\
+             \n    \
+             s{a} [label=\"State{a}(100)\", shape=box, fillcolor=lightblue, style=\"filled,bold\", penwidth=2];\n    \
+             s{b} [label=\"State{b}(-50, 50)\", shape=ellipse, fillcolor=lightgreen];\n    \
+             s{c} [label=\"State{c}([10, 20, 30, 40])\", shape=hexagon, fillcolor=lightsalmon];\n\
+             \n    \
+             s{a} -> s{b} [label=\"[true] 1.000s\"];\n    \
+             s{a} -> s{c} [label=\"[false] 1.000s\"];\n\
+             }}\n"
+        );
+        assert_eq!(botix.to_dot_string(), expected);
+    }
+
+    #[test]
+    fn test_to_mermaid_string() {
+        let (botix, [a, b, c]) = make_three_states();
+        let expected = format!(
+            "flowchart TD\n    \
+             s{a}[\"State{a}(100)\"]:::full\n    \
+             s{b}([\"State{b}(-50, 50)\"]):::leftRight\n    \
+             s{c}{{{{\"State{c}([10, 20, 30, 40])\"}}}}:::individual\n    \
+             s{a} -->|\"[true] 1.000s\"| s{b}\n    \
+             s{a} -->|\"[false] 1.000s\"| s{c}\n    \
+             classDef full fill:#add8e6\n    \
+             classDef leftRight fill:#90ee90\n    \
+             classDef individual fill:#ffa07a\n    \
+             classDef wait fill:#ffffe0\n    \
+             classDef start stroke-width:3px\n    \
+             class s{a} start\n"
+        );
+        assert_eq!(botix.to_mermaid_string(), expected);
+    }
+
     #[test]
     fn test_loop_rejected() {
         let s0 = MovingState::straight(100);
@@ -478,4 +1465,686 @@ mod tests {
         let result = Botix::build_full(controller, vec![s0, s1, s2], vec![t0, t1]);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_state_controller_steers_toward_tag() {
+        use bdmc_rs::controller::CLASSIC_MIS;
+        use bdmc_rs::sim::{SimConfig, SimulatedDriver};
+        use std::sync::{Arc, Mutex};
+
+        // The tag drifts toward the centre as the robot turns into it.
+        let bearing = Arc::new(Mutex::new(0.4_f64));
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let approach = crate::helpers::approach_tag_controller(
+            {
+                let bearing = Arc::clone(&bearing);
+                move || {
+                    let mut b = bearing.lock().unwrap();
+                    *b *= 0.5;
+                    Some((*b, 1.0))
+                }
+            },
+            crate::helpers::ApproachGains::default(),
+        );
+        let s0 = MovingState::halt().with_controller({
+            let commands = Arc::clone(&commands);
+            move |ctx, dt| {
+                let pattern = approach(ctx, dt);
+                commands.lock().unwrap().push(pattern.to_array());
+                pattern
+            }
+        });
+        let s1 = MovingState::halt();
+        let t0 = MovingTransition::new(0.05)
+            .unwrap()
+            .with_check_interval(0.01)
+            .with_from_state(s0.id())
+            .with_single_to_state(s1.id());
+
+        let driver = SimulatedDriver::new(&[1, 2, 3, 4], SimConfig::default());
+        let handle = driver.handle();
+        let mut controller =
+            CloseLoopController::new(Some(CLASSIC_MIS.to_vec()), None, None, None).unwrap();
+        controller.attach_serial(Box::new(driver));
+        let mut botix = Botix::build_full(controller, vec![s0, s1], vec![t0]).unwrap();
+        botix.execute().unwrap();
+
+        let commands = commands.lock().unwrap();
+        assert!(commands.len() >= 3, "only {} ticks", commands.len());
+        let steer: Vec<i32> = commands.iter().map(|c| c[2] - c[0]).collect();
+        assert!(steer[0] > 0);
+        assert!(steer.windows(2).all(|w| w[1] <= w[0]), "{steer:?}");
+        // The final halt state stops the motors.
+        assert_eq!(handle.motor(1).unwrap().target, 0.0);
+    }
+
+    #[test]
+    fn test_mock_records_speed_sequence() {
+        use crate::controller::MockController;
+
+        let s0 = MovingState::straight(100);
+        let s1 = MovingState::halt();
+        let s2 = MovingState::wait();
+        let s3 = MovingState::straight(50);
+        let ids = [s0.id(), s1.id(), s2.id(), s3.id()];
+        let transitions = ids
+            .windows(2)
+            .map(|pair| {
+                MovingTransition::new(0.02)
+                    .unwrap()
+                    .with_from_state(pair[0])
+                    .with_single_to_state(pair[1])
+            })
+            .collect();
+        let mut botix =
+            Botix::build_full(MockController::new(), vec![s0, s1, s2, s3], transitions).unwrap();
+        botix.run().unwrap();
+
+        // The wait state doesn't resend the halt state's zeros.
+        let mock = botix.controller();
+        assert_eq!(mock.speeds(), [[100; 4], [0; 4], [50; 4]]);
+        let commands = mock.commands();
+        assert!(commands[1].at - commands[0].at >= Duration::from_millis(20));
+        assert!(commands[2].at - commands[1].at >= Duration::from_millis(40));
+    }
+
+    #[test]
+    fn test_failed_run_stops_motors() {
+        use crate::controller::MockController;
+
+        let s0 = MovingState::straight(100);
+        let s1 = MovingState::halt();
+        let t0 = MovingTransition::new(1.0)
+            .unwrap()
+            .with_keyed_breaker(|| Some("reverse".to_string()))
+            .with_from_state(s0.id())
+            .with_to_state("forward", s1.id());
+        let mut botix = Botix::build_full(MockController::new(), vec![s0, s1], vec![t0]).unwrap();
+        assert!(matches!(
+            botix.run(),
+            Err(BotixError::NoMatchingBranch { .. })
+        ));
+        assert_eq!(botix.controller().speeds(), [[100; 4], [0; 4]]);
+    }
+
+    #[test]
+    fn test_dry_run_compresses_time() {
+        use crate::controller::MockController;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // The breaker fires on its fourth poll, 0.3s into the 10s transition.
+        let polls = Arc::new(AtomicUsize::new(0));
+        let s0 = MovingState::straight(100);
+        let s1 = MovingState::turn(TurnDirection::Left, 50);
+        let s2 = MovingState::halt();
+        let ids = [s0.id(), s1.id(), s2.id()];
+        let t0 = MovingTransition::new(10.0)
+            .unwrap()
+            .with_check_interval(0.1)
+            .with_keyed_breaker({
+                let polls = Arc::clone(&polls);
+                move || (polls.fetch_add(1, Ordering::SeqCst) >= 3).then(|| "turn".to_string())
+            })
+            .with_from_state(ids[0])
+            .with_to_state("turn", ids[1]);
+        let t1 = MovingTransition::new(60.0)
+            .unwrap()
+            .with_from_state(ids[1])
+            .with_single_to_state(ids[2]);
+        let botix =
+            Botix::build_full(MockController::new(), vec![s0, s1, s2], vec![t0, t1]).unwrap();
+
+        let started = Instant::now();
+        let trace = botix.dry_run(0.0).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(botix.controller().commands().is_empty());
+
+        assert_eq!(trace.state_ids(), ids);
+        let times: Vec<f64> = trace.entries.iter().map(|e| e.t.as_secs_f64()).collect();
+        assert!((times[1] - 0.3).abs() < 1e-9, "{times:?}");
+        assert!((trace.end.as_secs_f64() - 60.3).abs() < 1e-9);
+        assert_eq!(trace.entries[0].speeds, [100; 4]);
+        assert_eq!(trace.entries[2].speeds, [0; 4]);
+
+        let json = trace.to_json();
+        assert_eq!(json["entries"][1]["state_id"], ids[1]);
+        assert_eq!(
+            json["entries"][0]["speeds"],
+            serde_json::json!([100, 100, 100, 100])
+        );
+        let text = trace.to_string();
+        assert_eq!(text.lines().count(), 4);
+        assert!(
+            text.lines()
+                .next()
+                .unwrap()
+                .contains("[100, 100, 100, 100]")
+        );
+    }
+
+    #[test]
+    fn test_dry_run_time_scale_sleeps() {
+        let s0 = MovingState::straight(100);
+        let s1 = MovingState::halt();
+        let t0 = MovingTransition::new(1.0)
+            .unwrap()
+            .with_from_state(s0.id())
+            .with_single_to_state(s1.id());
+        let controller = CloseLoopController::new(None, None, None, None).unwrap();
+        let botix = Botix::build_full(controller, vec![s0, s1], vec![t0]).unwrap();
+
+        let started = Instant::now();
+        let trace = botix.dry_run(0.05).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert_eq!(trace.end, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_run_clamps_speeds() {
+        use crate::controller::MockController;
+
+        let make = |policy| {
+            let s0 = MovingState::new(SpeedPattern::Individual {
+                front_left: 20000,
+                rear_left: -5000,
+                front_right: 20000,
+                rear_right: -5000,
+            });
+            let s1 = MovingState::halt();
+            let t0 = MovingTransition::new(0.0)
+                .unwrap()
+                .with_from_state(s0.id())
+                .with_single_to_state(s1.id());
+            let mut botix =
+                Botix::build_full(MockController::new(), vec![s0, s1], vec![t0]).unwrap();
+            botix.set_clamp_policy(policy).set_max_speed(8000);
+            botix.run().unwrap();
+            botix.controller().speeds()[0]
+        };
+        let max = 8000;
+        assert_eq!(make(ClampPolicy::Saturate), [max, -5000, max, -5000]);
+        assert_eq!(
+            make(ClampPolicy::ScaleProportionally),
+            [max, -max / 4, max, -max / 4]
+        );
+    }
+
+    /// Replace the ID in every `s<id>` node name and `State<id>` label by its
+    /// rank among `ids`.
+    fn normalize_ids(text: &str, ids: &[usize]) -> String {
+        let mut out = String::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            out.push(c);
+            let names_id = c == 's' || out.ends_with("State");
+            if !names_id || !chars.peek().is_some_and(|d| d.is_ascii_digit()) {
+                continue;
+            }
+            let mut digits = String::new();
+            while let Some(d) = chars.next_if(|d| d.is_ascii_digit()) {
+                digits.push(d);
+            }
+            match ids.iter().position(|&id| id.to_string() == digits) {
+                Some(rank) => out.push_str(&format!("#{}", rank)),
+                None => out.push_str(&digits),
+            }
+        }
+        out
+    }
+
+    fn make_named_branch() -> (
+        Botix<crate::controller::MockController>,
+        HashMap<String, crate::transition::BreakerFn>,
+    ) {
+        use crate::controller::MockController;
+        use crate::transition::BreakerFn;
+
+        let breakers: HashMap<String, BreakerFn> = HashMap::from([
+            (
+                "see_edge".to_string(),
+                Arc::new(|| BreakerResult::Str("edge".to_string())) as BreakerFn,
+            ),
+            (
+                "never".to_string(),
+                Arc::new(|| BreakerResult::Placeholder) as BreakerFn,
+            ),
+        ]);
+        let s0 = MovingState::straight(100);
+        let s1 = MovingState::turn(TurnDirection::Left, 50);
+        let s2 = MovingState::wait();
+        let s3 = MovingState::halt();
+        let t0 = MovingTransition::new(1.0)
+            .unwrap()
+            .with_named_keyed_breaker("see_edge", breakers["see_edge"].clone())
+            .with_expected_keys(["edge"])
+            .with_from_state(s0.id())
+            .with_to_state("edge", s1.id())
+            .with_single_to_state(s2.id());
+        let t1 = MovingTransition::new(0.01)
+            .unwrap()
+            .with_from_state(s1.id())
+            .with_single_to_state(s3.id());
+        let t2 = MovingTransition::new(0.02)
+            .unwrap()
+            .with_named_breaker("never", breakers["never"].clone())
+            .with_from_state(s2.id())
+            .with_single_to_state(s3.id());
+        let botix = Botix::build_full(
+            MockController::new(),
+            vec![s0, s1, s2, s3],
+            vec![t0, t1, t2],
+        )
+        .unwrap();
+        (botix, breakers)
+    }
+
+    #[test]
+    fn test_behavior_round_trip() {
+        use crate::controller::MockController;
+
+        let (original, breakers) = make_named_branch();
+        let path = std::env::temp_dir().join(format!("behavior_{}.json", std::process::id()));
+        original.save_behavior(&path).unwrap();
+        let mut loaded = Botix::load_behavior(MockController::new(), &path, &breakers).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let sorted_ids = |botix: &Botix<MockController>| {
+            let mut ids: Vec<usize> = botix.states.keys().copied().collect();
+            ids.sort_unstable();
+            ids
+        };
+        let (old_ids, new_ids) = (sorted_ids(&original), sorted_ids(&loaded));
+        assert!(old_ids.iter().all(|id| !new_ids.contains(id)));
+        assert_eq!(
+            normalize_ids(&original.to_plantuml_string(ArrowStyle::Down), &old_ids),
+            normalize_ids(&loaded.to_plantuml_string(ArrowStyle::Down), &new_ids)
+        );
+
+        // The spec is independent of the IDs, and the named breaker still fires.
+        let mut spec = original.to_behavior_spec().unwrap();
+        let mut reloaded = loaded.to_behavior_spec().unwrap();
+        for (spec, ids) in [(&mut spec, &old_ids), (&mut reloaded, &new_ids)] {
+            let rank = |id: usize| ids.iter().position(|&i| i == id).unwrap();
+            spec.states.iter_mut().for_each(|s| s.id = rank(s.id));
+            for t in &mut spec.transitions {
+                t.from_states.iter_mut().for_each(|id| *id = rank(*id));
+                t.to_states.iter_mut().for_each(|(_, id)| *id = rank(*id));
+            }
+        }
+        assert_eq!(
+            serde_json::to_value(&spec).unwrap(),
+            serde_json::to_value(&reloaded).unwrap()
+        );
+        let visited = loaded.run().unwrap().state_ids();
+        assert_eq!(visited, [new_ids[0], new_ids[1], new_ids[3]]);
+    }
+
+    /// Write `spec` to a fresh behavior file named after `name`.
+    fn write_spec(name: &str, spec: &BehaviorSpec) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("{}_{}.json", name, std::process::id()));
+        std::fs::write(&path, serde_json::to_string_pretty(spec).unwrap()).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_reload_spec() {
+        use crate::controller::MockController;
+
+        let (built, breakers) = make_named_branch();
+        let spec = built.to_behavior_spec().unwrap();
+        let ids: Vec<usize> = spec.states.iter().map(|s| s.id).collect();
+        let loaded = write_spec("reload_base", &spec);
+        let mut botix = Botix::load_behavior(MockController::new(), &loaded, &breakers).unwrap();
+        std::fs::remove_file(&loaded).unwrap();
+
+        // Faster straight, the edge branch and its turn state dropped
+        let mut edited = spec.clone();
+        edited.states[0].pattern = SpeedPattern::Full(300);
+        edited.states.remove(1);
+        let edge = &mut edited.transitions[0];
+        edge.to_states.retain(|(_, to)| *to != ids[1]);
+        edge.breaker = Some("never".to_string());
+        edge.keyed = false;
+        edge.expected_keys = None;
+        edge.duration = 0.02;
+        edited.transitions.remove(1);
+        let path = write_spec("reload_ok", &edited);
+
+        let report = botix.reload_spec(&path, &breakers).unwrap();
+        assert_eq!(report.states_added, Vec::<usize>::new());
+        assert_eq!(report.states_removed, [ids[1]]);
+        assert_eq!(report.states_changed, [ids[0]]);
+        assert_eq!(report.transitions_rewired, [ids[0], ids[1]]);
+        assert_eq!(botix.state_count(), 3);
+        assert_eq!(botix.transition_count(), 2);
+        let first = botix.run().unwrap().visits[0].state_id;
+        assert!(matches!(
+            botix.get_state(first).unwrap().speed_pattern(),
+            SpeedPattern::Full(300)
+        ));
+
+        // Reloading the same file again changes nothing
+        assert!(botix.reload_spec(&path, &breakers).unwrap().is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_reload_spec_into_graph_built_in_code() {
+        use crate::controller::MockController;
+        use std::sync::Mutex;
+
+        // Hooks can't be saved, so the graph has no behavior file to diff against
+        let s0 = MovingState::straight(100).with_before_entering(|| {});
+        let s1 = MovingState::halt();
+        let t0 = MovingTransition::new(0.01)
+            .unwrap()
+            .with_from_state(s0.id())
+            .with_single_to_state(s1.id());
+        let mut botix = Botix::build_full(MockController::new(), vec![s0, s1], vec![t0]).unwrap();
+        assert!(botix.to_behavior_spec().is_err());
+
+        let (source, breakers) = make_named_branch();
+        let spec = source.to_behavior_spec().unwrap();
+        let path = write_spec("reload_coded", &spec);
+        let report = botix.reload_spec(&path, &breakers).unwrap();
+        let ids: Vec<usize> = spec.states.iter().map(|s| s.id).collect();
+        assert_eq!(report.states_added, ids);
+        assert!(report.states_removed.is_empty() && report.states_changed.is_empty());
+        assert_eq!(report.transitions_rewired, [ids[0], ids[1], ids[2]]);
+        assert_eq!(botix.state_count(), 4);
+
+        // Dropping the watcher doesn't wait out its interval
+        let watcher = watch_spec(
+            Arc::new(Mutex::new(botix)),
+            path.clone(),
+            breakers,
+            Duration::from_secs(60),
+        );
+        let dropped = Instant::now();
+        drop(watcher);
+        assert!(dropped.elapsed() < Duration::from_secs(30));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_reload_spec_rollback() {
+        let (mut botix, breakers) = make_named_branch();
+        let before = botix.to_plantuml_string(ArrowStyle::Down);
+        let mut spec = botix.to_behavior_spec().unwrap();
+
+        // The turn state loses its way to the end: two end states
+        spec.transitions.remove(1);
+        spec.states.push(StateSpec {
+            id: 1000,
+            pattern: SpeedPattern::Full(0),
+            wait: false,
+            max_total_duration: None,
+        });
+        let path = write_spec("reload_bad", &spec);
+        assert!(matches!(
+            botix.reload_spec(&path, &breakers),
+            Err(BotixError::InvalidStructure(_))
+        ));
+        std::fs::write(&path, "{").unwrap();
+        assert!(matches!(
+            botix.reload_spec(&path, &breakers),
+            Err(BotixError::Json(_))
+        ));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(botix.to_plantuml_string(ArrowStyle::Down), before);
+        assert_eq!(botix.run().unwrap().visits.len(), 3);
+    }
+
+    #[test]
+    fn test_watch_spec_refuses_while_running() {
+        use std::sync::Mutex;
+
+        // Slow the turn branch down so a run holds the graph for 0.3s
+        let (mut botix, breakers) = make_named_branch();
+        let mut spec = botix.to_behavior_spec().unwrap();
+        spec.transitions[1].duration = 0.3;
+        let path = write_spec("reload_watch", &spec);
+        botix.reload_spec(&path, &breakers).unwrap();
+        let running = botix.running.clone();
+        let botix = Arc::new(Mutex::new(botix));
+        let watcher = watch_spec(
+            botix.clone(),
+            path.clone(),
+            breakers,
+            Duration::from_millis(5),
+        );
+
+        let run = {
+            let botix = botix.clone();
+            std::thread::spawn(move || botix.lock().unwrap().run().unwrap())
+        };
+        while !running.load(Ordering::Acquire) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        spec.states[0].pattern = SpeedPattern::Full(300);
+        std::fs::write(&path, serde_json::to_string_pretty(&spec).unwrap()).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while watcher.status().refused == 0 {
+            assert!(Instant::now() < deadline, "reload was never refused");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(watcher.status().reloads, 0);
+
+        // Swapped in once the run is over
+        assert_eq!(run.join().unwrap().visits.len(), 3);
+        while watcher.status().reloads == 0 {
+            assert!(Instant::now() < deadline, "reload never happened");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let report = watcher.status().last.unwrap().unwrap();
+        assert_eq!(report.states_changed, [spec.states[0].id]);
+        drop(watcher);
+        std::fs::remove_file(&path).unwrap();
+
+        // Reloading directly is refused while flagged running as well
+        let mut botix = botix.lock().unwrap();
+        botix.running.store(true, Ordering::Release);
+        assert!(matches!(
+            botix.reload_spec(&path, &HashMap::new()),
+            Err(BotixError::Running)
+        ));
+    }
+
+    #[test]
+    fn test_behavior_errors() {
+        use crate::controller::MockController;
+
+        let (original, mut breakers) = make_named_branch();
+        let spec = original.to_behavior_spec().unwrap();
+
+        // Graph and parameter errors keep their types.
+        let mut broken = spec.clone();
+        broken.transitions.remove(0);
+        assert!(matches!(
+            Botix::from_behavior_spec(MockController::new(), broken, &breakers),
+            Err(BotixError::InvalidStructure(errors))
+                if matches!(errors[..], [StructureError::StartStates(_)])
+        ));
+        let mut broken = spec.clone();
+        broken.transitions[0].duration = -1.0;
+        assert!(matches!(
+            Botix::from_behavior_spec(MockController::new(), broken, &breakers),
+            Err(BotixError::InvalidMovement(_))
+        ));
+        assert!(matches!(
+            serde_json::from_str::<BehaviorSpec>("{").map_err(BotixError::from),
+            Err(BotixError::Json(_))
+        ));
+
+        breakers.remove("see_edge");
+        let err = Botix::from_behavior_spec(MockController::new(), spec, &breakers)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            &err,
+            BotixError::UnknownBreaker { name, available }
+                if name == "see_edge" && available == &["never"]
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Unknown breaker 'see_edge', available breakers: [never]"
+        );
+
+        // Closures without a name can't be saved.
+        let s0 = MovingState::straight(100);
+        let s1 = MovingState::halt();
+        let t0 = MovingTransition::new(0.1)
+            .unwrap()
+            .with_bool_breaker(|| true)
+            .with_from_state(s0.id())
+            .with_single_to_state(s1.id());
+        let botix = Botix::build_full(MockController::new(), vec![s0, s1], vec![t0]).unwrap();
+        assert!(matches!(
+            botix.to_behavior_spec(),
+            Err(BotixError::Behavior(_))
+        ));
+    }
+
+    #[test]
+    fn test_report_records_transitions_and_exits() {
+        use crate::controller::MockController;
+
+        let s0 = MovingState::straight(100);
+        let s1 = MovingState::turn(TurnDirection::Right, 100);
+        let s2 = MovingState::halt();
+        let ids = [s0.id(), s1.id(), s2.id()];
+        let t0 = MovingTransition::new(0.02)
+            .unwrap()
+            .with_from_state(ids[0])
+            .with_single_to_state(ids[1]);
+        // An unmatched result falls back to the Placeholder branch.
+        let t1 = MovingTransition::new(1.0)
+            .unwrap()
+            .with_breaker(|| BreakerResult::Int(7))
+            .with_fallback()
+            .with_from_state(ids[1])
+            .with_single_to_state(ids[2]);
+        let trans_ids = [t0.id(), t1.id()];
+        let mut botix =
+            Botix::build_full(MockController::new(), vec![s0, s1, s2], vec![t0, t1]).unwrap();
+        botix.set_log_transitions(true);
+        let report = botix.run().unwrap();
+
+        let [timed, broken, end] = &report.visits[..] else {
+            panic!("{:?}", report.visits);
+        };
+        assert_eq!(timed.transition, Some(trans_ids[0]));
+        assert_eq!(timed.exit, Some(ExitReason::DurationElapsed));
+        assert_eq!(timed.branch, Some(BreakerResult::Placeholder));
+        assert!(timed.duration() >= Duration::from_millis(20));
+        assert_eq!(broken.transition, Some(trans_ids[1]));
+        assert_eq!(broken.exit, Some(ExitReason::BreakerFired));
+        assert_eq!(broken.result, Some(BreakerResult::Int(7)));
+        assert_eq!(broken.branch, Some(BreakerResult::Placeholder));
+        assert!(broken.duration() < Duration::from_millis(500));
+        assert_eq!((end.transition, end.exit), (None, None));
+
+        let json = report.to_json();
+        assert_eq!(json["visits"][0]["exit"], "duration");
+        assert_eq!(json["visits"][1]["exit"], "breaker");
+        assert_eq!(json["visits"][1]["result"], serde_json::json!({ "Int": 7 }));
+        assert_eq!(json["visits"][1]["transition"], trans_ids[1]);
+        assert!(json["visits"][2]["exit"].is_null());
+
+        let summary = report.summary();
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].contains(&format!("t{}", trans_ids[0])));
+        assert!(lines[2].ends_with("breaker  _"), "{summary}");
+        assert!(lines[3].ends_with("end"), "{summary}");
+        assert!(lines[4].starts_with("total "));
+    }
+
+    /// A straight state that blocks in `hook` on entry, then a long
+    /// transition into a halt state.
+    fn make_slow_pair(
+        hook: impl Fn() + Send + Sync + 'static,
+        duration: f64,
+    ) -> (MovingState, MovingState, MovingTransition) {
+        let s0 = MovingState::straight(100).with_before_entering(hook);
+        let s1 = MovingState::halt();
+        let t0 = MovingTransition::new(duration)
+            .unwrap()
+            .with_from_state(s0.id())
+            .with_single_to_state(s1.id());
+        (s0, s1, t0)
+    }
+
+    #[test]
+    fn test_hook_timeout_policies() {
+        use crate::controller::MockController;
+
+        let slow = || std::thread::sleep(Duration::from_millis(300));
+        let (s0, s1, t0) = make_slow_pair(slow, 0.01);
+        let (s0_id, s1_id) = (s0.id(), s1.id());
+        let mut botix = Botix::build_full(MockController::new(), vec![s0, s1], vec![t0]).unwrap();
+        botix.set_hook_timeout(Some(0.02), HookPolicy::Continue);
+        let report = botix.run().unwrap();
+        assert_eq!(report.state_ids(), [s0_id, s1_id]);
+        assert_eq!(report.deadline_exceeded(), None);
+        assert_eq!(report.hook_timeouts.len(), 1);
+        let timeout = &report.hook_timeouts[0];
+        assert_eq!(
+            (timeout.state_id, timeout.kind, timeout.index),
+            (s0_id, "before_entering", 0)
+        );
+        assert!(report.total_duration() < Duration::from_millis(250));
+        assert_eq!(
+            report.to_json()["hook_timeouts"][0]["kind"],
+            "before_entering"
+        );
+
+        botix.set_hook_timeout(Some(0.02), HookPolicy::Abort);
+        botix.controller_mut().clear();
+        let report = botix.run().unwrap();
+        assert_eq!(report.state_ids(), [s0_id]);
+        assert_eq!(report.deadline_exceeded(), Some(DeadlineLevel::Hook));
+        assert_eq!(report.visits[0].transition, None);
+        // Aborted before the state's speeds were sent
+        assert_eq!(botix.controller().speeds(), [[0; 4]]);
+    }
+
+    #[test]
+    fn test_state_and_run_deadlines() {
+        use crate::controller::MockController;
+
+        // The state's cap cuts a transition that would hold for seconds
+        let (s0, s1, t0) = make_slow_pair(|| {}, 5.0);
+        let s0 = s0.with_max_total_duration(0.05);
+        let s0_id = s0.id();
+        let mut botix = Botix::build_full(MockController::new(), vec![s0, s1], vec![t0]).unwrap();
+        let report = botix.run().unwrap();
+        assert_eq!(report.state_ids(), [s0_id]);
+        assert_eq!(report.deadline_exceeded(), Some(DeadlineLevel::State));
+        assert!(report.visits[0].duration() >= Duration::from_millis(50));
+        assert!(report.total_duration() < Duration::from_secs(1));
+        assert_eq!(botix.controller().speeds().last(), Some(&[0; 4]));
+        assert_eq!(report.to_json()["visits"][0]["exit"], "state_deadline");
+
+        // The run's deadline comes first and also bounds a blocking hook
+        let (s0, s1, t0) = make_slow_pair(|| std::thread::sleep(Duration::from_secs(2)), 5.0);
+        let s0 = s0.with_max_total_duration(1.0);
+        let mut botix = Botix::build_full(MockController::new(), vec![s0, s1], vec![t0]).unwrap();
+        botix.set_run_deadline(Some(0.05));
+        let report = botix.run().unwrap();
+        assert_eq!(report.deadline_exceeded(), Some(DeadlineLevel::Run));
+        assert!(report.hook_timeouts.is_empty());
+        assert!(report.total_duration() < Duration::from_millis(500));
+        assert!(report.summary().contains("run_deadline"));
+
+        // A deadline that is never reached changes nothing
+        let (s0, s1, t0) = make_slow_pair(|| {}, 0.01);
+        let s0 = s0.with_max_total_duration(1.0);
+        let mut botix = Botix::build_full(MockController::new(), vec![s0, s1], vec![t0]).unwrap();
+        botix.set_run_deadline(Some(1.0));
+        let report = botix.run().unwrap();
+        assert_eq!(report.visits.len(), 2);
+        assert_eq!(report.visits[0].exit, Some(ExitReason::DurationElapsed));
+        assert_eq!(report.deadline_exceeded(), None);
+    }
 }
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use kazu_core::Pose;
+
+use crate::state::MovementConfig;
+use crate::transition::BreakerResult;
+
+use super::Botix;
+
+/// Pixels per meter in rendered path previews.
+const PIXELS_PER_METER: f64 = 200.0;
+/// Blank border around the rendered path, in pixels.
+const MARGIN: f64 = 40.0;
+/// Integration step for dead reckoning, in seconds.
+const INTEGRATION_STEP: f64 = 0.02;
+
+/// How to resolve the graph when previewing the path a routine traces.
+#[derive(Debug, Clone)]
+pub struct PathAssumptions {
+    /// Meters per length unit. Speeds are taken as these units per second,
+    /// and `MovementConfig::track_width` is measured in the same unit.
+    pub meters_per_unit: f64,
+    /// Breaker outcome to assume per transition ID. Branching transitions
+    /// not listed here follow their timeout (`Placeholder`) edge.
+    pub outcomes: HashMap<usize, BreakerResult>,
+    /// Upper bound on visited states.
+    pub max_steps: usize,
+}
+
+impl Default for PathAssumptions {
+    fn default() -> Self {
+        Self {
+            meters_per_unit: 0.001,
+            outcomes: HashMap::new(),
+            max_steps: 1000,
+        }
+    }
+}
+
+/// A state entry along the previewed path.
+struct Marker {
+    x: f64,
+    y: f64,
+    label: String,
+}
+
+impl<C> Botix<C> {
+    /// Render the most likely path through the graph as a top-down SVG.
+    ///
+    /// Walks from the start state, resolving breakers per `assumptions`, and
+    /// dead-reckons the pose over each state's nominal transition duration.
+    /// The start pose is at the origin facing +x. Left/right side speeds are
+    /// the front/rear averages, so drift patterns are approximated.
+    ///
+    /// Anything the walk had to guess — uncovered branches, dynamic speeds,
+    /// unbounded durations — is written into the SVG as a note.
+    pub fn render_path_svg(&self, config: &MovementConfig, assumptions: PathAssumptions) -> String {
+        let mut pose = Pose::default();
+        let mut points = vec![(0.0, 0.0)];
+        let mut markers = Vec::new();
+        let mut notes = Vec::new();
+        let mut current = self.start_state;
+
+        for step in 0.. {
+            if step == assumptions.max_steps {
+                notes.push(format!(
+                    "stopped after {} states (max_steps)",
+                    assumptions.max_steps
+                ));
+                break;
+            }
+            let Some(state) = self.states.get(&current) else {
+                break;
+            };
+
+            markers.push(Marker {
+                x: pose.x,
+                y: pose.y,
+                label: state.label(),
+            });
+            if state.is_dynamic() {
+                notes.push(format!("{}: dynamic speeds assumed zero", state.label()));
+            }
+
+            let Some(trans) = self
+                .forward_edge
+                .get(&current)
+                .and_then(|tid| self.transitions.get(tid))
+            else {
+                break;
+            };
+            if !trans.duration.is_finite() {
+                notes.push(format!(
+                    "{}: unbounded duration, path ends here",
+                    state.label()
+                ));
+                break;
+            }
+
+            let speeds = state.speeds();
+            let left = (speeds[0] + speeds[1]) as f64 / 2.0;
+            let right = (speeds[2] + speeds[3]) as f64 / 2.0;
+            let (linear, angular) = config.chassis_velocity(left, right);
+            let substeps = (trans.duration / INTEGRATION_STEP).ceil().max(1.0) as usize;
+            let dt = trans.duration / substeps as f64;
+            for _ in 0..substeps {
+                pose.integrate(linear, angular, dt);
+                push_point(&mut points, (pose.x, pose.y));
+            }
+
+            let next = match assumptions.outcomes.get(&trans.id()) {
+                Some(outcome) => trans.to_states.get(outcome).copied().or_else(|| {
+                    notes.push(format!(
+                        "transition {}: assumed outcome {} has no target",
+                        trans.id(),
+                        outcome
+                    ));
+                    None
+                }),
+                None if trans.to_states.len() > 1 => {
+                    notes.push(format!(
+                        "transition {}: branch not covered by assumptions, followed timeout",
+                        trans.id()
+                    ));
+                    trans.to_states.get(&BreakerResult::Placeholder).copied()
+                }
+                None => trans.to_states.values().next().copied(),
+            };
+            match next {
+                Some(next) => current = next,
+                None => break,
+            }
+        }
+
+        let scale = assumptions.meters_per_unit;
+        let points: Vec<(f64, f64)> = points
+            .iter()
+            .map(|&(x, y)| (x * scale, y * scale))
+            .collect();
+        for marker in &mut markers {
+            marker.x *= scale;
+            marker.y *= scale;
+        }
+        render_svg(&points, &markers, &notes)
+    }
+}
+
+/// Append a path point, merging it into the last segment when collinear.
+fn push_point(points: &mut Vec<(f64, f64)>, point: (f64, f64)) {
+    const EPSILON: f64 = 1e-9;
+    let Some(&last) = points.last() else {
+        points.push(point);
+        return;
+    };
+    if (point.0 - last.0).abs() < EPSILON && (point.1 - last.1).abs() < EPSILON {
+        return;
+    }
+    if points.len() >= 2 {
+        let prev = points[points.len() - 2];
+        let cross = (last.0 - prev.0) * (point.1 - prev.1) - (last.1 - prev.1) * (point.0 - prev.0);
+        let forward =
+            (last.0 - prev.0) * (point.0 - last.0) + (last.1 - prev.1) * (point.1 - last.1);
+        if cross.abs() < EPSILON && forward > 0.0 {
+            *points.last_mut().unwrap() = point;
+            return;
+        }
+    }
+    points.push(point);
+}
+
+/// Tick spacing in meters for an extent in meters.
+fn tick_spacing(extent: f64) -> f64 {
+    if extent <= 2.0 {
+        0.5
+    } else if extent <= 10.0 {
+        1.0
+    } else {
+        5.0
+    }
+}
+
+fn render_svg(points: &[(f64, f64)], markers: &[Marker], notes: &[String]) -> String {
+    let min_x = points.iter().map(|p| p.0).fold(0.0, f64::min);
+    let max_x = points.iter().map(|p| p.0).fold(0.0, f64::max);
+    let min_y = points.iter().map(|p| p.1).fold(0.0, f64::min);
+    let max_y = points.iter().map(|p| p.1).fold(0.0, f64::max);
+
+    let width = (max_x - min_x) * PIXELS_PER_METER + 2.0 * MARGIN;
+    let height = (max_y - min_y) * PIXELS_PER_METER + 2.0 * MARGIN;
+    let px = |x: f64| (x - min_x) * PIXELS_PER_METER + MARGIN;
+    let py = |y: f64| (max_y - y) * PIXELS_PER_METER + MARGIN;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.1}\" height=\"{:.1}\">",
+        width, height
+    );
+
+    // Axes through the start pose, ticked in meters.
+    let _ = writeln!(
+        svg,
+        "  <line class=\"axis\" x1=\"0.0\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#999\"/>",
+        py(0.0),
+        width,
+        py(0.0)
+    );
+    let _ = writeln!(
+        svg,
+        "  <line class=\"axis\" x1=\"{:.1}\" y1=\"0.0\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#999\"/>",
+        px(0.0),
+        px(0.0),
+        height
+    );
+    let tick = tick_spacing((max_x - min_x).max(max_y - min_y));
+    let mut t = (min_x / tick).ceil() * tick;
+    while t <= max_x + 1e-9 {
+        let _ = writeln!(
+            svg,
+            "  <text class=\"tick\" x=\"{:.1}\" y=\"{:.1}\" font-size=\"10\">{:.1} m</text>",
+            px(t),
+            py(0.0) + 12.0,
+            t
+        );
+        t += tick;
+    }
+    let mut t = (min_y / tick).ceil() * tick;
+    while t <= max_y + 1e-9 {
+        if t.abs() > 1e-9 {
+            let _ = writeln!(
+                svg,
+                "  <text class=\"tick\" x=\"{:.1}\" y=\"{:.1}\" font-size=\"10\">{:.1} m</text>",
+                px(0.0) + 4.0,
+                py(t),
+                t
+            );
+        }
+        t += tick;
+    }
+
+    let polyline: Vec<String> = points
+        .iter()
+        .map(|&(x, y)| format!("{:.1},{:.1}", px(x), py(y)))
+        .collect();
+    let _ = writeln!(
+        svg,
+        "  <polyline class=\"path\" points=\"{}\" fill=\"none\" stroke=\"#1f77b4\" stroke-width=\"2\"/>",
+        polyline.join(" ")
+    );
+
+    for marker in markers {
+        let _ = writeln!(
+            svg,
+            "  <circle class=\"entry\" cx=\"{:.1}\" cy=\"{:.1}\" r=\"4\" fill=\"#d62728\"/>",
+            px(marker.x),
+            py(marker.y)
+        );
+        let _ = writeln!(
+            svg,
+            "  <text class=\"label\" x=\"{:.1}\" y=\"{:.1}\" font-size=\"12\">{}</text>",
+            px(marker.x) + 6.0,
+            py(marker.y) - 6.0,
+            marker.label
+        );
+    }
+
+    for (i, note) in notes.iter().enumerate() {
+        let _ = writeln!(
+            svg,
+            "  <text class=\"note\" x=\"4.0\" y=\"{:.1}\" font-size=\"11\" fill=\"#b35900\">note: {}</text>",
+            14.0 + 14.0 * i as f64,
+            note
+        );
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
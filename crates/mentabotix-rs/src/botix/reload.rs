@@ -0,0 +1,238 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, PoisonError, TryLockError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde_json::Value;
+
+use super::{BehaviorSpec, Botix, BotixError};
+use crate::controller::{MockController, MotorController};
+use crate::transition::BreakerFn;
+
+/// What a reload changed, by the state IDs of the behavior files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReloadReport {
+    /// States only in the new file.
+    pub states_added: Vec<usize>,
+    /// States only in the old pool.
+    pub states_removed: Vec<usize>,
+    /// States in both whose speeds, wait flag or duration cap changed.
+    pub states_changed: Vec<usize>,
+    /// States whose outgoing transition was added, removed or changed
+    /// (targets, breaker, duration or flags).
+    pub transitions_rewired: Vec<usize>,
+}
+
+impl ReloadReport {
+    /// Whether the new file describes the same graph.
+    pub fn is_empty(&self) -> bool {
+        self.states_added.is_empty()
+            && self.states_removed.is_empty()
+            && self.states_changed.is_empty()
+            && self.transitions_rewired.is_empty()
+    }
+
+    fn between(old: &BehaviorSpec, new: &BehaviorSpec) -> Self {
+        let states = |spec: &BehaviorSpec| -> BTreeMap<usize, (Value, bool, Option<f64>)> {
+            spec.states
+                .iter()
+                .map(|s| (s.id, (to_value(&s.pattern), s.wait, s.max_total_duration)))
+                .collect()
+        };
+        // Each state has at most one outgoing transition, so its from-states key it
+        let transitions = |spec: &BehaviorSpec| -> BTreeMap<usize, Value> {
+            let mut by_state = BTreeMap::new();
+            for t in &spec.transitions {
+                let mut t = t.clone();
+                t.to_states.sort_by_key(|(key, to)| (*to, key.to_string()));
+                let value = to_value(&t);
+                for &from in &t.from_states {
+                    by_state.insert(from, value.clone());
+                }
+            }
+            by_state
+        };
+
+        let (old_states, new_states) = (states(old), states(new));
+        let (old_trans, new_trans) = (transitions(old), transitions(new));
+        let from_states: BTreeSet<usize> =
+            old_trans.keys().chain(new_trans.keys()).copied().collect();
+        ReloadReport {
+            states_added: new_states
+                .keys()
+                .filter(|id| !old_states.contains_key(id))
+                .copied()
+                .collect(),
+            states_removed: old_states
+                .keys()
+                .filter(|id| !new_states.contains_key(id))
+                .copied()
+                .collect(),
+            states_changed: new_states
+                .iter()
+                .filter(|(id, state)| old_states.get(id).is_some_and(|old| old != *state))
+                .map(|(&id, _)| id)
+                .collect(),
+            transitions_rewired: from_states
+                .into_iter()
+                .filter(|id| old_trans.get(id) != new_trans.get(id))
+                .collect(),
+        }
+    }
+}
+
+fn to_value<T: serde::Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+impl<C: MotorController> Botix<C> {
+    /// Replace the graph with the one in a behavior file, between runs.
+    ///
+    /// The file is parsed and built as in [`Botix::load_behavior`] and checked
+    /// with [`Botix::ensure_structure_validity`] before anything is swapped,
+    /// so on error the old graph stays in place untouched. The controller,
+    /// speed limit, clamp policy and logging settings are kept; the new
+    /// states and transitions get fresh IDs.
+    ///
+    /// The report compares the file with the one the graph was loaded from.
+    /// A graph built in code has no file IDs to compare with, so all of the
+    /// file's states and transitions are reported as added.
+    ///
+    /// Returns `BotixError::Running` while [`Botix::run`] executes the graph.
+    pub fn reload_spec(
+        &mut self,
+        path: &Path,
+        breakers: &HashMap<String, BreakerFn>,
+    ) -> Result<ReloadReport, BotixError> {
+        if self.is_running() {
+            return Err(BotixError::Running);
+        }
+        let json = fs::read_to_string(path)?;
+        let spec: BehaviorSpec = serde_json::from_str(&json)?;
+        let old = self.spec.clone().unwrap_or_default();
+        let report = ReloadReport::between(&old, &spec);
+        let new = Botix::from_behavior_spec(MockController::new(), spec, breakers)?;
+        new.ensure_structure_validity()?;
+
+        self.states = new.states;
+        self.transitions = new.transitions;
+        self.forward_edge = new.forward_edge;
+        self.incoming_edges = new.incoming_edges;
+        self.start_state = new.start_state;
+        self.allow_cycles = new.allow_cycles;
+        self.spec = new.spec;
+        self.last_sent = None;
+        log::info!("Reloaded graph from {}: {:?}", path.display(), report);
+        Ok(report)
+    }
+}
+
+/// Counters of a [`SpecWatcher`].
+#[derive(Debug, Clone, Default)]
+pub struct WatchStatus {
+    /// Changes of the file that were swapped in.
+    pub reloads: usize,
+    /// Checks that found the file changed while the graph was running.
+    pub refused: usize,
+    /// Outcome of the latest reload attempt, the error as text.
+    pub last: Option<Result<ReloadReport, String>>,
+}
+
+/// Reloads a behavior file into a shared graph whenever it changes; stops
+/// when dropped.
+pub struct SpecWatcher {
+    /// Dropped to stop the thread, waking it from its wait between checks.
+    stop: Option<mpsc::Sender<()>>,
+    status: Arc<Mutex<WatchStatus>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SpecWatcher {
+    /// A snapshot of the counters.
+    pub fn status(&self) -> WatchStatus {
+        self.status
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl Drop for SpecWatcher {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Watch `path` and [`Botix::reload_spec`] it into `botix` whenever its
+/// contents change.
+///
+/// Checks the file every `interval`. A change found while the graph is
+/// running is refused and tried again at the next check, so reloads only
+/// ever happen between runs. A file that fails to reload is not retried
+/// until it changes again.
+pub fn watch_spec<C: MotorController + Send + 'static>(
+    botix: Arc<Mutex<Botix<C>>>,
+    path: PathBuf,
+    breakers: HashMap<String, BreakerFn>,
+    interval: Duration,
+) -> SpecWatcher {
+    let (stop, stopped) = mpsc::channel::<()>();
+    let status = Arc::new(Mutex::new(WatchStatus::default()));
+    let running = botix
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .running
+        .clone();
+    let mut applied = fs::read_to_string(&path).ok();
+
+    let thread = {
+        let status = status.clone();
+        thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                let contents = fs::read_to_string(&path).ok();
+                if contents.is_none() || contents == applied {
+                    continue;
+                }
+                // Refused while a run holds the graph's lock or is flagged running
+                let mut graph = match botix.try_lock() {
+                    Ok(graph) if !running.load(Ordering::Acquire) => graph,
+                    Ok(_) | Err(TryLockError::WouldBlock) => {
+                        log::debug!("{} changed while running, reload refused", path.display());
+                        status
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .refused += 1;
+                        continue;
+                    }
+                    Err(TryLockError::Poisoned(e)) => e.into_inner(),
+                };
+                let result = graph.reload_spec(&path, &breakers);
+                drop(graph);
+                if let Err(e) = &result {
+                    log::warn!(
+                        "Reloading {} failed, keeping the old graph: {}",
+                        path.display(),
+                        e
+                    );
+                }
+                applied = contents;
+                let mut status = status.lock().unwrap_or_else(PoisonError::into_inner);
+                status.reloads += usize::from(result.is_ok());
+                status.last = Some(result.map_err(|e| e.to_string()));
+            }
+        })
+    };
+
+    SpecWatcher {
+        stop: Some(stop),
+        status,
+        thread: Some(thread),
+    }
+}
@@ -0,0 +1,211 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::transition::BreakerResult;
+
+/// Which deadline of a run ran out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadlineLevel {
+    /// A hook outlived `Botix::set_hook_timeout` under `HookPolicy::Abort`.
+    Hook,
+    /// The state outlived `MovingState::with_max_total_duration`.
+    State,
+    /// The run outlived `Botix::set_run_deadline`.
+    Run,
+}
+
+impl DeadlineLevel {
+    /// Short name used in logs.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            DeadlineLevel::Hook => "hook",
+            DeadlineLevel::State => "state",
+            DeadlineLevel::Run => "run",
+        }
+    }
+}
+
+impl fmt::Display for DeadlineLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Why a state's outgoing transition ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The breaker returned a result before the duration was up.
+    BreakerFired,
+    /// The transition's duration elapsed without a break.
+    DurationElapsed,
+    /// A deadline ran out in the state; the run was aborted there with the
+    /// motors stopped.
+    DeadlineExceeded(DeadlineLevel),
+}
+
+impl ExitReason {
+    /// Short name used in summaries and JSON.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ExitReason::BreakerFired => "breaker",
+            ExitReason::DurationElapsed => "duration",
+            ExitReason::DeadlineExceeded(DeadlineLevel::Hook) => "hook_deadline",
+            ExitReason::DeadlineExceeded(DeadlineLevel::State) => "state_deadline",
+            ExitReason::DeadlineExceeded(DeadlineLevel::Run) => "run_deadline",
+        }
+    }
+}
+
+impl fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// One state passed through during a run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateVisit {
+    /// ID of the visited state.
+    pub state_id: usize,
+    /// Time since the run started when the state was entered.
+    pub entered: Duration,
+    /// Time since the run started when the state was left.
+    pub exited: Duration,
+    /// Transition that left the state; `None` for the end state and a state
+    /// left on a deadline.
+    pub transition: Option<usize>,
+    /// Breaker result that picked the next state; `None` for the end state.
+    pub result: Option<BreakerResult>,
+    /// Key of the `to_states` branch taken, which differs from `result`
+    /// when the result fell back to the default branch.
+    pub branch: Option<BreakerResult>,
+    /// Whether the breaker fired, the duration ran out or a deadline aborted
+    /// the run; `None` for the end state.
+    pub exit: Option<ExitReason>,
+    /// Whether the state is a wait state (see `MovingState::wait`).
+    pub wait: bool,
+}
+
+impl StateVisit {
+    /// Time spent in the state.
+    pub fn duration(&self) -> Duration {
+        self.exited.saturating_sub(self.entered)
+    }
+}
+
+/// A hook given up on after `Botix::set_hook_timeout`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookTimeout {
+    /// ID of the state the hook belongs to.
+    pub state_id: usize,
+    /// `"before_entering"` or `"after_exiting"`.
+    pub kind: &'static str,
+    /// Position of the hook in its list.
+    pub index: usize,
+    /// Time since the run started when the hook was given up on.
+    pub at: Duration,
+}
+
+/// Record of a completed `Botix::run()`.
+#[derive(Debug, Clone)]
+pub struct ExecutionReport {
+    /// When the run started; visit timestamps are relative to it.
+    pub started: Instant,
+    /// Visited states in order, ending with the end state or the state a
+    /// deadline aborted the run in.
+    pub visits: Vec<StateVisit>,
+    /// Hooks that timed out, in order, whether or not they aborted the run.
+    pub hook_timeouts: Vec<HookTimeout>,
+}
+
+impl ExecutionReport {
+    /// The deadline that aborted the run, if one did.
+    pub fn deadline_exceeded(&self) -> Option<DeadlineLevel> {
+        match self.visits.last()?.exit? {
+            ExitReason::DeadlineExceeded(level) => Some(level),
+            _ => None,
+        }
+    }
+
+    /// IDs of the visited states in order.
+    pub fn state_ids(&self) -> Vec<usize> {
+        self.visits.iter().map(|v| v.state_id).collect()
+    }
+
+    /// Time from the start of the run until the end state was reached.
+    pub fn total_duration(&self) -> Duration {
+        self.visits.last().map_or(Duration::ZERO, |v| v.exited)
+    }
+
+    /// The report as JSON, with times in seconds.
+    pub fn to_json(&self) -> serde_json::Value {
+        let visits: Vec<serde_json::Value> = self
+            .visits
+            .iter()
+            .map(|v| {
+                serde_json::json!({
+                    "state_id": v.state_id,
+                    "entered": v.entered.as_secs_f64(),
+                    "exited": v.exited.as_secs_f64(),
+                    "duration": v.duration().as_secs_f64(),
+                    "transition": v.transition,
+                    "result": v.result,
+                    "branch": v.branch,
+                    "exit": v.exit.map(ExitReason::as_str),
+                    "wait": v.wait,
+                })
+            })
+            .collect();
+        let hook_timeouts: Vec<serde_json::Value> = self
+            .hook_timeouts
+            .iter()
+            .map(|h| {
+                serde_json::json!({
+                    "state_id": h.state_id,
+                    "kind": h.kind,
+                    "index": h.index,
+                    "at": h.at.as_secs_f64(),
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "visits": visits,
+            "hook_timeouts": hook_timeouts,
+            "total_duration": self.total_duration().as_secs_f64(),
+        })
+    }
+
+    /// A table of the visits, one line per state, and the total run time.
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!(
+            "{:>8} {:>9} {:>9} {:>10}  {:<8} {}",
+            "state", "entered", "held", "transition", "exit", "branch"
+        )];
+        for v in &self.visits {
+            let transition = v.transition.map_or("-".to_string(), |t| format!("t{}", t));
+            let exit = v.exit.map_or("end", ExitReason::as_str);
+            let branch = v.branch.as_ref().map_or(String::new(), |b| b.to_string());
+            let state = if v.wait {
+                format!("s{} wait", v.state_id)
+            } else {
+                format!("s{}", v.state_id)
+            };
+            let line = format!(
+                "{:>8} {:>8.3}s {:>8.3}s {:>10}  {:<8} {}",
+                state,
+                v.entered.as_secs_f64(),
+                v.duration().as_secs_f64(),
+                transition,
+                exit,
+                branch
+            );
+            lines.push(line.trim_end().to_string());
+        }
+        lines.push(format!(
+            "total {:.3}s over {} states",
+            self.total_duration().as_secs_f64(),
+            self.visits.len()
+        ));
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+use std::time::Duration;
+
+use super::{Botix, BotixError, seconds};
+use crate::controller::MotorController;
+use crate::state::lookup_state_label;
+use crate::transition::{BreakerResult, MovingTransition};
+
+/// Speeds a state would send at a point of a dry run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceEntry {
+    /// Simulated time since the start of the run.
+    pub t: Duration,
+    /// ID of the state sending the speeds.
+    pub state_id: usize,
+    /// Speeds sent, one per motor.
+    pub speeds: [i32; 4],
+}
+
+/// Speed timeline produced by `Botix::dry_run()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationTrace {
+    /// One entry per state entered, plus one per state controller evaluation.
+    pub entries: Vec<TraceEntry>,
+    /// Simulated time at which the end state was reached.
+    pub end: Duration,
+}
+
+impl SimulationTrace {
+    /// IDs of the entered states in order, with consecutive entries of one
+    /// state collapsed.
+    pub fn state_ids(&self) -> Vec<usize> {
+        let mut ids: Vec<usize> = self.entries.iter().map(|e| e.state_id).collect();
+        ids.dedup();
+        ids
+    }
+
+    /// The trace as JSON, with times in seconds, for plotting.
+    pub fn to_json(&self) -> serde_json::Value {
+        let entries: Vec<serde_json::Value> = self
+            .entries
+            .iter()
+            .map(|e| {
+                serde_json::json!({
+                    "t": e.t.as_secs_f64(),
+                    "state_id": e.state_id,
+                    "speeds": e.speeds,
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "entries": entries,
+            "end": self.end.as_secs_f64(),
+        })
+    }
+}
+
+impl fmt::Display for SimulationTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for e in &self.entries {
+            let label =
+                lookup_state_label(e.state_id).unwrap_or_else(|| format!("State({})", e.state_id));
+            let speeds = format!("{:?}", e.speeds);
+            writeln!(
+                f,
+                "{:>8.3}s  s{:<4} {:<24} {}",
+                e.t.as_secs_f64(),
+                e.state_id,
+                speeds,
+                label
+            )?;
+        }
+        write!(f, "{:>8.3}s  end", self.end.as_secs_f64())
+    }
+}
+
+/// Simulated time, optionally slept through at a fraction of real time.
+struct SimClock {
+    now: Duration,
+    time_scale: f64,
+}
+
+impl SimClock {
+    fn advance(&mut self, dt: Duration) {
+        self.now = self.now.saturating_add(dt);
+        if self.time_scale > 0.0 {
+            std::thread::sleep(seconds(dt.as_secs_f64() * self.time_scale));
+        }
+    }
+}
+
+impl<C: MotorController> Botix<C> {
+    /// Walk the graph like [`Botix::run`] without sending anything to the
+    /// controller, recording the speeds each state would send.
+    ///
+    /// Speeds and state controllers are resolved against the controller's
+    /// context and clamped as in a real run. Breakers are polled every `check_interval` of simulated time
+    /// and pick the next state as in a real run; hooks are not called.
+    /// `time_scale` is the real time slept per simulated second: `1.0` plays
+    /// the run in real time, `0.0` doesn't sleep at all.
+    ///
+    /// A breaker that never fires on an unbounded transition, or a cycle
+    /// allowed by `set_allow_cycles`, keeps the dry run going forever, as it
+    /// would a real one.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Botix::run`], except that the controller is never called.
+    pub fn dry_run(&self, time_scale: f64) -> Result<SimulationTrace, BotixError> {
+        self.ensure_structure_validity()?;
+        let mut clock = SimClock {
+            now: Duration::ZERO,
+            time_scale,
+        };
+        let mut entries = Vec::new();
+        let mut current = self.start_state;
+
+        loop {
+            let state = self
+                .states
+                .get(&current)
+                .ok_or(BotixError::UnknownState(current))?;
+            entries.push(TraceEntry {
+                t: clock.now,
+                state_id: current,
+                speeds: self.limit_speeds(current, state.resolve_speeds(self.controller.context())),
+            });
+
+            let Some(&trans_id) = self.forward_edge.get(&current) else {
+                return Ok(SimulationTrace {
+                    entries,
+                    end: clock.now,
+                });
+            };
+            let trans = self
+                .transitions
+                .get(&trans_id)
+                .ok_or(BotixError::UnknownTransition(trans_id))?;
+            let result = self.simulate_transition(current, trans, &mut clock, &mut entries);
+            current = trans
+                .next_state(&result)
+                .ok_or(BotixError::NoMatchingBranch {
+                    transition: trans_id,
+                    result,
+                })?;
+        }
+    }
+
+    /// Wait out a transition in simulated time, recording state controller
+    /// evaluations. Returns the breaker result as `wait_transition` does.
+    fn simulate_transition(
+        &self,
+        state_id: usize,
+        trans: &MovingTransition,
+        clock: &mut SimClock,
+        entries: &mut Vec<TraceEntry>,
+    ) -> BreakerResult {
+        let state_controller = self.states.get(&state_id).and_then(|s| s.controller());
+        let end = clock.now.saturating_add(seconds(trans.duration));
+        if trans.breaker.is_none() && state_controller.is_none() {
+            clock.advance(end - clock.now);
+            return BreakerResult::Placeholder;
+        }
+
+        let poll = || {
+            trans
+                .breaker
+                .as_ref()
+                .map_or(BreakerResult::Placeholder, |breaker| breaker())
+        };
+        let step = seconds(trans.check_interval.max(0.001));
+        let mut result = poll();
+        while result == BreakerResult::Placeholder && clock.now < end {
+            let dt = step.min(end - clock.now);
+            clock.advance(dt);
+            if let Some(state_controller) = state_controller {
+                let ctx = self.controller.context();
+                entries.push(TraceEntry {
+                    t: clock.now,
+                    state_id,
+                    speeds: self.limit_speeds(
+                        state_id,
+                        state_controller(ctx, dt.as_secs_f64()).resolve_speeds(ctx),
+                    ),
+                });
+            }
+            result = poll();
+        }
+        result
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use super::{Botix, BotixError};
+
+/// A structural problem in a Botix graph, carrying the offending IDs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StructureError {
+    /// Zero or several states without incoming transitions.
+    StartStates(Vec<usize>),
+    /// States not reachable from the start state.
+    Unreachable(Vec<usize>),
+    /// States forming a cycle, in the order they are visited.
+    Cycle(Vec<usize>),
+    /// A transition's branch keys don't match its breaker's keys.
+    InvalidKeys { transition: usize, detail: String },
+    /// A transition leaving no state.
+    EmptyFromStates(usize),
+    /// A transition leading to no state.
+    EmptyToStates(usize),
+    /// A transition passed to `Botix::build_full` more than once.
+    DuplicateTransition(usize),
+    /// A wait state whose transition has no breaker and no finite timeout.
+    InfiniteWait(usize),
+}
+
+impl fmt::Display for StructureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StructureError::StartStates(ids) => write!(
+                f,
+                "Must have exactly one start state (indegree 0), found {}: {:?}",
+                ids.len(),
+                ids
+            ),
+            StructureError::Unreachable(ids) => {
+                write!(f, "States not reachable from the start state: {:?}", ids)
+            }
+            StructureError::Cycle(ids) => write!(f, "States form a cycle: {:?}", ids),
+            StructureError::InvalidKeys { detail, .. } => write!(f, "{}", detail),
+            StructureError::EmptyFromStates(id) => {
+                write!(f, "Transition {} has no from_states", id)
+            }
+            StructureError::EmptyToStates(id) => write!(f, "Transition {} has no to_states", id),
+            StructureError::DuplicateTransition(id) => {
+                write!(f, "Duplicate transition ID: {}", id)
+            }
+            StructureError::InfiniteWait(id) => write!(
+                f,
+                "Wait state {} has no breaker and no finite timeout (likely infinite wait)",
+                id
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StructureError {}
+
+impl<C> Botix<C> {
+    /// Allow or reject cycles in `ensure_structure_validity`, and so in
+    /// `run`. Rejected by default; routines that loop back, such as a
+    /// stage-check loop, must opt in.
+    pub fn set_allow_cycles(&mut self, allow: bool) -> &mut Self {
+        self.allow_cycles = allow;
+        self
+    }
+
+    /// Check the graph structure, collecting every problem found.
+    ///
+    /// Checks for exactly one start state, states unreachable from it,
+    /// cycles (unless allowed with `set_allow_cycles`), keyed breakers whose
+    /// keys don't match their branches, transitions with empty `from_states`
+    /// or `to_states`, and wait states that could wait forever. `run` calls
+    /// this first and refuses to start on errors.
+    pub fn ensure_structure_validity(&self) -> Result<(), Vec<StructureError>> {
+        let mut errors = Vec::new();
+
+        let mut starts: Vec<usize> = self.start_states().into_iter().collect();
+        starts.sort_unstable();
+        if starts.len() != 1 {
+            errors.push(StructureError::StartStates(starts));
+        }
+
+        let reachable = Self::compute_reachable_set(
+            &self.states,
+            &self.forward_edge,
+            &self.transitions,
+            self.start_state,
+        );
+        let mut unreachable: Vec<usize> = self
+            .states
+            .keys()
+            .filter(|id| !reachable.contains(id))
+            .copied()
+            .collect();
+        if !unreachable.is_empty() {
+            unreachable.sort_unstable();
+            errors.push(StructureError::Unreachable(unreachable));
+        }
+
+        if !self.allow_cycles {
+            errors.extend(self.find_loops().into_iter().map(StructureError::Cycle));
+        }
+
+        let mut transition_ids: Vec<usize> = self.transitions.keys().copied().collect();
+        transition_ids.sort_unstable();
+        for tid in transition_ids {
+            let t = &self.transitions[&tid];
+            if t.from_states.is_empty() {
+                errors.push(StructureError::EmptyFromStates(tid));
+            }
+            if t.to_states.is_empty() {
+                errors.push(StructureError::EmptyToStates(tid));
+            }
+            if let Err(detail) = t.validate() {
+                errors.push(StructureError::InvalidKeys {
+                    transition: tid,
+                    detail,
+                });
+            }
+        }
+
+        let waits: HashSet<usize> = self
+            .states
+            .values()
+            .filter(|s| s.is_wait())
+            .map(|s| s.id())
+            .collect();
+        let mut infinite: Vec<usize> = waits
+            .into_iter()
+            .filter(|sid| {
+                self.forward_edge
+                    .get(sid)
+                    .and_then(|tid| self.transitions.get(tid))
+                    .is_some_and(|t| !t.has_breaker() && !t.duration.is_finite())
+            })
+            .collect();
+        infinite.sort_unstable();
+        errors.extend(infinite.into_iter().map(StructureError::InfiniteWait));
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl From<Vec<StructureError>> for BotixError {
+    fn from(errors: Vec<StructureError>) -> Self {
+        BotixError::InvalidStructure(errors)
+    }
+}
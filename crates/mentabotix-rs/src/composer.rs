@@ -10,6 +10,8 @@ pub struct MovingChainComposer {
     transitions: Vec<MovingTransition>,
     /// When true, the next `add` expects a MovingState; when false, a MovingTransition.
     next_is_state: bool,
+    /// Extra breaker keys to route to the next added state (besides Placeholder).
+    pending_keys: Vec<BreakerResult>,
 }
 
 impl MovingChainComposer {
@@ -19,6 +21,7 @@ impl MovingChainComposer {
             states: Vec::new(),
             transitions: Vec::new(),
             next_is_state: true,
+            pending_keys: Vec::new(),
         }
     }
 
@@ -46,6 +49,9 @@ impl MovingChainComposer {
             last_trans
                 .to_states
                 .insert(BreakerResult::Placeholder, state_id);
+            for key in self.pending_keys.drain(..) {
+                last_trans.to_states.insert(key, state_id);
+            }
         }
 
         self.states.push(state);
@@ -79,6 +85,38 @@ impl MovingChainComposer {
         self
     }
 
+    /// Sit still for `duration` seconds.
+    ///
+    /// Adds a wait state and a breakerless transition; the next added state
+    /// becomes the exit.
+    pub fn wait_for(&mut self, duration: f64) -> &mut Self {
+        self.add_state(MovingState::wait());
+        self.add_transition(MovingTransition::new(duration).unwrap());
+        self
+    }
+
+    /// Sit still until `breaker` returns true, or at most `timeout` seconds.
+    ///
+    /// Both the breaker firing and the timeout lead to the next added state.
+    pub fn wait_until<F>(&mut self, breaker: F, timeout: f64) -> &mut Self
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        let trans = MovingTransition::new(timeout)
+            .unwrap()
+            .with_breaker(move || {
+                if breaker() {
+                    BreakerResult::Bool(true)
+                } else {
+                    BreakerResult::Placeholder
+                }
+            });
+        self.add_state(MovingState::wait());
+        self.add_transition(trans);
+        self.pending_keys.push(BreakerResult::Bool(true));
+        self
+    }
+
     /// Concatenate a pre-built chain of states and transitions.
     pub fn concat(
         &mut self,
@@ -151,6 +189,7 @@ impl MovingChainComposer {
         self.states.clear();
         self.transitions.clear();
         self.next_is_state = true;
+        self.pending_keys.clear();
         self
     }
 
@@ -166,6 +205,79 @@ impl Default for MovingChainComposer {
     }
 }
 
+/// Builder for straight-line sequences of states.
+///
+/// Each hop names how long the previous state runs and the state that
+/// follows, so states and transitions can't get out of step:
+///
+/// ```
+/// use mentabotix_rs::{MovingState, StateChain, TurnDirection};
+///
+/// let (states, transitions) = StateChain::start(MovingState::straight(5000))
+///     .then(0.5, MovingState::turn(TurnDirection::Left, 4000))
+///     .then(0.3, MovingState::halt())
+///     .build();
+/// ```
+///
+/// The chain has one start state, every state is reachable and there are no
+/// cycles, so it passes `Botix::ensure_structure_validity`.
+pub struct StateChain {
+    composer: MovingChainComposer,
+}
+
+impl StateChain {
+    /// Start a chain at `state`.
+    pub fn start(state: MovingState) -> Self {
+        let mut composer = MovingChainComposer::new();
+        composer.add_state(state);
+        Self { composer }
+    }
+
+    /// Run the last state for `duration` seconds, then move to `state`.
+    ///
+    /// Panics if `duration` is negative or `state` is already in the chain.
+    pub fn then(self, duration: f64, state: MovingState) -> Self {
+        self.hop(Self::transition(duration), state)
+    }
+
+    /// Run the last state until `breaker` returns anything but
+    /// `Placeholder`, or at most `duration` seconds, then move to `state`
+    /// whatever the result.
+    ///
+    /// Panics if `duration` is negative or `state` is already in the chain.
+    pub fn then_with_breaker<F>(self, duration: f64, state: MovingState, breaker: F) -> Self
+    where
+        F: Fn() -> BreakerResult + Send + Sync + 'static,
+    {
+        self.hop(
+            Self::transition(duration)
+                .with_breaker(breaker)
+                .with_fallback(),
+            state,
+        )
+    }
+
+    fn transition(duration: f64) -> MovingTransition {
+        MovingTransition::new(duration).unwrap_or_else(|e| panic!("{}: {}", e, duration))
+    }
+
+    fn hop(mut self, transition: MovingTransition, state: MovingState) -> Self {
+        // A repeated state would loop the chain back on itself.
+        assert!(
+            !self.composer.states.iter().any(|s| s.id() == state.id()),
+            "State {} is already in the chain",
+            state.id()
+        );
+        self.composer.add_transition(transition).add_state(state);
+        self
+    }
+
+    /// Export the states and transitions, ready for `Botix::build_full`.
+    pub fn build(self) -> (Vec<MovingState>, Vec<MovingTransition>) {
+        self.composer.export()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -217,6 +329,68 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_state_chain_runs_end_to_end() {
+        use crate::Botix;
+        use crate::state::TurnDirection;
+        use bdmc_rs::controller::{CLASSIC_MIS, CloseLoopController};
+        use bdmc_rs::sim::{SimConfig, SimulatedDriver};
+        use std::time::{Duration, Instant};
+
+        let (states, transitions) = StateChain::start(MovingState::straight(5000))
+            .then(0.02, MovingState::turn(TurnDirection::Left, 4000))
+            .then_with_breaker(5.0, MovingState::straight(-3000), || {
+                BreakerResult::Bool(true)
+            })
+            .then(0.02, MovingState::halt())
+            .build();
+        assert_eq!((states.len(), transitions.len()), (4, 3));
+        let ids: Vec<usize> = states.iter().map(|s| s.id()).collect();
+
+        let driver = SimulatedDriver::new(&[1, 2, 3, 4], SimConfig::default());
+        let handle = driver.handle();
+        let mut controller =
+            CloseLoopController::new(Some(CLASSIC_MIS.to_vec()), None, None, None).unwrap();
+        controller.attach_serial(Box::new(driver));
+        let mut botix = Botix::build_full(controller, states, transitions).unwrap();
+        assert_eq!(botix.ensure_structure_validity(), Ok(()));
+
+        let start = Instant::now();
+        let report = botix.run().unwrap();
+        assert_eq!(report.state_ids(), ids);
+        // The breaker cuts the 5 s hop short.
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(handle.motor(1).unwrap().target, 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "already in the chain")]
+    fn test_state_chain_rejects_repeated_state() {
+        let s0 = MovingState::straight(100);
+        let s1 = MovingState::halt();
+        let _ = StateChain::start(s0.clone()).then(0.1, s1).then(0.1, s0);
+    }
+
+    #[test]
+    fn test_wait_until_routes_both_exits() {
+        let mut comp = MovingChainComposer::new();
+        comp.wait_until(|| true, 1.0)
+            .add_state(MovingState::straight(100));
+
+        let (states, transitions) = comp.export();
+        assert!(states[0].is_wait());
+        assert!(transitions[0].has_breaker());
+        let next = states[1].id();
+        assert_eq!(
+            transitions[0].to_states.get(&BreakerResult::Bool(true)),
+            Some(&next)
+        );
+        assert_eq!(
+            transitions[0].to_states.get(&BreakerResult::Placeholder),
+            Some(&next)
+        );
+    }
+
     #[test]
     #[should_panic]
     fn test_wrong_order_panics() {
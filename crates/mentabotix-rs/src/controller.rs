@@ -0,0 +1,122 @@
+use bdmc_rs::controller::CloseLoopController;
+use std::time::Instant;
+
+use crate::state::Context;
+
+/// Motor controller driven by `Botix`.
+///
+/// Implemented for the serial `CloseLoopController`; `MockController` records
+/// the commands instead, for running graphs in tests.
+pub trait MotorController {
+    /// Send one speed per motor.
+    fn set_motors_speed(&mut self, speeds: [i32; 4]) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Bring all motors to a halt. Failures are logged, not returned.
+    fn stop(&mut self);
+
+    /// Context that speed expressions and state controllers are resolved against.
+    fn context(&self) -> &Context;
+}
+
+impl MotorController for CloseLoopController {
+    fn set_motors_speed(&mut self, speeds: [i32; 4]) -> Result<(), Box<dyn std::error::Error>> {
+        let speeds_f64: Vec<f64> = speeds.iter().map(|&s| s as f64).collect();
+        CloseLoopController::set_motors_speed(self, &speeds_f64)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        if let Err(e) = CloseLoopController::set_motors_speed(self, &[0.0; 4]) {
+            log::error!("Failed to stop motors: {}", e);
+        }
+    }
+
+    fn context(&self) -> &Context {
+        CloseLoopController::context(self)
+    }
+}
+
+/// A speed command received by a `MockController`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeedCommand {
+    /// When the command was sent.
+    pub at: Instant,
+    /// Speeds sent, one per motor.
+    pub speeds: [i32; 4],
+}
+
+/// Controller that records every speed command instead of driving motors.
+///
+/// `stop()` records a zero command like any other.
+#[derive(Debug, Default)]
+pub struct MockController {
+    context: Context,
+    commands: Vec<SpeedCommand>,
+}
+
+impl MockController {
+    /// Create a mock with an empty context.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder: resolve speed expressions against `context`.
+    pub fn with_context(mut self, context: Context) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Mutable access to the context, e.g. to change inputs between runs.
+    pub fn context_mut(&mut self) -> &mut Context {
+        &mut self.context
+    }
+
+    /// All commands received, oldest first.
+    pub fn commands(&self) -> &[SpeedCommand] {
+        &self.commands
+    }
+
+    /// The speeds of all commands received, oldest first.
+    pub fn speeds(&self) -> Vec<[i32; 4]> {
+        self.commands.iter().map(|c| c.speeds).collect()
+    }
+
+    /// Forget the commands received so far.
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+}
+
+impl MotorController for MockController {
+    fn set_motors_speed(&mut self, speeds: [i32; 4]) -> Result<(), Box<dyn std::error::Error>> {
+        self.commands.push(SpeedCommand {
+            at: Instant::now(),
+            speeds,
+        });
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        let _ = MotorController::set_motors_speed(self, [0; 4]);
+    }
+
+    fn context(&self) -> &Context {
+        &self.context
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_records_commands_in_order() {
+        let mut mock = MockController::new();
+        mock.set_motors_speed([100, 100, 100, 100]).unwrap();
+        mock.stop();
+        assert_eq!(mock.speeds(), [[100; 4], [0; 4]]);
+        assert!(mock.commands()[0].at <= mock.commands()[1].at);
+        mock.clear();
+        assert!(mock.commands().is_empty());
+    }
+}
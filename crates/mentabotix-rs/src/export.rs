@@ -1,101 +1,58 @@
-use crate::state::{ArrowStyle, lookup_state_label};
+use crate::botix::diagram::{Node, render_plantuml, transition_edges};
+use crate::state::{ArrowStyle, WAIT_LABEL, lookup_state_label};
 use crate::transition::MovingTransition;
-use std::collections::{HashMap, HashSet};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
 /// Export a graph structure as a PlantUML file.
 ///
-/// State labels are retrieved from the global registry (populated by `MovingState::new()`).
-/// Falls back to `State(N)` when a state ID is not registered.
+/// Same diagram as `Botix::export_structure`, for graphs known only by their
+/// transitions, except that state labels are retrieved from the global registry
+/// (populated by `MovingState::new()`). Falls back to `State(N)` when a state
+/// ID is not registered.
 pub fn export_structure(
     save_path: &Path,
     transitions: &[MovingTransition],
     arrow_style: ArrowStyle,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let mut lines: Vec<String> = Vec::new();
-    lines.push("@startuml".to_string());
-
-    let mut all_ids: HashSet<usize> = HashSet::new();
-    let mut indegree: HashMap<usize, usize> = HashMap::new();
-    let mut outdegree: HashMap<usize, usize> = HashMap::new();
-
+    // (indegree, outdegree) per state, sorted by ID for stable output.
+    let mut degrees: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
     for t in transitions {
         for &sid in &t.from_states {
-            all_ids.insert(sid);
-            *outdegree.entry(sid).or_insert(0) += 1;
-            indegree.entry(sid).or_insert(0);
+            degrees.entry(sid).or_default().1 += 1;
         }
         for &sid in t.to_states.values() {
-            all_ids.insert(sid);
-            *indegree.entry(sid).or_insert(0) += 1;
-            outdegree.entry(sid).or_insert(0);
+            degrees.entry(sid).or_default().0 += 1;
         }
     }
 
-    let start_states: HashSet<usize> = indegree
-        .iter()
-        .filter(|(_, deg)| **deg == 0)
-        .map(|(&id, _)| id)
-        .collect();
-    let end_states: HashSet<usize> = outdegree
-        .iter()
-        .filter(|(_, deg)| **deg == 0)
-        .map(|(&id, _)| id)
-        .collect();
-
-    let arrow = arrow_style.as_str();
-
-    // Start markers.
-    for &sid in &start_states {
-        lines.push(format!("[*] {} s{}", arrow, sid));
-    }
-    lines.push(String::new());
-
-    // State declarations — use labels from global registry, fallback to State(N).
-    for &sid in &all_ids {
-        let label = lookup_state_label(sid).unwrap_or_else(|| format!("State({})", sid));
-        lines.push(format!("state \"{}\" as s{}", label, sid));
-    }
-    lines.push(String::new());
-
-    // Transitions.
-    for t in transitions {
-        if t.to_states.len() <= 1 {
-            for &from_id in &t.from_states {
-                if let Some(&to_id) = t.to_states.values().next() {
-                    let label = if t.breaker.is_some() {
-                        format!("{:.3}s [breaker]", t.duration)
-                    } else {
-                        format!("{:.3}s", t.duration)
-                    };
-                    lines.push(format!("s{} {} s{} : {}", from_id, arrow, to_id, label));
-                }
-            }
-        } else {
-            let break_node = format!("choice_t{}", t.id());
-            lines.push(format!("state {} <<choice>>", break_node));
-            for &from_id in &t.from_states {
-                lines.push(format!(
-                    "s{} {} {} : {:.3}s",
-                    from_id, arrow, break_node, t.duration
-                ));
+    let nodes: Vec<Node> = degrees
+        .keys()
+        .map(|&id| {
+            let label = lookup_state_label(id).unwrap_or_else(|| format!("State({})", id));
+            Node {
+                id,
+                wait: label == WAIT_LABEL,
+                label,
             }
-            for (key, &to_id) in &t.to_states {
-                lines.push(format!("{} {} s{} : {:?}", break_node, arrow, to_id, key));
-            }
-        }
-    }
-
-    lines.push(String::new());
-
-    // End markers.
-    for &sid in &end_states {
-        lines.push(format!("s{} {} [*]", sid, arrow));
-    }
-
-    lines.push("@enduml".to_string());
-    fs::write(save_path, lines.join("\n"))?;
+        })
+        .collect();
+    let with_degree = |pick: fn(&(usize, usize)) -> usize| -> Vec<usize> {
+        degrees
+            .iter()
+            .filter(|(_, deg)| pick(deg) == 0)
+            .map(|(&id, _)| id)
+            .collect()
+    };
+    let text = render_plantuml(
+        &nodes,
+        &transition_edges(transitions),
+        &with_degree(|deg| deg.0),
+        &with_degree(|deg| deg.1),
+        arrow_style,
+    );
+    fs::write(save_path, text)?;
     Ok(())
 }
 
@@ -152,10 +109,55 @@ mod tests {
         export_structure(&path, &[t_a_bcd, t_d_ef], ArrowStyle::Down).unwrap();
 
         let content = fs::read_to_string(&path).unwrap();
-        println!("=== Generated ===\n{}\n=== End ===", content);
+        let _ = fs::remove_file(&path);
 
         assert!(content.contains("[*] --> s100"));
-        assert!(content.contains("choice_t"));
+        assert!(content.contains("s100 --> s300 : [2] 1.000s"));
+        assert!(content.contains("s400 --> s600 : [2] 0.500s"));
         assert!(content.contains("s600 --> [*]"));
     }
+
+    #[test]
+    fn test_export_matches_botix_diagram() {
+        use crate::botix::Botix;
+        use crate::controller::MockController;
+        use crate::state::MovingState;
+
+        let s0 = MovingState::straight(100);
+        let s1 = MovingState::wait();
+        let s2 = MovingState::halt();
+        let t0 = MovingTransition::new(0.5)
+            .unwrap()
+            .with_bool_breaker(|| true)
+            .with_from_state(s0.id())
+            .with_to_state(true, s1.id())
+            .with_single_to_state(s2.id());
+        let t1 = MovingTransition::new(1.0)
+            .unwrap()
+            .with_from_state(s1.id())
+            .with_single_to_state(s2.id());
+        let transitions = vec![t0, t1];
+
+        let path = PathBuf::from(format!("test_export_botix_{}.puml", std::process::id()));
+        export_structure(&path, &transitions, ArrowStyle::Right).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        let botix =
+            Botix::build_full(MockController::new(), vec![s0, s1, s2], transitions).unwrap();
+        // Only the state labels differ: registry labels vs `Display`.
+        let without_labels = |text: &str| -> Vec<String> {
+            text.lines()
+                .map(|line| match line.strip_prefix("state ") {
+                    Some(rest) => rest.rsplit(" as ").next().unwrap().to_string(),
+                    None => line.to_string(),
+                })
+                .collect()
+        };
+        assert_eq!(
+            without_labels(&content),
+            without_labels(&botix.to_plantuml_string(ArrowStyle::Right))
+        );
+        assert!(content.contains("state \"wait\" as s"));
+    }
 }
@@ -1,7 +1,9 @@
 use crate::composer::MovingChainComposer;
-use crate::state::MovingState;
-use crate::transition::{BreakerResult, MovingTransition};
+use crate::ramp::RampProfile;
+use crate::state::{MovingState, SpeedPattern, StateController};
+use crate::transition::{BreakerFn, BreakerResult, MovingTransition};
 use rand::Rng;
+use std::sync::{Arc, Mutex, PoisonError};
 
 /// Simple counter-based unique name generator.
 pub struct NameGenerator {
@@ -74,6 +76,35 @@ pub fn weighted_selector<T: Clone + Send + Sync + 'static>(
     }
 }
 
+/// Wrap `breaker` so its result only breaks once it comes back `calls`
+/// times in a row.
+///
+/// Until then the wrapper returns `Placeholder` and the transition keeps
+/// waiting. A `Placeholder` or a different result from `breaker` starts the
+/// count over, so a single bad reading can't pick the branch. The count also
+/// starts over after a result is passed on.
+pub fn debounced_breaker(breaker: BreakerFn, calls: u32) -> BreakerFn {
+    let streak = Mutex::new((BreakerResult::Placeholder, 0u32));
+    Arc::new(move || {
+        let result = breaker();
+        let mut streak = streak.lock().unwrap_or_else(PoisonError::into_inner);
+        if result == BreakerResult::Placeholder {
+            *streak = (BreakerResult::Placeholder, 0);
+            return BreakerResult::Placeholder;
+        }
+        if streak.0 == result {
+            streak.1 += 1;
+        } else {
+            *streak = (result.clone(), 1);
+        }
+        if streak.1 < calls {
+            return BreakerResult::Placeholder;
+        }
+        *streak = (BreakerResult::Placeholder, 0);
+        result
+    })
+}
+
 /// Generate a straight-line acceleration/deceleration chain.
 ///
 /// Creates a sequence of states and transitions that linearly interpolate
@@ -130,6 +161,87 @@ pub fn straight_chain(
     (states, transitions)
 }
 
+/// Generate a straight-line ramp chain shaped by a [`RampProfile`].
+///
+/// Like [`straight_chain`], but the intermediate setpoints come from the
+/// profile. `duration` is only used by [`RampProfile::Linear`]; an S-curve
+/// derives its own duration from its accel/jerk bounds, and falls back to
+/// `Linear` when they are not finite and positive.
+pub fn profiled_chain(
+    start_speed: i32,
+    end_speed: i32,
+    duration: f64,
+    interval: f64,
+    profile: RampProfile,
+) -> (Vec<MovingState>, Vec<MovingTransition>) {
+    let setpoints = profile.setpoints(start_speed, end_speed, duration, interval);
+
+    let mut comp = MovingChainComposer::new();
+    comp.add_state(MovingState::straight(start_speed));
+
+    for pair in setpoints.windows(2) {
+        let (prev_t, _) = pair[0];
+        let (t, speed) = pair[1];
+        comp.add_transition(MovingTransition::new(t - prev_t).unwrap())
+            .add_state(MovingState::straight(speed));
+    }
+
+    comp.export()
+}
+
+/// Gains and limits for [`approach_tag_controller`].
+#[derive(Debug, Clone, Copy)]
+pub struct ApproachGains {
+    /// Differential speed per radian of bearing error.
+    pub steer_kp: f64,
+    /// Forward speed per meter of remaining distance.
+    pub speed_kp: f64,
+    /// Largest forward speed.
+    pub max_speed: i32,
+    /// Largest differential applied to either side.
+    pub max_steer: i32,
+    /// Distance (meters) at which forward motion stops.
+    pub stop_distance: f64,
+}
+
+impl Default for ApproachGains {
+    fn default() -> Self {
+        Self {
+            steer_kp: 800.0,
+            speed_kp: 3000.0,
+            max_speed: 3000,
+            max_steer: 1500,
+            stop_distance: 0.15,
+        }
+    }
+}
+
+/// Build a state controller that steers toward a tag and slows as it closes in.
+///
+/// `measure` returns the tag's bearing in radians (positive when the tag is
+/// to the left) and its distance in meters, or `None` when the tag is not
+/// visible. A lost tag freezes the robot at zero speed until it is seen again.
+pub fn approach_tag_controller<F>(measure: F, gains: ApproachGains) -> StateController
+where
+    F: Fn() -> Option<(f64, f64)> + Send + Sync + 'static,
+{
+    std::sync::Arc::new(move |_ctx, _dt| {
+        let Some((bearing, distance)) = measure() else {
+            return SpeedPattern::Full(0);
+        };
+        let steer = (gains.steer_kp * bearing)
+            .round()
+            .clamp(-gains.max_steer as f64, gains.max_steer as f64) as i32;
+        let forward = (gains.speed_kp * (distance - gains.stop_distance))
+            .round()
+            .clamp(0.0, gains.max_speed as f64) as i32;
+        SpeedPattern::LeftRight {
+            left: forward - steer,
+            right: forward + steer,
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -184,4 +296,63 @@ mod tests {
         assert_eq!(speeds[0], 0);
         assert_eq!(speeds.last(), Some(&100));
     }
+
+    #[test]
+    fn test_profiled_chain_scurve() {
+        let profile = RampProfile::SCurve {
+            max_accel: 2000.0,
+            max_jerk: 10000.0,
+        };
+        let (states, transitions) = profiled_chain(0, 1000, 0.0, 0.05, profile);
+
+        assert_eq!(states.len(), transitions.len() + 1);
+        assert_eq!(states.last().unwrap().speeds()[0], 1000);
+        let total: f64 = transitions.iter().map(|t| t.duration).sum();
+        assert!((total - profile.ramp_duration(1000.0, 0.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_debounced_breaker() {
+        let script = Mutex::new(vec![
+            BreakerResult::from("lost"),
+            BreakerResult::from("lost"),
+            BreakerResult::Placeholder,
+            BreakerResult::from("lost"),
+            BreakerResult::from("seen"),
+            BreakerResult::from("lost"),
+            BreakerResult::from("lost"),
+            BreakerResult::from("lost"),
+            BreakerResult::from("lost"),
+        ]);
+        let breaker = debounced_breaker(Arc::new(move || script.lock().unwrap().remove(0)), 3);
+        let results: Vec<BreakerResult> = (0..9).map(|_| breaker()).collect();
+        let mut expected = vec![BreakerResult::Placeholder; 9];
+        expected[7] = BreakerResult::from("lost");
+        assert_eq!(results, expected);
+
+        // A single call passes every result straight through.
+        let breaker = debounced_breaker(Arc::new(|| BreakerResult::Int(1)), 1);
+        assert_eq!(breaker(), BreakerResult::Int(1));
+        assert_eq!(breaker(), BreakerResult::Int(1));
+    }
+
+    #[test]
+    fn test_approach_tag_controller() {
+        let ctx = crate::state::Context::new();
+        let trace = std::sync::Mutex::new(vec![
+            Some((0.3, 1.0)),
+            Some((-0.1, 0.5)),
+            None,
+            Some((0.0, 0.1)),
+        ]);
+        let controller = approach_tag_controller(
+            move || trace.lock().unwrap().remove(0),
+            ApproachGains::default(),
+        );
+        let speeds: Vec<[i32; 4]> = (0..4).map(|_| controller(&ctx, 0.01).to_array()).collect();
+        assert_eq!(speeds[0], [2310, 2310, 2790, 2790]);
+        assert_eq!(speeds[1], [1130, 1130, 970, 970]);
+        assert_eq!(speeds[2], [0; 4]);
+        assert_eq!(speeds[3], [0; 4]);
+    }
 }
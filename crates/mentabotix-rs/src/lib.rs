@@ -1,22 +1,40 @@
 pub mod botix;
 pub mod composer;
+pub mod controller;
 pub mod export;
 pub mod helpers;
 pub mod menta;
+pub mod ramp;
 pub mod registry;
 pub mod state;
+pub mod testing;
+pub mod timeline;
 pub mod transition;
 
 // Re-exports for convenience.
-pub use botix::Botix;
-pub use composer::MovingChainComposer;
+pub use botix::{
+    BehaviorSpec, Botix, BotixError, DeadlineLevel, ExecutionReport, ExitReason, HookPolicy,
+    HookTimeout, PathAssumptions, ReloadReport, SimulationTrace, SpecWatcher, StateSpec,
+    StateVisit, StructureError, TraceEntry, TransitionSpec, WatchStatus, watch_spec,
+};
+pub use composer::{MovingChainComposer, StateChain};
+pub use controller::{MockController, MotorController, SpeedCommand};
 pub use export::export_structure;
-pub use helpers::{NameGenerator, straight_chain, weighted_selector};
+pub use helpers::{
+    ApproachGains, NameGenerator, approach_tag_controller, debounced_breaker, profiled_chain,
+    straight_chain, weighted_selector,
+};
 pub use menta::{Menta, Sampler, SamplerType, SamplerUsage};
+pub use ramp::RampProfile;
 pub use registry::CaseRegistry;
 pub use state::{
-    ArrowStyle, Context, FixedAxis, MovementConfig, MovingState, PatternType, SpeedExpr,
-    SpeedPattern, TurnDirection, clear_state_labels, lookup_state_label, register_state_label,
-    reset_state_id_counter,
+    ArrowStyle, ClampPolicy, Context, FixedAxis, MovementConfig, MovingState, PatternType,
+    SpeedExpr, SpeedPattern, StateController, TurnDirection, WAIT_LABEL, clear_state_labels,
+    lookup_state_label, movement_config, register_state_label, reset_movement_config,
+    reset_state_id_counter, set_movement_config,
+};
+pub use testing::{
+    Chaos, ChaosConfig, ChaosReport, ChaosTrial, ChaoticController, ChaoticDetections,
+    ChaoticSampler, TagReading, TrialOutcome, run_with_chaos,
 };
-pub use transition::{BreakerResult, MovingTransition};
+pub use transition::{BreakerFn, BreakerResult, MovingTransition};
@@ -0,0 +1,2 @@
+//! Ramp shaping, re-exported from `kazu-core`.
+pub use kazu_core::ramp::*;
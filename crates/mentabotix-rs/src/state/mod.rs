@@ -1,16 +1,24 @@
 use std::collections::HashMap;
 use std::fmt;
-use std::sync::Mutex;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
+
+use kazu_core::scale_speed;
+use serde::{Deserialize, Serialize};
+
+use crate::botix::BotixError;
 
 mod movement;
-pub use movement::{ArrowStyle, FixedAxis, MovementConfig, TurnDirection};
+pub use movement::{ArrowStyle, ClampPolicy, FixedAxis, MovementConfig, TurnDirection};
 
 /// Shared context for runtime evaluation of dynamic speed expressions.
 pub type Context = HashMap<String, serde_json::Value>;
 
 /// Motor speed configuration for different control patterns.
-#[derive(Debug, Clone)]
+///
+/// Concrete patterns serialize with serde; `Dynamic` ones hold closures and
+/// fail to serialize.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SpeedPattern {
     /// All wheels same speed (concrete).
     Full(i32),
@@ -24,6 +32,7 @@ pub enum SpeedPattern {
         rear_right: i32,
     },
     /// Expression-based: evaluated with context at runtime.
+    #[serde(skip)]
     Dynamic {
         pattern_type: PatternType,
         expressions: [SpeedExpr; 4],
@@ -122,8 +131,51 @@ impl SpeedPattern {
     pub fn is_dynamic(&self) -> bool {
         matches!(self, SpeedPattern::Dynamic { .. })
     }
+
+    /// Saturate every speed to `±max_abs`. Dynamic expressions are clamped
+    /// as they are evaluated.
+    pub fn clamped(self, max_abs: i32) -> Self {
+        let max_abs = max_abs.saturating_abs();
+        let clamp = |speed: i32| speed.clamp(-max_abs, max_abs);
+        match self {
+            SpeedPattern::Full(speed) => SpeedPattern::Full(clamp(speed)),
+            SpeedPattern::LeftRight { left, right } => SpeedPattern::LeftRight {
+                left: clamp(left),
+                right: clamp(right),
+            },
+            SpeedPattern::Individual {
+                front_left,
+                rear_left,
+                front_right,
+                rear_right,
+            } => SpeedPattern::Individual {
+                front_left: clamp(front_left),
+                rear_left: clamp(rear_left),
+                front_right: clamp(front_right),
+                rear_right: clamp(rear_right),
+            },
+            SpeedPattern::Dynamic {
+                pattern_type,
+                expressions,
+            } => SpeedPattern::Dynamic {
+                pattern_type,
+                expressions: expressions.map(|expr| match expr {
+                    SpeedExpr::Const(v) => SpeedExpr::Const(clamp(v)),
+                    SpeedExpr::Fn(f) => SpeedExpr::Fn(std::sync::Arc::new(move |ctx: &Context| {
+                        f(ctx).clamp(-max_abs, max_abs)
+                    })),
+                }),
+            },
+        }
+    }
 }
 
+/// Closure that recomputes a state's speeds while the state is active.
+///
+/// Called at the outgoing transition's check interval with the controller
+/// context and the seconds elapsed since the previous call.
+pub type StateController = std::sync::Arc<dyn Fn(&Context, f64) -> SpeedPattern + Send + Sync>;
+
 /// Counter for generating unique state IDs.
 static STATE_ID_COUNTER: AtomicUsize = AtomicUsize::new(0);
 /// Global registry: state ID → human-readable speed label.
@@ -153,6 +205,56 @@ pub fn clear_state_labels() {
     }
 }
 
+/// Movement config consulted by `MovingState::differential` and `::drift`;
+/// `None` until `set_movement_config()` is called.
+static MOVEMENT_CONFIG: RwLock<Option<MovementConfig>> = RwLock::new(None);
+
+/// Set the process-wide movement config used by `MovingState::differential`
+/// and `MovingState::drift`, and whose `max_speed` is the default speed limit
+/// of `Botix` graphs built afterwards. States and graphs already built are
+/// unaffected.
+///
+/// Returns an error, leaving the current config in place, unless
+/// `track_width`, `diagonal_multiplier` and `speed_to_velocity` are positive
+/// and finite and `max_speed` is positive.
+pub fn set_movement_config(config: MovementConfig) -> Result<(), &'static str> {
+    if !(config.track_width.is_finite() && config.track_width > 0.0) {
+        return Err("track_width must be positive and finite");
+    }
+    if !(config.diagonal_multiplier.is_finite() && config.diagonal_multiplier > 0.0) {
+        return Err("diagonal_multiplier must be positive and finite");
+    }
+    if !(config.speed_to_velocity.is_finite() && config.speed_to_velocity > 0.0) {
+        return Err("speed_to_velocity must be positive and finite");
+    }
+    if config.max_speed <= 0 {
+        return Err("max_speed must be positive");
+    }
+    if let Ok(mut guard) = MOVEMENT_CONFIG.write() {
+        *guard = Some(config);
+    }
+    Ok(())
+}
+
+/// The movement config set by `set_movement_config()`, or the default.
+pub fn movement_config() -> MovementConfig {
+    MOVEMENT_CONFIG
+        .read()
+        .ok()
+        .and_then(|guard| guard.clone())
+        .unwrap_or_default()
+}
+
+/// Go back to the default movement config.
+pub fn reset_movement_config() {
+    if let Ok(mut guard) = MOVEMENT_CONFIG.write() {
+        *guard = None;
+    }
+}
+
+/// Label registered for wait states (see [`MovingState::wait`]).
+pub const WAIT_LABEL: &str = "wait";
+
 /// Reset the state ID counter (only used in tests).
 pub fn reset_state_id_counter() {
     STATE_ID_COUNTER.store(0, Ordering::SeqCst);
@@ -171,6 +273,13 @@ pub struct MovingState {
     after_exiting: Vec<std::sync::Arc<dyn Fn() + Send + Sync>>,
     /// Names of context variables used in dynamic speed expressions.
     used_context_vars: Vec<String>,
+    /// Whether this is a wait state (motors held at zero until the transition fires).
+    wait: bool,
+    /// Closed-loop controller re-evaluated while the state is active.
+    controller: Option<StateController>,
+    /// Longest time the state may be active, hooks included, before the run
+    /// is aborted (seconds).
+    max_total_duration: Option<f64>,
 }
 
 impl MovingState {
@@ -223,6 +332,9 @@ impl MovingState {
             before_entering: Vec::new(),
             after_exiting: Vec::new(),
             used_context_vars: Vec::new(),
+            wait: false,
+            controller: None,
+            max_total_duration: None,
         }
     }
 
@@ -231,6 +343,18 @@ impl MovingState {
         Self::new(SpeedPattern::Full(0))
     }
 
+    /// Create a wait state: motors stay at zero until the outgoing
+    /// transition's breaker fires or its duration elapses.
+    ///
+    /// Unlike [`MovingState::halt`], the executor does not re-send zero
+    /// speeds when entering a wait state right after another zero-speed state.
+    pub fn wait() -> Self {
+        let mut state = Self::new(SpeedPattern::Full(0));
+        state.wait = true;
+        register_state_label(state.id, WAIT_LABEL.to_string());
+        state
+    }
+
     /// Create a straight movement state.
     pub fn straight(speed: i32) -> Self {
         Self::new(SpeedPattern::Full(speed))
@@ -238,68 +362,107 @@ impl MovingState {
 
     /// Create a turn state.
     pub fn turn(direction: TurnDirection, speed: i32) -> Self {
-        match direction {
-            TurnDirection::Left => Self::new(SpeedPattern::LeftRight {
-                left: -speed,
-                right: speed,
-            }),
-            TurnDirection::Right => Self::new(SpeedPattern::LeftRight {
-                left: speed,
-                right: -speed,
-            }),
+        let (left, right) = kazu_core::turn_speeds(direction, speed);
+        Self::new(SpeedPattern::LeftRight { left, right })
+    }
+
+    /// Create a differential movement state, using the config set by
+    /// `set_movement_config()`.
+    ///
+    /// `radius` is measured to the inner track; 0 stops the inner track and
+    /// pivots about it. Returns `BotixError::InvalidMovement` for a negative
+    /// or non-finite radius.
+    pub fn differential(
+        direction: TurnDirection,
+        radius: f64,
+        outer_speed: i32,
+    ) -> Result<Self, BotixError> {
+        Self::differential_with(&movement_config(), direction, radius, outer_speed)
+    }
+
+    /// Create a differential movement state for the robot described by `config`.
+    pub fn differential_with(
+        config: &MovementConfig,
+        direction: TurnDirection,
+        radius: f64,
+        outer_speed: i32,
+    ) -> Result<Self, BotixError> {
+        if !(radius.is_finite() && radius >= 0.0) {
+            return Err(BotixError::InvalidMovement(format!(
+                "Radius must be finite and non-negative, got {}",
+                radius
+            )));
         }
-    }
-
-    /// Create a differential movement state.
-    pub fn differential(direction: TurnDirection, radius: f64, outer_speed: i32) -> Self {
-        let config = MovementConfig::default();
-        let inner_speed = (radius / (radius + config.track_width) * outer_speed as f64) as i32;
-
-        match direction {
-            TurnDirection::Left => Self::new(SpeedPattern::LeftRight {
-                left: inner_speed,
-                right: outer_speed,
-            }),
-            TurnDirection::Right => Self::new(SpeedPattern::LeftRight {
-                left: outer_speed,
-                right: inner_speed,
-            }),
+        let (left, right) = config.differential_speeds(direction, radius, outer_speed);
+        Ok(Self::new(SpeedPattern::LeftRight { left, right }))
+    }
+
+    /// Create a differential state sweeping `angle_deg` degrees, using the
+    /// config set by `set_movement_config()`.
+    ///
+    /// Returns the state and the seconds it needs to sweep the angle, for
+    /// the duration of its outgoing transition.
+    pub fn arc(
+        direction: TurnDirection,
+        radius: f64,
+        outer_speed: i32,
+        angle_deg: f64,
+    ) -> Result<(Self, f64), BotixError> {
+        Self::arc_with(
+            &movement_config(),
+            direction,
+            radius,
+            outer_speed,
+            angle_deg,
+        )
+    }
+
+    /// Create an arc state for the robot described by `config`; see
+    /// [`MovingState::arc`].
+    ///
+    /// Returns `BotixError::InvalidMovement` for an invalid radius, a
+    /// negative or non-finite angle, or an `outer_speed` of 0 with a
+    /// non-zero angle.
+    pub fn arc_with(
+        config: &MovementConfig,
+        direction: TurnDirection,
+        radius: f64,
+        outer_speed: i32,
+        angle_deg: f64,
+    ) -> Result<(Self, f64), BotixError> {
+        if !(angle_deg.is_finite() && angle_deg >= 0.0) {
+            return Err(BotixError::InvalidMovement(format!(
+                "Angle must be finite and non-negative, got {}",
+                angle_deg
+            )));
         }
+        let state = Self::differential_with(config, direction, radius, outer_speed)?;
+        if angle_deg == 0.0 {
+            return Ok((state, 0.0));
+        }
+        if outer_speed == 0 {
+            return Err(BotixError::InvalidMovement(
+                "Outer speed of 0 never sweeps the angle".to_string(),
+            ));
+        }
+        Ok((state, config.arc_duration(radius, outer_speed, angle_deg)))
     }
 
-    /// Create a drift state.
+    /// Create a drift state, using the config set by `set_movement_config()`.
     pub fn drift(fixed_axis: FixedAxis, speed: i32) -> Self {
-        let config = MovementConfig::default();
-        let diagonal_speed = (speed as f64 * config.diagonal_multiplier) as i32;
-
-        let pattern = match fixed_axis {
-            FixedAxis::FrontLeft => SpeedPattern::Individual {
-                front_left: 0,
-                rear_left: speed,
-                front_right: diagonal_speed,
-                rear_right: speed,
-            },
-            FixedAxis::RearLeft => SpeedPattern::Individual {
-                front_left: speed,
-                rear_left: 0,
-                front_right: speed,
-                rear_right: diagonal_speed,
-            },
-            FixedAxis::RearRight => SpeedPattern::Individual {
-                front_left: diagonal_speed,
-                rear_left: speed,
-                front_right: 0,
-                rear_right: speed,
-            },
-            FixedAxis::FrontRight => SpeedPattern::Individual {
-                front_left: speed,
-                rear_left: diagonal_speed,
-                front_right: speed,
-                rear_right: 0,
-            },
-        };
+        Self::drift_with(&movement_config(), fixed_axis, speed)
+    }
 
-        Self::new(pattern)
+    /// Create a drift state for the robot described by `config`.
+    pub fn drift_with(config: &MovementConfig, fixed_axis: FixedAxis, speed: i32) -> Self {
+        let [front_left, rear_left, front_right, rear_right] =
+            config.drift_speeds(fixed_axis, speed);
+        Self::new(SpeedPattern::Individual {
+            front_left,
+            rear_left,
+            front_right,
+            rear_right,
+        })
     }
 
     /// Create a state with dynamic speed expressions.
@@ -326,6 +489,9 @@ impl MovingState {
             before_entering: Vec::new(),
             after_exiting: Vec::new(),
             used_context_vars,
+            wait: false,
+            controller: None,
+            max_total_duration: None,
         }
     }
 
@@ -334,6 +500,15 @@ impl MovingState {
         self.id
     }
 
+    /// Human-readable label, the same one registered for export.
+    pub fn label(&self) -> String {
+        if self.wait {
+            WAIT_LABEL.to_string()
+        } else {
+            Self::compute_speed_label(&self.speed_pattern)
+        }
+    }
+
     /// Get the speed pattern.
     pub fn speed_pattern(&self) -> &SpeedPattern {
         &self.speed_pattern
@@ -364,14 +539,19 @@ impl MovingState {
         self.speed_pattern.is_dynamic()
     }
 
+    /// Check if this is a wait state.
+    pub fn is_wait(&self) -> bool {
+        self.wait
+    }
+
     /// Apply a multiplier to the speeds.
     /// Panics if called on a dynamic pattern.
     pub fn with_multiplier(mut self, multiplier: f64) -> Self {
         self.speed_pattern = match self.speed_pattern {
-            SpeedPattern::Full(speed) => SpeedPattern::Full((speed as f64 * multiplier) as i32),
+            SpeedPattern::Full(speed) => SpeedPattern::Full(scale_speed(speed, multiplier)),
             SpeedPattern::LeftRight { left, right } => SpeedPattern::LeftRight {
-                left: (left as f64 * multiplier) as i32,
-                right: (right as f64 * multiplier) as i32,
+                left: scale_speed(left, multiplier),
+                right: scale_speed(right, multiplier),
             },
             SpeedPattern::Individual {
                 front_left,
@@ -379,10 +559,10 @@ impl MovingState {
                 front_right,
                 rear_right,
             } => SpeedPattern::Individual {
-                front_left: (front_left as f64 * multiplier) as i32,
-                rear_left: (rear_left as f64 * multiplier) as i32,
-                front_right: (front_right as f64 * multiplier) as i32,
-                rear_right: (rear_right as f64 * multiplier) as i32,
+                front_left: scale_speed(front_left, multiplier),
+                rear_left: scale_speed(rear_left, multiplier),
+                front_right: scale_speed(front_right, multiplier),
+                rear_right: scale_speed(rear_right, multiplier),
             },
             SpeedPattern::Dynamic { .. } => {
                 panic!("Cannot apply multiplier to a dynamic speed pattern")
@@ -403,6 +583,50 @@ impl MovingState {
         self
     }
 
+    /// Add a hook to be called before entering the state, in place.
+    pub fn add_before_entering<F: Fn() + Send + Sync + 'static>(&mut self, hook: F) -> &mut Self {
+        self.before_entering.push(std::sync::Arc::new(hook));
+        self
+    }
+
+    /// Add a hook to be called after exiting the state, in place.
+    pub fn add_after_exiting<F: Fn() + Send + Sync + 'static>(&mut self, hook: F) -> &mut Self {
+        self.after_exiting.push(std::sync::Arc::new(hook));
+        self
+    }
+
+    /// Attach a controller that recomputes the speeds at every check interval
+    /// until the outgoing transition fires.
+    ///
+    /// The state's own speed pattern is still sent on entry.
+    pub fn with_controller<F>(mut self, controller: F) -> Self
+    where
+        F: Fn(&Context, f64) -> SpeedPattern + Send + Sync + 'static,
+    {
+        self.controller = Some(std::sync::Arc::new(controller));
+        self
+    }
+
+    /// Cap the time the state may be active, entry and exit hooks included,
+    /// independent of its transition's duration.
+    ///
+    /// When the cap is reached before the transition fires, `Botix::run`
+    /// stops the motors and ends with `ExitReason::DeadlineExceeded`.
+    pub fn with_max_total_duration(mut self, secs: f64) -> Self {
+        self.max_total_duration = Some(secs);
+        self
+    }
+
+    /// Get the cap set with [`MovingState::with_max_total_duration`], if any.
+    pub fn max_total_duration(&self) -> Option<f64> {
+        self.max_total_duration
+    }
+
+    /// Get the attached controller, if any.
+    pub fn controller(&self) -> Option<&StateController> {
+        self.controller.as_ref()
+    }
+
     /// Get references to before-entering hooks.
     pub fn before_entering(&self) -> &[std::sync::Arc<dyn Fn() + Send + Sync>] {
         &self.before_entering
@@ -416,6 +640,9 @@ impl MovingState {
 
 impl fmt::Display for MovingState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.wait {
+            return write!(f, "State{}(wait)", self.id);
+        }
         match &self.speed_pattern {
             SpeedPattern::Full(speed) => write!(f, "State{}({})", self.id, speed),
             SpeedPattern::LeftRight { left, right } => {
@@ -506,4 +733,153 @@ mod tests {
         );
         assert!(dyn_state.is_dynamic());
     }
+
+    #[test]
+    fn test_wait_state() {
+        let state = MovingState::wait();
+        assert!(state.is_wait());
+        assert!(!MovingState::halt().is_wait());
+        assert_eq!(state.speeds(), [0, 0, 0, 0]);
+        assert_eq!(state.to_string(), format!("State{}(wait)", state.id()));
+    }
+
+    #[test]
+    fn test_differential_track_width() {
+        let narrow = MovementConfig {
+            track_width: 100.0,
+            ..MovementConfig::default()
+        };
+        let wide = MovementConfig {
+            track_width: 300.0,
+            ..MovementConfig::default()
+        };
+        let left = |config| {
+            MovingState::differential_with(config, TurnDirection::Left, 100.0, 200).unwrap()
+        };
+        assert_eq!(left(&narrow).speeds(), [100, 100, 200, 200]);
+        assert_eq!(left(&wide).speeds(), [50, 50, 200, 200]);
+    }
+
+    #[test]
+    fn test_set_movement_config() {
+        // The config is global: only build states here and put it back after.
+        let previous = movement_config();
+        let invalid = [
+            (0.0, 1.0, 1.0),
+            (-10.0, 1.0, 1.0),
+            (f64::NAN, 1.0, 1.0),
+            (100.0, 0.0, 1.0),
+            (100.0, 1.0, 0.0),
+        ];
+        for (track_width, diagonal_multiplier, speed_to_velocity) in invalid {
+            let config = MovementConfig {
+                track_width,
+                diagonal_multiplier,
+                speed_to_velocity,
+                ..MovementConfig::default()
+            };
+            assert!(set_movement_config(config).is_err());
+        }
+        let config = MovementConfig {
+            max_speed: 0,
+            ..MovementConfig::default()
+        };
+        assert!(set_movement_config(config).is_err());
+        assert_eq!(movement_config().track_width, previous.track_width);
+
+        let config = MovementConfig {
+            track_width: 300.0,
+            diagonal_multiplier: 2.0,
+            ..MovementConfig::default()
+        };
+        set_movement_config(config).unwrap();
+        let arc = MovingState::differential(TurnDirection::Right, 100.0, 200).unwrap();
+        let drift = MovingState::drift(FixedAxis::FrontLeft, 100);
+        reset_movement_config();
+        assert_eq!(movement_config().track_width, 100.0);
+        set_movement_config(previous).unwrap();
+        assert_eq!(arc.speeds(), [200, 200, 50, 50]);
+        assert_eq!(drift.speeds(), [0, 100, 200, 100]);
+    }
+
+    #[test]
+    fn test_differential_zero_and_negative_radius() {
+        let pivot = MovingState::differential(TurnDirection::Left, 0.0, 200).unwrap();
+        assert_eq!(pivot.speeds(), [0, 0, 200, 200]);
+        for radius in [-1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                MovingState::differential(TurnDirection::Left, radius, 200),
+                Err(BotixError::InvalidMovement(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_arc_quarter_turn() {
+        let config = MovementConfig {
+            track_width: 100.0,
+            speed_to_velocity: 0.5,
+            ..MovementConfig::default()
+        };
+        // Outer track at 100 units/s on a 200 unit radius turns at 0.5 rad/s.
+        let (state, duration) =
+            MovingState::arc_with(&config, TurnDirection::Right, 100.0, 200, 90.0).unwrap();
+        assert_eq!(state.speeds(), [200, 200, 100, 100]);
+        assert!((duration - std::f64::consts::PI).abs() < 1e-9);
+
+        // Pivoting doubles the angular rate.
+        let (_, duration) =
+            MovingState::arc_with(&config, TurnDirection::Left, 0.0, 200, 90.0).unwrap();
+        assert!((duration - std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+
+        assert!(MovingState::arc_with(&config, TurnDirection::Left, 100.0, 0, 90.0).is_err());
+        assert!(MovingState::arc_with(&config, TurnDirection::Left, 100.0, 200, -90.0).is_err());
+    }
+
+    #[test]
+    fn test_clamp_policies_mixed_signs() {
+        let speeds = [12000, -6000, 3000, -15000];
+        assert_eq!(
+            ClampPolicy::Saturate.apply(speeds, 10000),
+            [10000, -6000, 3000, -10000]
+        );
+        assert_eq!(
+            ClampPolicy::ScaleProportionally.apply(speeds, 10000),
+            [8000, -4000, 2000, -10000]
+        );
+        for policy in [ClampPolicy::Saturate, ClampPolicy::ScaleProportionally] {
+            assert_eq!(
+                policy.apply([100, -200, 300, -400], 10000),
+                [100, -200, 300, -400]
+            );
+        }
+    }
+
+    #[test]
+    fn test_clamped_pattern() {
+        let pattern = SpeedPattern::Individual {
+            front_left: 12000,
+            rear_left: -6000,
+            front_right: 3000,
+            rear_right: -15000,
+        };
+        assert_eq!(
+            pattern.clamped(10000).to_array(),
+            [10000, -6000, 3000, -10000]
+        );
+
+        let dynamic = SpeedPattern::Dynamic {
+            pattern_type: PatternType::LeftRight,
+            expressions: [
+                SpeedExpr::Const(-20000),
+                SpeedExpr::Const(-20000),
+                SpeedExpr::Fn(std::sync::Arc::new(|_: &Context| 20000)),
+                SpeedExpr::Fn(std::sync::Arc::new(|_: &Context| 20000)),
+            ],
+        };
+        assert_eq!(
+            dynamic.clamped(10000).resolve_speeds(&Context::new()),
+            [-10000, -10000, 10000, 10000]
+        );
+    }
 }
@@ -1,5 +1,7 @@
 use std::fmt;
 
+pub use kazu_core::movement::{FixedAxis, MovementConfig, TurnDirection};
+
 /// Arrow styles for UML generation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ArrowStyle {
@@ -39,36 +41,32 @@ impl fmt::Display for ArrowStyle {
     }
 }
 
-/// Configuration for movement calculations.
-#[derive(Debug, Clone)]
-pub struct MovementConfig {
-    /// The width of the track (distance between wheels with same axis).
-    pub track_width: f64,
-    /// The multiplier for diagonal speeds (designed for drift movement).
-    pub diagonal_multiplier: f64,
+/// How speeds beyond the driver's limit are brought back into range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClampPolicy {
+    /// Cut each wheel to the limit on its own.
+    #[default]
+    Saturate,
+    /// Scale all four wheels by the same factor, keeping their ratios and
+    /// so the turning radius.
+    ScaleProportionally,
 }
 
-impl Default for MovementConfig {
-    fn default() -> Self {
-        Self {
-            track_width: 100.0,
-            diagonal_multiplier: 1.53,
+impl ClampPolicy {
+    /// Bring `speeds` within `±max_abs`. Speeds already in range are
+    /// returned unchanged.
+    pub fn apply(self, speeds: [i32; 4], max_abs: i32) -> [i32; 4] {
+        let max_abs = max_abs.saturating_abs();
+        match self {
+            ClampPolicy::Saturate => speeds.map(|s| s.clamp(-max_abs, max_abs)),
+            ClampPolicy::ScaleProportionally => {
+                let peak = speeds.iter().map(|s| s.unsigned_abs()).max().unwrap_or(0);
+                if peak <= max_abs as u32 {
+                    return speeds;
+                }
+                let factor = max_abs as f64 / peak as f64;
+                speeds.map(|s| (s as f64 * factor) as i32)
+            }
         }
     }
 }
-
-/// Turn direction.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum TurnDirection {
-    Left,
-    Right,
-}
-
-/// Fixed axis for drift movement.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum FixedAxis {
-    FrontLeft,
-    RearLeft,
-    RearRight,
-    FrontRight,
-}
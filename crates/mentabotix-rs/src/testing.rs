@@ -0,0 +1,524 @@
+//! Seeded disturbances for running behavior graphs against an unreliable
+//! world.
+//!
+//! A [`ChaosConfig`] names how often samplers, tag detections and the motor
+//! transport misbehave. [`run_with_chaos`] runs a graph once per trial, each
+//! trial seeded from the config, and collects where the runs ended in a
+//! [`ChaosReport`]. Every wrapper draws from its own stream, one draw per
+//! call, so a seed reproduces the same disturbances however the calls are
+//! timed.
+
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::botix::{Botix, DeadlineLevel};
+use crate::controller::MotorController;
+use crate::menta::{Sampler, SamplerType};
+use crate::state::Context;
+
+/// How often each disturbance strikes, and how hard.
+///
+/// Probabilities are per call and clamped to `0.0..=1.0`. The default
+/// disturbs nothing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChaosConfig {
+    /// Seed of the first trial; trial `n` is seeded with `seed + n`.
+    pub seed: u64,
+    /// Standard deviation of the Gaussian noise added to each sampled value.
+    pub sampler_noise: f64,
+    /// Chance that a sample is lost and reads all zeros.
+    pub sampler_dropout: f64,
+    /// Chance that a tag in view isn't detected.
+    pub missed_detection: f64,
+    /// Chance that a detected tag's decision margin dips.
+    pub margin_dip: f64,
+    /// Fraction of the decision margin a dip takes away.
+    pub margin_dip_depth: f64,
+    /// Chance that a speed command fails to send.
+    pub write_failure: f64,
+    /// Chance that a speed command is held up before it's sent.
+    pub latency_spike: f64,
+    /// How long a latency spike holds a command up.
+    pub latency_spike_duration: Duration,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            sampler_noise: 0.0,
+            sampler_dropout: 0.0,
+            missed_detection: 0.0,
+            margin_dip: 0.0,
+            margin_dip_depth: 0.5,
+            write_failure: 0.0,
+            latency_spike: 0.0,
+            latency_spike_duration: Duration::from_millis(50),
+        }
+    }
+}
+
+/// Disturbances of one trial, handed out as wrappers.
+///
+/// Each wrapper gets the next stream of the trial's seed, so a graph that
+/// creates its wrappers in the same order sees the same disturbances.
+#[derive(Debug)]
+pub struct Chaos {
+    config: ChaosConfig,
+    seed: u64,
+    streams: u64,
+}
+
+impl Chaos {
+    /// Disturbances drawn from `seed`, at the rates of `config`.
+    pub fn new(config: &ChaosConfig, seed: u64) -> Self {
+        Self {
+            config: config.clone(),
+            seed,
+            streams: 0,
+        }
+    }
+
+    /// The seed the wrappers draw from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Add noise and dropouts to a sampler.
+    pub fn sampler(&mut self, inner: Box<dyn Sampler>) -> ChaoticSampler {
+        ChaoticSampler {
+            inner,
+            noise: self.config.sampler_noise,
+            dropout: self.config.sampler_dropout,
+            rng: Mutex::new(self.next_stream()),
+        }
+    }
+
+    /// Miss detections and dip margins of a stream of tag readings.
+    ///
+    /// `source` is called once per frame, whether or not the frame is missed.
+    pub fn detections<F>(&mut self, source: F) -> ChaoticDetections<F>
+    where
+        F: FnMut() -> Option<TagReading> + Send,
+    {
+        ChaoticDetections {
+            missed: self.config.missed_detection,
+            dip: self.config.margin_dip,
+            dip_depth: self.config.margin_dip_depth,
+            stream: Mutex::new((source, self.next_stream())),
+        }
+    }
+
+    /// Fail and delay speed commands sent to a controller.
+    pub fn controller<C: MotorController>(&mut self, inner: C) -> ChaoticController<C> {
+        ChaoticController {
+            inner,
+            failure: self.config.write_failure,
+            spike: self.config.latency_spike,
+            spike_duration: self.config.latency_spike_duration,
+            rng: self.next_stream(),
+        }
+    }
+
+    fn next_stream(&mut self) -> StdRng {
+        self.streams += 1;
+        StdRng::seed_from_u64(self.seed ^ self.streams.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+    }
+}
+
+/// Whether a disturbance with chance `p` strikes; always draws once.
+fn strikes(rng: &mut StdRng, p: f64) -> bool {
+    rng.r#gen::<f64>() < p
+}
+
+/// Gaussian noise by the Box-Muller transform.
+fn gaussian(rng: &mut StdRng, std_dev: f64) -> f64 {
+    // 1 - u lies in (0, 1], keeping the logarithm finite.
+    let u1 = 1.0 - rng.r#gen::<f64>();
+    let u2: f64 = rng.r#gen();
+    std_dev * (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+}
+
+/// Sampler with seeded noise and dropouts, see [`Chaos::sampler`].
+pub struct ChaoticSampler {
+    inner: Box<dyn Sampler>,
+    noise: f64,
+    dropout: f64,
+    rng: Mutex<StdRng>,
+}
+
+impl Sampler for ChaoticSampler {
+    fn sample(&self) -> Vec<f64> {
+        let mut data = self.inner.sample();
+        let mut rng = self.rng.lock().unwrap_or_else(PoisonError::into_inner);
+        if strikes(&mut rng, self.dropout) {
+            data.fill(0.0);
+            return data;
+        }
+        if self.noise > 0.0 {
+            for value in &mut data {
+                *value += gaussian(&mut rng, self.noise);
+            }
+        }
+        data
+    }
+
+    fn sampler_type(&self) -> SamplerType {
+        self.inner.sampler_type()
+    }
+}
+
+/// A tag seen in one frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TagReading {
+    /// Tag ID.
+    pub id: i32,
+    /// Decision margin of the detection.
+    pub margin: f64,
+}
+
+/// Tag readings with seeded misses and margin dips, see [`Chaos::detections`].
+///
+/// Shareable between breakers; frames are handed out in call order.
+pub struct ChaoticDetections<F> {
+    missed: f64,
+    dip: f64,
+    dip_depth: f64,
+    stream: Mutex<(F, StdRng)>,
+}
+
+impl<F: FnMut() -> Option<TagReading> + Send> ChaoticDetections<F> {
+    /// Reading of the next frame, or `None` if no tag was detected in it.
+    pub fn next_reading(&self) -> Option<TagReading> {
+        let mut stream = self.stream.lock().unwrap_or_else(PoisonError::into_inner);
+        let (source, rng) = &mut *stream;
+        let reading = source();
+        let missed = strikes(rng, self.missed);
+        let dipped = strikes(rng, self.dip);
+        let mut reading = reading.filter(|_| !missed)?;
+        if dipped {
+            reading.margin *= 1.0 - self.dip_depth;
+        }
+        Some(reading)
+    }
+}
+
+/// Controller with seeded write failures and latency spikes, see
+/// [`Chaos::controller`].
+///
+/// `stop()` is passed through untouched so an aborted run still halts.
+pub struct ChaoticController<C> {
+    inner: C,
+    failure: f64,
+    spike: f64,
+    spike_duration: Duration,
+    rng: StdRng,
+}
+
+impl<C> ChaoticController<C> {
+    /// The wrapped controller.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<C: MotorController> MotorController for ChaoticController<C> {
+    fn set_motors_speed(&mut self, speeds: [i32; 4]) -> Result<(), Box<dyn std::error::Error>> {
+        let failed = strikes(&mut self.rng, self.failure);
+        if strikes(&mut self.rng, self.spike) {
+            std::thread::sleep(self.spike_duration);
+        }
+        if failed {
+            return Err("injected write failure".into());
+        }
+        self.inner.set_motors_speed(speeds)
+    }
+
+    fn stop(&mut self) {
+        self.inner.stop();
+    }
+
+    fn context(&self) -> &Context {
+        self.inner.context()
+    }
+}
+
+/// Where one trial of [`run_with_chaos`] ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrialOutcome {
+    /// The run ended in the goal state.
+    Reached,
+    /// The run ended in another end state.
+    EndedIn(usize),
+    /// A deadline aborted the run.
+    DeadlineExceeded(DeadlineLevel),
+    /// The run failed, such as on a write failure.
+    Failed(String),
+}
+
+/// One trial of [`run_with_chaos`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChaosTrial {
+    /// Seed the trial's disturbances were drawn from.
+    pub seed: u64,
+    /// Where the run ended.
+    pub outcome: TrialOutcome,
+}
+
+/// Outcomes of [`run_with_chaos`], one per trial in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChaosReport {
+    /// The trials run.
+    pub trials: Vec<ChaosTrial>,
+}
+
+impl ChaosReport {
+    /// Number of trials that reached the goal state.
+    pub fn reached(&self) -> usize {
+        self.trials
+            .iter()
+            .filter(|t| t.outcome == TrialOutcome::Reached)
+            .count()
+    }
+
+    /// Fraction of trials that reached the goal state; 0 without trials.
+    pub fn success_rate(&self) -> f64 {
+        if self.trials.is_empty() {
+            return 0.0;
+        }
+        self.reached() as f64 / self.trials.len() as f64
+    }
+
+    /// Seeds of the trials that missed the goal, to replay them one by one.
+    pub fn failing_seeds(&self) -> Vec<u64> {
+        self.trials
+            .iter()
+            .filter(|t| t.outcome != TrialOutcome::Reached)
+            .map(|t| t.seed)
+            .collect()
+    }
+}
+
+/// Run a graph `trials` times under the disturbances of `chaos`.
+///
+/// `pool` builds a fresh graph for each trial from that trial's [`Chaos`],
+/// wrapping whatever it should disturb, and returns it with the ID of the
+/// end state the run should reach. Trial `n` is seeded with
+/// `chaos.seed + n`, so a failing trial replays alone with
+/// `ChaosConfig { seed, .. }` and `trials` of 1.
+pub fn run_with_chaos<C, F>(mut pool: F, chaos: &ChaosConfig, trials: usize) -> ChaosReport
+where
+    C: MotorController,
+    F: FnMut(&mut Chaos) -> (Botix<C>, usize),
+{
+    let trials = (0..trials as u64)
+        .map(|trial| {
+            let seed = chaos.seed.wrapping_add(trial);
+            let (mut botix, goal) = pool(&mut Chaos::new(chaos, seed));
+            let outcome = match botix.run() {
+                Ok(report) => match report.deadline_exceeded() {
+                    Some(level) => TrialOutcome::DeadlineExceeded(level),
+                    None => match report.visits.last().map(|v| v.state_id) {
+                        Some(end) if end == goal => TrialOutcome::Reached,
+                        Some(end) => TrialOutcome::EndedIn(end),
+                        None => TrialOutcome::Failed("no state visited".to_string()),
+                    },
+                },
+                Err(e) => TrialOutcome::Failed(e.to_string()),
+            };
+            log::debug!("Chaos trial with seed {}: {:?}", seed, outcome);
+            ChaosTrial { seed, outcome }
+        })
+        .collect();
+    ChaosReport { trials }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::MockController;
+    use crate::helpers::debounced_breaker;
+    use crate::state::MovingState;
+    use crate::transition::{BreakerFn, BreakerResult, MovingTransition};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    struct ConstSampler;
+
+    impl Sampler for ConstSampler {
+        fn sample(&self) -> Vec<f64> {
+            vec![1.0, 2.0, 3.0]
+        }
+        fn sampler_type(&self) -> SamplerType {
+            SamplerType::Sequence
+        }
+    }
+
+    fn noisy() -> ChaosConfig {
+        ChaosConfig {
+            seed: 11,
+            sampler_noise: 0.5,
+            sampler_dropout: 0.2,
+            missed_detection: 0.3,
+            margin_dip: 0.3,
+            ..ChaosConfig::default()
+        }
+    }
+
+    fn draw(chaos: &mut Chaos) -> (Vec<Vec<f64>>, Vec<Option<TagReading>>) {
+        let sampler = chaos.sampler(Box::new(ConstSampler));
+        let detections = chaos.detections(|| {
+            Some(TagReading {
+                id: 3,
+                margin: 80.0,
+            })
+        });
+        let samples = (0..20).map(|_| sampler.sample()).collect();
+        let readings = (0..20).map(|_| detections.next_reading()).collect();
+        (samples, readings)
+    }
+
+    #[test]
+    fn test_seed_reproduces_disturbances() {
+        let config = noisy();
+        let (samples, readings) = draw(&mut Chaos::new(&config, 11));
+        assert_eq!(
+            draw(&mut Chaos::new(&config, 11)),
+            (samples.clone(), readings.clone())
+        );
+        assert_ne!(draw(&mut Chaos::new(&config, 12)).0, samples);
+
+        assert!(samples.contains(&vec![0.0; 3]));
+        assert!(samples.iter().all(|s| s.len() == 3));
+        assert!(readings.contains(&None));
+        assert!(readings.contains(&Some(TagReading {
+            id: 3,
+            margin: 40.0
+        })));
+
+        // The default config leaves everything alone.
+        let (samples, readings) = draw(&mut Chaos::new(&ChaosConfig::default(), 11));
+        assert!(samples.iter().all(|s| *s == [1.0, 2.0, 3.0]));
+        assert!(readings.iter().all(|r| r.is_some_and(|r| r.margin == 80.0)));
+    }
+
+    #[test]
+    fn test_chaotic_controller() {
+        let config = ChaosConfig {
+            write_failure: 1.0,
+            ..ChaosConfig::default()
+        };
+        let mut controller = Chaos::new(&config, 0).controller(MockController::new());
+        assert!(controller.set_motors_speed([100; 4]).is_err());
+        controller.stop();
+        assert_eq!(controller.inner().speeds(), [[0; 4]]);
+
+        let config = ChaosConfig {
+            latency_spike: 1.0,
+            latency_spike_duration: Duration::from_millis(20),
+            ..ChaosConfig::default()
+        };
+        let mut controller = Chaos::new(&config, 0).controller(MockController::new());
+        let start = std::time::Instant::now();
+        controller.set_motors_speed([100; 4]).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert_eq!(controller.inner().speeds(), [[100; 4]]);
+    }
+
+    /// Drive toward a tag until it has been seen for 30 frames, giving up
+    /// as soon as the breaker reports it lost.
+    ///
+    /// Returns the graph with its "arrived" end state as the goal.
+    fn approach(
+        chaos: &mut Chaos,
+        debounce: Option<u32>,
+    ) -> (Botix<ChaoticController<MockController>>, usize) {
+        let detections = chaos.detections(|| {
+            Some(TagReading {
+                id: 3,
+                margin: 80.0,
+            })
+        });
+        let frames = AtomicU32::new(0);
+        let breaker: BreakerFn = Arc::new(move || {
+            let frame = frames.fetch_add(1, Ordering::Relaxed) + 1;
+            match detections.next_reading() {
+                None => BreakerResult::from("lost"),
+                Some(reading) if reading.margin < 50.0 => BreakerResult::Placeholder,
+                Some(_) if frame >= 30 => BreakerResult::from("arrived"),
+                Some(_) => BreakerResult::Placeholder,
+            }
+        });
+        let breaker = match debounce {
+            Some(calls) => debounced_breaker(breaker, calls),
+            None => breaker,
+        };
+
+        let approach = MovingState::straight(1000);
+        let arrived = MovingState::halt();
+        let lost = MovingState::halt();
+        let timed_out = MovingState::halt();
+        let goal = arrived.id();
+        let transition = MovingTransition::new(10.0)
+            .unwrap()
+            .with_arc_breaker(breaker)
+            .with_check_interval(0.001)
+            .with_from_state(approach.id())
+            .with_to_state("arrived", arrived.id())
+            .with_to_state("lost", lost.id())
+            .with_single_to_state(timed_out.id());
+        let controller = chaos.controller(MockController::new());
+        let botix = Botix::build_full(
+            controller,
+            vec![approach, arrived, lost, timed_out],
+            vec![transition],
+        )
+        .unwrap();
+        (botix, goal)
+    }
+
+    #[test]
+    fn test_debouncer_rides_out_missed_detections() {
+        let chaos = ChaosConfig {
+            seed: 5,
+            missed_detection: 0.2,
+            margin_dip: 0.1,
+            latency_spike: 0.5,
+            latency_spike_duration: Duration::from_millis(2),
+            ..ChaosConfig::default()
+        };
+
+        // Seed 5 misses a frame before the 30th but never three in a row.
+        let raw = run_with_chaos(|c| approach(c, None), &chaos, 1);
+        assert!(matches!(raw.trials[0].outcome, TrialOutcome::EndedIn(_)));
+        assert_eq!(raw.failing_seeds(), [5]);
+        let debounced = run_with_chaos(|c| approach(c, Some(3)), &chaos, 1);
+        assert_eq!(debounced.trials[0].outcome, TrialOutcome::Reached);
+        assert_eq!(debounced.success_rate(), 1.0);
+
+        let raw = run_with_chaos(|c| approach(c, None), &chaos, 8);
+        let debounced = run_with_chaos(|c| approach(c, Some(3)), &chaos, 8);
+        assert_eq!(debounced.trials.len(), 8);
+        assert!(debounced.reached() > raw.reached());
+    }
+
+    #[test]
+    fn test_write_failures_fail_the_trial() {
+        let chaos = ChaosConfig {
+            write_failure: 1.0,
+            ..ChaosConfig::default()
+        };
+        let report = run_with_chaos(|c| approach(c, None), &chaos, 2);
+        assert_eq!(report.reached(), 0);
+        assert_eq!(report.failing_seeds(), [0, 1]);
+        assert!(
+            report
+                .trials
+                .iter()
+                .all(|t| matches!(t.outcome, TrialOutcome::Failed(_)))
+        );
+    }
+}
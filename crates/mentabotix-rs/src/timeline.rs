@@ -0,0 +1,600 @@
+//! Merged timelines of a run's logs.
+//!
+//! Each log becomes a [`TimelineSource`] with its own clock: an
+//! `ExecutionReport` counts from the start of the run, a detection JSONL log
+//! from the Unix epoch, and a controller's command history from any
+//! `Instant`. [`merge`] moves every source onto the clock of the first one,
+//! either by a declared offset or by lining up an event both sources share,
+//! and returns one sorted [`Timeline`] for export as JSONL or HTML.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io::BufRead;
+use std::time::Instant;
+
+use serde_json::{Value, json};
+
+use crate::botix::ExecutionReport;
+
+/// Row of the HTML timeline an event is drawn in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Lane {
+    /// States of an execution report, drawn as bars.
+    State,
+    /// Published tag IDs of a detection log.
+    Tag,
+    /// Speeds sent to the motors.
+    Speed,
+    /// Anything added with `TimelineSource::with_event`.
+    Event,
+}
+
+impl Lane {
+    /// Short name used in JSONL and HTML.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Lane::State => "state",
+            Lane::Tag => "tag",
+            Lane::Speed => "speed",
+            Lane::Event => "event",
+        }
+    }
+}
+
+impl fmt::Display for Lane {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// One event of a source, timed by the source's own clock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineEvent {
+    /// Seconds on the source's clock.
+    pub time: f64,
+    pub lane: Lane,
+    pub label: String,
+    /// How long the event lasted in seconds, for states.
+    pub duration: Option<f64>,
+    /// Details exported alongside the label.
+    pub value: Value,
+}
+
+/// How a source's clock maps onto the timeline's.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClockAlign {
+    /// Seconds added to every event of the source.
+    Offset(f64),
+    /// Shift the source so its first event with this label lands on the first
+    /// event with the same label in an already aligned source.
+    SyncEvent(String),
+}
+
+/// A log to merge, with its events and clock alignment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineSource {
+    /// Name exported with each of the source's entries.
+    pub name: String,
+    pub events: Vec<TimelineEvent>,
+    pub align: ClockAlign,
+}
+
+/// Errors raised while reading or merging timeline sources.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimelineError {
+    /// A line of a detection log that isn't a detection record.
+    Parse {
+        source: String,
+        line: usize,
+        message: String,
+    },
+    /// No aligned source has the event a `ClockAlign::SyncEvent` source is
+    /// keyed on, or the source itself lacks it.
+    SyncEventMissing { source: String, label: String },
+}
+
+impl fmt::Display for TimelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimelineError::Parse {
+                source,
+                line,
+                message,
+            } => write!(f, "{} line {}: {}", source, line, message),
+            TimelineError::SyncEventMissing { source, label } => write!(
+                f,
+                "Cannot align {}: sync event {:?} not found in it and in an aligned source",
+                source, label
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TimelineError {}
+
+impl TimelineSource {
+    /// Create a source from events on its own clock, with no offset.
+    pub fn new(name: &str, events: Vec<TimelineEvent>) -> Self {
+        TimelineSource {
+            name: name.to_string(),
+            events,
+            align: ClockAlign::Offset(0.0),
+        }
+    }
+
+    /// One state event per visit of a run, on a clock starting with the run.
+    pub fn from_report(name: &str, report: &ExecutionReport) -> Self {
+        let events = report
+            .visits
+            .iter()
+            .map(|v| TimelineEvent {
+                time: v.entered.as_secs_f64(),
+                lane: Lane::State,
+                label: if v.wait {
+                    format!("s{} wait", v.state_id)
+                } else {
+                    format!("s{}", v.state_id)
+                },
+                duration: Some(v.duration().as_secs_f64()),
+                value: json!({
+                    "state_id": v.state_id,
+                    "transition": v.transition,
+                    "branch": v.branch,
+                    "exit": v.exit.map(|e| e.as_str()),
+                }),
+            })
+            .collect();
+        TimelineSource::new(name, events)
+    }
+
+    /// Read a detection log written in `LogFormat::Jsonl`, on the Unix clock.
+    ///
+    /// Only records where the published tag ID changes become events.
+    ///
+    /// # Errors
+    ///
+    /// Returns `TimelineError::Parse` for a line that can't be read or lacks a
+    /// numeric `timestamp` or `tag_id`.
+    pub fn from_detection_log(name: &str, log: impl BufRead) -> Result<Self, TimelineError> {
+        let parse_error = |line: usize, message: String| TimelineError::Parse {
+            source: name.to_string(),
+            line,
+            message,
+        };
+        let mut events = Vec::new();
+        let mut last_tag = None;
+        for (i, line) in log.lines().enumerate() {
+            let line = line.map_err(|e| parse_error(i + 1, e.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: Value =
+                serde_json::from_str(&line).map_err(|e| parse_error(i + 1, e.to_string()))?;
+            let (Some(time), Some(tag_id)) =
+                (record["timestamp"].as_f64(), record["tag_id"].as_i64())
+            else {
+                return Err(parse_error(
+                    i + 1,
+                    "expected numeric timestamp and tag_id".to_string(),
+                ));
+            };
+            if last_tag == Some(tag_id) {
+                continue;
+            }
+            last_tag = Some(tag_id);
+            events.push(TimelineEvent {
+                time,
+                lane: Lane::Tag,
+                label: format!("tag {}", tag_id),
+                duration: None,
+                value: json!({
+                    "tag_id": tag_id,
+                    "frame": record["frame"],
+                    "decision_margin": record["decision_margin"],
+                }),
+            });
+        }
+        Ok(TimelineSource::new(name, events))
+    }
+
+    /// One speed event per accepted command, on a clock starting at `origin`.
+    ///
+    /// Takes the entries of `CloseLoopController::history`; pass the report's
+    /// `started` as `origin` to share the run's clock.
+    pub fn from_commands(name: &str, origin: Instant, history: &[(Instant, Vec<i32>)]) -> Self {
+        let events = history
+            .iter()
+            .map(|(at, speeds)| TimelineEvent {
+                time: at.saturating_duration_since(origin).as_secs_f64(),
+                lane: Lane::Speed,
+                label: format!("{:?}", speeds),
+                duration: None,
+                value: json!({ "speeds": speeds }),
+            })
+            .collect();
+        TimelineSource::new(name, events)
+    }
+
+    /// Add a user event at `time` seconds on the source's clock.
+    pub fn with_event(mut self, time: f64, label: &str) -> Self {
+        self.events.push(TimelineEvent {
+            time,
+            lane: Lane::Event,
+            label: label.to_string(),
+            duration: None,
+            value: Value::Null,
+        });
+        self
+    }
+
+    /// Add `seconds` to every event of the source.
+    pub fn with_offset(mut self, seconds: f64) -> Self {
+        self.align = ClockAlign::Offset(seconds);
+        self
+    }
+
+    /// Align the source on its first event labelled `label`; see
+    /// `ClockAlign::SyncEvent`.
+    pub fn aligned_on(mut self, label: &str) -> Self {
+        self.align = ClockAlign::SyncEvent(label.to_string());
+        self
+    }
+
+    fn first_time_of(&self, label: &str) -> Option<f64> {
+        self.events
+            .iter()
+            .filter(|e| e.label == label)
+            .map(|e| e.time)
+            .min_by(f64::total_cmp)
+    }
+}
+
+/// An event on the merged clock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineEntry {
+    /// Seconds since the first entry of the timeline.
+    pub time: f64,
+    /// Name of the source the event came from.
+    pub source: String,
+    pub lane: Lane,
+    pub label: String,
+    pub duration: Option<f64>,
+    pub value: Value,
+}
+
+/// Events of several sources on one clock, in time order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Timeline {
+    /// Time of the first entry on the clock of the first source, after its offset.
+    pub origin: f64,
+    pub entries: Vec<TimelineEntry>,
+}
+
+/// Merge sources onto one clock, that of the first source.
+///
+/// Sources with `ClockAlign::SyncEvent` are aligned against any source
+/// aligned before them, in whatever order makes that possible. Entries at the
+/// same time keep the order of their sources.
+///
+/// # Errors
+///
+/// Returns `TimelineError::SyncEventMissing` if a source's sync event can't be
+/// found.
+pub fn merge(sources: Vec<TimelineSource>) -> Result<Timeline, TimelineError> {
+    let mut offsets: Vec<Option<f64>> = sources
+        .iter()
+        .map(|s| match s.align {
+            ClockAlign::Offset(offset) => Some(offset),
+            ClockAlign::SyncEvent(_) => None,
+        })
+        .collect();
+
+    // Each pass aligns the sources whose sync event shows up in an aligned one
+    loop {
+        let mut progress = false;
+        for i in 0..sources.len() {
+            let ClockAlign::SyncEvent(label) = &sources[i].align else {
+                continue;
+            };
+            if offsets[i].is_some() {
+                continue;
+            }
+            let Some(own) = sources[i].first_time_of(label) else {
+                continue;
+            };
+            let anchor = sources
+                .iter()
+                .zip(&offsets)
+                .find_map(|(other, offset)| Some(other.first_time_of(label)? + (*offset)?));
+            if let Some(anchor) = anchor {
+                offsets[i] = Some(anchor - own);
+                progress = true;
+            }
+        }
+        if !progress {
+            break;
+        }
+    }
+
+    let mut entries = Vec::new();
+    for (source, offset) in sources.into_iter().zip(offsets) {
+        let Some(offset) = offset else {
+            let ClockAlign::SyncEvent(label) = source.align else {
+                unreachable!("offset sources are aligned from the start");
+            };
+            return Err(TimelineError::SyncEventMissing {
+                source: source.name,
+                label,
+            });
+        };
+        entries.extend(source.events.into_iter().map(|e| TimelineEntry {
+            time: e.time + offset,
+            source: source.name.clone(),
+            lane: e.lane,
+            label: e.label,
+            duration: e.duration,
+            value: e.value,
+        }));
+    }
+
+    entries.sort_by(|a, b| a.time.total_cmp(&b.time));
+    let origin = entries.first().map_or(0.0, |e| e.time);
+    for entry in &mut entries {
+        entry.time = round_micros(entry.time - origin);
+        entry.duration = entry.duration.map(round_micros);
+    }
+    Ok(Timeline { origin, entries })
+}
+
+/// Round seconds to microseconds, so exports don't carry float noise.
+fn round_micros(seconds: f64) -> f64 {
+    (seconds * 1e6).round() / 1e6
+}
+
+/// Width of the HTML plot area in pixels.
+const HTML_WIDTH: f64 = 960.0;
+/// Height of one HTML lane in pixels.
+const HTML_LANE_HEIGHT: f64 = 48.0;
+/// Width of the lane names left of the plot area in pixels.
+const HTML_GUTTER: f64 = 140.0;
+
+impl Timeline {
+    /// Time of the last entry, including its duration.
+    pub fn span(&self) -> f64 {
+        self.entries
+            .iter()
+            .map(|e| e.time + e.duration.unwrap_or(0.0))
+            .fold(0.0, f64::max)
+    }
+
+    /// The timeline as one JSON object per line.
+    pub fn to_jsonl(&self) -> String {
+        self.entries
+            .iter()
+            .map(|e| {
+                json!({
+                    "time": e.time,
+                    "source": e.source,
+                    "lane": e.lane.as_str(),
+                    "label": e.label,
+                    "duration": e.duration,
+                    "value": e.value,
+                })
+                .to_string()
+                    + "\n"
+            })
+            .collect()
+    }
+
+    /// The timeline as a self-contained HTML page with one lane per source
+    /// and event kind; hover an event for its details.
+    pub fn to_html(&self) -> String {
+        let lanes: BTreeSet<(Lane, &str)> = self
+            .entries
+            .iter()
+            .map(|e| (e.lane, e.source.as_str()))
+            .collect();
+        let lanes: Vec<(Lane, &str)> = lanes.into_iter().collect();
+        let scale = HTML_WIDTH / self.span().max(1e-3);
+        let height = HTML_LANE_HEIGHT * lanes.len() as f64 + 24.0;
+
+        let mut svg = Vec::new();
+        for (row, (lane, source)) in lanes.iter().enumerate() {
+            let y = HTML_LANE_HEIGHT * row as f64;
+            svg.push(format!(
+                "<text class=\"lane\" x=\"4\" y=\"{:.0}\">{} {}</text>",
+                y + 28.0,
+                escape_html(source),
+                lane
+            ));
+            for e in self
+                .entries
+                .iter()
+                .filter(|e| e.lane == *lane && e.source == *source)
+            {
+                let x = HTML_GUTTER + e.time * scale;
+                let mut title = format!("{:.3}s {}", e.time, e.label);
+                if !e.value.is_null() {
+                    title += &format!(" {}", e.value);
+                }
+                let mark = match e.duration {
+                    Some(duration) => format!(
+                        "<rect x=\"{:.1}\" y=\"{:.0}\" width=\"{:.1}\" height=\"28\"/>",
+                        x,
+                        y + 8.0,
+                        (duration * scale).max(1.0)
+                    ),
+                    None => format!(
+                        "<line x1=\"{:.1}\" y1=\"{:.0}\" x2=\"{:.1}\" y2=\"{:.0}\"/>",
+                        x,
+                        y + 8.0,
+                        x,
+                        y + 36.0
+                    ),
+                };
+                svg.push(format!(
+                    "<g class=\"{}\"><title>{}</title>{}<text x=\"{:.1}\" y=\"{:.0}\">{}</text></g>",
+                    lane,
+                    escape_html(&title),
+                    mark,
+                    x + 2.0,
+                    y + 26.0,
+                    escape_html(&e.label)
+                ));
+            }
+        }
+        svg.push(format!(
+            "<text class=\"axis\" x=\"{:.0}\" y=\"{:.0}\">0s</text><text class=\"axis\" x=\"{:.0}\" y=\"{:.0}\" text-anchor=\"end\">{:.3}s</text>",
+            HTML_GUTTER,
+            height - 6.0,
+            HTML_GUTTER + HTML_WIDTH,
+            height - 6.0,
+            self.span()
+        ));
+
+        format!(
+            "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>Run timeline</title>
+<style>
+body {{ font-family: sans-serif; }}
+text {{ font-size: 11px; }}
+.lane {{ font-weight: bold; }}
+rect {{ fill: #9ecae1; stroke: #3182bd; }}
+line {{ stroke: #444; stroke-width: 2; }}
+.tag line {{ stroke: #e6550d; }}
+.speed line {{ stroke: #31a354; }}
+</style>
+</head>
+<body>
+<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\">
+{}
+</svg>
+</body>
+</html>
+",
+            HTML_GUTTER + HTML_WIDTH + 80.0,
+            height,
+            svg.join("\n")
+        )
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botix::{ExitReason, StateVisit};
+    use crate::transition::BreakerResult;
+    use std::time::Duration;
+
+    const DETECTIONS: &str = include_str!("../testdata/timeline/detections.jsonl");
+
+    fn report() -> ExecutionReport {
+        let visit = |state_id, entered, exited, transition: Option<usize>| StateVisit {
+            state_id,
+            entered: Duration::from_millis(entered),
+            exited: Duration::from_millis(exited),
+            transition,
+            result: transition.map(|_| BreakerResult::Bool(true)),
+            branch: transition.map(|_| BreakerResult::Bool(true)),
+            exit: transition.map(|_| ExitReason::BreakerFired),
+            wait: false,
+        };
+        ExecutionReport {
+            started: Instant::now(),
+            visits: vec![
+                visit(0, 0, 400, Some(0)),
+                visit(1, 400, 900, Some(1)),
+                visit(2, 900, 900, None),
+            ],
+            hook_timeouts: Vec::new(),
+        }
+    }
+
+    fn sources() -> Vec<TimelineSource> {
+        let report = report();
+        let history = [
+            (report.started, vec![0, 0]),
+            (report.started + Duration::from_millis(400), vec![500, -500]),
+        ];
+        vec![
+            TimelineSource::from_report("run", &report).with_event(0.25, "tag 3"),
+            TimelineSource::from_commands("motors", report.started, &history),
+            // Unix clock, lined up on the first sighting of tag 3
+            TimelineSource::from_detection_log("camera", DETECTIONS.as_bytes())
+                .unwrap()
+                .aligned_on("tag 3"),
+        ]
+    }
+
+    #[test]
+    fn test_merge_golden() {
+        let timeline = merge(sources()).unwrap();
+        assert_eq!(
+            timeline.to_jsonl(),
+            include_str!("../testdata/timeline/merged.jsonl")
+        );
+        assert_eq!(
+            timeline.to_html(),
+            include_str!("../testdata/timeline/merged.html")
+        );
+    }
+
+    #[test]
+    fn test_offsets_and_sync_order() {
+        // The sync anchor may come from a source listed after the synced one
+        let camera = TimelineSource::from_detection_log("camera", DETECTIONS.as_bytes())
+            .unwrap()
+            .aligned_on("tag 3");
+        let run = TimelineSource::new("run", Vec::new()).with_event(10.0, "tag 3");
+        let timeline = merge(vec![run.clone(), camera.clone()]).unwrap();
+        let tag3 = timeline
+            .entries
+            .iter()
+            .find(|e| e.source == "camera" && e.label == "tag 3");
+        let event = timeline.entries.iter().find(|e| e.source == "run").unwrap();
+        assert_eq!(tag3.unwrap().time, event.time);
+
+        // A declared offset shifts the whole source
+        let shifted = merge(vec![
+            TimelineSource::new("a", Vec::new()).with_event(1.0, "x"),
+            TimelineSource::new("b", Vec::new())
+                .with_event(1.0, "y")
+                .with_offset(-0.5),
+        ])
+        .unwrap();
+        assert_eq!(shifted.origin, 0.5);
+        let labels: Vec<(&str, f64)> = shifted
+            .entries
+            .iter()
+            .map(|e| (e.label.as_str(), e.time))
+            .collect();
+        assert_eq!(labels, [("y", 0.0), ("x", 0.5)]);
+
+        let missing = merge(vec![run, camera.aligned_on("tag 9")]);
+        assert_eq!(
+            missing,
+            Err(TimelineError::SyncEventMissing {
+                source: "camera".to_string(),
+                label: "tag 9".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_detection_log_errors() {
+        let err = TimelineSource::from_detection_log("camera", "{\"timestamp\":1.0}\n".as_bytes());
+        assert!(matches!(err, Err(TimelineError::Parse { line: 1, .. })));
+        let err = TimelineSource::from_detection_log("camera", "\nnot json\n".as_bytes());
+        assert!(matches!(err, Err(TimelineError::Parse { line: 2, .. })));
+    }
+}
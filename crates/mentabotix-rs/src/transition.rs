@@ -2,8 +2,10 @@ use std::collections::HashMap;
 use std::fmt;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+use serde::{Deserialize, Serialize};
+
 /// Typed breaker result — replaces Python's arbitrary KT type variable.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BreakerResult {
     Bool(bool),
     Int(i64),
@@ -53,6 +55,9 @@ impl From<String> for BreakerResult {
     }
 }
 
+/// A shareable breaker closure, as stored in `MovingTransition::breaker`.
+pub type BreakerFn = std::sync::Arc<dyn Fn() -> BreakerResult + Send + Sync>;
+
 /// Counter for generating unique transition IDs.
 static TRANSITION_ID_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
@@ -67,13 +72,25 @@ pub struct MovingTransition {
     /// Transition duration in seconds.
     pub duration: f64,
     /// Optional breaker function to interrupt the transition.
-    pub breaker: Option<std::sync::Arc<dyn Fn() -> BreakerResult + Send + Sync>>,
+    pub breaker: Option<BreakerFn>,
+    /// Name the breaker is registered under, for saving the transition to a
+    /// behavior file (see `with_named_breaker`).
+    pub breaker_name: Option<String>,
     /// Frequency to check for state transition (seconds).
     pub check_interval: f64,
     /// Starting state IDs for the transition.
     pub from_states: Vec<usize>,
     /// Destination state IDs mapped by breaker result.
     pub to_states: HashMap<BreakerResult, usize>,
+    /// Whether the breaker returns string keys (see `with_keyed_breaker`).
+    /// Keys without a branch are then an error even with `fallback` set.
+    pub keyed: bool,
+    /// Whether results without a branch of their own follow the
+    /// `Placeholder` branch (see `with_fallback`).
+    pub fallback: bool,
+    /// Keys a keyed breaker may return; `validate()` checks them against the
+    /// string keys of `to_states`.
+    pub expected_keys: Option<Vec<String>>,
 }
 
 impl MovingTransition {
@@ -87,9 +104,13 @@ impl MovingTransition {
             id: TRANSITION_ID_COUNTER.fetch_add(1, Ordering::SeqCst),
             duration,
             breaker: None,
+            breaker_name: None,
             check_interval: 0.01,
             from_states: Vec::new(),
             to_states: HashMap::new(),
+            keyed: false,
+            fallback: false,
+            expected_keys: None,
         })
     }
 
@@ -99,15 +120,32 @@ impl MovingTransition {
         F: Fn() -> BreakerResult + Send + Sync + 'static,
     {
         self.breaker = Some(std::sync::Arc::new(breaker));
+        self.breaker_name = None;
         self
     }
 
     /// Set the breaker from an existing Arc.
-    pub fn with_arc_breaker(
-        mut self,
-        breaker: std::sync::Arc<dyn Fn() -> BreakerResult + Send + Sync>,
-    ) -> Self {
+    pub fn with_arc_breaker(mut self, breaker: BreakerFn) -> Self {
         self.breaker = Some(breaker);
+        self.breaker_name = None;
+        self
+    }
+
+    /// Set a breaker registered under `name`, so the transition can be saved
+    /// to a behavior file and loaded back with the same registry (see
+    /// `Botix::save_behavior`).
+    pub fn with_named_breaker(mut self, name: impl Into<String>, breaker: BreakerFn) -> Self {
+        self.breaker = Some(breaker);
+        self.breaker_name = Some(name.into());
+        self
+    }
+
+    /// Set a named breaker returning branch keys: `with_named_breaker` for a
+    /// breaker that behaves like one set with `with_keyed_breaker`, returning
+    /// `Str` keys or `Placeholder`.
+    pub fn with_named_keyed_breaker(mut self, name: impl Into<String>, breaker: BreakerFn) -> Self {
+        self = self.with_named_breaker(name, breaker);
+        self.keyed = true;
         self
     }
 
@@ -117,6 +155,34 @@ impl MovingTransition {
         F: Fn() -> bool + Send + Sync + 'static,
     {
         self.breaker = Some(std::sync::Arc::new(move || BreakerResult::Bool(breaker())));
+        self.breaker_name = None;
+        self
+    }
+
+    /// Set a breaker returning the key of the branch to take (convenience).
+    ///
+    /// `Some(key)` fires the transition into `to_states[key]`, which must
+    /// exist; `None` keeps waiting until the duration elapses, which follows
+    /// the `Placeholder` branch.
+    pub fn with_keyed_breaker<F>(mut self, breaker: F) -> Self
+    where
+        F: Fn() -> Option<String> + Send + Sync + 'static,
+    {
+        self.breaker = Some(std::sync::Arc::new(move || {
+            breaker().map_or(BreakerResult::Placeholder, BreakerResult::Str)
+        }));
+        self.breaker_name = None;
+        self.keyed = true;
+        self
+    }
+
+    /// Declare the keys a keyed breaker may return.
+    pub fn with_expected_keys<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.expected_keys = Some(keys.into_iter().map(Into::into).collect());
         self
     }
 
@@ -144,11 +210,85 @@ impl MovingTransition {
         self
     }
 
+    /// Send breaker results without a branch of their own down the
+    /// `Placeholder` branch instead of failing the run. Ignored for keyed
+    /// breakers.
+    pub fn with_fallback(mut self) -> Self {
+        self.fallback = true;
+        self
+    }
+
+    /// Check the branch keys of a keyed transition: a keyed breaker only
+    /// returns strings, and declared `expected_keys` must match the string
+    /// keys of `to_states`. The `Placeholder` branch is ignored.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.keyed && self.expected_keys.is_none() {
+            return Ok(());
+        }
+        let mut actual: Vec<&str> = Vec::new();
+        for key in self.to_states.keys() {
+            match key {
+                BreakerResult::Str(s) => actual.push(s),
+                BreakerResult::Placeholder => {}
+                other => {
+                    return Err(format!(
+                        "Transition {} expects string keys, found {:?}",
+                        self.id, other
+                    ));
+                }
+            }
+        }
+        let Some(expected) = &self.expected_keys else {
+            return Ok(());
+        };
+        let mut declared: Vec<&str> = expected.iter().map(String::as_str).collect();
+        declared.sort_unstable();
+        declared.dedup();
+        actual.sort_unstable();
+        if declared != actual {
+            return Err(format!(
+                "Transition {} expects keys {:?}, but to_states has {:?}",
+                self.id, declared, actual
+            ));
+        }
+        Ok(())
+    }
+
     /// Get the transition identifier.
     pub fn id(&self) -> usize {
         self.id
     }
 
+    /// Destination state ID for a breaker result.
+    ///
+    /// The result must match a `to_states` key exactly; a breaker that
+    /// times out returns `Placeholder`, which follows the branch set with
+    /// `with_single_to_state`. Other results only fall back to that branch
+    /// with `with_fallback`. A transition without a breaker has no result
+    /// to match and leads to its only destination. `None` means no branch
+    /// matches, which `Botix::run` reports as `NoMatchingBranch`.
+    pub fn next_state(&self, result: &BreakerResult) -> Option<usize> {
+        self.branch(result).map(|(_, next)| next)
+    }
+
+    /// The `to_states` entry a breaker result follows, as its key and
+    /// destination state ID; see [`MovingTransition::next_state`].
+    pub fn branch(&self, result: &BreakerResult) -> Option<(&BreakerResult, usize)> {
+        if let Some((key, &next)) = self.to_states.get_key_value(result) {
+            return Some((key, next));
+        }
+        if self.fallback && !self.keyed {
+            return self
+                .to_states
+                .get_key_value(&BreakerResult::Placeholder)
+                .map(|(key, &next)| (key, next));
+        }
+        if self.breaker.is_none() && self.to_states.len() == 1 {
+            return self.to_states.iter().next().map(|(key, &next)| (key, next));
+        }
+        None
+    }
+
     /// Check if this transition has branching (multiple to_states).
     pub fn is_branching(&self) -> bool {
         self.to_states.len() > 1
@@ -177,9 +317,13 @@ impl fmt::Debug for MovingTransition {
         f.debug_struct("MovingTransition")
             .field("id", &self.id)
             .field("duration", &self.duration)
+            .field("breaker_name", &self.breaker_name)
             .field("check_interval", &self.check_interval)
             .field("from_states", &self.from_states)
             .field("to_states", &self.to_states)
+            .field("keyed", &self.keyed)
+            .field("fallback", &self.fallback)
+            .field("expected_keys", &self.expected_keys)
             .finish()
     }
 }
@@ -244,6 +388,75 @@ mod tests {
         assert!(t.is_branching());
     }
 
+    #[test]
+    fn test_next_state() {
+        let t = MovingTransition::new(1.0)
+            .unwrap()
+            .with_to_state(BreakerResult::Int(1), 1)
+            .with_to_state(BreakerResult::Int(2), 2);
+        assert_eq!(t.next_state(&BreakerResult::Int(2)), Some(2));
+        assert_eq!(t.next_state(&BreakerResult::Placeholder), None);
+
+        // Unmatched results are an error unless they may fall back.
+        let t = t.with_single_to_state(0);
+        assert_eq!(t.next_state(&BreakerResult::Int(3)), None);
+        assert_eq!(t.next_state(&BreakerResult::Placeholder), Some(0));
+        let t = t.with_fallback();
+        assert_eq!(t.next_state(&BreakerResult::Int(3)), Some(0));
+        assert_eq!(t.next_state(&BreakerResult::Int(1)), Some(1));
+
+        let single = MovingTransition::new(1.0)
+            .unwrap()
+            .with_to_state(BreakerResult::Bool(true), 5);
+        assert_eq!(single.next_state(&BreakerResult::Placeholder), Some(5));
+    }
+
+    #[test]
+    fn test_keyed_next_state() {
+        let t = MovingTransition::new(1.0)
+            .unwrap()
+            .with_keyed_breaker(|| None)
+            .with_to_state("left", 1)
+            .with_to_state("right", 2)
+            .with_single_to_state(0);
+        assert!(t.keyed);
+        assert_eq!(t.breaker.as_ref().unwrap()(), BreakerResult::Placeholder);
+        assert_eq!(t.next_state(&BreakerResult::from("right")), Some(2));
+        // Unknown keys don't fall back to the timeout branch.
+        assert_eq!(t.next_state(&BreakerResult::from("up")), None);
+        let t = t.with_fallback();
+        assert_eq!(t.next_state(&BreakerResult::from("up")), None);
+        assert_eq!(t.next_state(&BreakerResult::Placeholder), Some(0));
+    }
+
+    #[test]
+    fn test_validate_expected_keys() {
+        let t = MovingTransition::new(1.0)
+            .unwrap()
+            .with_keyed_breaker(|| Some("left".into()))
+            .with_to_state("left", 1)
+            .with_to_state("right", 2)
+            .with_single_to_state(0);
+        assert!(t.validate().is_ok());
+        let t = t.with_expected_keys(["right", "left"]);
+        assert!(t.validate().is_ok());
+        let t = t.with_expected_keys(["left"]);
+        assert!(t.validate().is_err());
+
+        let mixed = MovingTransition::new(1.0)
+            .unwrap()
+            .with_to_state("left", 1)
+            .with_to_state(true, 2)
+            .with_expected_keys(["left"]);
+        assert!(mixed.validate().is_err());
+        // A keyed breaker can never return a bool.
+        let keyed = MovingTransition::new(1.0)
+            .unwrap()
+            .with_keyed_breaker(|| None)
+            .with_to_state(true, 2);
+        assert!(keyed.validate().is_err());
+    }
+
     #[test]
     fn test_breaker_result_from() {
         assert_eq!(BreakerResult::from(true), BreakerResult::Bool(true));
@@ -0,0 +1,160 @@
+use std::fmt;
+
+/// Errors returned by upic-rs
+///
+/// Distinguishes setup mistakes from camera and OpenCV backend failures so
+/// callers can decide whether retrying makes sense.
+#[derive(Debug)]
+pub enum UpicError {
+    /// An operation needed a camera but none is open
+    CameraNotInitialized,
+    /// The camera device could not be opened
+    CameraOpenFailed { device_id: i32 },
+    /// The camera stream, such as an RTSP URL or GStreamer pipeline, could not be opened
+    StreamOpenFailed { path: String },
+    /// The frame source returned no frame
+    FrameReadFailed,
+    /// The frame source has no frames left, such as a video file played to its end
+    EndOfStream,
+    /// Frame reads kept failing until the camera counted as disconnected; see
+    /// `Config::disconnect_after`
+    CameraDisconnected,
+    /// The operation needs the frame source, which the running detection thread owns
+    DetectionRunning,
+    /// The operation needs the detection thread, which is not running
+    DetectionNotRunning,
+    /// An OpenCV call failed
+    #[cfg(feature = "opencv")]
+    OpenCv(opencv::Error),
+    /// A configuration value is out of range or inconsistent
+    InvalidConfig(String),
+    /// The detection thread panicked; carries the panic message
+    DetectionThreadPanicked(String),
+    /// The operating system refused to start the detection thread
+    ThreadSpawnFailed(std::io::Error),
+    /// Writing a file, such as a captured frame, failed
+    Io(std::io::Error),
+    /// The camera did not apply the requested frame rate
+    UnsupportedFps { requested: f64, actual: f64 },
+    /// The operation needs a cargo feature upic-rs was built without, such as
+    /// opening a camera without `opencv`
+    FeatureDisabled { feature: &'static str },
+}
+
+impl fmt::Display for UpicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpicError::CameraNotInitialized => {
+                write!(f, "Camera is not initialized! Use open_camera() first!")
+            }
+            UpicError::CameraOpenFailed { device_id } => {
+                write!(f, "Can't open camera {}!", device_id)
+            }
+            UpicError::StreamOpenFailed { path } => {
+                write!(f, "Can't open camera stream {}!", path)
+            }
+            UpicError::FrameReadFailed => write!(f, "Failed to read a frame"),
+            UpicError::EndOfStream => write!(f, "The frame source has no frames left"),
+            UpicError::CameraDisconnected => write!(f, "Camera disconnected"),
+            UpicError::DetectionRunning => {
+                write!(f, "AprilTag detection is running! Stop it first!")
+            }
+            UpicError::DetectionNotRunning => {
+                write!(f, "AprilTag detection is not running! Start it first!")
+            }
+            #[cfg(feature = "opencv")]
+            UpicError::OpenCv(e) => write!(f, "OpenCV error: {}", e),
+            UpicError::InvalidConfig(message) => write!(f, "Invalid config: {}", message),
+            UpicError::DetectionThreadPanicked(message) => {
+                write!(f, "AprilTag detection thread panicked: {}", message)
+            }
+            UpicError::ThreadSpawnFailed(e) => {
+                write!(f, "Can't start the AprilTag detection thread: {}", e)
+            }
+            UpicError::Io(e) => write!(f, "I/O error: {}", e),
+            UpicError::UnsupportedFps { requested, actual } => {
+                write!(
+                    f,
+                    "Camera ignored the requested {} FPS, it runs at {} FPS",
+                    requested, actual
+                )
+            }
+            UpicError::FeatureDisabled { feature } => {
+                write!(f, "upic-rs was built without the {} feature", feature)
+            }
+        }
+    }
+}
+
+impl std::error::Error for UpicError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "opencv")]
+            UpicError::OpenCv(e) => Some(e),
+            UpicError::ThreadSpawnFailed(e) => Some(e),
+            UpicError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "opencv")]
+impl From<opencv::Error> for UpicError {
+    fn from(e: opencv::Error) -> Self {
+        UpicError::OpenCv(e)
+    }
+}
+
+impl From<std::io::Error> for UpicError {
+    fn from(e: std::io::Error) -> Self {
+        UpicError::Io(e)
+    }
+}
+
+impl UpicError {
+    /// Whether retrying the operation, for example reopening the camera, may succeed
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            UpicError::CameraOpenFailed { .. }
+            | UpicError::StreamOpenFailed { .. }
+            | UpicError::FrameReadFailed
+            | UpicError::CameraDisconnected => true,
+            #[cfg(feature = "opencv")]
+            UpicError::OpenCv(_) => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retryable_and_source() {
+        #[cfg(feature = "opencv")]
+        {
+            let backend = UpicError::from(opencv::Error::new(opencv::core::StsError, "backend"));
+            assert!(backend.is_retryable());
+            assert!(std::error::Error::source(&backend).is_some());
+        }
+        assert!(UpicError::CameraOpenFailed { device_id: 2 }.is_retryable());
+        assert!(
+            UpicError::StreamOpenFailed {
+                path: "rtsp://field-cam/stream".to_string()
+            }
+            .is_retryable()
+        );
+        assert!(UpicError::CameraDisconnected.is_retryable());
+        assert!(!UpicError::CameraNotInitialized.is_retryable());
+        assert!(!UpicError::DetectionNotRunning.is_retryable());
+        assert!(!UpicError::EndOfStream.is_retryable());
+        assert!(!UpicError::FeatureDisabled { feature: "opencv" }.is_retryable());
+        let io = UpicError::from(std::io::Error::from(std::io::ErrorKind::PermissionDenied));
+        assert!(std::error::Error::source(&io).is_some());
+        assert_eq!(
+            UpicError::CameraOpenFailed { device_id: 2 }.to_string(),
+            "Can't open camera 2!"
+        );
+    }
+}
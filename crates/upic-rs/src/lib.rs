@@ -1,2 +1,6 @@
+pub mod error;
 pub mod tag_detector;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub use error::UpicError;
 pub use tag_detector::TagDetector;
@@ -0,0 +1,280 @@
+use std::time::{Duration, Instant};
+
+use super::config::{AutoScalePolicy, FrameOrientation};
+use super::frame::Rect;
+
+/// Factor the multiplier changes by per step; frame time changes with its square
+const STEP: f64 = 0.8;
+
+/// Decides when the detection thread rescales the camera resolution.
+///
+/// Kept free of camera types so the decisions can be exercised with synthetic
+/// timings.
+pub(crate) struct ResolutionScaler {
+    policy: Option<AutoScalePolicy>,
+    /// Frame size multipliers apply to
+    base: (f64, f64),
+    multiplier: f64,
+    last_change: Instant,
+    /// Upper bound on the multiplier while the detection thread idles
+    cap: Option<f64>,
+    /// Multiplier to return to once the cap is lifted
+    resume: Option<f64>,
+}
+
+impl ResolutionScaler {
+    pub(crate) fn new(policy: Option<AutoScalePolicy>, base: (f64, f64), now: Instant) -> Self {
+        ResolutionScaler {
+            policy,
+            base,
+            multiplier: 1.0,
+            last_change: now,
+            cap: None,
+            resume: None,
+        }
+    }
+
+    /// Replace the policy; removing it scales back to the base size on the next
+    /// observation.
+    pub(crate) fn set_policy(&mut self, policy: Option<AutoScalePolicy>) {
+        self.policy = policy;
+    }
+
+    /// Make `base` the size multipliers apply to, as after a resolution set by
+    /// the caller.
+    pub(crate) fn rebase(&mut self, base: (f64, f64), now: Instant) {
+        self.base = base;
+        self.multiplier = 1.0;
+        self.last_change = now;
+        self.resume = self.resume.map(|_| 1.0);
+    }
+
+    /// Cap the multiplier until the cap is lifted with `None`, as while the
+    /// detection thread idles.
+    ///
+    /// The cap applies on the next observation without waiting for
+    /// `min_interval`, and lifting it returns to the multiplier it interrupted.
+    pub(crate) fn set_cap(&mut self, cap: Option<f64>) {
+        if self.cap.is_none() && cap.is_some() {
+            self.resume = Some(self.multiplier);
+        }
+        self.cap = cap;
+    }
+
+    /// Record the average frame time after a processed frame.
+    ///
+    /// # Returns
+    ///
+    /// The resolution to switch to, or `None` to keep the current one.
+    pub(crate) fn observe(&mut self, avg_frame_time: Duration, now: Instant) -> Option<(f64, f64)> {
+        if self.base.0 <= 0.0 || self.base.1 <= 0.0 {
+            return None;
+        }
+        let cap = self.cap.unwrap_or(f64::INFINITY);
+        let resume = if self.cap.is_none() {
+            self.resume.take()
+        } else {
+            None
+        };
+        let multiplier = match (resume, self.policy) {
+            (Some(resume), _) => resume,
+            (None, None) => cap.min(1.0),
+            (None, Some(policy)) => {
+                let max = policy.max_multiplier.min(cap);
+                let min = policy.min_multiplier.min(max);
+                let clamped = self.multiplier.clamp(min, max);
+                if clamped != self.multiplier {
+                    clamped
+                } else if now.duration_since(self.last_change) < policy.min_interval
+                    || avg_frame_time.is_zero()
+                {
+                    return None;
+                } else if avg_frame_time > policy.target_frame_time {
+                    (self.multiplier * STEP).max(min)
+                } else if avg_frame_time.div_f64(STEP * STEP) <= policy.target_frame_time {
+                    (self.multiplier / STEP).min(max)
+                } else {
+                    self.multiplier
+                }
+            }
+        };
+        if multiplier == self.multiplier {
+            return None;
+        }
+        if resume.is_some() || multiplier == cap {
+            log::info!(
+                "Idle mode changed, scaling the resolution from {:.2}x to {:.2}x",
+                self.multiplier,
+                multiplier
+            );
+        } else {
+            log::info!(
+                "Average frame time {:?}, scaling the resolution from {:.2}x to {:.2}x",
+                avg_frame_time,
+                self.multiplier,
+                multiplier
+            );
+        }
+        self.multiplier = multiplier;
+        self.last_change = now;
+        Some(self.resolution())
+    }
+
+    /// Resolution at the current multiplier
+    pub(crate) fn resolution(&self) -> (f64, f64) {
+        (
+            (self.base.0 * self.multiplier).round(),
+            (self.base.1 * self.multiplier).round(),
+        )
+    }
+
+    pub(crate) fn multiplier(&self) -> f64 {
+        self.multiplier
+    }
+
+    /// The size the resolution was scaled from, `None` while unscaled
+    pub(crate) fn scaled_from(&self) -> Option<(f64, f64)> {
+        (self.multiplier != 1.0).then_some(self.base)
+    }
+
+    /// Move `roi`, given in pixels of oriented frames at the base size, onto
+    /// oriented frames whose unoriented size is `actual`.
+    pub(crate) fn scale_roi(
+        &self,
+        roi: Rect,
+        actual: (f64, f64),
+        orientation: FrameOrientation,
+    ) -> Rect {
+        let (scale_x, scale_y) =
+            orientation.oriented_size((actual.0 / self.base.0, actual.1 / self.base.1));
+        let x = (roi.x as f64 * scale_x).round() as i32;
+        let y = (roi.y as f64 * scale_y).round() as i32;
+        let right = ((roi.x + roi.width) as f64 * scale_x).round() as i32;
+        let bottom = ((roi.y + roi.height) as f64 * scale_y).round() as i32;
+        Rect::new(x, y, (right - x).max(1), (bottom - y).max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: (f64, f64) = (1280.0, 720.0);
+
+    fn policy() -> Option<AutoScalePolicy> {
+        Some(AutoScalePolicy {
+            target_frame_time: Duration::from_millis(30),
+            min_multiplier: 0.5,
+            max_multiplier: 1.0,
+            min_interval: Duration::from_secs(2),
+        })
+    }
+
+    #[test]
+    fn test_steps_down_and_back_up() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let slow = Duration::from_millis(45);
+        let mut scaler = ResolutionScaler::new(policy(), BASE, start);
+
+        // Changes are at least min_interval apart
+        assert_eq!(scaler.observe(slow, at(1)), None);
+        assert_eq!(scaler.observe(slow, at(2)), Some((1024.0, 576.0)));
+        assert_eq!(scaler.scaled_from(), Some(BASE));
+        assert_eq!(scaler.observe(slow, at(3)), None);
+        assert_eq!(scaler.observe(slow, at(4)), Some((819.0, 461.0)));
+        // Stepping down stops at min_multiplier
+        assert_eq!(scaler.observe(slow, at(6)), Some((655.0, 369.0)));
+        assert_eq!(scaler.observe(slow, at(8)), Some((640.0, 360.0)));
+        assert_eq!(scaler.multiplier(), 0.5);
+        assert_eq!(scaler.observe(slow, at(10)), None);
+
+        // Within the budget, but stepping up is expected to exceed it
+        assert_eq!(scaler.observe(Duration::from_millis(25), at(12)), None);
+        assert_eq!(
+            scaler.observe(Duration::from_millis(15), at(12)),
+            Some((800.0, 450.0))
+        );
+    }
+
+    #[test]
+    fn test_policy_changes_and_rebase() {
+        let start = Instant::now();
+        let mut scaler = ResolutionScaler::new(policy(), BASE, start);
+        let slow = Duration::from_millis(45);
+        scaler.observe(slow, start + Duration::from_secs(2));
+        assert_eq!(scaler.multiplier(), 0.8);
+
+        // Tighter bounds apply at once, without waiting for min_interval
+        scaler.set_policy(Some(AutoScalePolicy {
+            max_multiplier: 0.6,
+            ..policy().unwrap()
+        }));
+        assert_eq!(
+            scaler.observe(slow, start + Duration::from_secs(2)),
+            Some((768.0, 432.0))
+        );
+        // Without a policy the base size is restored
+        scaler.set_policy(None);
+        assert_eq!(scaler.observe(slow, start), Some(BASE));
+        assert_eq!(scaler.scaled_from(), None);
+
+        scaler.set_policy(policy());
+        scaler.rebase((640.0, 480.0), start + Duration::from_secs(3));
+        assert_eq!(
+            scaler.observe(slow, start + Duration::from_secs(5)),
+            Some((512.0, 384.0))
+        );
+
+        // A source without a size is never scaled
+        let mut sizeless = ResolutionScaler::new(policy(), (0.0, 0.0), start);
+        assert_eq!(sizeless.observe(slow, start + Duration::from_secs(5)), None);
+    }
+
+    #[test]
+    fn test_idle_cap() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let slow = Duration::from_millis(45);
+        let mut scaler = ResolutionScaler::new(policy(), BASE, start);
+        scaler.observe(slow, at(2));
+        assert_eq!(scaler.multiplier(), 0.8);
+
+        // The cap applies and lifts at once, without waiting for min_interval
+        scaler.set_cap(Some(0.25));
+        assert_eq!(scaler.observe(slow, at(3)), Some((320.0, 180.0)));
+        assert_eq!(scaler.observe(slow, at(6)), None);
+        scaler.set_cap(None);
+        assert_eq!(scaler.observe(slow, at(7)), Some((1024.0, 576.0)));
+
+        // Without auto scaling the cap scales from and back to the base size
+        let mut unscaled = ResolutionScaler::new(None, BASE, start);
+        unscaled.set_cap(Some(0.5));
+        assert_eq!(unscaled.observe(slow, at(1)), Some((640.0, 360.0)));
+        assert_eq!(unscaled.scaled_from(), Some(BASE));
+        unscaled.set_cap(None);
+        assert_eq!(unscaled.observe(slow, at(1)), Some(BASE));
+        assert_eq!(unscaled.scaled_from(), None);
+    }
+
+    #[test]
+    fn test_scale_roi() {
+        let start = Instant::now();
+        let scaler = ResolutionScaler::new(policy(), BASE, start);
+        let roi = Rect::new(0, 360, 1280, 360);
+        assert_eq!(
+            scaler.scale_roi(roi, (640.0, 360.0), FrameOrientation::Normal),
+            Rect::new(0, 180, 640, 180)
+        );
+        // Rotated frames swap the axes the scale applies to
+        let rotated = ResolutionScaler::new(policy(), (1280.0, 640.0), start);
+        assert_eq!(
+            rotated.scale_roi(
+                Rect::new(100, 200, 50, 40),
+                (640.0, 160.0),
+                FrameOrientation::Rotate90
+            ),
+            Rect::new(25, 100, 13, 20)
+        );
+    }
+}
@@ -1,11 +1,101 @@
-use opencv::Result;
+#[cfg(feature = "opencv")]
 use opencv::prelude::*;
 
+use super::TagDetector;
+use super::config::FrameOrientation;
+use super::frame::Frame;
+use super::pipeline::FramePipeline;
+use super::selection::{filter_detections, select_detection};
+use crate::error::UpicError;
+
+/// Statistics of timed frames, in seconds
+///
+/// Returned by `benchmark_frames()` and `benchmark_detection()`. Percentiles
+/// use the nearest-rank method, so each is one of the measured times. All
+/// values are 0 when no frame was timed.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FrameTimeReport {
+    /// Number of timed frames, after the discarded warm-up frames
+    pub samples: usize,
+    /// Average time per frame
+    pub mean: f64,
+    /// Sample standard deviation; 0 with fewer than two frames
+    pub std_dev: f64,
+    /// Median time
+    pub p50: f64,
+    /// Time 95% of frames stayed within
+    pub p95: f64,
+    /// Time 99% of frames stayed within
+    pub p99: f64,
+    /// Fastest frame
+    pub min: f64,
+    /// Slowest frame
+    pub max: f64,
+}
+
+impl FrameTimeReport {
+    /// Summarize frame times given in seconds.
+    pub fn from_samples(durations: &[f64]) -> Self {
+        let samples = durations.len();
+        if samples == 0 {
+            return FrameTimeReport::default();
+        }
+        let mut sorted = durations.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        // Nearest rank: the smallest time at least `percent` of frames stayed within
+        let percentile = |percent: f64| {
+            let rank = (percent / 100.0 * samples as f64).ceil() as usize;
+            sorted[rank.clamp(1, samples) - 1]
+        };
+
+        let mean = durations.iter().sum::<f64>() / samples as f64;
+        let std_dev = if samples > 1 {
+            let variance =
+                durations.iter().map(|&d| (d - mean).powi(2)).sum::<f64>() / (samples - 1) as f64;
+            variance.sqrt()
+        } else {
+            0.0
+        };
+        FrameTimeReport {
+            samples,
+            mean,
+            std_dev,
+            p50: percentile(50.0),
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+            min: sorted[0],
+            max: sorted[samples - 1],
+        }
+    }
+
+    /// Log the statistics under `title`
+    pub(crate) fn log(&self, title: &str) {
+        log::info!(
+            "{}:\n\
+            \tRunning on [{}] frame updates\n\
+            \tAverage Frame time: [{:.6}s]\n\
+            \tStd Error: [{:.6}s]\n\
+            \tP50/P95/P99: [{:.6}s/{:.6}s/{:.6}s]\n\
+            \tMin/Max: [{:.6}s/{:.6}s]",
+            title,
+            self.samples,
+            self.mean,
+            self.std_dev,
+            self.p50,
+            self.p95,
+            self.p99,
+            self.min,
+            self.max
+        );
+    }
+}
+
 /// Benchmark camera frame acquisition performance over multiple samples.
 ///
 /// This utility function measures the time required to read frames from a camera
 /// over a specified number of iterations. It's useful for optimizing camera settings
-/// and evaluating system performance for real-time applications.
+/// and evaluating system performance for real-time applications. Needs the
+/// `opencv` feature.
 ///
 /// # Arguments
 ///
@@ -17,7 +107,8 @@ use opencv::prelude::*;
 ///
 /// # Returns
 ///
-/// Returns the average frame acquisition time in seconds per frame.
+/// Returns the average frame acquisition time in seconds per frame, or 0 if
+/// no frame was read.
 ///
 /// # Examples
 ///
@@ -32,43 +123,199 @@ use opencv::prelude::*;
 ///
 /// This function performs blocking frame reads and will take significant time
 /// to complete based on the test_frames_count parameter. Results may vary based
-/// on camera resolution and system load.
+/// on camera resolution and system load. Use `benchmark_frames()` to skip the
+/// slow first frames and get percentiles.
+#[cfg(feature = "opencv")]
 pub fn test_frame_time(
     camera: &mut opencv::videoio::VideoCapture,
     test_frames_count: usize,
-) -> Result<f64, Box<dyn std::error::Error>> {
+) -> opencv::Result<f64> {
+    Ok(benchmark_frames(camera, 0, test_frames_count)?.mean)
+}
+
+/// Benchmark camera frame acquisition, skipping the first frames.
+///
+/// The first frames after opening a camera are slow while auto exposure
+/// settles, and a real-time loop is limited by its outliers more than by its
+/// average, so this reports percentiles of the frames after a warm-up. Needs
+/// the `opencv` feature.
+///
+/// # Arguments
+///
+/// * `camera` - Opened and configured camera to test.
+/// * `warmup_frames` - Number of frames read first and left out of the report.
+/// * `test_frames_count` - Number of frames timed after the warm-up.
+///
+/// # Returns
+///
+/// Returns the statistics of the timed frames.
+///
+/// # Errors
+///
+/// Returns the OpenCV error of a failed read.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut camera = opencv::videoio::VideoCapture::new(0, opencv::videoio::CAP_ANY)?;
+/// let report = benchmark_frames(&mut camera, 10, 200)?;
+/// println!("P99 frame time: {:.4}s", report.p99);
+/// ```
+#[cfg(feature = "opencv")]
+pub fn benchmark_frames(
+    camera: &mut opencv::videoio::VideoCapture,
+    warmup_frames: usize,
+    test_frames_count: usize,
+) -> opencv::Result<FrameTimeReport> {
     let mut durations = Vec::with_capacity(test_frames_count);
-    let mut frame = opencv::core::Mat::default();
+    let mut frame = Frame::default();
 
+    for _ in 0..warmup_frames {
+        camera.read(&mut frame)?;
+    }
     for _ in 0..test_frames_count {
         let start = std::time::Instant::now();
         camera.read(&mut frame)?;
-        let duration = start.elapsed().as_secs_f64();
-        durations.push(duration);
+        durations.push(start.elapsed().as_secs_f64());
     }
 
-    let total_duration: f64 = durations.iter().sum();
-    let average_duration = total_duration / test_frames_count as f64;
-
-    // Calculate standard deviation
-    let variance: f64 = durations
-        .iter()
-        .map(|&d| (d - average_duration).powi(2))
-        .sum::<f64>()
-        / (test_frames_count - 1) as f64;
-    let std_error = variance.sqrt();
-
-    log::info!(
-        "Frame Time Test Results:\n\
-        \tRunning on [{}] frame updates\n\
-        \tTotal Time Cost: [{:.4}s]\n\
-        \tAverage Frame time: [{:.6}s]\n\
-        \tStd Error: [{:.6}s]",
-        test_frames_count,
-        total_duration,
-        average_duration,
-        std_error
-    );
-
-    Ok(average_duration)
+    let report = FrameTimeReport::from_samples(&durations);
+    report.log("Frame Time Test Results");
+    Ok(report)
+}
+
+/// Benchmark reading and detecting tags the way the detection thread does.
+///
+/// Each timed frame is read from the detector's camera, turned by
+/// `Config::orientation`, preprocessed, decoded within `Config::roi` and its
+/// tag selected, so the report shows the frame rate the configured detection
+/// can sustain.
+///
+/// # Arguments
+///
+/// * `detector` - Detector with an open camera and detection stopped.
+/// * `frames` - Number of frames to time.
+///
+/// # Returns
+///
+/// Returns the statistics of the timed frames.
+///
+/// # Errors
+///
+/// Returns `UpicError::DetectionRunning` if the detection thread owns the
+/// camera, `UpicError::CameraNotInitialized` if no camera is open, or the
+/// error of a failed read or detection.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut detector = TagDetector::new(Some(0), None)?;
+/// let report = benchmark_detection(&mut detector, 100)?;
+/// println!("Sustainable rate: {:.1} fps", 1.0 / report.p95);
+/// ```
+pub fn benchmark_detection(
+    detector: &mut TagDetector,
+    frames: usize,
+) -> Result<FrameTimeReport, UpicError> {
+    let report = FrameTimeReport::from_samples(&time_detection(detector, 0, frames)?);
+    report.log("Detection Time Test Results");
+    Ok(report)
+}
+
+/// Time frames from the start of their read until their tag is selected.
+///
+/// # Arguments
+///
+/// * `drain_frames` - Frames read and discarded first, to empty the camera's
+///   buffer.
+/// * `frames` - Number of frames to time.
+///
+/// # Returns
+///
+/// The time of each frame in seconds.
+pub(crate) fn time_detection(
+    detector: &mut TagDetector,
+    drain_frames: usize,
+    frames: usize,
+) -> Result<Vec<f64>, UpicError> {
+    if detector.detect_thread.is_some() {
+        return Err(UpicError::DetectionRunning);
+    }
+    let config = detector.config.clone();
+    let center = detector.oriented_center();
+    let camera = detector
+        .camera
+        .as_mut()
+        .ok_or(UpicError::CameraNotInitialized)?;
+    let mut pipeline = FramePipeline::new(&config)?;
+    let mut durations = Vec::with_capacity(frames);
+    let mut frame = Frame::default();
+    let mut oriented = Frame::default();
+
+    for _ in 0..drain_frames {
+        camera.read_frame_into(&mut frame)?;
+    }
+    for _ in 0..frames {
+        let start = std::time::Instant::now();
+        camera.read_frame_into(&mut frame)?;
+        if config.orientation != FrameOrientation::Normal {
+            config.orientation.apply(&frame, &mut oriented)?;
+            std::mem::swap(&mut frame, &mut oriented);
+        }
+        let mut candidates = pipeline.detect(&frame, &config)?;
+        filter_detections(&config, &mut candidates);
+        select_detection(&candidates, &config.ordering_method, center);
+        durations.push(start.elapsed().as_secs_f64());
+    }
+    Ok(durations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "opencv")]
+    use crate::tag_detector::source::MockFrameSource;
+
+    #[test]
+    fn test_frame_time_report() {
+        assert_eq!(
+            FrameTimeReport::from_samples(&[]),
+            FrameTimeReport::default()
+        );
+
+        let single = FrameTimeReport::from_samples(&[0.02]);
+        assert_eq!((single.mean, single.std_dev, single.p99), (0.02, 0.0, 0.02));
+
+        // One slow outlier among a hundred frames only shows in p99 and max
+        let mut durations = vec![0.01; 99];
+        durations.push(0.5);
+        let report = FrameTimeReport::from_samples(&durations);
+        assert_eq!(report.samples, 100);
+        assert_eq!((report.p50, report.p95, report.p99), (0.01, 0.01, 0.01));
+        assert_eq!((report.min, report.max), (0.01, 0.5));
+        assert!((report.mean - 0.0149).abs() < 1e-9);
+        assert!(report.std_dev > 0.0);
+    }
+
+    #[cfg(feature = "opencv")]
+    #[test]
+    fn test_benchmark_detection() {
+        let frame = Frame::new_rows_cols_with_default(
+            240,
+            320,
+            opencv::core::CV_8UC1,
+            opencv::core::Scalar::all(255.0),
+        )
+        .unwrap();
+        let mut detector =
+            TagDetector::with_source(Box::new(MockFrameSource::new(vec![frame]))).unwrap();
+        assert_eq!(benchmark_detection(&mut detector, 5).unwrap().samples, 5);
+
+        let mut detector =
+            TagDetector::with_source(Box::new(MockFrameSource::new(Vec::new()))).unwrap();
+        assert!(matches!(
+            benchmark_detection(&mut detector, 5),
+            Err(UpicError::FrameReadFailed)
+        ));
+    }
 }
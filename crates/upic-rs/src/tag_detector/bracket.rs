@@ -0,0 +1,289 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use super::config::BracketConfig;
+use super::source::{CameraProperty, FrameSource};
+
+/// Steps the exposure through `BracketConfig::exposures` when decision margins
+/// stay low, and keeps the one that decoded best.
+///
+/// Driven by the detection thread once per decoded frame, between reads, so
+/// exposure changes never race a read.
+pub(crate) struct ExposureBracketer {
+    /// Best margin of recent frames with a tag, oldest first, spanning `dwell`
+    margins: VecDeque<(Instant, f64)>,
+    /// Since when the mean of `margins` has been below the trigger
+    low_since: Option<Instant>,
+    /// Bracketing in progress, `None` while watching the margins
+    trial: Option<Trial>,
+}
+
+struct Trial {
+    /// Exposure bracketing started from, restored if it is interrupted
+    original: Option<f64>,
+    /// Index of the exposure being measured
+    index: usize,
+    /// Sum and count of the margins measured at it so far
+    sum: f64,
+    frames: u32,
+    /// Mean margin measured at each exposure tried, in order
+    means: Vec<f64>,
+}
+
+impl ExposureBracketer {
+    pub(crate) fn new() -> Self {
+        ExposureBracketer {
+            margins: VecDeque::new(),
+            low_since: None,
+            trial: None,
+        }
+    }
+
+    /// Whether an exposure is being measured
+    #[cfg(test)]
+    pub(crate) fn is_bracketing(&self) -> bool {
+        self.trial.is_some()
+    }
+
+    /// Record a decoded frame and change the exposure if it is due.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source whose exposure is bracketed
+    /// * `config` - `Config::auto_bracket`; `None` interrupts bracketing
+    /// * `margin` - Highest decision margin among the frame's decoded tags,
+    ///   before any filter, or `None` when no tag was decoded
+    /// * `now` - When the frame was read
+    pub(crate) fn observe(
+        &mut self,
+        source: &mut dyn FrameSource,
+        config: Option<&BracketConfig>,
+        margin: Option<f64>,
+        now: Instant,
+    ) {
+        let Some(config) = config else {
+            self.interrupt(source);
+            return;
+        };
+
+        if let Some(trial) = &mut self.trial {
+            trial.sum += margin.unwrap_or(0.0);
+            trial.frames += 1;
+            if trial.frames < config.frames_per_exposure {
+                return;
+            }
+            trial.means.push(trial.sum / f64::from(trial.frames));
+            trial.sum = 0.0;
+            trial.frames = 0;
+            trial.index += 1;
+            if let Some(&next) = config.exposures.get(trial.index) {
+                self.set_exposure(source, next);
+                return;
+            }
+            self.finish(source, config);
+            return;
+        }
+
+        if let Some(margin) = margin {
+            self.margins.push_back((now, margin));
+        }
+        while self
+            .margins
+            .front()
+            .is_some_and(|&(at, _)| now.duration_since(at) > config.dwell)
+        {
+            self.margins.pop_front();
+        }
+        // Without a tag in view there is nothing to judge the exposure by
+        if self.margins.is_empty() {
+            self.low_since = None;
+            return;
+        }
+        let mean = self.margins.iter().map(|&(_, m)| m).sum::<f64>() / self.margins.len() as f64;
+        if mean >= config.trigger_margin {
+            self.low_since = None;
+            return;
+        }
+        let low_since = *self.low_since.get_or_insert(now);
+        if now.duration_since(low_since) < config.dwell {
+            return;
+        }
+
+        log::info!(
+            "Mean decision margin {:.1} below {:.1} for {:?}, bracketing the exposure over {:?}",
+            mean,
+            config.trigger_margin,
+            config.dwell,
+            config.exposures
+        );
+        self.trial = Some(Trial {
+            original: source.property(CameraProperty::Exposure).ok(),
+            index: 0,
+            sum: 0.0,
+            frames: 0,
+            means: Vec::with_capacity(config.exposures.len()),
+        });
+        self.set_exposure(source, config.exposures[0]);
+    }
+
+    /// Stop bracketing in progress and restore the exposure it started from,
+    /// as when detection is halted.
+    pub(crate) fn interrupt(&mut self, source: &mut dyn FrameSource) {
+        if let Some(original) = self.trial.take().and_then(|trial| trial.original) {
+            log::info!(
+                "Exposure bracketing interrupted, restoring exposure {}",
+                original
+            );
+            if let Err(e) = source.set_property(CameraProperty::Exposure, original) {
+                log::warn!("Can't restore the exposure: {}", e);
+            }
+        }
+        self.reset();
+    }
+
+    /// Stop bracketing in progress without touching the exposure, as when the
+    /// caller sets camera controls or switches the source.
+    pub(crate) fn cancel(&mut self) {
+        if self.trial.take().is_some() {
+            log::info!("Exposure bracketing cancelled by a camera change");
+        }
+        self.reset();
+    }
+
+    fn reset(&mut self) {
+        self.margins.clear();
+        self.low_since = None;
+    }
+
+    /// Lock in the exposure that measured best, the earliest one on ties.
+    fn finish(&mut self, source: &mut dyn FrameSource, config: &BracketConfig) {
+        let Some(trial) = self.trial.take() else {
+            return;
+        };
+        let mut best = 0;
+        for (index, &mean) in trial.means.iter().enumerate() {
+            if mean > trial.means[best] {
+                best = index;
+            }
+        }
+        let exposure = config.exposures[best];
+        log::info!(
+            "Exposure bracketing measured mean margins {:?} at exposures {:?}, locking in {}",
+            trial.means,
+            config.exposures,
+            exposure
+        );
+        self.set_exposure(source, exposure);
+        self.reset();
+    }
+
+    fn set_exposure(&mut self, source: &mut dyn FrameSource, exposure: f64) {
+        match source.set_property(CameraProperty::Exposure, exposure) {
+            Ok(accepted) => log::debug!("Bracketing at exposure {}", accepted),
+            Err(e) => {
+                log::warn!("Can't set the exposure, bracketing abandoned: {}", e);
+                self.trial = None;
+                self.reset();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tag_detector::source::MockFrameSource;
+    use std::time::Duration;
+
+    fn bracket() -> BracketConfig {
+        BracketConfig {
+            exposures: vec![40.0, 80.0, 160.0, 320.0],
+            trigger_margin: 50.0,
+            dwell: Duration::from_millis(500),
+            frames_per_exposure: 3,
+        }
+    }
+
+    /// Margins peak at exposure 160 and fall off either side of it
+    fn scripted_margin(source: &MockFrameSource) -> Option<f64> {
+        let exposure = source.property(CameraProperty::Exposure).unwrap();
+        Some((90.0 - (exposure - 160.0).abs() / 4.0).max(0.0))
+    }
+
+    /// Feed frames 50ms apart, each with the margin the current exposure gives.
+    fn run_frames(
+        bracketer: &mut ExposureBracketer,
+        source: &mut MockFrameSource,
+        config: &BracketConfig,
+        start: Instant,
+        frames: std::ops::Range<u32>,
+    ) {
+        for i in frames {
+            let margin = scripted_margin(source);
+            let now = start + Duration::from_millis(50) * i;
+            bracketer.observe(source, Some(config), margin, now);
+        }
+    }
+
+    #[test]
+    fn test_brackets_to_best_exposure() {
+        let config = bracket();
+        let mut source = MockFrameSource::new(Vec::new());
+        // Far too bright: margin 0
+        source
+            .set_property(CameraProperty::Exposure, 600.0)
+            .unwrap();
+        let mut bracketer = ExposureBracketer::new();
+        let start = Instant::now();
+
+        // The low mean must last the whole dwell before bracketing starts
+        run_frames(&mut bracketer, &mut source, &config, start, 0..10);
+        assert!(!bracketer.is_bracketing());
+        assert_eq!(source.property(CameraProperty::Exposure).unwrap(), 600.0);
+        run_frames(&mut bracketer, &mut source, &config, start, 10..11);
+        assert!(bracketer.is_bracketing());
+        assert_eq!(source.property(CameraProperty::Exposure).unwrap(), 40.0);
+
+        // Three frames at each of the four candidates
+        run_frames(&mut bracketer, &mut source, &config, start, 11..23);
+        assert!(!bracketer.is_bracketing());
+        assert_eq!(source.property(CameraProperty::Exposure).unwrap(), 160.0);
+
+        // Good margins at the locked exposure don't trigger again
+        run_frames(&mut bracketer, &mut source, &config, start, 23..60);
+        assert!(!bracketer.is_bracketing());
+        assert_eq!(source.property(CameraProperty::Exposure).unwrap(), 160.0);
+    }
+
+    #[test]
+    fn test_interrupt_restores_exposure() {
+        let config = bracket();
+        let mut source = MockFrameSource::new(Vec::new());
+        source
+            .set_property(CameraProperty::Exposure, 600.0)
+            .unwrap();
+        let mut bracketer = ExposureBracketer::new();
+        let start = Instant::now();
+        run_frames(&mut bracketer, &mut source, &config, start, 0..13);
+        assert!(bracketer.is_bracketing());
+
+        bracketer.interrupt(&mut source);
+        assert!(!bracketer.is_bracketing());
+        assert_eq!(source.property(CameraProperty::Exposure).unwrap(), 600.0);
+
+        // Frames without a tag never trigger it
+        let mut bracketer = ExposureBracketer::new();
+        for i in 0..40 {
+            let now = start + Duration::from_millis(50) * i;
+            bracketer.observe(&mut source, Some(&config), None, now);
+        }
+        assert!(!bracketer.is_bracketing());
+
+        // Neither does a config without bracketing
+        for i in 0..40 {
+            let now = start + Duration::from_millis(50) * i;
+            bracketer.observe(&mut source, None, Some(0.0), now);
+        }
+        assert!(!bracketer.is_bracketing());
+    }
+}
@@ -0,0 +1,400 @@
+use std::time::{Duration, Instant};
+
+use opencv::core::Mat;
+use opencv::prelude::*;
+use opencv::videoio::{self, VideoCapture};
+
+use super::source::{CameraBackend, CameraProperty, FrameSource};
+use crate::error::UpicError;
+
+impl CameraProperty {
+    /// The OpenCV `CAP_PROP_*` identifier
+    pub fn cap_prop(self) -> i32 {
+        match self {
+            CameraProperty::Exposure => videoio::CAP_PROP_EXPOSURE,
+            CameraProperty::Gain => videoio::CAP_PROP_GAIN,
+            CameraProperty::AutoExposure => videoio::CAP_PROP_AUTO_EXPOSURE,
+            CameraProperty::WhiteBalance => videoio::CAP_PROP_WB_TEMPERATURE,
+            CameraProperty::Fps => videoio::CAP_PROP_FPS,
+            CameraProperty::Brightness => videoio::CAP_PROP_BRIGHTNESS,
+        }
+    }
+}
+
+impl FrameSource for VideoCapture {
+    fn read_frame(&mut self) -> Result<Mat, UpicError> {
+        let mut frame = Mat::default();
+        self.read_frame_into(&mut frame)?;
+        Ok(frame)
+    }
+
+    fn read_frame_into(&mut self, frame: &mut Mat) -> Result<(), UpicError> {
+        if !self.read(frame)? || frame.empty() {
+            return Err(UpicError::FrameReadFailed);
+        }
+        Ok(())
+    }
+
+    fn resolution(&self) -> (f64, f64) {
+        (
+            self.get(videoio::CAP_PROP_FRAME_WIDTH).unwrap_or(0.0),
+            self.get(videoio::CAP_PROP_FRAME_HEIGHT).unwrap_or(0.0),
+        )
+    }
+
+    fn set_resolution(&mut self, width: f64, height: f64) -> Result<(), UpicError> {
+        self.set(videoio::CAP_PROP_FRAME_WIDTH, width)?;
+        self.set(videoio::CAP_PROP_FRAME_HEIGHT, height)?;
+        Ok(())
+    }
+
+    fn set_buffer_size(&mut self, buffer_size: i32) -> Result<(), UpicError> {
+        self.set(videoio::CAP_PROP_BUFFERSIZE, buffer_size as f64)?;
+        Ok(())
+    }
+
+    fn video_capture(&self) -> Option<&VideoCapture> {
+        Some(self)
+    }
+
+    fn is_connected(&self) -> bool {
+        self.is_opened().unwrap_or(false)
+    }
+
+    fn set_property(&mut self, property: CameraProperty, value: f64) -> Result<f64, UpicError> {
+        // A white balance temperature only sticks with automatic white balance off
+        if property == CameraProperty::WhiteBalance {
+            self.set(videoio::CAP_PROP_AUTO_WB, 0.0)?;
+        }
+        if !self.set(property.cap_prop(), value)? {
+            log::warn!("Camera driver rejected {:?} = {}", property, value);
+        }
+        self.property(property)
+    }
+
+    fn property(&self, property: CameraProperty) -> Result<f64, UpicError> {
+        Ok(self.get(property.cap_prop())?)
+    }
+}
+
+impl CameraBackend {
+    /// The OpenCV `CAP_*` API preference
+    pub fn api_preference(self) -> i32 {
+        match self {
+            CameraBackend::Any => videoio::CAP_ANY,
+            CameraBackend::V4l2 => videoio::CAP_V4L2,
+            CameraBackend::GStreamer => videoio::CAP_GSTREAMER,
+            CameraBackend::FFmpeg => videoio::CAP_FFMPEG,
+        }
+    }
+}
+
+/// What a `CameraSource` was opened from
+#[derive(Debug, Clone)]
+enum CameraTarget {
+    Device(i32),
+    Path(String, CameraBackend),
+}
+
+/// A camera that remembers how it was opened and configured
+///
+/// Either a local device or a stream such as an RTSP URL or GStreamer pipeline.
+/// Unlike a bare `VideoCapture`, it can be reopened by the detection thread
+/// after the camera drops out, restoring the requested resolution, buffer size
+/// and camera controls.
+pub struct CameraSource {
+    capture: VideoCapture,
+    target: CameraTarget,
+    resolution: Option<(f64, f64)>,
+    buffer_size: Option<i32>,
+    /// Controls set so far, in the order they were last set
+    properties: Vec<(CameraProperty, f64)>,
+}
+
+impl CameraSource {
+    /// Open a camera device.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_id` - Camera device identifier. Typically 0 for the default camera.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::CameraOpenFailed` if the device cannot be opened, or
+    /// `UpicError::OpenCv` if the OpenCV backend fails.
+    pub fn open(device_id: i32) -> Result<Self, UpicError> {
+        Self::open_target(CameraTarget::Device(device_id))
+    }
+
+    /// Open a camera stream by path, URL or pipeline string.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Device path, stream URL such as `rtsp://...`, or GStreamer pipeline.
+    /// * `backend` - Capture backend that understands `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::StreamOpenFailed` if the stream cannot be opened, or
+    /// `UpicError::OpenCv` if the OpenCV backend fails.
+    pub fn open_path(path: &str, backend: CameraBackend) -> Result<Self, UpicError> {
+        Self::open_target(CameraTarget::Path(path.to_string(), backend))
+    }
+
+    /// Camera device identifier the source was opened with, `None` for streams.
+    pub fn device_id(&self) -> Option<i32> {
+        match self.target {
+            CameraTarget::Device(device_id) => Some(device_id),
+            CameraTarget::Path(..) => None,
+        }
+    }
+
+    fn open_target(target: CameraTarget) -> Result<Self, UpicError> {
+        Ok(Self {
+            capture: Self::open_capture(&target)?,
+            target,
+            resolution: None,
+            buffer_size: None,
+            properties: Vec::new(),
+        })
+    }
+
+    fn open_capture(target: &CameraTarget) -> Result<VideoCapture, UpicError> {
+        match target {
+            CameraTarget::Device(device_id) => {
+                let capture = VideoCapture::new(*device_id, videoio::CAP_ANY)?;
+                if !capture.is_opened()? {
+                    return Err(UpicError::CameraOpenFailed {
+                        device_id: *device_id,
+                    });
+                }
+                Ok(capture)
+            }
+            CameraTarget::Path(path, backend) => {
+                let capture = VideoCapture::from_file(path, backend.api_preference())?;
+                if !capture.is_opened()? {
+                    return Err(UpicError::StreamOpenFailed { path: path.clone() });
+                }
+                Ok(capture)
+            }
+        }
+    }
+}
+
+impl FrameSource for CameraSource {
+    fn read_frame(&mut self) -> Result<Mat, UpicError> {
+        self.capture.read_frame()
+    }
+
+    fn read_frame_into(&mut self, frame: &mut Mat) -> Result<(), UpicError> {
+        self.capture.read_frame_into(frame)
+    }
+
+    fn resolution(&self) -> (f64, f64) {
+        self.capture.resolution()
+    }
+
+    fn set_resolution(&mut self, width: f64, height: f64) -> Result<(), UpicError> {
+        self.capture.set_resolution(width, height)?;
+        self.resolution = Some((width, height));
+        Ok(())
+    }
+
+    fn set_buffer_size(&mut self, buffer_size: i32) -> Result<(), UpicError> {
+        self.capture.set_buffer_size(buffer_size)?;
+        self.buffer_size = Some(buffer_size);
+        Ok(())
+    }
+
+    fn video_capture(&self) -> Option<&VideoCapture> {
+        Some(&self.capture)
+    }
+
+    fn is_connected(&self) -> bool {
+        self.capture.is_connected()
+    }
+
+    fn set_property(&mut self, property: CameraProperty, value: f64) -> Result<f64, UpicError> {
+        let accepted = self.capture.set_property(property, value)?;
+        // Replay in the order last set, so manual exposure follows turning auto exposure off
+        self.properties.retain(|(set, _)| *set != property);
+        self.properties.push((property, value));
+        Ok(accepted)
+    }
+
+    fn property(&self, property: CameraProperty) -> Result<f64, UpicError> {
+        self.capture.property(property)
+    }
+
+    fn reconnect(&mut self) -> Result<(), UpicError> {
+        // Release the device before reopening it, some drivers refuse a second handle
+        self.capture.release()?;
+        self.capture = Self::open_capture(&self.target)?;
+        if let Some((width, height)) = self.resolution {
+            self.capture.set_resolution(width, height)?;
+        }
+        if let Some(buffer_size) = self.buffer_size {
+            self.capture.set_buffer_size(buffer_size)?;
+        }
+        for &(property, value) in &self.properties {
+            self.capture.set_property(property, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Frames read from a video file
+///
+/// Useful for replaying recorded matches through the detector. Frames are read
+/// as fast as they are decoded, or at the file's frame rate with
+/// `with_realtime()`. At the end of the file reads fail with
+/// `UpicError::EndOfStream`, which stops the detection thread, unless the
+/// source loops.
+pub struct VideoFileSource {
+    capture: VideoCapture,
+    looping: bool,
+    /// Time between frames at the file's frame rate, `None` if it reports none
+    frame_period: Option<Duration>,
+    realtime: bool,
+    next_frame_at: Option<Instant>,
+}
+
+impl VideoFileSource {
+    /// Open a video file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the video file.
+    /// * `looping` - Whether to restart from the first frame at the end of the
+    ///   file instead of reporting `UpicError::EndOfStream`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::InvalidConfig` if the file cannot be opened.
+    pub fn open(path: &str, looping: bool) -> Result<Self, UpicError> {
+        let capture = VideoCapture::from_file(path, videoio::CAP_ANY)?;
+        if !capture.is_opened()? {
+            return Err(UpicError::InvalidConfig(format!(
+                "Can't open video file {}",
+                path
+            )));
+        }
+        let fps = capture.get(videoio::CAP_PROP_FPS)?;
+        let frame_period =
+            (fps.is_finite() && fps > 0.0).then(|| Duration::from_secs_f64(1.0 / fps));
+        Ok(Self {
+            capture,
+            looping,
+            frame_period,
+            realtime: false,
+            next_frame_at: None,
+        })
+    }
+
+    /// Pace reads to the file's frame rate, so a recorded match replays at the
+    /// speed it was played.
+    ///
+    /// Reads never run ahead of the recording; when detection is slower than the
+    /// file's frame rate, playback slows down rather than skipping frames. Files
+    /// that report no frame rate are read as fast as possible.
+    pub fn with_realtime(mut self, realtime: bool) -> Self {
+        self.realtime = realtime;
+        self
+    }
+
+    /// Sleep until the next frame is due at the file's frame rate.
+    fn pace(&mut self) {
+        let Some(frame_period) = self.frame_period.filter(|_| self.realtime) else {
+            return;
+        };
+        let now = Instant::now();
+        let due = self.next_frame_at.map_or(now, |due| due.max(now));
+        std::thread::sleep(due - now);
+        // A late frame delays the ones after it instead of bunching them up
+        self.next_frame_at = Some(due + frame_period);
+    }
+}
+
+impl FrameSource for VideoFileSource {
+    fn read_frame(&mut self) -> Result<Mat, UpicError> {
+        let mut frame = Mat::default();
+        self.read_frame_into(&mut frame)?;
+        Ok(frame)
+    }
+
+    fn read_frame_into(&mut self, frame: &mut Mat) -> Result<(), UpicError> {
+        self.pace();
+        match self.capture.read_frame_into(frame) {
+            Err(UpicError::FrameReadFailed) if self.looping => {
+                self.capture.set(videoio::CAP_PROP_POS_FRAMES, 0.0)?;
+                self.capture.read_frame_into(frame)
+            }
+            Err(UpicError::FrameReadFailed) => Err(UpicError::EndOfStream),
+            result => result,
+        }
+    }
+
+    fn resolution(&self) -> (f64, f64) {
+        self.capture.resolution()
+    }
+
+    fn property(&self, property: CameraProperty) -> Result<f64, UpicError> {
+        self.capture.property(property)
+    }
+
+    fn video_capture(&self) -> Option<&VideoCapture> {
+        Some(&self.capture)
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::testing::write_video;
+    use opencv::core::{CV_8UC1, Scalar};
+
+    /// A 64x48 clip of `frames` blank frames at 25 fps
+    fn clip(name: &str, frames: usize) -> String {
+        let path = std::env::temp_dir().join(format!("upic-{}-{}.avi", name, std::process::id()));
+        let frame = Mat::new_rows_cols_with_default(48, 64, CV_8UC1, Scalar::all(128.0)).unwrap();
+        write_video(&path, &vec![frame; frames], 25.0).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_video_file_ends() {
+        let path = clip("video-end", 5);
+        let mut source = VideoFileSource::open(&path, false).unwrap();
+        assert_eq!(source.resolution(), (64.0, 48.0));
+        assert_eq!(source.property(CameraProperty::Fps).unwrap(), 25.0);
+        let mut frame = Mat::default();
+        for _ in 0..5 {
+            source.read_frame_into(&mut frame).unwrap();
+        }
+        assert!(matches!(
+            source.read_frame_into(&mut frame),
+            Err(UpicError::EndOfStream)
+        ));
+
+        // A looping source starts over instead
+        let mut looping = VideoFileSource::open(&path, true).unwrap();
+        for _ in 0..12 {
+            looping.read_frame_into(&mut frame).unwrap();
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_video_file_realtime() {
+        let path = clip("video-realtime", 5);
+        let mut frame = Mat::default();
+        let mut source = VideoFileSource::open(&path, false)
+            .unwrap()
+            .with_realtime(true);
+        let started = Instant::now();
+        while source.read_frame_into(&mut frame).is_ok() {}
+        // Five frames at 25 fps; the first is due right away
+        assert!(started.elapsed() >= Duration::from_millis(160));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
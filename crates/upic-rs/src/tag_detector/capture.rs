@@ -0,0 +1,122 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "opencv")]
+use opencv::core::Vector;
+#[cfg(feature = "opencv")]
+use opencv::imgcodecs;
+#[cfg(feature = "opencv")]
+use opencv::prelude::*;
+
+use super::frame::Frame;
+use super::sync::Recover;
+use crate::error::UpicError;
+
+/// Pending `TagDetector::capture_frame()` calls, each waiting for a frame from
+/// the detection thread
+pub(crate) type FrameRequests = Arc<Mutex<Vec<Sender<Frame>>>>;
+
+/// Hand a copy of `frame` to every pending capture request.
+///
+/// With an empty frame, before any frame was read, the requests are dropped,
+/// which makes their callers fail right away instead of waiting for a frame
+/// that may never come.
+pub(crate) fn serve_frame_requests(requests: &Mutex<Vec<Sender<Frame>>>, frame: &Frame) {
+    for request in requests.lock().recover().drain(..) {
+        if frame.empty() {
+            continue;
+        }
+        match frame.try_clone() {
+            // The caller may have timed out and gone away
+            Ok(copy) => {
+                let _ = request.send(copy);
+            }
+            Err(e) => log::warn!("Can't copy the frame for a capture request: {}", e),
+        }
+    }
+}
+
+/// Encode a frame as PNG.
+#[cfg(feature = "opencv")]
+pub(crate) fn encode_png(frame: &Frame) -> Result<Vec<u8>, UpicError> {
+    let mut buffer = Vector::<u8>::new();
+    if !imgcodecs::imencode(".png", frame, &mut buffer, &Vector::new())? {
+        return Err(UpicError::OpenCv(opencv::Error::new(
+            opencv::core::StsError,
+            "PNG encoding failed",
+        )));
+    }
+    Ok(buffer.to_vec())
+}
+
+/// Encoding images needs the `opencv` feature, so this always fails with
+/// `UpicError::FeatureDisabled`.
+#[cfg(not(feature = "opencv"))]
+pub(crate) fn encode_png(_frame: &Frame) -> Result<Vec<u8>, UpicError> {
+    Err(UpicError::FeatureDisabled { feature: "opencv" })
+}
+
+/// Save a frame as a timestamped PNG file in `dir`, creating it if needed.
+///
+/// # Returns
+///
+/// The path of the written file.
+pub(crate) fn save_frame(dir: &Path, prefix: &str, frame: &Frame) -> Result<PathBuf, UpicError> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis());
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}_{}.png", prefix, millis));
+    fs::write(&path, encode_png(frame)?)?;
+    Ok(path)
+}
+
+#[cfg(all(test, feature = "opencv"))]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    /// Signature every PNG file starts with
+    const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+    fn frame() -> Frame {
+        Frame::new_rows_cols_with_default(
+            48,
+            64,
+            opencv::core::CV_8UC1,
+            opencv::core::Scalar::all(128.0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_serve_and_save_frames() {
+        let requests = Mutex::new(Vec::new());
+        let (sender, receiver) = mpsc::channel();
+        requests.lock().unwrap().push(sender);
+        serve_frame_requests(&requests, &frame());
+        let served = receiver.try_recv().unwrap();
+        assert_eq!((served.cols(), served.rows()), (64, 48));
+        assert!(requests.lock().unwrap().is_empty());
+
+        // Without a frame the caller sees the channel close
+        let (sender, receiver) = mpsc::channel();
+        requests.lock().unwrap().push(sender);
+        serve_frame_requests(&requests, &Frame::default());
+        assert!(matches!(
+            receiver.try_recv(),
+            Err(mpsc::TryRecvError::Disconnected)
+        ));
+
+        assert!(encode_png(&served).unwrap().starts_with(&PNG_SIGNATURE));
+
+        let dir = std::env::temp_dir().join(format!("upic-capture-{}", std::process::id()));
+        let path = save_frame(&dir, "error", &served).unwrap();
+        assert!(path.starts_with(&dir));
+        assert!(fs::read(&path).unwrap().starts_with(&PNG_SIGNATURE));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
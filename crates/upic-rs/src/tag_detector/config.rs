@@ -1,30 +1,286 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
+use super::detection::TagDetection;
+use super::frame::Rect;
+use crate::error::UpicError;
+
+/// Custom tag selector: returns the index of the detection to publish, or `None`
+pub type TagSelector = Arc<dyn Fn(&[TagDetection]) -> Option<usize> + Send + Sync>;
+
 /// Tag selection method for when multiple tags are detected
-#[derive(Debug, Clone, Copy)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OrderingMethod {
     /// Select the tag nearest to the frame center
+    #[default]
     Nearest,
     /// Select the first detected tag
     Single,
+    /// Select the tag with the largest pixel area, usually the physically closest one
+    Largest,
+    /// Only report the tag with this ID; any other tags count as not detected
+    ById(i32),
+    /// Select with a user-provided function over all detections in the frame
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(TagSelector),
+}
+
+impl fmt::Debug for OrderingMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderingMethod::Nearest => write!(f, "Nearest"),
+            OrderingMethod::Single => write!(f, "Single"),
+            OrderingMethod::Largest => write!(f, "Largest"),
+            OrderingMethod::ById(id) => write!(f, "ById({})", id),
+            OrderingMethod::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+/// AprilTag families the detector can decode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TagFamily {
+    Tag36h11,
+    Tag25h9,
+    Tag16h5,
+    TagStandard41h12,
+    TagCircle21h7,
+}
+
+/// Exposure settle criterion applied after the fixed frame discard
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SettleSpec {
+    /// Upper bound on the time spent waiting for exposure to settle
+    #[cfg_attr(feature = "serde", serde(with = "millis"))]
+    pub max_wait: Duration,
+    /// Maximum mean-brightness change (0–255 scale) between two consecutive
+    /// frames for the exposure to count as settled
+    pub brightness_delta: f64,
+}
+
+/// Camera warm-up performed before detection starts
+///
+/// The default discards nothing and does not wait, which matches the
+/// behavior of detectors without a warm-up policy.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WarmupPolicy {
+    /// Number of frames read and thrown away unconditionally
+    pub discard_frames: usize,
+    /// Optional wait for auto-exposure to settle after the discard
+    pub settle: Option<SettleSpec>,
+}
+
+/// Reduced frame rate mode entered when no tag has been detected for a while
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IdlePolicy {
+    /// Time without an accepted detection before the frame rate is reduced
+    #[cfg_attr(feature = "serde", serde(with = "millis"))]
+    pub after: Duration,
+    /// Frame rate used while idle
+    pub reduced_fps: f64,
+    /// Whether a detection while idle restores the full frame rate; when false,
+    /// only `TagDetector::resume_full_rate` does
+    pub wake_on_detection: bool,
+    /// Resolution multiplier the camera drops to while idle, on top of
+    /// `Config::auto_scale`; `None` keeps the resolution
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub resolution_multiplier: Option<f64>,
+}
+
+/// Scaling of the camera resolution to keep the detection thread within a
+/// frame time budget, e.g. while the board throttles under heat
+///
+/// Multipliers are relative to the resolution detection started at, or was last
+/// set to with `TagDetector::set_cam_resolution`. Frame time grows roughly with
+/// the pixel count, so the resolution is stepped down while the average frame
+/// time exceeds `target_frame_time`, and back up once the larger frames are
+/// expected to fit within it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AutoScalePolicy {
+    /// Average time per frame to stay within, as in `DetectionStats::avg_frame_time`
+    #[cfg_attr(feature = "serde", serde(with = "millis"))]
+    pub target_frame_time: Duration,
+    /// Smallest multiplier the resolution is scaled down to
+    pub min_multiplier: f64,
+    /// Largest multiplier the resolution is scaled up to
+    pub max_multiplier: f64,
+    /// Shortest time between two changes, so the average frame time reflects
+    /// the current resolution before the next one
+    #[cfg_attr(feature = "serde", serde(with = "millis"))]
+    pub min_interval: Duration,
+}
+
+impl Default for AutoScalePolicy {
+    fn default() -> Self {
+        AutoScalePolicy {
+            target_frame_time: Duration::from_millis(33),
+            min_multiplier: 0.5,
+            max_multiplier: 1.0,
+            min_interval: Duration::from_secs(3),
+        }
+    }
+}
+
+/// Stepping of the camera exposure when decision margins collapse, e.g. under
+/// changed arena lighting
+///
+/// When the mean decision margin of the frames with a tag stays below
+/// `trigger_margin` for `dwell`, the detection thread sets each of `exposures`
+/// in turn, measures the mean margin over `frames_per_exposure` frames at each,
+/// and keeps the one that measured best. Frames without a tag don't count
+/// towards the trigger, and count as margin 0 while measuring. Bracketing
+/// never runs while detection is halted; halting detection part way restores
+/// the exposure it started from. Exposures are in driver units, as in
+/// `TagDetector::set_exposure`, and only take effect with auto exposure off.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BracketConfig {
+    /// Candidate exposures, tried in order
+    pub exposures: Vec<f64>,
+    /// Mean decision margin below which the exposure is bracketed
+    pub trigger_margin: f64,
+    /// How long the mean margin must stay below `trigger_margin`, also the
+    /// span of frames it is averaged over
+    #[cfg_attr(feature = "serde", serde(with = "millis"))]
+    pub dwell: Duration,
+    /// Frames measured at each candidate exposure
+    pub frames_per_exposure: u32,
+}
+
+/// Reopening of the frame source after repeated read failures
+///
+/// Attempts are spaced with exponential backoff, starting at `initial_backoff`
+/// and doubling after every failed attempt up to `max_backoff`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReconnectPolicy {
+    /// Consecutive failed reads before the source is reopened
+    pub max_consecutive_errors: u32,
+    /// Wait after the first failed reconnect attempt
+    #[cfg_attr(feature = "serde", serde(with = "millis"))]
+    pub initial_backoff: Duration,
+    /// Longest wait between reconnect attempts
+    #[cfg_attr(feature = "serde", serde(with = "millis"))]
+    pub max_backoff: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            max_consecutive_errors: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+/// One frame preprocessing step
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PreprocessStep {
+    /// Convert color frames to grayscale; grayscale frames pass through
+    Grayscale,
+    /// Gaussian blur with a `ksize` x `ksize` kernel; `ksize` must be odd
+    GaussianBlur { ksize: i32 },
+    /// Contrast limited adaptive histogram equalization over a `tile` x `tile`
+    /// grid; color frames are converted to grayscale first
+    Clahe { clip_limit: f64, tile: i32 },
+    /// Binary threshold: pixels above `value` become white, the rest black
+    Threshold { value: f64 },
+}
+
+/// Frame preprocessing applied by the detection thread before decoding
+///
+/// Steps run in order. The default has no steps and hands frames to the
+/// decoder untouched.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Preprocess {
+    pub steps: Vec<PreprocessStep>,
+}
+
+/// Tuning of the AprilTag detector, which dominates the trade-off between CPU
+/// time and detection range and accuracy
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DetectorParams {
+    /// Factor frames are downsampled by before looking for quads; higher is
+    /// faster but misses small, distant tags. Decoding still uses full resolution
+    pub quad_decimate: f64,
+    /// Standard deviation of the Gaussian blur applied before looking for quads,
+    /// which helps with noisy frames; 0 disables it
+    pub quad_sigma: f64,
+    /// Whether quad edges are refined on the full-resolution frame, recovering
+    /// the corner accuracy lost to decimation
+    pub refine_edges: bool,
+    /// Number of threads each detector uses
+    pub nthreads: u8,
 }
 
-impl Default for OrderingMethod {
+impl Default for DetectorParams {
     fn default() -> Self {
-        OrderingMethod::Nearest
+        DetectorParams {
+            quad_decimate: 2.0,
+            quad_sigma: 0.0,
+            refine_edges: true,
+            nthreads: 1,
+        }
     }
 }
 
+/// How frames are turned before detection, for cameras mounted rotated or
+/// mirrored
+///
+/// Rotations are clockwise as seen in the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FrameOrientation {
+    /// Frames are used as the camera delivers them
+    #[default]
+    Normal,
+    /// Rotated a quarter turn clockwise; width and height swap
+    Rotate90,
+    /// Rotated half a turn, for a camera mounted upside down
+    Rotate180,
+    /// Rotated a quarter turn counterclockwise; width and height swap
+    Rotate270,
+    /// Mirrored left to right
+    FlipHorizontal,
+    /// Mirrored top to bottom
+    FlipVertical,
+}
+
 /// Configuration parameters for TagDetector behavior
+///
+/// With the `serde` feature a config can be stored in and loaded from TOML or
+/// JSON files, see `Config::from_toml_file()`. Durations are stored in
+/// milliseconds.
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
 pub struct Config {
-    /// Whether to operate in single tag detection mode
+    /// Whether only the selected tag is published; with `false`, every tag that
+    /// passed the filters is also published for `TagDetector::all_detections`
     pub single_tag_mode: bool,
     /// Multiplier for camera resolution scaling
     pub resolution_multiplier: f64,
     /// Method for selecting tags when multiple are detected
     pub ordering_method: OrderingMethod,
-    /// Time interval between halt status checks during detection loop
+    /// Longest time a halted detection thread waits before rechecking its flags;
+    /// resuming or stopping detection wakes it immediately
+    #[cfg_attr(feature = "serde", serde(with = "millis"))]
     pub halt_check_interval: Duration,
     /// Tag ID returned when no tags are detected
     pub default_tag_id: i32,
@@ -32,6 +288,96 @@ pub struct Config {
     pub error_tag_id: i32,
     /// Camera buffer size for real-time performance
     pub buffer_size: i32,
+    /// Camera warm-up applied when detection starts
+    pub warmup: WarmupPolicy,
+    /// Optional reduced frame rate mode while no tags are seen
+    pub idle_policy: Option<IdlePolicy>,
+    /// Optional scaling of the camera resolution within a frame time budget;
+    /// `roi` stays in pixels of the unscaled resolution and is scaled along
+    pub auto_scale: Option<AutoScalePolicy>,
+    /// Optional exposure bracketing when decision margins stay low
+    pub auto_bracket: Option<BracketConfig>,
+    /// Tag families decoded in every frame
+    pub families: Vec<TagFamily>,
+    /// Tuning of the AprilTag detector
+    pub detector_params: DetectorParams,
+    /// If set, only tags with these IDs are reported; others are discarded as if never seen
+    pub allowed_ids: Option<HashSet<i32>>,
+    /// Tags with these IDs are discarded as if never seen
+    pub ignored_ids: HashSet<i32>,
+    /// If set, tags whose shortest quad side is shorter than this many pixels
+    /// are discarded as too far away to decode reliably
+    pub min_tag_pixels: Option<f64>,
+    /// If set, tags decoded with a lower decision margin are discarded as dubious
+    pub min_decision_margin: Option<f64>,
+    /// Number of consecutive frames a new tag, or the loss of a tag, must be seen
+    /// in before it is published; 1 publishes every frame's result
+    pub min_consecutive_frames: u32,
+    /// Number of frames the published tag ID is voted over: the ID selected
+    /// most often among the last this many debounced frames is published, ties
+    /// going to the most recent. 1 publishes every frame's selection
+    pub smoothing_window: usize,
+    /// Oldest tag ID the breakers made by `TagDetector::breaker_for` and its
+    /// siblings act on; an older ID counts as no tag. Must exceed the frame
+    /// period, including the reduced rate of an idle policy
+    #[cfg_attr(feature = "serde", serde(with = "millis"))]
+    pub breaker_max_age: Duration,
+    /// Number of consecutive failed reads after which `TagDetector::camera_state`
+    /// reports the camera as disconnected
+    pub disconnect_after: u32,
+    /// Reopening of the camera after read failures; `None` keeps publishing
+    /// the error ID until detection is restarted
+    pub reconnect: Option<ReconnectPolicy>,
+    /// Whether `TagDetector::open_camera` and `TagDetector::set_source` switch
+    /// the running detection thread to the new camera; with `false` they return
+    /// `UpicError::DetectionRunning` while detection runs
+    pub hot_swap: bool,
+    /// Region of interest in full-frame pixels; detection only runs inside it,
+    /// while reported coordinates stay in full-frame pixel space
+    #[cfg_attr(feature = "serde", serde(default, with = "roi"))]
+    pub roi: Option<Rect>,
+    /// Frame rate the detection loop is paced to; `None` runs it as fast as
+    /// frames can be read and decoded
+    pub target_fps: Option<f64>,
+    /// Whether the detection thread shows each frame with the detected tags
+    /// outlined in a window; needs a display
+    pub show_preview: bool,
+    /// If set, the frame behind each switch to `error_tag_id` is saved as a PNG
+    /// file in this directory, for debugging missed detections after a match
+    pub error_frame_dir: Option<PathBuf>,
+    /// Preprocessing applied to every frame before tags are decoded
+    pub preprocess: Preprocess,
+    /// Whether frames are preprocessed and converted to grayscale through
+    /// OpenCL, falling back to the CPU with a warning when no OpenCL device is
+    /// available; `DetectionStats::opencl` tells which path is used
+    pub use_opencl: bool,
+    /// Whether frames are undistorted with the intrinsics given to
+    /// `TagDetector::set_intrinsics` before detection; reported coordinates are
+    /// then in undistorted pixel space
+    pub undistort: bool,
+    /// Rotation or flip applied to every frame, after undistortion and before
+    /// detection; the ROI, frame center, preview and reported coordinates are
+    /// all in the oriented frame
+    pub orientation: FrameOrientation,
+    /// Horizontal field of view of the camera in degrees, for turning a tag's
+    /// pixel offset into a bearing with `TagDetector::tag_bearing_deg`
+    pub horizontal_fov_deg: f64,
+    /// Weight of each new frame's estimate in the moving average behind
+    /// `TagDetector::tag_distance_m`; 1 disables smoothing
+    pub distance_smoothing: f64,
+    /// Number of detections kept for `TagDetector::recent_tags` and
+    /// `TagDetector::last_seen`, one per tag and frame; 0 keeps none
+    pub history_capacity: usize,
+    /// Number of tags discarded by the filters kept for
+    /// `TagDetector::rejections`; 0 keeps none
+    pub rejection_capacity: usize,
+    /// Name of the detection thread, for finding it in `top -H` or pinning it;
+    /// Linux shows only the first 15 bytes
+    pub thread_name: String,
+    /// Nice value the detection thread runs at, from -20 (most favored) to 19;
+    /// needs the `thread-priority` feature on Linux and is ignored with a
+    /// warning elsewhere. Values below the current one need `CAP_SYS_NICE`
+    pub thread_priority: Option<i32>,
 }
 
 impl Default for Config {
@@ -44,6 +390,837 @@ impl Default for Config {
             default_tag_id: -1,
             error_tag_id: -10,
             buffer_size: 2,
+            warmup: WarmupPolicy::default(),
+            idle_policy: None,
+            auto_scale: None,
+            auto_bracket: None,
+            families: vec![TagFamily::Tag36h11],
+            detector_params: DetectorParams::default(),
+            allowed_ids: None,
+            ignored_ids: HashSet::new(),
+            min_tag_pixels: None,
+            min_decision_margin: None,
+            min_consecutive_frames: 1,
+            smoothing_window: 1,
+            breaker_max_age: Duration::from_millis(500),
+            disconnect_after: 10,
+            reconnect: Some(ReconnectPolicy::default()),
+            hot_swap: true,
+            roi: None,
+            target_fps: Some(30.0),
+            show_preview: false,
+            error_frame_dir: None,
+            preprocess: Preprocess::default(),
+            use_opencl: false,
+            undistort: false,
+            orientation: FrameOrientation::Normal,
+            horizontal_fov_deg: 60.0,
+            distance_smoothing: 0.3,
+            history_capacity: 256,
+            rejection_capacity: 64,
+            thread_name: "upic-tag-detect".to_string(),
+            thread_priority: None,
+        }
+    }
+}
+
+impl Config {
+    /// Start building a config from the defaults
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder {
+            config: Config::default(),
+        }
+    }
+
+    /// Check that all values are in range and consistent
+    ///
+    /// Without the `opencv` feature, preprocessing, undistortion, orientations
+    /// other than `Normal`, the preview and error frames are refused with
+    /// `UpicError::FeatureDisabled`.
+    pub fn validate(&self) -> Result<(), UpicError> {
+        let invalid = |message: String| Err(UpicError::InvalidConfig(message));
+        if !(self.resolution_multiplier.is_finite() && self.resolution_multiplier > 0.0) {
+            return invalid(format!(
+                "resolution_multiplier must be positive, got {}",
+                self.resolution_multiplier
+            ));
+        }
+        if self.buffer_size < 1 {
+            return invalid(format!(
+                "buffer_size must be at least 1, got {}",
+                self.buffer_size
+            ));
+        }
+        if self.halt_check_interval.is_zero() {
+            return invalid("halt_check_interval must be positive".to_string());
+        }
+        if self.default_tag_id == self.error_tag_id {
+            return invalid(format!(
+                "default_tag_id and error_tag_id must differ, both are {}",
+                self.default_tag_id
+            ));
+        }
+        if self.families.is_empty() {
+            return invalid("families must not be empty".to_string());
+        }
+        let params = &self.detector_params;
+        if !(params.quad_decimate.is_finite() && params.quad_decimate >= 1.0) {
+            return invalid(format!(
+                "detector_params.quad_decimate must be at least 1, got {}",
+                params.quad_decimate
+            ));
+        }
+        if !(params.quad_sigma.is_finite() && params.quad_sigma >= 0.0) {
+            return invalid(format!(
+                "detector_params.quad_sigma must not be negative, got {}",
+                params.quad_sigma
+            ));
+        }
+        if params.nthreads < 1 {
+            return invalid("detector_params.nthreads must be at least 1".to_string());
+        }
+        if self.min_consecutive_frames < 1 {
+            return invalid("min_consecutive_frames must be at least 1".to_string());
+        }
+        if self.smoothing_window < 1 {
+            return invalid("smoothing_window must be at least 1".to_string());
+        }
+        if self.breaker_max_age.is_zero() {
+            return invalid("breaker_max_age must be positive".to_string());
+        }
+        if let Some(min_tag_pixels) = self.min_tag_pixels
+            && !(min_tag_pixels.is_finite() && min_tag_pixels > 0.0)
+        {
+            return invalid(format!(
+                "min_tag_pixels must be positive, got {}",
+                min_tag_pixels
+            ));
+        }
+        if let Some(min_decision_margin) = self.min_decision_margin
+            && !(min_decision_margin.is_finite() && min_decision_margin >= 0.0)
+        {
+            return invalid(format!(
+                "min_decision_margin must not be negative, got {}",
+                min_decision_margin
+            ));
+        }
+        if self.disconnect_after < 1 {
+            return invalid("disconnect_after must be at least 1".to_string());
+        }
+        if let Some(reconnect) = self.reconnect {
+            if reconnect.max_consecutive_errors < 1 {
+                return invalid("reconnect.max_consecutive_errors must be at least 1".to_string());
+            }
+            if reconnect.max_backoff < reconnect.initial_backoff {
+                return invalid(format!(
+                    "reconnect.max_backoff {:?} is shorter than initial_backoff {:?}",
+                    reconnect.max_backoff, reconnect.initial_backoff
+                ));
+            }
+        }
+        if let Some(roi) = self.roi
+            && (roi.x < 0 || roi.y < 0 || roi.width <= 0 || roi.height <= 0)
+        {
+            return invalid(format!(
+                "roi must be a non-empty region of the frame, got {:?}",
+                roi
+            ));
+        }
+        if let Some(target_fps) = self.target_fps
+            && !(target_fps.is_finite() && target_fps > 0.0)
+        {
+            return invalid(format!("target_fps must be positive, got {}", target_fps));
+        }
+        if let Some(idle_policy) = self.idle_policy {
+            if !(idle_policy.reduced_fps.is_finite() && idle_policy.reduced_fps > 0.0) {
+                return invalid(format!(
+                    "idle_policy.reduced_fps must be positive, got {}",
+                    idle_policy.reduced_fps
+                ));
+            }
+            if let Some(multiplier) = idle_policy.resolution_multiplier
+                && !(multiplier > 0.0 && multiplier <= 1.0)
+            {
+                return invalid(format!(
+                    "idle_policy.resolution_multiplier must be in (0, 1], got {}",
+                    multiplier
+                ));
+            }
+        }
+        if let Some(auto_scale) = self.auto_scale {
+            if auto_scale.target_frame_time.is_zero() {
+                return invalid("auto_scale.target_frame_time must not be zero".to_string());
+            }
+            if !(auto_scale.min_multiplier.is_finite()
+                && auto_scale.min_multiplier > 0.0
+                && auto_scale.max_multiplier.is_finite()
+                && auto_scale.min_multiplier <= auto_scale.max_multiplier)
+            {
+                return invalid(format!(
+                    "auto_scale multipliers must be positive with min <= max, got {} and {}",
+                    auto_scale.min_multiplier, auto_scale.max_multiplier
+                ));
+            }
+        }
+        if let Some(bracket) = &self.auto_bracket {
+            if bracket.exposures.len() < 2 || !bracket.exposures.iter().all(|e| e.is_finite()) {
+                return invalid(format!(
+                    "auto_bracket.exposures must hold at least two finite values, got {:?}",
+                    bracket.exposures
+                ));
+            }
+            if !(bracket.trigger_margin.is_finite() && bracket.trigger_margin > 0.0) {
+                return invalid(format!(
+                    "auto_bracket.trigger_margin must be positive, got {}",
+                    bracket.trigger_margin
+                ));
+            }
+            if bracket.dwell.is_zero() {
+                return invalid("auto_bracket.dwell must not be zero".to_string());
+            }
+            if bracket.frames_per_exposure == 0 {
+                return invalid("auto_bracket.frames_per_exposure must not be zero".to_string());
+            }
+        }
+        if !(self.horizontal_fov_deg > 0.0 && self.horizontal_fov_deg < 180.0) {
+            return invalid(format!(
+                "horizontal_fov_deg must be within 0-180, got {}",
+                self.horizontal_fov_deg
+            ));
+        }
+        if !(self.distance_smoothing > 0.0 && self.distance_smoothing <= 1.0) {
+            return invalid(format!(
+                "distance_smoothing must be within 0-1 and not 0, got {}",
+                self.distance_smoothing
+            ));
+        }
+        if self.thread_name.contains('\0') {
+            return invalid("thread_name must not contain NUL bytes".to_string());
+        }
+        if let Some(priority) = self.thread_priority
+            && !(-20..=19).contains(&priority)
+        {
+            return invalid(format!(
+                "thread_priority must be within -20 to 19, got {}",
+                priority
+            ));
+        }
+        for step in &self.preprocess.steps {
+            match *step {
+                PreprocessStep::GaussianBlur { ksize } if ksize < 1 || ksize % 2 == 0 => {
+                    return invalid(format!(
+                        "GaussianBlur ksize must be odd and positive, got {}",
+                        ksize
+                    ));
+                }
+                PreprocessStep::Clahe { clip_limit, tile }
+                    if !(clip_limit.is_finite() && clip_limit > 0.0) || tile < 1 =>
+                {
+                    return invalid(format!(
+                        "Clahe needs a positive clip_limit and tile, got {} and {}",
+                        clip_limit, tile
+                    ));
+                }
+                PreprocessStep::Threshold { value } if !(0.0..=255.0).contains(&value) => {
+                    return invalid(format!(
+                        "Threshold value must be within 0-255, got {}",
+                        value
+                    ));
+                }
+                _ => {}
+            }
+        }
+        #[cfg(not(feature = "opencv"))]
+        if !self.preprocess.steps.is_empty()
+            || self.undistort
+            || self.orientation != FrameOrientation::Normal
+            || self.show_preview
+            || self.error_frame_dir.is_some()
+        {
+            // Caught here rather than failing every frame once detection runs
+            return Err(UpicError::FeatureDisabled { feature: "opencv" });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Config {
+    /// Load a config from a TOML file
+    ///
+    /// Durations are given in milliseconds and `roi` as `[x, y, width, height]`.
+    /// Every field except the optional ones must be present.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File to read
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::Io` if the file can't be read, and
+    /// `UpicError::InvalidConfig` if it doesn't parse, has unknown fields or
+    /// fails the same validation as `ConfigBuilder::build()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use upic_rs::tag_detector::Config;
+    ///
+    /// let config = Config::from_toml_file("detector.toml")?;
+    /// # Ok::<(), upic_rs::UpicError>(())
+    /// ```
+    pub fn from_toml_file<P: AsRef<std::path::Path>>(path: P) -> Result<Config, UpicError> {
+        let text = std::fs::read_to_string(path).map_err(UpicError::Io)?;
+        let config: Config =
+            toml::from_str(&text).map_err(|e| UpicError::InvalidConfig(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Save the config to a TOML file, replacing it if it exists
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::InvalidConfig` if the config holds an
+    /// `OrderingMethod::Custom`, which can't be stored, and `UpicError::Io` if
+    /// the file can't be written.
+    pub fn to_toml_file<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), UpicError> {
+        let text = toml::to_string(self).map_err(|e| UpicError::InvalidConfig(e.to_string()))?;
+        std::fs::write(path, text).map_err(UpicError::Io)
+    }
+
+    /// Load a config from a JSON file
+    ///
+    /// Uses the same layout as `Config::from_toml_file()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::Io` if the file can't be read, and
+    /// `UpicError::InvalidConfig` if it doesn't parse, has unknown fields or
+    /// fails validation.
+    pub fn from_json_file<P: AsRef<std::path::Path>>(path: P) -> Result<Config, UpicError> {
+        let text = std::fs::read_to_string(path).map_err(UpicError::Io)?;
+        let config: Config =
+            serde_json::from_str(&text).map_err(|e| UpicError::InvalidConfig(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Save the config to a pretty-printed JSON file, replacing it if it exists
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::InvalidConfig` if the config holds an
+    /// `OrderingMethod::Custom`, and `UpicError::Io` if the file can't be written.
+    pub fn to_json_file<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), UpicError> {
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|e| UpicError::InvalidConfig(e.to_string()))?;
+        std::fs::write(path, text).map_err(UpicError::Io)
+    }
+}
+
+/// `Duration` stored as whole milliseconds
+#[cfg(feature = "serde")]
+mod millis {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        duration: &Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let millis = u64::try_from(duration.as_millis()).map_err(serde::ser::Error::custom)?;
+        serializer.serialize_u64(millis)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+/// Region of interest stored as `[x, y, width, height]`
+#[cfg(feature = "serde")]
+mod roi {
+    use super::Rect;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        roi: &Option<Rect>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        roi.map(|r| [r.x, r.y, r.width, r.height])
+            .serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Rect>, D::Error> {
+        let roi = Option::<[i32; 4]>::deserialize(deserializer)?;
+        Ok(roi.map(|[x, y, width, height]| Rect::new(x, y, width, height)))
+    }
+}
+
+/// Check that a region of interest lies within a frame of `frame_size` pixels.
+pub(crate) fn check_roi(roi: &Rect, frame_size: (f64, f64)) -> Result<(), UpicError> {
+    let (width, height) = frame_size;
+    if (roi.x + roi.width) as f64 > width || (roi.y + roi.height) as f64 > height {
+        return Err(UpicError::InvalidConfig(format!(
+            "roi {:?} exceeds the {}x{} frame",
+            roi, width, height
+        )));
+    }
+    Ok(())
+}
+
+/// Builder for a validated `Config`
+#[derive(Debug, Clone)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    /// Set whether to operate in single tag detection mode
+    pub fn single_tag_mode(mut self, single_tag_mode: bool) -> Self {
+        self.config.single_tag_mode = single_tag_mode;
+        self
+    }
+
+    /// Set the camera resolution multiplier; must be positive
+    pub fn resolution_multiplier(mut self, resolution_multiplier: f64) -> Self {
+        self.config.resolution_multiplier = resolution_multiplier;
+        self
+    }
+
+    /// Set the tag selection method
+    pub fn ordering_method(mut self, ordering_method: OrderingMethod) -> Self {
+        self.config.ordering_method = ordering_method;
+        self
+    }
+
+    /// Set the halted thread's recheck interval; must be positive
+    pub fn halt_check_interval(mut self, halt_check_interval: Duration) -> Self {
+        self.config.halt_check_interval = halt_check_interval;
+        self
+    }
+
+    /// Set the tag ID published when no tags are detected
+    pub fn default_tag_id(mut self, default_tag_id: i32) -> Self {
+        self.config.default_tag_id = default_tag_id;
+        self
+    }
+
+    /// Set the tag ID published on camera errors; must differ from the default ID
+    pub fn error_tag_id(mut self, error_tag_id: i32) -> Self {
+        self.config.error_tag_id = error_tag_id;
+        self
+    }
+
+    /// Set the camera buffer size; must be at least 1
+    pub fn buffer_size(mut self, buffer_size: i32) -> Self {
+        self.config.buffer_size = buffer_size;
+        self
+    }
+
+    /// Set the camera warm-up policy
+    pub fn warmup(mut self, warmup: WarmupPolicy) -> Self {
+        self.config.warmup = warmup;
+        self
+    }
+
+    /// Set the idle policy
+    pub fn idle_policy(mut self, idle_policy: Option<IdlePolicy>) -> Self {
+        self.config.idle_policy = idle_policy;
+        self
+    }
+
+    /// Set the resolution auto-scaling policy
+    pub fn auto_scale(mut self, auto_scale: Option<AutoScalePolicy>) -> Self {
+        self.config.auto_scale = auto_scale;
+        self
+    }
+
+    /// Set the exposure bracketing
+    pub fn auto_bracket(mut self, auto_bracket: Option<BracketConfig>) -> Self {
+        self.config.auto_bracket = auto_bracket;
+        self
+    }
+
+    /// Set the tag families to decode; must not be empty
+    pub fn families(mut self, families: Vec<TagFamily>) -> Self {
+        self.config.families = families;
+        self
+    }
+
+    /// Set the AprilTag detector tuning; `quad_decimate` must be at least 1,
+    /// `quad_sigma` must not be negative and `nthreads` must be at least 1
+    pub fn detector_params(mut self, detector_params: DetectorParams) -> Self {
+        self.config.detector_params = detector_params;
+        self
+    }
+
+    /// Set the tag IDs to report, or `None` to report all IDs
+    pub fn allowed_ids(mut self, allowed_ids: Option<HashSet<i32>>) -> Self {
+        self.config.allowed_ids = allowed_ids;
+        self
+    }
+
+    /// Set the tag IDs to discard
+    pub fn ignored_ids(mut self, ignored_ids: HashSet<i32>) -> Self {
+        self.config.ignored_ids = ignored_ids;
+        self
+    }
+
+    /// Set the shortest quad side in pixels a tag needs to be reported, or `None`
+    /// for no limit; must be positive
+    pub fn min_tag_pixels(mut self, min_tag_pixels: Option<f64>) -> Self {
+        self.config.min_tag_pixels = min_tag_pixels;
+        self
+    }
+
+    /// Set the decision margin a tag needs to be reported, or `None` for no
+    /// limit; must not be negative
+    pub fn min_decision_margin(mut self, min_decision_margin: Option<f64>) -> Self {
+        self.config.min_decision_margin = min_decision_margin;
+        self
+    }
+
+    /// Set the number of consecutive frames needed to publish a change; must be at least 1
+    pub fn min_consecutive_frames(mut self, min_consecutive_frames: u32) -> Self {
+        self.config.min_consecutive_frames = min_consecutive_frames;
+        self
+    }
+
+    /// Set the number of frames the published tag ID is majority-voted over; 1
+    /// disables smoothing
+    pub fn smoothing_window(mut self, smoothing_window: usize) -> Self {
+        self.config.smoothing_window = smoothing_window;
+        self
+    }
+
+    /// Set the oldest tag ID breakers act on; must be positive
+    pub fn breaker_max_age(mut self, breaker_max_age: Duration) -> Self {
+        self.config.breaker_max_age = breaker_max_age;
+        self
+    }
+
+    /// Set the number of consecutive failed reads that count as a disconnect;
+    /// must be at least 1
+    pub fn disconnect_after(mut self, disconnect_after: u32) -> Self {
+        self.config.disconnect_after = disconnect_after;
+        self
+    }
+
+    /// Set the reconnect policy
+    pub fn reconnect(mut self, reconnect: Option<ReconnectPolicy>) -> Self {
+        self.config.reconnect = reconnect;
+        self
+    }
+
+    /// Set whether opening a camera while detection runs switches detection to it
+    pub fn hot_swap(mut self, hot_swap: bool) -> Self {
+        self.config.hot_swap = hot_swap;
+        self
+    }
+
+    /// Set the region of interest, or `None` to detect over the full frame
+    pub fn roi(mut self, roi: Option<Rect>) -> Self {
+        self.config.roi = roi;
+        self
+    }
+
+    /// Set the detection loop's frame rate, or `None` to run it flat out; must be positive
+    pub fn target_fps(mut self, target_fps: Option<f64>) -> Self {
+        self.config.target_fps = target_fps;
+        self
+    }
+
+    /// Set the frame preprocessing steps
+    pub fn preprocess(mut self, preprocess: Preprocess) -> Self {
+        self.config.preprocess = preprocess;
+        self
+    }
+
+    /// Set whether frames are preprocessed through OpenCL when available
+    pub fn use_opencl(mut self, use_opencl: bool) -> Self {
+        self.config.use_opencl = use_opencl;
+        self
+    }
+
+    /// Set whether frames are undistorted before detection
+    pub fn undistort(mut self, undistort: bool) -> Self {
+        self.config.undistort = undistort;
+        self
+    }
+
+    /// Set the rotation or flip applied to frames before detection
+    pub fn orientation(mut self, orientation: FrameOrientation) -> Self {
+        self.config.orientation = orientation;
+        self
+    }
+
+    /// Set the camera's horizontal field of view in degrees; must be within 0-180
+    pub fn horizontal_fov_deg(mut self, horizontal_fov_deg: f64) -> Self {
+        self.config.horizontal_fov_deg = horizontal_fov_deg;
+        self
+    }
+
+    /// Set the weight of new distance estimates in their moving average; must
+    /// be within 0-1 and not 0, with 1 disabling smoothing
+    pub fn distance_smoothing(mut self, distance_smoothing: f64) -> Self {
+        self.config.distance_smoothing = distance_smoothing;
+        self
+    }
+
+    /// Set the number of detections kept in the history, or 0 to keep none
+    pub fn history_capacity(mut self, history_capacity: usize) -> Self {
+        self.config.history_capacity = history_capacity;
+        self
+    }
+
+    /// Set the number of rejected tags kept for tuning the filters, or 0 to keep none
+    pub fn rejection_capacity(mut self, rejection_capacity: usize) -> Self {
+        self.config.rejection_capacity = rejection_capacity;
+        self
+    }
+
+    /// Set whether the detection thread shows a preview window
+    pub fn show_preview(mut self, show_preview: bool) -> Self {
+        self.config.show_preview = show_preview;
+        self
+    }
+
+    /// Set the directory frames are saved to when the error ID is published, or
+    /// `None` to save nothing
+    pub fn error_frame_dir(mut self, error_frame_dir: Option<PathBuf>) -> Self {
+        self.config.error_frame_dir = error_frame_dir;
+        self
+    }
+
+    /// Set the name of the detection thread
+    pub fn thread_name(mut self, thread_name: String) -> Self {
+        self.config.thread_name = thread_name;
+        self
+    }
+
+    /// Set the nice value of the detection thread, or `None` to inherit the
+    /// caller's; must be within -20 to 19
+    pub fn thread_priority(mut self, thread_priority: Option<i32>) -> Self {
+        self.config.thread_priority = thread_priority;
+        self
+    }
+
+    /// Validate and return the config
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::InvalidConfig` describing the first invalid value, or
+    /// `UpicError::FeatureDisabled` for an option the build can't provide.
+    pub fn build(self) -> Result<Config, UpicError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_validation() {
+        let config = Config::builder()
+            .resolution_multiplier(0.75)
+            .buffer_size(1)
+            .build()
+            .unwrap();
+        assert_eq!(config.resolution_multiplier, 0.75);
+        assert_eq!(config.buffer_size, 1);
+
+        let bracket = BracketConfig {
+            exposures: vec![50.0, 100.0],
+            trigger_margin: 40.0,
+            dwell: Duration::from_secs(2),
+            frames_per_exposure: 5,
+        };
+        assert!(
+            Config::builder()
+                .auto_bracket(Some(bracket.clone()))
+                .build()
+                .is_ok()
+        );
+        let invalid = [
+            Config::builder().resolution_multiplier(0.0),
+            Config::builder().buffer_size(0),
+            Config::builder().halt_check_interval(Duration::ZERO),
+            Config::builder().default_tag_id(-5).error_tag_id(-5),
+            Config::builder().families(Vec::new()),
+            Config::builder().min_consecutive_frames(0),
+            Config::builder().smoothing_window(0),
+            Config::builder().breaker_max_age(Duration::ZERO),
+            Config::builder().disconnect_after(0),
+            Config::builder().detector_params(DetectorParams {
+                quad_decimate: 0.5,
+                ..DetectorParams::default()
+            }),
+            Config::builder().detector_params(DetectorParams {
+                quad_sigma: -1.0,
+                ..DetectorParams::default()
+            }),
+            Config::builder().detector_params(DetectorParams {
+                nthreads: 0,
+                ..DetectorParams::default()
+            }),
+            Config::builder().min_tag_pixels(Some(0.0)),
+            Config::builder().min_decision_margin(Some(-1.0)),
+            Config::builder().min_decision_margin(Some(f64::NAN)),
+            Config::builder().target_fps(Some(0.0)),
+            Config::builder().horizontal_fov_deg(180.0),
+            Config::builder().distance_smoothing(0.0),
+            Config::builder().thread_name("upic\0detect".to_string()),
+            Config::builder().thread_priority(Some(20)),
+            Config::builder().preprocess(Preprocess {
+                steps: vec![PreprocessStep::GaussianBlur { ksize: 4 }],
+            }),
+            Config::builder().preprocess(Preprocess {
+                steps: vec![PreprocessStep::Clahe {
+                    clip_limit: 2.0,
+                    tile: 0,
+                }],
+            }),
+            Config::builder().roi(Some(Rect::new(10, 10, 0, 20))),
+            Config::builder().roi(Some(Rect::new(-1, 10, 20, 20))),
+            Config::builder().reconnect(Some(ReconnectPolicy {
+                max_backoff: Duration::from_millis(10),
+                ..ReconnectPolicy::default()
+            })),
+            Config::builder().idle_policy(Some(IdlePolicy {
+                after: Duration::from_secs(1),
+                reduced_fps: 0.0,
+                wake_on_detection: true,
+                resolution_multiplier: None,
+            })),
+            Config::builder().idle_policy(Some(IdlePolicy {
+                after: Duration::from_secs(1),
+                reduced_fps: 2.0,
+                wake_on_detection: true,
+                resolution_multiplier: Some(1.5),
+            })),
+            Config::builder().auto_scale(Some(AutoScalePolicy {
+                min_multiplier: 0.0,
+                ..AutoScalePolicy::default()
+            })),
+            Config::builder().auto_scale(Some(AutoScalePolicy {
+                min_multiplier: 1.5,
+                max_multiplier: 1.0,
+                ..AutoScalePolicy::default()
+            })),
+            Config::builder().auto_bracket(Some(BracketConfig {
+                exposures: vec![100.0],
+                ..bracket.clone()
+            })),
+            Config::builder().auto_bracket(Some(BracketConfig {
+                exposures: vec![100.0, f64::NAN],
+                ..bracket.clone()
+            })),
+            Config::builder().auto_bracket(Some(BracketConfig {
+                trigger_margin: 0.0,
+                ..bracket.clone()
+            })),
+            Config::builder().auto_bracket(Some(BracketConfig {
+                dwell: Duration::ZERO,
+                ..bracket.clone()
+            })),
+            Config::builder().auto_bracket(Some(BracketConfig {
+                frames_per_exposure: 0,
+                ..bracket.clone()
+            })),
+        ];
+        for builder in invalid {
+            assert!(matches!(builder.build(), Err(UpicError::InvalidConfig(_))));
         }
     }
+
+    #[test]
+    fn test_roi_bounds() {
+        let frame_size = (1920.0, 1080.0);
+        assert!(check_roi(&Rect::new(0, 540, 1920, 540), frame_size).is_ok());
+        assert!(check_roi(&Rect::new(0, 541, 1920, 540), frame_size).is_err());
+        assert!(check_roi(&Rect::new(100, 0, 1900, 100), frame_size).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_config_file_round_trip() {
+        let dir = std::env::temp_dir().join(format!("upic-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = Config::builder()
+            .ordering_method(OrderingMethod::ById(3))
+            .halt_check_interval(Duration::from_millis(250))
+            .families(vec![TagFamily::Tag36h11, TagFamily::Tag25h9])
+            .ignored_ids(HashSet::from([7]))
+            .roi(Some(Rect::new(0, 120, 640, 240)))
+            .reconnect(Some(ReconnectPolicy::default()))
+            .auto_bracket(Some(BracketConfig {
+                exposures: vec![50.0, 100.0, 200.0],
+                trigger_margin: 40.0,
+                dwell: Duration::from_millis(1500),
+                frames_per_exposure: 4,
+            }))
+            .warmup(WarmupPolicy {
+                discard_frames: 5,
+                settle: Some(SettleSpec {
+                    max_wait: Duration::from_secs(2),
+                    brightness_delta: 3.0,
+                }),
+            })
+            .build()
+            .unwrap();
+
+        let toml_path = dir.join("config.toml");
+        config.to_toml_file(&toml_path).unwrap();
+        let text = std::fs::read_to_string(&toml_path).unwrap();
+        assert!(text.contains("halt_check_interval = 250\n"));
+        assert!(text.contains("roi = [0, 120, 640, 240]\n"));
+        let loaded = Config::from_toml_file(&toml_path).unwrap();
+        assert_eq!(format!("{:?}", loaded), format!("{:?}", config));
+
+        let json_path = dir.join("config.json");
+        config.to_json_file(&json_path).unwrap();
+        let loaded = Config::from_json_file(&json_path).unwrap();
+        assert_eq!(format!("{:?}", loaded), format!("{:?}", config));
+
+        // Unknown fields are rejected, not ignored
+        std::fs::write(&toml_path, format!("frame_rate = 30\n{}", text)).unwrap();
+        assert!(matches!(
+            Config::from_toml_file(&toml_path),
+            Err(UpicError::InvalidConfig(_))
+        ));
+        // Loaded configs are validated like built ones
+        std::fs::write(
+            &toml_path,
+            text.replace("buffer_size = 2", "buffer_size = 0"),
+        )
+        .unwrap();
+        assert!(matches!(
+            Config::from_toml_file(&toml_path),
+            Err(UpicError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            Config::from_toml_file(dir.join("missing.toml")),
+            Err(UpicError::Io(_))
+        ));
+
+        // Custom selectors are code and can't be stored
+        let custom = Config::builder()
+            .ordering_method(OrderingMethod::Custom(Arc::new(|_| None)))
+            .build()
+            .unwrap();
+        assert!(matches!(
+            custom.to_json_file(&json_path),
+            Err(UpicError::InvalidConfig(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
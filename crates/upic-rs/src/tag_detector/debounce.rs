@@ -0,0 +1,130 @@
+use super::detection::TagDetection;
+
+/// Holds back changes of the selected tag until they persist for enough frames.
+///
+/// Both a new tag and the loss of a tag must be seen in `min_frames`
+/// consecutive frames before they are published; a streak restarts whenever
+/// the selected ID changes mid-streak.
+pub(crate) struct Debouncer {
+    min_frames: u32,
+    published: Option<TagDetection>,
+    candidate: Option<i32>,
+    streak: u32,
+}
+
+impl Debouncer {
+    pub(crate) fn new(min_frames: u32) -> Self {
+        Debouncer {
+            min_frames,
+            published: None,
+            candidate: None,
+            streak: 0,
+        }
+    }
+
+    /// Change the number of frames a change must persist; a pending streak is kept.
+    pub(crate) fn set_min_frames(&mut self, min_frames: u32) {
+        self.min_frames = min_frames;
+    }
+
+    /// Record the detection selected in one frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `selected` - The detection picked by the ordering method, or `None`
+    ///   when no tag was visible.
+    ///
+    /// # Returns
+    ///
+    /// The detection to publish. While the published tag is still selected it is
+    /// refreshed with the new frame's data; while a change is pending the last
+    /// published detection is held.
+    pub(crate) fn observe(&mut self, selected: Option<TagDetection>) -> Option<TagDetection> {
+        let id = selected.map(|d| d.id);
+        if id == self.published.map(|d| d.id) {
+            self.published = selected;
+            self.candidate = None;
+            self.streak = 0;
+            return self.published;
+        }
+
+        if self.streak > 0 && self.candidate == id {
+            self.streak += 1;
+        } else {
+            self.candidate = id;
+            self.streak = 1;
+        }
+
+        if self.streak >= self.min_frames {
+            self.published = selected;
+            self.candidate = None;
+            self.streak = 0;
+        }
+        self.published
+    }
+
+    /// Forget the published tag and any pending change.
+    ///
+    /// Used when the published ID was replaced by a sentinel, so a tag seen
+    /// afterwards has to build up a full streak again.
+    pub(crate) fn reset(&mut self) {
+        self.published = None;
+        self.candidate = None;
+        self.streak = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tag_detector::config::TagFamily;
+
+    fn detection(id: i32) -> Option<TagDetection> {
+        Some(TagDetection {
+            id,
+            family: TagFamily::Tag16h5,
+            corners: [[0.0, 0.0]; 4],
+            center: [0.0, 0.0],
+            decision_margin: 30.0,
+            hamming: 0,
+        })
+    }
+
+    fn published_ids(debouncer: &mut Debouncer, frames: &[Option<i32>]) -> Vec<Option<i32>> {
+        frames
+            .iter()
+            .map(|id| {
+                debouncer
+                    .observe(id.and_then(detection))
+                    .map(|detection| detection.id)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_single_frame_publishes_immediately() {
+        let mut debouncer = Debouncer::new(1);
+        let frames = [Some(4), None, Some(7), Some(4)];
+        assert_eq!(published_ids(&mut debouncer, &frames), frames);
+    }
+
+    #[test]
+    fn test_requires_consecutive_frames() {
+        let mut debouncer = Debouncer::new(3);
+
+        // A single-frame false positive never gets published
+        let frames = [Some(4), None, Some(4), Some(4), Some(4), Some(4)];
+        let expected = [None, None, None, None, Some(4), Some(4)];
+        assert_eq!(published_ids(&mut debouncer, &frames), expected);
+
+        // Losing the tag also takes three frames; the streak restarts on a new ID
+        let frames = [None, None, Some(7), None, None, None];
+        let expected = [Some(4), Some(4), Some(4), Some(4), Some(4), None];
+        assert_eq!(published_ids(&mut debouncer, &frames), expected);
+
+        debouncer.observe(detection(7));
+        debouncer.observe(detection(7));
+        debouncer.reset();
+        assert_eq!(debouncer.observe(detection(7)), None);
+    }
+}
@@ -0,0 +1,324 @@
+use apriltag::{Detector, DetectorBuilder, Family, Image};
+#[cfg(feature = "opencv")]
+use opencv::boxed_ref::BoxedRef;
+#[cfg(feature = "opencv")]
+use opencv::core::{Mat, ToInputArray};
+#[cfg(feature = "opencv")]
+use opencv::imgproc;
+#[cfg(feature = "opencv")]
+use opencv::prelude::*;
+
+use super::config::{DetectorParams, TagFamily};
+use super::detection::TagDetection;
+use super::frame::{Frame, Rect};
+use crate::error::UpicError;
+
+impl TagFamily {
+    /// The apriltag family definition
+    fn family(self) -> Family {
+        match self {
+            TagFamily::Tag36h11 => Family::tag_36h11(),
+            TagFamily::Tag25h9 => Family::tag_25h9(),
+            TagFamily::Tag16h5 => Family::tag_16h5(),
+            TagFamily::TagStandard41h12 => Family::tag_standard_41h12(),
+            TagFamily::TagCircle21h7 => Family::tag_circle_21h7(),
+        }
+    }
+}
+
+/// Decodes AprilTags in frames
+///
+/// Wraps one apriltag detector per family and the conversion from OpenCV
+/// frames to the grayscale images they work on. The conversion buffers are
+/// kept across frames and only reallocated when the frame size changes.
+pub(crate) struct TagDecoder {
+    detectors: Vec<(TagFamily, Detector)>,
+    #[cfg(feature = "opencv")]
+    gray: Mat,
+    /// Image handed to the detectors, with its `(width, height)`
+    image: Option<((usize, usize), Image)>,
+}
+
+impl TagDecoder {
+    /// Create a decoder for the given families.
+    ///
+    /// Each family gets its own detector, tuned with `params`, so detections can
+    /// be attributed to their family; all of them run on the same grayscale image.
+    pub(crate) fn new(families: &[TagFamily], params: &DetectorParams) -> Result<Self, UpicError> {
+        let detectors = families
+            .iter()
+            .map(|&family| {
+                DetectorBuilder::new()
+                    .add_family_bits(family.family(), 1)
+                    .set_decimation(params.quad_decimate as f32)
+                    .set_sigma(params.quad_sigma as f32)
+                    .set_refine_edges(params.refine_edges)
+                    .set_thread_number(params.nthreads)
+                    .build()
+                    .map(|detector| (family, detector))
+                    .map_err(|e| {
+                        UpicError::InvalidConfig(format!(
+                            "Can't build AprilTag detector for {:?}: {:?}",
+                            family, e
+                        ))
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            detectors,
+            #[cfg(feature = "opencv")]
+            gray: Mat::default(),
+            image: None,
+        })
+    }
+
+    /// Decode the tags inside a region of interest of a frame.
+    ///
+    /// Only the part of `roi` within the frame is searched; detections are
+    /// reported in full-frame pixel coordinates. Without a region the whole
+    /// frame is searched.
+    pub(crate) fn decode_region(
+        &mut self,
+        frame: &Frame,
+        roi: Option<Rect>,
+    ) -> Result<Vec<TagDetection>, UpicError> {
+        let Some(roi) = roi else {
+            return self.decode(frame);
+        };
+        let (region, offset) = crop_to_roi(frame, roi)?;
+        Ok(self
+            .decode(&region)?
+            .into_iter()
+            .map(|detection| detection.translated(offset))
+            .collect())
+    }
+
+    /// Decode all tags in a BGR or grayscale frame.
+    #[cfg(feature = "opencv")]
+    pub(crate) fn decode(
+        &mut self,
+        frame: &(impl MatTraitConst + ToInputArray),
+    ) -> Result<Vec<TagDetection>, UpicError> {
+        let image = if frame.channels() == 1 {
+            load_image(&mut self.image, frame)?
+        } else {
+            imgproc::cvt_color_def(frame, &mut self.gray, imgproc::COLOR_BGR2GRAY)?;
+            load_image(&mut self.image, &self.gray)?
+        };
+        Ok(detect_all(&mut self.detectors, image))
+    }
+
+    /// Decode all tags in a grayscale frame.
+    #[cfg(not(feature = "opencv"))]
+    pub(crate) fn decode(&mut self, frame: &Frame) -> Result<Vec<TagDetection>, UpicError> {
+        let image = load_image(&mut self.image, frame)?;
+        Ok(detect_all(&mut self.detectors, image))
+    }
+}
+
+/// Run every family's detector on the same image.
+fn detect_all(detectors: &mut [(TagFamily, Detector)], image: &Image) -> Vec<TagDetection> {
+    let mut detections = Vec::new();
+    for (family, detector) in detectors {
+        detections.extend(
+            detector
+                .detect(image)
+                .into_iter()
+                .map(|detection| TagDetection {
+                    id: detection.id() as i32,
+                    family: *family,
+                    corners: detection.corners(),
+                    center: detection.center(),
+                    decision_margin: detection.decision_margin() as f64,
+                    hamming: detection.hamming() as u32,
+                }),
+        );
+    }
+    detections
+}
+
+/// The detector image in `slot`, reused when it is `width` x `height`
+fn image_slot(
+    slot: &mut Option<((usize, usize), Image)>,
+    (width, height): (usize, usize),
+) -> Result<&mut Image, UpicError> {
+    let image = match slot.take() {
+        Some((size, image)) if size == (width, height) => image,
+        _ => Image::zeros_with_stride(width, height, width).ok_or(UpicError::FrameReadFailed)?,
+    };
+    let (_, image) = slot.insert(((width, height), image));
+    Ok(image)
+}
+
+/// Copy a grayscale frame into the detector image.
+///
+/// The image in `slot` is reused when it has the frame's size.
+#[cfg(feature = "opencv")]
+fn load_image<'a>(
+    slot: &'a mut Option<((usize, usize), Image)>,
+    gray: &impl MatTraitConst,
+) -> Result<&'a Image, UpicError> {
+    let (width, height) = (gray.cols() as usize, gray.rows() as usize);
+    let image = image_slot(slot, (width, height))?;
+    for y in 0..height {
+        let row = gray.at_row::<u8>(y as i32)?;
+        for (x, value) in row.iter().enumerate() {
+            image[(x, y)] = *value;
+        }
+    }
+    Ok(image)
+}
+
+/// Copy a grayscale frame into the detector image.
+///
+/// The image in `slot` is reused when it has the frame's size.
+#[cfg(not(feature = "opencv"))]
+fn load_image<'a>(
+    slot: &'a mut Option<((usize, usize), Image)>,
+    gray: &Frame,
+) -> Result<&'a Image, UpicError> {
+    let (width, height) = (gray.cols() as usize, gray.rows() as usize);
+    let image = image_slot(slot, (width, height))?;
+    for y in 0..height {
+        for (x, value) in gray.row(y).iter().enumerate() {
+            image[(x, y)] = *value;
+        }
+    }
+    Ok(image)
+}
+
+/// View the part of `roi` within the frame, without copying it.
+///
+/// Returns the region and its top-left corner in frame coordinates, the offset
+/// that maps region coordinates back to the frame.
+#[cfg(feature = "opencv")]
+pub(crate) fn crop_to_roi(
+    frame: &Mat,
+    roi: Rect,
+) -> Result<(BoxedRef<'_, Mat>, [f64; 2]), UpicError> {
+    let region = clip_roi(frame, roi)?;
+    Ok((Mat::roi(frame, region)?, [region.x as f64, region.y as f64]))
+}
+
+/// Copy the part of `roi` within the frame.
+///
+/// Returns the region and its top-left corner in frame coordinates, the offset
+/// that maps region coordinates back to the frame.
+#[cfg(not(feature = "opencv"))]
+pub(crate) fn crop_to_roi(frame: &Frame, roi: Rect) -> Result<(Frame, [f64; 2]), UpicError> {
+    let region = clip_roi(frame, roi)?;
+    Ok((frame.crop(region), [region.x as f64, region.y as f64]))
+}
+
+/// The part of `roi` within the frame.
+fn clip_roi(frame: &Frame, roi: Rect) -> Result<Rect, UpicError> {
+    let x = roi.x.clamp(0, frame.cols());
+    let y = roi.y.clamp(0, frame.rows());
+    let width = (roi.x + roi.width).min(frame.cols()) - x;
+    let height = (roi.y + roi.height).min(frame.rows()) - y;
+    if width <= 0 || height <= 0 {
+        return Err(UpicError::InvalidConfig(format!(
+            "roi {:?} is outside the {}x{} frame",
+            roi,
+            frame.cols(),
+            frame.rows()
+        )));
+    }
+    Ok(Rect::new(x, y, width, height))
+}
+
+#[cfg(all(test, feature = "opencv"))]
+mod tests {
+    use super::*;
+    use crate::tag_detector::source::{FrameSource, MockFrameSource};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_crop_to_roi_offsets() {
+        let frame = Mat::new_rows_cols_with_default(
+            1080,
+            1920,
+            opencv::core::CV_8UC1,
+            opencv::core::Scalar::all(255.0),
+        )
+        .unwrap();
+        let mut source = MockFrameSource::new(vec![frame]);
+        let frame = source.read_frame().unwrap();
+
+        // Lower half of a 1080p frame
+        let (region, offset) = crop_to_roi(&frame, Rect::new(0, 540, 1920, 540)).unwrap();
+        assert_eq!((region.cols(), region.rows()), (1920, 540));
+        assert_eq!(offset, [0.0, 540.0]);
+
+        // Regions reaching past the frame are clipped to it
+        let (region, offset) = crop_to_roi(&frame, Rect::new(1800, 1000, 400, 400)).unwrap();
+        assert_eq!((region.cols(), region.rows()), (120, 80));
+        assert_eq!(offset, [1800.0, 1000.0]);
+        assert!(crop_to_roi(&frame, Rect::new(2000, 0, 10, 10)).is_err());
+
+        // A blank region decodes to nothing
+        let mut decoder =
+            TagDecoder::new(&[TagFamily::Tag36h11], &DetectorParams::default()).unwrap();
+        let detections = decoder
+            .decode_region(&frame, Some(Rect::new(0, 540, 1920, 540)))
+            .unwrap();
+        assert!(detections.is_empty());
+    }
+
+    /// Average time to decode `frame` over a few runs
+    fn decode_time(params: &DetectorParams, frame: &Mat) -> Duration {
+        const RUNS: u32 = 5;
+        let mut decoder = TagDecoder::new(&[TagFamily::Tag36h11], params).unwrap();
+        // The first run allocates the image buffer
+        assert_eq!(decoder.decode(frame).unwrap().len(), 1);
+        let started = Instant::now();
+        for _ in 0..RUNS {
+            assert_eq!(decoder.decode(frame).unwrap().len(), 1);
+        }
+        started.elapsed() / RUNS
+    }
+
+    // Renders a marker with OpenCV's objdetect module
+    #[cfg(feature = "testing")]
+    #[test]
+    fn test_decimation_speeds_up_decoding() {
+        // A 1080p frame with one large tag and some texture for the quad search
+        let mut frame = Mat::new_rows_cols_with_default(
+            1080,
+            1920,
+            opencv::core::CV_8UC1,
+            opencv::core::Scalar::all(255.0),
+        )
+        .unwrap();
+        opencv::core::randu(
+            &mut Mat::roi_mut(&mut frame, Rect::new(1200, 0, 720, 1080)).unwrap(),
+            &opencv::core::Scalar::all(0.0),
+            &opencv::core::Scalar::all(255.0),
+        )
+        .unwrap();
+        let dictionary = opencv::objdetect::get_predefined_dictionary(
+            opencv::objdetect::PredefinedDictionaryType::DICT_APRILTAG_36h11,
+        )
+        .unwrap();
+        let mut marker = Mat::default();
+        opencv::objdetect::generate_image_marker(&dictionary, 4, 320, &mut marker, 1).unwrap();
+        marker
+            .copy_to(&mut Mat::roi_mut(&mut frame, Rect::new(400, 380, 320, 320)).unwrap())
+            .unwrap();
+
+        let decimated = decode_time(&DetectorParams::default(), &frame);
+        let full = decode_time(
+            &DetectorParams {
+                quad_decimate: 1.0,
+                ..DetectorParams::default()
+            },
+            &frame,
+        );
+        log::info!(
+            "1080p decode: {:?} with quad_decimate 2, {:?} with quad_decimate 1",
+            decimated,
+            full
+        );
+        assert!(decimated < full, "{:?} vs {:?}", decimated, full);
+    }
+}
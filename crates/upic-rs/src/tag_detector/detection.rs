@@ -0,0 +1,162 @@
+use super::config::TagFamily;
+
+/// A single AprilTag detection published by the detection thread
+///
+/// Coordinates are in pixels of the frame the tag was detected in, with the
+/// origin at the top-left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TagDetection {
+    /// Decoded tag ID
+    pub id: i32,
+    /// Family the tag was decoded from
+    pub family: TagFamily,
+    /// Corner points in the order reported by the detector
+    /// (counter-clockwise, starting bottom-left in tag coordinates)
+    pub corners: [[f64; 2]; 4],
+    /// Center point of the tag
+    pub center: [f64; 2],
+    /// Decision margin of the decode; higher means a more confident detection
+    pub decision_margin: f64,
+    /// Number of bit errors corrected to decode the tag; 0 is an exact match
+    pub hamming: u32,
+}
+
+impl TagDetection {
+    /// Squared pixel distance from the tag center to `point`
+    pub fn distance_sq_to(&self, point: [f64; 2]) -> f64 {
+        let dx = self.center[0] - point[0];
+        let dy = self.center[1] - point[1];
+        dx * dx + dy * dy
+    }
+
+    /// Whether the decision margin exceeds `min_margin`
+    pub(crate) fn is_confident(&self, min_margin: f64) -> bool {
+        self.decision_margin > min_margin
+    }
+
+    /// The same detection moved by `offset` pixels, e.g. from region of
+    /// interest to full-frame coordinates
+    pub(crate) fn translated(mut self, offset: [f64; 2]) -> Self {
+        for corner in &mut self.corners {
+            corner[0] += offset[0];
+            corner[1] += offset[1];
+        }
+        self.center[0] += offset[0];
+        self.center[1] += offset[1];
+        self
+    }
+
+    /// Pixel area of the tag's quad
+    pub fn area(&self) -> f64 {
+        let c = &self.corners;
+        let twice_area: f64 = (0..4)
+            .map(|i| {
+                let j = (i + 1) % 4;
+                c[i][0] * c[j][1] - c[j][0] * c[i][1]
+            })
+            .sum();
+        twice_area.abs() / 2.0
+    }
+
+    /// Pixel length of the shortest side of the tag's quad
+    pub fn min_side(&self) -> f64 {
+        self.side_lengths()
+            .into_iter()
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /// Mean pixel length of the sides of the tag's quad, its apparent size
+    pub fn mean_side(&self) -> f64 {
+        self.side_lengths().iter().sum::<f64>() / 4.0
+    }
+
+    fn side_lengths(&self) -> [f64; 4] {
+        let c = &self.corners;
+        std::array::from_fn(|i| {
+            let j = (i + 1) % 4;
+            (c[j][0] - c[i][0]).hypot(c[j][1] - c[i][1])
+        })
+    }
+}
+
+/// Offset of the selected tag's center from the frame center, published with
+/// the tag ID
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct CenterOffset {
+    /// Pixels right and below of the frame center; negative is left and above
+    pub(crate) offset: (f64, f64),
+    /// Width of the frame, assumed to be twice the frame center's x
+    pub(crate) frame_width: f64,
+}
+
+impl CenterOffset {
+    pub(crate) fn new(detection: &TagDetection, frame_center: [f64; 2]) -> Self {
+        CenterOffset {
+            offset: (
+                detection.center[0] - frame_center[0],
+                detection.center[1] - frame_center[1],
+            ),
+            frame_width: frame_center[0] * 2.0,
+        }
+    }
+
+    /// Horizontal angle from the optical axis to the tag in degrees, positive
+    /// to the right, for a pinhole camera with the given field of view
+    pub(crate) fn bearing_deg(&self, horizontal_fov_deg: f64) -> f64 {
+        let focal_px = self.frame_width / 2.0 / (horizontal_fov_deg.to_radians() / 2.0).tan();
+        (self.offset.0 / focal_px).atan().to_degrees()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detection(id: i32, center: [f64; 2]) -> TagDetection {
+        sized_detection(id, center, 10.0)
+    }
+
+    fn sized_detection(id: i32, center: [f64; 2], half_size: f64) -> TagDetection {
+        let [x, y] = center;
+        TagDetection {
+            id,
+            family: TagFamily::Tag36h11,
+            corners: [
+                [x - half_size, y + half_size],
+                [x + half_size, y + half_size],
+                [x + half_size, y - half_size],
+                [x - half_size, y - half_size],
+            ],
+            center,
+            decision_margin: 50.0,
+            hamming: 0,
+        }
+    }
+
+    #[test]
+    fn test_center_offset_and_bearing() {
+        let frame_center = [320.0, 240.0];
+        let left = CenterOffset::new(&detection(1, [160.0, 300.0]), frame_center);
+        assert_eq!(left.offset, (-160.0, 60.0));
+        assert_eq!(left.frame_width, 640.0);
+
+        // Halfway to the edge, not half the half-FOV: angles compress toward the edge
+        let bearing = left.bearing_deg(90.0);
+        assert!((bearing - -(0.5f64).atan().to_degrees()).abs() < 1e-9);
+        assert!((-27.0..-26.0).contains(&bearing), "{}", bearing);
+
+        // The frame edge sits at half the field of view, the center on axis
+        let edge = CenterOffset::new(&detection(2, [640.0, 240.0]), frame_center);
+        assert!((edge.bearing_deg(60.0) - 30.0).abs() < 1e-9);
+        let centered = CenterOffset::new(&detection(3, frame_center), frame_center);
+        assert_eq!(centered.bearing_deg(60.0), 0.0);
+    }
+
+    #[test]
+    fn test_translated() {
+        let moved = detection(5, [20.0, 30.0]).translated([100.0, 540.0]);
+        assert_eq!(moved.center, [120.0, 570.0]);
+        assert_eq!(moved.corners[0], [110.0, 580.0]);
+        assert_eq!(moved.area(), detection(5, [20.0, 30.0]).area());
+    }
+}
@@ -0,0 +1,210 @@
+use crate::error::UpicError;
+
+/// Error of the operations that need OpenCV
+fn opencv_disabled() -> UpicError {
+    UpicError::FeatureDisabled { feature: "opencv" }
+}
+
+/// Stands in for the OpenCV camera and video file sources, which can't be opened
+pub(crate) mod camera {
+    use std::convert::Infallible;
+
+    use super::opencv_disabled;
+    use crate::error::UpicError;
+    use crate::tag_detector::frame::Frame;
+    use crate::tag_detector::source::{CameraBackend, FrameSource};
+
+    /// A camera opened through OpenCV
+    ///
+    /// Without the `opencv` feature no camera can be opened, so no value of this
+    /// type exists.
+    pub struct CameraSource {
+        never: Infallible,
+    }
+
+    impl CameraSource {
+        /// Open a camera device.
+        ///
+        /// # Errors
+        ///
+        /// Always returns `UpicError::FeatureDisabled`.
+        pub fn open(_device_id: i32) -> Result<Self, UpicError> {
+            Err(opencv_disabled())
+        }
+
+        /// Open a camera stream by path, URL or pipeline string.
+        ///
+        /// # Errors
+        ///
+        /// Always returns `UpicError::FeatureDisabled`.
+        pub fn open_path(_path: &str, _backend: CameraBackend) -> Result<Self, UpicError> {
+            Err(opencv_disabled())
+        }
+
+        /// Camera device identifier the source was opened with, `None` for streams.
+        pub fn device_id(&self) -> Option<i32> {
+            match self.never {}
+        }
+    }
+
+    impl FrameSource for CameraSource {
+        fn read_frame(&mut self) -> Result<Frame, UpicError> {
+            match self.never {}
+        }
+
+        fn resolution(&self) -> (f64, f64) {
+            match self.never {}
+        }
+    }
+
+    /// Frames read from a video file through OpenCV
+    ///
+    /// Without the `opencv` feature no file can be opened, so no value of this
+    /// type exists.
+    pub struct VideoFileSource {
+        never: Infallible,
+    }
+
+    impl VideoFileSource {
+        /// Open a video file.
+        ///
+        /// # Errors
+        ///
+        /// Always returns `UpicError::FeatureDisabled`.
+        pub fn open(_path: &str, _looping: bool) -> Result<Self, UpicError> {
+            Err(opencv_disabled())
+        }
+
+        /// Pace reads to the file's frame rate.
+        pub fn with_realtime(self, _realtime: bool) -> Self {
+            match self.never {}
+        }
+    }
+
+    impl FrameSource for VideoFileSource {
+        fn read_frame(&mut self) -> Result<Frame, UpicError> {
+            match self.never {}
+        }
+
+        fn resolution(&self) -> (f64, f64) {
+            match self.never {}
+        }
+    }
+}
+
+/// Stands in for frame preprocessing, which only passes frames through
+pub(crate) mod preprocess {
+    use super::opencv_disabled;
+    use crate::error::UpicError;
+    use crate::tag_detector::config::Preprocess;
+    use crate::tag_detector::frame::Frame;
+
+    pub(crate) struct Preprocessor;
+
+    impl Preprocessor {
+        /// # Errors
+        ///
+        /// Returns `UpicError::FeatureDisabled` if there are steps to run.
+        pub(crate) fn new(preprocess: &Preprocess, use_opencl: bool) -> Result<Self, UpicError> {
+            if !preprocess.steps.is_empty() {
+                return Err(opencv_disabled());
+            }
+            if use_opencl {
+                log::warn!("OpenCL needs the opencv feature, ignoring use_opencl");
+            }
+            Ok(Preprocessor)
+        }
+
+        pub(crate) fn uses_opencl(&self) -> bool {
+            false
+        }
+
+        pub(crate) fn apply<'a>(&'a mut self, frame: &'a Frame) -> Result<&'a Frame, UpicError> {
+            Ok(frame)
+        }
+    }
+}
+
+/// Stands in for the detection preview, which can't be drawn or shown
+pub(crate) mod preview {
+    use super::opencv_disabled;
+    use crate::error::UpicError;
+    use crate::tag_detector::detection::TagDetection;
+    use crate::tag_detector::frame::{Frame, Rect};
+
+    /// # Errors
+    ///
+    /// Always returns `UpicError::FeatureDisabled`.
+    pub(crate) fn draw_overlay(
+        _frame: &Frame,
+        _candidates: &[TagDetection],
+        _selected: Option<&TagDetection>,
+        _center: [f64; 2],
+        _roi: Option<Rect>,
+    ) -> Result<Frame, UpicError> {
+        Err(opencv_disabled())
+    }
+
+    pub(crate) struct PreviewWindow;
+
+    impl PreviewWindow {
+        pub(crate) fn new() -> Self {
+            PreviewWindow
+        }
+
+        /// # Errors
+        ///
+        /// Always returns `UpicError::FeatureDisabled`.
+        pub(crate) fn show(&mut self, _canvas: &Frame) -> Result<(), UpicError> {
+            Err(opencv_disabled())
+        }
+
+        pub(crate) fn close(&mut self) {}
+    }
+}
+
+/// Stands in for lens undistortion, which can't be applied
+pub(crate) mod undistort {
+    use super::opencv_disabled;
+    use crate::error::UpicError;
+    use crate::tag_detector::frame::Frame;
+    use crate::tag_detector::pose::CameraIntrinsics;
+
+    pub(crate) struct Undistorter;
+
+    impl Undistorter {
+        pub(crate) fn new() -> Self {
+            Undistorter
+        }
+
+        /// # Errors
+        ///
+        /// Always returns `UpicError::FeatureDisabled`.
+        pub(crate) fn apply(
+            &mut self,
+            _frame: &Frame,
+            _intrinsics: &CameraIntrinsics,
+            _undistorted: &mut Frame,
+        ) -> Result<(), UpicError> {
+            Err(opencv_disabled())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::camera::{CameraSource, VideoFileSource};
+    use super::*;
+
+    #[test]
+    fn test_cameras_need_opencv() {
+        assert!(matches!(
+            CameraSource::open(0),
+            Err(UpicError::FeatureDisabled { feature: "opencv" })
+        ));
+        assert!(matches!(
+            VideoFileSource::open("match.mp4", false),
+            Err(UpicError::FeatureDisabled { .. })
+        ));
+    }
+}
@@ -0,0 +1,125 @@
+use super::detection::TagDetection;
+
+/// Approximate distance to a tag of known size from its apparent size.
+///
+/// Uses the pinhole relation `distance = size * focal / apparent_size`, which
+/// needs only a focal length in pixels instead of full intrinsics.
+///
+/// # Arguments
+///
+/// * `size_m` - Edge length of the tag's black square in meters.
+/// * `focal_px` - Focal length of the camera in pixels.
+/// * `apparent_px` - Edge length of the tag in the image in pixels.
+pub(crate) fn estimate_distance(size_m: f64, focal_px: f64, apparent_px: f64) -> f64 {
+    size_m * focal_px / apparent_px
+}
+
+/// Focal length in pixels that makes a tag of `size_m` appearing `apparent_px`
+/// wide come out at `distance_m`; the inverse of `estimate_distance`.
+pub(crate) fn focal_length_for(size_m: f64, distance_m: f64, apparent_px: f64) -> f64 {
+    apparent_px * distance_m / size_m
+}
+
+/// Smooths per-frame distance estimates with an exponential moving average.
+///
+/// The average restarts whenever the tag changes or is lost, so the distance
+/// to a new tag never blends with the previous one's.
+pub(crate) struct DistanceSmoother {
+    /// Weight of the newest estimate; 1 disables smoothing
+    alpha: f64,
+    /// Tag the average belongs to, and the average
+    smoothed: Option<(i32, f64)>,
+}
+
+impl DistanceSmoother {
+    pub(crate) fn new(alpha: f64) -> Self {
+        DistanceSmoother {
+            alpha,
+            smoothed: None,
+        }
+    }
+
+    /// Change the weight of new estimates; the current average is kept.
+    pub(crate) fn set_alpha(&mut self, alpha: f64) {
+        self.alpha = alpha;
+    }
+
+    /// Record one frame's estimate.
+    ///
+    /// # Arguments
+    ///
+    /// * `detection` - The published detection, or `None` when no tag is published.
+    /// * `distance` - The raw estimate for it, or `None` when it can't be estimated.
+    ///
+    /// # Returns
+    ///
+    /// The smoothed distance, or `None` without an estimate for this frame.
+    pub(crate) fn observe(
+        &mut self,
+        detection: Option<&TagDetection>,
+        distance: Option<f64>,
+    ) -> Option<f64> {
+        let (Some(detection), Some(distance)) = (detection, distance) else {
+            self.reset();
+            return None;
+        };
+        let smoothed = match self.smoothed {
+            Some((id, average)) if id == detection.id => {
+                average + self.alpha * (distance - average)
+            }
+            _ => distance,
+        };
+        self.smoothed = Some((detection.id, smoothed));
+        Some(smoothed)
+    }
+
+    /// Forget the average, e.g. while detection is halted.
+    pub(crate) fn reset(&mut self) {
+        self.smoothed = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tag_detector::config::TagFamily;
+
+    fn detection(id: i32) -> TagDetection {
+        TagDetection {
+            id,
+            family: TagFamily::Tag36h11,
+            corners: [[0.0, 50.0], [50.0, 50.0], [50.0, 0.0], [0.0, 0.0]],
+            center: [25.0, 25.0],
+            decision_margin: 50.0,
+            hamming: 0,
+        }
+    }
+
+    #[test]
+    fn test_estimate_and_calibrate() {
+        // A 10 cm tag 50 px wide with a 500 px focal length is 1 m away
+        assert_eq!(estimate_distance(0.1, 500.0, 50.0), 1.0);
+        let focal_px = focal_length_for(0.1, 2.0, 25.0);
+        assert_eq!(focal_px, 500.0);
+        assert_eq!(estimate_distance(0.1, focal_px, 25.0), 2.0);
+    }
+
+    #[test]
+    fn test_smoothing_restarts_on_new_tag() {
+        let mut smoother = DistanceSmoother::new(0.5);
+        let tag = detection(3);
+        assert_eq!(smoother.observe(Some(&tag), Some(2.0)), Some(2.0));
+        assert_eq!(smoother.observe(Some(&tag), Some(1.0)), Some(1.5));
+        assert_eq!(smoother.observe(Some(&tag), Some(1.0)), Some(1.25));
+
+        // Another tag starts from its own first estimate
+        assert_eq!(smoother.observe(Some(&detection(4)), Some(3.0)), Some(3.0));
+
+        // So does the same tag after it was lost
+        assert_eq!(smoother.observe(None, None), None);
+        assert_eq!(smoother.observe(Some(&tag), Some(1.0)), Some(1.0));
+
+        smoother.set_alpha(1.0);
+        assert_eq!(smoother.observe(Some(&tag), Some(4.0)), Some(4.0));
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::mem::{self, Discriminant};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::sync::Recover;
+use crate::error::UpicError;
+
+/// Shortest time between two reports of the same kind of error
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Callback receiving errors the detection thread runs into
+pub(crate) type ErrorCallback = Arc<dyn Fn(&UpicError) + Send + Sync>;
+
+/// Hands the detection thread's errors to the error callbacks.
+///
+/// Each kind of error, as told by its `UpicError` variant, is reported at most
+/// once per `REPORT_INTERVAL`, so a camera failing every read doesn't flood
+/// the callbacks.
+pub(crate) struct ErrorReporter {
+    /// When each kind of error was last reported
+    last_reported: HashMap<Discriminant<UpicError>, Instant>,
+}
+
+impl ErrorReporter {
+    pub(crate) fn new() -> Self {
+        ErrorReporter {
+            last_reported: HashMap::new(),
+        }
+    }
+
+    /// Whether an error may be reported at `now`; if so, the report is recorded.
+    fn admit(&mut self, error: &UpicError, now: Instant) -> bool {
+        let kind = mem::discriminant(error);
+        match self.last_reported.get(&kind) {
+            Some(&last) if now.saturating_duration_since(last) < REPORT_INTERVAL => false,
+            _ => {
+                self.last_reported.insert(kind, now);
+                true
+            }
+        }
+    }
+
+    /// Run the callbacks with `error` unless its kind was reported too recently.
+    ///
+    /// The callback list is cloned first, so callbacks run without any detector
+    /// lock held.
+    pub(crate) fn report(
+        &mut self,
+        callbacks: &Mutex<Vec<ErrorCallback>>,
+        error: &UpicError,
+        now: Instant,
+    ) {
+        if self.admit(error, now) {
+            let callbacks = callbacks.lock().recover().clone();
+            for callback in &callbacks {
+                callback(error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limited_per_kind() {
+        let start = Instant::now();
+        let mut reporter = ErrorReporter::new();
+        assert!(reporter.admit(&UpicError::FrameReadFailed, start));
+        assert!(!reporter.admit(&UpicError::FrameReadFailed, start + REPORT_INTERVAL / 2));
+        // Other kinds have their own limit, whatever their contents
+        assert!(reporter.admit(&UpicError::CameraOpenFailed { device_id: 0 }, start));
+        assert!(!reporter.admit(&UpicError::CameraOpenFailed { device_id: 1 }, start));
+
+        // A suppressed error doesn't extend the wait
+        assert!(reporter.admit(&UpicError::FrameReadFailed, start + REPORT_INTERVAL));
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&calls);
+        let callbacks: Mutex<Vec<ErrorCallback>> = Mutex::new(vec![Arc::new(move |e| {
+            seen.lock().unwrap().push(e.to_string())
+        })]);
+        let later = start + REPORT_INTERVAL * 3;
+        reporter.report(&callbacks, &UpicError::CameraDisconnected, later);
+        reporter.report(&callbacks, &UpicError::CameraDisconnected, later);
+        assert_eq!(*calls.lock().unwrap(), ["Camera disconnected"]);
+    }
+}
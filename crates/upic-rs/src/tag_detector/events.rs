@@ -0,0 +1,184 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use super::sync::Recover;
+
+/// A tag entering or leaving the camera's view
+///
+/// Received from `TagDetector::subscribe_events()`. Both are debounced with
+/// `Config::min_consecutive_frames`, so a single frame in which a tag is missed
+/// doesn't produce a `Left` followed by an `Entered`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagEvent {
+    /// The tag has been seen for enough consecutive frames
+    Entered {
+        /// ID of the tag
+        id: i32,
+        /// Read time of the frame that completed the streak
+        at: Instant,
+    },
+    /// The tag has been missing for enough consecutive frames, or detection
+    /// was halted or stopped while it was in view
+    Left {
+        /// ID of the tag
+        id: i32,
+        /// Read time of the frame that completed the streak
+        at: Instant,
+    },
+}
+
+/// Subscribers to tag events; closed receivers are dropped on the next event
+pub(crate) type EventSubscribers = Arc<Mutex<Vec<Sender<TagEvent>>>>;
+
+/// Hand events to every subscriber, in order.
+pub(crate) fn send_events(subscribers: &Mutex<Vec<Sender<TagEvent>>>, events: Vec<TagEvent>) {
+    if events.is_empty() {
+        return;
+    }
+    let mut subscribers = subscribers.lock().recover();
+    subscribers.retain(|subscriber| events.iter().all(|event| subscriber.send(*event).is_ok()));
+}
+
+/// Turns the tags visible in each frame into debounced enter and leave events.
+///
+/// A tag's presence only flips once it has differed from the reported state
+/// for `min_frames` consecutive frames; a frame agreeing with the reported
+/// state restarts the streak.
+pub(crate) struct PresenceTracker {
+    min_frames: u32,
+    /// Tags reported as in view
+    present: BTreeSet<i32>,
+    /// Tags whose visibility differs from `present`, with the length of the streak
+    pending: BTreeMap<i32, u32>,
+}
+
+impl PresenceTracker {
+    pub(crate) fn new(min_frames: u32) -> Self {
+        PresenceTracker {
+            min_frames,
+            present: BTreeSet::new(),
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Change the number of frames a change must persist; pending streaks are kept.
+    pub(crate) fn set_min_frames(&mut self, min_frames: u32) {
+        self.min_frames = min_frames;
+    }
+
+    /// Record the tags visible in one frame.
+    ///
+    /// # Returns
+    ///
+    /// The events completed by this frame: tags leaving first, then tags
+    /// entering, each in ID order.
+    pub(crate) fn observe(
+        &mut self,
+        visible: impl IntoIterator<Item = i32>,
+        at: Instant,
+    ) -> Vec<TagEvent> {
+        let visible: BTreeSet<i32> = visible.into_iter().collect();
+        let changed: BTreeSet<i32> = self
+            .present
+            .symmetric_difference(&visible)
+            .copied()
+            .collect();
+        self.pending.retain(|id, _| changed.contains(id));
+
+        let mut left = Vec::new();
+        let mut entered = Vec::new();
+        for id in changed {
+            let streak = self.pending.entry(id).or_insert(0);
+            *streak += 1;
+            if *streak < self.min_frames {
+                continue;
+            }
+            self.pending.remove(&id);
+            if self.present.remove(&id) {
+                left.push(TagEvent::Left { id, at });
+            } else {
+                self.present.insert(id);
+                entered.push(TagEvent::Entered { id, at });
+            }
+        }
+        left.extend(entered);
+        left
+    }
+
+    /// Report every tag in view as left, e.g. when detection is halted.
+    pub(crate) fn reset(&mut self, at: Instant) -> Vec<TagEvent> {
+        self.pending.clear();
+        std::mem::take(&mut self.present)
+            .into_iter()
+            .map(|id| TagEvent::Left { id, at })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn ids(events: &[TagEvent]) -> Vec<(bool, i32)> {
+        events
+            .iter()
+            .map(|event| match *event {
+                TagEvent::Entered { id, .. } => (true, id),
+                TagEvent::Left { id, .. } => (false, id),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_presence_is_debounced() {
+        let now = Instant::now();
+        let mut tracker = PresenceTracker::new(2);
+        assert!(tracker.observe([5], now).is_empty());
+        assert_eq!(ids(&tracker.observe([5, 7], now)), [(true, 5)]);
+
+        // A one-frame dropout of 5 doesn't make it leave
+        assert_eq!(ids(&tracker.observe([7], now)), [(true, 7)]);
+        assert!(tracker.observe([5, 7], now).is_empty());
+        assert!(tracker.observe([7], now).is_empty());
+        assert!(tracker.observe([5, 7], now).is_empty());
+
+        // Leaving and entering in the same frame lists the leave first
+        assert!(tracker.observe([9], now).is_empty());
+        assert_eq!(
+            ids(&tracker.observe([9], now)),
+            [(false, 5), (false, 7), (true, 9)]
+        );
+
+        assert_eq!(ids(&tracker.reset(now)), [(false, 9)]);
+        assert!(tracker.reset(now).is_empty());
+
+        tracker.set_min_frames(1);
+        assert_eq!(ids(&tracker.observe([3, 3], now)), [(true, 3)]);
+    }
+
+    #[test]
+    fn test_send_events_drops_closed_subscribers() {
+        let (kept, received) = mpsc::channel();
+        let (closed, _) = mpsc::channel();
+        let subscribers = Mutex::new(vec![kept, closed]);
+        let at = Instant::now();
+        send_events(
+            &subscribers,
+            vec![
+                TagEvent::Entered { id: 1, at },
+                TagEvent::Left { id: 1, at },
+            ],
+        );
+        assert_eq!(subscribers.lock().unwrap().len(), 1);
+        assert_eq!(
+            received.try_iter().collect::<Vec<_>>(),
+            [
+                TagEvent::Entered { id: 1, at },
+                TagEvent::Left { id: 1, at }
+            ]
+        );
+    }
+}
@@ -0,0 +1,182 @@
+#[cfg(not(feature = "opencv"))]
+use crate::error::UpicError;
+
+/// Image delivered by frame sources and searched for tags
+///
+/// OpenCV's `Mat` with the `opencv` feature, an 8-bit grayscale buffer
+/// without it.
+#[cfg(feature = "opencv")]
+pub type Frame = opencv::core::Mat;
+
+/// Rectangle in pixels, such as `Config::roi`
+#[cfg(feature = "opencv")]
+pub use opencv::core::Rect;
+
+/// Rectangle in pixels, such as `Config::roi`
+///
+/// Stands in for OpenCV's `Rect` when upic-rs is built without the `opencv`
+/// feature.
+#[cfg(not(feature = "opencv"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[cfg(not(feature = "opencv"))]
+impl Rect {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// Image delivered by frame sources and searched for tags
+///
+/// Stands in for OpenCV's `Mat` when upic-rs is built without the `opencv`
+/// feature: an 8-bit grayscale image in row-major order. The methods shared
+/// with `Mat` keep its names, so code reading frames builds either way.
+#[cfg(not(feature = "opencv"))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Frame {
+    width: i32,
+    height: i32,
+    pixels: Vec<u8>,
+}
+
+#[cfg(not(feature = "opencv"))]
+impl Frame {
+    /// Create a frame from its pixels, row by row.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::InvalidConfig` if the size is negative or `pixels`
+    /// doesn't hold `width * height` values.
+    pub fn from_gray(width: i32, height: i32, pixels: Vec<u8>) -> Result<Self, UpicError> {
+        if width < 0 || height < 0 || pixels.len() != width as usize * height as usize {
+            return Err(UpicError::InvalidConfig(format!(
+                "{} pixels don't make a {}x{} frame",
+                pixels.len(),
+                width,
+                height
+            )));
+        }
+        Ok(Frame {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Create a frame of a single gray value.
+    pub fn filled(width: i32, height: i32, value: u8) -> Self {
+        let (width, height) = (width.max(0), height.max(0));
+        Frame {
+            width,
+            height,
+            pixels: vec![value; width as usize * height as usize],
+        }
+    }
+
+    /// Width in pixels
+    pub fn cols(&self) -> i32 {
+        self.width
+    }
+
+    /// Height in pixels
+    pub fn rows(&self) -> i32 {
+        self.height
+    }
+
+    /// Number of channels, always 1
+    pub fn channels(&self) -> i32 {
+        1
+    }
+
+    /// Whether the frame has no pixels, as before the first read
+    pub fn empty(&self) -> bool {
+        self.pixels.is_empty()
+    }
+
+    /// All pixels, row by row
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Copy of the frame; can't fail, unlike `Mat::try_clone()`.
+    pub fn try_clone(&self) -> Result<Frame, UpicError> {
+        Ok(self.clone())
+    }
+
+    /// Copy the frame into `output`, reusing its buffer.
+    pub fn copy_to(&self, output: &mut Frame) -> Result<(), UpicError> {
+        output.clone_from(self);
+        Ok(())
+    }
+
+    /// Pixels of row `y`
+    pub(crate) fn row(&self, y: usize) -> &[u8] {
+        let width = self.width as usize;
+        &self.pixels[y * width..(y + 1) * width]
+    }
+
+    /// Copy of the pixels within `rect`, which must lie within the frame
+    pub(crate) fn crop(&self, rect: Rect) -> Frame {
+        let (x, width) = (rect.x as usize, rect.width as usize);
+        let pixels = (rect.y..rect.y + rect.height)
+            .flat_map(|y| &self.row(y as usize)[x..x + width])
+            .copied()
+            .collect();
+        Frame {
+            width: rect.width,
+            height: rect.height,
+            pixels,
+        }
+    }
+
+    /// Copy scaled to `width` x `height` with nearest-neighbor sampling
+    pub(crate) fn resized(&self, width: i32, height: i32) -> Frame {
+        if self.empty() {
+            return Frame::filled(width, height, 0);
+        }
+        let mut resized = Frame::filled(width, height, 0);
+        let (width, height) = (resized.width as usize, resized.height as usize);
+        for y in 0..height {
+            let row = self.row(y * self.height as usize / height);
+            for x in 0..width {
+                resized.pixels[y * width + x] = row[x * self.width as usize / width];
+            }
+        }
+        resized
+    }
+}
+
+#[cfg(all(test, not(feature = "opencv")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gray_frame() {
+        assert!(Frame::default().empty());
+        assert!(Frame::from_gray(3, 2, vec![0; 5]).is_err());
+
+        let frame = Frame::from_gray(3, 2, vec![1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!((frame.cols(), frame.rows(), frame.channels()), (3, 2, 1));
+        assert_eq!(frame.crop(Rect::new(1, 0, 2, 2)).pixels(), [2, 3, 5, 6]);
+        assert_eq!(
+            frame.resized(6, 2).pixels(),
+            [1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6]
+        );
+        assert_eq!(frame.resized(1, 1).pixels(), [1]);
+
+        let mut copy = Frame::default();
+        frame.copy_to(&mut copy).unwrap();
+        assert_eq!(copy, frame);
+    }
+}
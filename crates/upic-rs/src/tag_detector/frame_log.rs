@@ -0,0 +1,266 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::sync::Recover;
+use crate::error::UpicError;
+
+/// Records waiting for the writer thread; further records are dropped
+const QUEUE_CAPACITY: usize = 256;
+
+/// CSV column names, in the order `FrameRecord::to_csv` writes them
+const CSV_HEADER: &str =
+    "timestamp,frame,tag_id,detections,rejected,read_ms,detect_ms,decision_margin,warmup_frames";
+
+/// File format of the detection log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Comma-separated values with a header line
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+}
+
+/// What the detection thread believed about one frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct FrameRecord {
+    /// Wall clock time the frame's result was published
+    pub(crate) timestamp: SystemTime,
+    /// Index of the frame since detection started
+    pub(crate) frame: u64,
+    /// Published tag ID, including the sentinel IDs
+    pub(crate) tag_id: i32,
+    /// Number of tags that passed the filters
+    pub(crate) detections: usize,
+    /// Number of decoded tags the filters discarded
+    pub(crate) rejected: usize,
+    /// Time spent reading the frame
+    pub(crate) read_time: Duration,
+    /// Time from the read to publishing, spent decoding, selecting and locating tags
+    pub(crate) detect_time: Duration,
+    /// Decision margin of the selected tag, `None` without one
+    pub(crate) decision_margin: Option<f64>,
+    /// Frames camera warm-ups threw away since the previous record; 0 unless
+    /// detection just started, reconnected or switched sources
+    pub(crate) warmup_frames: usize,
+}
+
+impl FrameRecord {
+    /// Seconds since the Unix epoch
+    fn unix_time(&self) -> f64 {
+        self.timestamp
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |elapsed| elapsed.as_secs_f64())
+    }
+
+    /// The decision margin formatted as a number, or `missing` without one
+    fn margin_or(&self, missing: &str) -> String {
+        self.decision_margin
+            .map_or_else(|| missing.to_string(), |margin| format!("{:.3}", margin))
+    }
+
+    fn to_csv(self) -> String {
+        format!(
+            "{:.3},{},{},{},{},{:.3},{:.3},{},{}",
+            self.unix_time(),
+            self.frame,
+            self.tag_id,
+            self.detections,
+            self.rejected,
+            millis(self.read_time),
+            millis(self.detect_time),
+            self.margin_or(""),
+            self.warmup_frames
+        )
+    }
+
+    fn to_json(self) -> String {
+        format!(
+            "{{\"timestamp\":{:.3},\"frame\":{},\"tag_id\":{},\"detections\":{},\"rejected\":{},\"read_ms\":{:.3},\"detect_ms\":{:.3},\"decision_margin\":{},\"warmup_frames\":{}}}",
+            self.unix_time(),
+            self.frame,
+            self.tag_id,
+            self.detections,
+            self.rejected,
+            millis(self.read_time),
+            millis(self.detect_time),
+            self.margin_or("null"),
+            self.warmup_frames
+        )
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// The detection thread's end of an open log
+///
+/// Records are handed over without blocking; when the writer falls behind and
+/// the queue is full they are dropped and counted instead.
+pub(crate) struct LogSender {
+    records: SyncSender<FrameRecord>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl LogSender {
+    pub(crate) fn record(&self, record: FrameRecord) {
+        // A disconnected writer has failed and already reported why
+        if let Err(TrySendError::Full(_)) = self.records.try_send(record) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The log shared with the detection thread; `None` while not logging
+pub(crate) type SharedLog = Arc<Mutex<Option<LogSender>>>;
+
+/// An open detection log and its writer thread
+pub(crate) struct FrameLog {
+    writer: JoinHandle<io::Result<()>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl FrameLog {
+    /// Create the log file and start its writer thread.
+    ///
+    /// # Returns
+    ///
+    /// The log, and the sender to hand to the detection thread. Dropping the
+    /// sender makes the writer flush the file and exit.
+    pub(crate) fn create(path: &Path, format: LogFormat) -> Result<(Self, LogSender), UpicError> {
+        let mut file = BufWriter::new(File::create(path)?);
+        if format == LogFormat::Csv {
+            writeln!(file, "{}", CSV_HEADER)?;
+        }
+        let (records, received) = mpsc::sync_channel(QUEUE_CAPACITY);
+        let writer = thread::spawn(move || write_records(file, format, received));
+        let dropped = Arc::new(AtomicU64::new(0));
+        let sender = LogSender {
+            records,
+            dropped: Arc::clone(&dropped),
+        };
+        Ok((FrameLog { writer, dropped }, sender))
+    }
+
+    /// Wait for the writer to write the remaining records and close the file.
+    ///
+    /// The sender must have been dropped, or this waits forever.
+    ///
+    /// # Returns
+    ///
+    /// The number of records dropped because the writer couldn't keep up.
+    pub(crate) fn finish(self) -> Result<u64, UpicError> {
+        self.writer
+            .join()
+            .map_err(|_| UpicError::Io(io::Error::other("log writer thread panicked")))??;
+        Ok(self.dropped.load(Ordering::Relaxed))
+    }
+}
+
+/// Write records until the sender is dropped, then flush.
+fn write_records(
+    mut file: BufWriter<File>,
+    format: LogFormat,
+    records: Receiver<FrameRecord>,
+) -> io::Result<()> {
+    for record in records {
+        let line = match format {
+            LogFormat::Csv => record.to_csv(),
+            LogFormat::Jsonl => record.to_json(),
+        };
+        if let Err(e) = writeln!(file, "{}", line) {
+            log::error!("Can't write the detection log: {}", e);
+            return Err(e);
+        }
+    }
+    file.flush()
+}
+
+/// Hand a record to the log if one is open.
+pub(crate) fn log_frame(log: &Mutex<Option<LogSender>>, record: FrameRecord) {
+    if let Some(sender) = &*log.lock().recover() {
+        sender.record(record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn record(frame: u64) -> FrameRecord {
+        FrameRecord {
+            timestamp: UNIX_EPOCH + Duration::from_millis(1_700_000_000_250),
+            frame,
+            tag_id: 5,
+            detections: 2,
+            rejected: 1,
+            read_time: Duration::from_micros(1500),
+            detect_time: Duration::from_millis(12),
+            decision_margin: Some(42.5),
+            warmup_frames: 0,
+        }
+    }
+
+    #[test]
+    fn test_write_csv_and_jsonl() {
+        let dir = std::env::temp_dir().join(format!("upic-frame-log-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let csv = dir.join("frames.csv");
+        let (log, sender) = FrameLog::create(&csv, LogFormat::Csv).unwrap();
+        let shared = Mutex::new(Some(sender));
+        log_frame(&shared, record(0));
+        log_frame(
+            &shared,
+            FrameRecord {
+                decision_margin: None,
+                warmup_frames: 6,
+                ..record(1)
+            },
+        );
+        shared.lock().unwrap().take();
+        assert_eq!(log.finish().unwrap(), 0);
+        assert_eq!(
+            fs::read_to_string(&csv).unwrap(),
+            format!(
+                "{}\n1700000000.250,0,5,2,1,1.500,12.000,42.500,0\n1700000000.250,1,5,2,1,1.500,12.000,,6\n",
+                CSV_HEADER
+            )
+        );
+
+        let jsonl = dir.join("frames.jsonl");
+        let (log, sender) = FrameLog::create(&jsonl, LogFormat::Jsonl).unwrap();
+        sender.record(record(7));
+        drop(sender);
+        log.finish().unwrap();
+        assert_eq!(
+            fs::read_to_string(&jsonl).unwrap(),
+            "{\"timestamp\":1700000000.250,\"frame\":7,\"tag_id\":5,\"detections\":2,\"rejected\":1,\"read_ms\":1.500,\"detect_ms\":12.000,\"decision_margin\":42.500,\"warmup_frames\":0}\n"
+        );
+
+        // Without an open log, records go nowhere
+        log_frame(&Mutex::new(None), record(8));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_full_queue_drops_records() {
+        let (records, received) = mpsc::sync_channel(1);
+        let sender = LogSender {
+            records,
+            dropped: Arc::new(AtomicU64::new(0)),
+        };
+        for frame in 0..4 {
+            sender.record(record(frame));
+        }
+        assert_eq!(sender.dropped.load(Ordering::Relaxed), 3);
+        assert_eq!(received.try_recv().unwrap().frame, 0);
+    }
+}
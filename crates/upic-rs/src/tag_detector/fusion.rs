@@ -0,0 +1,258 @@
+use std::time::Duration;
+
+use super::detection::TagDetection;
+use crate::error::UpicError;
+
+/// Fusion of a tag seen by several cameras of a `MultiTagDetector`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FusionConfig {
+    /// How recently a camera must have seen the tag for its view to be fused
+    pub window: Duration,
+    /// Largest difference between two cameras' bearings in degrees that is
+    /// still averaged; beyond it the views are flagged as disagreeing
+    pub max_disagreement_deg: f64,
+}
+
+impl Default for FusionConfig {
+    fn default() -> Self {
+        FusionConfig {
+            window: Duration::from_millis(200),
+            max_disagreement_deg: 10.0,
+        }
+    }
+}
+
+impl FusionConfig {
+    pub(crate) fn validate(&self) -> Result<(), UpicError> {
+        if !(self.max_disagreement_deg.is_finite() && self.max_disagreement_deg >= 0.0) {
+            return Err(UpicError::InvalidConfig(format!(
+                "fusion max_disagreement_deg must be non-negative, got {}",
+                self.max_disagreement_deg
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Where a camera of a `MultiTagDetector` points and how far its views are trusted
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CameraMount {
+    /// Angle from the robot's forward axis to the camera's optical axis in
+    /// degrees, positive to the right
+    pub yaw_deg: f64,
+    /// Factor the camera's decision margins are weighted by when fusing; 0
+    /// leaves the camera out
+    pub trust: f64,
+}
+
+impl Default for CameraMount {
+    fn default() -> Self {
+        CameraMount {
+            yaw_deg: 0.0,
+            trust: 1.0,
+        }
+    }
+}
+
+impl CameraMount {
+    pub(crate) fn validate(&self) -> Result<(), UpicError> {
+        if !self.yaw_deg.is_finite() {
+            return Err(UpicError::InvalidConfig(format!(
+                "camera yaw_deg must be finite, got {}",
+                self.yaw_deg
+            )));
+        }
+        if !(self.trust.is_finite() && self.trust >= 0.0) {
+            return Err(UpicError::InvalidConfig(format!(
+                "camera trust must be non-negative, got {}",
+                self.trust
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A tag seen by one or more cameras, combined into one bearing
+#[derive(Debug, Clone, PartialEq)]
+pub struct FusedDetection {
+    /// Decoded tag ID
+    pub id: i32,
+    /// Bearing from the robot's forward axis in degrees, positive to the right;
+    /// the weighted mean of the cameras' bearings, or the most trusted camera's
+    /// own bearing when they disagree
+    pub bearing_deg: f64,
+    /// Cameras that saw the tag, most trusted first
+    pub cameras: Vec<usize>,
+    /// The most trusted camera's detection, in that camera's pixel coordinates
+    pub detection: TagDetection,
+    /// Largest difference between two cameras' bearings in degrees; 0 for a
+    /// single camera
+    pub spread_deg: f64,
+    /// Whether the spread exceeds `FusionConfig::max_disagreement_deg`
+    pub disagreement: bool,
+}
+
+/// Counters of `MultiTagDetector::fused_detection()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FusionStats {
+    /// Fusions that combined two or more cameras
+    pub fused: u64,
+    /// Fusions whose cameras disagreed and were not averaged
+    pub disagreements: u64,
+}
+
+/// One camera's view of a tag
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct CameraView {
+    pub(crate) camera: usize,
+    pub(crate) detection: TagDetection,
+    /// Bearing from the robot's forward axis in degrees, mount yaw included
+    pub(crate) bearing_deg: f64,
+    pub(crate) trust: f64,
+}
+
+impl CameraView {
+    fn weight(&self) -> f64 {
+        self.detection.decision_margin.max(0.0) * self.trust
+    }
+}
+
+/// Signed difference `a - b` between two angles in degrees, within -180..=180
+fn angle_diff(a: f64, b: f64) -> f64 {
+    let diff = (a - b).rem_euclid(360.0);
+    if diff > 180.0 { diff - 360.0 } else { diff }
+}
+
+/// Combine the views of one tag, weighting each by decision margin times trust.
+///
+/// Views without weight are left out. Returns `None` if none is left.
+pub(crate) fn fuse(views: &[CameraView], max_disagreement_deg: f64) -> Option<FusedDetection> {
+    let mut views: Vec<&CameraView> = views.iter().filter(|v| v.weight() > 0.0).collect();
+    views.sort_by(|a, b| b.weight().total_cmp(&a.weight()));
+    let reference = *views.first()?;
+
+    // Measured from the most trusted bearing, so views across ±180 average correctly
+    let diffs: Vec<f64> = views
+        .iter()
+        .map(|v| angle_diff(v.bearing_deg, reference.bearing_deg))
+        .collect();
+    let spread_deg = diffs.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+        - diffs.iter().copied().fold(f64::INFINITY, f64::min);
+    let disagreement = spread_deg > max_disagreement_deg;
+    let bearing_deg = if disagreement {
+        reference.bearing_deg
+    } else {
+        let total: f64 = views.iter().map(|v| v.weight()).sum();
+        let mean_diff: f64 = views
+            .iter()
+            .zip(&diffs)
+            .map(|(v, diff)| v.weight() * diff)
+            .sum::<f64>()
+            / total;
+        angle_diff(reference.bearing_deg + mean_diff, 0.0)
+    };
+
+    Some(FusedDetection {
+        id: reference.detection.id,
+        bearing_deg,
+        cameras: views.iter().map(|v| v.camera).collect(),
+        detection: reference.detection,
+        spread_deg,
+        disagreement,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tag_detector::TagFamily;
+
+    fn view(camera: usize, bearing_deg: f64, margin: f64, trust: f64) -> CameraView {
+        CameraView {
+            camera,
+            detection: TagDetection {
+                id: 5,
+                family: TagFamily::Tag36h11,
+                corners: [[0.0; 2]; 4],
+                center: [0.0; 2],
+                decision_margin: margin,
+                hamming: 0,
+            },
+            bearing_deg,
+            trust,
+        }
+    }
+
+    #[test]
+    fn test_fuse_weights_margin_and_trust() {
+        // Front camera: margin 60, trust 1. Side camera: margin 40, trust 0.5
+        let fused = fuse(&[view(1, 14.0, 40.0, 0.5), view(0, 10.0, 60.0, 1.0)], 10.0).unwrap();
+        assert_eq!(fused.id, 5);
+        assert_eq!(fused.cameras, [0, 1]);
+        assert_eq!(fused.detection.decision_margin, 60.0);
+        assert!(
+            (fused.bearing_deg - 11.0).abs() < 1e-9,
+            "{}",
+            fused.bearing_deg
+        );
+        assert!((fused.spread_deg - 4.0).abs() < 1e-9);
+        assert!(!fused.disagreement);
+
+        // A single camera is passed through
+        let single = fuse(&[view(2, -30.0, 20.0, 1.0)], 10.0).unwrap();
+        assert_eq!(single.bearing_deg, -30.0);
+        assert_eq!(single.spread_deg, 0.0);
+        assert_eq!(single.cameras, [2]);
+
+        // Zero trust leaves a camera out
+        let untrusted = fuse(&[view(0, 10.0, 60.0, 1.0), view(1, 50.0, 90.0, 0.0)], 10.0).unwrap();
+        assert_eq!(untrusted.cameras, [0]);
+        assert_eq!(untrusted.bearing_deg, 10.0);
+        assert!(fuse(&[view(0, 10.0, 60.0, 0.0)], 10.0).is_none());
+        assert!(fuse(&[], 10.0).is_none());
+    }
+
+    #[test]
+    fn test_fuse_flags_disagreement() {
+        let fused = fuse(&[view(0, 10.0, 60.0, 1.0), view(1, 35.0, 50.0, 1.0)], 10.0).unwrap();
+        assert!(fused.disagreement);
+        assert_eq!(fused.spread_deg, 25.0);
+        // Not averaged: the most trusted camera's bearing is kept
+        assert_eq!(fused.bearing_deg, 10.0);
+        assert_eq!(fused.cameras, [0, 1]);
+    }
+
+    #[test]
+    fn test_fuse_across_the_rear() {
+        // Two rear-facing views either side of 180 degrees average behind the robot
+        let fused = fuse(
+            &[view(0, 178.0, 50.0, 1.0), view(1, -176.0, 50.0, 1.0)],
+            10.0,
+        )
+        .unwrap();
+        assert!(!fused.disagreement);
+        assert!((fused.spread_deg - 6.0).abs() < 1e-9);
+        assert!(
+            (fused.bearing_deg - -179.0).abs() < 1e-9,
+            "{}",
+            fused.bearing_deg
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(FusionConfig::default().validate().is_ok());
+        let negative = FusionConfig {
+            max_disagreement_deg: -1.0,
+            ..FusionConfig::default()
+        };
+        assert!(negative.validate().is_err());
+        assert!(CameraMount::default().validate().is_ok());
+        let mount = |yaw_deg, trust| CameraMount { yaw_deg, trust };
+        assert!(mount(90.0, 0.0).validate().is_ok());
+        assert!(mount(f64::NAN, 1.0).validate().is_err());
+        assert!(mount(0.0, -0.5).validate().is_err());
+    }
+}
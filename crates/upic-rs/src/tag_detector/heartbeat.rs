@@ -0,0 +1,69 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::sync::Recover;
+
+/// Expected beats that may be missed before the detection thread counts as stuck
+const MISSED_BEATS: u32 = 3;
+
+/// Shortest interval beats are judged by, so a fast loop isn't reported stuck
+/// over scheduling jitter
+const MIN_BEAT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Proof of life the detection thread gives once per iteration
+///
+/// Each beat says when the next one is due, so known waits such as a halt or a
+/// reconnect backoff don't count as the thread being stuck, while a read or
+/// decode that hangs does.
+pub(crate) struct Heartbeat {
+    /// Time of the last beat and the interval the next one is expected within
+    last: Mutex<(Instant, Duration)>,
+}
+
+impl Heartbeat {
+    pub(crate) fn new(now: Instant) -> Self {
+        Heartbeat {
+            last: Mutex::new((now, Duration::ZERO)),
+        }
+    }
+
+    /// Record a beat, expecting the next one within `interval`.
+    pub(crate) fn beat(&self, now: Instant, interval: Duration) {
+        *self.last.lock().recover() = (now, interval);
+    }
+
+    /// Whether the last beat is recent enough for the thread to count as running.
+    ///
+    /// # Returns
+    ///
+    /// `false` once `MISSED_BEATS` expected beats, at least `MIN_BEAT_INTERVAL`
+    /// apart, have passed since the last beat.
+    pub(crate) fn is_fresh(&self, now: Instant) -> bool {
+        let (last, interval) = *self.last.lock().recover();
+        now.saturating_duration_since(last) <= interval.max(MIN_BEAT_INTERVAL) * MISSED_BEATS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missed_beats() {
+        let start = Instant::now();
+        let heartbeat = Heartbeat::new(start);
+        // Short intervals are judged by the minimum
+        assert!(heartbeat.is_fresh(start + MIN_BEAT_INTERVAL * MISSED_BEATS));
+        assert!(!heartbeat.is_fresh(start + MIN_BEAT_INTERVAL * (MISSED_BEATS + 1)));
+
+        // A long announced wait is not a missed beat
+        heartbeat.beat(start, Duration::from_secs(1));
+        assert!(heartbeat.is_fresh(start + Duration::from_secs(2)));
+        assert!(!heartbeat.is_fresh(start + Duration::from_secs(4)));
+
+        let later = start + Duration::from_secs(10);
+        heartbeat.beat(later, Duration::from_millis(30));
+        assert!(heartbeat.is_fresh(later + Duration::from_millis(250)));
+        assert!(!heartbeat.is_fresh(later + Duration::from_millis(350)));
+    }
+}
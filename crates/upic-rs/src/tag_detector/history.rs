@@ -0,0 +1,109 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Recently detected tags, oldest first, as `(read time, ID)`
+///
+/// Kept by the detection thread behind `TagDetector::recent_tags()` and
+/// `TagDetector::last_seen()`. Once full, each new entry drops the oldest one.
+pub(crate) struct TagHistory {
+    entries: VecDeque<(Instant, i32)>,
+    capacity: usize,
+}
+
+impl TagHistory {
+    pub(crate) fn new(capacity: usize) -> Self {
+        TagHistory {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Change the number of entries kept, dropping the oldest ones if needed.
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.entries.len() > capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Record tags detected in a frame read at `at`.
+    pub(crate) fn record(&mut self, at: Instant, ids: impl IntoIterator<Item = i32>) {
+        if self.capacity == 0 {
+            return;
+        }
+        for id in ids {
+            if self.entries.len() == self.capacity {
+                self.entries.pop_front();
+            }
+            self.entries.push_back((at, id));
+        }
+    }
+
+    /// Entries read within `window` before `now`, oldest first.
+    pub(crate) fn recent(&self, window: Duration, now: Instant) -> Vec<(Instant, i32)> {
+        // Entries are in read order, so only the recent tail is visited
+        let recent = self
+            .entries
+            .iter()
+            .rev()
+            .take_while(|(at, _)| now.saturating_duration_since(*at) <= window)
+            .count();
+        self.entries
+            .range(self.entries.len() - recent..)
+            .copied()
+            .collect()
+    }
+
+    /// Read time of the latest entry with `id`.
+    pub(crate) fn last_seen(&self, id: i32) -> Option<Instant> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|&&(_, entry_id)| entry_id == id)
+            .map(|&(at, _)| at)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_window_and_capacity() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut history = TagHistory::new(4);
+        history.record(at(0), [1]);
+        history.record(at(100), [2, 3]);
+        history.record(at(200), [1]);
+        assert_eq!(history.last_seen(1), Some(at(200)));
+        assert_eq!(history.last_seen(7), None);
+        assert_eq!(
+            history.recent(Duration::from_millis(150), at(250)),
+            [(at(100), 2), (at(100), 3), (at(200), 1)]
+        );
+
+        // Once full the oldest entries go first
+        history.record(at(300), [4]);
+        assert_eq!(history.recent(Duration::from_secs(1), at(300)).len(), 4);
+        assert_eq!(
+            history.recent(Duration::from_secs(1), at(300))[0],
+            (at(100), 2)
+        );
+        history.set_capacity(2);
+        assert_eq!(
+            history.recent(Duration::from_secs(1), at(300)),
+            [(at(200), 1), (at(300), 4)]
+        );
+
+        history.clear();
+        assert!(history.recent(Duration::from_secs(1), at(300)).is_empty());
+        let mut disabled = TagHistory::new(0);
+        disabled.record(at(0), [1]);
+        assert_eq!(disabled.last_seen(1), None);
+    }
+}
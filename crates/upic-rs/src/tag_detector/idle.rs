@@ -0,0 +1,179 @@
+use std::time::{Duration, Instant};
+
+use super::config::IdlePolicy;
+
+/// Tracks detection activity and decides the detection loop's frame interval.
+///
+/// Kept free of camera types so the timing logic can be exercised with
+/// synthetic instants.
+pub(crate) struct IdleTracker {
+    policy: Option<IdlePolicy>,
+    full_interval: Duration,
+    last_activity: Instant,
+    idle: bool,
+}
+
+impl IdleTracker {
+    pub(crate) fn new(policy: Option<IdlePolicy>, full_interval: Duration, now: Instant) -> Self {
+        IdleTracker {
+            policy,
+            full_interval,
+            last_activity: now,
+            idle: false,
+        }
+    }
+
+    /// Replace the interval returned at full rate.
+    pub(crate) fn set_full_interval(&mut self, full_interval: Duration) {
+        self.full_interval = full_interval;
+    }
+
+    /// Replace the policy; removing it leaves idle mode.
+    pub(crate) fn set_policy(&mut self, policy: Option<IdlePolicy>) {
+        if policy.is_none() && self.idle {
+            self.idle = false;
+            log::info!("Idle policy removed, returning to full frame rate");
+        }
+        self.policy = policy;
+    }
+
+    /// Record the outcome of one loop iteration.
+    ///
+    /// # Arguments
+    ///
+    /// * `detected` - Whether this iteration produced an accepted detection.
+    /// * `now` - The time of the iteration.
+    ///
+    /// # Returns
+    ///
+    /// The delay before the next iteration: the full-rate interval, or the
+    /// reduced-rate interval while idle.
+    pub(crate) fn observe(&mut self, detected: bool, now: Instant) -> Duration {
+        let Some(policy) = self.policy else {
+            return self.full_interval;
+        };
+
+        if detected && (!self.idle || policy.wake_on_detection) {
+            self.last_activity = now;
+            if self.idle {
+                self.idle = false;
+                log::info!("Detection seen, returning to full frame rate");
+            }
+        }
+
+        if !self.idle && now.duration_since(self.last_activity) >= policy.after {
+            self.idle = true;
+            log::info!(
+                "No detections for {:?}, dropping to {} FPS",
+                policy.after,
+                policy.reduced_fps
+            );
+        }
+
+        if self.idle {
+            Duration::from_secs_f64(1.0 / policy.reduced_fps.max(f64::EPSILON))
+        } else {
+            self.full_interval
+        }
+    }
+
+    /// Leave idle mode and restart the inactivity timer.
+    pub(crate) fn wake(&mut self, now: Instant) {
+        self.last_activity = now;
+        if self.idle {
+            self.idle = false;
+            log::info!("Full frame rate resumed on request");
+        }
+    }
+
+    pub(crate) fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    /// Resolution multiplier to cap the camera at, `None` at full rate.
+    pub(crate) fn resolution_cap(&self) -> Option<f64> {
+        self.policy
+            .filter(|_| self.idle)
+            .and_then(|policy| policy.resolution_multiplier)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FULL: Duration = Duration::from_millis(33);
+
+    fn policy(wake_on_detection: bool) -> Option<IdlePolicy> {
+        Some(IdlePolicy {
+            after: Duration::from_secs(5),
+            reduced_fps: 2.0,
+            wake_on_detection,
+            resolution_multiplier: None,
+        })
+    }
+
+    #[test]
+    fn test_drops_rate_after_quiet_period() {
+        let start = Instant::now();
+        let mut tracker = IdleTracker::new(policy(true), FULL, start);
+
+        assert_eq!(tracker.observe(false, start + Duration::from_secs(4)), FULL);
+        assert!(!tracker.is_idle());
+        assert_eq!(
+            tracker.observe(false, start + Duration::from_secs(5)),
+            Duration::from_millis(500)
+        );
+        assert!(tracker.is_idle());
+    }
+
+    #[test]
+    fn test_detection_wakes_instantly() {
+        let start = Instant::now();
+        let mut tracker = IdleTracker::new(policy(true), FULL, start);
+        tracker.observe(false, start + Duration::from_secs(6));
+        assert!(tracker.is_idle());
+
+        assert_eq!(tracker.observe(true, start + Duration::from_secs(7)), FULL);
+        assert!(!tracker.is_idle());
+    }
+
+    #[test]
+    fn test_resolution_cap_while_idle() {
+        let start = Instant::now();
+        let policy = policy(true).map(|policy| IdlePolicy {
+            resolution_multiplier: Some(0.5),
+            ..policy
+        });
+        let mut tracker = IdleTracker::new(policy, FULL, start);
+        assert_eq!(tracker.resolution_cap(), None);
+        tracker.observe(false, start + Duration::from_secs(6));
+        assert_eq!(tracker.resolution_cap(), Some(0.5));
+        tracker.wake(start + Duration::from_secs(7));
+        assert_eq!(tracker.resolution_cap(), None);
+    }
+
+    #[test]
+    fn test_detection_ignored_without_wake_on_detection() {
+        let start = Instant::now();
+        let mut tracker = IdleTracker::new(policy(false), FULL, start);
+        tracker.observe(false, start + Duration::from_secs(6));
+
+        tracker.observe(true, start + Duration::from_secs(7));
+        assert!(tracker.is_idle());
+
+        tracker.wake(start + Duration::from_secs(8));
+        assert_eq!(tracker.observe(false, start + Duration::from_secs(9)), FULL);
+    }
+
+    #[test]
+    fn test_no_policy_keeps_full_rate() {
+        let start = Instant::now();
+        let mut tracker = IdleTracker::new(None, FULL, start);
+        assert_eq!(
+            tracker.observe(false, start + Duration::from_secs(60)),
+            FULL
+        );
+        assert!(!tracker.is_idle());
+    }
+}
@@ -1,15 +1,158 @@
+mod autoscale;
 mod bench;
+mod bracket;
+#[cfg(feature = "opencv")]
+mod camera;
+mod capture;
 mod config;
+mod debounce;
+mod decode;
+mod detection;
+// Stand-ins for the modules that need OpenCV throughout
+#[cfg(not(feature = "opencv"))]
+mod disabled;
+mod distance;
+mod error_report;
+mod events;
+mod frame;
+mod frame_log;
+mod fusion;
+mod heartbeat;
+mod history;
+mod idle;
+mod multi;
+mod orientation;
+mod pacing;
+mod pipeline;
+mod pose;
+#[cfg(feature = "opencv")]
+mod preprocess;
+#[cfg(feature = "opencv")]
+mod preview;
+mod priority;
+mod property;
+mod reconnect;
+mod rejection;
+mod resolution;
+mod selection;
+mod smoothing;
+mod source;
+mod stats;
+mod swap;
+mod sync;
+#[cfg(feature = "tokio")]
+mod tokio_bridge;
+#[cfg(feature = "opencv")]
+mod undistort;
+mod warmup;
+mod watch;
 
-pub use bench::test_frame_time;
-pub use config::{Config, OrderingMethod};
+#[cfg(not(feature = "opencv"))]
+use disabled::{camera, preprocess, preview, undistort};
 
+pub use bench::{FrameTimeReport, benchmark_detection};
+#[cfg(feature = "opencv")]
+pub use bench::{benchmark_frames, test_frame_time};
+pub use camera::{CameraSource, VideoFileSource};
+pub use config::{
+    AutoScalePolicy, BracketConfig, Config, ConfigBuilder, DetectorParams, FrameOrientation,
+    IdlePolicy, OrderingMethod, Preprocess, PreprocessStep, ReconnectPolicy, SettleSpec, TagFamily,
+    TagSelector, WarmupPolicy,
+};
+pub use detection::TagDetection;
+pub use events::TagEvent;
+pub use frame::{Frame, Rect};
+pub use frame_log::LogFormat;
+pub use fusion::{CameraMount, FusedDetection, FusionConfig, FusionStats};
+pub use multi::{MultiTagDetector, Scheduling};
+pub use pose::{CameraIntrinsics, TagPose};
+pub use property::CameraProperties;
+pub use reconnect::CameraState;
+pub use rejection::{RejectedDetection, RejectionCounts, RejectionReason};
+pub use source::{CameraBackend, CameraProperty, FrameSource, MockFrameSource};
+pub use stats::DetectionStats;
+pub use warmup::{WarmupOutcome, warm_up};
+pub use watch::TagWatcher;
+
+#[cfg(feature = "opencv")]
 use opencv::prelude::*;
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex, RwLock};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::error::UpicError;
+use autoscale::ResolutionScaler;
+use bench::time_detection;
+use bracket::ExposureBracketer;
+use capture::{FrameRequests, encode_png, save_frame, serve_frame_requests};
+use config::check_roi;
+use detection::CenterOffset;
+use distance::{DistanceSmoother, estimate_distance, focal_length_for};
+use error_report::{ErrorCallback, ErrorReporter};
+use events::{EventSubscribers, PresenceTracker, send_events};
+use frame_log::{FrameLog, FrameRecord, SharedLog, log_frame};
+use heartbeat::Heartbeat;
+use history::TagHistory;
+use idle::IdleTracker;
+use multi::TurnGate;
+use pacing::{frame_period, remaining_budget};
+use pipeline::{FramePipeline, detect_on_caller};
+use pose::estimate_pose;
+use preview::{PreviewWindow, draw_overlay};
+use priority::apply_thread_priority;
+use property::{
+    PropertiesRequest, PropertiesRequests, PropertyRequest, PropertyRequests, apply_properties,
+    apply_property, auto_exposure_value, is_auto_exposure, serve_properties_requests,
+    serve_property_requests, snapshot_properties,
+};
+use reconnect::ReconnectTracker;
+use rejection::RejectionTrace;
+use resolution::{PROBE_TIMEOUT, ResolutionProbes, probe_resolutions, serve_resolution_probes};
+use selection::{
+    SelectionConfig, SelectionState, filter_detections, select_tag, select_unfiltered,
+};
+use stats::StatsTracker;
+use swap::{SwapRequest, SwapRequests, serve_swap_requests};
+use sync::{Recover, panic_message};
+#[cfg(feature = "tokio")]
+use tokio_bridge::{DetectionSubscribers, STREAM_CAPACITY, send_detection};
+use undistort::Undistorter;
+use warmup::mean_brightness;
+use watch::{PublishedTagId, SharedTagId};
+
+/// Largest difference between a requested and the applied camera frame rate
+/// `set_cam_fps()` accepts, which absorbs drivers reporting 29.97 for 30
+const FPS_TOLERANCE: f64 = 0.5;
+
+/// Longest time a frame capture or camera control request waits for the
+/// detection thread to answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
 
-use opencv::{Result, highgui, imgproc, videoio};
+/// Pause after a panicking detection iteration before trying the next frame
+const PANIC_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Callback receiving `(old_id, new_id)` when the published tag ID changes
+type TagChangeCallback = Arc<dyn Fn(i32, i32) + Send + Sync>;
+
+/// Run the tag change callbacks if `new_id` differs from the last reported ID.
+///
+/// Returns the ID to treat as reported from now on. The callback list is cloned
+/// first, so callbacks run without any detector lock held and may read the detector.
+fn report_tag_change(callbacks: &Mutex<Vec<TagChangeCallback>>, reported: i32, new_id: i32) -> i32 {
+    if new_id != reported {
+        let callbacks = callbacks.lock().recover().clone();
+        for callback in &callbacks {
+            callback(reported, new_id);
+        }
+    }
+    new_id
+}
 
 /// A comprehensive AprilTag detection system for real-time computer vision applications.
 ///
@@ -41,14 +184,54 @@ use opencv::{Result, highgui, imgproc, videoio};
 /// # Note
 ///
 /// The detector uses the tag36h11 family by default, which provides a good balance
-/// between detection reliability and computational efficiency.
+/// between detection reliability and computational efficiency. Other families, or
+/// several at once, can be selected with `Config::families`.
 pub struct TagDetector {
     config: Config,
-    frame_center: [f64; 2],
-    camera: Option<opencv::videoio::VideoCapture>,
-    tag_id: Arc<Mutex<i32>>,
-    continue_detection: Arc<Mutex<bool>>,
-    halt_detection: Arc<Mutex<bool>>,
+    shared_config: Arc<RwLock<Config>>,
+    /// Bumped with every change of `shared_config`, so the detection thread
+    /// only copies the config when it changed
+    config_generation: Arc<AtomicU64>,
+    frame_center: Arc<Mutex<[f64; 2]>>,
+    /// Frame size `Config::auto_scale` scaled the camera from, `None` while unscaled
+    scaled_from: Arc<Mutex<Option<(f64, f64)>>>,
+    resolution_request: Arc<Mutex<Option<(f64, f64)>>>,
+    camera: Option<Box<dyn FrameSource + Send>>,
+    tag_id: SharedTagId,
+    detection: Arc<Mutex<Option<TagDetection>>>,
+    raw_detection: Arc<Mutex<Option<TagDetection>>>,
+    unfiltered_detections: Arc<Mutex<Vec<TagDetection>>>,
+    all_detections: Arc<Mutex<Vec<TagDetection>>>,
+    offset: Arc<Mutex<Option<CenterOffset>>>,
+    pose: Arc<Mutex<Option<TagPose>>>,
+    distance: Arc<Mutex<Option<f64>>>,
+    history: Arc<Mutex<TagHistory>>,
+    rejections: Arc<Mutex<RejectionTrace>>,
+    intrinsics: Arc<Mutex<Option<CameraIntrinsics>>>,
+    focal_length_px: Arc<Mutex<Option<f64>>>,
+    tag_sizes: Arc<Mutex<HashMap<i32, f64>>>,
+    continue_detection: Arc<AtomicBool>,
+    halt_detection: Arc<(Mutex<bool>, Condvar)>,
+    heartbeat: Arc<Heartbeat>,
+    idle: Arc<AtomicBool>,
+    wake_request: Arc<AtomicBool>,
+    tag_change_callbacks: Arc<Mutex<Vec<TagChangeCallback>>>,
+    error_callbacks: Arc<Mutex<Vec<ErrorCallback>>>,
+    event_subscribers: EventSubscribers,
+    #[cfg(feature = "tokio")]
+    detection_subscribers: DetectionSubscribers,
+    stats: Arc<Mutex<DetectionStats>>,
+    camera_state: Arc<Mutex<CameraState>>,
+    frame_requests: FrameRequests,
+    property_requests: PropertyRequests,
+    properties_requests: PropertiesRequests,
+    resolution_probes: ResolutionProbes,
+    swap_requests: SwapRequests,
+    frame_log: SharedLog,
+    log_writer: Option<FrameLog>,
+    /// Decoding turns shared with the other cameras of a round-robin `MultiTagDetector`
+    decode_turns: Option<Arc<TurnGate>>,
+    detect_thread: Option<thread::JoinHandle<Box<dyn FrameSource + Send>>>,
 }
 
 impl TagDetector {
@@ -66,7 +249,8 @@ impl TagDetector {
     ///
     /// # Errors
     ///
-    /// Returns an error if camera initialization fails or resolution setting is invalid.
+    /// Returns `UpicError::InvalidConfig` if `resolution_multiplier` is not positive,
+    /// and the errors of `open_camera()` if the camera cannot be opened.
     ///
     /// # Examples
     ///
@@ -82,23 +266,12 @@ impl TagDetector {
     ///
     /// Camera buffer configuration is automatically applied when a camera is opened
     /// to ensure optimal real-time performance with minimal latency.
-    pub fn new(
-        cam_id: Option<i32>,
-        resolution_multiplier: Option<f64>,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(cam_id: Option<i32>, resolution_multiplier: Option<f64>) -> Result<Self, UpicError> {
         let config = Config::default();
-        let mut detector = TagDetector {
-            config: Config {
-                resolution_multiplier: resolution_multiplier
-                    .unwrap_or(config.resolution_multiplier),
-                ..config
-            },
-            frame_center: [0.0, 0.0],
-            camera: None,
-            tag_id: Arc::new(Mutex::new(Config::default().default_tag_id)),
-            continue_detection: Arc::new(Mutex::new(false)),
-            halt_detection: Arc::new(Mutex::new(false)),
-        };
+        let mut detector = TagDetector::with_config(Config {
+            resolution_multiplier: resolution_multiplier.unwrap_or(config.resolution_multiplier),
+            ..config
+        })?;
 
         if let Some(cam_id) = cam_id {
             detector.open_camera(cam_id)?;
@@ -107,254 +280,237 @@ impl TagDetector {
         Ok(detector)
     }
 
-    /// Configure camera buffer size for real-time performance
-    ///
-    /// This internal method sets the camera's frame buffer size to the configured value
-    /// to minimize latency in real-time applications. A smaller buffer size ensures that
-    /// frames are processed with minimal delay, which is crucial for responsive tag detection.
-    fn configure_camera_buffer(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(ref mut camera) = self.camera {
-            camera.set(
-                opencv::videoio::CAP_PROP_BUFFERSIZE,
-                self.config.buffer_size as f64,
-            )?;
-            log::info!(
-                "Camera buffer size set to {} for real-time performance",
-                self.config.buffer_size
-            );
-        }
-        Ok(())
-    }
-
-    /// Open and configure a camera device for AprilTag detection.
+    /// Initialize the TagDetector with a fully custom configuration.
     ///
-    /// This method initializes a camera connection, configures it for optimal performance,
-    /// and prepares it for tag detection. If a camera is already open, it will be properly
-    /// released before opening the new one.
+    /// Creates a detector without a camera; open one with `open_camera()` before
+    /// starting detection.
     ///
     /// # Arguments
     ///
-    /// * `device_id` - Camera device identifier. Typically 0 for the default camera.
+    /// * `config` - Detector configuration, typically built with `Config::builder()`.
     ///
     /// # Errors
     ///
-    /// Returns an error if the camera cannot be opened or configured properly.
-    pub fn open_camera(&mut self, device_id: i32) -> Result<&mut Self, Box<dyn std::error::Error>> {
-        // Release existing camera if present
-        if self.camera.is_some() {
-            self.release_camera();
-        }
-
-        // Open new camera
-        let mut camera = opencv::videoio::VideoCapture::new(device_id, opencv::videoio::CAP_ANY)?;
-
-        if camera.is_opened()? {
-            self.camera = Some(camera);
-            self.configure_camera_buffer()?;
-            self.update_cam_center()?;
-
-            // Log camera information
-            if let Some(ref camera) = self.camera {
-                let width = camera.get(opencv::videoio::CAP_PROP_FRAME_WIDTH)?;
-                let height = camera.get(opencv::videoio::CAP_PROP_FRAME_HEIGHT)?;
-                let fps = camera.get(opencv::videoio::CAP_PROP_FPS)?;
-                let buffer_size = camera.get(opencv::videoio::CAP_PROP_BUFFERSIZE)?;
-
-                log::info!(
-                    "CAMERA RESOLUTION: {}x{}\nCAMERA FPS: [{}]\nCAM CENTER: [{:?}]\nBUFFER SIZE: [{}]",
-                    width,
-                    height,
-                    fps,
-                    self.frame_center,
-                    buffer_size
-                );
-            }
-        } else {
-            return Err("Can't open camera!".into());
-        }
-
-        Ok(self)
-    }
-
-    /// Release the camera resource and clean up associated connections.
+    /// Returns `UpicError::InvalidConfig` if the config fails `Config::validate()`.
     ///
-    /// This method properly releases the camera resource to free system resources and
-    /// ensure the camera is available for other applications.
-    pub fn release_camera(&mut self) -> &mut Self {
-        if self.camera.is_some() {
-            log::info!("Releasing camera...");
-            self.camera = None;
-            log::info!("Camera released!");
-        } else {
-            log::warn!("There is no camera need to release!");
-        }
-        self
+    /// # Examples
+    ///
+    /// ```rust
+    /// let config = Config::builder()
+    ///     .ordering_method(OrderingMethod::Single)
+    ///     .buffer_size(1)
+    ///     .build()?;
+    /// let mut detector = TagDetector::with_config(config)?;
+    /// detector.open_camera(0)?;
+    /// ```
+    pub fn with_config(config: Config) -> Result<Self, UpicError> {
+        config.validate()?;
+        Ok(TagDetector {
+            frame_center: Arc::new(Mutex::new([0.0, 0.0])),
+            scaled_from: Arc::new(Mutex::new(None)),
+            resolution_request: Arc::new(Mutex::new(None)),
+            camera: None,
+            tag_id: Arc::new(PublishedTagId::new(config.default_tag_id)),
+            detection: Arc::new(Mutex::new(None)),
+            raw_detection: Arc::new(Mutex::new(None)),
+            unfiltered_detections: Arc::new(Mutex::new(Vec::new())),
+            all_detections: Arc::new(Mutex::new(Vec::new())),
+            offset: Arc::new(Mutex::new(None)),
+            pose: Arc::new(Mutex::new(None)),
+            distance: Arc::new(Mutex::new(None)),
+            history: Arc::new(Mutex::new(TagHistory::new(config.history_capacity))),
+            rejections: Arc::new(Mutex::new(RejectionTrace::new(config.rejection_capacity))),
+            intrinsics: Arc::new(Mutex::new(None)),
+            focal_length_px: Arc::new(Mutex::new(None)),
+            tag_sizes: Arc::new(Mutex::new(HashMap::new())),
+            continue_detection: Arc::new(AtomicBool::new(false)),
+            halt_detection: Arc::new((Mutex::new(false), Condvar::new())),
+            heartbeat: Arc::new(Heartbeat::new(Instant::now())),
+            idle: Arc::new(AtomicBool::new(false)),
+            wake_request: Arc::new(AtomicBool::new(false)),
+            tag_change_callbacks: Arc::new(Mutex::new(Vec::new())),
+            error_callbacks: Arc::new(Mutex::new(Vec::new())),
+            event_subscribers: Arc::new(Mutex::new(Vec::new())),
+            #[cfg(feature = "tokio")]
+            detection_subscribers: Arc::new(Mutex::new(Vec::new())),
+            stats: Arc::new(Mutex::new(DetectionStats::default())),
+            camera_state: Arc::new(Mutex::new(CameraState::Ok)),
+            frame_requests: Arc::new(Mutex::new(Vec::new())),
+            property_requests: Arc::new(Mutex::new(Vec::new())),
+            properties_requests: Arc::new(Mutex::new(Vec::new())),
+            resolution_probes: Arc::new(Mutex::new(Vec::new())),
+            swap_requests: Arc::new(Mutex::new(Vec::new())),
+            frame_log: Arc::new(Mutex::new(None)),
+            log_writer: None,
+            decode_turns: None,
+            detect_thread: None,
+            shared_config: Arc::new(RwLock::new(config.clone())),
+            config_generation: Arc::new(AtomicU64::new(0)),
+            config,
+        })
     }
 
-    /// Start AprilTag detection in a background thread.
-    ///
-    /// Initiates the AprilTag detection process by spawning a dedicated thread that
-    /// continuously processes camera frames in real-time, applying the configured tag
-    /// selection method and updating the internal tag ID.
+    /// Initialize the TagDetector with a custom frame source.
     ///
-    /// The detection process supports two ordering methods:
-    /// - `OrderingMethod::Nearest`: Selects the tag closest to the frame center
-    /// - `OrderingMethod::Single`: Selects the first detected tag in the list
+    /// Creates a detector that reads frames from `source` instead of opening a
+    /// camera device, for example a `VideoFileSource` replaying a recorded match
+    /// or a `MockFrameSource` feeding pre-rendered tag images in tests.
     ///
-    /// # Returns
+    /// # Arguments
     ///
-    /// Returns `Result<&mut Self, Box<dyn std::error::Error>>` for method chaining.
+    /// * `source` - Frame source consumed by the detection thread.
     ///
     /// # Errors
     ///
-    /// Returns an error if camera is not initialized.
+    /// Returns `UpicError::OpenCv` if configuring the source's buffer fails.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// let source = VideoFileSource::open("match.mp4", false)?.with_realtime(true);
+    /// let mut detector = TagDetector::with_source(Box::new(source))?;
     /// detector.apriltag_detect_start()?;
-    /// // Detection runs in background thread
-    /// let tag_id = detector.tag_id();
-    /// detector.apriltag_detect_end()?;
     /// ```
     ///
     /// # Note
     ///
-    /// The detection thread includes comprehensive error handling and will log
-    /// exceptions while attempting to continue operation. The thread is automatically
-    /// cleaned up when the TagDetector is dropped.
-    pub fn apriltag_detect_start(&mut self) -> Result<&mut Self, Box<dyn std::error::Error>> {
-        if self.camera.is_none() {
-            return Err("Camera is not initialized! Use open_camera() first!".into());
-        }
-
-        log::info!("Tag detecting mode: {:?}", self.config.ordering_method);
-
-        // Set detection flags
-        *self.continue_detection.lock().unwrap() = true;
-        *self.halt_detection.lock().unwrap() = false;
-
-        // Clone Arc references for the thread
-        let continue_detection = Arc::clone(&self.continue_detection);
-        let halt_detection = Arc::clone(&self.halt_detection);
-        let tag_id = Arc::clone(&self.tag_id);
-
-        // Get configuration values
-        let frame_center = self.frame_center;
-        let check_interval = self.config.halt_check_interval;
-        let default_tag_id = self.config.default_tag_id;
-        let error_tag_id = self.config.error_tag_id;
-        let ordering_method = self.config.ordering_method;
-
-        // Create detection thread
-        thread::spawn(move || {
-            log::info!("AprilTag detection thread started");
-
-            loop {
-                // Check if detection should continue
-                if !*continue_detection.lock().unwrap() {
-                    break;
-                }
-
-                // Check if detection should be halted
-                if *halt_detection.lock().unwrap() {
-                    log::debug!("AprilTag detect halted!");
-                    thread::sleep(check_interval);
-                    continue;
-                }
-
-                // Note: Actual AprilTag detection implementation would go here
-                // For now, this is a placeholder that sets default values
-
-                // Simulate detection logic
-                match ordering_method {
-                    OrderingMethod::Nearest => {
-                        // Would implement nearest tag selection based on frame_center
-                        *tag_id.lock().unwrap() = default_tag_id;
-                    }
-                    OrderingMethod::Single => {
-                        // Would implement first tag selection
-                        *tag_id.lock().unwrap() = default_tag_id;
-                    }
-                }
-
-                // Small delay to prevent busy waiting
-                thread::sleep(Duration::from_millis(33)); // ~30 FPS
-            }
-
-            log::info!("AprilTag detect stopped");
-        });
+    /// `resolution_multiplier` is not applied to custom sources; frames are
+    /// processed at whatever size the source delivers. When a finite source such
+    /// as a non-looping video file runs out of frames, detection stops and the
+    /// default tag ID is published, as after `apriltag_detect_end()`.
+    pub fn with_source(source: Box<dyn FrameSource + Send>) -> Result<Self, UpicError> {
+        let mut detector = TagDetector::new(None, None)?;
+        detector.camera = Some(source);
+        detector.configure_camera_buffer()?;
+        detector.update_cam_center()?;
+        Ok(detector)
+    }
 
-        log::info!("AprilTag detect Activated");
-        Ok(self)
+    /// Get the detector configuration.
+    pub fn config(&self) -> &Config {
+        &self.config
     }
 
-    /// Stop AprilTag detection and terminate the background thread.
+    /// Change the detector configuration, also while detection is running.
     ///
-    /// This method gracefully terminates the detection thread by setting the control
-    /// flags and resets the internal tag ID to the default value. The detection thread
-    /// will complete its current iteration and then exit cleanly.
+    /// The detection thread re-reads the configuration every frame, so changes to
+    /// the ordering method, ID filters, sentinel IDs, debouncing, idle and
+    /// reconnect policies, tag families and detector parameters take effect within
+    /// one frame; the AprilTag detector is rebuilt when the latter two change.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Function modifying a copy of the current configuration.
     ///
     /// # Returns
     ///
-    /// Returns `&mut Self` for method chaining.
+    /// Returns `Result<&mut Self, UpicError>` for method chaining.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::InvalidConfig` if the modified config fails
+    /// `Config::validate()` or its `roi` exceeds the camera's frame; the current
+    /// configuration is kept in that case.
+    /// Returns `UpicError::OpenCv` if applying a new `buffer_size` to the open
+    /// camera fails.
     ///
     /// # Examples
     ///
     /// ```rust
     /// let mut detector = TagDetector::new(Some(0), None)?;
     /// detector.apriltag_detect_start()?;
-    /// // ... perform detection operations ...
-    /// detector.apriltag_detect_end(); // Stop detection
+    /// // Lock onto the tag straight ahead from now on
+    /// detector.update_config(|config| config.ordering_method = OrderingMethod::Nearest)?;
     /// ```
     ///
     /// # Note
     ///
-    /// This method only signals the detection thread to stop; it does not forcibly
-    /// terminate the thread. The thread will stop after completing its current
-    /// processing cycle, ensuring clean shutdown without resource corruption.
-    pub fn apriltag_detect_end(&mut self) -> &mut Self {
-        *self.continue_detection.lock().unwrap() = false;
-        *self.tag_id.lock().unwrap() = self.config.default_tag_id;
-        self
+    /// A new `buffer_size` is applied to the camera right away, by the detection
+    /// thread while it runs. `warmup` applies to the next start or reconnect, and
+    /// `resolution_multiplier` only to `TagDetector::new`; use
+    /// `set_cam_resolution_mul()` to rescale an open camera.
+    pub fn update_config<F: FnOnce(&mut Config)>(&mut self, f: F) -> Result<&mut Self, UpicError> {
+        let mut config = self.config.clone();
+        f(&mut config);
+        config.validate()?;
+        if let (Some(roi), Some(frame_size)) = (config.roi, self.frame_size()) {
+            check_roi(&roi, config.orientation.oriented_size(frame_size))?;
+        }
+
+        let buffer_changed = config.buffer_size != self.config.buffer_size;
+        self.config = config;
+        self.publish_config();
+        // The detection thread applies the buffer size itself while it owns the camera
+        if buffer_changed {
+            self.configure_camera_buffer()?;
+        }
+        Ok(self)
     }
 
-    /// Temporarily halt the tag detection process without stopping the thread.
+    /// Restrict detection to a region of interest.
     ///
-    /// This method pauses the detection process while keeping the detection thread active.
-    /// The thread will enter a sleep state and periodically check for resume signals.
-    /// The tag ID is reset to the default value during the halt period.
+    /// Each frame is cropped to the region before tags are decoded, which saves
+    /// time when tags can only appear in part of a large frame. Detected corners
+    /// and centers are translated back to full-frame pixel coordinates, so
+    /// `OrderingMethod::Nearest` and pose estimation are unaffected. Takes effect
+    /// on the next frame, also while detection is running.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`, `y` - Top-left corner of the region in full-frame pixels.
+    /// * `width`, `height` - Size of the region in pixels.
     ///
     /// # Returns
     ///
-    /// Returns `&mut Self` for method chaining.
+    /// Returns `Result<&mut Self, UpicError>` for method chaining.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::InvalidConfig` if the region is empty, negative or
+    /// exceeds the current frame.
     ///
     /// # Examples
     ///
     /// ```rust
     /// let mut detector = TagDetector::new(Some(0), None)?;
-    /// detector.apriltag_detect_start()?;
-    /// detector.halt_detection();    // Pause detection
-    /// detector.resume_detection();  // Resume detection
+    /// detector.set_cam_resolution(1920, 1080)?;
+    /// // Tags only ever show up in the lower half
+    /// detector.set_roi(0, 540, 1920, 540)?;
     /// ```
     ///
     /// # Note
     ///
-    /// Unlike `apriltag_detect_end()`, this method keeps the detection thread alive
-    /// but inactive, allowing for quick resumption without thread recreation overhead.
-    /// The thread will sleep for `Config::halt_check_interval` between status checks.
-    pub fn halt_detection(&mut self) -> &mut Self {
-        *self.halt_detection.lock().unwrap() = true;
-        *self.tag_id.lock().unwrap() = self.config.default_tag_id;
+    /// If the resolution is lowered afterwards, the part of the region outside
+    /// the frame is ignored. With `Config::orientation` set, the region is given
+    /// in the rotated or flipped frame.
+    pub fn set_roi(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    ) -> Result<&mut Self, UpicError> {
+        self.update_config(|config| config.roi = Some(Rect::new(x, y, width, height)))
+    }
+
+    /// Detect over the full frame again.
+    pub fn clear_roi(&mut self) -> &mut Self {
+        self.config.roi = None;
+        self.publish_config();
         self
     }
 
-    /// Resume the halted tag detection process.
+    /// Show the frames being processed in a preview window.
     ///
-    /// This method resumes detection that was previously halted using `halt_detection()`.
-    /// The detection thread will immediately exit its sleep state and begin processing
-    /// camera frames again in the next iteration.
+    /// While enabled, the detection thread draws the outlines and IDs of the
+    /// detected tags, the published one in green, the frame center crosshair and
+    /// the region of interest on a copy of each frame and shows it in a window.
+    /// Disabling the preview closes the window. Takes effect on the next frame,
+    /// also while detection is running.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to show the preview window.
     ///
     /// # Returns
     ///
@@ -364,193 +520,3364 @@ impl TagDetector {
     ///
     /// ```rust
     /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.set_preview(true);
     /// detector.apriltag_detect_start()?;
-    /// detector.halt_detection();    // Pause detection
-    /// // ... do other work ...
-    /// detector.resume_detection();  // Resume detection immediately
     /// ```
     ///
     /// # Note
     ///
-    /// This method only works if the detection thread is currently active but halted.
-    /// If the detection thread has been stopped with `apriltag_detect_end()`, you must
-    /// call `apriltag_detect_start()` instead to restart the detection process.
-    pub fn resume_detection(&mut self) -> &mut Self {
-        *self.halt_detection.lock().unwrap() = false;
+    /// The preview needs a display and costs a frame copy plus drawing time per
+    /// frame, so it is meant for tuning on a desk rather than for matches. With
+    /// the preview disabled nothing is drawn or copied.
+    pub fn set_preview(&mut self, enabled: bool) -> &mut Self {
+        self.config.show_preview = enabled;
+        self.publish_config();
         self
     }
 
-    /// Get the currently detected AprilTag ID.
+    /// Current size of the frames the camera delivers, before
+    /// `Config::orientation`, from the camera or, while the detection thread
+    /// owns the camera, from the shared frame center. Under `Config::auto_scale`
+    /// this is the unscaled size `Config::roi` is given in.
+    fn frame_size(&self) -> Option<(f64, f64)> {
+        match &self.camera {
+            Some(camera) => Some(camera.resolution()),
+            None if self.detect_thread.is_some() => {
+                if let Some(scaled_from) = *self.scaled_from.lock().recover() {
+                    return Some(scaled_from);
+                }
+                let [center_x, center_y] = *self.frame_center.lock().recover();
+                Some((center_x * 2.0, center_y * 2.0))
+            }
+            None => None,
+        }
+    }
+
+    /// Make the detection thread see the current configuration
+    fn publish_config(&mut self) {
+        *self.shared_config.write().recover() = self.config.clone();
+        self.config_generation.fetch_add(1, Ordering::Release);
+    }
+
+    /// Configure camera buffer size for real-time performance
     ///
-    /// This method provides thread-safe access to the most recently detected tag ID.
-    /// The value is updated continuously by the detection thread when active.
+    /// This internal method sets the camera's frame buffer size to the configured value
+    /// to minimize latency in real-time applications. A smaller buffer size ensures that
+    /// frames are processed with minimal delay, which is crucial for responsive tag detection.
+    fn configure_camera_buffer(&mut self) -> Result<(), UpicError> {
+        if let Some(ref mut camera) = self.camera {
+            camera.set_buffer_size(self.config.buffer_size)?;
+            log::info!(
+                "Camera buffer size set to {} for real-time performance",
+                self.config.buffer_size
+            );
+        }
+        Ok(())
+    }
+
+    /// Open and configure a camera device for AprilTag detection.
     ///
-    /// # Returns
+    /// This method initializes a camera connection, configures it for optimal performance,
+    /// and prepares it for tag detection. If a camera is already open, it will be properly
+    /// released before opening the new one. While detection runs, detection is
+    /// switched to the new camera as with `set_source()`.
     ///
-    /// Returns the ID of the currently detected AprilTag. Returns `Config::default_tag_id`
-    /// when no tags are detected, `Config::error_tag_id` when camera errors occur,
-    /// or the actual tag ID when a tag is successfully detected.
+    /// # Arguments
     ///
-    /// # Examples
+    /// * `device_id` - Camera device identifier. Typically 0 for the default camera.
     ///
-    /// ```rust
-    /// let mut detector = TagDetector::new(Some(0), None)?;
-    /// detector.apriltag_detect_start()?;
-    /// let current_tag = detector.tag_id();
-    /// if current_tag >= 0 {
-    ///     println!("Detected tag: {}", current_tag);
-    /// } else if current_tag == detector.config.default_tag_id {
-    ///     println!("No tag detected");
-    /// } else if current_tag == detector.config.error_tag_id {
-    ///     println!("Camera error");
-    /// }
-    /// ```
+    /// # Errors
+    ///
+    /// Returns `UpicError::CameraOpenFailed` if the device cannot be opened, or
+    /// `UpicError::OpenCv` if the OpenCV backend fails while configuring it.
+    /// While detection runs, also returns the errors of `set_source()`.
     ///
     /// # Note
     ///
-    /// The tag ID is updated atomically by the detection thread, so this method
-    /// is safe to access from multiple threads without additional synchronization.
-    pub fn tag_id(&self) -> i32 {
-        *self.tag_id.lock().unwrap()
+    /// If frame reads keep failing while detection runs, the detection thread
+    /// reopens the same device according to `Config::reconnect`, restoring the
+    /// resolution and buffer size set through this detector.
+    ///
+    /// While detection runs the new camera is opened before the old one is
+    /// released, so reopening the device detection reads from fails on drivers
+    /// that refuse a second handle.
+    pub fn open_camera(&mut self, device_id: i32) -> Result<&mut Self, UpicError> {
+        self.prepare_camera_switch()?;
+
+        // Open new camera; the detection thread reopens it by device ID if it drops out
+        let camera = CameraSource::open(device_id)?;
+        self.install_camera(camera)
     }
 
-    /// Update the internal frame center coordinates based on current camera resolution.
+    /// Open a camera device and replay saved camera settings onto it.
     ///
-    /// This internal method recalculates the center point of the camera frame based on
-    /// the current camera resolution settings. It's used internally for "nearest" tag
-    /// selection mode to determine which detected tag is closest to the frame center.
+    /// Works like `open_camera()` followed by `apply_camera_properties()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_id` - Camera device identifier. Typically 0 for the default camera.
+    /// * `properties` - Settings to apply right after opening, as captured with
+    ///   `snapshot_camera_properties()`.
     ///
     /// # Errors
     ///
-    /// Returns an error if camera properties cannot be accessed.
+    /// Returns the errors of `open_camera()` and `apply_camera_properties()`.
     ///
-    /// # Note
+    /// # Examples
     ///
-    /// This is an internal method and should not be called directly by users.
-    /// It is automatically invoked when camera resolution changes or camera
-    /// is opened/configured.
-    fn update_cam_center(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(ref camera) = self.camera {
-            let width = camera.get(opencv::videoio::CAP_PROP_FRAME_WIDTH)?;
-            let height = camera.get(opencv::videoio::CAP_PROP_FRAME_HEIGHT)?;
-            self.frame_center = [width / 2.0, height / 2.0];
-        }
-        Ok(())
+    /// ```rust
+    /// let properties: CameraProperties = toml::from_str(&std::fs::read_to_string("camera.toml")?)?;
+    /// let mut detector = TagDetector::new(None, None)?;
+    /// detector.open_camera_with_properties(0, &properties)?;
+    /// ```
+    pub fn open_camera_with_properties(
+        &mut self,
+        device_id: i32,
+        properties: &CameraProperties,
+    ) -> Result<&mut Self, UpicError> {
+        self.open_camera(device_id)?
+            .apply_camera_properties(properties)
     }
 
-    /// Set camera resolution by multiplying current resolution with a scaling factor.
+    /// Open and configure a camera stream for AprilTag detection.
     ///
-    /// This method provides a convenient way to scale the camera resolution while
-    /// maintaining the aspect ratio. It's particularly useful for balancing detection
-    /// performance with processing speed.
+    /// Works like `open_camera()`, but opens the camera by path, stream URL or
+    /// GStreamer pipeline string instead of a local device index.
     ///
     /// # Arguments
     ///
-    /// * `resolution_multiplier` - Scaling factor for resolution adjustment.
-    ///   Values > 1.0 increase resolution (better quality, slower processing),
-    ///   values < 1.0 decrease resolution (faster processing, lower quality).
-    ///   For example, 0.5 reduces resolution to half in both dimensions.
-    ///
-    /// # Returns
-    ///
-    /// Returns `Result<&mut Self, Box<dyn std::error::Error>>` for method chaining.
+    /// * `path` - Device path, stream URL such as `rtsp://...`, or GStreamer pipeline.
+    /// * `backend` - Capture backend that understands `path`.
     ///
     /// # Errors
     ///
-    /// Returns an error if camera is not initialized or resolution cannot be set.
+    /// Returns `UpicError::StreamOpenFailed` if the stream cannot be opened, or
+    /// `UpicError::OpenCv` if the OpenCV backend fails while configuring it.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// let mut detector = TagDetector::new(Some(0), None)?;
-    /// detector.set_cam_resolution_mul(0.5)?;   // Half resolution for speed
-    /// detector.set_cam_resolution_mul(1.5)?;   // 1.5x resolution for quality
+    /// let mut detector = TagDetector::new(None, None)?;
+    /// // Fall back to the local camera when the overhead stream is down
+    /// match detector.open_camera_path("rtsp://10.0.0.5/overhead", CameraBackend::FFmpeg) {
+    ///     Err(UpicError::StreamOpenFailed { .. }) => {
+    ///         detector.open_camera(0)?;
+    ///     }
+    ///     result => {
+    ///         result?;
+    ///     }
+    /// }
     /// ```
     ///
     /// # Note
     ///
-    /// The actual resolution set may be adjusted by the camera driver to the
-    /// nearest supported resolution. The method automatically updates the frame
-    /// center calculations after resolution changes.
+    /// Buffer configuration, resolution changes and reconnecting after read
+    /// failures behave as for cameras opened with `open_camera()`.
+    pub fn open_camera_path(
+        &mut self,
+        path: &str,
+        backend: CameraBackend,
+    ) -> Result<&mut Self, UpicError> {
+        self.prepare_camera_switch()?;
+
+        let camera = CameraSource::open_path(path, backend)?;
+        self.install_camera(camera)
+    }
+
+    /// Release the current camera before opening another one, unless detection
+    /// runs and switches cameras itself
+    fn prepare_camera_switch(&mut self) -> Result<(), UpicError> {
+        if self.detect_thread.is_some() {
+            if !self.config.hot_swap {
+                return Err(UpicError::DetectionRunning);
+            }
+        } else if self.camera.is_some() {
+            self.release_camera();
+        }
+        Ok(())
+    }
+
+    /// Configure a freshly opened camera and make it the frame source
+    fn install_camera(&mut self, camera: CameraSource) -> Result<&mut Self, UpicError> {
+        self.set_source(Box::new(camera))?;
+
+        // Log camera information
+        #[cfg(feature = "opencv")]
+        if let Some(camera) = self.camera_device() {
+            let width = camera.get(opencv::videoio::CAP_PROP_FRAME_WIDTH)?;
+            let height = camera.get(opencv::videoio::CAP_PROP_FRAME_HEIGHT)?;
+            let fps = camera.get(opencv::videoio::CAP_PROP_FPS)?;
+            let buffer_size = camera.get(opencv::videoio::CAP_PROP_BUFFERSIZE)?;
+
+            log::info!(
+                "CAMERA RESOLUTION: {}x{}\nCAMERA FPS: [{}]\nCAM CENTER: [{:?}]\nBUFFER SIZE: [{}]",
+                width,
+                height,
+                fps,
+                *self.frame_center.lock().recover(),
+                buffer_size
+            );
+        }
+
+        Ok(self)
+    }
+
+    /// Replace the frame source, also while detection is running.
+    ///
+    /// Without a detection thread the source simply replaces the current one.
+    /// While detection runs, the new source is set to the configured buffer
+    /// size and the frame size of the source it replaces, so the ROI and frame
+    /// center stay valid, and is warmed up according to `Config::warmup`. Then
+    /// detection is halted, the detection thread switches to the new source and
+    /// updates the frame center, and detection resumes unless it was halted
+    /// before. The replaced source is released by this call.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Frame source to read from from now on.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<&mut Self, UpicError>` for method chaining.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::DetectionRunning` while detection runs with
+    /// `Config::hot_swap` off, `UpicError::DetectionNotRunning` if the detection
+    /// thread stops before switching, or the error of setting the buffer size
+    /// or warming up the new source. A source that fails to set up is dropped
+    /// and the current one stays in use.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// // Switch to the rear camera mid-match
+    /// let rear = CameraSource::open(1)?;
+    /// detector.set_source(Box::new(rear))?;
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// Detection publishes `Config::default_tag_id` from the halt until the
+    /// first frame of the new source has been decoded.
+    pub fn set_source(
+        &mut self,
+        mut source: Box<dyn FrameSource + Send>,
+    ) -> Result<&mut Self, UpicError> {
+        if self.detect_thread.is_none() {
+            self.camera = Some(source);
+            self.configure_camera_buffer()?;
+            self.update_cam_center()?;
+            return Ok(self);
+        }
+        if !self.config.hot_swap {
+            return Err(UpicError::DetectionRunning);
+        }
+
+        // Set up the new source while detection still reads from the old one
+        source.set_buffer_size(self.config.buffer_size)?;
+        if let Some((width, height)) = self.frame_size()
+            && let Err(e) = source.set_resolution(width, height)
+        {
+            log::warn!("Can't set camera resolution: {}", e);
+        }
+        let outcome = warm_up(source.as_mut(), &self.config.warmup)?;
+        log::info!(
+            "Camera warm-up: discarded {} frames in {:?}, settled: {:?}",
+            outcome.frames_discarded,
+            outcome.elapsed,
+            outcome.settled
+        );
+
+        let was_halted = self.is_halted();
+        self.halt_detection();
+        let replaced = self.request_swap(source, outcome);
+        if !was_halted {
+            self.resume_detection();
+        }
+        // Released here instead of stalling the detection thread
+        drop(replaced?);
+        Ok(self)
+    }
+
+    /// Hand a new frame source to the detection thread and wait for the one it replaced
+    fn request_swap(
+        &self,
+        source: Box<dyn FrameSource + Send>,
+        warmup: WarmupOutcome,
+    ) -> Result<Box<dyn FrameSource + Send>, UpicError> {
+        // Queued under the halt lock like capture requests, so a halted thread wakes
+        let (reply, replaced) = mpsc::channel();
+        {
+            let (halted, wakeup) = &*self.halt_detection;
+            let _halted = halted.lock().recover();
+            self.swap_requests.lock().recover().push(SwapRequest {
+                source,
+                warmup,
+                reply,
+            });
+            wakeup.notify_all();
+        }
+        replaced
+            .recv_timeout(REQUEST_TIMEOUT)
+            .map_err(|_| UpicError::DetectionNotRunning)
+    }
+
+    /// Release the camera resource and clean up associated connections.
+    ///
+    /// This method properly releases the camera resource to free system resources and
+    /// ensure the camera is available for other applications.
+    pub fn release_camera(&mut self) -> &mut Self {
+        if self.camera.is_some() {
+            log::info!("Releasing camera...");
+            self.camera = None;
+            log::info!("Camera released!");
+        } else {
+            log::warn!("There is no camera need to release!");
+        }
+        self
+    }
+
+    /// Start AprilTag detection in a background thread.
+    ///
+    /// Initiates the AprilTag detection process by spawning a dedicated thread that
+    /// continuously processes camera frames in real-time, applying the configured tag
+    /// selection method and updating the internal tag ID.
+    ///
+    /// The detection process supports these ordering methods:
+    /// - `OrderingMethod::Nearest`: Selects the tag closest to the frame center
+    /// - `OrderingMethod::Single`: Selects the first detected tag in the list
+    /// - `OrderingMethod::Largest`: Selects the tag with the largest pixel area
+    /// - `OrderingMethod::ById`: Only reports the tag with the given ID
+    /// - `OrderingMethod::Custom`: Selects with a user-provided function
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<&mut Self, UpicError>` for method chaining.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::CameraNotInitialized` if no camera is open, or
+    /// `UpicError::OpenCv` if reading frames during the configured warm-up fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.apriltag_detect_start()?;
+    /// // Detection runs in background thread
+    /// let tag_id = detector.tag_id();
+    /// detector.apriltag_detect_end()?;
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// The detection thread includes comprehensive error handling and will log
+    /// exceptions while attempting to continue operation. The thread is automatically
+    /// cleaned up when the TagDetector is dropped.
+    ///
+    /// Before the thread is spawned, the camera is warmed up according to
+    /// `Config::warmup`, so this call may block for up to the configured
+    /// settle `max_wait`.
+    pub fn apriltag_detect_start(&mut self) -> Result<&mut Self, UpicError> {
+        // Never leave a previous detection thread running alongside the new one
+        if self.detect_thread.is_some() {
+            log::warn!("AprilTag detection already running, restarting it");
+            self.apriltag_detect_end_join()?;
+        }
+
+        if self.camera.is_none() {
+            return Err(UpicError::CameraNotInitialized);
+        }
+        if let (Some(roi), Some(frame_size)) = (self.config.roi, self.frame_size()) {
+            check_roi(&roi, self.config.orientation.oriented_size(frame_size))?;
+        }
+
+        log::info!("Tag detecting mode: {:?}", self.config.ordering_method);
+
+        // Statistics restart with the warm-up, which the first log record reports
+        let mut stats_tracker = StatsTracker::new();
+        let mut warmup_frames = 0;
+        if let Some(camera) = self.camera.as_mut() {
+            let outcome = warm_up(camera.as_mut(), &self.config.warmup)?;
+            log::info!(
+                "Camera warm-up: discarded {} frames in {:?}, settled: {:?}",
+                outcome.frames_discarded,
+                outcome.elapsed,
+                outcome.settled
+            );
+            stats_tracker.record_warmup(outcome);
+            warmup_frames = outcome.frames_discarded;
+        }
+
+        // Set detection flags
+        self.continue_detection.store(true, Ordering::Release);
+        *self.stats.lock().recover() = stats_tracker.snapshot();
+        *self.camera_state.lock().recover() = CameraState::Ok;
+        *self.halt_detection.0.lock().recover() = false;
+        self.heartbeat
+            .beat(Instant::now(), frame_period(self.config.target_fps));
+
+        // The thread owns the frame source until it is joined
+        let source = self.camera.take().ok_or(UpicError::CameraNotInitialized)?;
+
+        // Clone Arc references for the thread
+        let continue_detection = Arc::clone(&self.continue_detection);
+        let halt_detection = Arc::clone(&self.halt_detection);
+        let heartbeat = Arc::clone(&self.heartbeat);
+        let tag_id = Arc::clone(&self.tag_id);
+        let detection = Arc::clone(&self.detection);
+        let raw_detection = Arc::clone(&self.raw_detection);
+        let unfiltered_detections = Arc::clone(&self.unfiltered_detections);
+        let all_detections = Arc::clone(&self.all_detections);
+        let offset = Arc::clone(&self.offset);
+        let pose = Arc::clone(&self.pose);
+        let distance = Arc::clone(&self.distance);
+        let history = Arc::clone(&self.history);
+        let rejections = Arc::clone(&self.rejections);
+        let focal_length_px = Arc::clone(&self.focal_length_px);
+        let intrinsics = Arc::clone(&self.intrinsics);
+        let tag_sizes = Arc::clone(&self.tag_sizes);
+        let shared_config = Arc::clone(&self.shared_config);
+        let config_generation = Arc::clone(&self.config_generation);
+        let idle = Arc::clone(&self.idle);
+        let wake_request = Arc::clone(&self.wake_request);
+        let tag_change_callbacks = Arc::clone(&self.tag_change_callbacks);
+        let error_callbacks = Arc::clone(&self.error_callbacks);
+        let event_subscribers = Arc::clone(&self.event_subscribers);
+        #[cfg(feature = "tokio")]
+        let detection_subscribers = Arc::clone(&self.detection_subscribers);
+        let stats = Arc::clone(&self.stats);
+        let camera_state = Arc::clone(&self.camera_state);
+        let frame_requests = Arc::clone(&self.frame_requests);
+        let property_requests = Arc::clone(&self.property_requests);
+        let properties_requests = Arc::clone(&self.properties_requests);
+        let resolution_probes = Arc::clone(&self.resolution_probes);
+        let swap_requests = Arc::clone(&self.swap_requests);
+        let frame_log = Arc::clone(&self.frame_log);
+        let decode_turns = self.decode_turns.clone();
+
+        // Get configuration values; the rest is re-read from the shared config
+        // whenever it changes
+        let frame_center = Arc::clone(&self.frame_center);
+        let scaled_from = Arc::clone(&self.scaled_from);
+        let resolution_request = Arc::clone(&self.resolution_request);
+        let initial_config = self.config.clone();
+
+        // The source is handed over once the thread is running, so a failed
+        // spawn leaves it with the detector
+        let (handover, handed_over) = mpsc::channel::<Box<dyn FrameSource + Send>>();
+
+        // Create detection thread
+        let builder = thread::Builder::new().name(initial_config.thread_name.clone());
+        let spawned = builder.spawn(move || {
+            let Ok(mut source) = handed_over.recv() else {
+                unreachable!("the source is sent right after spawning");
+            };
+            log::info!("AprilTag detection thread started");
+            if let Some(priority) = initial_config.thread_priority {
+                apply_thread_priority(priority);
+            }
+
+            // Last ID handed to the tag change callbacks
+            let mut reported = tag_id.id();
+
+            let mut error_reporter = ErrorReporter::new();
+            let mut buffer_size = initial_config.buffer_size;
+            let mut pipeline = match FramePipeline::new(&initial_config) {
+                Ok(pipeline) => pipeline,
+                Err(e) => {
+                    log::error!("Can't set up detection: {}", e);
+                    error_reporter.report(&error_callbacks, &e, Instant::now());
+                    let error_tag_id = initial_config.error_tag_id;
+                    tag_id.publish(error_tag_id, Some(Instant::now()));
+                    report_tag_change(&tag_change_callbacks, reported, error_tag_id);
+                    return source;
+                }
+            };
+
+            let mut undistorter = Undistorter::new();
+
+            let mut idle_tracker = IdleTracker::new(
+                initial_config.idle_policy,
+                frame_period(initial_config.target_fps),
+                Instant::now(),
+            );
+            idle.store(false, Ordering::Release);
+
+            let mut scaler = ResolutionScaler::new(
+                initial_config.auto_scale,
+                source.resolution(),
+                Instant::now(),
+            );
+            let mut bracketer = ExposureBracketer::new();
+
+            let mut selection = SelectionState::new(&SelectionConfig::from_config(&initial_config));
+            let mut presence = PresenceTracker::new(initial_config.min_consecutive_frames);
+            let mut distance_smoother = DistanceSmoother::new(initial_config.distance_smoothing);
+            let mut reconnect_tracker = ReconnectTracker::new(initial_config.reconnect);
+            let mut last_camera_state = CameraState::Ok;
+            // Index of the next frame read, for the detection log
+            let mut next_frame_index: u64 = 0;
+            stats_tracker.set_nominal_fps(source.property(CameraProperty::Fps).unwrap_or(0.0));
+            let mut preview = PreviewWindow::new();
+            // Frame buffers reused across iterations, so frames of an unchanged size
+            // are read, undistorted and oriented without allocating
+            let mut frame = Frame::default();
+            let mut undistorted = Frame::default();
+            let mut oriented = Frame::default();
+            // Most recent frame read, kept for capture requests and error frames;
+            // empty until the first frame is read
+            let mut last_frame = Frame::default();
+            // Copy of the shared config, refreshed only when its generation changes;
+            // the ROI is rescaled in it every frame
+            let mut config = initial_config.clone();
+            let mut unscaled_roi = config.roi;
+            let mut applied_generation = None;
+
+            loop {
+                // A panic in an iteration, from OpenCV or the frame source, only costs
+                // that frame instead of silently ending detection
+                let iteration = panic::catch_unwind(AssertUnwindSafe(|| {
+                    // Check if detection should continue
+                    if !continue_detection.load(Ordering::Acquire) {
+                        return ControlFlow::Break(());
+                    }
+
+                    // Pick up changes made with update_config() since the last iteration
+                    let generation = config_generation.load(Ordering::Acquire);
+                    if applied_generation != Some(generation) {
+                        config.clone_from(&shared_config.read().recover());
+                        unscaled_roi = config.roi;
+                        applied_generation = Some(generation);
+                    }
+                    if let Err(e) = pipeline.update(&config) {
+                        error_reporter.report(&error_callbacks, &e, Instant::now());
+                    }
+                    stats_tracker.set_opencl(pipeline.uses_opencl());
+                    stats_tracker.set_resolution_multiplier(scaler.multiplier());
+                    history
+                        .lock()
+                        .recover()
+                        .set_capacity(config.history_capacity);
+                    rejections
+                        .lock()
+                        .recover()
+                        .set_capacity(config.rejection_capacity);
+                    if config.buffer_size != buffer_size {
+                        if let Err(e) = source.set_buffer_size(config.buffer_size) {
+                            log::warn!("Can't set camera buffer size: {}", e);
+                        }
+                        buffer_size = config.buffer_size;
+                    }
+                    idle_tracker.set_policy(config.idle_policy);
+                    idle_tracker.set_full_interval(frame_period(config.target_fps));
+                    scaler.set_policy(config.auto_scale);
+                    presence.set_min_frames(config.min_consecutive_frames);
+                    distance_smoother.set_alpha(config.distance_smoothing);
+                    reconnect_tracker.set_policy(config.reconnect);
+                    let default_tag_id = config.default_tag_id;
+                    let error_tag_id = config.error_tag_id;
+
+                    // Apply a resolution change requested with set_cam_resolution()
+                    let requested = resolution_request.lock().recover().take();
+                    if let Some((width, height)) = requested {
+                        if let Err(e) = source.set_resolution(width, height) {
+                            log::warn!("Can't set camera resolution: {}", e);
+                        }
+                        let (actual_width, actual_height) = source.resolution();
+                        log::info!(
+                            "Set CAMERA RESOLUTION: {}x{}",
+                            actual_width as i32,
+                            actual_height as i32
+                        );
+                        *frame_center.lock().recover() = [actual_width / 2.0, actual_height / 2.0];
+                        // Auto scaling continues from the caller's resolution
+                        scaler.rebase((actual_width, actual_height), Instant::now());
+                    }
+
+                    // Camera controls are changed here, between reads, never during one
+                    for (property, accepted) in
+                        serve_property_requests(&property_requests, source.as_mut())
+                    {
+                        if property == CameraProperty::Fps {
+                            stats_tracker.set_nominal_fps(accepted);
+                        }
+                        // The caller's exposure wins over bracketing in progress
+                        if matches!(
+                            property,
+                            CameraProperty::Exposure | CameraProperty::AutoExposure
+                        ) {
+                            bracketer.cancel();
+                        }
+                    }
+                    if serve_properties_requests(&properties_requests, source.as_mut()) {
+                        bracketer.cancel();
+                        let (width, height) = source.resolution();
+                        *frame_center.lock().recover() = [width / 2.0, height / 2.0];
+                        scaler.rebase((width, height), Instant::now());
+                        stats_tracker
+                            .set_nominal_fps(source.property(CameraProperty::Fps).unwrap_or(0.0));
+                    }
+                    // The driver may not restore the exact resolution it was probed from
+                    if serve_resolution_probes(&resolution_probes, source.as_mut()) {
+                        let (width, height) = source.resolution();
+                        *frame_center.lock().recover() = [width / 2.0, height / 2.0];
+                    }
+                    // A new source was set up by the caller; only its frame size and
+                    // rate are picked up here
+                    let swap_warmups = serve_swap_requests(&swap_requests, &mut source);
+                    if !swap_warmups.is_empty() {
+                        bracketer.cancel();
+                        for outcome in swap_warmups {
+                            stats_tracker.record_warmup(outcome);
+                            warmup_frames += outcome.frames_discarded;
+                        }
+                        let (width, height) = source.resolution();
+                        *frame_center.lock().recover() = [width / 2.0, height / 2.0];
+                        scaler.rebase((width, height), Instant::now());
+                        stats_tracker
+                            .set_nominal_fps(source.property(CameraProperty::Fps).unwrap_or(0.0));
+                        // Read failures of the old source don't count against the new one
+                        reconnect_tracker.reconnected();
+                        log::info!("Switched to the new frame source, {}x{}", width, height);
+                    }
+                    *scaled_from.lock().recover() = scaler.scaled_from();
+                    // The ROI is given at the unscaled resolution
+                    config.roi = unscaled_roi;
+                    if let Some(roi) = unscaled_roi
+                        && scaler.scaled_from().is_some()
+                    {
+                        let [center_x, center_y] = *frame_center.lock().recover();
+                        config.roi = Some(scaler.scale_roi(
+                            roi,
+                            (center_x * 2.0, center_y * 2.0),
+                            config.orientation,
+                        ));
+                    }
+
+                    // Check if detection should be halted; resume and stop notify the
+                    // condvar, so the wait only times out as a safety net
+                    {
+                        let (halted, wakeup) = &*halt_detection;
+                        let guard = halted.lock().recover();
+                        if *guard {
+                            log::debug!("AprilTag detect halted!");
+                            // halt_detection() reset the published ID to the default
+                            reported =
+                                report_tag_change(&tag_change_callbacks, reported, default_tag_id);
+                            selection.reset();
+                            distance_smoother.reset();
+                            send_events(&event_subscribers, presence.reset(Instant::now()));
+                            // Bracketing never runs while halted; one in progress is undone
+                            bracketer.interrupt(source.as_mut());
+                            // Capture requests still get a fresh frame while halted
+                            if !frame_requests.lock().recover().is_empty() {
+                                match source.read_frame_into(&mut frame) {
+                                    Ok(()) => std::mem::swap(&mut frame, &mut last_frame),
+                                    Err(e) => log::warn!("Can't read a frame to capture: {}", e),
+                                }
+                                serve_frame_requests(&frame_requests, &last_frame);
+                            }
+                            heartbeat.beat(Instant::now(), config.halt_check_interval);
+                            let _ = wakeup
+                                .wait_timeout_while(guard, config.halt_check_interval, |halted| {
+                                    *halted
+                                        && continue_detection.load(Ordering::Acquire)
+                                        && frame_requests.lock().recover().is_empty()
+                                        && property_requests.lock().recover().is_empty()
+                                        && properties_requests.lock().recover().is_empty()
+                                        && resolution_probes.lock().recover().is_empty()
+                                        && swap_requests.lock().recover().is_empty()
+                                })
+                                .recover();
+                            return ControlFlow::Continue(());
+                        }
+                    }
+
+                    // Ages are measured from the read, not from publishing the result
+                    let frame_started = Instant::now();
+                    let read = source.read_frame_into(&mut frame);
+                    let read_at = Instant::now();
+                    // A finished video file stops detection like apriltag_detect_end()
+                    if matches!(read, Err(UpicError::EndOfStream)) {
+                        log::info!("Frame source ended, stopping detection");
+                        let _halted = halt_detection.0.lock().recover();
+                        continue_detection.store(false, Ordering::Release);
+                        *raw_detection.lock().recover() = None;
+                        unfiltered_detections.lock().recover().clear();
+                        all_detections.lock().recover().clear();
+                        *detection.lock().recover() = None;
+                        *offset.lock().recover() = None;
+                        *pose.lock().recover() = None;
+                        *distance.lock().recover() = None;
+                        history.lock().recover().clear();
+                        rejections.lock().recover().clear();
+                        tag_id.publish(default_tag_id, None);
+                        return ControlFlow::Break(());
+                    }
+                    let reconnect_due = reconnect_tracker.observe_read(read.is_ok());
+                    let frame_index = next_frame_index;
+                    next_frame_index += 1;
+                    if let Err(e) = &read {
+                        stats_tracker.record_read_error(reconnect_tracker.consecutive_errors());
+                        error_reporter.report(&error_callbacks, e, read_at);
+                    }
+                    let detect_started = Instant::now();
+
+                    // Undistort with the current intrinsics; the maps are rebuilt when
+                    // the resolution or the intrinsics change
+                    let undistorted_with = if config.undistort {
+                        intrinsics.lock().recover().clone()
+                    } else {
+                        None
+                    };
+                    // Swapping keeps both buffers allocated for the next frame
+                    let read = match (&undistorted_with, read) {
+                        (Some(frame_intrinsics), Ok(())) => undistorter
+                            .apply(&frame, frame_intrinsics, &mut undistorted)
+                            .map(|()| std::mem::swap(&mut frame, &mut undistorted)),
+                        (_, read) => read,
+                    };
+                    // Everything from here on, including capture and the preview, sees
+                    // the oriented frame
+                    let read = match (config.orientation, read) {
+                        (FrameOrientation::Normal, read) => read,
+                        (orientation, Ok(())) => orientation
+                            .apply(&frame, &mut oriented)
+                            .map(|()| std::mem::swap(&mut frame, &mut oriented)),
+                        (_, read) => read,
+                    };
+                    let frame_read = read.is_ok();
+
+                    // Capture and the preview get the frame before preprocessing
+                    let candidates = read.and_then(|()| {
+                        // Cameras scheduled round-robin decode one at a time
+                        let _turn = decode_turns.as_deref().map(TurnGate::enter);
+                        pipeline.detect(&frame, &config)
+                    });
+
+                    // Every decoded tag is kept for tuning the filters
+                    let unfiltered = candidates.as_ref().map_or_else(|_| Vec::new(), Vec::clone);
+
+                    // Bracketing judges the exposure by every decoded tag, including
+                    // those the margin filter is about to discard
+                    if candidates.is_ok() {
+                        let margin = unfiltered
+                            .iter()
+                            .map(|d| d.decision_margin)
+                            .reduce(f64::max);
+                        bracketer.observe(
+                            source.as_mut(),
+                            config.auto_bracket.as_ref(),
+                            margin,
+                            read_at,
+                        );
+                    }
+
+                    // Filtered tags, and tags too small or too uncertain to trust, are
+                    // discarded as if never seen, apart from the rejection trace
+                    let mut rejected = Vec::new();
+                    let candidates = candidates.map(|mut candidates| {
+                        rejected = filter_detections(&config, &mut candidates);
+                        candidates
+                    });
+                    for (_, reason) in &rejected {
+                        stats_tracker.record_rejection(*reason);
+                    }
+
+                    // Publish the selected detection once it has been debounced, None
+                    // when no tag is visible, and the error id when the frame could not
+                    // be read or decoded
+                    let source_center = *frame_center.lock().recover();
+                    let center = config.orientation.oriented_center(source_center);
+                    let (raw, selected, published) = match &candidates {
+                        Ok(candidates) => {
+                            // Debounced and voted over the last frames, against flapping
+                            // between tags; the candidates passed the filters already
+                            let voted = select_tag(
+                                candidates,
+                                &SelectionConfig::from_config(&config),
+                                center,
+                                &mut selection,
+                            );
+                            let (raw, selected) = (selection.raw(), selection.selected());
+                            stats_tracker.record_frame(
+                                raw.map(|d| d.decision_margin),
+                                detect_started.elapsed(),
+                                read_at,
+                            );
+                            (raw, selected, voted.unwrap_or(default_tag_id))
+                        }
+                        Err(e) => {
+                            log::warn!("AprilTag detection failed: {}", e);
+                            selection.reset();
+                            (None, None, error_tag_id)
+                        }
+                    };
+
+                    // In multi-tag mode every tag that passed the filters is published,
+                    // not only the one the ordering method picked
+                    let all = match &candidates {
+                        Ok(candidates) if !config.single_tag_mode => candidates.clone(),
+                        _ => Vec::new(),
+                    };
+
+                    // Tags whose presence is tracked for enter and leave events; a
+                    // failed frame counts as one without tags
+                    let visible: Vec<i32> = match &candidates {
+                        Ok(candidates) if !config.single_tag_mode => {
+                            candidates.iter().map(|d| d.id).collect()
+                        }
+                        _ => raw.iter().map(|d| d.id).collect(),
+                    };
+
+                    // Tags callers are shown, for the history; the sentinels are no detections
+                    let seen: Vec<i32> = if config.single_tag_mode {
+                        [published]
+                            .into_iter()
+                            .filter(|&id| id != default_tag_id && id != error_tag_id)
+                            .collect()
+                    } else {
+                        all.iter().map(|d| d.id).collect()
+                    };
+
+                    // Measured from the same center the ordering method used
+                    let selected_offset =
+                        selected.map(|selected| CenterOffset::new(&selected, center));
+
+                    // Estimate the pose when intrinsics and the tag's size are known
+                    let selected_pose = selected.and_then(|selected| {
+                        // Corners of an undistorted frame have no lens distortion left
+                        let intrinsics = match &undistorted_with {
+                            Some(frame_intrinsics) => frame_intrinsics.rectified(),
+                            None => intrinsics.lock().recover().clone()?,
+                        };
+                        // Intrinsics are calibrated on the frames the camera delivers
+                        let [center_x, center_y] = source_center;
+                        let intrinsics = config
+                            .orientation
+                            .orient_intrinsics(&intrinsics, (center_x * 2.0, center_y * 2.0));
+                        let tag_size = *tag_sizes.lock().recover().get(&selected.id)?;
+                        estimate_pose(&selected, &intrinsics, tag_size).unwrap_or_else(|e| {
+                            log::warn!("Pose estimation for tag {} failed: {}", selected.id, e);
+                            None
+                        })
+                    });
+
+                    // Approximate distance from the tag's apparent size, for callers
+                    // without full intrinsics
+                    let raw_distance = selected.and_then(|selected| {
+                        let size_m = *tag_sizes.lock().recover().get(&selected.id)?;
+                        let focal_px = focal_length_px
+                            .lock()
+                            .recover()
+                            .or_else(|| intrinsics.lock().recover().as_ref().map(|i| i.fx))?;
+                        Some(estimate_distance(size_m, focal_px, selected.mean_side()))
+                    });
+                    let selected_distance =
+                        distance_smoother.observe(selected.as_ref(), raw_distance);
+
+                    // Publish under the halt lock, so a frame finished after
+                    // halt_detection() or apriltag_detect_end() can't overwrite their reset
+                    {
+                        let halted = halt_detection.0.lock().recover();
+                        if *halted || !continue_detection.load(Ordering::Acquire) {
+                            return ControlFlow::Continue(());
+                        }
+                        *raw_detection.lock().recover() = raw;
+                        *unfiltered_detections.lock().recover() = unfiltered;
+                        *all_detections.lock().recover() = all;
+                        *detection.lock().recover() = selected;
+                        *offset.lock().recover() = selected_offset;
+                        *pose.lock().recover() = selected_pose;
+                        *distance.lock().recover() = selected_distance;
+                        history.lock().recover().record(read_at, seen);
+                        rejections.lock().recover().record(rejected.iter().map(
+                            |(detection, reason)| {
+                                RejectedDetection::new(detection, *reason, frame_index)
+                            },
+                        ));
+                        tag_id.publish(published, Some(read_at));
+                    }
+                    // From starting the read, so a read blocked waiting for the camera counts
+                    if frame_read {
+                        stats_tracker.record_latency(frame_started.elapsed());
+                    }
+                    send_events(&event_subscribers, presence.observe(visible, read_at));
+                    #[cfg(feature = "tokio")]
+                    if let Some(selected) = selected {
+                        send_detection(&detection_subscribers, selected);
+                    }
+                    log_frame(
+                        &frame_log,
+                        FrameRecord {
+                            timestamp: SystemTime::now(),
+                            frame: frame_index,
+                            tag_id: published,
+                            detections: candidates.as_ref().map_or(0, Vec::len),
+                            rejected: rejected.len(),
+                            read_time: read_at - frame_started,
+                            detect_time: detect_started.elapsed(),
+                            decision_margin: selected.map(|d| d.decision_margin),
+                            warmup_frames: std::mem::take(&mut warmup_frames),
+                        },
+                    );
+                    let entered_error = published == error_tag_id && reported != error_tag_id;
+                    reported = report_tag_change(&tag_change_callbacks, reported, published);
+
+                    // Draw the preview after publishing so it never delays the tag id
+                    if config.show_preview {
+                        if let Ok(candidates) = &candidates {
+                            let shown = draw_overlay(
+                                &frame,
+                                candidates,
+                                selected.as_ref(),
+                                center,
+                                config.roi,
+                            )
+                            .and_then(|canvas| preview.show(&canvas));
+                            if let Err(e) = shown {
+                                log::warn!("Can't show the preview: {}", e);
+                            }
+                        }
+                    } else {
+                        preview.close();
+                    }
+
+                    // A failed read leaves the previous frame as the most recent one
+                    if frame_read {
+                        std::mem::swap(&mut frame, &mut last_frame);
+                    }
+                    serve_frame_requests(&frame_requests, &last_frame);
+                    if entered_error && let Some(dir) = &config.error_frame_dir {
+                        if last_frame.empty() {
+                            log::warn!("No frame read yet, nothing to save as error frame");
+                        } else {
+                            match save_frame(dir, "error", &last_frame) {
+                                Ok(path) => log::info!("Saved error frame to {}", path.display()),
+                                Err(e) => log::warn!("Can't save error frame: {}", e),
+                            }
+                        }
+                    }
+                    if frame_read {
+                        stats_tracker.record_frame_time(frame_started.elapsed());
+                        // The next frame is read at the new resolution
+                        let avg_frame_time = stats_tracker.snapshot().avg_frame_time;
+                        if let Some((width, height)) =
+                            scaler.observe(avg_frame_time, Instant::now())
+                        {
+                            if let Err(e) = source.set_resolution(width, height) {
+                                log::warn!("Can't scale camera resolution: {}", e);
+                            }
+                            let (actual_width, actual_height) = source.resolution();
+                            *frame_center.lock().recover() =
+                                [actual_width / 2.0, actual_height / 2.0];
+                            *scaled_from.lock().recover() = scaler.scaled_from();
+                            stats_tracker.set_resolution_multiplier(scaler.multiplier());
+                        }
+                    }
+
+                    // Reopen the source after repeated read failures; the error id stays
+                    // published until frames can be read again
+                    if reconnect_due {
+                        log::warn!(
+                            "{} consecutive frame reads failed, reconnecting the camera",
+                            reconnect_tracker.consecutive_errors()
+                        );
+                        match source.reconnect() {
+                            Ok(()) => {
+                                reconnect_tracker.reconnected();
+                                stats_tracker.record_reconnect();
+                                log::info!("Camera reconnected");
+                                // A reopened camera needs the same warm-up as a fresh one
+                                match warm_up(source.as_mut(), &config.warmup) {
+                                    Ok(outcome) => {
+                                        stats_tracker.record_warmup(outcome);
+                                        warmup_frames += outcome.frames_discarded;
+                                    }
+                                    Err(e) => {
+                                        log::warn!("Camera warm-up after reconnect failed: {}", e)
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                let backoff = reconnect_tracker.reconnect_failed();
+                                log::warn!(
+                                    "Camera reconnect failed: {}, retrying in {:?}",
+                                    e,
+                                    backoff
+                                );
+                                error_reporter.report(&error_callbacks, &e, Instant::now());
+                                heartbeat.beat(Instant::now(), backoff);
+                                // Halting or stopping detection cuts the backoff short
+                                let (halted, wakeup) = &*halt_detection;
+                                let guard = halted.lock().recover();
+                                let _ = wakeup
+                                    .wait_timeout_while(guard, backoff, |halted| {
+                                        !*halted && continue_detection.load(Ordering::Acquire)
+                                    })
+                                    .recover();
+                            }
+                        }
+                    }
+                    *stats.lock().recover() = stats_tracker.snapshot();
+
+                    // Judged after any reconnect, so a successful one reads as Ok again
+                    let state = reconnect_tracker
+                        .camera_state(source.is_connected(), config.disconnect_after);
+                    if state != last_camera_state {
+                        match state {
+                            CameraState::Disconnected => log::error!("Camera disconnected"),
+                            CameraState::Ok if last_camera_state == CameraState::Disconnected => {
+                                log::info!("Camera connected again")
+                            }
+                            _ => {}
+                        }
+                        *camera_state.lock().recover() = state;
+                        last_camera_state = state;
+                        if state == CameraState::Disconnected {
+                            error_reporter.report(
+                                &error_callbacks,
+                                &UpicError::CameraDisconnected,
+                                Instant::now(),
+                            );
+                        }
+                    }
+
+                    if wake_request.swap(false, Ordering::AcqRel) {
+                        idle_tracker.wake(Instant::now());
+                    }
+
+                    // Any tag passing the filters wakes, before debouncing publishes it
+                    let detected = candidates.as_ref().is_ok_and(|c| !c.is_empty());
+                    let frame_interval = idle_tracker.observe(detected, Instant::now());
+                    idle.store(idle_tracker.is_idle(), Ordering::Release);
+                    // The resolution follows on the next frame read
+                    scaler.set_cap(idle_tracker.resolution_cap());
+                    if stats_tracker.snapshot().idle != idle_tracker.is_idle() {
+                        stats_tracker.set_idle(idle_tracker.is_idle());
+                        *stats.lock().recover() = stats_tracker.snapshot();
+                    }
+                    // The next beat follows the sleep, or a frame as slow as this one
+                    heartbeat.beat(Instant::now(), frame_interval.max(frame_started.elapsed()));
+
+                    // Sleep only what is left of the frame period after reading and
+                    // detecting, so the loop neither caps fast nor slows down slow hardware
+                    thread::sleep(remaining_budget(
+                        frame_interval,
+                        frame_started,
+                        Instant::now(),
+                    ));
+                    ControlFlow::Continue(())
+                }));
+                match iteration {
+                    Ok(ControlFlow::Continue(())) => {}
+                    Ok(ControlFlow::Break(())) => break,
+                    Err(panic) => {
+                        log::error!("Detection iteration panicked: {}", panic_message(&*panic));
+                        selection.reset();
+                        distance_smoother.reset();
+                        // Like a frame that failed to decode, no tag was seen
+                        send_events(&event_subscribers, presence.observe([], Instant::now()));
+                        let error_tag_id = shared_config.read().recover().error_tag_id;
+                        // Halting and stopping reset the published ID themselves
+                        let published = {
+                            let halted = halt_detection.0.lock().recover();
+                            let publish = !*halted && continue_detection.load(Ordering::Acquire);
+                            if publish {
+                                *raw_detection.lock().recover() = None;
+                                unfiltered_detections.lock().recover().clear();
+                                all_detections.lock().recover().clear();
+                                *detection.lock().recover() = None;
+                                *offset.lock().recover() = None;
+                                *pose.lock().recover() = None;
+                                *distance.lock().recover() = None;
+                                tag_id.publish(error_tag_id, Some(Instant::now()));
+                            }
+                            publish
+                        };
+                        if published {
+                            reported =
+                                report_tag_change(&tag_change_callbacks, reported, error_tag_id);
+                        }
+                        // Back off so a source that panics on every read doesn't spin;
+                        // halting or stopping detection cuts the wait short
+                        heartbeat.beat(Instant::now(), PANIC_BACKOFF);
+                        let (halted, wakeup) = &*halt_detection;
+                        let guard = halted.lock().recover();
+                        let _ = wakeup
+                            .wait_timeout_while(guard, PANIC_BACKOFF, |halted| {
+                                !*halted && continue_detection.load(Ordering::Acquire)
+                            })
+                            .recover();
+                    }
+                }
+            }
+
+            // Leave the camera at the resolution auto scaling started from
+            if let Some((width, height)) = scaler.scaled_from() {
+                if let Err(e) = source.set_resolution(width, height) {
+                    log::warn!("Can't restore camera resolution: {}", e);
+                }
+                let (actual_width, actual_height) = source.resolution();
+                *frame_center.lock().recover() = [actual_width / 2.0, actual_height / 2.0];
+            }
+            *scaled_from.lock().recover() = None;
+
+            // Fail pending capture and camera control requests instead of leaving them to time out
+            frame_requests.lock().recover().clear();
+            property_requests.lock().recover().clear();
+            properties_requests.lock().recover().clear();
+            resolution_probes.lock().recover().clear();
+            swap_requests.lock().recover().clear();
+
+            // apriltag_detect_end() reset the published ID to the default
+            let default_tag_id = shared_config.read().recover().default_tag_id;
+            report_tag_change(&tag_change_callbacks, reported, default_tag_id);
+            send_events(&event_subscribers, presence.reset(Instant::now()));
+            log::info!("AprilTag detect stopped");
+            source
+        });
+        let handle = match spawned {
+            Ok(handle) => handle,
+            Err(e) => {
+                self.continue_detection.store(false, Ordering::Release);
+                self.camera = Some(source);
+                return Err(UpicError::ThreadSpawnFailed(e));
+            }
+        };
+        // The receiving thread is alive, it only exits after taking the source
+        let _ = handover.send(source);
+        self.detect_thread = Some(handle);
+
+        log::info!("AprilTag detect Activated");
+        Ok(self)
+    }
+
+    /// Stop AprilTag detection and terminate the background thread.
+    ///
+    /// This method gracefully terminates the detection thread by setting the control
+    /// flags and resets the internal tag ID to the default value. The detection thread
+    /// will complete its current iteration and then exit cleanly.
+    ///
+    /// # Returns
+    ///
+    /// Returns `&mut Self` for method chaining.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.apriltag_detect_start()?;
+    /// // ... perform detection operations ...
+    /// detector.apriltag_detect_end(); // Stop detection
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// This method only signals the detection thread to stop; it does not forcibly
+    /// terminate the thread. The thread will stop after completing its current
+    /// processing cycle, ensuring clean shutdown without resource corruption.
+    /// Use `apriltag_detect_end_join()` to wait for the thread to exit.
+    pub fn apriltag_detect_end(&mut self) -> &mut Self {
+        self.continue_detection.store(false, Ordering::Release);
+        // Wake a halted thread so it sees the stop request immediately; holding
+        // the halt lock keeps the notification from slipping in before its wait,
+        // and the reset from being overwritten by a frame in flight
+        let (halted, wakeup) = &*self.halt_detection;
+        let guard = halted.lock().recover();
+        wakeup.notify_all();
+        self.tag_id.publish(self.config.default_tag_id, None);
+        *self.detection.lock().recover() = None;
+        *self.offset.lock().recover() = None;
+        *self.raw_detection.lock().recover() = None;
+        self.unfiltered_detections.lock().recover().clear();
+        self.all_detections.lock().recover().clear();
+        *self.pose.lock().recover() = None;
+        *self.distance.lock().recover() = None;
+        self.history.lock().recover().clear();
+        self.rejections.lock().recover().clear();
+        drop(guard);
+        self
+    }
+
+    /// Stop AprilTag detection and wait for the background thread to exit.
+    ///
+    /// Signals the detection thread like `apriltag_detect_end()`, then joins it,
+    /// so the camera is guaranteed to be idle once this method returns.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<&mut Self, UpicError>` for method chaining.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::DetectionThreadPanicked` with the panic message if the
+    /// detection thread panicked.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.apriltag_detect_start()?;
+    /// // ... perform detection operations ...
+    /// detector.apriltag_detect_end_join()?.release_camera();
+    /// detector.open_camera(0)?; // The device is free again
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// Calling this method when no detection thread is running only resets the
+    /// published tag ID, like `apriltag_detect_end()`.
+    pub fn apriltag_detect_end_join(&mut self) -> Result<&mut Self, UpicError> {
+        self.apriltag_detect_end();
+        if let Some(handle) = self.detect_thread.take() {
+            let source = handle
+                .join()
+                .map_err(|panic| UpicError::DetectionThreadPanicked(panic_message(&*panic)))?;
+            self.camera = Some(source);
+            log::info!("AprilTag detection thread joined");
+        }
+        Ok(self)
+    }
+
+    /// Temporarily halt the tag detection process without stopping the thread.
+    ///
+    /// This method pauses the detection process while keeping the detection thread active.
+    /// The thread will enter a sleep state and periodically check for resume signals.
+    /// The tag ID is reset to the default value during the halt period and
+    /// reported as stale by `is_stale()`.
+    ///
+    /// # Returns
+    ///
+    /// Returns `&mut Self` for method chaining.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.apriltag_detect_start()?;
+    /// detector.halt_detection();    // Pause detection
+    /// detector.resume_detection();  // Resume detection
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// Unlike `apriltag_detect_end()`, this method keeps the detection thread alive
+    /// but inactive, allowing for quick resumption without thread recreation overhead.
+    /// The halted thread blocks on a condition variable, so it uses no CPU and
+    /// wakes as soon as detection is resumed or stopped.
+    pub fn halt_detection(&mut self) -> &mut Self {
+        // Reset under the halt lock, which the detection thread publishes under
+        let (halted, wakeup) = &*self.halt_detection;
+        let mut halted = halted.lock().recover();
+        *halted = true;
+        // Cut a reconnect backoff short
+        wakeup.notify_all();
+        self.tag_id.publish(self.config.default_tag_id, None);
+        *self.detection.lock().recover() = None;
+        *self.offset.lock().recover() = None;
+        *self.raw_detection.lock().recover() = None;
+        self.unfiltered_detections.lock().recover().clear();
+        self.all_detections.lock().recover().clear();
+        *self.pose.lock().recover() = None;
+        *self.distance.lock().recover() = None;
+        drop(halted);
+        self
+    }
+
+    /// Resume the halted tag detection process.
+    ///
+    /// This method resumes detection that was previously halted using `halt_detection()`.
+    /// The detection thread will immediately exit its sleep state and begin processing
+    /// camera frames again in the next iteration.
+    ///
+    /// # Returns
+    ///
+    /// Returns `&mut Self` for method chaining.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.apriltag_detect_start()?;
+    /// detector.halt_detection();    // Pause detection
+    /// // ... do other work ...
+    /// detector.resume_detection();  // Resume detection immediately
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// This method only works if the detection thread is currently active but halted.
+    /// If the detection thread has been stopped with `apriltag_detect_end()`, you must
+    /// call `apriltag_detect_start()` instead to restart the detection process.
+    pub fn resume_detection(&mut self) -> &mut Self {
+        let (halted, wakeup) = &*self.halt_detection;
+        *halted.lock().recover() = false;
+        wakeup.notify_all();
+        self
+    }
+
+    /// Whether detection is running and keeping up.
+    ///
+    /// True after `apriltag_detect_start()` while the detection thread is alive
+    /// and finished an iteration recently: within a few frame intervals, or
+    /// within the wait it announced while halted or backing off before a
+    /// reconnect. A thread stuck in a frame read or a decode reports `false`
+    /// although it was never stopped. Halted detection counts as detecting, see
+    /// `is_halted()`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// // Watchdog restarting detection that died
+    /// if !detector.is_detecting() {
+    ///     if detector.thread_alive() {
+    ///         log::error!("Detection thread is stuck");
+    ///     } else {
+    ///         detector.apriltag_detect_start()?;
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// Restarting detection joins the previous thread first, which blocks for as
+    /// long as a stuck thread stays stuck. Check `thread_alive()` to tell a stuck
+    /// thread from a finished one.
+    pub fn is_detecting(&self) -> bool {
+        self.continue_detection.load(Ordering::Acquire)
+            && self.thread_alive()
+            && self.heartbeat.is_fresh(Instant::now())
+    }
+
+    /// Whether detection is halted with `halt_detection()`.
+    ///
+    /// False when no detection thread is alive.
+    pub fn is_halted(&self) -> bool {
+        self.thread_alive() && *self.halt_detection.0.lock().recover()
+    }
+
+    /// Whether the detection thread is alive, halted or not.
+    ///
+    /// Stays true after `apriltag_detect_end()` until the thread has exited, and
+    /// turns false when it exits on its own, for example because the detection
+    /// pipeline couldn't be set up. Only `apriltag_detect_end_join()` hands the
+    /// camera back.
+    pub fn thread_alive(&self) -> bool {
+        self.detect_thread
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+    }
+
+    /// Register a callback fired when the published tag ID changes.
+    ///
+    /// The callback receives `(old_id, new_id)` and is invoked from the detection
+    /// thread only when the value actually changes. Changes to and from
+    /// `Config::default_tag_id` fire too, including the reset done by
+    /// `halt_detection()` and `apriltag_detect_end()`, so a lost tag is reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Callback to register. Multiple callbacks are run in registration order.
+    ///
+    /// # Returns
+    ///
+    /// Returns `&mut Self` for method chaining.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.on_tag_change(|old, new| println!("Tag changed: {} -> {}", old, new));
+    /// detector.apriltag_detect_start()?;
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// Callbacks run without any detector lock held, so they may read the detector,
+    /// but they block the detection thread while running and should return quickly.
+    /// Callbacks registered while detection runs take effect on the next change.
+    pub fn on_tag_change<F: Fn(i32, i32) + Send + Sync + 'static>(&mut self, f: F) -> &mut Self {
+        self.tag_change_callbacks.lock().recover().push(Arc::new(f));
+        self
+    }
+
+    /// Register a callback run when the detection thread runs into an error.
+    ///
+    /// Reported are failed frame reads, failed reconnects, the camera turning
+    /// `CameraState::Disconnected`, and decoders or preprocessing that can't be
+    /// set up. Each kind of error, as told by its `UpicError` variant, is
+    /// reported at most once per second, so a camera failing every read doesn't
+    /// flood the callback. The errors are still logged as before.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Callback to register. Multiple callbacks are run in registration order.
+    ///
+    /// # Returns
+    ///
+    /// Returns `&mut Self` for method chaining.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.on_error(|e| {
+    ///     if matches!(e, UpicError::CameraDisconnected) {
+    ///         status_led.blink();
+    ///     }
+    /// });
+    /// detector.apriltag_detect_start()?;
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// Like tag change callbacks, error callbacks run on the detection thread
+    /// without any detector lock held, and should return quickly.
+    pub fn on_error<F: Fn(&UpicError) + Send + Sync + 'static>(&mut self, f: F) -> &mut Self {
+        self.error_callbacks.lock().recover().push(Arc::new(f));
+        self
+    }
+
+    /// Subscribe to tags entering and leaving the camera's view.
+    ///
+    /// In single tag mode only the tag the ordering method selects is tracked; with
+    /// it off, every tag that passes the filters is tracked separately. A tag enters
+    /// after being seen in `Config::min_consecutive_frames` consecutive frames and
+    /// leaves after missing from as many, so a one-frame dropout produces no events.
+    /// Halting and stopping detection make every tag in view leave at once.
+    ///
+    /// # Returns
+    ///
+    /// A receiver for the events, in the order the detection thread produced them.
+    /// Events within one frame list leaving tags first, each group in ID order.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// let events = detector.subscribe_events();
+    /// detector.apriltag_detect_start()?;
+    /// for event in events.iter() {
+    ///     match event {
+    ///         TagEvent::Entered { id, .. } => println!("Tag {} entered", id),
+    ///         TagEvent::Left { id, .. } => println!("Tag {} left", id),
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// The channel is unbounded; a subscriber that stops receiving should drop its
+    /// receiver, which unsubscribes it on the next event.
+    pub fn subscribe_events(&self) -> mpsc::Receiver<TagEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.event_subscribers.lock().recover().push(sender);
+        receiver
+    }
+
+    /// Watch the published tag ID from async code.
+    ///
+    /// Needs the `tokio` feature. The receiver starts at the current ID and is
+    /// notified each time the ID changes, the same changes that wake
+    /// `wait_for_tag()`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut tag_id = detector.watch();
+    /// while tag_id.changed().await.is_ok() {
+    ///     println!("Tag: {}", *tag_id.borrow_and_update());
+    /// }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn watch(&self) -> tokio::sync::watch::Receiver<i32> {
+        self.tag_id.subscribe()
+    }
+
+    /// Stream the selected detection of every frame from async code.
+    ///
+    /// Needs the `tokio` feature. Each frame in which a tag is selected yields
+    /// the detection `latest_detection()` reports for it; frames without a tag
+    /// yield nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tokio_stream::StreamExt;
+    ///
+    /// let mut detections = detector.detection_stream();
+    /// while let Some(detection) = detections.next().await {
+    ///     println!("Tag {} at {:?}", detection.id, detection.center);
+    /// }
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// The detection thread never waits for a stream. A stream buffers a few
+    /// detections, and once it is full, new detections are skipped until the
+    /// consumer catches up. Dropping the stream unsubscribes it.
+    #[cfg(feature = "tokio")]
+    pub fn detection_stream(&self) -> impl tokio_stream::Stream<Item = TagDetection> + use<> {
+        let (sender, receiver) = tokio::sync::mpsc::channel(STREAM_CAPACITY);
+        self.detection_subscribers.lock().recover().push(sender);
+        tokio_stream::wrappers::ReceiverStream::new(receiver)
+    }
+
+    /// Wait until the published tag ID satisfies `predicate` or `timeout` elapses,
+    /// without blocking a thread.
+    ///
+    /// Needs the `tokio` feature and a Tokio runtime with its timer enabled. The
+    /// async counterpart of `wait_for_tag()`.
+    ///
+    /// # Returns
+    ///
+    /// A future resolving to the first matching ID, or `None` on timeout. It
+    /// doesn't borrow the detector, so it can be spawned as a task.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// match detector.wait_for_tag_async(|id| id >= 0, Duration::from_secs(2)).await {
+    ///     Some(id) => println!("Found tag {}", id),
+    ///     None => println!("No tag within 2 seconds"),
+    /// }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn wait_for_tag_async<F: Fn(i32) -> bool + Send + 'static>(
+        &self,
+        predicate: F,
+        timeout: Duration,
+    ) -> impl Future<Output = Option<i32>> + Send + 'static {
+        let mut tag_id = self.watch();
+        async move {
+            let found =
+                tokio::time::timeout(timeout, tag_id.wait_for(move |id| predicate(*id))).await;
+            match found {
+                Ok(Ok(id)) => Some(*id),
+                // Timed out, or the detector was dropped
+                _ => None,
+            }
+        }
+    }
+
+    /// Restrict the reported tags to the given IDs.
+    ///
+    /// Detections of other tags are discarded before the ordering method runs,
+    /// as if they were never seen, so `tag_id()` returns `Config::default_tag_id`
+    /// while only discarded tags are visible. Takes effect on the next frame, also
+    /// while detection is running.
+    ///
+    /// # Arguments
+    ///
+    /// * `allowed_ids` - IDs to report, or `None` to report all IDs not ignored.
+    ///
+    /// # Returns
+    ///
+    /// Returns `&mut Self` for method chaining.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.set_allowed_ids(Some(HashSet::from([1, 2, 3])));
+    /// detector.apriltag_detect_start()?;
+    /// ```
+    pub fn set_allowed_ids(&mut self, allowed_ids: Option<HashSet<i32>>) -> &mut Self {
+        self.config.allowed_ids = allowed_ids;
+        self.publish_config();
+        self
+    }
+
+    /// Discard the tags with the given IDs.
+    ///
+    /// Detections of these tags are discarded before the ordering method runs,
+    /// as if they were never seen, which keeps stray tags on the field from being
+    /// reported. Takes effect on the next frame, also while detection is running.
+    ///
+    /// # Arguments
+    ///
+    /// * `ignored_ids` - IDs to discard; an empty set discards nothing.
+    ///
+    /// # Returns
+    ///
+    /// Returns `&mut Self` for method chaining.
+    ///
+    /// # Note
+    ///
+    /// An ID that is both allowed and ignored is discarded.
+    pub fn set_ignored_ids(&mut self, ignored_ids: HashSet<i32>) -> &mut Self {
+        self.config.ignored_ids = ignored_ids;
+        self.publish_config();
+        self
+    }
+
+    /// Set the size and decision margin thresholds tags must meet to be reported.
+    ///
+    /// Tags below either threshold are discarded before the ordering method
+    /// runs, as if they were never seen, which keeps far away or poorly decoded
+    /// tags from driving behavior. They still show up in
+    /// `latest_unfiltered_detections()`. Takes effect on the next frame, also
+    /// while detection is running.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_tag_pixels` - Shortest quad side in pixels a tag needs, or `None` for no limit.
+    /// * `min_decision_margin` - Decision margin a tag needs, or `None` for no limit.
+    ///
+    /// # Returns
+    ///
+    /// Returns `&mut Self` for method chaining.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::InvalidConfig` if `min_tag_pixels` is not positive or
+    /// `min_decision_margin` is negative.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.set_detection_thresholds(Some(20.0), Some(35.0))?;
+    /// detector.apriltag_detect_start()?;
+    /// ```
+    pub fn set_detection_thresholds(
+        &mut self,
+        min_tag_pixels: Option<f64>,
+        min_decision_margin: Option<f64>,
+    ) -> Result<&mut Self, UpicError> {
+        self.update_config(|config| {
+            config.min_tag_pixels = min_tag_pixels;
+            config.min_decision_margin = min_decision_margin;
+        })
+    }
+
+    /// Return the detection thread to its full frame rate immediately.
+    ///
+    /// Clears the reduced frame rate mode entered under `Config::idle_policy` and
+    /// restarts its inactivity timer. The request is picked up by the detection
+    /// thread on its next iteration, and a resolution lowered by
+    /// `IdlePolicy::resolution_multiplier` is restored on the frame after.
+    ///
+    /// # Returns
+    ///
+    /// Returns `&mut Self` for method chaining.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.apriltag_detect_start()?;
+    /// // ... robot parked, detector went idle ...
+    /// detector.resume_full_rate(); // Match is about to start
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// Idle mode only changes the frame rate and resolution. The published tag ID
+    /// is left untouched, so entering idle never looks like a lost tag to callers.
+    pub fn resume_full_rate(&mut self) -> &mut Self {
+        self.wake_request.store(true, Ordering::Release);
+        self
+    }
+
+    /// Check whether the detection thread is running at its reduced idle frame rate.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` while the detection thread is idle under `Config::idle_policy`,
+    /// `false` at full rate or when no idle policy is configured.
+    pub fn is_idle(&self) -> bool {
+        self.idle.load(Ordering::Acquire)
+    }
+
+    /// Get the currently detected AprilTag ID.
+    ///
+    /// This method provides thread-safe access to the most recently detected tag ID.
+    /// The value is updated continuously by the detection thread when active.
+    ///
+    /// # Returns
+    ///
+    /// Returns the ID of the currently detected AprilTag. Returns `Config::default_tag_id`
+    /// when no tags are detected, `Config::error_tag_id` when camera errors occur,
+    /// or the actual tag ID when a tag is successfully detected.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.apriltag_detect_start()?;
+    /// let current_tag = detector.tag_id();
+    /// if current_tag >= 0 {
+    ///     println!("Detected tag: {}", current_tag);
+    /// } else if current_tag == detector.config().default_tag_id {
+    ///     println!("No tag detected");
+    /// } else if current_tag == detector.config().error_tag_id {
+    ///     println!("Camera error");
+    /// }
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// The tag ID is an atomic updated by the detection thread. Reading it takes
+    /// no lock, so it can be polled from a fast control loop, from any thread,
+    /// without ever contending with detection. Set `Config::smoothing_window` if
+    /// it flaps between two tags in view.
+    pub fn tag_id(&self) -> i32 {
+        self.tag_id.id()
+    }
+
+    /// Block until the published tag ID satisfies a predicate or a timeout elapses.
+    ///
+    /// Only the calling thread blocks; it sleeps on a condition variable that
+    /// the detection thread notifies whenever the published ID changes, so
+    /// waiting costs no CPU. To wait from other threads, hand them a
+    /// `TagWatcher` from `watcher()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `predicate` - Condition on the published tag ID, also checked immediately.
+    /// * `timeout` - Longest time to wait.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(tag_id)` with the first published ID satisfying `predicate`,
+    /// or `None` if none did within `timeout`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// // Drive forward until any tag shows up or 2 seconds pass
+    /// controller.set_motors_speed([3000, 3000, -3000, -3000])?;
+    /// let seen = detector.wait_for_tag(|id| id >= 0, Duration::from_secs(2));
+    /// controller.set_motors_speed([0, 0, 0, 0])?;
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// An ID published and replaced again between two wake-ups of the waiting
+    /// thread may be missed; debounce with `Config::min_consecutive_frames` if
+    /// single-frame IDs must not count anyway.
+    pub fn wait_for_tag<F: Fn(i32) -> bool>(&self, predicate: F, timeout: Duration) -> Option<i32> {
+        self.watcher().wait_for_tag(predicate, timeout)
+    }
+
+    /// Block until the tag with `id` is published or a timeout elapses.
+    ///
+    /// Convenience wrapper around `wait_for_tag()`.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the tag was published within `timeout`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// if !detector.wait_for_tag_id(3, Duration::from_secs(2)) {
+    ///     log::warn!("Tag 3 not found, taking the fallback route");
+    /// }
+    /// ```
+    pub fn wait_for_tag_id(&self, id: i32, timeout: Duration) -> bool {
+        self.watcher().wait_for_tag_id(id, timeout)
+    }
+
+    /// Get a handle for reading and waiting on the published tag ID from other threads.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let watcher = detector.watcher();
+    /// let waiter = std::thread::spawn(move || watcher.wait_for_tag_id(3, Duration::from_secs(2)));
+    /// detector.apriltag_detect_start()?;
+    /// let seen = waiter.join().unwrap();
+    /// ```
+    pub fn watcher(&self) -> TagWatcher {
+        TagWatcher::new(Arc::clone(&self.tag_id))
+    }
+
+    /// Make a breaker that fires while one of the given tags is in view.
+    ///
+    /// For ending a `MovingTransition` of mentabotix-rs when the robot reaches
+    /// a tag. The breaker holds its own handle on the published tag ID, so it
+    /// stays valid after the detector is moved, and reading it takes no lock
+    /// shared with detection.
+    ///
+    /// # Arguments
+    ///
+    /// * `ids` - Tags that fire the breaker.
+    ///
+    /// # Returns
+    ///
+    /// A breaker returning `true` while the published tag ID is one of `ids` and
+    /// no older than `Config::breaker_max_age`, as of when it was made.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let sees_tag_3 = detector.breaker_for(&[3]);
+    /// let approach = MovingTransition::new(4.0)?
+    ///     .with_breaker(move || {
+    ///         if sees_tag_3() {
+    ///             BreakerResult::Bool(true)
+    ///         } else {
+    ///             BreakerResult::Placeholder
+    ///         }
+    ///     })
+    ///     .with_to_state(true, stop_state);
+    /// ```
+    ///
+    /// See `examples/tag_breaker.rs` for a complete program.
+    pub fn breaker_for(&self, ids: &[i32]) -> impl Fn() -> bool + Send + Sync + 'static {
+        let tag_id = Arc::clone(&self.tag_id);
+        let max_age = self.config.breaker_max_age;
+        let ids: HashSet<i32> = ids.iter().copied().collect();
+        move || tag_id.fresh(max_age).is_some_and(|id| ids.contains(&id))
+    }
+
+    /// Make a breaker that fires once a tag is no longer in view.
+    ///
+    /// For driving along a tag until it is lost, e.g. past a marker on the
+    /// floor. Like `breaker_for()`, it stays valid after the detector is moved.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Tag the robot is following.
+    ///
+    /// # Returns
+    ///
+    /// A breaker returning `true` while the published tag ID is not `id`, or is
+    /// older than `Config::breaker_max_age`, so halted or stopped detection also
+    /// fires it.
+    pub fn breaker_until_lost(&self, id: i32) -> impl Fn() -> bool + Send + Sync + 'static {
+        let tag_id = Arc::clone(&self.tag_id);
+        let max_age = self.config.breaker_max_age;
+        move || tag_id.fresh(max_age) != Some(id)
+    }
+
+    /// Make a breaker that picks a transition key by the tag in view.
+    ///
+    /// For branching a `MovingTransition` on the tag the robot sees, with the
+    /// keys given to `MovingTransition::with_to_state`. Like `breaker_for()`, it
+    /// stays valid after the detector is moved.
+    ///
+    /// # Arguments
+    ///
+    /// * `map` - Transition key for each tag of interest.
+    ///
+    /// # Returns
+    ///
+    /// A breaker returning the key of the published tag ID, or `None` while no
+    /// tag in `map` is in view or the ID is older than `Config::breaker_max_age`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let route = detector.keyed_breaker(HashMap::from([
+    ///     (1, "left".to_string()),
+    ///     (2, "right".to_string()),
+    /// ]));
+    /// let fork = MovingTransition::new(3.0)?
+    ///     .with_breaker(move || route().map_or(BreakerResult::Placeholder, BreakerResult::Str))
+    ///     .with_to_state("left", turn_left)
+    ///     .with_to_state("right", turn_right);
+    /// ```
+    pub fn keyed_breaker(
+        &self,
+        map: HashMap<i32, String>,
+    ) -> impl Fn() -> Option<String> + Send + Sync + 'static {
+        let tag_id = Arc::clone(&self.tag_id);
+        let max_age = self.config.breaker_max_age;
+        move || map.get(&tag_id.fresh(max_age)?).cloned()
+    }
+
+    /// Get the currently detected AprilTag ID together with its age.
+    ///
+    /// The age is measured from the moment the frame the ID was decoded from was
+    /// read, so a wedged detection thread or a stalled camera shows up as a
+    /// growing age even though the ID itself does not change.
+    ///
+    /// # Returns
+    ///
+    /// Returns `(tag_id, age)` where `tag_id` is the value of `tag_id()`. The age
+    /// is `Duration::MAX` before the first frame is processed and after detection
+    /// is halted or stopped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let (tag_id, age) = detector.tag_id_with_age();
+    /// if age < Duration::from_millis(200) {
+    ///     println!("Fresh tag: {}", tag_id);
+    /// }
+    /// ```
+    pub fn tag_id_with_age(&self) -> (i32, Duration) {
+        let (id, read_at) = self.tag_id.stamped();
+        let age = read_at.map_or(Duration::MAX, |read_at| read_at.elapsed());
+        (id, age)
+    }
+
+    /// Check whether the current tag ID is too old to be trusted.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_age` - Oldest acceptable age, see `tag_id_with_age()`.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the ID is older than `max_age`, and always `true` while
+    /// detection is halted or stopped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// if detector.is_stale(Duration::from_millis(500)) {
+    ///     // Don't steer toward a tag we may no longer see
+    ///     return;
+    /// }
+    /// ```
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        self.tag_id_with_age().1 > max_age
+    }
+
+    /// Get the full data of the currently detected AprilTag.
+    ///
+    /// This method provides thread-safe access to the most recent detection selected
+    /// by the configured ordering method, including its corners, center and decision
+    /// margin. The whole detection is published under a single lock, so its fields
+    /// always belong to the same frame.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(TagDetection)` while a tag is detected, `None` when no tag is
+    /// visible, detection is halted or stopped, or a camera error occurred.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.apriltag_detect_start()?;
+    /// if let Some(detection) = detector.latest_detection() {
+    ///     println!("Tag {} at {:?}", detection.id, detection.center);
+    /// }
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// `tag_id()` reports the same tag's ID, falling back to the configured
+    /// sentinel IDs where this method returns `None`. With
+    /// `Config::smoothing_window` above 1, `tag_id()` is the majority over the
+    /// last frames instead, while this method keeps following each frame.
+    pub fn latest_detection(&self) -> Option<TagDetection> {
+        *self.detection.lock().recover()
+    }
+
+    /// Get the ID of the currently detected AprilTag if its decode is confident.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_margin` - Decision margin the detection must exceed.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(id)` for the detection `latest_detection()` reports when
+    /// its decision margin exceeds `min_margin`, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// // Only turn toward tags read with a clear margin
+    /// if let Some(id) = detector.tag_id_if_confident(50.0) {
+    ///     println!("Heading to tag {}", id);
+    /// }
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// Unlike `Config::min_decision_margin`, this doesn't discard anything: the
+    /// detection thread keeps publishing uncertain tags to `tag_id()`, and each
+    /// caller decides how much certainty it needs.
+    pub fn tag_id_if_confident(&self, min_margin: f64) -> Option<i32> {
+        self.latest_detection()
+            .filter(|detection| detection.is_confident(min_margin))
+            .map(|detection| detection.id)
+    }
+
+    /// Get the pixel offset of the selected tag from the frame center.
+    ///
+    /// Enough for simple steering, where only how far left or right of center
+    /// the tag is matters. Updated together with `tag_id()`.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some((dx, dy))` with the tag center minus the frame center in
+    /// pixels, positive to the right and down, or `None` when `latest_detection()`
+    /// is `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// if let Some((dx, _)) = detector.tag_offset() {
+    ///     let turn = (dx * 0.01).clamp(-1.0, 1.0);
+    ///     println!("Steer {:+.2}", turn);
+    /// }
+    /// ```
+    pub fn tag_offset(&self) -> Option<(f64, f64)> {
+        self.offset.lock().recover().map(|offset| offset.offset)
+    }
+
+    /// Get the horizontal bearing of the selected tag in degrees.
+    ///
+    /// Converts `tag_offset()` to an angle from the camera's optical axis,
+    /// treating the camera as a pinhole camera with the given field of view.
+    /// Lens distortion is not accounted for, so the bearing is approximate
+    /// toward the frame edges unless `Config::undistort` is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `horizontal_fov_deg` - Horizontal field of view of the camera in degrees,
+    ///   usually `Config::horizontal_fov_deg`.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(degrees)`, positive when the tag is right of center, or
+    /// `None` when no tag is selected or the field of view is not within 0-180.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let fov = detector.config().horizontal_fov_deg;
+    /// if let Some(bearing) = detector.tag_bearing_deg(fov) {
+    ///     println!("Tag at {:+.1} degrees", bearing);
+    /// }
+    /// ```
+    pub fn tag_bearing_deg(&self, horizontal_fov_deg: f64) -> Option<f64> {
+        if !(horizontal_fov_deg > 0.0 && horizontal_fov_deg < 180.0) {
+            return None;
+        }
+        let offset = (*self.offset.lock().recover())?;
+        Some(offset.bearing_deg(horizontal_fov_deg))
+    }
+
+    /// Get the last detection selected by the ordering method, before debouncing.
+    ///
+    /// With `Config::min_consecutive_frames` above 1, `latest_detection()` only
+    /// changes once a tag has been selected for that many frames in a row; this
+    /// method reports every frame's selection, which makes it possible to compare
+    /// the debounced and raw behavior.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(TagDetection)` if a tag passing the ID filters was selected in
+    /// the last processed frame, `None` otherwise or while detection is halted or
+    /// stopped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let config = Config::builder().min_consecutive_frames(3).build()?;
+    /// let mut detector = TagDetector::with_config(config)?;
+    /// detector.open_camera(0)?.apriltag_detect_start()?;
+    /// if detector.latest_raw_detection() != detector.latest_detection() {
+    ///     println!("Change pending debounce");
+    /// }
+    /// ```
+    pub fn latest_raw_detection(&self) -> Option<TagDetection> {
+        *self.raw_detection.lock().recover()
+    }
+
+    /// Get every tag detected in the last frame, in multi-tag mode.
+    ///
+    /// With `Config::single_tag_mode` off, the detection thread publishes all
+    /// tags that passed the ID filters and thresholds, e.g. for triangulating
+    /// the robot's position between two wall markers. The ordering method then
+    /// only decides which of them `tag_id()` and `latest_detection()` report.
+    ///
+    /// # Returns
+    ///
+    /// Returns the tags in the order the decoder reported them; empty if none
+    /// were detected, in single tag mode, or while detection is halted or stopped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let config = Config::builder().single_tag_mode(false).build()?;
+    /// let mut detector = TagDetector::with_config(config)?;
+    /// detector.open_camera(0)?.apriltag_detect_start()?;
+    /// for tag in detector.all_detections() {
+    ///     println!("Tag {} at {:?}", tag.id, tag.center);
+    /// }
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// Unlike `latest_detection()`, the list is not debounced; it always
+    /// reflects the last processed frame.
+    pub fn all_detections(&self) -> Vec<TagDetection> {
+        self.all_detections.lock().recover().clone()
+    }
+
+    /// Get the tags detected within a recent time window.
+    ///
+    /// The detection thread records the published tag of every frame, or in
+    /// multi-tag mode every tag of `all_detections()`, with the frame's read
+    /// time. `Config::default_tag_id` and `Config::error_tag_id` are not
+    /// recorded, and only the last `Config::history_capacity` entries are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - How far back to look from now.
+    ///
+    /// # Returns
+    ///
+    /// Returns `(read time, tag ID)` entries, oldest first; empty after
+    /// `apriltag_detect_end()`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// // Which tag did we see most recently in the last 2 seconds?
+    /// let latest = detector
+    ///     .recent_tags(Duration::from_secs(2))
+    ///     .last()
+    ///     .map(|&(_, id)| id);
+    /// ```
+    pub fn recent_tags(&self, window: Duration) -> Vec<(Instant, i32)> {
+        self.history.lock().recover().recent(window, Instant::now())
+    }
+
+    /// Get the read time of the latest frame in which the tag with `id` was
+    /// recorded, as described in `recent_tags()`.
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the tag is not in the history.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let home_visible = detector
+    ///     .last_seen(HOME_TAG)
+    ///     .is_some_and(|at| at.elapsed() < Duration::from_millis(500));
+    /// ```
+    pub fn last_seen(&self, id: i32) -> Option<Instant> {
+        self.history.lock().recover().last_seen(id)
+    }
+
+    /// Get the tags the filters discarded most recently.
+    ///
+    /// The detection thread keeps the last `Config::rejection_capacity` tags
+    /// discarded by `Config::ignored_ids`, `Config::allowed_ids`,
+    /// `Config::min_tag_pixels` or `Config::min_decision_margin`, each with the
+    /// first of those stages it failed. When the detector keeps reporting no
+    /// tag, this tells whether tags were decoded and rejected, and by which
+    /// stage. `DetectionStats::rejections` counts them per stage.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - Number of rejections to return at most.
+    ///
+    /// # Returns
+    ///
+    /// Returns the last `n` rejections, oldest first; empty after
+    /// `apriltag_detect_end()`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// for rejected in detector.rejections(10) {
+    ///     println!(
+    ///         "frame {}: tag {} rejected ({}), margin {:.1}",
+    ///         rejected.frame_index, rejected.id, rejected.reason, rejected.decision_margin
+    ///     );
+    /// }
+    /// ```
+    pub fn rejections(&self, n: usize) -> Vec<RejectedDetection> {
+        self.rejections.lock().recover().latest(n)
+    }
+
+    /// Get every tag decoded in the last frame, before any filtering.
+    ///
+    /// Unlike `latest_raw_detection()`, this includes the tags discarded by the ID
+    /// filters and by `Config::min_tag_pixels` and `Config::min_decision_margin`,
+    /// which makes it the place to look when tuning those thresholds.
+    ///
+    /// # Returns
+    ///
+    /// Returns the decoded tags in the order the decoder reported them; empty if
+    /// none were decoded, the frame could not be read, or while detection is
+    /// halted or stopped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// for tag in detector.latest_unfiltered_detections() {
+    ///     println!(
+    ///         "tag {}: {:.0} px, margin {:.1}",
+    ///         tag.id,
+    ///         tag.min_side(),
+    ///         tag.decision_margin
+    ///     );
+    /// }
+    /// ```
+    pub fn latest_unfiltered_detections(&self) -> Vec<TagDetection> {
+        self.unfiltered_detections.lock().recover().clone()
+    }
+
+    /// Detect the tags in a frame captured elsewhere.
+    ///
+    /// Runs the same preprocessing and decoding as the detection thread, on the
+    /// calling thread and without a camera, for applications with their own
+    /// frame grabbing. Pick the tag to act on with `select_tag()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - BGR or grayscale frame.
+    ///
+    /// # Returns
+    ///
+    /// Returns every tag decoded within `Config::roi`, in full-frame pixel
+    /// coordinates of the frame turned by `Config::orientation`, and before the
+    /// ID filters and quality thresholds.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::InvalidConfig` if the region of interest lies outside
+    /// the frame or the decoder can't be built, and `UpicError::OpenCv` if
+    /// orienting or preprocessing the frame fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let detector = TagDetector::with_config(Config::default())?;
+    /// let frame = my_pipeline.latest_frame();
+    /// let detections = detector.process_frame(&frame)?;
+    /// if let Some(tag) = detector.select_tag(&detections) {
+    ///     println!("Tag {} at {:?}", tag.id, tag.center);
+    /// }
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// Calling this while the detection thread runs is allowed and cheap: the
+    /// detector's state is only read, and each calling thread keeps its own
+    /// decoder, built on the first call and reused afterwards. `Config::undistort`
+    /// is not applied. Without a camera, the frame's center becomes the one
+    /// `select_tag()` measures `OrderingMethod::Nearest` from.
+    pub fn process_frame(&self, frame: &Frame) -> Result<Vec<TagDetection>, UpicError> {
+        if self.camera.is_none() && self.detect_thread.is_none() {
+            *self.frame_center.lock().recover() =
+                [frame.cols() as f64 / 2.0, frame.rows() as f64 / 2.0];
+        }
+        if self.config.orientation == FrameOrientation::Normal {
+            return detect_on_caller(frame, &self.config);
+        }
+        let mut oriented = Frame::default();
+        self.config.orientation.apply(frame, &mut oriented)?;
+        detect_on_caller(&oriented, &self.config)
+    }
+
+    /// Pick the tag to act on, as the detection thread would.
+    ///
+    /// Applies the ID filters and quality thresholds, then the ordering method,
+    /// without debouncing.
+    ///
+    /// # Arguments
+    ///
+    /// * `detections` - Tags decoded in one frame, typically by `process_frame()`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the selected detection, or `None` if none passes the filters.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let detections = detector.process_frame(&frame)?;
+    /// let tag_id = detector
+    ///     .select_tag(&detections)
+    ///     .map_or(detector.config().default_tag_id, |tag| tag.id);
+    /// ```
+    pub fn select_tag<'a>(&self, detections: &'a [TagDetection]) -> Option<&'a TagDetection> {
+        select_unfiltered(detections, &self.config, self.oriented_center())
+    }
+
+    /// Center of the frames detection runs on, after `Config::orientation`
+    pub(crate) fn oriented_center(&self) -> [f64; 2] {
+        self.config
+            .orientation
+            .oriented_center(*self.frame_center.lock().recover())
+    }
+
+    /// Start logging what the detection thread sees, one record per frame.
+    ///
+    /// Each record holds the time, the frame index since detection started,
+    /// the published tag ID, the number of tags that passed the filters and of
+    /// those they discarded (see `rejections()`), the read and detection times
+    /// in milliseconds, the selected tag's decision margin and the frames camera
+    /// warm-ups threw away before the frame, for replaying a match afterwards. A log that is already open is closed first.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File to write; an existing file is overwritten.
+    /// * `format` - `LogFormat::Csv` with a header line, or `LogFormat::Jsonl`.
+    ///
+    /// # Returns
+    ///
+    /// Returns `&mut Self` for method chaining.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::Io` if the file can't be created, or if closing the
+    /// previous log fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// detector.start_logging("match_12.csv", LogFormat::Csv)?;
+    /// detector.apriltag_detect_start()?;
+    /// // ... match ...
+    /// detector.stop_logging()?;
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// The file is written by a separate thread, so a slow disk never delays
+    /// detection. If the writer falls behind by more than a few hundred records,
+    /// new records are dropped; `stop_logging()` reports how many.
+    pub fn start_logging<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        format: LogFormat,
+    ) -> Result<&mut Self, UpicError> {
+        self.stop_logging()?;
+        let (log, sender) = FrameLog::create(path.as_ref(), format)?;
+        *self.frame_log.lock().recover() = Some(sender);
+        self.log_writer = Some(log);
+        log::info!("Logging detections to {}", path.as_ref().display());
+        Ok(self)
+    }
+
+    /// Stop logging, flush the remaining records and close the log file.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of records dropped because the disk couldn't keep
+    /// up; 0 when no log was open.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::Io` if writing or flushing the file failed.
+    pub fn stop_logging(&mut self) -> Result<u64, UpicError> {
+        // Dropping the sender tells the writer no more records are coming
+        self.frame_log.lock().recover().take();
+        let Some(log) = self.log_writer.take() else {
+            return Ok(0);
+        };
+        let dropped = log.finish()?;
+        if dropped > 0 {
+            log::warn!("Detection log dropped {} records", dropped);
+        }
+        Ok(dropped)
+    }
+
+    /// Get a snapshot of the detection thread's statistics.
+    ///
+    /// Useful for checking whether the vision pipeline keeps up with the camera
+    /// on a slow board. Reading the statistics only copies a small struct under
+    /// a mutex, so it can be done every control loop iteration.
+    ///
+    /// # Returns
+    ///
+    /// Returns the statistics as of the last processed frame. They are reset by
+    /// `apriltag_detect_start()` and kept after detection stops.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let stats = detector.stats();
+    /// println!(
+    ///     "{:.1} FPS, {:?} per frame, {} of {} frames with a tag",
+    ///     stats.fps, stats.avg_detect_time, stats.detections, stats.frames_processed
+    /// );
+    /// if stats.reconnects > 0 {
+    ///     println!("Camera dropped out {} times", stats.reconnects);
+    /// }
+    /// ```
+    pub fn stats(&self) -> DetectionStats {
+        *self.stats.lock().recover()
+    }
+
+    /// Measure the time from reading a frame to having its tag, frame by frame.
+    ///
+    /// Detection must be stopped. The camera's buffer is drained first by
+    /// reading `Config::buffer_size` frames, then each frame is timed from the
+    /// start of its read until its tag is selected, as in the detection thread.
+    /// Run it with different buffer sizes to compare them; `DetectionStats`
+    /// reports the same latency while detection runs.
+    ///
+    /// # Arguments
+    ///
+    /// * `n_frames` - Number of frames to time.
+    ///
+    /// # Returns
+    ///
+    /// Returns the latency statistics in seconds.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::DetectionRunning` while detection runs,
+    /// `UpicError::CameraNotInitialized` if no camera is open, or the error of a
+    /// failed read or detection.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// for buffer_size in [1, 2, 4] {
+    ///     detector.update_config(|config| config.buffer_size = buffer_size)?;
+    ///     let report = detector.measure_pipeline_latency(100)?;
+    ///     println!("Buffer {}: p95 latency {:.1} ms", buffer_size, report.p95 * 1000.0);
+    /// }
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// A read returns a frame the camera buffered earlier without waiting, so
+    /// the time it spent in the buffer is not part of the measurement. With a
+    /// deep buffer, fast reads and a high latency as seen by the robot go
+    /// together.
+    pub fn measure_pipeline_latency(
+        &mut self,
+        n_frames: usize,
+    ) -> Result<FrameTimeReport, UpicError> {
+        let drain_frames = self.config.buffer_size as usize;
+        let report = FrameTimeReport::from_samples(&time_detection(self, drain_frames, n_frames)?);
+        report.log("Pipeline Latency Results");
+        Ok(report)
+    }
+
+    /// Get the health of the camera.
+    ///
+    /// Tells a camera that is gone from a bad frame, which both publish
+    /// `Config::error_tag_id`. The camera counts as disconnected once it reports
+    /// itself closed or `Config::disconnect_after` consecutive reads failed, and
+    /// as `Ok` again after the next good read or successful reconnect.
+    ///
+    /// # Returns
+    ///
+    /// Returns the state as of the last frame read. It is reset to `Ok` by
+    /// `apriltag_detect_start()` and kept after detection stops.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// match detector.camera_state() {
+    ///     CameraState::Ok => {}
+    ///     CameraState::ReadErrors(count) => println!("{} bad frames in a row", count),
+    ///     CameraState::Disconnected => println!("Check the camera cable"),
+    /// }
+    /// ```
+    pub fn camera_state(&self) -> CameraState {
+        *self.camera_state.lock().recover()
+    }
+
+    /// Grab the most recent frame as PNG bytes.
+    ///
+    /// Asks the detection thread for the frame it read last, or for a fresh one
+    /// while detection is halted, and encodes it as PNG on the calling thread.
+    /// Useful for checking what the camera actually saw when a detection was
+    /// missed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the PNG-encoded frame at the resolution detection runs at.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::CameraNotInitialized` if no camera is open,
+    /// `UpicError::DetectionNotRunning` if a camera is open but the detection
+    /// thread is not running, `UpicError::FrameReadFailed` if no frame could be
+    /// read, or `UpicError::OpenCv` if encoding fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.apriltag_detect_start()?;
+    /// let png = detector.capture_frame()?;
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// Blocks until the detection thread finishes its current frame, at most
+    /// a few seconds if the camera stopped delivering frames.
+    pub fn capture_frame(&self) -> Result<Vec<u8>, UpicError> {
+        encode_png(&self.latest_frame()?)
+    }
+
+    /// Measure the brightness of the most recent frame.
+    ///
+    /// Grabs the frame like `capture_frame()`, for checking the exposure of a
+    /// camera before relying on it.
+    ///
+    /// # Returns
+    ///
+    /// Returns the mean pixel value over all channels, from 0 to 255.
+    ///
+    /// # Errors
+    ///
+    /// Same as `capture_frame()`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// detector.apriltag_detect_start()?;
+    /// if detector.frame_brightness()? < 30.0 {
+    ///     println!("Camera is too dark, check the lighting or the lens cap");
+    /// }
+    /// ```
+    pub fn frame_brightness(&self) -> Result<f64, UpicError> {
+        mean_brightness(&self.latest_frame()?)
+    }
+
+    /// Ask the detection thread for its latest frame, as `capture_frame()` describes.
+    fn latest_frame(&self) -> Result<Frame, UpicError> {
+        if !self.thread_alive() {
+            return Err(if self.camera.is_some() {
+                UpicError::DetectionNotRunning
+            } else {
+                UpicError::CameraNotInitialized
+            });
+        }
+
+        // Queue the request under the halt lock, so a halted thread can't miss
+        // the wakeup between checking for requests and waiting
+        let (sender, receiver) = mpsc::channel();
+        {
+            let (halted, wakeup) = &*self.halt_detection;
+            let _halted = halted.lock().recover();
+            self.frame_requests.lock().recover().push(sender);
+            wakeup.notify_all();
+        }
+        receiver
+            .recv_timeout(REQUEST_TIMEOUT)
+            .map_err(|_| UpicError::FrameReadFailed)
+    }
+
+    /// Grab the most recent frame and save it as a PNG file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File to write, usually ending in `.png`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `capture_frame()`, or `UpicError::Io` if the file
+    /// can't be written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// detector.capture_frame_to("missed_tag.png")?;
+    /// ```
+    pub fn capture_frame_to<P: AsRef<Path>>(&self, path: P) -> Result<(), UpicError> {
+        let png = self.capture_frame()?;
+        std::fs::write(path.as_ref(), png)?;
+        log::info!("Saved frame to {}", path.as_ref().display());
+        Ok(())
+    }
+
+    /// Set the camera exposure.
+    ///
+    /// While detection is running the change is handed to the detection thread,
+    /// which applies it between two frame reads, so it never races a read.
+    ///
+    /// # Arguments
+    ///
+    /// * `exposure` - Exposure in driver units; for V4L2 cameras usually in
+    ///   units of 100 µs. Only takes effect with auto exposure off.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<&mut Self, UpicError>` for method chaining.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::CameraNotInitialized` if no camera is open,
+    /// `UpicError::InvalidConfig` if the frame source has no exposure control, or
+    /// `UpicError::OpenCv` if the backend fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.set_auto_exposure(false)?.set_exposure(80.0)?.set_gain(10.0)?;
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// Drivers clamp or round values they don't support; the value the driver
+    /// accepted is logged and can be read back with `exposure()`.
+    pub fn set_exposure(&mut self, exposure: f64) -> Result<&mut Self, UpicError> {
+        self.set_camera_property(CameraProperty::Exposure, exposure)?;
+        Ok(self)
+    }
+
+    /// Get the camera exposure in driver units.
+    ///
+    /// # Errors
+    ///
+    /// Same as `set_exposure()`.
+    pub fn exposure(&self) -> Result<f64, UpicError> {
+        self.camera_property(CameraProperty::Exposure)
+    }
+
+    /// Set the camera sensor gain.
+    ///
+    /// Coordinated with the detection thread like `set_exposure()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `gain` - Gain in driver units.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<&mut Self, UpicError>` for method chaining.
+    ///
+    /// # Errors
+    ///
+    /// Same as `set_exposure()`.
+    pub fn set_gain(&mut self, gain: f64) -> Result<&mut Self, UpicError> {
+        self.set_camera_property(CameraProperty::Gain, gain)?;
+        Ok(self)
+    }
+
+    /// Get the camera sensor gain in driver units.
+    ///
+    /// # Errors
+    ///
+    /// Same as `set_exposure()`.
+    pub fn gain(&self) -> Result<f64, UpicError> {
+        self.camera_property(CameraProperty::Gain)
+    }
+
+    /// Turn the camera's auto exposure on or off.
+    ///
+    /// Coordinated with the detection thread like `set_exposure()`. Turn it off
+    /// before setting a fixed exposure, so the competition lights can't make the
+    /// camera drift between matches.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether the camera picks the exposure itself.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<&mut Self, UpicError>` for method chaining.
+    ///
+    /// # Errors
+    ///
+    /// Same as `set_exposure()`.
+    ///
+    /// # Note
+    ///
+    /// Uses the V4L2 backend's auto exposure modes; other backends may
+    /// interpret the values differently.
+    pub fn set_auto_exposure(&mut self, enabled: bool) -> Result<&mut Self, UpicError> {
+        self.set_camera_property(CameraProperty::AutoExposure, auto_exposure_value(enabled))?;
+        Ok(self)
+    }
+
+    /// Get whether the camera's auto exposure is on.
+    ///
+    /// # Errors
+    ///
+    /// Same as `set_exposure()`.
+    pub fn auto_exposure(&self) -> Result<bool, UpicError> {
+        self.camera_property(CameraProperty::AutoExposure)
+            .map(is_auto_exposure)
+    }
+
+    /// Set a fixed white balance temperature.
+    ///
+    /// Turns automatic white balance off, then sets the temperature.
+    /// Coordinated with the detection thread like `set_exposure()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `kelvin` - White balance temperature in Kelvin, such as 4600.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<&mut Self, UpicError>` for method chaining.
+    ///
+    /// # Errors
+    ///
+    /// Same as `set_exposure()`.
+    pub fn set_white_balance(&mut self, kelvin: f64) -> Result<&mut Self, UpicError> {
+        self.set_camera_property(CameraProperty::WhiteBalance, kelvin)?;
+        Ok(self)
+    }
+
+    /// Get the white balance temperature in Kelvin.
+    ///
+    /// # Errors
+    ///
+    /// Same as `set_exposure()`.
+    pub fn white_balance(&self) -> Result<f64, UpicError> {
+        self.camera_property(CameraProperty::WhiteBalance)
+    }
+
+    /// Capture the camera settings worth restoring after a restart.
+    ///
+    /// Reads the resolution, frame rate, auto exposure, exposure, gain and
+    /// brightness from the camera, or from the detection thread while it runs,
+    /// and the buffer size from `Config::buffer_size`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the values the driver reports. Controls the frame source doesn't
+    /// have are `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::CameraNotInitialized` if no camera is open, or
+    /// `UpicError::DetectionNotRunning` if the detection thread stops before
+    /// answering.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.set_auto_exposure(false)?.set_exposure(80.0)?;
+    /// let properties = detector.snapshot_camera_properties()?;
+    /// std::fs::write("camera.toml", toml::to_string(&properties)?)?;
+    /// ```
+    pub fn snapshot_camera_properties(&self) -> Result<CameraProperties, UpicError> {
+        let snapshot = if self.thread_alive() {
+            self.request_properties(None)?
+        } else {
+            let camera = self
+                .camera
+                .as_ref()
+                .ok_or(UpicError::CameraNotInitialized)?;
+            snapshot_properties(camera.as_ref())
+        };
+        Ok(CameraProperties {
+            buffer_size: Some(self.config.buffer_size),
+            ..snapshot
+        })
+    }
+
+    /// Replay camera settings onto the open camera.
+    ///
+    /// Settings that are `None` are left as they are. The buffer size goes
+    /// through `update_config()`; the rest is set on the camera, auto exposure
+    /// before the exposure. While detection runs it is halted around the
+    /// change, so no frame is read with half the settings applied, and resumed
+    /// afterwards unless it was halted already.
+    ///
+    /// # Arguments
+    ///
+    /// * `properties` - Settings as captured with `snapshot_camera_properties()`.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<&mut Self, UpicError>` for method chaining.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::CameraNotInitialized` if no camera is open,
+    /// `UpicError::InvalidConfig` for a buffer size below 1,
+    /// `UpicError::DetectionNotRunning` if the detection thread stops before
+    /// answering, or `UpicError::OpenCv` if the backend fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let properties: CameraProperties = toml::from_str(&std::fs::read_to_string("camera.toml")?)?;
+    /// detector.apply_camera_properties(&properties)?;
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// Drivers clamp or round values they don't support. Each value the driver
+    /// applied differently is logged with the requested and the applied value;
+    /// controls the frame source doesn't have are skipped with a warning.
+    pub fn apply_camera_properties(
+        &mut self,
+        properties: &CameraProperties,
+    ) -> Result<&mut Self, UpicError> {
+        let running = self.thread_alive();
+        if !running && self.camera.is_none() {
+            return Err(UpicError::CameraNotInitialized);
+        }
+        if let Some(buffer_size) = properties.buffer_size {
+            self.update_config(|config| config.buffer_size = buffer_size)?;
+        }
+
+        if running {
+            let was_halted = self.is_halted();
+            self.halt_detection();
+            let applied = self.request_properties(Some(*properties));
+            if !was_halted {
+                self.resume_detection();
+            }
+            applied?;
+        } else if let Some(ref mut camera) = self.camera {
+            apply_properties(camera.as_mut(), properties)?;
+            self.stats.lock().recover().nominal_fps =
+                camera.property(CameraProperty::Fps).unwrap_or(0.0);
+            self.update_cam_center()?;
+        }
+        Ok(self)
+    }
+
+    /// Hand a settings request to the detection thread and wait for its snapshot
+    fn request_properties(
+        &self,
+        apply: Option<CameraProperties>,
+    ) -> Result<CameraProperties, UpicError> {
+        // Queued under the halt lock like capture requests, so a halted thread wakes
+        let (reply, result) = mpsc::channel();
+        {
+            let (halted, wakeup) = &*self.halt_detection;
+            let _halted = halted.lock().recover();
+            self.properties_requests
+                .lock()
+                .recover()
+                .push(PropertiesRequest { apply, reply });
+            wakeup.notify_all();
+        }
+        // Changing the resolution is slow on some drivers
+        result
+            .recv_timeout(PROBE_TIMEOUT)
+            .map_err(|_| UpicError::DetectionNotRunning)?
+    }
+
+    /// Set a camera control and log the value the driver accepted
+    fn set_camera_property(
+        &mut self,
+        property: CameraProperty,
+        value: f64,
+    ) -> Result<f64, UpicError> {
+        let accepted = if self.thread_alive() {
+            self.request_property(property, Some(value))?
+        } else {
+            let camera = self
+                .camera
+                .as_mut()
+                .ok_or(UpicError::CameraNotInitialized)?;
+            apply_property(camera.as_mut(), property, Some(value))?
+        };
+        log::info!(
+            "Set camera {:?} to {}, driver accepted {}",
+            property,
+            value,
+            accepted
+        );
+        Ok(accepted)
+    }
+
+    fn camera_property(&self, property: CameraProperty) -> Result<f64, UpicError> {
+        if self.thread_alive() {
+            self.request_property(property, None)
+        } else {
+            let camera = self
+                .camera
+                .as_ref()
+                .ok_or(UpicError::CameraNotInitialized)?;
+            camera.property(property)
+        }
+    }
+
+    /// Hand a camera control request to the detection thread, which owns the
+    /// camera while it runs, and wait for the answer
+    fn request_property(
+        &self,
+        property: CameraProperty,
+        value: Option<f64>,
+    ) -> Result<f64, UpicError> {
+        // Queued under the halt lock like capture requests, so a halted thread wakes
+        let (reply, result) = mpsc::channel();
+        {
+            let (halted, wakeup) = &*self.halt_detection;
+            let _halted = halted.lock().recover();
+            self.property_requests
+                .lock()
+                .recover()
+                .push(PropertyRequest {
+                    property,
+                    value,
+                    reply,
+                });
+            wakeup.notify_all();
+        }
+        // The thread drops pending requests when it stops
+        result
+            .recv_timeout(REQUEST_TIMEOUT)
+            .map_err(|_| UpicError::DetectionNotRunning)?
+    }
+
+    /// Set the camera intrinsics used for tag pose estimation.
+    ///
+    /// Takes effect on the next frame, also while detection is running. Pose
+    /// estimation is skipped while no intrinsics are set. With
+    /// `Config::undistort`, frames are also undistorted with these intrinsics.
+    ///
+    /// # Arguments
+    ///
+    /// * `intrinsics` - Intrinsics calibrated at the resolution detection runs at,
+    ///   or `None` to disable pose estimation.
+    ///
+    /// # Returns
+    ///
+    /// Returns `&mut Self` for method chaining.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector
+    ///     .set_intrinsics(Some(CameraIntrinsics {
+    ///         fx: 600.0,
+    ///         fy: 600.0,
+    ///         cx: 320.0,
+    ///         cy: 240.0,
+    ///         distortion: vec![],
+    ///     }))
+    ///     .set_tag_size(3, 0.08);
+    /// ```
+    pub fn set_intrinsics(&mut self, intrinsics: Option<CameraIntrinsics>) -> &mut Self {
+        *self.intrinsics.lock().recover() = intrinsics;
+        self
+    }
+
+    /// Register the physical size of a tag for pose and distance estimation.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Tag ID the size applies to.
+    /// * `meters` - Edge length of the tag's black square in meters.
+    ///
+    /// # Returns
+    ///
+    /// Returns `&mut Self` for method chaining.
+    ///
+    /// # Note
+    ///
+    /// Tags without a registered size never get a pose or a distance, since
+    /// the distance to a tag cannot be recovered from its image alone.
+    pub fn set_tag_size(&mut self, id: i32, meters: f64) -> &mut Self {
+        self.tag_sizes.lock().recover().insert(id, meters);
+        self
+    }
+
+    /// Set the camera's focal length in pixels for distance estimation.
+    ///
+    /// `tag_distance_m()` only needs this single number instead of full
+    /// intrinsics. Without it, the `fx` of the intrinsics given to
+    /// `set_intrinsics()` is used. Takes effect on the next frame, also while
+    /// detection is running.
+    ///
+    /// # Arguments
+    ///
+    /// * `focal_length_px` - Focal length in pixels, or `None` to fall back to
+    ///   the intrinsics. Use `calibrate_distance()` to measure it.
+    ///
+    /// # Returns
+    ///
+    /// Returns `&mut Self` for method chaining.
+    pub fn set_focal_length_px(&mut self, focal_length_px: Option<f64>) -> &mut Self {
+        *self.focal_length_px.lock().recover() = focal_length_px;
+        self
+    }
+
+    /// Measure the focal length from a tag at a known distance.
+    ///
+    /// Place a tag with a registered size straight in front of the camera at
+    /// `known_distance_m`, let detection pick it up, then call this method. The
+    /// measured focal length is applied as with `set_focal_length_px()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `known_distance_m` - Distance from the camera to the tag in meters.
+    ///
+    /// # Returns
+    ///
+    /// Returns the measured focal length in pixels.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::InvalidConfig` if the distance is not positive, no tag
+    /// is currently detected, or the detected tag's size was never registered
+    /// with `set_tag_size()`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// detector.set_tag_size(0, 0.1);
+    /// detector.apriltag_detect_start()?;
+    /// // ... tag 0 held 1 m in front of the camera ...
+    /// let focal_px = detector.calibrate_distance(1.0)?;
+    /// println!("Focal length: {:.0} px", focal_px);
+    /// ```
+    pub fn calibrate_distance(&mut self, known_distance_m: f64) -> Result<f64, UpicError> {
+        if !(known_distance_m.is_finite() && known_distance_m > 0.0) {
+            return Err(UpicError::InvalidConfig(format!(
+                "known distance must be positive, got {}",
+                known_distance_m
+            )));
+        }
+        let detection = self.latest_detection().ok_or_else(|| {
+            UpicError::InvalidConfig("no tag detected to calibrate against".to_string())
+        })?;
+        let size_m = *self
+            .tag_sizes
+            .lock()
+            .recover()
+            .get(&detection.id)
+            .ok_or_else(|| {
+                UpicError::InvalidConfig(format!("no size registered for tag {}", detection.id))
+            })?;
+        let focal_px = focal_length_for(size_m, known_distance_m, detection.mean_side());
+        log::info!(
+            "Calibrated focal length {:.1} px on tag {} at {} m",
+            focal_px,
+            detection.id,
+            known_distance_m
+        );
+        self.set_focal_length_px(Some(focal_px));
+        Ok(focal_px)
+    }
+
+    /// Get the approximate distance to the currently detected tag in meters.
+    ///
+    /// Estimated from the tag's apparent size with the focal length from
+    /// `set_focal_length_px()` or the intrinsics, and smoothed over frames with
+    /// `Config::distance_smoothing`. Cheaper and less precise than
+    /// `latest_pose()`: a tag seen at a steep angle looks smaller and reads as
+    /// farther away than it is.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(meters)` for the detection returned by `latest_detection()`,
+    /// `None` when no tag is detected, its size was never registered with
+    /// `set_tag_size()`, or no focal length is known.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// if let Some(distance) = detector.tag_distance_m() {
+    ///     println!("Tag is about {:.2} m away", distance);
+    /// }
+    /// ```
+    pub fn tag_distance_m(&self) -> Option<f64> {
+        *self.distance.lock().recover()
+    }
+
+    /// Get the pose of the currently detected AprilTag relative to the camera.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(TagPose)` for the detection returned by `latest_detection()`
+    /// when intrinsics are set and the tag's size is registered, `None` otherwise
+    /// or when pose estimation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// if let Some(pose) = detector.latest_pose() {
+    ///     println!("Tag {} is {:.2} m away", pose.id, pose.distance());
+    /// }
+    /// ```
+    pub fn latest_pose(&self) -> Option<TagPose> {
+        *self.pose.lock().recover()
+    }
+
+    /// Update the internal frame center coordinates based on current camera resolution.
+    ///
+    /// This internal method recalculates the center point of the camera frame based on
+    /// the current camera resolution settings. It's used internally for "nearest" tag
+    /// selection mode to determine which detected tag is closest to the frame center.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if camera properties cannot be accessed.
+    ///
+    /// # Note
+    ///
+    /// This is an internal method and should not be called directly by users.
+    /// It is automatically invoked when camera resolution changes or camera
+    /// is opened/configured.
+    fn update_cam_center(&mut self) -> Result<(), UpicError> {
+        if let Some(ref camera) = self.camera {
+            let (width, height) = camera.resolution();
+            *self.frame_center.lock().recover() = [width / 2.0, height / 2.0];
+        }
+        Ok(())
+    }
+
+    /// Set camera resolution by multiplying current resolution with a scaling factor.
+    ///
+    /// This method provides a convenient way to scale the camera resolution while
+    /// maintaining the aspect ratio. It's particularly useful for balancing detection
+    /// performance with processing speed.
+    ///
+    /// # Arguments
+    ///
+    /// * `resolution_multiplier` - Scaling factor for resolution adjustment.
+    ///   Values > 1.0 increase resolution (better quality, slower processing),
+    ///   values < 1.0 decrease resolution (faster processing, lower quality).
+    ///   For example, 0.5 reduces resolution to half in both dimensions.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<&mut Self, UpicError>` for method chaining.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::InvalidConfig` if the multiplier is not positive,
+    /// `UpicError::CameraNotInitialized` if no camera is open, or `UpicError::OpenCv`
+    /// if the resolution cannot be set. While detection runs, the change is made
+    /// by the detection thread as in `set_cam_resolution()`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.set_cam_resolution_mul(0.5)?;   // Half resolution for speed
+    /// detector.set_cam_resolution_mul(1.5)?;   // 1.5x resolution for quality
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// The actual resolution set may be adjusted by the camera driver to the
+    /// nearest supported resolution. The method automatically updates the frame
+    /// center calculations after resolution changes.
     pub fn set_cam_resolution_mul(
         &mut self,
         resolution_multiplier: f64,
-    ) -> Result<&mut Self, Box<dyn std::error::Error>> {
-        if self.camera.is_none() {
-            return Err("Camera is not initialized!".into());
+    ) -> Result<&mut Self, UpicError> {
+        if resolution_multiplier <= 0.0 {
+            return Err(UpicError::InvalidConfig(format!(
+                "resolution_multiplier must be positive, got {}",
+                resolution_multiplier
+            )));
+        }
+
+        let (current_width, current_height) =
+            self.frame_size().ok_or(UpicError::CameraNotInitialized)?;
+
+        self.set_cam_resolution(
+            (current_width * resolution_multiplier) as i32,
+            (current_height * resolution_multiplier) as i32,
+        )
+    }
+
+    /// Set the camera resolution to specific width and height values.
+    ///
+    /// This method directly sets the camera resolution to the specified dimensions.
+    /// The camera driver may adjust the values to the nearest supported resolution.
+    ///
+    /// # Arguments
+    ///
+    /// * `new_width` - Target width in pixels.
+    /// * `new_height` - Target height in pixels.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Result<&mut Self, UpicError>` for method chaining.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::CameraNotInitialized` if no camera is open, or
+    /// `UpicError::OpenCv` if the resolution cannot be set.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// detector.set_cam_resolution(640, 480)?;    // VGA resolution
+    /// detector.set_cam_resolution(1920, 1080)?;  // Full HD resolution
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// Common resolutions include:
+    /// - 640x480 (VGA): Good balance of speed and quality
+    /// - 1280x720 (HD): Higher quality, moderate processing load
+    /// - 1920x1080 (Full HD): Highest quality, highest processing load
+    ///
+    /// The method automatically updates frame center calculations and logs
+    /// the actual resolution set by the camera driver.
+    ///
+    /// While detection runs, the detection thread owns the camera. The change is
+    /// then handed to the thread, which applies it and updates the frame center
+    /// used by `OrderingMethod::Nearest` before its next frame; failures are logged
+    /// by the thread instead of being returned.
+    pub fn set_cam_resolution(
+        &mut self,
+        new_width: i32,
+        new_height: i32,
+    ) -> Result<&mut Self, UpicError> {
+        // The detection thread owns the camera while it runs; it applies the
+        // change and updates the frame center before its next frame
+        if self.detect_thread.is_some() {
+            *self.resolution_request.lock().recover() = Some((new_width as f64, new_height as f64));
+            return Ok(self);
+        }
+        if let Some(ref mut camera) = self.camera {
+            camera.set_resolution(new_width as f64, new_height as f64)?;
+
+            let (actual_width, actual_height) = camera.resolution();
+
+            log::info!(
+                "Set CAMERA RESOLUTION: {}x{}",
+                actual_width as i32,
+                actual_height as i32
+            );
+
+            self.update_cam_center()?;
+        } else {
+            return Err(UpicError::CameraNotInitialized);
+        }
+
+        Ok(self)
+    }
+
+    /// List the resolutions the open camera supports.
+    ///
+    /// Drivers silently round `set_cam_resolution()` to a mode they support.
+    /// This requests each common resolution from 320x240 (QVGA) to 3840x2160
+    /// (4K UHD) in turn, reads back what the driver accepted, and restores the
+    /// original resolution before returning. While detection runs it is halted
+    /// around the probing, so no frame is read mid-change, and resumed afterwards
+    /// unless it was halted already.
+    ///
+    /// # Returns
+    ///
+    /// Returns the distinct accepted resolutions as `(width, height)`, smallest
+    /// first. Sources with a fixed frame size report only that size.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::CameraNotInitialized` if no camera is open,
+    /// `UpicError::DetectionNotRunning` if the detection thread stops before
+    /// answering, or the error of restoring the original resolution.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut detector = TagDetector::new(Some(0), None)?;
+    /// for (width, height) in detector.supported_resolutions()? {
+    ///     println!("{}x{}", width, height);
+    /// }
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// Switching resolution is slow on many cameras, so this call can take a
+    /// second or two. Some UVC drivers accept every request verbatim without
+    /// delivering it; a warning is logged when every probed resolution comes
+    /// back exactly as requested.
+    pub fn supported_resolutions(&mut self) -> Result<Vec<(i32, i32)>, UpicError> {
+        if !self.thread_alive() {
+            let camera = self
+                .camera
+                .as_mut()
+                .ok_or(UpicError::CameraNotInitialized)?;
+            return probe_resolutions(camera.as_mut());
         }
 
-        let camera = self.camera.as_ref().unwrap();
-        let current_width = camera.get(opencv::videoio::CAP_PROP_FRAME_WIDTH)?;
-        let current_height = camera.get(opencv::videoio::CAP_PROP_FRAME_HEIGHT)?;
-
-        self.set_cam_resolution(
-            (current_width * resolution_multiplier) as i32,
-            (current_height * resolution_multiplier) as i32,
-        )
+        let was_halted = self.is_halted();
+        self.halt_detection();
+        // Queued under the halt lock like capture requests, so the halted thread wakes
+        let (reply, result) = mpsc::channel();
+        {
+            let (halted, wakeup) = &*self.halt_detection;
+            let _halted = halted.lock().recover();
+            self.resolution_probes.lock().recover().push(reply);
+            wakeup.notify_all();
+        }
+        // The thread drops pending probes when it stops
+        let probed = result.recv_timeout(PROBE_TIMEOUT);
+        if !was_halted {
+            self.resume_detection();
+        }
+        probed.map_err(|_| UpicError::DetectionNotRunning)?
     }
 
-    /// Set the camera resolution to specific width and height values.
+    /// Set the camera frame rate.
     ///
-    /// This method directly sets the camera resolution to the specified dimensions.
-    /// The camera driver may adjust the values to the nearest supported resolution.
+    /// Requests the frame rate from the camera driver and reads back the rate it
+    /// applied; both are logged. Many cameras drop to a low frame rate in dim
+    /// light unless one is requested explicitly. While detection runs, the change
+    /// is applied by the detection thread between two frame reads.
     ///
     /// # Arguments
     ///
-    /// * `new_width` - Target width in pixels.
-    /// * `new_height` - Target height in pixels.
+    /// * `fps` - Frame rate in frames per second; must be positive.
     ///
     /// # Returns
     ///
-    /// Returns `Result<&mut Self, Box<dyn std::error::Error>>` for method chaining.
+    /// Returns `Result<&mut Self, UpicError>` for method chaining.
     ///
     /// # Errors
     ///
-    /// Returns an error if camera is not initialized or resolution cannot be set.
+    /// Returns `UpicError::InvalidConfig` if `fps` is not positive or the frame
+    /// source has no frame rate control, `UpicError::CameraNotInitialized` if no
+    /// camera is open, or `UpicError::UnsupportedFps` if the driver applied a
+    /// rate more than half a frame per second off the requested one.
     ///
     /// # Examples
     ///
     /// ```rust
     /// let mut detector = TagDetector::new(Some(0), None)?;
-    /// detector.set_cam_resolution(640, 480)?;    // VGA resolution
-    /// detector.set_cam_resolution(1920, 1080)?;  // Full HD resolution
+    /// match detector.set_cam_fps(30.0) {
+    ///     Err(UpicError::UnsupportedFps { actual, .. }) => {
+    ///         println!("Camera runs at {} FPS", actual)
+    ///     }
+    ///     result => result.map(|_| ())?,
+    /// }
     /// ```
     ///
     /// # Note
     ///
-    /// Common resolutions include:
-    /// - 640x480 (VGA): Good balance of speed and quality
-    /// - 1280x720 (HD): Higher quality, moderate processing load
-    /// - 1920x1080 (Full HD): Highest quality, highest processing load
-    ///
-    /// The method automatically updates frame center calculations and logs
-    /// the actual resolution set by the camera driver.
-    pub fn set_cam_resolution(
-        &mut self,
-        new_width: i32,
-        new_height: i32,
-    ) -> Result<&mut Self, Box<dyn std::error::Error>> {
-        if let Some(ref mut camera) = self.camera {
-            camera.set(opencv::videoio::CAP_PROP_FRAME_WIDTH, new_width as f64)?;
-            camera.set(opencv::videoio::CAP_PROP_FRAME_HEIGHT, new_height as f64)?;
-
-            let actual_width = camera.get(opencv::videoio::CAP_PROP_FRAME_WIDTH)?;
-            let actual_height = camera.get(opencv::videoio::CAP_PROP_FRAME_HEIGHT)?;
-
-            log::info!(
-                "Set CAMERA RESOLUTION: {}x{}",
-                actual_width as i32,
-                actual_height as i32
-            );
-
-            self.update_cam_center()?;
-        } else {
-            return Err("Camera is not initialized!".into());
+    /// The applied rate is reported as `DetectionStats::nominal_fps`, also when
+    /// it differs from the requested one.
+    pub fn set_cam_fps(&mut self, fps: f64) -> Result<&mut Self, UpicError> {
+        if !(fps.is_finite() && fps > 0.0) {
+            return Err(UpicError::InvalidConfig(format!(
+                "fps must be positive, got {}",
+                fps
+            )));
         }
 
+        let actual = self.set_camera_property(CameraProperty::Fps, fps)?;
+        // A running detection thread records the applied rate itself
+        if !self.thread_alive() {
+            self.stats.lock().recover().nominal_fps = actual;
+        }
+        if (actual - fps).abs() > FPS_TOLERANCE {
+            return Err(UpicError::UnsupportedFps {
+                requested: fps,
+                actual,
+            });
+        }
         Ok(self)
     }
 
+    /// Get the camera frame rate reported by the driver.
+    ///
+    /// # Errors
+    ///
+    /// Same as `set_exposure()`.
+    pub fn cam_fps(&self) -> Result<f64, UpicError> {
+        self.camera_property(CameraProperty::Fps)
+    }
+
     /// Get the underlying OpenCV VideoCapture device instance.
     ///
     /// This method provides direct access to the OpenCV VideoCapture object for
@@ -560,7 +3887,8 @@ impl TagDetector {
     /// # Returns
     ///
     /// Returns a reference to the VideoCapture instance if a camera is open,
-    /// None if no camera is currently initialized.
+    /// None if no camera is currently initialized, the frame source is not backed
+    /// by a VideoCapture, or the detection thread currently owns the source.
     ///
     /// # Examples
     ///
@@ -583,7 +3911,1410 @@ impl TagDetector {
     ///
     /// This method is primarily intended for advanced users who need access
     /// to camera features not exposed through the TagDetector interface.
+    /// Exposure, gain and white balance are exposed through `set_exposure()`
+    /// and its siblings, which are safe to use while detection is running.
+    #[cfg(feature = "opencv")]
     pub fn camera_device(&self) -> Option<&opencv::videoio::VideoCapture> {
-        self.camera.as_ref()
+        self.camera
+            .as_deref()
+            .and_then(|camera| camera.video_capture())
+    }
+}
+
+impl Drop for TagDetector {
+    /// Stop the detection thread and wait for it so it never outlives the detector.
+    fn drop(&mut self) {
+        if let Err(e) = self.apriltag_detect_end_join() {
+            log::error!("{}", e);
+        }
+        if let Err(e) = self.stop_logging() {
+            log::error!("{}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A uniform gray frame
+    fn gray_frame(width: i32, height: i32, value: u8) -> Frame {
+        #[cfg(feature = "opencv")]
+        return opencv::core::Mat::new_rows_cols_with_default(
+            height,
+            width,
+            opencv::core::CV_8UC1,
+            opencv::core::Scalar::all(f64::from(value)),
+        )
+        .unwrap();
+        #[cfg(not(feature = "opencv"))]
+        Frame::filled(width, height, value)
+    }
+
+    fn blank_frames(count: usize) -> Vec<Frame> {
+        (0..count).map(|_| gray_frame(320, 240, 255)).collect()
+    }
+
+    /// Poll `condition` until it holds, failing the test after five seconds
+    fn wait_until(mut condition: impl FnMut() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() {
+            assert!(Instant::now() < deadline, "condition not met within 5s");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// Wait until the detection thread has processed `count` more frames
+    fn wait_for_frames(detector: &TagDetector, count: u64) {
+        let target = detector.stats().frames_processed + count;
+        wait_until(|| detector.stats().frames_processed >= target);
+    }
+
+    /// Wait until a halted detection thread has stopped reading frames
+    fn wait_until_parked(detector: &TagDetector) {
+        let reads = || {
+            let stats = detector.stats();
+            stats.frames_processed + stats.read_errors
+        };
+        let mut last = reads();
+        wait_until(|| {
+            thread::sleep(Duration::from_millis(20));
+            let now = reads();
+            std::mem::replace(&mut last, now) == now
+        });
+    }
+
+    #[test]
+    fn test_mock_source_without_tags() {
+        let source = MockFrameSource::new(blank_frames(3));
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        assert_eq!(*detector.frame_center.lock().unwrap(), [160.0, 120.0]);
+
+        detector.apriltag_detect_start().unwrap();
+        wait_for_frames(&detector, 2);
+        assert_eq!(detector.tag_id(), Config::default().default_tag_id);
+        assert_eq!(detector.latest_detection(), None);
+        assert_eq!(detector.tag_offset(), None);
+        assert_eq!(detector.tag_bearing_deg(60.0), None);
+        assert_eq!(detector.tag_distance_m(), None);
+        // Calibration needs a visible tag
+        detector.set_tag_size(0, 0.1);
+        assert!(matches!(
+            detector.calibrate_distance(1.0),
+            Err(UpicError::InvalidConfig(_))
+        ));
+
+        // Joining hands the source back to the detector
+        detector.apriltag_detect_end_join().unwrap();
+        assert!(detector.camera.is_some());
+    }
+
+    #[test]
+    fn test_failing_source_publishes_error_id() {
+        let source = MockFrameSource::new(Vec::new());
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        detector.apriltag_detect_start().unwrap();
+        let error_tag_id = Config::default().error_tag_id;
+        assert!(detector.wait_for_tag_id(error_tag_id, Duration::from_secs(2)));
+        detector
+            .update_config(|config| config.hot_swap = false)
+            .unwrap();
+        assert!(matches!(
+            detector.open_camera(0),
+            Err(UpicError::DetectionRunning)
+        ));
+    }
+
+    #[test]
+    fn test_resume_wakes_thread_immediately() {
+        let source = MockFrameSource::new(Vec::new());
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        // Only the condvar wake can end a halt check this long within the test
+        let halt_check_interval = Duration::from_secs(60);
+        detector
+            .update_config(|config| config.halt_check_interval = halt_check_interval)
+            .unwrap();
+        detector.apriltag_detect_start().unwrap();
+        let error_tag_id = Config::default().error_tag_id;
+        assert!(detector.wait_for_tag_id(error_tag_id, Duration::from_secs(2)));
+
+        detector.halt_detection();
+        wait_until_parked(&detector);
+        assert_eq!(detector.tag_id(), Config::default().default_tag_id);
+
+        // The failing source publishes the error id as soon as a frame is read again
+        let resumed = Instant::now();
+        detector.resume_detection();
+        assert!(detector.wait_for_tag_id(error_tag_id, Duration::from_secs(10)));
+        assert!(
+            resumed.elapsed() < halt_check_interval,
+            "resume took {:?}",
+            resumed.elapsed()
+        );
+    }
+
+    #[test]
+    fn test_panicking_source_keeps_detection_running() {
+        let source = MockFrameSource::new(blank_frames(1)).with_read_panics(3);
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        detector.apriltag_detect_start().unwrap();
+        let error_tag_id = Config::default().error_tag_id;
+        assert!(detector.wait_for_tag_id(error_tag_id, Duration::from_secs(2)));
+
+        // Once the source stops panicking detection carries on as before
+        wait_for_frames(&detector, 1);
+        assert_eq!(detector.tag_id(), Config::default().default_tag_id);
+        detector.apriltag_detect_end_join().unwrap();
+        assert!(detector.camera.is_some());
+    }
+
+    #[test]
+    fn test_thread_health() {
+        let source = MockFrameSource::new(blank_frames(1));
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        assert!(!detector.is_detecting() && !detector.thread_alive());
+        detector.apriltag_detect_start().unwrap();
+        wait_for_frames(&detector, 1);
+        assert!(detector.is_detecting() && !detector.is_halted());
+
+        // A halted thread beats once per halt check and still counts as detecting
+        detector.halt_detection();
+        wait_until_parked(&detector);
+        thread::sleep(Config::default().halt_check_interval * 2);
+        assert!(detector.is_halted() && detector.is_detecting());
+        detector.resume_detection();
+        assert!(!detector.is_halted());
+
+        detector.apriltag_detect_end();
+        assert!(!detector.is_detecting());
+        detector.apriltag_detect_end_join().unwrap();
+        assert!(!detector.thread_alive() && !detector.is_halted());
+
+        // A read hanging for many frame intervals looks stuck until it returns
+        let source =
+            MockFrameSource::new(blank_frames(1)).with_read_delay(Duration::from_millis(600));
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        detector.apriltag_detect_start().unwrap();
+        wait_until(|| !detector.is_detecting());
+        assert!(detector.thread_alive());
+        assert_eq!(detector.stats().frames_processed, 0);
+        // Frames that slow are then expected
+        wait_until(|| detector.is_detecting());
+        detector.apriltag_detect_end_join().unwrap();
+    }
+
+    #[test]
+    fn test_tag_change_callbacks() {
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let source = MockFrameSource::new(Vec::new());
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        for _ in 0..2 {
+            let changes = Arc::clone(&changes);
+            detector.on_tag_change(move |old, new| changes.lock().unwrap().push((old, new)));
+        }
+
+        let Config {
+            default_tag_id,
+            error_tag_id,
+            ..
+        } = Config::default();
+        detector.apriltag_detect_start().unwrap();
+        assert!(detector.wait_for_tag_id(error_tag_id, Duration::from_secs(2)));
+        detector.halt_detection();
+        wait_until(|| changes.lock().unwrap().len() == 4);
+        detector.apriltag_detect_end_join().unwrap();
+
+        // Repeated failed reads fire once; halting reports the tag as lost
+        let expected = [
+            (default_tag_id, error_tag_id),
+            (default_tag_id, error_tag_id),
+            (error_tag_id, default_tag_id),
+            (error_tag_id, default_tag_id),
+        ];
+        assert_eq!(*changes.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_tag_id_age_and_staleness() {
+        let source = MockFrameSource::new(blank_frames(1));
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        assert!(detector.is_stale(Duration::from_secs(3600)));
+
+        detector.apriltag_detect_start().unwrap();
+        wait_for_frames(&detector, 1);
+        let (tag_id, age) = detector.tag_id_with_age();
+        assert_eq!(tag_id, Config::default().default_tag_id);
+        assert!(age < Duration::from_millis(100), "age {:?}", age);
+        assert!(!detector.is_stale(Duration::from_millis(100)));
+
+        // Halting marks the ID stale right away
+        detector.halt_detection();
+        assert_eq!(detector.tag_id_with_age().1, Duration::MAX);
+        assert!(detector.is_stale(Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn test_reconnect_after_read_failures() {
+        let source = MockFrameSource::new(blank_frames(1)).with_read_failures(4);
+        let config = Config::builder()
+            .reconnect(Some(ReconnectPolicy {
+                max_consecutive_errors: 2,
+                initial_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(10),
+            }))
+            .build()
+            .unwrap();
+        let mut detector = TagDetector::with_config(config).unwrap();
+        detector.camera = Some(Box::new(source));
+
+        detector.apriltag_detect_start().unwrap();
+        wait_for_frames(&detector, 1);
+        let stats = detector.stats();
+        assert_eq!(stats.reconnects, 2);
+        assert_eq!(stats.consecutive_read_errors, 0);
+        assert_eq!(detector.tag_id(), Config::default().default_tag_id);
+        assert_eq!(detector.camera_state(), CameraState::Ok);
+    }
+
+    #[test]
+    fn test_failed_reconnect_keeps_error_id() {
+        let source = MockFrameSource::new(Vec::new());
+        let config = Config::builder()
+            .reconnect(Some(ReconnectPolicy {
+                max_consecutive_errors: 1,
+                initial_backoff: Duration::from_millis(5),
+                max_backoff: Duration::from_millis(20),
+            }))
+            .disconnect_after(2)
+            .build()
+            .unwrap();
+        let mut detector = TagDetector::with_config(config).unwrap();
+        detector.camera = Some(Box::new(source));
+        assert_eq!(detector.camera_state(), CameraState::Ok);
+
+        detector.apriltag_detect_start().unwrap();
+        wait_until(|| detector.camera_state() == CameraState::Disconnected);
+        assert_eq!(detector.tag_id(), Config::default().error_tag_id);
+        assert_eq!(detector.stats().reconnects, 0);
+        assert!(detector.stats().consecutive_read_errors > 1);
+        assert_eq!(detector.camera_state(), CameraState::Disconnected);
+        detector.apriltag_detect_end_join().unwrap();
+        // The state outlives the thread
+        assert_eq!(detector.camera_state(), CameraState::Disconnected);
+    }
+
+    #[test]
+    fn test_error_callbacks() {
+        let source = MockFrameSource::new(Vec::new());
+        let config = Config::builder()
+            .reconnect(Some(ReconnectPolicy {
+                max_consecutive_errors: 1,
+                initial_backoff: Duration::from_millis(5),
+                max_backoff: Duration::from_millis(20),
+            }))
+            .disconnect_after(2)
+            .build()
+            .unwrap();
+        let mut detector = TagDetector::with_config(config).unwrap();
+        detector.camera = Some(Box::new(source));
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&errors);
+        detector.on_error(move |e| seen.lock().unwrap().push(e.to_string()));
+
+        detector.apriltag_detect_start().unwrap();
+        wait_until(|| errors.lock().unwrap().len() == 2 && detector.stats().read_errors > 2);
+        detector.apriltag_detect_end_join().unwrap();
+        // Failed reads and reconnects are the same kind, reported once a second
+        assert_eq!(
+            *errors.lock().unwrap(),
+            [
+                UpicError::FrameReadFailed.to_string(),
+                UpicError::CameraDisconnected.to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_named_thread() {
+        let source = MockFrameSource::new(blank_frames(1)).with_read_failures(1);
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        detector
+            .update_config(|config| config.thread_name = "front-tags".to_string())
+            .unwrap();
+        let name = Arc::new(Mutex::new(None));
+        let seen = Arc::clone(&name);
+        // Error callbacks run on the detection thread
+        detector.on_error(move |_| {
+            *seen.lock().unwrap() = thread::current().name().map(str::to_string)
+        });
+        detector.apriltag_detect_start().unwrap();
+        wait_until(|| name.lock().unwrap().is_some());
+        detector.apriltag_detect_end_join().unwrap();
+        assert_eq!(name.lock().unwrap().as_deref(), Some("front-tags"));
+    }
+
+    #[test]
+    fn test_stats_count_frames() {
+        let source = MockFrameSource::new(blank_frames(2)).with_read_failures(2);
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        detector.apriltag_detect_start().unwrap();
+        wait_for_frames(&detector, 3);
+        detector.apriltag_detect_end_join().unwrap();
+
+        let stats = detector.stats();
+        assert!(stats.frames_processed >= 3, "{:?}", stats);
+        assert_eq!(stats.detections, 0);
+        assert_eq!(stats.read_errors, 2);
+        assert_eq!(stats.consecutive_read_errors, 0);
+        assert!(stats.fps > 0.0);
+        assert!(!stats.opencl);
+
+        // Restarting resets the statistics
+        detector.apriltag_detect_start().unwrap();
+        assert!(detector.stats().frames_processed < stats.frames_processed);
+    }
+
+    #[test]
+    fn test_latency_includes_read() {
+        let source =
+            MockFrameSource::new(blank_frames(1)).with_read_delay(Duration::from_millis(20));
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        let report = detector.measure_pipeline_latency(3).unwrap();
+        assert_eq!(report.samples, 3);
+        assert!(report.min >= 0.02, "{:?}", report);
+
+        detector.apriltag_detect_start().unwrap();
+        wait_for_frames(&detector, 3);
+        assert!(matches!(
+            detector.measure_pipeline_latency(3),
+            Err(UpicError::DetectionRunning)
+        ));
+        detector.apriltag_detect_end_join().unwrap();
+        let stats = detector.stats();
+        assert!(
+            stats.avg_latency >= Duration::from_millis(20),
+            "{:?}",
+            stats
+        );
+        assert!(stats.p95_latency >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn test_unpaced_throughput() {
+        let frames = (0..4)
+            .map(|shade| gray_frame(640, 480, shade * 60))
+            .collect();
+        let config = Config::builder().target_fps(None).build().unwrap();
+        let mut detector = TagDetector::with_config(config).unwrap();
+        detector.camera = Some(Box::new(MockFrameSource::new(frames)));
+        detector.apriltag_detect_start().unwrap();
+        wait_for_frames(&detector, 10);
+
+        // Frames captured from the reused buffers are whole copies
+        #[cfg(feature = "opencv")]
+        {
+            let png = opencv::core::Vector::from_slice(&detector.capture_frame().unwrap());
+            let captured =
+                opencv::imgcodecs::imdecode(&png, opencv::imgcodecs::IMREAD_GRAYSCALE).unwrap();
+            assert_eq!((captured.cols(), captured.rows()), (640, 480));
+        }
+        detector.apriltag_detect_end_join().unwrap();
+
+        let stats = detector.stats();
+        assert_eq!(detector.tag_id(), Config::default().default_tag_id);
+        assert_eq!(stats.detections, 0);
+        assert_eq!(stats.read_errors, 0);
+        assert!(stats.frames_processed >= 10, "{:?}", stats);
+        assert!(stats.avg_frame_time > Duration::ZERO);
+        assert!(stats.avg_frame_time >= stats.avg_detect_time);
+    }
+
+    #[test]
+    fn test_detection_log() {
+        let path =
+            std::env::temp_dir().join(format!("upic-detection-log-{}.csv", std::process::id()));
+        let source = MockFrameSource::new(blank_frames(1));
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        detector.start_logging(&path, LogFormat::Csv).unwrap();
+        detector.apriltag_detect_start().unwrap();
+        wait_for_frames(&detector, 2);
+        detector.apriltag_detect_end_join().unwrap();
+        assert_eq!(detector.stop_logging().unwrap(), 0);
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        let mut lines = log.lines();
+        assert!(lines.next().unwrap().starts_with("timestamp,frame,tag_id"));
+        let records: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+        assert!(!records.is_empty());
+        for (index, record) in records.iter().enumerate() {
+            assert_eq!(record[1], index.to_string());
+            assert_eq!(record[2], "-1");
+            assert_eq!(record[3], "0");
+            assert_eq!(record[4], "0");
+            // No tag is selected, so there is no margin
+            assert_eq!(record[7], "");
+        }
+
+        // Stopping again is a no-op
+        assert_eq!(detector.stop_logging().unwrap(), 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_warmup_in_stats_and_log() {
+        let path = std::env::temp_dir().join(format!("upic-warmup-log-{}.csv", std::process::id()));
+        // The image brightens while auto-exposure settles, then holds
+        let frames = [0, 60, 120, 180, 240, 240]
+            .map(|level| gray_frame(320, 240, level))
+            .to_vec();
+        let mut detector =
+            TagDetector::with_source(Box::new(MockFrameSource::new(frames))).unwrap();
+        detector
+            .update_config(|config| {
+                config.warmup = WarmupPolicy {
+                    discard_frames: 1,
+                    settle: Some(SettleSpec {
+                        max_wait: Duration::from_secs(5),
+                        brightness_delta: 5.0,
+                    }),
+                }
+            })
+            .unwrap();
+        detector.start_logging(&path, LogFormat::Csv).unwrap();
+        detector.apriltag_detect_start().unwrap();
+
+        // The warm-up shows in the statistics as soon as detection starts
+        let stats = detector.stats();
+        assert_eq!((stats.warmups, stats.warmup_frames), (1, 6));
+        assert_eq!(stats.last_warmup.unwrap().settled, Some(true));
+        wait_for_frames(&detector, 3);
+        detector.apriltag_detect_end_join().unwrap();
+        assert_eq!(detector.stats().warmups, 1);
+        detector.stop_logging().unwrap();
+
+        // and the first logged frame carries the frames it threw away
+        let log = std::fs::read_to_string(&path).unwrap();
+        let warmup_frames: Vec<&str> = log
+            .lines()
+            .skip(1)
+            .map(|line| line.split(',').nth(8).unwrap())
+            .collect();
+        assert!(warmup_frames.len() >= 3);
+        assert_eq!(warmup_frames[0], "6");
+        assert!(warmup_frames[1..].iter().all(|&frames| frames == "0"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_update_config_while_running() {
+        let source = MockFrameSource::new(Vec::new());
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        detector.apriltag_detect_start().unwrap();
+        let error_tag_id = Config::default().error_tag_id;
+        assert!(detector.wait_for_tag_id(error_tag_id, Duration::from_secs(2)));
+
+        // The running thread picks the new sentinel up on its next frame
+        detector
+            .update_config(|config| config.error_tag_id = -20)
+            .unwrap();
+        assert!(detector.wait_for_tag_id(-20, Duration::from_secs(2)));
+
+        // Invalid changes are rejected and leave the config untouched
+        let rejected = detector.update_config(|config| config.default_tag_id = -20);
+        assert!(matches!(rejected, Err(UpicError::InvalidConfig(_))));
+        assert_eq!(detector.config().default_tag_id, -1);
+        assert_eq!(detector.shared_config.read().unwrap().default_tag_id, -1);
+
+        detector
+            .set_detection_thresholds(Some(24.0), Some(40.0))
+            .unwrap();
+        let shared = detector.shared_config.read().unwrap().clone();
+        assert_eq!(shared.min_tag_pixels, Some(24.0));
+        assert_eq!(shared.min_decision_margin, Some(40.0));
+        assert!(matches!(
+            detector.set_detection_thresholds(Some(-1.0), None),
+            Err(UpicError::InvalidConfig(_))
+        ));
+        // Failed reads decode nothing
+        assert!(detector.latest_unfiltered_detections().is_empty());
+    }
+
+    #[test]
+    fn test_frame_center_follows_resolution_while_running() {
+        let source = MockFrameSource::new(blank_frames(1));
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        detector.apriltag_detect_start().unwrap();
+        wait_for_frames(&detector, 1);
+
+        detector.set_cam_resolution_mul(0.5).unwrap();
+        wait_until(|| *detector.frame_center.lock().unwrap() == [80.0, 60.0]);
+
+        // The source comes back with the new resolution
+        detector.apriltag_detect_end_join().unwrap();
+        assert_eq!(
+            detector.camera.as_ref().unwrap().resolution(),
+            (160.0, 120.0)
+        );
+    }
+
+    #[test]
+    fn test_auto_scale_within_frame_time() {
+        let source = MockFrameSource::new(blank_frames(1));
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        // No frame is this fast, so the resolution steps down to the minimum
+        detector
+            .update_config(|config| {
+                config.roi = Some(Rect::new(0, 120, 320, 120));
+                config.auto_scale = Some(AutoScalePolicy {
+                    target_frame_time: Duration::from_nanos(1),
+                    min_interval: Duration::ZERO,
+                    ..AutoScalePolicy::default()
+                });
+            })
+            .unwrap();
+        detector.apriltag_detect_start().unwrap();
+        wait_until(|| detector.stats().resolution_multiplier == 0.5);
+        wait_for_frames(&detector, 1);
+        assert_eq!(*detector.frame_center.lock().unwrap(), [80.0, 60.0]);
+
+        // The ROI is still checked against the unscaled frames
+        detector
+            .update_config(|config| config.roi = Some(Rect::new(0, 0, 320, 240)))
+            .unwrap();
+
+        // Stopping restores the resolution scaling started from
+        detector.apriltag_detect_end_join().unwrap();
+        assert_eq!(
+            detector.camera.as_ref().unwrap().resolution(),
+            (320.0, 240.0)
+        );
+        assert_eq!(*detector.frame_center.lock().unwrap(), [160.0, 120.0]);
+    }
+
+    #[test]
+    fn test_supported_resolutions_while_running() {
+        let source = MockFrameSource::new(blank_frames(1));
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        // The mock resizes to whatever it is asked for
+        assert_eq!(
+            detector.supported_resolutions().unwrap(),
+            resolution::COMMON_RESOLUTIONS
+        );
+
+        detector.apriltag_detect_start().unwrap();
+        wait_for_frames(&detector, 1);
+        assert_eq!(
+            detector.supported_resolutions().unwrap(),
+            resolution::COMMON_RESOLUTIONS
+        );
+        // Probing restores the resolution and resumes detection
+        assert_eq!(*detector.frame_center.lock().unwrap(), [160.0, 120.0]);
+        assert!(!*detector.halt_detection.0.lock().unwrap());
+
+        // Halted detection stays halted
+        detector.halt_detection();
+        detector.supported_resolutions().unwrap();
+        assert!(*detector.halt_detection.0.lock().unwrap());
+        detector.apriltag_detect_end_join().unwrap();
+        assert_eq!(
+            detector.camera.as_ref().unwrap().resolution(),
+            (320.0, 240.0)
+        );
+    }
+
+    #[test]
+    fn test_idle_mode_drops_rate_and_resolution() {
+        let source = MockFrameSource::new(blank_frames(1));
+        let config = Config::builder()
+            .idle_policy(Some(IdlePolicy {
+                after: Duration::from_millis(100),
+                reduced_fps: 10.0,
+                wake_on_detection: true,
+                resolution_multiplier: Some(0.5),
+            }))
+            .build()
+            .unwrap();
+        let mut detector = TagDetector::with_config(config).unwrap();
+        detector.camera = Some(Box::new(source));
+        detector.update_cam_center().unwrap();
+        detector.apriltag_detect_start().unwrap();
+        assert!(!detector.stats().idle);
+
+        // Without tags the thread idles at half the resolution
+        wait_until(|| detector.is_idle());
+        wait_until(|| *detector.frame_center.lock().unwrap() == [80.0, 60.0]);
+        let stats = detector.stats();
+        assert!(stats.idle);
+        assert_eq!(stats.idle_entries, 1);
+        assert_eq!(stats.resolution_multiplier, 0.5);
+        assert_eq!(detector.tag_id(), Config::default().default_tag_id);
+
+        detector
+            .update_config(|config| {
+                if let Some(policy) = config.idle_policy.as_mut() {
+                    policy.after = Duration::from_secs(60);
+                }
+            })
+            .unwrap();
+        detector.resume_full_rate();
+        wait_until(|| *detector.frame_center.lock().unwrap() == [160.0, 120.0]);
+        assert!(!detector.is_idle());
+        let stats = detector.stats();
+        assert!(!stats.idle);
+        assert_eq!(stats.idle_entries, 1);
+        assert_eq!(stats.resolution_multiplier, 1.0);
+        detector.apriltag_detect_end_join().unwrap();
+        assert_eq!(
+            detector.camera.as_ref().unwrap().resolution(),
+            (320.0, 240.0)
+        );
+    }
+
+    #[test]
+    fn test_wait_for_tag() {
+        let error_tag_id = Config::default().error_tag_id;
+        let source = MockFrameSource::new(Vec::new());
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        assert_eq!(
+            detector.wait_for_tag(|id| id >= 0, Duration::from_millis(20)),
+            None
+        );
+
+        let waiters: Vec<_> = (0..2)
+            .map(|_| {
+                let watcher = detector.watcher();
+                thread::spawn(move || {
+                    watcher.wait_for_tag(|id| id == error_tag_id, Duration::from_secs(1))
+                })
+            })
+            .collect();
+        detector.apriltag_detect_start().unwrap();
+        for waiter in waiters {
+            assert_eq!(waiter.join().unwrap(), Some(error_tag_id));
+        }
+        assert!(detector.wait_for_tag_id(error_tag_id, Duration::ZERO));
+    }
+
+    #[test]
+    fn test_set_roi_checks_frame_bounds() {
+        let source = MockFrameSource::new(blank_frames(1));
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        detector.set_roi(0, 120, 320, 120).unwrap();
+        assert_eq!(detector.config().roi, Some(Rect::new(0, 120, 320, 120)));
+
+        assert!(matches!(
+            detector.set_roi(0, 121, 320, 120),
+            Err(UpicError::InvalidConfig(_))
+        ));
+        assert_eq!(detector.config().roi, Some(Rect::new(0, 120, 320, 120)));
+
+        // Detection over the region runs like over the full frame
+        detector.apriltag_detect_start().unwrap();
+        wait_for_frames(&detector, 1);
+        assert_eq!(detector.tag_id(), Config::default().default_tag_id);
+        detector.clear_roi();
+        assert_eq!(detector.config().roi, None);
+    }
+
+    #[test]
+    fn test_target_fps_pacing() {
+        // 10 ms reads paced to 40 ms frames
+        let source =
+            MockFrameSource::new(blank_frames(1)).with_read_delay(Duration::from_millis(10));
+        let config = Config::builder().target_fps(Some(25.0)).build().unwrap();
+        let mut detector = TagDetector::with_config(config).unwrap();
+        detector.camera = Some(Box::new(source));
+        detector.apriltag_detect_start().unwrap();
+        wait_for_frames(&detector, 12);
+        let fps = detector.stats().fps;
+        assert!((20.0..=26.0).contains(&fps), "paced fps {}", fps);
+
+        // Without a target the loop only waits for the source
+        detector
+            .update_config(|config| config.target_fps = None)
+            .unwrap();
+        // Flush the paced frames out of the averaging window
+        wait_for_frames(&detector, 40);
+        let fps = detector.stats().fps;
+        assert!(fps > 50.0, "unpaced fps {}", fps);
+    }
+
+    #[cfg(feature = "opencv")]
+    #[test]
+    fn test_capture_frame() {
+        let source = MockFrameSource::new(blank_frames(1));
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        assert!(matches!(
+            detector.capture_frame(),
+            Err(UpicError::DetectionNotRunning)
+        ));
+
+        detector.apriltag_detect_start().unwrap();
+        let png = detector.capture_frame().unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+
+        // A halted thread reads a frame just for the capture
+        detector.halt_detection();
+        wait_until_parked(&detector);
+        let requested = Instant::now();
+        assert!(detector.capture_frame().unwrap().starts_with(b"\x89PNG"));
+        assert!(requested.elapsed() < Config::default().halt_check_interval);
+
+        detector.apriltag_detect_end_join().unwrap();
+        detector.release_camera();
+        assert!(matches!(
+            detector.capture_frame(),
+            Err(UpicError::CameraNotInitialized)
+        ));
+    }
+
+    #[test]
+    fn test_capture_frame_without_frames_fails() {
+        let source = MockFrameSource::new(Vec::new());
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        detector.apriltag_detect_start().unwrap();
+        assert!(matches!(
+            detector.capture_frame(),
+            Err(UpicError::FrameReadFailed)
+        ));
+    }
+
+    #[test]
+    fn test_frame_brightness() {
+        let source = MockFrameSource::new(vec![gray_frame(320, 240, 90)]);
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        assert!(matches!(
+            detector.frame_brightness(),
+            Err(UpicError::DetectionNotRunning)
+        ));
+        detector.apriltag_detect_start().unwrap();
+        assert_eq!(detector.frame_brightness().unwrap(), 90.0);
+        detector.apriltag_detect_end_join().unwrap();
+    }
+
+    #[test]
+    fn test_camera_controls() {
+        let source = MockFrameSource::new(blank_frames(1));
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        detector
+            .set_auto_exposure(false)
+            .unwrap()
+            .set_exposure(80.0)
+            .unwrap();
+        assert!(!detector.auto_exposure().unwrap());
+        assert_eq!(detector.exposure().unwrap(), 80.0);
+
+        // The detection thread applies changes while it owns the camera
+        detector.apriltag_detect_start().unwrap();
+        detector
+            .set_gain(12.0)
+            .unwrap()
+            .set_white_balance(4600.0)
+            .unwrap();
+        assert_eq!(detector.gain().unwrap(), 12.0);
+        assert_eq!(detector.white_balance().unwrap(), 4600.0);
+
+        detector.halt_detection();
+        wait_until_parked(&detector);
+        let requested = Instant::now();
+        detector.set_auto_exposure(true).unwrap();
+        assert!(detector.auto_exposure().unwrap());
+        assert!(requested.elapsed() < Config::default().halt_check_interval);
+
+        // The settings stay with the camera after it is handed back
+        detector.apriltag_detect_end_join().unwrap();
+        assert_eq!(detector.exposure().unwrap(), 80.0);
+        detector.release_camera();
+        assert!(matches!(
+            detector.set_gain(1.0),
+            Err(UpicError::CameraNotInitialized)
+        ));
+        assert!(matches!(
+            detector.gain(),
+            Err(UpicError::CameraNotInitialized)
+        ));
+    }
+
+    #[test]
+    fn test_camera_properties() {
+        let source = MockFrameSource::new(blank_frames(1));
+        let mut tuned = TagDetector::with_source(Box::new(source)).unwrap();
+        tuned
+            .set_auto_exposure(false)
+            .unwrap()
+            .set_exposure(80.0)
+            .unwrap()
+            .set_cam_fps(25.0)
+            .unwrap();
+        tuned.apriltag_detect_start().unwrap();
+        let properties = tuned.snapshot_camera_properties().unwrap();
+        assert_eq!(properties.exposure, Some(80.0));
+        assert_eq!(properties.auto_exposure, Some(false));
+        assert_eq!(properties.buffer_size, Some(2));
+        tuned.apriltag_detect_end_join().unwrap();
+
+        // Replayed onto a running detector, which keeps running
+        let source = MockFrameSource::new(blank_frames(1));
+        let mut restarted = TagDetector::with_source(Box::new(source)).unwrap();
+        restarted.apriltag_detect_start().unwrap();
+        let properties = CameraProperties {
+            width: Some(640),
+            height: Some(480),
+            buffer_size: Some(1),
+            ..properties
+        };
+        restarted.apply_camera_properties(&properties).unwrap();
+        assert_eq!(restarted.snapshot_camera_properties().unwrap(), properties);
+        assert_eq!(restarted.oriented_center(), [320.0, 240.0]);
+        wait_until(|| restarted.stats().nominal_fps == 25.0);
+        wait_for_frames(&restarted, 1);
+        restarted.apriltag_detect_end_join().unwrap();
+
+        restarted.release_camera();
+        assert!(matches!(
+            restarted.apply_camera_properties(&properties),
+            Err(UpicError::CameraNotInitialized)
+        ));
+        assert_eq!(restarted.config().buffer_size, 1);
+    }
+
+    #[test]
+    fn test_set_cam_fps() {
+        let source = MockFrameSource::new(blank_frames(1)).with_max_fps(30.0);
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        detector.set_cam_fps(30.0).unwrap();
+        assert_eq!(detector.cam_fps().unwrap(), 30.0);
+        assert_eq!(detector.stats().nominal_fps, 30.0);
+        assert!(matches!(
+            detector.set_cam_fps(0.0),
+            Err(UpicError::InvalidConfig(_))
+        ));
+
+        // Starting detection picks the rate up from the camera
+        detector.apriltag_detect_start().unwrap();
+        wait_for_frames(&detector, 1);
+        assert_eq!(detector.stats().nominal_fps, 30.0);
+
+        detector.set_cam_fps(15.0).unwrap();
+        wait_until(|| detector.stats().nominal_fps == 15.0);
+
+        // The driver clamps a rate it can't reach
+        let result = detector.set_cam_fps(60.0);
+        assert!(
+            matches!(
+                result,
+                Err(UpicError::UnsupportedFps {
+                    requested: 60.0,
+                    actual: 30.0
+                })
+            ),
+            "{:?}",
+            result.as_ref().err()
+        );
+        wait_until(|| detector.stats().nominal_fps == 30.0);
+    }
+
+    /// Tests decoding rendered markers, which needs the `testing` feature
+    #[cfg(feature = "testing")]
+    mod tags {
+        use super::*;
+        use crate::testing::{TagSpec, render_multi};
+        use opencv::core::Mat;
+        /// Side length of rendered tags in pixels, black border included
+        const TAG_PIXELS: i32 = 96;
+
+        /// A white 640x480 frame with tag36h11 markers of the given IDs centered at
+        /// the given points
+        fn tag_frame(tags: &[(i32, [i32; 2])]) -> Mat {
+            let tags: Vec<TagSpec> = tags
+                .iter()
+                .map(|&(id, [x, y])| TagSpec {
+                    family: TagFamily::Tag36h11,
+                    id,
+                    size_px: TAG_PIXELS,
+                    center: [x as f64, y as f64],
+                    rotation_deg: 0.0,
+                })
+                .collect();
+            render_multi((640, 480), &tags).unwrap()
+        }
+
+        #[test]
+        fn test_multi_tag_mode() {
+            let frame = tag_frame(&[(0, [150, 240]), (1, [380, 240])]);
+            let source = MockFrameSource::new(vec![frame]);
+            let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+            detector
+                .update_config(|config| config.single_tag_mode = false)
+                .unwrap();
+            let events = detector.subscribe_events();
+            detector.apriltag_detect_start().unwrap();
+            wait_until(|| detector.all_detections().len() == 2);
+
+            // Both tags are listed, and the one nearest the center is selected
+            let mut ids: Vec<i32> = detector.all_detections().iter().map(|d| d.id).collect();
+            ids.sort();
+            assert_eq!(ids, [0, 1]);
+            assert_eq!(detector.tag_id(), 1);
+            assert_eq!(detector.latest_detection().map(|d| d.id), Some(1));
+            let event_ids = |events: &mpsc::Receiver<TagEvent>| -> Vec<(bool, i32)> {
+                events
+                    .try_iter()
+                    .map(|event| match event {
+                        TagEvent::Entered { id, .. } => (true, id),
+                        TagEvent::Left { id, .. } => (false, id),
+                    })
+                    .collect()
+            };
+            // Every tag enters, and a still frame produces nothing more
+            wait_for_frames(&detector, 2);
+            assert_eq!(event_ids(&events), [(true, 0), (true, 1)]);
+
+            // Single tag mode only publishes and tracks the selection
+            detector
+                .update_config(|config| config.single_tag_mode = true)
+                .unwrap();
+            let mut seen = Vec::new();
+            wait_until(|| {
+                seen.extend(event_ids(&events));
+                !seen.is_empty()
+            });
+            wait_for_frames(&detector, 2);
+            seen.extend(event_ids(&events));
+            assert_eq!(seen, [(false, 0)]);
+            assert!(detector.all_detections().is_empty());
+            assert_eq!(detector.tag_id(), 1);
+
+            detector.halt_detection();
+            assert!(detector.all_detections().is_empty());
+            let mut seen = Vec::new();
+            wait_until(|| {
+                seen.extend(event_ids(&events));
+                !seen.is_empty()
+            });
+            assert_eq!(seen, [(false, 1)]);
+        }
+
+        #[test]
+        fn test_process_frame_without_camera() {
+            let mut detector = TagDetector::with_config(Config::default()).unwrap();
+            let frame = tag_frame(&[(0, [150, 240]), (1, [330, 250])]);
+            let mut detections = detector.process_frame(&frame).unwrap();
+            detections.sort_by_key(|d| d.id);
+            assert_eq!(detections.iter().map(|d| d.id).collect::<Vec<_>>(), [0, 1]);
+            // Nearest is measured from the processed frame's center
+            assert_eq!(detector.select_tag(&detections).map(|d| d.id), Some(1));
+
+            detector
+                .update_config(|config| config.ignored_ids = HashSet::from([1]))
+                .unwrap();
+            assert_eq!(detector.select_tag(&detections).map(|d| d.id), Some(0));
+            // Filters don't apply to the decoded tags themselves
+            assert_eq!(detector.process_frame(&frame).unwrap().len(), 2);
+
+            detector
+                .update_config(|config| config.roi = Some(Rect::new(700, 0, 100, 100)))
+                .unwrap();
+            assert!(matches!(
+                detector.process_frame(&frame),
+                Err(UpicError::InvalidConfig(_))
+            ));
+            assert_eq!(detector.tag_id(), Config::default().default_tag_id);
+        }
+
+        #[test]
+        fn test_orientation() {
+            let source = MockFrameSource::new(vec![tag_frame(&[(0, [100, 240])])]);
+            let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+            // A region only fitting the frame once width and height swap
+            let tall_roi = Some(Rect::new(0, 0, 480, 600));
+            assert!(matches!(
+                detector.update_config(|config| config.roi = tall_roi),
+                Err(UpicError::InvalidConfig(_))
+            ));
+            detector
+                .update_config(|config| {
+                    config.orientation = FrameOrientation::Rotate90;
+                    config.roi = tall_roi;
+                })
+                .unwrap();
+            detector.apriltag_detect_start().unwrap();
+            wait_until(|| detector.latest_detection().is_some());
+            let center = detector.latest_detection().unwrap().center;
+            assert!((center[0] - 239.0).abs() < 2.0 && (center[1] - 100.0).abs() < 2.0);
+            assert_eq!(detector.oriented_center(), [240.0, 320.0]);
+
+            // A new orientation applies from the next frame
+            detector
+                .update_config(|config| {
+                    config.orientation = FrameOrientation::Rotate180;
+                    config.roi = None;
+                })
+                .unwrap();
+            wait_until(|| {
+                detector.latest_detection().is_some_and(|d| {
+                    (d.center[0] - 539.0).abs() < 2.0 && (d.center[1] - 239.0).abs() < 2.0
+                })
+            });
+            detector.apriltag_detect_end_join().unwrap();
+        }
+
+        #[test]
+        fn test_filtered_tag_keeps_idle_detection_awake() {
+            let source = MockFrameSource::new(vec![tag_frame(&[(3, [320, 240])])]);
+            let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+            // The tag passes the filters every frame, but is never published
+            detector
+                .update_config(|config| {
+                    config.min_consecutive_frames = 1000;
+                    config.idle_policy = Some(IdlePolicy {
+                        after: Duration::from_millis(50),
+                        reduced_fps: 2.0,
+                        wake_on_detection: true,
+                        resolution_multiplier: None,
+                    });
+                })
+                .unwrap();
+            detector.apriltag_detect_start().unwrap();
+            wait_for_frames(&detector, 10);
+            assert_eq!(detector.tag_id(), Config::default().default_tag_id);
+            assert!(!detector.is_idle());
+            assert_eq!(detector.stats().idle_entries, 0);
+            detector.apriltag_detect_end_join().unwrap();
+        }
+
+        #[test]
+        fn test_breakers() {
+            let source = MockFrameSource::new(vec![tag_frame(&[(3, [320, 240])])]);
+            let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+            let sees_tag = detector.breaker_for(&[3, 4]);
+            let lost = detector.breaker_until_lost(3);
+            let route = detector.keyed_breaker(HashMap::from([(3, "left".to_string())]));
+            // Nothing published yet
+            assert!(!sees_tag());
+            assert!(lost());
+            assert_eq!(route(), None);
+
+            // The breakers outlive moves of the detector
+            let mut detector = Box::new(detector);
+            detector.apriltag_detect_start().unwrap();
+            wait_until(&sees_tag);
+            assert!(!lost());
+            assert_eq!(route().as_deref(), Some("left"));
+
+            detector.halt_detection();
+            assert!(!sees_tag());
+            assert!(lost());
+            assert_eq!(route(), None);
+            detector.apriltag_detect_end_join().unwrap();
+        }
+
+        #[cfg(feature = "tokio")]
+        #[tokio::test]
+        async fn test_async_api() {
+            use tokio_stream::StreamExt;
+
+            let source = MockFrameSource::new(vec![tag_frame(&[(3, [320, 240])])]);
+            let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+            let mut tag_id = detector.watch();
+            let mut detections = detector.detection_stream();
+            let found = detector.wait_for_tag_async(|id| id == 3, Duration::from_secs(2));
+            let absent = detector.wait_for_tag_async(|id| id == 5, Duration::from_millis(200));
+            assert_eq!(*tag_id.borrow(), -1);
+
+            detector.apriltag_detect_start().unwrap();
+            assert_eq!(found.await, Some(3));
+            tag_id.changed().await.unwrap();
+            assert_eq!(*tag_id.borrow_and_update(), 3);
+            let detection = tokio::time::timeout(Duration::from_secs(2), detections.next())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(detection.id, 3);
+            assert_eq!(absent.await, None);
+
+            detector.apriltag_detect_end_join().unwrap();
+            // Streams end once the detector is gone
+            drop(detector);
+            while detections.next().await.is_some() {}
+        }
+
+        #[test]
+        fn test_rejection_trace() {
+            let source = MockFrameSource::new(vec![tag_frame(&[(3, [320, 240])])]);
+            let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+            // No decode is ever this confident
+            detector
+                .update_config(|config| {
+                    config.min_decision_margin = Some(10_000.0);
+                    config.rejection_capacity = 4;
+                })
+                .unwrap();
+            detector.apriltag_detect_start().unwrap();
+            wait_for_frames(&detector, 6);
+
+            assert_eq!(detector.tag_id(), Config::default().default_tag_id);
+            let rejected = detector.rejections(10);
+            assert_eq!(rejected.len(), 4);
+            for (i, entry) in rejected.iter().enumerate() {
+                assert_eq!(entry.id, 3);
+                assert_eq!(entry.reason, RejectionReason::LowMargin);
+                assert!(entry.decision_margin > 0.0 && entry.decision_margin < 10_000.0);
+                assert!((entry.center[0] - 320.0).abs() < 2.0, "{:?}", entry);
+                if i > 0 {
+                    assert_eq!(entry.frame_index, rejected[i - 1].frame_index + 1);
+                }
+            }
+            assert_eq!(detector.rejections(1), rejected[3..]);
+            let counts = detector.stats().rejections;
+            assert!(counts.low_margin >= 6);
+            assert_eq!(counts.total(), counts.low_margin);
+
+            detector.apriltag_detect_end_join().unwrap();
+            assert!(detector.rejections(10).is_empty());
+        }
+
+        #[test]
+        fn test_multi_camera_fusion() {
+            let mut cameras =
+                MultiTagDetector::new(Config::default(), Scheduling::ThreadPerCamera).unwrap();
+            let front = MockFrameSource::new(vec![tag_frame(&[(5, [360, 240])])]);
+            let side = MockFrameSource::new(vec![tag_frame(&[(5, [280, 240])])]);
+            let front = cameras
+                .add_camera(TagDetector::with_source(Box::new(front)).unwrap(), |_| {})
+                .unwrap();
+            let side = cameras
+                .add_camera(TagDetector::with_source(Box::new(side)).unwrap(), |_| {})
+                .unwrap();
+            assert!(cameras.fused_detection(5).is_none());
+            cameras
+                .set_camera_mount(
+                    side,
+                    CameraMount {
+                        yaw_deg: 4.0,
+                        trust: 0.5,
+                    },
+                )
+                .unwrap()
+                .set_fusion(Some(FusionConfig {
+                    window: Duration::from_secs(1),
+                    max_disagreement_deg: 20.0,
+                }))
+                .unwrap();
+            assert!(cameras.set_camera_mount(2, CameraMount::default()).is_err());
+            cameras.apriltag_detect_start().unwrap();
+            wait_until(|| {
+                cameras.tag_id_for(front) == Some(5) && cameras.tag_id_for(side) == Some(5)
+            });
+
+            // Each camera's bearing weighted by its margin times its trust
+            let view = |cam: usize, yaw_deg: f64| {
+                let detector = cameras.camera(cam).unwrap();
+                let fov = detector.config().horizontal_fov_deg;
+                let bearing = detector.tag_bearing_deg(fov).unwrap() + yaw_deg;
+                (
+                    bearing,
+                    detector.latest_detection().unwrap().decision_margin,
+                )
+            };
+            let (front_bearing, front_margin) = view(front, 0.0);
+            let (side_bearing, side_margin) = view(side, 4.0);
+            let expected = (front_bearing * front_margin + side_bearing * side_margin * 0.5)
+                / (front_margin + side_margin * 0.5);
+            let fused = cameras.fused_detection(5).unwrap();
+            assert_eq!(fused.cameras, [front, side]);
+            assert!(!fused.disagreement);
+            assert!((fused.bearing_deg - expected).abs() < 1e-6, "{:?}", fused);
+            assert!((fused.spread_deg - (side_bearing - front_bearing).abs()).abs() < 1e-6);
+            assert_eq!(
+                cameras.best_tag().map(|(cam, tag)| (cam, tag.id)),
+                Some((front, 5))
+            );
+            assert!(cameras.fused_detection(6).is_none());
+
+            // Turned far enough, the side camera disagrees and is not averaged in
+            cameras
+                .set_camera_mount(
+                    side,
+                    CameraMount {
+                        yaw_deg: 40.0,
+                        trust: 0.5,
+                    },
+                )
+                .unwrap();
+            let stats = cameras.fusion_stats();
+            let fused = cameras.fused_detection(5).unwrap();
+            assert!(fused.disagreement);
+            assert_eq!(fused.bearing_deg, front_bearing);
+            assert_eq!(
+                cameras.fusion_stats().disagreements,
+                stats.disagreements + 1
+            );
+            assert_eq!(cameras.fusion_stats().fused, stats.fused + 1);
+            cameras.apriltag_detect_end_join().unwrap();
+        }
+
+        #[test]
+        fn test_multi_camera_detection() {
+            let mut cameras =
+                MultiTagDetector::new(Config::default(), Scheduling::RoundRobin).unwrap();
+            let off_center = MockFrameSource::new(vec![tag_frame(&[(0, [150, 240])])]);
+            let centered = MockFrameSource::new(vec![tag_frame(&[(1, [330, 250])])]);
+            let failing = MockFrameSource::new(Vec::new());
+            let left = cameras
+                .add_camera(
+                    TagDetector::with_source(Box::new(off_center)).unwrap(),
+                    |_| {},
+                )
+                .unwrap();
+            let right = cameras
+                .add_camera(
+                    TagDetector::with_source(Box::new(centered)).unwrap(),
+                    |_| {},
+                )
+                .unwrap();
+            // The override only applies to its own camera
+            let broken = cameras
+                .add_camera(
+                    TagDetector::with_source(Box::new(failing)).unwrap(),
+                    |config| config.error_tag_id = -7,
+                )
+                .unwrap();
+            assert_eq!(cameras.camera_count(), 3);
+            assert_eq!(
+                cameras.camera(left).unwrap().config().error_tag_id,
+                Config::default().error_tag_id
+            );
+            cameras.apriltag_detect_start().unwrap();
+
+            // The failing camera doesn't disturb the others
+            wait_until(|| {
+                cameras.tag_id_for(left) == Some(0)
+                    && cameras.tag_id_for(right) == Some(1)
+                    && cameras.tag_id_for(broken) == Some(-7)
+            });
+            assert_eq!(cameras.tag_id_for(3), None);
+            assert_eq!(
+                cameras.best_tag().map(|(cam, tag)| (cam, tag.id)),
+                Some((right, 1))
+            );
+            assert_eq!(cameras.best_tag_id(), 1);
+
+            cameras.halt_detection();
+            assert_eq!(
+                cameras.tag_id_for(left),
+                Some(Config::default().default_tag_id)
+            );
+            assert_eq!(cameras.best_tag(), None);
+            assert_eq!(cameras.best_tag_id(), Config::default().default_tag_id);
+
+            cameras.resume_detection();
+            wait_until(|| cameras.tag_id_for(right) == Some(1));
+            cameras.apriltag_detect_end_join().unwrap();
+            assert!(cameras.camera(right).unwrap().camera.is_some());
+        }
+
+        #[test]
+        fn test_video_file_playback_ends_detection() {
+            let path =
+                std::env::temp_dir().join(format!("upic-playback-{}.avi", std::process::id()));
+            crate::testing::write_video(&path, &vec![tag_frame(&[(4, [200, 240])]); 10], 25.0)
+                .unwrap();
+            let source = VideoFileSource::open(&path.to_string_lossy(), false)
+                .unwrap()
+                .with_realtime(true);
+            let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+            assert_eq!(*detector.frame_center.lock().unwrap(), [320.0, 240.0]);
+            detector.apriltag_detect_start().unwrap();
+            assert_eq!(
+                detector.wait_for_tag(|id| id >= 0, Duration::from_secs(2)),
+                Some(4)
+            );
+
+            // Ten frames at 25 fps, then detection stops by itself
+            wait_until(|| !detector.is_detecting());
+            assert_eq!(detector.tag_id(), Config::default().default_tag_id);
+            assert!(detector.latest_detection().is_none());
+            detector.apriltag_detect_end_join().unwrap();
+            assert!(detector.camera.is_some());
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn test_hot_swap_source() {
+            let first = tag_frame(&[(1, [320, 240])]);
+            let second = tag_frame(&[(2, [320, 240])]);
+            let mut detector =
+                TagDetector::with_source(Box::new(MockFrameSource::new(vec![first]))).unwrap();
+            detector.apriltag_detect_start().unwrap();
+            assert_eq!(
+                detector.wait_for_tag(|id| id >= 0, Duration::from_secs(2)),
+                Some(1)
+            );
+
+            detector
+                .set_source(Box::new(MockFrameSource::new(vec![second])))
+                .unwrap();
+            assert_eq!(
+                detector.wait_for_tag(|id| id == 2, Duration::from_secs(2)),
+                Some(2)
+            );
+            assert!(!detector.is_halted());
+            assert_eq!(*detector.frame_center.lock().unwrap(), [320.0, 240.0]);
+
+            // Joining hands back the new source
+            detector.apriltag_detect_end_join().unwrap();
+            assert!(detector.camera.is_some());
+            detector.apriltag_detect_start().unwrap();
+            assert_eq!(
+                detector.wait_for_tag(|id| id >= 0, Duration::from_secs(2)),
+                Some(2)
+            );
+
+            detector
+                .update_config(|config| config.hot_swap = false)
+                .unwrap();
+            let blank = MockFrameSource::new(blank_frames(1));
+            assert!(matches!(
+                detector.set_source(Box::new(blank)),
+                Err(UpicError::DetectionRunning)
+            ));
+            assert_eq!(detector.tag_id(), 2);
+        }
+
+        #[test]
+        #[ignore = "requires camera device 0"]
+        fn test_reopen_camera_after_join() {
+            let mut detector = TagDetector::new(Some(0), None).unwrap();
+            detector.apriltag_detect_start().unwrap();
+            wait_for_frames(&detector, 1);
+
+            detector
+                .apriltag_detect_end_join()
+                .unwrap()
+                .release_camera();
+            detector.open_camera(0).unwrap();
+            assert!(detector.camera_device().is_some());
+        }
+
+        #[test]
+        fn test_detection_history() {
+            let frame = tag_frame(&[(3, [320, 240])]);
+            let mut detector =
+                TagDetector::with_source(Box::new(MockFrameSource::new(vec![frame]))).unwrap();
+            let started = Instant::now();
+            detector.apriltag_detect_start().unwrap();
+            wait_until(|| detector.recent_tags(Duration::from_secs(1)).len() > 1);
+
+            let recent = detector.recent_tags(Duration::from_secs(1));
+            assert!(recent.iter().all(|&(at, id)| id == 3 && at >= started));
+            assert!(recent.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+            assert_eq!(detector.last_seen(3), Some(recent.last().unwrap().0));
+            assert_eq!(detector.last_seen(4), None);
+
+            detector.apriltag_detect_end_join().unwrap();
+            assert!(detector.recent_tags(Duration::from_secs(1)).is_empty());
+            assert_eq!(detector.last_seen(3), None);
+        }
     }
 }
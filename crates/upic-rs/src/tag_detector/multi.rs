@@ -0,0 +1,478 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Instant;
+
+use super::TagDetector;
+use super::config::Config;
+use super::detection::{CenterOffset, TagDetection};
+use super::fusion::{CameraMount, CameraView, FusedDetection, FusionConfig, FusionStats, fuse};
+use super::selection::select_index;
+use super::sync::Recover;
+use crate::error::UpicError;
+
+/// How a `MultiTagDetector` spreads decoding over its cameras
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scheduling {
+    /// Every camera decodes in its own thread, in parallel with the others
+    #[default]
+    ThreadPerCamera,
+    /// Cameras take turns decoding one frame each, in round-robin order, so all
+    /// of them together use about one core
+    RoundRobin,
+}
+
+/// Hands out turns in the order they were asked for
+///
+/// A ticket lock: each caller draws the next ticket and waits until it is
+/// served, so callers that keep coming back are served round-robin.
+pub(crate) struct TurnGate {
+    /// Next ticket to hand out and the ticket being served
+    tickets: Mutex<(u64, u64)>,
+    served: Condvar,
+}
+
+/// A turn in a `TurnGate`, ended when dropped, including while unwinding
+pub(crate) struct Turn<'a> {
+    gate: &'a TurnGate,
+}
+
+impl TurnGate {
+    pub(crate) fn new() -> Self {
+        TurnGate {
+            tickets: Mutex::new((0, 0)),
+            served: Condvar::new(),
+        }
+    }
+
+    /// Wait for every earlier caller's turn to end, then take one.
+    pub(crate) fn enter(&self) -> Turn<'_> {
+        let mut tickets = self.tickets.lock().recover();
+        let ticket = tickets.0;
+        tickets.0 += 1;
+        drop(
+            self.served
+                .wait_while(tickets, |(_, serving)| *serving != ticket)
+                .recover(),
+        );
+        Turn { gate: self }
+    }
+}
+
+impl Drop for Turn<'_> {
+    fn drop(&mut self) {
+        self.gate.tickets.lock().recover().1 += 1;
+        self.gate.served.notify_all();
+    }
+}
+
+/// Detects AprilTags on several cameras at once, such as a front and a rear camera.
+///
+/// Each camera is a `TagDetector` with the shared base configuration plus its
+/// own overrides. Results are queried per camera index, in the order the cameras
+/// were added, or as the best tag across all cameras. Starting, stopping and
+/// halting apply to every camera, and each camera's errors stay its own: a
+/// camera that fails to read publishes `Config::error_tag_id` for its index only.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut cameras = MultiTagDetector::new(Config::default(), Scheduling::RoundRobin)?;
+/// let front = cameras.add_camera(TagDetector::new(Some(0), None)?, |_| {})?;
+/// let rear = cameras.add_camera(TagDetector::new(Some(1), None)?, |config| {
+///     config.ignored_ids.insert(3);
+/// })?;
+/// cameras.apriltag_detect_start()?;
+/// println!("Front sees {:?}", cameras.tag_id_for(front));
+/// if let Some((camera, tag)) = cameras.best_tag() {
+///     println!("Tag {} on camera {}", tag.id, camera);
+/// }
+/// ```
+///
+/// A tag seen by several cameras at once can be combined into one bearing with
+/// `fused_detection()`, once the cameras' mounts are set with
+/// `set_camera_mount()` and fusion is enabled with `set_fusion()`.
+pub struct MultiTagDetector {
+    config: Config,
+    scheduling: Scheduling,
+    cameras: Vec<TagDetector>,
+    /// Mount of each camera, by camera index
+    mounts: Vec<CameraMount>,
+    fusion: Option<FusionConfig>,
+    fusion_stats: Mutex<FusionStats>,
+    /// Decoding turns shared by the cameras under `Scheduling::RoundRobin`
+    turns: Arc<TurnGate>,
+}
+
+impl MultiTagDetector {
+    /// Create a detector without cameras; add them with `add_camera()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Base configuration of every camera. Its `ordering_method` also
+    ///   picks the best tag across cameras, and its `default_tag_id` is reported
+    ///   when no camera sees a tag.
+    /// * `scheduling` - Whether cameras decode in parallel or take turns.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::InvalidConfig` if the config fails `Config::validate()`.
+    pub fn new(config: Config, scheduling: Scheduling) -> Result<Self, UpicError> {
+        config.validate()?;
+        Ok(MultiTagDetector {
+            config,
+            scheduling,
+            cameras: Vec::new(),
+            mounts: Vec::new(),
+            fusion: None,
+            fusion_stats: Mutex::new(FusionStats::default()),
+            turns: Arc::new(TurnGate::new()),
+        })
+    }
+
+    /// Get the base configuration.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Add a camera.
+    ///
+    /// # Arguments
+    ///
+    /// * `detector` - Detector with the camera or frame source already opened.
+    ///   Its configuration is replaced by the base configuration.
+    /// * `overrides` - Changes to the base configuration for this camera only.
+    ///
+    /// # Returns
+    ///
+    /// The index of the camera, for the per-camera methods. The camera is
+    /// mounted facing forward with a trust of 1 until `set_camera_mount()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `TagDetector::update_config()` if the overridden
+    /// configuration is invalid for the camera; the camera is not added then.
+    ///
+    /// # Note
+    ///
+    /// A camera added while detection runs starts with the next
+    /// `apriltag_detect_start()`.
+    pub fn add_camera<F: FnOnce(&mut Config)>(
+        &mut self,
+        mut detector: TagDetector,
+        overrides: F,
+    ) -> Result<usize, UpicError> {
+        let mut config = self.config.clone();
+        overrides(&mut config);
+        detector.update_config(|camera_config| *camera_config = config)?;
+        detector.decode_turns = match self.scheduling {
+            Scheduling::ThreadPerCamera => None,
+            Scheduling::RoundRobin => Some(Arc::clone(&self.turns)),
+        };
+        self.cameras.push(detector);
+        self.mounts.push(CameraMount::default());
+        Ok(self.cameras.len() - 1)
+    }
+
+    /// Set where a camera points and how far its views are trusted when fusing.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::InvalidConfig` if there is no camera with that index,
+    /// the yaw is not finite or the trust is negative.
+    pub fn set_camera_mount(
+        &mut self,
+        cam: usize,
+        mount: CameraMount,
+    ) -> Result<&mut Self, UpicError> {
+        mount.validate()?;
+        let Some(slot) = self.mounts.get_mut(cam) else {
+            return Err(UpicError::InvalidConfig(format!(
+                "There is no camera {}",
+                cam
+            )));
+        };
+        *slot = mount;
+        Ok(self)
+    }
+
+    /// Get a camera's mount, as set with `set_camera_mount()`.
+    pub fn camera_mount(&self, cam: usize) -> Option<CameraMount> {
+        self.mounts.get(cam).copied()
+    }
+
+    /// Enable fusing the cameras' views of a tag, or disable it with `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::InvalidConfig` if the fusion config is invalid.
+    pub fn set_fusion(&mut self, fusion: Option<FusionConfig>) -> Result<&mut Self, UpicError> {
+        if let Some(fusion) = &fusion {
+            fusion.validate()?;
+        }
+        self.fusion = fusion;
+        Ok(self)
+    }
+
+    /// Get the fusion config, `None` while fusion is disabled.
+    pub fn fusion(&self) -> Option<FusionConfig> {
+        self.fusion
+    }
+
+    /// Get the fusion counters, which count calls of `fused_detection()`,
+    /// including through `best_tag()`.
+    pub fn fusion_stats(&self) -> FusionStats {
+        *self.fusion_stats.lock().recover()
+    }
+
+    /// Get the number of cameras.
+    pub fn camera_count(&self) -> usize {
+        self.cameras.len()
+    }
+
+    /// Get a camera's detector, for its detections, pose, stats and controls.
+    pub fn camera(&self, cam: usize) -> Option<&TagDetector> {
+        self.cameras.get(cam)
+    }
+
+    /// Get a camera's detector mutably, e.g. to change its configuration.
+    pub fn camera_mut(&mut self, cam: usize) -> Option<&mut TagDetector> {
+        self.cameras.get_mut(cam)
+    }
+
+    /// Start detection on every camera.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first camera's error if any camera fails to start. The other
+    /// cameras are started anyway and keep running.
+    pub fn apriltag_detect_start(&mut self) -> Result<&mut Self, UpicError> {
+        let mut first_error = None;
+        for (cam, detector) in self.cameras.iter_mut().enumerate() {
+            if let Err(e) = detector.apriltag_detect_start() {
+                log::error!("Can't start detection on camera {}: {}", cam, e);
+                first_error.get_or_insert(e);
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(self),
+        }
+    }
+
+    /// Signal detection on every camera to stop, like `TagDetector::apriltag_detect_end()`.
+    pub fn apriltag_detect_end(&mut self) -> &mut Self {
+        for detector in &mut self.cameras {
+            detector.apriltag_detect_end();
+        }
+        self
+    }
+
+    /// Stop detection on every camera and wait for all detection threads to exit.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first camera's `UpicError::DetectionThreadPanicked`, after
+    /// every thread was joined.
+    pub fn apriltag_detect_end_join(&mut self) -> Result<&mut Self, UpicError> {
+        // Signal every camera first so the threads wind down together
+        self.apriltag_detect_end();
+        let mut first_error = None;
+        for (cam, detector) in self.cameras.iter_mut().enumerate() {
+            if let Err(e) = detector.apriltag_detect_end_join() {
+                log::error!("Detection on camera {} failed: {}", cam, e);
+                first_error.get_or_insert(e);
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(self),
+        }
+    }
+
+    /// Halt detection on every camera, like `TagDetector::halt_detection()`.
+    pub fn halt_detection(&mut self) -> &mut Self {
+        for detector in &mut self.cameras {
+            detector.halt_detection();
+        }
+        self
+    }
+
+    /// Resume detection on every camera, like `TagDetector::resume_detection()`.
+    pub fn resume_detection(&mut self) -> &mut Self {
+        for detector in &mut self.cameras {
+            detector.resume_detection();
+        }
+        self
+    }
+
+    /// Get the tag ID published by one camera.
+    ///
+    /// # Returns
+    ///
+    /// The camera's `TagDetector::tag_id()`, or `None` if there is no camera with
+    /// that index.
+    pub fn tag_id_for(&self, cam: usize) -> Option<i32> {
+        self.cameras.get(cam).map(TagDetector::tag_id)
+    }
+
+    /// Get the best tag across all cameras.
+    ///
+    /// Each camera's selected detection is a candidate, and the base config's
+    /// ordering method picks among them. `OrderingMethod::Nearest` compares how
+    /// far each tag is from the center of its own camera's frame, and
+    /// `OrderingMethod::Single` takes the camera with the lowest index.
+    ///
+    /// With fusion enabled, the picked tag is reported by the camera
+    /// `fused_detection()` trusts most for it, which may not be the camera that
+    /// selected it.
+    ///
+    /// # Returns
+    ///
+    /// The camera index and the detection, in that camera's pixel coordinates,
+    /// or `None` if no camera sees a tag.
+    pub fn best_tag(&self) -> Option<(usize, TagDetection)> {
+        let seen: Vec<(usize, TagDetection)> = self
+            .cameras
+            .iter()
+            .enumerate()
+            .filter_map(|(cam, detector)| Some((cam, detector.latest_detection()?)))
+            .collect();
+        // Measured from each camera's own center, which becomes the origin
+        let candidates: Vec<TagDetection> = seen
+            .iter()
+            .map(|(cam, detection)| {
+                let [cx, cy] = self.cameras[*cam].oriented_center();
+                detection.translated([-cx, -cy])
+            })
+            .collect();
+        let index = select_index(&candidates, &self.config.ordering_method, [0.0, 0.0])?;
+        let best = seen[index];
+        if self.fusion.is_some()
+            && let Some(fused) = self.fused_detection(best.1.id)
+        {
+            return Some((fused.cameras[0], fused.detection));
+        }
+        Some(best)
+    }
+
+    /// Combine every camera's view of a tag into one bearing.
+    ///
+    /// A camera contributes when its last frame had the tag among the tags
+    /// passing its filters, or selected it in single-tag mode, read within
+    /// `FusionConfig::window`. Each view is
+    /// weighted by its decision margin times the camera's trust, and its
+    /// bearing is measured from the robot's forward axis using the camera's
+    /// `Config::horizontal_fov_deg` and mount yaw. When two bearings differ by
+    /// more than `FusionConfig::max_disagreement_deg`, the most trusted
+    /// camera's bearing is used on its own and the disagreement is counted in
+    /// `fusion_stats()`.
+    ///
+    /// # Returns
+    ///
+    /// The fused detection, or `None` if fusion is disabled or no trusted
+    /// camera sees the tag.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// cameras.set_camera_mount(side, CameraMount { yaw_deg: 90.0, trust: 0.5 })?;
+    /// cameras.set_fusion(Some(FusionConfig::default()))?;
+    /// if let Some(tag) = cameras.fused_detection(5) {
+    ///     println!("Tag 5 at {:+.1} degrees", tag.bearing_deg);
+    /// }
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// Views are timed with `TagDetector::last_seen()`, so a camera with
+    /// `Config::history_capacity` set to 0 never contributes.
+    pub fn fused_detection(&self, id: i32) -> Option<FusedDetection> {
+        let fusion = self.fusion?;
+        let now = Instant::now();
+        let views: Vec<CameraView> = self
+            .cameras
+            .iter()
+            .zip(&self.mounts)
+            .enumerate()
+            .filter_map(|(camera, (detector, mount))| {
+                let seen_at = detector.last_seen(id)?;
+                if now.saturating_duration_since(seen_at) > fusion.window {
+                    return None;
+                }
+                // In single-tag mode only the selected tag is published
+                let detection = detector
+                    .all_detections()
+                    .into_iter()
+                    .chain(detector.latest_detection())
+                    .find(|detection| detection.id == id)?;
+                let offset = CenterOffset::new(&detection, detector.oriented_center());
+                let bearing_deg =
+                    offset.bearing_deg(detector.config().horizontal_fov_deg) + mount.yaw_deg;
+                Some(CameraView {
+                    camera,
+                    detection,
+                    bearing_deg,
+                    trust: mount.trust,
+                })
+            })
+            .collect();
+        let fused = fuse(&views, fusion.max_disagreement_deg)?;
+        if fused.cameras.len() > 1 {
+            let mut stats = self.fusion_stats.lock().recover();
+            stats.fused += 1;
+            if fused.disagreement {
+                stats.disagreements += 1;
+                log::warn!(
+                    "Cameras {:?} disagree on tag {} by {:.1} degrees",
+                    fused.cameras,
+                    id,
+                    fused.spread_deg
+                );
+            }
+        }
+        Some(fused)
+    }
+
+    /// Get the ID of the best tag across all cameras, as picked by `best_tag()`.
+    ///
+    /// # Returns
+    ///
+    /// The tag ID, or the base config's `default_tag_id` if no camera sees a tag.
+    pub fn best_tag_id(&self) -> i32 {
+        self.best_tag()
+            .map_or(self.config.default_tag_id, |(_, detection)| detection.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_turns_are_served_in_order() {
+        let gate = Arc::new(TurnGate::new());
+        let (served, order) = mpsc::channel();
+        let first = gate.enter();
+
+        let mut waiting = Vec::new();
+        for caller in 0..3 {
+            let gate = Arc::clone(&gate);
+            let served = served.clone();
+            waiting.push(thread::spawn(move || {
+                let _turn = gate.enter();
+                served.send(caller).unwrap();
+            }));
+            // Draw the tickets in caller order
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert!(order.try_recv().is_err());
+
+        drop(first);
+        for caller in waiting {
+            caller.join().unwrap();
+        }
+        assert_eq!(order.try_iter().collect::<Vec<_>>(), [0, 1, 2]);
+    }
+}
@@ -0,0 +1,222 @@
+#[cfg(feature = "opencv")]
+use opencv::core;
+#[cfg(feature = "opencv")]
+use opencv::prelude::*;
+
+use super::config::FrameOrientation;
+use super::frame::Frame;
+use super::pose::CameraIntrinsics;
+use crate::error::UpicError;
+
+impl FrameOrientation {
+    /// Whether width and height trade places
+    fn swaps_axes(self) -> bool {
+        matches!(
+            self,
+            FrameOrientation::Rotate90 | FrameOrientation::Rotate270
+        )
+    }
+
+    /// Size of oriented frames, from the `(width, height)` the camera delivers
+    pub(crate) fn oriented_size(self, (width, height): (f64, f64)) -> (f64, f64) {
+        if self.swaps_axes() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Center of oriented frames, from the center of the frames the camera delivers
+    pub(crate) fn oriented_center(self, [x, y]: [f64; 2]) -> [f64; 2] {
+        if self.swaps_axes() { [y, x] } else { [x, y] }
+    }
+
+    /// Rotate or flip a frame into `oriented`, reusing its buffer.
+    ///
+    /// `Normal` copies the frame; callers skip the call for it instead.
+    #[cfg(feature = "opencv")]
+    pub(crate) fn apply(self, frame: &Frame, oriented: &mut Frame) -> Result<(), UpicError> {
+        match self {
+            FrameOrientation::Normal => frame.copy_to(oriented)?,
+            FrameOrientation::Rotate90 => core::rotate(frame, oriented, core::ROTATE_90_CLOCKWISE)?,
+            FrameOrientation::Rotate180 => core::rotate(frame, oriented, core::ROTATE_180)?,
+            FrameOrientation::Rotate270 => {
+                core::rotate(frame, oriented, core::ROTATE_90_COUNTERCLOCKWISE)?
+            }
+            FrameOrientation::FlipHorizontal => core::flip(frame, oriented, 1)?,
+            FrameOrientation::FlipVertical => core::flip(frame, oriented, 0)?,
+        }
+        Ok(())
+    }
+
+    /// Copy a frame into `oriented`; rotating and flipping need the `opencv`
+    /// feature and fail with `UpicError::FeatureDisabled`.
+    #[cfg(not(feature = "opencv"))]
+    pub(crate) fn apply(self, frame: &Frame, oriented: &mut Frame) -> Result<(), UpicError> {
+        match self {
+            FrameOrientation::Normal => frame.copy_to(oriented),
+            _ => Err(UpicError::FeatureDisabled { feature: "opencv" }),
+        }
+    }
+
+    /// Intrinsics of oriented frames, from those of the camera's own frames.
+    ///
+    /// Moves the principal point with the pixels, swaps the focal lengths when
+    /// the axes swap, and turns the tangential distortion along. Radial
+    /// distortion is unaffected; further terms such as thin prism are kept as
+    /// they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `intrinsics` - Intrinsics of the frames the camera delivers.
+    /// * `size` - `(width, height)` of the frames the camera delivers.
+    pub(crate) fn orient_intrinsics(
+        self,
+        intrinsics: &CameraIntrinsics,
+        (width, height): (f64, f64),
+    ) -> CameraIntrinsics {
+        let CameraIntrinsics { fx, fy, cx, cy, .. } = *intrinsics;
+        // Last pixel coordinate along each axis, which mirroring maps 0 to
+        let (right, bottom) = (width - 1.0, height - 1.0);
+        let (fx, fy, cx, cy) = match self {
+            FrameOrientation::Normal => (fx, fy, cx, cy),
+            FrameOrientation::Rotate90 => (fy, fx, bottom - cy, cx),
+            FrameOrientation::Rotate180 => (fx, fy, right - cx, bottom - cy),
+            FrameOrientation::Rotate270 => (fy, fx, cy, right - cx),
+            FrameOrientation::FlipHorizontal => (fx, fy, right - cx, cy),
+            FrameOrientation::FlipVertical => (fx, fy, cx, bottom - cy),
+        };
+
+        let mut distortion = intrinsics.distortion.clone();
+        if let [_, _, p1, p2, ..] = distortion.as_mut_slice() {
+            let (p1_, p2_) = (*p1, *p2);
+            (*p1, *p2) = match self {
+                FrameOrientation::Normal => (p1_, p2_),
+                FrameOrientation::Rotate90 => (p2_, -p1_),
+                FrameOrientation::Rotate180 => (-p1_, -p2_),
+                FrameOrientation::Rotate270 => (-p2_, p1_),
+                FrameOrientation::FlipHorizontal => (p1_, -p2_),
+                FrameOrientation::FlipVertical => (-p1_, p2_),
+            };
+        }
+        CameraIntrinsics {
+            fx,
+            fy,
+            cx,
+            cy,
+            distortion,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Distort a normalized point with OpenCV's radial and tangential model
+    fn distort(distortion: &[f64], [x, y]: [f64; 2]) -> [f64; 2] {
+        let (k1, k2, p1, p2) = (distortion[0], distortion[1], distortion[2], distortion[3]);
+        let r2 = x * x + y * y;
+        let radial = 1.0 + k1 * r2 + k2 * r2 * r2;
+        [
+            x * radial + 2.0 * p1 * x * y + p2 * (r2 + 2.0 * x * x),
+            y * radial + p1 * (r2 + 2.0 * y * y) + 2.0 * p2 * x * y,
+        ]
+    }
+
+    /// Project a normalized point to pixels
+    fn project(intrinsics: &CameraIntrinsics, point: [f64; 2]) -> [f64; 2] {
+        let [x, y] = distort(&intrinsics.distortion, point);
+        [
+            intrinsics.fx * x + intrinsics.cx,
+            intrinsics.fy * y + intrinsics.cy,
+        ]
+    }
+
+    /// Where a pixel of a `size` frame ends up in the oriented frame
+    fn orient_pixel(orientation: FrameOrientation, [x, y]: [f64; 2], size: (f64, f64)) -> [f64; 2] {
+        let (right, bottom) = (size.0 - 1.0, size.1 - 1.0);
+        match orientation {
+            FrameOrientation::Normal => [x, y],
+            FrameOrientation::Rotate90 => [bottom - y, x],
+            FrameOrientation::Rotate180 => [right - x, bottom - y],
+            FrameOrientation::Rotate270 => [y, right - x],
+            FrameOrientation::FlipHorizontal => [right - x, y],
+            FrameOrientation::FlipVertical => [x, bottom - y],
+        }
+    }
+
+    /// The same direction in the oriented camera's normalized coordinates
+    fn orient_normalized(orientation: FrameOrientation, [x, y]: [f64; 2]) -> [f64; 2] {
+        match orientation {
+            FrameOrientation::Normal => [x, y],
+            FrameOrientation::Rotate90 => [-y, x],
+            FrameOrientation::Rotate180 => [-x, -y],
+            FrameOrientation::Rotate270 => [y, -x],
+            FrameOrientation::FlipHorizontal => [-x, y],
+            FrameOrientation::FlipVertical => [x, -y],
+        }
+    }
+
+    const ORIENTATIONS: [FrameOrientation; 6] = [
+        FrameOrientation::Normal,
+        FrameOrientation::Rotate90,
+        FrameOrientation::Rotate180,
+        FrameOrientation::Rotate270,
+        FrameOrientation::FlipHorizontal,
+        FrameOrientation::FlipVertical,
+    ];
+
+    #[test]
+    fn test_oriented_intrinsics_project_like_oriented_pixels() {
+        let size = (640.0, 480.0);
+        let intrinsics = CameraIntrinsics {
+            fx: 500.0,
+            fy: 520.0,
+            cx: 330.0,
+            cy: 236.0,
+            distortion: vec![-0.2, 0.05, 0.003, -0.002, 0.0],
+        };
+        for orientation in ORIENTATIONS {
+            let oriented = orientation.orient_intrinsics(&intrinsics, size);
+            for point in [[0.1, 0.2], [-0.3, 0.05], [0.25, -0.15]] {
+                let expected = orient_pixel(orientation, project(&intrinsics, point), size);
+                let actual = project(&oriented, orient_normalized(orientation, point));
+                for axis in 0..2 {
+                    assert!(
+                        (expected[axis] - actual[axis]).abs() < 1e-9,
+                        "{:?}: {:?} vs {:?}",
+                        orientation,
+                        expected,
+                        actual
+                    );
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "opencv")]
+    #[test]
+    fn test_apply_matches_oriented_size() {
+        let mut frame =
+            Frame::new_rows_cols_with_default(48, 64, core::CV_8UC1, core::Scalar::all(0.0))
+                .unwrap();
+        // Mark the top-left pixel to follow it around
+        *frame.at_2d_mut::<u8>(0, 0).unwrap() = 255;
+        let mut oriented = Frame::default();
+        for orientation in ORIENTATIONS {
+            orientation.apply(&frame, &mut oriented).unwrap();
+            let (width, height) = orientation.oriented_size((64.0, 48.0));
+            assert_eq!(
+                (oriented.cols(), oriented.rows()),
+                (width as i32, height as i32)
+            );
+            let [x, y] = orient_pixel(orientation, [0.0, 0.0], (64.0, 48.0));
+            assert_eq!(*oriented.at_2d::<u8>(y as i32, x as i32).unwrap(), 255);
+        }
+        assert_eq!(
+            FrameOrientation::Rotate270.oriented_center([32.0, 24.0]),
+            [24.0, 32.0]
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use std::time::{Duration, Instant};
+
+/// Interval between frame starts for a target frame rate.
+///
+/// `None` gives a zero period, which runs the detection loop flat out.
+pub(crate) fn frame_period(target_fps: Option<f64>) -> Duration {
+    target_fps.map_or(Duration::ZERO, |fps| Duration::from_secs_f64(1.0 / fps))
+}
+
+/// Time left to sleep so frames start `period` apart.
+///
+/// Frame read and detection already used up `now - frame_started` of the
+/// period; frames taking longer than the period are not slowed down further.
+pub(crate) fn remaining_budget(period: Duration, frame_started: Instant, now: Instant) -> Duration {
+    period.saturating_sub(now.duration_since(frame_started))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sleeps_only_the_remaining_budget() {
+        let period = frame_period(Some(25.0));
+        assert_eq!(period, Duration::from_millis(40));
+
+        let started = Instant::now();
+        let after = |millis| started + Duration::from_millis(millis);
+        assert_eq!(
+            remaining_budget(period, started, after(15)),
+            Duration::from_millis(25)
+        );
+        // Slow frames get no artificial sleep
+        assert_eq!(remaining_budget(period, started, after(60)), Duration::ZERO);
+
+        assert_eq!(frame_period(None), Duration::ZERO);
+        assert_eq!(
+            remaining_budget(frame_period(None), started, after(5)),
+            Duration::ZERO
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use std::cell::RefCell;
+
+use super::config::{Config, DetectorParams, Preprocess, TagFamily};
+use super::decode::TagDecoder;
+use super::detection::TagDetection;
+use super::frame::Frame;
+use super::preprocess::Preprocessor;
+use crate::error::UpicError;
+
+/// Turns frames into tag detections as configured
+///
+/// Preprocesses a frame and decodes the tags in its region of interest. Shared
+/// by the detection thread and `TagDetector::process_frame()`, so both find the
+/// same tags in the same frame. The decoder and preprocessor are rebuilt only
+/// when their part of the configuration changes.
+pub(crate) struct FramePipeline {
+    families: Vec<TagFamily>,
+    detector_params: DetectorParams,
+    decoder: TagDecoder,
+    preprocess: Preprocess,
+    use_opencl: bool,
+    preprocessor: Preprocessor,
+}
+
+impl FramePipeline {
+    pub(crate) fn new(config: &Config) -> Result<Self, UpicError> {
+        Ok(FramePipeline {
+            families: config.families.clone(),
+            detector_params: config.detector_params,
+            decoder: TagDecoder::new(&config.families, &config.detector_params)?,
+            preprocess: config.preprocess.clone(),
+            use_opencl: config.use_opencl,
+            preprocessor: Preprocessor::new(&config.preprocess, config.use_opencl)?,
+        })
+    }
+
+    /// Follow changes to the tag families, detector parameters, preprocessing
+    /// and OpenCL use.
+    ///
+    /// A part that fails to rebuild is logged and the previous one kept, so
+    /// frames are still decoded; it is retried on the next call.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the last part that failed to rebuild.
+    pub(crate) fn update(&mut self, config: &Config) -> Result<(), UpicError> {
+        let mut result = Ok(());
+        if config.families != self.families || config.detector_params != self.detector_params {
+            match TagDecoder::new(&config.families, &config.detector_params) {
+                Ok(decoder) => {
+                    log::info!(
+                        "Decoding tag families {:?} with {:?}",
+                        config.families,
+                        config.detector_params
+                    );
+                    self.decoder = decoder;
+                    self.families = config.families.clone();
+                    self.detector_params = config.detector_params;
+                }
+                Err(e) => {
+                    log::error!("{}", e);
+                    result = Err(e);
+                }
+            }
+        }
+        if config.preprocess != self.preprocess || config.use_opencl != self.use_opencl {
+            match Preprocessor::new(&config.preprocess, config.use_opencl) {
+                Ok(preprocessor) => {
+                    log::info!(
+                        "Preprocessing frames with {:?} on the {}",
+                        config.preprocess.steps,
+                        if preprocessor.uses_opencl() {
+                            "OpenCL device"
+                        } else {
+                            "CPU"
+                        }
+                    );
+                    self.preprocessor = preprocessor;
+                    self.preprocess = config.preprocess.clone();
+                    self.use_opencl = config.use_opencl;
+                }
+                Err(e) => {
+                    log::error!("Can't set up frame preprocessing: {}", e);
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+
+    /// Whether frames are preprocessed through OpenCL
+    pub(crate) fn uses_opencl(&self) -> bool {
+        self.preprocessor.uses_opencl()
+    }
+
+    /// Preprocess a frame and decode every tag within `Config::roi`.
+    ///
+    /// # Returns
+    ///
+    /// All decoded tags in full-frame pixel coordinates, before the ID filter
+    /// and quality thresholds.
+    pub(crate) fn detect(
+        &mut self,
+        frame: &Frame,
+        config: &Config,
+    ) -> Result<Vec<TagDetection>, UpicError> {
+        let input = self.preprocessor.apply(frame)?;
+        self.decoder.decode_region(input, config.roi)
+    }
+}
+
+thread_local! {
+    /// Pipeline behind `TagDetector::process_frame()`, kept per calling thread so
+    /// repeated calls reuse the decoder without the detector having to share it
+    static CALLER_PIPELINE: RefCell<Option<FramePipeline>> = const { RefCell::new(None) };
+}
+
+/// Detect tags in a frame with the calling thread's pipeline, built on first use.
+pub(crate) fn detect_on_caller(
+    frame: &Frame,
+    config: &Config,
+) -> Result<Vec<TagDetection>, UpicError> {
+    CALLER_PIPELINE.with_borrow_mut(|pipeline| {
+        let pipeline = match pipeline {
+            Some(pipeline) => {
+                // A part that failed to rebuild is logged, the previous one still decodes
+                let _ = pipeline.update(config);
+                pipeline
+            }
+            None => pipeline.insert(FramePipeline::new(config)?),
+        };
+        pipeline.detect(frame, config)
+    })
+}
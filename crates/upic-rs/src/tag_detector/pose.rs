@@ -0,0 +1,208 @@
+#[cfg(feature = "opencv")]
+use opencv::core::{Point2d, Point3d, Vector};
+#[cfg(feature = "opencv")]
+use opencv::prelude::*;
+#[cfg(feature = "opencv")]
+use opencv::{Result, calib3d};
+
+use super::detection::TagDetection;
+#[cfg(not(feature = "opencv"))]
+use crate::error::UpicError;
+
+/// Pinhole camera intrinsics used for tag pose estimation
+///
+/// Focal lengths and principal point are in pixels at the resolution
+/// detection runs at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraIntrinsics {
+    /// Focal length along x
+    pub fx: f64,
+    /// Focal length along y
+    pub fy: f64,
+    /// Principal point x
+    pub cx: f64,
+    /// Principal point y
+    pub cy: f64,
+    /// Distortion coefficients in OpenCV order (k1, k2, p1, p2[, k3, ...]);
+    /// empty for an undistorted image
+    pub distortion: Vec<f64>,
+}
+
+impl CameraIntrinsics {
+    /// The 3x3 camera matrix
+    #[cfg(feature = "opencv")]
+    pub(crate) fn camera_matrix(&self) -> Result<Mat> {
+        Mat::from_slice_2d(&[
+            [self.fx, 0.0, self.cx],
+            [0.0, self.fy, self.cy],
+            [0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Intrinsics of frames undistorted with these intrinsics
+    ///
+    /// Undistortion keeps the camera matrix, so only the distortion is dropped.
+    pub(crate) fn rectified(&self) -> CameraIntrinsics {
+        CameraIntrinsics {
+            distortion: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Pose of a tag relative to the camera
+///
+/// Uses the OpenCV camera frame: x to the right, y down, z forward along the
+/// optical axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TagPose {
+    /// ID of the tag this pose belongs to
+    pub id: i32,
+    /// Position of the tag center in meters
+    pub translation: [f64; 3],
+    /// Rotation from tag to camera coordinates, row-major
+    pub rotation: [[f64; 3]; 3],
+}
+
+impl TagPose {
+    /// Rotation as a unit quaternion `[w, x, y, z]`
+    pub fn quaternion(&self) -> [f64; 4] {
+        let r = &self.rotation;
+        let trace = r[0][0] + r[1][1] + r[2][2];
+        if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            [
+                0.25 * s,
+                (r[2][1] - r[1][2]) / s,
+                (r[0][2] - r[2][0]) / s,
+                (r[1][0] - r[0][1]) / s,
+            ]
+        } else if r[0][0] > r[1][1] && r[0][0] > r[2][2] {
+            let s = (1.0 + r[0][0] - r[1][1] - r[2][2]).sqrt() * 2.0;
+            [
+                (r[2][1] - r[1][2]) / s,
+                0.25 * s,
+                (r[0][1] + r[1][0]) / s,
+                (r[0][2] + r[2][0]) / s,
+            ]
+        } else if r[1][1] > r[2][2] {
+            let s = (1.0 + r[1][1] - r[0][0] - r[2][2]).sqrt() * 2.0;
+            [
+                (r[0][2] - r[2][0]) / s,
+                (r[0][1] + r[1][0]) / s,
+                0.25 * s,
+                (r[1][2] + r[2][1]) / s,
+            ]
+        } else {
+            let s = (1.0 + r[2][2] - r[0][0] - r[1][1]).sqrt() * 2.0;
+            [
+                (r[1][0] - r[0][1]) / s,
+                (r[0][2] + r[2][0]) / s,
+                (r[1][2] + r[2][1]) / s,
+                0.25 * s,
+            ]
+        }
+    }
+
+    /// Straight-line distance from the camera to the tag center in meters
+    pub fn distance(&self) -> f64 {
+        let [x, y, z] = self.translation;
+        (x * x + y * y + z * z).sqrt()
+    }
+}
+
+/// Estimate the pose of a detected tag with solvePnP
+///
+/// `tag_size` is the edge length of the tag's black square in meters.
+/// Returns `Ok(None)` when solvePnP finds no solution.
+#[cfg(feature = "opencv")]
+pub(crate) fn estimate_pose(
+    detection: &TagDetection,
+    intrinsics: &CameraIntrinsics,
+    tag_size: f64,
+) -> Result<Option<TagPose>> {
+    // Object points in the order required by SOLVEPNP_IPPE_SQUARE, matching
+    // the detector's corner order
+    let half = tag_size / 2.0;
+    let object_points: Vector<Point3d> = Vector::from_iter([
+        Point3d::new(-half, half, 0.0),
+        Point3d::new(half, half, 0.0),
+        Point3d::new(half, -half, 0.0),
+        Point3d::new(-half, -half, 0.0),
+    ]);
+    let image_points: Vector<Point2d> = detection
+        .corners
+        .iter()
+        .map(|[x, y]| Point2d::new(*x, *y))
+        .collect();
+
+    let camera_matrix = intrinsics.camera_matrix()?;
+    let distortion: Vector<f64> = Vector::from_slice(&intrinsics.distortion);
+
+    let mut rvec = Mat::default();
+    let mut tvec = Mat::default();
+    let solved = calib3d::solve_pnp(
+        &object_points,
+        &image_points,
+        &camera_matrix,
+        &distortion,
+        &mut rvec,
+        &mut tvec,
+        false,
+        calib3d::SOLVEPNP_IPPE_SQUARE,
+    )?;
+    if !solved {
+        return Ok(None);
+    }
+
+    let mut rotation_matrix = Mat::default();
+    calib3d::rodrigues(&rvec, &mut rotation_matrix, &mut Mat::default())?;
+
+    let mut translation = [0.0; 3];
+    for (i, value) in translation.iter_mut().enumerate() {
+        *value = *tvec.at::<f64>(i as i32)?;
+    }
+    let mut rotation = [[0.0; 3]; 3];
+    for (row, values) in rotation.iter_mut().enumerate() {
+        for (col, value) in values.iter_mut().enumerate() {
+            *value = *rotation_matrix.at_2d::<f64>(row as i32, col as i32)?;
+        }
+    }
+
+    Ok(Some(TagPose {
+        id: detection.id,
+        translation,
+        rotation,
+    }))
+}
+
+/// Pose estimation needs solvePnP, so without the `opencv` feature it always
+/// fails with `UpicError::FeatureDisabled`.
+#[cfg(not(feature = "opencv"))]
+pub(crate) fn estimate_pose(
+    _detection: &TagDetection,
+    _intrinsics: &CameraIntrinsics,
+    _tag_size: f64,
+) -> Result<Option<TagPose>, UpicError> {
+    Err(UpicError::FeatureDisabled { feature: "opencv" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quaternion_identity_and_half_turn() {
+        let mut pose = TagPose {
+            id: 0,
+            translation: [0.3, 0.0, 0.4],
+            rotation: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        };
+        assert_eq!(pose.quaternion(), [1.0, 0.0, 0.0, 0.0]);
+        assert!((pose.distance() - 0.5).abs() < 1e-12);
+
+        // 180° about y, as for a tag facing the camera
+        pose.rotation = [[-1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -1.0]];
+        assert_eq!(pose.quaternion(), [0.0, 0.0, 1.0, 0.0]);
+    }
+}
@@ -0,0 +1,330 @@
+use opencv::core::{self, Mat, Ptr, Size, ToInputArray, ToOutputArray, UMat};
+use opencv::imgproc::{self, CLAHE};
+use opencv::prelude::*;
+
+use super::config::{Preprocess, PreprocessStep};
+use crate::error::UpicError;
+
+/// A `PreprocessStep` ready to run
+enum Stage {
+    Grayscale,
+    GaussianBlur(Size),
+    /// The equalizer is built once, not per frame
+    Clahe(Ptr<CLAHE>),
+    Threshold(f64),
+}
+
+/// A frame buffer the steps run on, in host memory or on the OpenCL device
+trait Frame: ToInputArray + ToOutputArray + Sized {
+    fn new_empty() -> Self;
+    fn channel_count(&self) -> i32;
+    fn copy_into(&self, output: &mut Self) -> opencv::Result<()>;
+}
+
+impl Frame for Mat {
+    fn new_empty() -> Self {
+        Mat::default()
+    }
+
+    fn channel_count(&self) -> i32 {
+        MatTraitConst::channels(self)
+    }
+
+    fn copy_into(&self, output: &mut Self) -> opencv::Result<()> {
+        self.copy_to(output)
+    }
+}
+
+impl Frame for UMat {
+    fn new_empty() -> Self {
+        UMat::new_def()
+    }
+
+    fn channel_count(&self) -> i32 {
+        UMatTraitConst::channels(self)
+    }
+
+    fn copy_into(&self, output: &mut Self) -> opencv::Result<()> {
+        self.copy_to(output)
+    }
+}
+
+/// Buffers the steps write alternately into, kept across frames
+struct Buffers<F> {
+    front: F,
+    back: F,
+    gray: F,
+}
+
+impl<F: Frame> Buffers<F> {
+    fn new() -> Self {
+        Buffers {
+            front: F::new_empty(),
+            back: F::new_empty(),
+            gray: F::new_empty(),
+        }
+    }
+
+    /// Run the steps on `frame`.
+    ///
+    /// # Returns
+    ///
+    /// Whether any step ran; the output of the last one is then in `front`.
+    fn run(&mut self, stages: &mut [Stage], frame: &F) -> Result<bool, UpicError> {
+        let mut processed = false;
+        for stage in stages {
+            let input = if processed { &self.front } else { frame };
+            match stage {
+                Stage::Grayscale => to_gray(input, &mut self.back)?,
+                Stage::GaussianBlur(ksize) => {
+                    imgproc::gaussian_blur_def(input, &mut self.back, *ksize, 0.0)?;
+                }
+                Stage::Clahe(equalizer) => {
+                    if input.channel_count() == 1 {
+                        equalizer.apply(input, &mut self.back)?;
+                    } else {
+                        to_gray(input, &mut self.gray)?;
+                        equalizer.apply(&self.gray, &mut self.back)?;
+                    }
+                }
+                Stage::Threshold(value) => {
+                    imgproc::threshold(
+                        input,
+                        &mut self.back,
+                        *value,
+                        255.0,
+                        imgproc::THRESH_BINARY,
+                    )?;
+                }
+            }
+            // The output becomes the next step's input; swapping keeps both allocations
+            std::mem::swap(&mut self.front, &mut self.back);
+            processed = true;
+        }
+        Ok(processed)
+    }
+}
+
+/// Device buffers of the OpenCL path
+struct OpenClBuffers {
+    /// The frame uploaded to the device
+    upload: UMat,
+    steps: Buffers<UMat>,
+    /// The gray result in host memory, where the decoder reads it
+    download: Mat,
+}
+
+/// Applies a `Preprocess` pipeline to frames.
+///
+/// Steps write alternately into two buffers that are kept across frames, so
+/// once the first frame has been processed no step allocates as long as the
+/// frame size stays the same. With OpenCL the buffers live on the device and
+/// the frame is converted to grayscale there, so only the plane the decoder
+/// reads is copied back.
+pub(crate) struct Preprocessor {
+    stages: Vec<Stage>,
+    cpu: Buffers<Mat>,
+    /// Set when frames are processed through OpenCL
+    opencl: Option<OpenClBuffers>,
+}
+
+impl Preprocessor {
+    /// Set up the steps of `preprocess`.
+    ///
+    /// With `use_opencl` the steps run through OpenCL if a device is available;
+    /// otherwise a warning is logged and they run on the CPU.
+    pub(crate) fn new(preprocess: &Preprocess, use_opencl: bool) -> Result<Self, UpicError> {
+        let stages = preprocess
+            .steps
+            .iter()
+            .map(|step| match *step {
+                PreprocessStep::Grayscale => Ok(Stage::Grayscale),
+                PreprocessStep::GaussianBlur { ksize } => {
+                    Ok(Stage::GaussianBlur(Size::new(ksize, ksize)))
+                }
+                PreprocessStep::Clahe { clip_limit, tile } => {
+                    imgproc::create_clahe(clip_limit, Size::new(tile, tile)).map(Stage::Clahe)
+                }
+                PreprocessStep::Threshold { value } => Ok(Stage::Threshold(value)),
+            })
+            .collect::<Result<_, opencv::Error>>()?;
+        let opencl = if use_opencl && opencl_available()? {
+            Some(OpenClBuffers {
+                upload: UMat::new_def(),
+                steps: Buffers::new(),
+                download: Mat::default(),
+            })
+        } else {
+            if use_opencl {
+                log::warn!("OpenCL is not available, preprocessing frames on the CPU");
+            }
+            None
+        };
+        Ok(Self {
+            stages,
+            cpu: Buffers::new(),
+            opencl,
+        })
+    }
+
+    /// Whether frames are processed through OpenCL
+    pub(crate) fn uses_opencl(&self) -> bool {
+        self.opencl.is_some()
+    }
+
+    /// Run the steps on a frame.
+    ///
+    /// # Returns
+    ///
+    /// The processed frame, or `frame` itself when there are no steps and
+    /// OpenCL isn't used. It stays valid until the next call.
+    pub(crate) fn apply<'a>(&'a mut self, frame: &'a Mat) -> Result<&'a Mat, UpicError> {
+        let Some(opencl) = &mut self.opencl else {
+            let processed = self.cpu.run(&mut self.stages, frame)?;
+            return Ok(if processed { &self.cpu.front } else { frame });
+        };
+        frame.copy_to(&mut opencl.upload)?;
+        let processed = opencl.steps.run(&mut self.stages, &opencl.upload)?;
+        let output = if processed {
+            &opencl.steps.front
+        } else {
+            &opencl.upload
+        };
+        // Detection needs the pixels in host memory, and only reads the gray plane
+        if output.channel_count() == 1 {
+            output.copy_to(&mut opencl.download)?;
+        } else {
+            imgproc::cvt_color_def(output, &mut opencl.steps.gray, imgproc::COLOR_BGR2GRAY)?;
+            opencl.steps.gray.copy_to(&mut opencl.download)?;
+        }
+        Ok(&opencl.download)
+    }
+}
+
+/// Whether OpenCV can run operations through OpenCL on this machine.
+fn opencl_available() -> Result<bool, UpicError> {
+    Ok(core::have_opencl()? && core::use_opencl()?)
+}
+
+/// Convert to grayscale, copying frames that already are.
+fn to_gray<F: Frame>(input: &F, output: &mut F) -> Result<(), UpicError> {
+    if input.channel_count() == 1 {
+        input.copy_into(output)?;
+    } else {
+        imgproc::cvt_color_def(input, output, imgproc::COLOR_BGR2GRAY)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tag_detector::source::{FrameSource, MockFrameSource};
+    use std::time::{Duration, Instant};
+
+    /// Frames processed per pipeline in the timing comparison
+    const FRAMES: usize = 30;
+
+    fn gradient_frames() -> Vec<Mat> {
+        let mut frame = Mat::new_rows_cols_with_default(
+            480,
+            640,
+            opencv::core::CV_8UC3,
+            opencv::core::Scalar::all(0.0),
+        )
+        .unwrap();
+        for y in 0..480 {
+            let row = frame.at_row_mut::<opencv::core::Vec3b>(y).unwrap();
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let value = ((x + y as usize) / 5 % 256) as u8;
+                *pixel = opencv::core::VecN([value; 3]);
+            }
+        }
+        vec![frame]
+    }
+
+    /// Average time per frame through `preprocess`, and the last output's size
+    /// and channel count.
+    fn time_per_frame(preprocess: &Preprocess) -> (Duration, (i32, i32, i32)) {
+        let mut source = MockFrameSource::new(gradient_frames());
+        let mut preprocessor = Preprocessor::new(preprocess, false).unwrap();
+        let mut shape = (0, 0, 0);
+        let started = Instant::now();
+        for _ in 0..FRAMES {
+            let frame = source.read_frame().unwrap();
+            let output = preprocessor.apply(&frame).unwrap();
+            shape = (output.cols(), output.rows(), output.channels());
+        }
+        (started.elapsed() / FRAMES as u32, shape)
+    }
+
+    #[test]
+    fn test_preprocess_timing_with_and_without_clahe() {
+        let grayscale = Preprocess {
+            steps: vec![
+                PreprocessStep::Grayscale,
+                PreprocessStep::GaussianBlur { ksize: 3 },
+            ],
+        };
+        let mut clahe = grayscale.clone();
+        clahe.steps.push(PreprocessStep::Clahe {
+            clip_limit: 2.0,
+            tile: 8,
+        });
+        clahe.steps.push(PreprocessStep::Threshold { value: 127.0 });
+
+        let (untouched, shape) = time_per_frame(&Preprocess::default());
+        assert_eq!(shape, (640, 480, 3));
+        let (without_clahe, shape) = time_per_frame(&grayscale);
+        assert_eq!(shape, (640, 480, 1));
+        let (with_clahe, shape) = time_per_frame(&clahe);
+        assert_eq!(shape, (640, 480, 1));
+        log::debug!(
+            "Preprocessing per frame: none {:?}, grayscale + blur {:?}, with CLAHE {:?}",
+            untouched,
+            without_clahe,
+            with_clahe
+        );
+
+        // The buffers are reused, so the output stays in place between frames
+        let mut source = MockFrameSource::new(gradient_frames());
+        let mut preprocessor = Preprocessor::new(&clahe, false).unwrap();
+        let frame = source.read_frame().unwrap();
+        let first = preprocessor.apply(&frame).unwrap().data();
+        let frame = source.read_frame().unwrap();
+        assert_eq!(preprocessor.apply(&frame).unwrap().data(), first);
+    }
+
+    #[test]
+    fn test_opencl_matches_cpu() {
+        let blur = Preprocess {
+            steps: vec![
+                PreprocessStep::Grayscale,
+                PreprocessStep::GaussianBlur { ksize: 5 },
+            ],
+        };
+        let frame = gradient_frames().remove(0);
+        for preprocess in [Preprocess::default(), blur] {
+            let mut cpu = Preprocessor::new(&preprocess, false).unwrap();
+            let mut opencl = Preprocessor::new(&preprocess, true).unwrap();
+            // Without a device the OpenCL request falls back to the CPU
+            assert_eq!(opencl.uses_opencl(), opencl_available().unwrap());
+            assert!(!cpu.uses_opencl());
+
+            let mut expected = Mat::default();
+            to_gray(cpu.apply(&frame).unwrap(), &mut expected).unwrap();
+            let output = opencl.apply(&frame).unwrap();
+            assert_eq!((output.cols(), output.rows()), (640, 480));
+            if opencl.uses_opencl() {
+                // The device result is downloaded as the gray plane the decoder reads
+                assert_eq!(output.channels(), 1);
+            }
+            let mut gray = Mat::default();
+            to_gray(output, &mut gray).unwrap();
+            // Device kernels may round differently
+            let difference =
+                core::norm2(&expected, &gray, core::NORM_INF, &core::no_array()).unwrap();
+            assert!(difference <= 1.0, "outputs differ by {}", difference);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use opencv::core::{Mat, Point, Rect, Scalar, Vector};
+use opencv::prelude::*;
+use opencv::{highgui, imgproc};
+
+use super::detection::TagDetection;
+use crate::error::UpicError;
+
+/// Title of the preview window
+const WINDOW_NAME: &str = "upic tag preview";
+
+/// Outline color of the published tag (BGR)
+const SELECTED_COLOR: (f64, f64, f64) = (0.0, 255.0, 0.0);
+/// Outline color of the other tags that passed the ID filters (BGR)
+const CANDIDATE_COLOR: (f64, f64, f64) = (0.0, 200.0, 255.0);
+/// Color of the frame center crosshair (BGR)
+const CENTER_COLOR: (f64, f64, f64) = (0.0, 0.0, 255.0);
+/// Color of the region of interest outline (BGR)
+const ROI_COLOR: (f64, f64, f64) = (255.0, 128.0, 0.0);
+
+fn color((b, g, r): (f64, f64, f64)) -> Scalar {
+    Scalar::new(b, g, r, 0.0)
+}
+
+fn point([x, y]: [f64; 2]) -> Point {
+    Point::new(x.round() as i32, y.round() as i32)
+}
+
+/// Draw the detection overlay on a color copy of a frame.
+///
+/// # Arguments
+///
+/// * `frame` - The frame the tags were detected in, grayscale or BGR.
+/// * `candidates` - The tags that passed the ID filters, outlined with their ID.
+/// * `selected` - The published tag, outlined in a distinct color.
+/// * `center` - The frame center nearest-tag ordering measures from.
+/// * `roi` - The region of interest detection was restricted to, if any.
+///
+/// # Returns
+///
+/// The annotated copy; `frame` itself is left untouched.
+pub(crate) fn draw_overlay(
+    frame: &Mat,
+    candidates: &[TagDetection],
+    selected: Option<&TagDetection>,
+    center: [f64; 2],
+    roi: Option<Rect>,
+) -> Result<Mat, UpicError> {
+    let mut canvas = Mat::default();
+    if frame.channels() == 1 {
+        imgproc::cvt_color_def(frame, &mut canvas, imgproc::COLOR_GRAY2BGR)?;
+    } else {
+        frame.copy_to(&mut canvas)?;
+    }
+
+    if let Some(roi) = roi {
+        imgproc::rectangle(&mut canvas, roi, color(ROI_COLOR), 1, imgproc::LINE_8, 0)?;
+    }
+
+    for candidate in candidates {
+        let is_selected = selected.is_some_and(|selected| {
+            selected.id == candidate.id && selected.family == candidate.family
+        });
+        let outline_color = color(if is_selected {
+            SELECTED_COLOR
+        } else {
+            CANDIDATE_COLOR
+        });
+
+        let corners: Vector<Point> = candidate.corners.iter().copied().map(point).collect();
+        let outlines: Vector<Vector<Point>> = std::iter::once(corners).collect();
+        imgproc::polylines(
+            &mut canvas,
+            &outlines,
+            true,
+            outline_color,
+            2,
+            imgproc::LINE_8,
+            0,
+        )?;
+        imgproc::put_text(
+            &mut canvas,
+            &candidate.id.to_string(),
+            point(candidate.center),
+            imgproc::FONT_HERSHEY_SIMPLEX,
+            0.8,
+            outline_color,
+            2,
+            imgproc::LINE_AA,
+            false,
+        )?;
+    }
+
+    imgproc::draw_marker(
+        &mut canvas,
+        point(center),
+        color(CENTER_COLOR),
+        imgproc::MARKER_CROSS,
+        20,
+        1,
+        imgproc::LINE_8,
+    )?;
+    Ok(canvas)
+}
+
+/// The preview window shown by the detection thread.
+///
+/// Tracks whether the window is open so it is only created when the preview
+/// is enabled and only destroyed when it was actually shown.
+pub(crate) struct PreviewWindow {
+    open: bool,
+}
+
+impl PreviewWindow {
+    pub(crate) fn new() -> Self {
+        PreviewWindow { open: false }
+    }
+
+    /// Show an annotated frame, opening the window if needed.
+    ///
+    /// Also pumps the window's events, which HighGUI requires for the window
+    /// to repaint.
+    pub(crate) fn show(&mut self, canvas: &Mat) -> Result<(), UpicError> {
+        highgui::imshow(WINDOW_NAME, canvas)?;
+        self.open = true;
+        highgui::wait_key(1)?;
+        Ok(())
+    }
+
+    /// Destroy the window if it is open.
+    pub(crate) fn close(&mut self) {
+        if std::mem::take(&mut self.open)
+            && let Err(e) = highgui::destroy_window(WINDOW_NAME)
+        {
+            log::warn!("Can't close the preview window: {}", e);
+        }
+    }
+}
+
+impl Drop for PreviewWindow {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tag_detector::config::TagFamily;
+
+    #[test]
+    fn test_draw_overlay_leaves_frame_untouched() {
+        let frame =
+            Mat::new_rows_cols_with_default(120, 160, opencv::core::CV_8UC1, Scalar::all(0.0))
+                .unwrap();
+        let tag = TagDetection {
+            id: 7,
+            family: TagFamily::Tag36h11,
+            corners: [[40.0, 40.0], [80.0, 40.0], [80.0, 80.0], [40.0, 80.0]],
+            center: [60.0, 60.0],
+            decision_margin: 30.0,
+            hamming: 0,
+        };
+
+        let canvas = draw_overlay(
+            &frame,
+            &[tag],
+            Some(&tag),
+            [80.0, 60.0],
+            Some(Rect::new(10, 10, 140, 100)),
+        )
+        .unwrap();
+        assert_eq!((canvas.cols(), canvas.rows()), (160, 120));
+        assert_eq!(canvas.channels(), 3);
+
+        // The outline is drawn in the selected color on the copy only
+        let edge = canvas.at_2d::<opencv::core::Vec3b>(40, 60).unwrap();
+        assert_eq!(edge.0, [0, 255, 0]);
+        assert_eq!(opencv::core::count_non_zero(&frame).unwrap(), 0);
+    }
+}
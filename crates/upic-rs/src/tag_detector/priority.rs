@@ -0,0 +1,49 @@
+/// Give the calling thread the nice value `priority`.
+///
+/// Failures, typically a missing `CAP_SYS_NICE` for raising the priority, are
+/// logged and the thread keeps its priority.
+#[cfg(all(feature = "thread-priority", target_os = "linux"))]
+pub(crate) fn apply_thread_priority(priority: i32) {
+    // SAFETY: gettid has no preconditions and can't fail
+    let tid = unsafe { libc::gettid() };
+    // On Linux a thread ID selects only that thread, not the whole process
+    // SAFETY: setpriority only reads its integer arguments
+    let result = unsafe { libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, priority) };
+    if result == 0 {
+        log::info!("Detection thread runs at nice value {}", priority);
+    } else {
+        log::warn!(
+            "Can't set the detection thread's nice value to {}: {}",
+            priority,
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+/// Thread priorities are only supported on Linux with the `thread-priority` feature.
+#[cfg(not(all(feature = "thread-priority", target_os = "linux")))]
+pub(crate) fn apply_thread_priority(priority: i32) {
+    log::warn!(
+        "Ignoring thread_priority {}, it needs the thread-priority feature on Linux",
+        priority
+    );
+}
+
+#[cfg(all(test, feature = "thread-priority", target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lower_priority() {
+        // The lowest priority needs no privileges, whatever the test runs at
+        std::thread::spawn(|| {
+            apply_thread_priority(19);
+            // SAFETY: gettid has no preconditions, getpriority only reads its arguments
+            let nice =
+                unsafe { libc::getpriority(libc::PRIO_PROCESS, libc::gettid() as libc::id_t) };
+            assert_eq!(nice, 19);
+        })
+        .join()
+        .unwrap();
+    }
+}
@@ -0,0 +1,335 @@
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+use super::source::{CameraProperty, FrameSource};
+use super::sync::Recover;
+use crate::error::UpicError;
+
+/// `CAP_PROP_AUTO_EXPOSURE` value turning auto exposure on with the V4L2 backend
+const AUTO_EXPOSURE_ON: f64 = 3.0;
+/// `CAP_PROP_AUTO_EXPOSURE` value selecting manual exposure with the V4L2 backend
+const AUTO_EXPOSURE_OFF: f64 = 1.0;
+/// Auto exposure value some older backends report instead of `AUTO_EXPOSURE_ON`
+const LEGACY_AUTO_EXPOSURE_ON: f64 = 0.75;
+
+/// Raw `CAP_PROP_AUTO_EXPOSURE` value for turning auto exposure on or off
+pub(crate) fn auto_exposure_value(enabled: bool) -> f64 {
+    if enabled {
+        AUTO_EXPOSURE_ON
+    } else {
+        AUTO_EXPOSURE_OFF
+    }
+}
+
+/// Whether a raw `CAP_PROP_AUTO_EXPOSURE` value means auto exposure is on
+pub(crate) fn is_auto_exposure(value: f64) -> bool {
+    value == AUTO_EXPOSURE_ON || value == LEGACY_AUTO_EXPOSURE_ON
+}
+
+/// Camera settings captured with `TagDetector::snapshot_camera_properties()`
+///
+/// Replayed onto a camera with `TagDetector::apply_camera_properties()` or
+/// `TagDetector::open_camera_with_properties()`. With the `serde` feature the
+/// settings can be stored, for example in a TOML file, so an exposure tuned
+/// during practice survives a restart. `None` fields leave the camera as it is.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CameraProperties {
+    /// Frame width in pixels
+    pub width: Option<i32>,
+    /// Frame height in pixels
+    pub height: Option<i32>,
+    /// Frame rate in frames per second
+    pub fps: Option<f64>,
+    /// Whether the camera picks the exposure itself; a fixed `exposure` needs it off
+    pub auto_exposure: Option<bool>,
+    /// Exposure in driver units
+    pub exposure: Option<f64>,
+    /// Sensor gain in driver units
+    pub gain: Option<f64>,
+    /// Brightness in driver units
+    pub brightness: Option<f64>,
+    /// Frames buffered by the camera, see `Config::buffer_size`
+    pub buffer_size: Option<i32>,
+}
+
+/// Read the settings of `source`.
+///
+/// The buffer size is left out; the detector knows it from `Config::buffer_size`.
+/// Controls the source doesn't have are `None`.
+pub(crate) fn snapshot_properties(source: &dyn FrameSource) -> CameraProperties {
+    let (width, height) = source.resolution();
+    let known = |size: f64| (size > 0.0).then_some(size as i32);
+    let control = |property| source.property(property).ok();
+    CameraProperties {
+        width: known(width),
+        height: known(height),
+        fps: control(CameraProperty::Fps),
+        auto_exposure: control(CameraProperty::AutoExposure).map(is_auto_exposure),
+        exposure: control(CameraProperty::Exposure),
+        gain: control(CameraProperty::Gain),
+        brightness: control(CameraProperty::Brightness),
+        buffer_size: None,
+    }
+}
+
+/// Replay settings onto `source`, logging values the driver didn't take as requested.
+///
+/// Auto exposure is set before the exposure, so a fixed exposure sticks. The
+/// buffer size is left to the caller, which keeps it in `Config::buffer_size`.
+///
+/// # Errors
+///
+/// Returns the OpenCV error of a failed change. Controls the source doesn't
+/// have are skipped with a warning.
+pub(crate) fn apply_properties(
+    source: &mut dyn FrameSource,
+    properties: &CameraProperties,
+) -> Result<(), UpicError> {
+    if properties.width.is_some() || properties.height.is_some() {
+        // A lone width or height keeps the other dimension
+        let (current_width, current_height) = source.resolution();
+        let width = properties.width.map_or(current_width, f64::from);
+        let height = properties.height.map_or(current_height, f64::from);
+        source.set_resolution(width, height)?;
+        let (actual_width, actual_height) = source.resolution();
+        if (actual_width, actual_height) != (width, height) {
+            log::warn!(
+                "Camera resolution requested {}x{}, driver applied {}x{}",
+                width,
+                height,
+                actual_width,
+                actual_height
+            );
+        }
+    }
+
+    let controls = [
+        (
+            CameraProperty::AutoExposure,
+            properties.auto_exposure.map(auto_exposure_value),
+        ),
+        (CameraProperty::Exposure, properties.exposure),
+        (CameraProperty::Gain, properties.gain),
+        (CameraProperty::Brightness, properties.brightness),
+        (CameraProperty::Fps, properties.fps),
+    ];
+    for (property, value) in controls {
+        let Some(value) = value else {
+            continue;
+        };
+        match source.set_property(property, value) {
+            Ok(accepted) if accepted != value => log::warn!(
+                "Camera {:?} requested {}, driver applied {}",
+                property,
+                value,
+                accepted
+            ),
+            Ok(_) => {}
+            Err(UpicError::InvalidConfig(message)) => {
+                log::warn!("Skipping camera {:?}: {}", property, message)
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// A camera control read or change handed to the detection thread, which owns
+/// the frame source while it runs
+pub(crate) struct PropertyRequest {
+    pub(crate) property: CameraProperty,
+    /// Value to set, or `None` to only read the control
+    pub(crate) value: Option<f64>,
+    pub(crate) reply: Sender<Result<f64, UpicError>>,
+}
+
+/// Pending camera control requests, served between frame reads
+pub(crate) type PropertyRequests = Arc<Mutex<Vec<PropertyRequest>>>;
+
+/// A settings snapshot, optionally after replaying settings, handed to the
+/// detection thread
+pub(crate) struct PropertiesRequest {
+    /// Settings to replay before the snapshot, or `None` to only take it
+    pub(crate) apply: Option<CameraProperties>,
+    pub(crate) reply: Sender<Result<CameraProperties, UpicError>>,
+}
+
+/// Pending settings requests, served between frame reads
+pub(crate) type PropertiesRequests = Arc<Mutex<Vec<PropertiesRequest>>>;
+
+/// Read or set a camera control on `source`.
+///
+/// # Returns
+///
+/// The value the driver reports, after the change when setting.
+pub(crate) fn apply_property(
+    source: &mut dyn FrameSource,
+    property: CameraProperty,
+    value: Option<f64>,
+) -> Result<f64, UpicError> {
+    match value {
+        Some(value) => source.set_property(property, value),
+        None => source.property(property),
+    }
+}
+
+/// Serve every pending camera control request against `source`.
+///
+/// # Returns
+///
+/// The controls that were set successfully, with the values the driver accepted.
+pub(crate) fn serve_property_requests(
+    requests: &Mutex<Vec<PropertyRequest>>,
+    source: &mut dyn FrameSource,
+) -> Vec<(CameraProperty, f64)> {
+    let pending = std::mem::take(&mut *requests.lock().recover());
+    let mut applied = Vec::new();
+    for request in pending {
+        let result = apply_property(source, request.property, request.value);
+        if let (Some(_), Ok(accepted)) = (request.value, &result) {
+            applied.push((request.property, *accepted));
+        }
+        // The caller may have timed out and gone away
+        let _ = request.reply.send(result);
+    }
+    applied
+}
+
+/// Serve every pending settings request against `source`.
+///
+/// # Returns
+///
+/// Whether settings were replayed, in which case the resolution and frame rate
+/// may have changed.
+pub(crate) fn serve_properties_requests(
+    requests: &Mutex<Vec<PropertiesRequest>>,
+    source: &mut dyn FrameSource,
+) -> bool {
+    let pending = std::mem::take(&mut *requests.lock().recover());
+    let mut replayed = false;
+    for request in pending {
+        let applied = match &request.apply {
+            Some(properties) => {
+                replayed = true;
+                apply_properties(source, properties)
+            }
+            None => Ok(()),
+        };
+        // The caller may have timed out and gone away
+        let _ = request
+            .reply
+            .send(applied.map(|()| snapshot_properties(source)));
+    }
+    replayed
+}
+
+#[cfg(all(test, feature = "opencv"))]
+mod tests {
+    use super::*;
+    use crate::tag_detector::source::MockFrameSource;
+    use opencv::core::Mat;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_serve_property_requests() {
+        let mut source = MockFrameSource::new(Vec::new());
+        let requests = Mutex::new(Vec::new());
+        let (reply, results) = mpsc::channel();
+        for (property, value) in [
+            (
+                CameraProperty::AutoExposure,
+                Some(auto_exposure_value(false)),
+            ),
+            (CameraProperty::Exposure, Some(120.0)),
+            (CameraProperty::Exposure, None),
+            (CameraProperty::Gain, None),
+        ] {
+            requests.lock().unwrap().push(PropertyRequest {
+                property,
+                value,
+                reply: reply.clone(),
+            });
+        }
+
+        let applied = serve_property_requests(&requests, &mut source);
+        assert_eq!(
+            applied,
+            [
+                (CameraProperty::AutoExposure, AUTO_EXPOSURE_OFF),
+                (CameraProperty::Exposure, 120.0)
+            ]
+        );
+        let results: Vec<f64> = results.try_iter().map(Result::unwrap).collect();
+        assert_eq!(results, [AUTO_EXPOSURE_OFF, 120.0, 120.0, 0.0]);
+        assert!(requests.lock().unwrap().is_empty());
+        assert!(!is_auto_exposure(
+            source.property(CameraProperty::AutoExposure).unwrap()
+        ));
+        assert!(is_auto_exposure(auto_exposure_value(true)));
+        assert!(is_auto_exposure(LEGACY_AUTO_EXPOSURE_ON));
+    }
+
+    #[test]
+    fn test_properties_round_trip() {
+        let frame = Mat::new_rows_cols_with_default(
+            480,
+            640,
+            opencv::core::CV_8UC1,
+            opencv::core::Scalar::all(255.0),
+        )
+        .unwrap();
+        let mut tuned = MockFrameSource::new(vec![frame.clone()]).with_max_fps(30.0);
+        let properties = CameraProperties {
+            width: Some(320),
+            fps: Some(60.0),
+            auto_exposure: Some(false),
+            exposure: Some(80.0),
+            brightness: Some(40.0),
+            ..CameraProperties::default()
+        };
+        apply_properties(&mut tuned, &properties).unwrap();
+        // The driver's answers are captured, not the requests
+        let snapshot = snapshot_properties(&tuned);
+        assert_eq!(
+            snapshot,
+            CameraProperties {
+                width: Some(320),
+                height: Some(480),
+                fps: Some(30.0),
+                auto_exposure: Some(false),
+                exposure: Some(80.0),
+                gain: Some(0.0),
+                brightness: Some(40.0),
+                buffer_size: None,
+            }
+        );
+
+        let mut restarted = MockFrameSource::new(vec![frame]);
+        let requests = Mutex::new(Vec::new());
+        let (reply, result) = mpsc::channel();
+        requests.lock().unwrap().push(PropertiesRequest {
+            apply: Some(snapshot),
+            reply,
+        });
+        assert!(serve_properties_requests(&requests, &mut restarted));
+        assert_eq!(result.try_recv().unwrap().unwrap(), snapshot);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_properties_toml() {
+        let properties = CameraProperties {
+            exposure: Some(80.0),
+            buffer_size: Some(1),
+            ..CameraProperties::default()
+        };
+        let text = toml::to_string(&properties).unwrap();
+        // Settings left to the camera are omitted
+        assert_eq!(text, "exposure = 80.0\nbuffer_size = 1\n");
+        assert_eq!(
+            toml::from_str::<CameraProperties>(&text).unwrap(),
+            properties
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use std::time::Duration;
+
+use super::config::ReconnectPolicy;
+
+/// Health of the camera as seen by the detection thread
+///
+/// Unlike `Config::error_tag_id`, which any bad frame publishes, this tells a
+/// transient read error from a camera that is gone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CameraState {
+    /// The last frame was read
+    #[default]
+    Ok,
+    /// This many consecutive reads failed, fewer than `Config::disconnect_after`
+    ReadErrors(u32),
+    /// The camera reports itself closed, or `Config::disconnect_after`
+    /// consecutive reads failed; a successful reconnect returns to `Ok`
+    Disconnected,
+}
+
+/// Counts consecutive read failures and paces the reconnect attempts.
+///
+/// Kept free of camera types so the backoff logic can be exercised without
+/// a failing device.
+pub(crate) struct ReconnectTracker {
+    policy: Option<ReconnectPolicy>,
+    consecutive_errors: u32,
+    backoff: Duration,
+}
+
+impl ReconnectTracker {
+    pub(crate) fn new(policy: Option<ReconnectPolicy>) -> Self {
+        ReconnectTracker {
+            policy,
+            consecutive_errors: 0,
+            backoff: policy.map_or(Duration::ZERO, |policy| policy.initial_backoff),
+        }
+    }
+
+    /// Replace the policy; the error count and current backoff are kept.
+    pub(crate) fn set_policy(&mut self, policy: Option<ReconnectPolicy>) {
+        if let Some(policy) = policy {
+            self.backoff = self
+                .backoff
+                .clamp(policy.initial_backoff, policy.max_backoff);
+        }
+        self.policy = policy;
+    }
+
+    /// Record the outcome of one frame read.
+    ///
+    /// # Returns
+    ///
+    /// Whether the source should be reconnected now.
+    pub(crate) fn observe_read(&mut self, ok: bool) -> bool {
+        if ok {
+            self.consecutive_errors = 0;
+            if let Some(policy) = self.policy {
+                self.backoff = policy.initial_backoff;
+            }
+            return false;
+        }
+
+        self.consecutive_errors = self.consecutive_errors.saturating_add(1);
+        self.policy
+            .is_some_and(|policy| self.consecutive_errors >= policy.max_consecutive_errors)
+    }
+
+    /// Record a failed reconnect attempt.
+    ///
+    /// # Returns
+    ///
+    /// The delay before the next attempt, doubling with every failure up to
+    /// `ReconnectPolicy::max_backoff`.
+    pub(crate) fn reconnect_failed(&mut self) -> Duration {
+        let delay = self.backoff;
+        if let Some(policy) = self.policy {
+            self.backoff = (self.backoff * 2).min(policy.max_backoff);
+        }
+        delay
+    }
+
+    /// Record a successful reconnect.
+    pub(crate) fn reconnected(&mut self) {
+        self.consecutive_errors = 0;
+        if let Some(policy) = self.policy {
+            self.backoff = policy.initial_backoff;
+        }
+    }
+
+    pub(crate) fn consecutive_errors(&self) -> u32 {
+        self.consecutive_errors
+    }
+
+    /// State of the camera after the reads recorded so far.
+    ///
+    /// # Arguments
+    ///
+    /// * `connected` - Whether the source reports itself open.
+    /// * `disconnect_after` - Consecutive failed reads that count as a disconnect.
+    pub(crate) fn camera_state(&self, connected: bool, disconnect_after: u32) -> CameraState {
+        match self.consecutive_errors {
+            0 if connected => CameraState::Ok,
+            errors if connected && errors < disconnect_after => CameraState::ReadErrors(errors),
+            _ => CameraState::Disconnected,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> Option<ReconnectPolicy> {
+        Some(ReconnectPolicy {
+            max_consecutive_errors: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(350),
+        })
+    }
+
+    #[test]
+    fn test_reconnects_after_consecutive_errors() {
+        let mut tracker = ReconnectTracker::new(policy());
+        assert!(!tracker.observe_read(false));
+        assert!(!tracker.observe_read(false));
+        assert!(!tracker.observe_read(true));
+        assert_eq!(tracker.consecutive_errors(), 0);
+
+        assert!(!tracker.observe_read(false));
+        assert!(!tracker.observe_read(false));
+        assert!(tracker.observe_read(false));
+        assert_eq!(tracker.consecutive_errors(), 3);
+
+        tracker.reconnected();
+        assert!(!tracker.observe_read(false));
+    }
+
+    #[test]
+    fn test_camera_state() {
+        let mut tracker = ReconnectTracker::new(policy());
+        assert_eq!(tracker.camera_state(true, 2), CameraState::Ok);
+        // A closed camera is gone even before a read fails
+        assert_eq!(tracker.camera_state(false, 2), CameraState::Disconnected);
+
+        tracker.observe_read(false);
+        assert_eq!(tracker.camera_state(true, 2), CameraState::ReadErrors(1));
+        tracker.observe_read(false);
+        assert_eq!(tracker.camera_state(true, 2), CameraState::Disconnected);
+        tracker.reconnect_failed();
+        assert_eq!(tracker.camera_state(true, 2), CameraState::Disconnected);
+
+        tracker.reconnected();
+        assert_eq!(tracker.camera_state(true, 2), CameraState::Ok);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let mut tracker = ReconnectTracker::new(policy());
+        let delays: Vec<_> = (0..4).map(|_| tracker.reconnect_failed()).collect();
+        assert_eq!(
+            delays,
+            [100, 200, 350, 350].map(Duration::from_millis).to_vec()
+        );
+
+        // A good frame restarts the backoff
+        tracker.observe_read(true);
+        assert_eq!(tracker.reconnect_failed(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_no_policy_never_reconnects() {
+        let mut tracker = ReconnectTracker::new(None);
+        assert!((0..100).all(|_| !tracker.observe_read(false)));
+        assert_eq!(tracker.consecutive_errors(), 100);
+    }
+}
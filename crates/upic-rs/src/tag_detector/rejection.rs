@@ -0,0 +1,211 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use super::detection::TagDetection;
+
+/// Filter stage that discarded a decoded tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RejectionReason {
+    /// The ID is in `Config::ignored_ids`
+    IgnoredId,
+    /// The ID is not in `Config::allowed_ids`
+    NotAllowed,
+    /// The shortest side is below `Config::min_tag_pixels`
+    TooSmall,
+    /// The decision margin is below `Config::min_decision_margin`
+    LowMargin,
+}
+
+impl RejectionReason {
+    /// Short name used in logs
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            RejectionReason::IgnoredId => "ignored_id",
+            RejectionReason::NotAllowed => "not_allowed",
+            RejectionReason::TooSmall => "too_small",
+            RejectionReason::LowMargin => "low_margin",
+        }
+    }
+}
+
+impl fmt::Display for RejectionReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A decoded tag the filters discarded, returned by `TagDetector::rejections()`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RejectedDetection {
+    /// Decoded tag ID
+    pub id: i32,
+    /// First filter stage the tag failed
+    pub reason: RejectionReason,
+    /// Decision margin of the decode
+    pub decision_margin: f64,
+    /// Center point of the tag, in the pixels of the frame it was decoded in
+    pub center: [f64; 2],
+    /// Index of the frame since detection started, as in the detection log
+    pub frame_index: u64,
+}
+
+impl RejectedDetection {
+    pub(crate) fn new(detection: &TagDetection, reason: RejectionReason, frame_index: u64) -> Self {
+        RejectedDetection {
+            id: detection.id,
+            reason,
+            decision_margin: detection.decision_margin,
+            center: detection.center,
+            frame_index,
+        }
+    }
+}
+
+/// Number of tags each filter stage discarded, in `DetectionStats::rejections`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RejectionCounts {
+    /// Tags discarded by `RejectionReason::IgnoredId`
+    pub ignored_id: u64,
+    /// Tags discarded by `RejectionReason::NotAllowed`
+    pub not_allowed: u64,
+    /// Tags discarded by `RejectionReason::TooSmall`
+    pub too_small: u64,
+    /// Tags discarded by `RejectionReason::LowMargin`
+    pub low_margin: u64,
+}
+
+impl RejectionCounts {
+    /// Number of tags discarded for `reason`
+    pub fn get(&self, reason: RejectionReason) -> u64 {
+        match reason {
+            RejectionReason::IgnoredId => self.ignored_id,
+            RejectionReason::NotAllowed => self.not_allowed,
+            RejectionReason::TooSmall => self.too_small,
+            RejectionReason::LowMargin => self.low_margin,
+        }
+    }
+
+    /// Number of tags discarded by any filter stage
+    pub fn total(&self) -> u64 {
+        self.ignored_id + self.not_allowed + self.too_small + self.low_margin
+    }
+
+    pub(crate) fn add(&mut self, reason: RejectionReason) {
+        let count = match reason {
+            RejectionReason::IgnoredId => &mut self.ignored_id,
+            RejectionReason::NotAllowed => &mut self.not_allowed,
+            RejectionReason::TooSmall => &mut self.too_small,
+            RejectionReason::LowMargin => &mut self.low_margin,
+        };
+        *count += 1;
+    }
+}
+
+/// Recently rejected tags, oldest first
+///
+/// Kept by the detection thread behind `TagDetector::rejections()`. Once full,
+/// each new entry drops the oldest one.
+pub(crate) struct RejectionTrace {
+    entries: VecDeque<RejectedDetection>,
+    capacity: usize,
+}
+
+impl RejectionTrace {
+    pub(crate) fn new(capacity: usize) -> Self {
+        RejectionTrace {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Change the number of entries kept, dropping the oldest ones if needed.
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.entries.len() > capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Record tags rejected in one frame.
+    pub(crate) fn record(&mut self, rejected: impl IntoIterator<Item = RejectedDetection>) {
+        if self.capacity == 0 {
+            return;
+        }
+        for entry in rejected {
+            if self.entries.len() == self.capacity {
+                self.entries.pop_front();
+            }
+            self.entries.push_back(entry);
+        }
+    }
+
+    /// The last `n` entries, oldest first.
+    pub(crate) fn latest(&self, n: usize) -> Vec<RejectedDetection> {
+        let skip = self.entries.len().saturating_sub(n);
+        self.entries.iter().skip(skip).copied().collect()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejected(id: i32, reason: RejectionReason, frame_index: u64) -> RejectedDetection {
+        RejectedDetection {
+            id,
+            reason,
+            decision_margin: 20.0,
+            center: [0.0, 0.0],
+            frame_index,
+        }
+    }
+
+    #[test]
+    fn test_trace_keeps_the_latest() {
+        let mut trace = RejectionTrace::new(3);
+        trace.record([
+            rejected(1, RejectionReason::IgnoredId, 0),
+            rejected(2, RejectionReason::LowMargin, 0),
+        ]);
+        trace.record([
+            rejected(3, RejectionReason::TooSmall, 1),
+            rejected(4, RejectionReason::NotAllowed, 1),
+        ]);
+        let ids =
+            |entries: Vec<RejectedDetection>| entries.iter().map(|e| e.id).collect::<Vec<_>>();
+        assert_eq!(ids(trace.latest(10)), [2, 3, 4]);
+        assert_eq!(ids(trace.latest(2)), [3, 4]);
+        assert!(trace.latest(0).is_empty());
+
+        trace.set_capacity(1);
+        assert_eq!(ids(trace.latest(10)), [4]);
+        trace.set_capacity(0);
+        trace.record([rejected(5, RejectionReason::LowMargin, 2)]);
+        assert!(trace.latest(10).is_empty());
+        trace.set_capacity(2);
+        trace.record([rejected(6, RejectionReason::LowMargin, 3)]);
+        trace.clear();
+        assert!(trace.latest(10).is_empty());
+    }
+
+    #[test]
+    fn test_counts_per_reason() {
+        let mut counts = RejectionCounts::default();
+        for reason in [
+            RejectionReason::LowMargin,
+            RejectionReason::LowMargin,
+            RejectionReason::IgnoredId,
+        ] {
+            counts.add(reason);
+        }
+        assert_eq!(counts.get(RejectionReason::LowMargin), 2);
+        assert_eq!(counts.get(RejectionReason::IgnoredId), 1);
+        assert_eq!(counts.get(RejectionReason::TooSmall), 0);
+        assert_eq!(counts.total(), 3);
+        assert_eq!(RejectionReason::NotAllowed.to_string(), "not_allowed");
+    }
+}
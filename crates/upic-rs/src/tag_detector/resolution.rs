@@ -0,0 +1,150 @@
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::source::FrameSource;
+use super::sync::Recover;
+use crate::error::UpicError;
+
+/// Resolutions tried by `probe_resolutions()`, from QVGA to 4K UHD
+pub(crate) const COMMON_RESOLUTIONS: [(i32, i32); 12] = [
+    (320, 240),
+    (640, 360),
+    (640, 480),
+    (800, 600),
+    (1024, 768),
+    (1280, 720),
+    (1280, 960),
+    (1280, 1024),
+    (1600, 1200),
+    (1920, 1080),
+    (2560, 1440),
+    (3840, 2160),
+];
+
+/// Longest wait for the detection thread to probe the camera; switching
+/// resolution takes up to a few hundred milliseconds per step on some drivers
+pub(crate) const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Receives the resolutions found by a probe
+pub(crate) type ProbeReply = Sender<Result<Vec<(i32, i32)>, UpicError>>;
+
+/// Pending resolution probes, served by the detection thread between frame reads
+pub(crate) type ResolutionProbes = Arc<Mutex<Vec<ProbeReply>>>;
+
+/// Find the resolutions a source supports by requesting each common one.
+///
+/// Every resolution in `COMMON_RESOLUTIONS` is requested and the size the
+/// source settles on is read back, so a driver rounding a request to its
+/// nearest mode reports that mode. The original resolution is restored
+/// afterwards.
+///
+/// # Returns
+///
+/// The distinct accepted resolutions as `(width, height)`, smallest first.
+///
+/// # Errors
+///
+/// Returns the error of restoring the original resolution. Requests the source
+/// rejects are skipped.
+pub(crate) fn probe_resolutions(
+    source: &mut dyn FrameSource,
+) -> Result<Vec<(i32, i32)>, UpicError> {
+    let (original_width, original_height) = source.resolution();
+    let mut supported = Vec::new();
+    let mut verbatim = 0;
+    for (width, height) in COMMON_RESOLUTIONS {
+        if let Err(e) = source.set_resolution(width as f64, height as f64) {
+            log::debug!("Camera rejected {}x{}: {}", width, height, e);
+            continue;
+        }
+        let (accepted_width, accepted_height) = source.resolution();
+        let accepted = (accepted_width as i32, accepted_height as i32);
+        if accepted == (width, height) {
+            verbatim += 1;
+        }
+        if accepted.0 > 0 && accepted.1 > 0 && !supported.contains(&accepted) {
+            supported.push(accepted);
+        }
+    }
+    if verbatim == COMMON_RESOLUTIONS.len() {
+        log::warn!(
+            "Camera accepted every probed resolution as requested; its driver may \
+             report sizes it doesn't deliver"
+        );
+    }
+    source.set_resolution(original_width, original_height)?;
+    supported.sort_by_key(|&(width, height)| (width * height, width));
+    Ok(supported)
+}
+
+/// Serve every pending resolution probe against `source`.
+///
+/// # Returns
+///
+/// Whether any probe was served, in which case the resolution was changed and
+/// restored.
+pub(crate) fn serve_resolution_probes(
+    probes: &Mutex<Vec<ProbeReply>>,
+    source: &mut dyn FrameSource,
+) -> bool {
+    let pending = std::mem::take(&mut *probes.lock().recover());
+    let served = !pending.is_empty();
+    for reply in pending {
+        // The caller may have timed out and gone away
+        let _ = reply.send(probe_resolutions(source));
+    }
+    served
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tag_detector::frame::Frame;
+
+    /// A camera snapping requests to the nearest of a few modes
+    struct SnappingSource {
+        modes: Vec<(i32, i32)>,
+        current: (i32, i32),
+    }
+
+    impl FrameSource for SnappingSource {
+        fn read_frame(&mut self) -> Result<Frame, UpicError> {
+            Err(UpicError::FrameReadFailed)
+        }
+
+        fn resolution(&self) -> (f64, f64) {
+            (self.current.0 as f64, self.current.1 as f64)
+        }
+
+        fn set_resolution(&mut self, width: f64, height: f64) -> Result<(), UpicError> {
+            let area = width * height;
+            self.current = *self
+                .modes
+                .iter()
+                .min_by_key(|(w, h)| ((*w * *h) as f64 - area).abs() as i64)
+                .unwrap();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_probe_resolutions() {
+        let mut source = SnappingSource {
+            modes: vec![(1920, 1080), (640, 480), (1280, 720)],
+            current: (1280, 720),
+        };
+        assert_eq!(
+            probe_resolutions(&mut source).unwrap(),
+            [(640, 480), (1280, 720), (1920, 1080)]
+        );
+        assert_eq!(source.current, (1280, 720));
+
+        let probes = Mutex::new(Vec::new());
+        assert!(!serve_resolution_probes(&probes, &mut source));
+        let (reply, result) = std::sync::mpsc::channel();
+        probes.lock().unwrap().push(reply);
+        assert!(serve_resolution_probes(&probes, &mut source));
+        assert_eq!(result.try_recv().unwrap().unwrap().len(), 3);
+    }
+}
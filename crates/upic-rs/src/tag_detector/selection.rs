@@ -0,0 +1,648 @@
+use std::collections::HashSet;
+
+use super::config::{Config, OrderingMethod};
+use super::debounce::Debouncer;
+use super::detection::TagDetection;
+use super::rejection::RejectionReason;
+use super::smoothing::IdVoter;
+
+/// Tag ID allowlist and denylist applied before the ordering method
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct IdFilter<'a> {
+    pub(crate) allowed: Option<&'a HashSet<i32>>,
+    pub(crate) ignored: &'a HashSet<i32>,
+}
+
+impl<'a> IdFilter<'a> {
+    pub(crate) fn from_config(config: &'a Config) -> Self {
+        IdFilter {
+            allowed: config.allowed_ids.as_ref(),
+            ignored: &config.ignored_ids,
+        }
+    }
+
+    /// Why a tag with this ID may not be reported, `None` if it may
+    pub(crate) fn rejection(&self, id: i32) -> Option<RejectionReason> {
+        if self.ignored.contains(&id) {
+            Some(RejectionReason::IgnoredId)
+        } else if self.allowed.is_some_and(|allowed| !allowed.contains(&id)) {
+            Some(RejectionReason::NotAllowed)
+        } else {
+            None
+        }
+    }
+
+    /// Whether a tag with this ID may be reported
+    pub(crate) fn permits(&self, id: i32) -> bool {
+        self.rejection(id).is_none()
+    }
+
+    /// Discard the candidates with a filtered ID, returning them with the reason
+    pub(crate) fn apply(
+        &self,
+        candidates: &mut Vec<TagDetection>,
+    ) -> Vec<(TagDetection, RejectionReason)> {
+        retain_rejecting(candidates, |detection| self.rejection(detection.id))
+    }
+}
+
+/// Minimum tag size and decision margin applied before the ordering method
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct QualityFilter {
+    pub(crate) min_tag_pixels: Option<f64>,
+    pub(crate) min_decision_margin: Option<f64>,
+}
+
+impl QualityFilter {
+    pub(crate) fn from_config(config: &Config) -> Self {
+        QualityFilter {
+            min_tag_pixels: config.min_tag_pixels,
+            min_decision_margin: config.min_decision_margin,
+        }
+    }
+
+    /// Which threshold a detection falls below, `None` if it is large and
+    /// confident enough to be reported
+    pub(crate) fn rejection(&self, detection: &TagDetection) -> Option<RejectionReason> {
+        if self
+            .min_tag_pixels
+            .is_some_and(|min_tag_pixels| detection.min_side() < min_tag_pixels)
+        {
+            Some(RejectionReason::TooSmall)
+        } else if self
+            .min_decision_margin
+            .is_some_and(|min_decision_margin| detection.decision_margin < min_decision_margin)
+        {
+            Some(RejectionReason::LowMargin)
+        } else {
+            None
+        }
+    }
+
+    /// Whether a detection is large and confident enough to be reported
+    pub(crate) fn permits(&self, detection: &TagDetection) -> bool {
+        self.rejection(detection).is_none()
+    }
+
+    /// Discard the candidates below a threshold, returning them with the reason
+    pub(crate) fn apply(
+        &self,
+        candidates: &mut Vec<TagDetection>,
+    ) -> Vec<(TagDetection, RejectionReason)> {
+        retain_rejecting(candidates, |detection| self.rejection(detection))
+    }
+}
+
+/// Keep the candidates `rejection` has no reason for, returning the others
+fn retain_rejecting(
+    candidates: &mut Vec<TagDetection>,
+    rejection: impl Fn(&TagDetection) -> Option<RejectionReason>,
+) -> Vec<(TagDetection, RejectionReason)> {
+    let mut rejected = Vec::new();
+    candidates.retain(|detection| match rejection(detection) {
+        Some(reason) => {
+            rejected.push((*detection, reason));
+            false
+        }
+        None => true,
+    });
+    rejected
+}
+
+/// Discard the candidates rejected by the config's ID filter or quality
+/// thresholds, returning them with the first stage each failed
+pub(crate) fn filter_detections(
+    config: &Config,
+    candidates: &mut Vec<TagDetection>,
+) -> Vec<(TagDetection, RejectionReason)> {
+    let mut rejected = IdFilter::from_config(config).apply(candidates);
+    rejected.extend(QualityFilter::from_config(config).apply(candidates));
+    rejected
+}
+
+/// Index of the detection to publish among all candidates in a frame
+///
+/// `OrderingMethod::Nearest` picks the candidate whose center is closest to
+/// `frame_center`, `Single` the first candidate, `Largest` the one with the
+/// largest quad area and `ById` the first one with that ID. A `Custom` selector
+/// returning an out-of-range index selects nothing.
+pub(crate) fn select_index(
+    candidates: &[TagDetection],
+    ordering_method: &OrderingMethod,
+    frame_center: [f64; 2],
+) -> Option<usize> {
+    match ordering_method {
+        OrderingMethod::Nearest => candidates
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                a.distance_sq_to(frame_center)
+                    .total_cmp(&b.distance_sq_to(frame_center))
+            })
+            .map(|(index, _)| index),
+        OrderingMethod::Single => (!candidates.is_empty()).then_some(0),
+        OrderingMethod::Largest => candidates
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.area().total_cmp(&b.area()))
+            .map(|(index, _)| index),
+        OrderingMethod::ById(id) => candidates.iter().position(|d| d.id == *id),
+        OrderingMethod::Custom(selector) => {
+            selector(candidates).filter(|&index| index < candidates.len())
+        }
+    }
+}
+
+/// Pick the detection to publish from all candidates in a frame, as described
+/// for `select_index`
+pub(crate) fn select_detection(
+    candidates: &[TagDetection],
+    ordering_method: &OrderingMethod,
+    frame_center: [f64; 2],
+) -> Option<TagDetection> {
+    select_index(candidates, ordering_method, frame_center).map(|index| candidates[index])
+}
+
+/// Pick the detection to publish from unfiltered detections, applying the
+/// config's filters and then its ordering method, without debouncing
+pub(crate) fn select_unfiltered<'a>(
+    detections: &'a [TagDetection],
+    config: &Config,
+    frame_center: [f64; 2],
+) -> Option<&'a TagDetection> {
+    let id_filter = IdFilter::from_config(config);
+    let quality_filter = QualityFilter::from_config(config);
+    let (positions, candidates): (Vec<usize>, Vec<TagDetection>) = detections
+        .iter()
+        .enumerate()
+        .filter(|(_, detection)| {
+            id_filter.permits(detection.id) && quality_filter.permits(detection)
+        })
+        .map(|(position, detection)| (position, *detection))
+        .unzip();
+    let index = select_index(&candidates, &config.ordering_method, frame_center)?;
+    detections.get(positions[index])
+}
+
+/// The parts of `Config` that decide which tag is published
+#[derive(Clone, Copy)]
+pub(crate) struct SelectionConfig<'a> {
+    pub(crate) ordering_method: &'a OrderingMethod,
+    pub(crate) id_filter: IdFilter<'a>,
+    pub(crate) quality_filter: QualityFilter,
+    pub(crate) min_consecutive_frames: u32,
+    pub(crate) smoothing_window: usize,
+}
+
+impl<'a> SelectionConfig<'a> {
+    pub(crate) fn from_config(config: &'a Config) -> Self {
+        SelectionConfig {
+            ordering_method: &config.ordering_method,
+            id_filter: IdFilter::from_config(config),
+            quality_filter: QualityFilter::from_config(config),
+            min_consecutive_frames: config.min_consecutive_frames,
+            smoothing_window: config.smoothing_window,
+        }
+    }
+}
+
+/// What `select_tag` remembers between frames: the debounce streak, the
+/// smoothing window and the last frame's selections
+pub(crate) struct SelectionState {
+    debouncer: Debouncer,
+    voter: IdVoter,
+    raw: Option<TagDetection>,
+    selected: Option<TagDetection>,
+}
+
+impl SelectionState {
+    pub(crate) fn new(config: &SelectionConfig) -> Self {
+        SelectionState {
+            debouncer: Debouncer::new(config.min_consecutive_frames),
+            voter: IdVoter::new(config.smoothing_window),
+            raw: None,
+            selected: None,
+        }
+    }
+
+    /// Detection the ordering method picked in the last frame, before debouncing
+    pub(crate) fn raw(&self) -> Option<TagDetection> {
+        self.raw
+    }
+
+    /// Detection published for the last frame once debounced
+    pub(crate) fn selected(&self) -> Option<TagDetection> {
+        self.selected
+    }
+
+    /// Forget every frame seen, as after a sentinel ID was published.
+    pub(crate) fn reset(&mut self) {
+        self.debouncer.reset();
+        self.voter.reset();
+        self.raw = None;
+        self.selected = None;
+    }
+}
+
+/// Decide the tag ID to publish for one frame.
+///
+/// The detections are filtered by ID and quality, the ordering method picks
+/// one, the debouncer holds back changes that haven't persisted for
+/// `min_consecutive_frames`, and the ID is voted over the last
+/// `smoothing_window` frames. The picked and debounced detections are kept in
+/// `state`.
+///
+/// # Arguments
+///
+/// * `detections` - Every tag decoded in the frame.
+/// * `config` - Filters, ordering method and frame counts to apply.
+/// * `center` - Frame center `OrderingMethod::Nearest` measures from.
+/// * `state` - Selection state carried over from the previous frames.
+///
+/// # Returns
+///
+/// The tag ID to publish, or `None` when no tag is to be published.
+pub(crate) fn select_tag(
+    detections: &[TagDetection],
+    config: &SelectionConfig,
+    center: [f64; 2],
+    state: &mut SelectionState,
+) -> Option<i32> {
+    state
+        .debouncer
+        .set_min_frames(config.min_consecutive_frames);
+    state.voter.set_window(config.smoothing_window);
+    let candidates: Vec<TagDetection> = detections
+        .iter()
+        .filter(|detection| {
+            config.id_filter.permits(detection.id) && config.quality_filter.permits(detection)
+        })
+        .copied()
+        .collect();
+    state.raw = select_detection(&candidates, config.ordering_method, center);
+    state.selected = state.debouncer.observe(state.raw);
+    state.voter.observe(state.selected.map(|d| d.id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tag_detector::config::TagFamily;
+
+    fn detection(id: i32, center: [f64; 2]) -> TagDetection {
+        sized_detection(id, center, 10.0)
+    }
+
+    fn sized_detection(id: i32, center: [f64; 2], half_size: f64) -> TagDetection {
+        let [x, y] = center;
+        TagDetection {
+            id,
+            family: TagFamily::Tag36h11,
+            corners: [
+                [x - half_size, y + half_size],
+                [x + half_size, y + half_size],
+                [x + half_size, y - half_size],
+                [x - half_size, y - half_size],
+            ],
+            center,
+            decision_margin: 50.0,
+            hamming: 0,
+        }
+    }
+
+    #[test]
+    fn test_select_nearest_and_single() {
+        let candidates = [detection(3, [20.0, 20.0]), detection(7, [310.0, 250.0])];
+
+        let nearest = select_detection(&candidates, &OrderingMethod::Nearest, [320.0, 240.0]);
+        assert_eq!(nearest.map(|d| d.id), Some(7));
+
+        let single = select_detection(&candidates, &OrderingMethod::Single, [320.0, 240.0]);
+        assert_eq!(single.map(|d| d.id), Some(3));
+
+        assert_eq!(
+            select_detection(&[], &OrderingMethod::Nearest, [320.0, 240.0]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_select_largest_by_id_and_custom() {
+        let candidates = [
+            sized_detection(3, [20.0, 20.0], 30.0),
+            sized_detection(7, [310.0, 250.0], 10.0),
+            sized_detection(7, [100.0, 100.0], 20.0),
+        ];
+        let center = [320.0, 240.0];
+        assert_eq!(candidates[0].area(), 3600.0);
+
+        let largest = select_detection(&candidates, &OrderingMethod::Largest, center);
+        assert_eq!(largest.map(|d| d.id), Some(3));
+
+        let by_id = select_detection(&candidates, &OrderingMethod::ById(7), center);
+        assert_eq!(by_id.map(|d| d.center), Some([310.0, 250.0]));
+        assert_eq!(
+            select_detection(&candidates, &OrderingMethod::ById(9), center),
+            None
+        );
+
+        let last = OrderingMethod::Custom(std::sync::Arc::new(|detections: &[TagDetection]| {
+            detections.len().checked_sub(1)
+        }));
+        let custom = select_detection(&candidates, &last, center);
+        assert_eq!(custom.map(|d| d.center), Some([100.0, 100.0]));
+
+        let out_of_range =
+            OrderingMethod::Custom(std::sync::Arc::new(|_: &[TagDetection]| Some(5)));
+        assert_eq!(select_detection(&candidates, &out_of_range, center), None);
+    }
+
+    #[test]
+    fn test_id_filter() {
+        let mut candidates = vec![
+            detection(1, [0.0, 0.0]),
+            detection(2, [0.0, 0.0]),
+            detection(3, [0.0, 0.0]),
+        ];
+        let allowed = HashSet::from([2, 3]);
+        let ignored = HashSet::from([3]);
+        let filter = IdFilter {
+            allowed: Some(&allowed),
+            ignored: &ignored,
+        };
+        assert!(IdFilter::from_config(&Config::default()).permits(1));
+        assert!(!filter.permits(1));
+        assert!(!filter.permits(3));
+        // The denylist is checked first
+        assert_eq!(filter.rejection(3), Some(RejectionReason::IgnoredId));
+        assert_eq!(filter.rejection(1), Some(RejectionReason::NotAllowed));
+        assert_eq!(filter.rejection(2), None);
+
+        let rejected = filter.apply(&mut candidates);
+        assert_eq!(candidates.iter().map(|d| d.id).collect::<Vec<_>>(), [2]);
+        assert_eq!(
+            rejected
+                .iter()
+                .map(|&(d, reason)| (d.id, reason))
+                .collect::<Vec<_>>(),
+            [
+                (1, RejectionReason::NotAllowed),
+                (3, RejectionReason::IgnoredId)
+            ]
+        );
+
+        // With only filtered tags visible, nothing is selected
+        let mut only_stray = vec![detection(3, [0.0, 0.0])];
+        filter.apply(&mut only_stray);
+        assert_eq!(
+            select_detection(&only_stray, &OrderingMethod::Nearest, [0.0, 0.0]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_select_unfiltered_applies_filters() {
+        let detections = [
+            detection(3, [310.0, 250.0]),
+            sized_detection(5, [300.0, 240.0], 2.0),
+            detection(7, [20.0, 20.0]),
+        ];
+        let center = [320.0, 240.0];
+        let config = Config {
+            ignored_ids: HashSet::from([3]),
+            min_tag_pixels: Some(10.0),
+            ..Config::default()
+        };
+        // The nearest tags are ignored or too small, so the far one is selected
+        let selected = select_unfiltered(&detections, &config, center);
+        assert!(std::ptr::eq(selected.unwrap(), &detections[2]));
+
+        let mut filtered = detections.to_vec();
+        let rejected = filter_detections(&config, &mut filtered);
+        assert_eq!(filtered, [detections[2]]);
+        assert_eq!(
+            rejected,
+            [
+                (detections[0], RejectionReason::IgnoredId),
+                (detections[1], RejectionReason::TooSmall)
+            ]
+        );
+
+        assert_eq!(
+            select_unfiltered(&detections, &Config::default(), center).map(|d| d.id),
+            Some(3)
+        );
+        assert_eq!(select_unfiltered(&[], &config, center), None);
+    }
+
+    #[test]
+    fn test_confident_selection() {
+        let mut near = detection(3, [310.0, 250.0]);
+        near.decision_margin = 24.0;
+        near.hamming = 1;
+        let far = detection(7, [20.0, 20.0]);
+        let detections = [near, far];
+        let selected = select_unfiltered(&detections, &Config::default(), [320.0, 240.0]).unwrap();
+        assert_eq!((selected.id, selected.hamming), (3, 1));
+
+        // The nearest tag is selected whatever its margin, then judged on it
+        assert!(selected.is_confident(20.0));
+        assert!(!selected.is_confident(24.0));
+        assert!(!selected.is_confident(40.0));
+        assert!(detections[1].is_confident(40.0));
+    }
+
+    #[test]
+    fn test_quality_filter() {
+        let mut far = sized_detection(1, [0.0, 0.0], 4.0);
+        far.decision_margin = 80.0;
+        let mut dubious = sized_detection(2, [0.0, 0.0], 20.0);
+        dubious.decision_margin = 12.0;
+        let good = sized_detection(3, [0.0, 0.0], 20.0);
+        assert_eq!(far.min_side(), 8.0);
+        assert_eq!(far.mean_side(), 8.0);
+
+        let filter = QualityFilter {
+            min_tag_pixels: Some(10.0),
+            min_decision_margin: Some(30.0),
+        };
+        assert!(!filter.permits(&far));
+        assert!(!filter.permits(&dubious));
+        assert!(filter.permits(&good));
+        assert_eq!(filter.rejection(&far), Some(RejectionReason::TooSmall));
+        assert_eq!(filter.rejection(&dubious), Some(RejectionReason::LowMargin));
+        let mut candidates = vec![far, dubious, good];
+        let rejected = filter.apply(&mut candidates);
+        assert_eq!(candidates.iter().map(|d| d.id).collect::<Vec<_>>(), [3]);
+        assert_eq!(rejected.len(), 2);
+
+        // Without thresholds everything passes
+        let unlimited = QualityFilter::from_config(&Config::default());
+        assert!(unlimited.permits(&far) && unlimited.permits(&dubious));
+    }
+
+    /// One scenario: the config, then each frame's detections and the ID
+    /// published for it
+    struct Case {
+        name: &'static str,
+        config: Config,
+        frames: Vec<Vec<TagDetection>>,
+        published: Vec<Option<i32>>,
+    }
+
+    #[test]
+    fn test_select_tag_cases() {
+        // Nearest to the center, and largest and first in the frame
+        let near = detection(7, [310.0, 250.0]);
+        let large = sized_detection(3, [20.0, 20.0], 30.0);
+        let both = vec![large, near];
+        let cases = [
+            Case {
+                name: "nearest",
+                config: Config::default(),
+                frames: vec![both.clone()],
+                published: vec![Some(7)],
+            },
+            Case {
+                name: "single",
+                config: Config {
+                    ordering_method: OrderingMethod::Single,
+                    ..Config::default()
+                },
+                frames: vec![both.clone()],
+                published: vec![Some(3)],
+            },
+            Case {
+                name: "largest",
+                config: Config {
+                    ordering_method: OrderingMethod::Largest,
+                    ..Config::default()
+                },
+                frames: vec![both.clone(), vec![near]],
+                published: vec![Some(3), Some(7)],
+            },
+            Case {
+                name: "by id",
+                config: Config {
+                    ordering_method: OrderingMethod::ById(7),
+                    ..Config::default()
+                },
+                frames: vec![both.clone(), vec![large]],
+                published: vec![Some(7), None],
+            },
+            Case {
+                name: "allow list",
+                config: Config {
+                    allowed_ids: Some(HashSet::from([3])),
+                    ..Config::default()
+                },
+                frames: vec![both.clone(), vec![near]],
+                published: vec![Some(3), None],
+            },
+            Case {
+                name: "deny list",
+                config: Config {
+                    ignored_ids: HashSet::from([7]),
+                    ..Config::default()
+                },
+                frames: vec![both.clone()],
+                published: vec![Some(3)],
+            },
+            Case {
+                name: "deny list hides every tag",
+                config: Config {
+                    ignored_ids: HashSet::from([3, 7]),
+                    ..Config::default()
+                },
+                frames: vec![both.clone()],
+                published: vec![None],
+            },
+            Case {
+                // A tag and its loss both need three frames in a row
+                name: "debounce",
+                config: Config {
+                    min_consecutive_frames: 3,
+                    ..Config::default()
+                },
+                frames: vec![
+                    vec![near],
+                    vec![near],
+                    vec![],
+                    vec![near],
+                    vec![near],
+                    vec![near],
+                    vec![],
+                    vec![large],
+                    vec![],
+                    vec![],
+                    vec![],
+                ],
+                published: vec![
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    Some(7),
+                    Some(7),
+                    Some(7),
+                    Some(7),
+                    Some(7),
+                    None,
+                ],
+            },
+            Case {
+                // The majority of the last three frames, ties to the newest
+                name: "smoothing",
+                config: Config {
+                    smoothing_window: 3,
+                    ..Config::default()
+                },
+                frames: vec![
+                    vec![near],
+                    vec![large],
+                    vec![near],
+                    vec![large],
+                    vec![large],
+                ],
+                published: vec![Some(7), Some(3), Some(7), Some(3), Some(3)],
+            },
+        ];
+
+        for case in cases {
+            let config = SelectionConfig::from_config(&case.config);
+            let mut state = SelectionState::new(&config);
+            let published: Vec<Option<i32>> = case
+                .frames
+                .iter()
+                .map(|frame| select_tag(frame, &config, [320.0, 240.0], &mut state))
+                .collect();
+            assert_eq!(published, case.published, "{}", case.name);
+        }
+    }
+
+    #[test]
+    fn test_selection_state() {
+        let config = Config {
+            min_consecutive_frames: 2,
+            ..Config::default()
+        };
+        let selection = SelectionConfig::from_config(&config);
+        let mut state = SelectionState::new(&selection);
+        let tag = detection(4, [0.0, 0.0]);
+
+        // The pick shows at once, the published detection once debounced
+        assert_eq!(select_tag(&[tag], &selection, [0.0, 0.0], &mut state), None);
+        assert_eq!((state.raw(), state.selected()), (Some(tag), None));
+        assert_eq!(
+            select_tag(&[tag], &selection, [0.0, 0.0], &mut state),
+            Some(4)
+        );
+        assert_eq!(state.selected(), Some(tag));
+
+        // After a reset the tag needs a full streak again
+        state.reset();
+        assert_eq!(state.raw(), None);
+        assert_eq!(select_tag(&[tag], &selection, [0.0, 0.0], &mut state), None);
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::VecDeque;
+
+/// Publishes the tag ID selected most often over the last few frames.
+///
+/// Keeps the IDs of the last `window` frames, `None` for frames without a tag,
+/// and reports the most frequent one. Among equally frequent IDs the one seen
+/// most recently wins, so a window of 1 reports every frame's ID as is.
+pub(crate) struct IdVoter {
+    window: usize,
+    /// Selected IDs, oldest first
+    recent: VecDeque<Option<i32>>,
+}
+
+impl IdVoter {
+    pub(crate) fn new(window: usize) -> Self {
+        IdVoter {
+            window,
+            recent: VecDeque::with_capacity(window),
+        }
+    }
+
+    /// Change the number of frames voted over; a smaller window drops the oldest frames.
+    pub(crate) fn set_window(&mut self, window: usize) {
+        self.window = window;
+        while self.recent.len() > window {
+            self.recent.pop_front();
+        }
+    }
+
+    /// Record the ID selected in one frame.
+    ///
+    /// # Returns
+    ///
+    /// The majority ID over the window, including this frame; `None` if frames
+    /// without a tag are in the majority.
+    pub(crate) fn observe(&mut self, id: Option<i32>) -> Option<i32> {
+        if self.recent.len() >= self.window {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(id);
+        majority(&self.recent)
+    }
+
+    /// Forget every recorded frame.
+    pub(crate) fn reset(&mut self) {
+        self.recent.clear();
+    }
+}
+
+/// The most frequent value, ties broken by the most recent occurrence.
+///
+/// # Arguments
+///
+/// * `recent` - Values, oldest first.
+fn majority(recent: &VecDeque<Option<i32>>) -> Option<i32> {
+    let mut best = None;
+    let mut best_votes = 0;
+    // Walking from the newest, a tie keeps the value seen later in the window
+    for (i, &id) in recent.iter().enumerate().rev() {
+        // Each value is counted once, at its newest occurrence
+        if recent.range(i + 1..).any(|&later| later == id) {
+            continue;
+        }
+        let votes = recent.iter().filter(|&&other| other == id).count();
+        if votes > best_votes {
+            best = id;
+            best_votes = votes;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn published_ids(voter: &mut IdVoter, frames: &[Option<i32>]) -> Vec<Option<i32>> {
+        frames.iter().map(|&id| voter.observe(id)).collect()
+    }
+
+    #[test]
+    fn test_majority_breaks_ties_by_most_recent() {
+        let votes = |ids: &[Option<i32>]| majority(&ids.iter().copied().collect());
+        assert_eq!(votes(&[]), None);
+        assert_eq!(votes(&[Some(4), Some(7), Some(4)]), Some(4));
+        assert_eq!(votes(&[Some(4), Some(7)]), Some(7));
+        assert_eq!(votes(&[Some(7), Some(4), Some(4), Some(7)]), Some(7));
+        assert_eq!(votes(&[Some(4), None, Some(7), None]), None);
+    }
+
+    #[test]
+    fn test_window_fills_up() {
+        let mut voter = IdVoter::new(5);
+        // Until the window is full, the frames seen so far vote; then the oldest
+        // frame drops out with each new one
+        let frames = [Some(4), Some(7), Some(7), Some(4), Some(4), Some(7)];
+        let expected = [Some(4), Some(7), Some(7), Some(4), Some(4), Some(7)];
+        assert_eq!(published_ids(&mut voter, &frames), expected);
+
+        voter.reset();
+        assert_eq!(voter.observe(Some(7)), Some(7));
+
+        let mut voter = IdVoter::new(1);
+        let frames = [Some(4), None, Some(7), Some(4)];
+        assert_eq!(published_ids(&mut voter, &frames), frames);
+    }
+
+    #[test]
+    fn test_disappearing_tag_outvoted() {
+        let mut voter = IdVoter::new(3);
+        published_ids(&mut voter, &[Some(4), Some(4), Some(4)]);
+        // The tag is reported until frames without it are the majority
+        let frames = [None, None, None];
+        let expected = [Some(4), None, None];
+        assert_eq!(published_ids(&mut voter, &frames), expected);
+
+        // Shrinking the window drops the oldest frames
+        published_ids(&mut voter, &[Some(4), Some(7), Some(7)]);
+        voter.set_window(1);
+        assert_eq!(voter.observe(Some(4)), Some(4));
+    }
+}
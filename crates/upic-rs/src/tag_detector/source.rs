@@ -0,0 +1,288 @@
+#[cfg(feature = "opencv")]
+use opencv::core::Size;
+#[cfg(feature = "opencv")]
+use opencv::imgproc;
+#[cfg(feature = "opencv")]
+use opencv::prelude::*;
+#[cfg(feature = "opencv")]
+use opencv::videoio::VideoCapture;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::frame::Frame;
+use crate::error::UpicError;
+
+/// Camera controls that can be changed through `TagDetector`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CameraProperty {
+    /// Exposure in driver units (`CAP_PROP_EXPOSURE`)
+    Exposure,
+    /// Sensor gain in driver units (`CAP_PROP_GAIN`)
+    Gain,
+    /// Raw auto exposure mode (`CAP_PROP_AUTO_EXPOSURE`)
+    AutoExposure,
+    /// White balance temperature in Kelvin (`CAP_PROP_WB_TEMPERATURE`)
+    WhiteBalance,
+    /// Nominal frame rate (`CAP_PROP_FPS`)
+    Fps,
+    /// Brightness in driver units (`CAP_PROP_BRIGHTNESS`)
+    Brightness,
+}
+
+/// A source of frames for the detection thread
+///
+/// Implemented for live cameras (`CameraSource`, `VideoCapture`) and video
+/// files (`VideoFileSource`) with the `opencv` feature, and for in-memory frames
+/// (`MockFrameSource`), so the detection pipeline can run without camera hardware.
+pub trait FrameSource {
+    /// Read the next frame.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::FrameReadFailed` when no frame is available,
+    /// `UpicError::EndOfStream` when a finite source such as a video file has no
+    /// frames left, or `UpicError::OpenCv` if the backend fails.
+    fn read_frame(&mut self) -> Result<Frame, UpicError>;
+
+    /// Read the next frame into `frame`, reusing its buffer when the size matches.
+    ///
+    /// The detection thread reads through this, so sources that can fill an
+    /// existing buffer avoid allocating a frame per read. The default reads a
+    /// new frame with `read_frame()`.
+    ///
+    /// # Errors
+    ///
+    /// Same as `read_frame()`; `frame` is unspecified after an error.
+    fn read_frame_into(&mut self, frame: &mut Frame) -> Result<(), UpicError> {
+        *frame = self.read_frame()?;
+        Ok(())
+    }
+
+    /// Current frame size as `(width, height)` in pixels.
+    fn resolution(&self) -> (f64, f64);
+
+    /// Request a new frame size; the source may pick the nearest supported one.
+    ///
+    /// Sources with a fixed frame size ignore the request.
+    fn set_resolution(&mut self, _width: f64, _height: f64) -> Result<(), UpicError> {
+        Ok(())
+    }
+
+    /// Set the number of frames buffered by the source.
+    ///
+    /// Sources without a buffer ignore the request.
+    fn set_buffer_size(&mut self, _buffer_size: i32) -> Result<(), UpicError> {
+        Ok(())
+    }
+
+    /// The underlying OpenCV capture, if the source is backed by one.
+    #[cfg(feature = "opencv")]
+    fn video_capture(&self) -> Option<&VideoCapture> {
+        None
+    }
+
+    /// Set a camera control.
+    ///
+    /// # Returns
+    ///
+    /// The value the driver reports after the change, which may differ from
+    /// `value` when the driver clamps or rounds it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::InvalidConfig` if the source has no such control, or
+    /// `UpicError::OpenCv` if the backend fails.
+    fn set_property(&mut self, property: CameraProperty, _value: f64) -> Result<f64, UpicError> {
+        Err(UpicError::InvalidConfig(format!(
+            "The frame source has no {:?} control",
+            property
+        )))
+    }
+
+    /// Current value of a camera control.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UpicError::InvalidConfig` if the source has no such control, or
+    /// `UpicError::OpenCv` if the backend fails.
+    fn property(&self, property: CameraProperty) -> Result<f64, UpicError> {
+        Err(UpicError::InvalidConfig(format!(
+            "The frame source has no {:?} control",
+            property
+        )))
+    }
+
+    /// Whether the source is still open.
+    ///
+    /// A camera whose cable was pulled may report itself closed before, or
+    /// instead of, its reads failing. Sources that can't close report `true`.
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    /// Release and reopen the source after repeated read failures.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the failed reopen. Sources that can't be reopened
+    /// return `UpicError::FrameReadFailed`.
+    fn reconnect(&mut self) -> Result<(), UpicError> {
+        Err(UpicError::FrameReadFailed)
+    }
+}
+
+/// OpenCV capture backend used to open a camera stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CameraBackend {
+    /// Let OpenCV pick a backend (`CAP_ANY`)
+    #[default]
+    Any,
+    /// Video4Linux2 devices such as `/dev/video0` (`CAP_V4L2`)
+    V4l2,
+    /// GStreamer pipeline strings (`CAP_GSTREAMER`)
+    GStreamer,
+    /// Files and network streams such as RTSP URLs (`CAP_FFMPEG`)
+    FFmpeg,
+}
+
+/// In-memory frames, replayed in order and then repeated
+///
+/// Intended for tests that feed pre-rendered images through the detection pipeline.
+/// Setting the resolution rescales the stored frames, and camera controls are
+/// stored as set, reading back as 0 until then.
+pub struct MockFrameSource {
+    frames: Vec<Frame>,
+    next: usize,
+    reads: usize,
+    read_failures: usize,
+    read_panics: usize,
+    read_delay: Duration,
+    properties: HashMap<CameraProperty, f64>,
+    max_fps: Option<f64>,
+}
+
+impl MockFrameSource {
+    /// Create a source cycling through `frames`.
+    ///
+    /// An empty list makes every read fail with `UpicError::FrameReadFailed`,
+    /// which simulates a disconnected camera.
+    pub fn new(frames: Vec<Frame>) -> Self {
+        Self {
+            frames,
+            next: 0,
+            reads: 0,
+            read_failures: 0,
+            read_panics: 0,
+            read_delay: Duration::ZERO,
+            properties: HashMap::new(),
+            max_fps: None,
+        }
+    }
+
+    /// Make every read take `delay`, which simulates a slow camera or board.
+    pub fn with_read_delay(mut self, delay: Duration) -> Self {
+        self.read_delay = delay;
+        self
+    }
+
+    /// Fail the next `count` reads with `UpicError::FrameReadFailed` before
+    /// replaying frames, which simulates a camera glitch.
+    ///
+    /// Reconnecting succeeds whenever there are frames to replay.
+    pub fn with_read_failures(mut self, count: usize) -> Self {
+        self.read_failures = count;
+        self
+    }
+
+    /// Panic in the next `count` reads before replaying frames, which simulates
+    /// a bug in a camera driver or in the OpenCV bindings.
+    pub fn with_read_panics(mut self, count: usize) -> Self {
+        self.read_panics = count;
+        self
+    }
+
+    /// Clamp requested frame rates to `max_fps`, which simulates a camera that
+    /// can't run as fast as asked.
+    pub fn with_max_fps(mut self, max_fps: f64) -> Self {
+        self.max_fps = Some(max_fps);
+        self
+    }
+
+    /// Number of frames handed out so far.
+    pub fn reads(&self) -> usize {
+        self.reads
+    }
+}
+
+impl FrameSource for MockFrameSource {
+    fn read_frame(&mut self) -> Result<Frame, UpicError> {
+        let mut frame = Frame::default();
+        self.read_frame_into(&mut frame)?;
+        Ok(frame)
+    }
+
+    fn read_frame_into(&mut self, frame: &mut Frame) -> Result<(), UpicError> {
+        std::thread::sleep(self.read_delay);
+        if self.read_panics > 0 {
+            self.read_panics -= 1;
+            panic!("mock frame source read panicked");
+        }
+        if self.frames.is_empty() {
+            return Err(UpicError::FrameReadFailed);
+        }
+        if self.read_failures > 0 {
+            self.read_failures -= 1;
+            return Err(UpicError::FrameReadFailed);
+        }
+        self.frames[self.next].copy_to(frame)?;
+        self.next = (self.next + 1) % self.frames.len();
+        self.reads += 1;
+        Ok(())
+    }
+
+    fn resolution(&self) -> (f64, f64) {
+        self.frames.first().map_or((0.0, 0.0), |frame| {
+            (frame.cols() as f64, frame.rows() as f64)
+        })
+    }
+
+    #[cfg(feature = "opencv")]
+    fn set_resolution(&mut self, width: f64, height: f64) -> Result<(), UpicError> {
+        let size = Size::new(width as i32, height as i32);
+        for frame in &mut self.frames {
+            let mut resized = Frame::default();
+            imgproc::resize(frame, &mut resized, size, 0.0, 0.0, imgproc::INTER_AREA)?;
+            *frame = resized;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "opencv"))]
+    fn set_resolution(&mut self, width: f64, height: f64) -> Result<(), UpicError> {
+        for frame in &mut self.frames {
+            *frame = frame.resized(width as i32, height as i32);
+        }
+        Ok(())
+    }
+
+    fn set_property(&mut self, property: CameraProperty, value: f64) -> Result<f64, UpicError> {
+        let value = match (property, self.max_fps) {
+            (CameraProperty::Fps, Some(max_fps)) => value.min(max_fps),
+            _ => value,
+        };
+        self.properties.insert(property, value);
+        Ok(value)
+    }
+
+    fn property(&self, property: CameraProperty) -> Result<f64, UpicError> {
+        Ok(self.properties.get(&property).copied().unwrap_or(0.0))
+    }
+
+    fn reconnect(&mut self) -> Result<(), UpicError> {
+        if self.frames.is_empty() {
+            return Err(UpicError::FrameReadFailed);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use super::rejection::{RejectionCounts, RejectionReason};
+use super::warmup::WarmupOutcome;
+
+/// Number of recent frames the frame rate and detection time are averaged over
+const WINDOW: usize = 30;
+
+/// Health counters of the detection thread
+///
+/// A snapshot returned by `TagDetector::stats()`, reset when detection starts.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DetectionStats {
+    /// Frames read and decoded
+    pub frames_processed: u64,
+    /// Frames in which a tag passing the ID filters was found
+    pub detections: u64,
+    /// Average decision margin of the tags found in the last frames with one;
+    /// 0 until a tag is found
+    pub avg_decision_margin: f64,
+    /// Frame rate over the last frames processed; 0 until two frames are processed
+    pub fps: f64,
+    /// Average time spent decoding and selecting tags per frame over the last frames
+    pub avg_detect_time: Duration,
+    /// Average time from reading a frame to being done with it, publishing and
+    /// the preview included, over the last frames; the pacing sleep is not counted
+    pub avg_frame_time: Duration,
+    /// Average time from starting to read a frame to publishing its tag ID, over
+    /// the last frames read; time the frame spent in the camera's buffer before
+    /// the read is not visible here
+    pub avg_latency: Duration,
+    /// Latency 95% of the last frames read stayed within
+    pub p95_latency: Duration,
+    /// Failed frame reads
+    pub read_errors: u64,
+    /// Consecutive failed frame reads; zero once a frame is read again
+    pub consecutive_read_errors: u32,
+    /// Number of times the frame source was reconnected after read failures
+    pub reconnects: u64,
+    /// Frame rate the camera is set to according to its driver; 0 if the
+    /// source doesn't report one
+    pub nominal_fps: f64,
+    /// Whether frames are preprocessed through OpenCL; false with
+    /// `Config::use_opencl` off or when it fell back to the CPU
+    pub opencl: bool,
+    /// Multiplier `Config::auto_scale` currently applies to the camera
+    /// resolution; 1 while the resolution is unscaled
+    pub resolution_multiplier: f64,
+    /// Camera warm-ups run: the one before detection started, one after each
+    /// reconnect and one per hot-swapped source
+    pub warmups: u64,
+    /// Frames thrown away by those warm-ups
+    pub warmup_frames: u64,
+    /// Outcome of the latest warm-up; `None` before the first
+    pub last_warmup: Option<WarmupOutcome>,
+    /// Whether the detection thread runs at the reduced frame rate of
+    /// `Config::idle_policy`
+    pub idle: bool,
+    /// Number of times the detection thread went idle
+    pub idle_entries: u64,
+    /// Decoded tags the filters discarded, per filter stage
+    pub rejections: RejectionCounts,
+}
+
+/// Accumulates `DetectionStats` in the detection thread.
+///
+/// Takes explicit instants and durations so the windowed averages can be
+/// exercised with synthetic timings.
+pub(crate) struct StatsTracker {
+    stats: DetectionStats,
+    frame_times: VecDeque<Instant>,
+    detect_times: VecDeque<Duration>,
+    margins: VecDeque<f64>,
+    work_times: VecDeque<Duration>,
+    latencies: VecDeque<Duration>,
+}
+
+impl StatsTracker {
+    pub(crate) fn new() -> Self {
+        StatsTracker {
+            stats: DetectionStats::default(),
+            frame_times: VecDeque::with_capacity(WINDOW),
+            detect_times: VecDeque::with_capacity(WINDOW),
+            margins: VecDeque::with_capacity(WINDOW),
+            work_times: VecDeque::with_capacity(WINDOW),
+            latencies: VecDeque::with_capacity(WINDOW),
+        }
+    }
+
+    /// Record a processed frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `margin` - Decision margin of the tag selected in the frame, or `None`
+    ///   if no tag passing the ID filters was found.
+    /// * `detect_time` - Time spent decoding and selecting tags.
+    /// * `read_at` - The time the frame was read.
+    pub(crate) fn record_frame(
+        &mut self,
+        margin: Option<f64>,
+        detect_time: Duration,
+        read_at: Instant,
+    ) {
+        self.stats.frames_processed += 1;
+        self.stats.consecutive_read_errors = 0;
+        if let Some(margin) = margin {
+            self.stats.detections += 1;
+            if self.margins.len() == WINDOW {
+                self.margins.pop_front();
+            }
+            self.margins.push_back(margin);
+            self.stats.avg_decision_margin =
+                self.margins.iter().sum::<f64>() / self.margins.len() as f64;
+        }
+
+        if self.frame_times.len() == WINDOW {
+            self.frame_times.pop_front();
+            self.detect_times.pop_front();
+        }
+        self.frame_times.push_back(read_at);
+        self.detect_times.push_back(detect_time);
+
+        let span = read_at.duration_since(self.frame_times[0]);
+        self.stats.fps = if span.is_zero() {
+            0.0
+        } else {
+            (self.frame_times.len() - 1) as f64 / span.as_secs_f64()
+        };
+        self.stats.avg_detect_time =
+            self.detect_times.iter().sum::<Duration>() / self.detect_times.len() as u32;
+    }
+
+    /// Record the total time spent on a processed frame.
+    pub(crate) fn record_frame_time(&mut self, frame_time: Duration) {
+        if self.work_times.len() == WINDOW {
+            self.work_times.pop_front();
+        }
+        self.work_times.push_back(frame_time);
+        self.stats.avg_frame_time =
+            self.work_times.iter().sum::<Duration>() / self.work_times.len() as u32;
+    }
+
+    /// Record the time from starting to read a frame to publishing its result.
+    pub(crate) fn record_latency(&mut self, latency: Duration) {
+        if self.latencies.len() == WINDOW {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(latency);
+        self.stats.avg_latency =
+            self.latencies.iter().sum::<Duration>() / self.latencies.len() as u32;
+        let mut sorted: Vec<Duration> = self.latencies.iter().copied().collect();
+        sorted.sort();
+        // Nearest rank, so the percentile is one of the measured latencies
+        let rank = (sorted.len() * 95).div_ceil(100);
+        self.stats.p95_latency = sorted[rank - 1];
+    }
+
+    /// Record a failed frame read.
+    pub(crate) fn record_read_error(&mut self, consecutive_errors: u32) {
+        self.stats.read_errors += 1;
+        self.stats.consecutive_read_errors = consecutive_errors;
+    }
+
+    /// Record a successful reconnect of the frame source.
+    pub(crate) fn record_reconnect(&mut self) {
+        self.stats.reconnects += 1;
+        self.stats.consecutive_read_errors = 0;
+    }
+
+    /// Record the camera's nominal frame rate.
+    pub(crate) fn set_nominal_fps(&mut self, nominal_fps: f64) {
+        self.stats.nominal_fps = nominal_fps;
+    }
+
+    /// Record the multiplier applied to the camera resolution.
+    pub(crate) fn set_resolution_multiplier(&mut self, resolution_multiplier: f64) {
+        self.stats.resolution_multiplier = resolution_multiplier;
+    }
+
+    /// Record a completed camera warm-up.
+    pub(crate) fn record_warmup(&mut self, outcome: WarmupOutcome) {
+        self.stats.warmups += 1;
+        self.stats.warmup_frames += outcome.frames_discarded as u64;
+        self.stats.last_warmup = Some(outcome);
+    }
+
+    /// Record whether the detection thread is idle.
+    pub(crate) fn set_idle(&mut self, idle: bool) {
+        if idle && !self.stats.idle {
+            self.stats.idle_entries += 1;
+        }
+        self.stats.idle = idle;
+    }
+
+    /// Record a decoded tag the filters discarded.
+    pub(crate) fn record_rejection(&mut self, reason: RejectionReason) {
+        self.stats.rejections.add(reason);
+    }
+
+    /// Record whether frames are preprocessed through OpenCL.
+    pub(crate) fn set_opencl(&mut self, opencl: bool) {
+        self.stats.opencl = opencl;
+    }
+
+    pub(crate) fn snapshot(&self) -> DetectionStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_and_windowed_averages() {
+        let start = Instant::now();
+        let mut tracker = StatsTracker::new();
+        assert_eq!(tracker.snapshot(), DetectionStats::default());
+
+        tracker.record_frame(Some(10.0), Duration::from_millis(10), start);
+        assert_eq!(tracker.snapshot().fps, 0.0);
+        assert_eq!(tracker.snapshot().avg_decision_margin, 10.0);
+
+        // 20 ms per frame is 50 FPS
+        for i in 1..=40u32 {
+            let detect_time = Duration::from_millis(if i > 10 { 4 } else { 30 });
+            tracker.record_frame(
+                (i % 2 == 0).then_some(40.0 + i as f64),
+                detect_time,
+                start + i * Duration::from_millis(20),
+            );
+        }
+        let stats = tracker.snapshot();
+        assert_eq!(stats.frames_processed, 41);
+        assert_eq!(stats.detections, 21);
+        // Fewer than 30 frames had a tag, so every margin counts
+        assert!((stats.avg_decision_margin - 1230.0 / 21.0).abs() < 1e-9);
+        assert!((stats.fps - 50.0).abs() < 1e-6, "fps {}", stats.fps);
+        // Only the last 30 frames count toward the average
+        assert_eq!(stats.avg_detect_time, Duration::from_millis(4));
+
+        for frame_time in [10, 20, 30] {
+            tracker.record_frame_time(Duration::from_millis(frame_time));
+        }
+        assert_eq!(tracker.snapshot().avg_frame_time, Duration::from_millis(20));
+
+        // A buffered frame now and then shows in the p95 more than in the average
+        for i in 0..40u64 {
+            let latency = if i % 10 == 9 { 120 } else { 20 };
+            tracker.record_latency(Duration::from_millis(latency));
+        }
+        let stats = tracker.snapshot();
+        assert_eq!(stats.avg_latency, Duration::from_millis(30));
+        assert_eq!(stats.p95_latency, Duration::from_millis(120));
+
+        tracker.record_read_error(1);
+        tracker.record_read_error(2);
+        assert_eq!(tracker.snapshot().read_errors, 2);
+        assert_eq!(tracker.snapshot().consecutive_read_errors, 2);
+        tracker.record_reconnect();
+        assert_eq!(tracker.snapshot().consecutive_read_errors, 0);
+        assert_eq!(tracker.snapshot().reconnects, 1);
+
+        tracker.set_nominal_fps(30.0);
+        assert_eq!(tracker.snapshot().nominal_fps, 30.0);
+        tracker.set_resolution_multiplier(0.8);
+        assert_eq!(tracker.snapshot().resolution_multiplier, 0.8);
+
+        for frames_discarded in [5, 2] {
+            tracker.record_warmup(WarmupOutcome {
+                frames_discarded,
+                settled: None,
+                elapsed: Duration::from_millis(10),
+            });
+        }
+        let stats = tracker.snapshot();
+        assert_eq!((stats.warmups, stats.warmup_frames), (2, 7));
+        assert_eq!(stats.last_warmup.map(|w| w.frames_discarded), Some(2));
+
+        // Entering idle mode counts once until it is left
+        for idle in [true, true, false, true] {
+            tracker.set_idle(idle);
+        }
+        assert!(tracker.snapshot().idle);
+        assert_eq!(tracker.snapshot().idle_entries, 2);
+
+        tracker.record_rejection(RejectionReason::LowMargin);
+        tracker.record_rejection(RejectionReason::NotAllowed);
+        let rejections = tracker.snapshot().rejections;
+        assert_eq!(rejections.low_margin, 1);
+        assert_eq!(rejections.total(), 2);
+    }
+}
@@ -0,0 +1,81 @@
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+use super::source::FrameSource;
+use super::sync::Recover;
+use super::warmup::WarmupOutcome;
+
+/// A frame source handed to the detection thread to read from instead of its own
+pub(crate) struct SwapRequest {
+    pub(crate) source: Box<dyn FrameSource + Send>,
+    /// Warm-up the caller ran on the source, for the detection statistics
+    pub(crate) warmup: WarmupOutcome,
+    /// Receives the replaced source, so the caller releases it rather than the thread
+    pub(crate) reply: Sender<Box<dyn FrameSource + Send>>,
+}
+
+/// Pending source swaps, served between frame reads
+pub(crate) type SwapRequests = Arc<Mutex<Vec<SwapRequest>>>;
+
+/// Switch `source` to each pending replacement in turn.
+///
+/// # Returns
+///
+/// The warm-ups of the sources switched to, oldest first. When it isn't empty
+/// the source was replaced, and the frame size and frame rate may have changed.
+pub(crate) fn serve_swap_requests(
+    requests: &Mutex<Vec<SwapRequest>>,
+    source: &mut Box<dyn FrameSource + Send>,
+) -> Vec<WarmupOutcome> {
+    let pending = std::mem::take(&mut *requests.lock().recover());
+    let mut warmups = Vec::with_capacity(pending.len());
+    for request in pending {
+        let replaced = std::mem::replace(source, request.source);
+        warmups.push(request.warmup);
+        // The caller may have timed out and gone away, the old source is then dropped here
+        let _ = request.reply.send(replaced);
+    }
+    warmups
+}
+
+#[cfg(all(test, feature = "opencv"))]
+mod tests {
+    use super::*;
+    use crate::tag_detector::source::MockFrameSource;
+    use opencv::core::{CV_8UC1, Mat, Scalar};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    fn source(width: i32) -> Box<dyn FrameSource + Send> {
+        let frame = Mat::new_rows_cols_with_default(10, width, CV_8UC1, Scalar::all(0.0)).unwrap();
+        Box::new(MockFrameSource::new(vec![frame]))
+    }
+
+    #[test]
+    fn test_serve_swap_requests() {
+        let requests = Mutex::new(Vec::new());
+        let mut current = source(10);
+        assert!(serve_swap_requests(&requests, &mut current).is_empty());
+
+        let (reply, replaced) = mpsc::channel();
+        for width in [20, 30] {
+            requests.lock().unwrap().push(SwapRequest {
+                source: source(width),
+                warmup: WarmupOutcome {
+                    frames_discarded: width as usize,
+                    settled: None,
+                    elapsed: Duration::ZERO,
+                },
+                reply: reply.clone(),
+            });
+        }
+        let warmups = serve_swap_requests(&requests, &mut current);
+        let discarded: Vec<usize> = warmups.iter().map(|w| w.frames_discarded).collect();
+        assert_eq!(discarded, [20, 30]);
+        assert_eq!(current.resolution(), (30.0, 10.0));
+        // Each request gets back the source it replaced
+        assert_eq!(replaced.recv().unwrap().resolution(), (10.0, 10.0));
+        assert_eq!(replaced.recv().unwrap().resolution(), (20.0, 10.0));
+        assert!(requests.lock().unwrap().is_empty());
+    }
+}
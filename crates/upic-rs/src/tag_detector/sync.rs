@@ -0,0 +1,53 @@
+use std::any::Any;
+use std::sync::{LockResult, PoisonError};
+
+/// Recovery from lock poisoning
+///
+/// A lock is poisoned when a thread panics while holding it. Every lock in the
+/// detector guards plain values that are replaced whole, never left half
+/// updated, so the data is still valid and taking it beats propagating the
+/// panic into the detection thread or the caller.
+pub(crate) trait Recover<G> {
+    /// The guard, whether or not the lock was poisoned.
+    fn recover(self) -> G;
+}
+
+impl<G> Recover<G> for LockResult<G> {
+    fn recover(self) -> G {
+        self.unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The message a panic was raised with, as far as it can be recovered.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic payload".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    #[test]
+    fn test_recover_poisoned_lock() {
+        let value = Arc::new(Mutex::new(1));
+        let poisoner = Arc::clone(&value);
+        let panic = thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("poisoning the lock");
+        })
+        .join()
+        .unwrap_err();
+        assert_eq!(panic_message(&*panic), "poisoning the lock");
+        assert!(value.is_poisoned());
+
+        *value.lock().recover() += 1;
+        assert_eq!(*value.lock().recover(), 2);
+        assert_eq!(panic_message(&42), "unknown panic payload");
+    }
+}
@@ -0,0 +1,60 @@
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use super::detection::TagDetection;
+use super::sync::Recover;
+
+/// Detections buffered for each `TagDetector::detection_stream()` consumer
+pub(crate) const STREAM_CAPACITY: usize = 16;
+
+/// Feeds of `TagDetector::detection_stream()`; closed streams are dropped on
+/// the next detection
+pub(crate) type DetectionSubscribers = Arc<Mutex<Vec<mpsc::Sender<TagDetection>>>>;
+
+/// Hand a detection to every stream without blocking the detection thread.
+///
+/// A stream whose buffer is full misses the detection, so a consumer that
+/// falls behind gets the detections from after it caught up.
+pub(crate) fn send_detection(
+    subscribers: &Mutex<Vec<mpsc::Sender<TagDetection>>>,
+    detection: TagDetection,
+) {
+    let mut subscribers = subscribers.lock().recover();
+    subscribers.retain(|subscriber| {
+        !matches!(subscriber.try_send(detection), Err(TrySendError::Closed(_)))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tag_detector::config::TagFamily;
+
+    fn detection(id: i32) -> TagDetection {
+        TagDetection {
+            id,
+            family: TagFamily::Tag36h11,
+            corners: [[0.0, 0.0]; 4],
+            center: [0.0, 0.0],
+            decision_margin: 30.0,
+            hamming: 0,
+        }
+    }
+
+    #[test]
+    fn test_send_detection_skips_full_and_drops_closed() {
+        let (kept, mut received) = mpsc::channel(1);
+        let (closed, _) = mpsc::channel(1);
+        let subscribers = Mutex::new(vec![kept, closed]);
+        send_detection(&subscribers, detection(1));
+        send_detection(&subscribers, detection(2));
+        assert_eq!(subscribers.lock().unwrap().len(), 1);
+
+        // The full stream missed tag 2 and gets tag 3 once drained
+        assert_eq!(received.try_recv().unwrap().id, 1);
+        assert!(received.try_recv().is_err());
+        send_detection(&subscribers, detection(3));
+        assert_eq!(received.try_recv().unwrap().id, 3);
+    }
+}
@@ -0,0 +1,153 @@
+use opencv::core::{Mat, Size, Vector};
+use opencv::prelude::*;
+use opencv::{calib3d, core, imgproc};
+
+use super::pose::CameraIntrinsics;
+use crate::error::UpicError;
+
+/// Removes lens distortion from frames.
+///
+/// The remap tables are expensive to build, so they are cached and only
+/// rebuilt when the frame size or the intrinsics change.
+pub(crate) struct Undistorter {
+    /// Frame size and intrinsics the maps were built for
+    built_for: Option<(Size, CameraIntrinsics)>,
+    map_x: Mat,
+    map_y: Mat,
+}
+
+impl Undistorter {
+    pub(crate) fn new() -> Self {
+        Undistorter {
+            built_for: None,
+            map_x: Mat::default(),
+            map_y: Mat::default(),
+        }
+    }
+
+    /// Undistort a frame into `undistorted`, reusing its buffer.
+    ///
+    /// The result keeps the camera matrix of `intrinsics` and has no
+    /// distortion, as described by `CameraIntrinsics::rectified()`.
+    pub(crate) fn apply(
+        &mut self,
+        frame: &Mat,
+        intrinsics: &CameraIntrinsics,
+        undistorted: &mut Mat,
+    ) -> Result<(), UpicError> {
+        let size = frame.size()?;
+        let current = self
+            .built_for
+            .as_ref()
+            .is_some_and(|(built_size, built_intrinsics)| {
+                *built_size == size && built_intrinsics == intrinsics
+            });
+        if !current {
+            self.build_maps(size, intrinsics)?;
+        }
+
+        imgproc::remap(
+            frame,
+            undistorted,
+            &self.map_x,
+            &self.map_y,
+            imgproc::INTER_LINEAR,
+            core::BORDER_CONSTANT,
+            core::Scalar::default(),
+        )?;
+        Ok(())
+    }
+
+    fn build_maps(&mut self, size: Size, intrinsics: &CameraIntrinsics) -> Result<(), UpicError> {
+        log::info!(
+            "Building undistortion maps for {}x{} frames",
+            size.width,
+            size.height
+        );
+        let camera_matrix = intrinsics.camera_matrix()?;
+        calib3d::init_undistort_rectify_map(
+            &camera_matrix,
+            &Vector::<f64>::from_slice(&intrinsics.distortion),
+            &Mat::default(),
+            &camera_matrix,
+            size,
+            core::CV_16SC2,
+            &mut self.map_x,
+            &mut self.map_y,
+        )?;
+        self.built_for = Some((size, intrinsics.clone()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intrinsics(distortion: Vec<f64>) -> CameraIntrinsics {
+        CameraIntrinsics {
+            fx: 300.0,
+            fy: 300.0,
+            cx: 160.0,
+            cy: 120.0,
+            distortion,
+        }
+    }
+
+    #[test]
+    fn test_maps_follow_frame_size() {
+        let mut frame =
+            Mat::new_rows_cols_with_default(240, 320, core::CV_8UC1, core::Scalar::all(0.0))
+                .unwrap();
+        imgproc::rectangle(
+            &mut frame,
+            core::Rect::new(100, 60, 120, 120),
+            core::Scalar::all(255.0),
+            -1,
+            imgproc::LINE_8,
+            0,
+        )
+        .unwrap();
+        let mut undistorter = Undistorter::new();
+        let mut undistorted = Mat::default();
+
+        // Without distortion the center of the frame stays where it is
+        undistorter
+            .apply(&frame, &intrinsics(Vec::new()), &mut undistorted)
+            .unwrap();
+        assert_eq!(*undistorted.at_2d::<u8>(120, 160).unwrap(), 255);
+        assert_eq!(*undistorted.at_2d::<u8>(10, 10).unwrap(), 0);
+
+        // Barrel distortion correction pulls the corners in from outside the frame
+        let barrel = intrinsics(vec![-0.3, 0.1, 0.0, 0.0]);
+        undistorter
+            .apply(&frame, &barrel, &mut undistorted)
+            .unwrap();
+        assert_eq!(undistorted.size().unwrap(), Size::new(320, 240));
+        assert_eq!(*undistorted.at_2d::<u8>(120, 160).unwrap(), 255);
+        assert_eq!(
+            undistorter.built_for.as_ref().map(|(size, _)| *size),
+            Some(Size::new(320, 240))
+        );
+
+        // A new resolution rebuilds the maps
+        let mut smaller = Mat::default();
+        imgproc::resize(
+            &frame,
+            &mut smaller,
+            Size::new(160, 120),
+            0.0,
+            0.0,
+            imgproc::INTER_AREA,
+        )
+        .unwrap();
+        undistorter
+            .apply(&smaller, &barrel, &mut undistorted)
+            .unwrap();
+        assert_eq!(undistorted.size().unwrap(), Size::new(160, 120));
+        assert_eq!(
+            undistorter.built_for.as_ref().map(|(size, _)| *size),
+            Some(Size::new(160, 120))
+        );
+    }
+}
@@ -0,0 +1,176 @@
+#[cfg(feature = "opencv")]
+use opencv::prelude::*;
+use std::time::{Duration, Instant};
+
+use super::config::WarmupPolicy;
+use super::frame::Frame;
+use super::source::FrameSource;
+use crate::error::UpicError;
+
+/// Summary of a completed camera warm-up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WarmupOutcome {
+    /// Total number of frames read and thrown away.
+    pub frames_discarded: usize,
+    /// Whether the brightness settled within the configured delta.
+    /// `None` when no settle phase was configured.
+    pub settled: Option<bool>,
+    /// Wall-clock time spent warming up.
+    pub elapsed: Duration,
+}
+
+/// Discard frames until the camera output is usable.
+///
+/// First reads and drops `policy.discard_frames` frames. If a settle phase is
+/// configured, keeps reading until two consecutive frames differ in mean
+/// brightness by at most `brightness_delta`, or until `max_wait` expires.
+///
+/// # Arguments
+///
+/// * `camera` - An opened camera or other frame source to warm up.
+/// * `policy` - The warm-up policy from `Config::warmup`.
+///
+/// # Errors
+///
+/// Returns `UpicError::FrameReadFailed` or `UpicError::OpenCv` if a frame read
+/// or the brightness computation fails.
+///
+/// # Note
+///
+/// An expired `max_wait` is not an error: the outcome reports `settled: Some(false)`
+/// and detection starts anyway, since a slowly drifting exposure is still
+/// better than no detection at all.
+pub fn warm_up(
+    camera: &mut dyn FrameSource,
+    policy: &WarmupPolicy,
+) -> Result<WarmupOutcome, UpicError> {
+    let start = Instant::now();
+    let mut frames_discarded = 0;
+
+    for _ in 0..policy.discard_frames {
+        camera.read_frame()?;
+        frames_discarded += 1;
+    }
+
+    let settled = match policy.settle {
+        None => None,
+        Some(settle) => {
+            let settle_start = Instant::now();
+            let mut previous: Option<f64> = None;
+            let mut settled = false;
+
+            while settle_start.elapsed() < settle.max_wait {
+                let frame = camera.read_frame()?;
+                frames_discarded += 1;
+                let brightness = mean_brightness(&frame)?;
+                if let Some(prev) = previous
+                    && (brightness - prev).abs() <= settle.brightness_delta
+                {
+                    settled = true;
+                    break;
+                }
+                previous = Some(brightness);
+            }
+            Some(settled)
+        }
+    };
+
+    Ok(WarmupOutcome {
+        frames_discarded,
+        settled,
+        elapsed: start.elapsed(),
+    })
+}
+
+/// Mean pixel value over all channels of a frame (0–255 for 8-bit frames).
+#[cfg(feature = "opencv")]
+pub(crate) fn mean_brightness(frame: &Frame) -> Result<f64, UpicError> {
+    let channels = (frame.channels().max(1) as usize).min(4);
+    let mean = opencv::core::mean(frame, &opencv::core::no_array())?;
+    Ok(mean.0[..channels].iter().sum::<f64>() / channels as f64)
+}
+
+/// Mean pixel value of a grayscale frame (0–255).
+#[cfg(not(feature = "opencv"))]
+pub(crate) fn mean_brightness(frame: &Frame) -> Result<f64, UpicError> {
+    let pixels = frame.pixels();
+    let sum: f64 = pixels.iter().map(|&pixel| f64::from(pixel)).sum();
+    Ok(sum / pixels.len().max(1) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tag_detector::config::SettleSpec;
+    use crate::tag_detector::source::MockFrameSource;
+
+    /// Uniform frames whose brightness steps through `levels`
+    fn frames(levels: &[u8]) -> Vec<Frame> {
+        levels
+            .iter()
+            .map(|&level| {
+                #[cfg(feature = "opencv")]
+                return opencv::core::Mat::new_rows_cols_with_default(
+                    8,
+                    8,
+                    opencv::core::CV_8UC1,
+                    opencv::core::Scalar::all(f64::from(level)),
+                )
+                .unwrap();
+                #[cfg(not(feature = "opencv"))]
+                Frame::filled(8, 8, level)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_settles_once_brightness_stops_ramping() {
+        // Auto-exposure brightening the image over the first frames
+        let mut camera = MockFrameSource::new(frames(&[0, 40, 80, 120, 160, 200, 202, 202]));
+        let policy = WarmupPolicy {
+            discard_frames: 2,
+            settle: Some(SettleSpec {
+                max_wait: Duration::from_secs(5),
+                brightness_delta: 5.0,
+            }),
+        };
+        let outcome = warm_up(&mut camera, &policy).unwrap();
+        // 0 and 40 are discarded, then 80 through 202 are read until two agree
+        assert_eq!(outcome.frames_discarded, 7);
+        assert_eq!(outcome.settled, Some(true));
+        assert_eq!(
+            mean_brightness(&camera.read_frame().unwrap()).unwrap(),
+            202.0
+        );
+    }
+
+    #[test]
+    fn test_gives_up_after_max_wait() {
+        let mut camera = MockFrameSource::new(frames(&[0, 100]));
+        let policy = WarmupPolicy {
+            discard_frames: 0,
+            settle: Some(SettleSpec {
+                max_wait: Duration::from_millis(20),
+                brightness_delta: 5.0,
+            }),
+        };
+        let outcome = warm_up(&mut camera, &policy).unwrap();
+        assert_eq!(outcome.settled, Some(false));
+        assert!(outcome.frames_discarded > 1);
+
+        // Without a settle phase only the fixed count is read
+        let policy = WarmupPolicy {
+            discard_frames: 3,
+            settle: None,
+        };
+        let outcome = warm_up(&mut camera, &policy).unwrap();
+        assert_eq!((outcome.frames_discarded, outcome.settled), (3, None));
+
+        // A camera that can't be read fails the warm-up
+        let mut camera = MockFrameSource::new(Vec::new());
+        assert!(matches!(
+            warm_up(&mut camera, &policy),
+            Err(UpicError::FrameReadFailed)
+        ));
+    }
+}
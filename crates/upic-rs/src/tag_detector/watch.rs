@@ -0,0 +1,152 @@
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use super::sync::Recover;
+
+/// The published tag ID and the time its frame was read
+///
+/// The ID is an atomic, so reading it never takes a lock. The read time and
+/// the condvar sit behind a mutex that publishing holds while storing the ID,
+/// so a waiter can't miss a change between checking the ID and going to sleep.
+pub(crate) struct PublishedTagId {
+    id: AtomicI32,
+    /// No time marks the ID as stale
+    read_at: Mutex<Option<Instant>>,
+    changed: Condvar,
+    /// Mirror of the ID for async watchers
+    #[cfg(feature = "tokio")]
+    watch: tokio::sync::watch::Sender<i32>,
+}
+
+impl PublishedTagId {
+    pub(crate) fn new(id: i32) -> Self {
+        PublishedTagId {
+            id: AtomicI32::new(id),
+            read_at: Mutex::new(None),
+            changed: Condvar::new(),
+            #[cfg(feature = "tokio")]
+            watch: tokio::sync::watch::Sender::new(id),
+        }
+    }
+
+    /// The published ID, without locking.
+    pub(crate) fn id(&self) -> i32 {
+        self.id.load(Ordering::Acquire)
+    }
+
+    /// The published ID with the time its frame was read.
+    pub(crate) fn stamped(&self) -> (i32, Option<Instant>) {
+        let read_at = self.read_at.lock().recover();
+        (self.id(), *read_at)
+    }
+
+    /// The published ID if its frame was read at most `max_age` ago.
+    pub(crate) fn fresh(&self, max_age: Duration) -> Option<i32> {
+        let (id, read_at) = self.stamped();
+        read_at
+            .is_some_and(|read_at| read_at.elapsed() <= max_age)
+            .then_some(id)
+    }
+
+    /// Publish a tag ID, waking `wait_for_tag` callers if the ID changed.
+    pub(crate) fn publish(&self, id: i32, read_at: Option<Instant>) {
+        let mut stamp = self.read_at.lock().recover();
+        *stamp = read_at;
+        let previous = self.id.swap(id, Ordering::AcqRel);
+        if previous != id {
+            self.changed.notify_all();
+            #[cfg(feature = "tokio")]
+            self.watch.send_replace(id);
+        }
+    }
+
+    /// A receiver of the ID, notified when it changes.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn subscribe(&self) -> tokio::sync::watch::Receiver<i32> {
+        self.watch.subscribe()
+    }
+}
+
+/// The published tag ID shared between the detector, its thread and watchers
+pub(crate) type SharedTagId = Arc<PublishedTagId>;
+
+/// A cloneable handle on a detector's published tag ID
+///
+/// Obtained from `TagDetector::watcher()`. Unlike the detector itself, it can be
+/// moved to and shared between threads, so several threads can wait for tags
+/// at the same time.
+#[derive(Clone)]
+pub struct TagWatcher {
+    tag_id: SharedTagId,
+}
+
+impl TagWatcher {
+    pub(crate) fn new(tag_id: SharedTagId) -> Self {
+        TagWatcher { tag_id }
+    }
+
+    /// The currently published tag ID, see `TagDetector::tag_id()`.
+    pub fn tag_id(&self) -> i32 {
+        self.tag_id.id()
+    }
+
+    /// Block until the published tag ID satisfies `predicate` or `timeout` elapses.
+    ///
+    /// See `TagDetector::wait_for_tag()`.
+    pub fn wait_for_tag<F: Fn(i32) -> bool>(&self, predicate: F, timeout: Duration) -> Option<i32> {
+        let guard = self.tag_id.read_at.lock().recover();
+        let (_guard, _) = self
+            .tag_id
+            .changed
+            .wait_timeout_while(guard, timeout, |_| !predicate(self.tag_id.id()))
+            .recover();
+        let id = self.tag_id.id();
+        predicate(id).then_some(id)
+    }
+
+    /// Block until the tag with `id` is published or `timeout` elapses.
+    ///
+    /// Returns `true` if the tag was published in time.
+    pub fn wait_for_tag_id(&self, id: i32, timeout: Duration) -> bool {
+        self.wait_for_tag(|tag_id| tag_id == id, timeout).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_waiters_wake_on_change() {
+        let tag_id: SharedTagId = Arc::new(PublishedTagId::new(-1));
+        let watcher = TagWatcher::new(Arc::clone(&tag_id));
+        assert_eq!(
+            watcher.wait_for_tag(|id| id >= 0, Duration::from_millis(10)),
+            None
+        );
+
+        let waiters: Vec<_> = (0..3)
+            .map(|_| {
+                let watcher = watcher.clone();
+                thread::spawn(move || watcher.wait_for_tag(|id| id >= 0, Duration::from_secs(5)))
+            })
+            .collect();
+        thread::sleep(Duration::from_millis(20));
+        tag_id.publish(4, Some(Instant::now()));
+
+        for waiter in waiters {
+            assert_eq!(waiter.join().unwrap(), Some(4));
+        }
+        assert!(watcher.wait_for_tag_id(4, Duration::ZERO));
+        assert_eq!(watcher.tag_id(), 4);
+        assert_eq!(tag_id.stamped().0, 4);
+        assert!(tag_id.stamped().1.is_some());
+        assert_eq!(tag_id.fresh(Duration::from_secs(5)), Some(4));
+        thread::sleep(Duration::from_millis(5));
+        assert_eq!(tag_id.fresh(Duration::from_millis(1)), None);
+        tag_id.publish(-1, None);
+        assert_eq!(tag_id.fresh(Duration::from_secs(5)), None);
+    }
+}
@@ -0,0 +1,309 @@
+//! Synthetic frames with decodable AprilTags, for tests and demos without a camera
+//!
+//! Needs the `testing` feature outside this crate's own tests. Tags are drawn
+//! from OpenCV's AprilTag dictionaries, which hold the same codes as the
+//! AprilTag library, so the detector decodes the rendered IDs. OpenCV has no
+//! dictionary for `TagStandard41h12` and `TagCircle21h7`, which can't be rendered.
+
+use std::path::Path;
+
+use opencv::core::{self, Mat, Point2f, Size};
+use opencv::imgproc;
+use opencv::objdetect::{self, PredefinedDictionaryType};
+use opencv::prelude::*;
+use opencv::videoio::{self, VideoWriter};
+
+use crate::error::UpicError;
+use crate::tag_detector::TagFamily;
+
+/// Gray level of the frame around the rendered tags
+const BACKGROUND: f64 = 255.0;
+
+/// A tag to draw with `render_multi()`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TagSpec {
+    pub family: TagFamily,
+    pub id: i32,
+    /// Side length of the black square in pixels, before rotation
+    pub size_px: i32,
+    /// Point the tag's center lands on, in the pixel coordinates of
+    /// `TagDetection::center`
+    pub center: [f64; 2],
+    /// Rotation about the center in degrees, counter-clockwise as seen in the frame
+    pub rotation_deg: f64,
+}
+
+impl TagFamily {
+    /// OpenCV's dictionary holding the family's codes, if it has one
+    fn dictionary_type(self) -> Option<PredefinedDictionaryType> {
+        match self {
+            TagFamily::Tag36h11 => Some(PredefinedDictionaryType::DICT_APRILTAG_36h11),
+            TagFamily::Tag25h9 => Some(PredefinedDictionaryType::DICT_APRILTAG_25h9),
+            TagFamily::Tag16h5 => Some(PredefinedDictionaryType::DICT_APRILTAG_16h5),
+            TagFamily::TagStandard41h12 | TagFamily::TagCircle21h7 => None,
+        }
+    }
+}
+
+/// Render one tag onto a white grayscale frame.
+///
+/// # Arguments
+///
+/// * `family` - Tag family; `Tag36h11`, `Tag25h9` or `Tag16h5`.
+/// * `id` - Tag ID within the family.
+/// * `size_px` - Side length of the black square in pixels.
+/// * `center` - Point the tag's center lands on.
+/// * `frame_size` - `(width, height)` of the frame.
+/// * `rotation_deg` - Counter-clockwise rotation about the center in degrees.
+///
+/// # Errors
+///
+/// Same as `render_multi()`.
+///
+/// # Examples
+///
+/// ```rust
+/// // Demo mode without a camera attached
+/// let frame = render_tag_frame(TagFamily::Tag36h11, 3, 120, [320.0, 240.0], (640, 480), 15.0)?;
+/// let mut detector = TagDetector::with_source(Box::new(MockFrameSource::new(vec![frame])))?;
+/// detector.apriltag_detect_start()?;
+/// ```
+pub fn render_tag_frame(
+    family: TagFamily,
+    id: i32,
+    size_px: i32,
+    center: [f64; 2],
+    frame_size: (i32, i32),
+    rotation_deg: f64,
+) -> Result<Mat, UpicError> {
+    render_multi(
+        frame_size,
+        &[TagSpec {
+            family,
+            id,
+            size_px,
+            center,
+            rotation_deg,
+        }],
+    )
+}
+
+/// Render several tags onto a white grayscale frame.
+///
+/// Tags are drawn in order, so later tags cover earlier ones where they
+/// overlap. The detector needs a white margin of about one tag bit around each
+/// tag to find it.
+///
+/// # Arguments
+///
+/// * `frame_size` - `(width, height)` of the frame.
+/// * `tags` - Tags to draw.
+///
+/// # Errors
+///
+/// Returns `UpicError::InvalidConfig` for a family that can't be rendered, an
+/// ID outside the family, or a tag too small to hold its bits, and
+/// `UpicError::OpenCv` if drawing fails.
+pub fn render_multi(frame_size: (i32, i32), tags: &[TagSpec]) -> Result<Mat, UpicError> {
+    let (width, height) = frame_size;
+    let mut frame = Mat::new_rows_cols_with_default(
+        height,
+        width,
+        core::CV_8UC1,
+        core::Scalar::all(BACKGROUND),
+    )?;
+    for tag in tags {
+        let marker = render_marker(tag)?;
+        // Rotate about the marker's center, then move that center onto the tag's
+        let half = tag.size_px as f32 / 2.0;
+        let mut transform =
+            imgproc::get_rotation_matrix_2d(Point2f::new(half, half), tag.rotation_deg, 1.0)?;
+        *transform.at_2d_mut::<f64>(0, 2)? += tag.center[0] - half as f64;
+        *transform.at_2d_mut::<f64>(1, 2)? += tag.center[1] - half as f64;
+        // A transparent border leaves the frame untouched outside the tag
+        imgproc::warp_affine(
+            &marker,
+            &mut frame,
+            &transform,
+            Size::new(width, height),
+            imgproc::INTER_LINEAR,
+            core::BORDER_TRANSPARENT,
+            core::Scalar::all(BACKGROUND),
+        )?;
+    }
+    Ok(frame)
+}
+
+/// Write frames to a Motion JPEG video, for replaying with `VideoFileSource`.
+///
+/// # Arguments
+///
+/// * `path` - File to write, conventionally with an `.avi` extension.
+/// * `frames` - Grayscale or BGR frames, all of the first frame's size.
+/// * `fps` - Frame rate recorded in the file.
+///
+/// # Errors
+///
+/// Returns `UpicError::InvalidConfig` if there are no frames or the file can't
+/// be created, and `UpicError::OpenCv` if encoding fails.
+///
+/// # Examples
+///
+/// ```rust
+/// let frame = render_tag_frame(TagFamily::Tag36h11, 3, 120, [320.0, 240.0], (640, 480), 0.0)?;
+/// write_video("match.avi", &vec![frame; 30], 30.0)?;
+/// let source = VideoFileSource::open("match.avi", false)?.with_realtime(true);
+/// ```
+pub fn write_video(path: impl AsRef<Path>, frames: &[Mat], fps: f64) -> Result<(), UpicError> {
+    let path = path.as_ref();
+    let first = frames
+        .first()
+        .ok_or_else(|| UpicError::InvalidConfig("No frames to write".to_string()))?;
+    // OpenCV's own encoder, so writing doesn't depend on the codecs installed
+    let mut writer = VideoWriter::new_with_backend(
+        &path.to_string_lossy(),
+        videoio::CAP_OPENCV_MJPEG,
+        VideoWriter::fourcc('M', 'J', 'P', 'G')?,
+        fps,
+        first.size()?,
+        true,
+    )?;
+    if !writer.is_opened()? {
+        return Err(UpicError::InvalidConfig(format!(
+            "Can't create video file {}",
+            path.display()
+        )));
+    }
+    let mut color = Mat::default();
+    for frame in frames {
+        if frame.channels() == 1 {
+            imgproc::cvt_color_def(frame, &mut color, imgproc::COLOR_GRAY2BGR)?;
+            writer.write(&color)?;
+        } else {
+            writer.write(frame)?;
+        }
+    }
+    writer.release()?;
+    Ok(())
+}
+
+/// The black-bordered bitmap of one tag, `size_px` wide
+fn render_marker(tag: &TagSpec) -> Result<Mat, UpicError> {
+    let dictionary_type = tag.family.dictionary_type().ok_or_else(|| {
+        UpicError::InvalidConfig(format!("{:?} tags can't be rendered", tag.family))
+    })?;
+    let dictionary = objdetect::get_predefined_dictionary(dictionary_type)?;
+    let codes = dictionary.bytes_list().rows();
+    if !(0..codes).contains(&tag.id) {
+        return Err(UpicError::InvalidConfig(format!(
+            "{:?} has IDs 0 to {}, got {}",
+            tag.family,
+            codes - 1,
+            tag.id
+        )));
+    }
+    // The data bits plus one border bit on each side
+    let bits = dictionary.marker_size() + 2;
+    if tag.size_px < bits {
+        return Err(UpicError::InvalidConfig(format!(
+            "{:?} tags need at least {} pixels, got {}",
+            tag.family, bits, tag.size_px
+        )));
+    }
+    let mut marker = Mat::default();
+    objdetect::generate_image_marker(&dictionary, tag.id, tag.size_px, &mut marker, 1)?;
+    Ok(marker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TagDetector;
+    use crate::tag_detector::{Config, MockFrameSource};
+    use std::thread;
+    use std::time::Duration;
+
+    fn spec(family: TagFamily, id: i32, center: [f64; 2], rotation_deg: f64) -> TagSpec {
+        TagSpec {
+            family,
+            id,
+            size_px: 96,
+            center,
+            rotation_deg,
+        }
+    }
+
+    #[test]
+    fn test_detector_decodes_rendered_tags() {
+        let tags = [
+            spec(TagFamily::Tag36h11, 586, [150.0, 130.0], 0.0),
+            spec(TagFamily::Tag36h11, 42, [470.0, 140.0], 30.0),
+            spec(TagFamily::Tag25h9, 7, [320.0, 350.0], -75.0),
+        ];
+        let frame = render_multi((640, 480), &tags).unwrap();
+        let source = MockFrameSource::new(vec![frame]);
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        detector
+            .update_config(|config| {
+                config.families = vec![TagFamily::Tag36h11, TagFamily::Tag25h9];
+                config.single_tag_mode = false;
+            })
+            .unwrap();
+        detector.apriltag_detect_start().unwrap();
+        thread::sleep(Duration::from_millis(200));
+        let mut detections = detector.all_detections();
+        detector.apriltag_detect_end_join().unwrap();
+
+        assert_eq!(detections.len(), tags.len());
+        for tag in tags {
+            let position = detections
+                .iter()
+                .position(|d| d.id == tag.id && d.family == tag.family)
+                .unwrap_or_else(|| panic!("{:?} not decoded", tag));
+            let detection = detections.swap_remove(position);
+            for axis in 0..2 {
+                assert!(
+                    (detection.center[axis] - tag.center[axis]).abs() < 1.0,
+                    "{:?} decoded at {:?}",
+                    tag,
+                    detection.center
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_single_tag_frame() {
+        let frame =
+            render_tag_frame(TagFamily::Tag16h5, 3, 80, [200.0, 100.0], (320, 240), 45.0).unwrap();
+        assert_eq!((frame.cols(), frame.rows()), (320, 240));
+        let detector = TagDetector::with_config(
+            Config::builder()
+                .families(vec![TagFamily::Tag16h5])
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+        let detections = detector.process_frame(&frame).unwrap();
+        assert_eq!(detections.iter().map(|d| d.id).collect::<Vec<_>>(), [3]);
+    }
+
+    #[test]
+    fn test_unrenderable_tags() {
+        let render = |family, id, size_px| {
+            render_tag_frame(family, id, size_px, [50.0, 50.0], (100, 100), 0.0)
+        };
+        for (family, id, size_px) in [
+            (TagFamily::TagStandard41h12, 0, 60),
+            (TagFamily::Tag36h11, 587, 60),
+            (TagFamily::Tag25h9, -1, 60),
+            (TagFamily::Tag36h11, 0, 7),
+        ] {
+            assert!(matches!(
+                render(family, id, size_px),
+                Err(UpicError::InvalidConfig(_))
+            ));
+        }
+        assert!(render(TagFamily::Tag36h11, 586, 8).is_ok());
+    }
+}
@@ -0,0 +1,42 @@
+//! Stop waiting on a `MovingTransition` as soon as the camera sees tag 3.
+//!
+//! Run with `cargo run --example tag_breaker --features vision -- [camera]`,
+//! camera index 0 by default, and hold tag 3 in front of the camera.
+
+use std::error::Error;
+
+use mentabotix_rs::{Botix, BreakerResult, MovingTransition};
+use upic_rs::TagDetector;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::init();
+    let camera = std::env::args()
+        .nth(1)
+        .map(|arg| arg.parse())
+        .transpose()?
+        .unwrap_or(0);
+
+    let mut detector = TagDetector::new(Some(camera), None)?;
+    detector.apriltag_detect_start()?;
+
+    // The breaker keeps its own handle on the published tag ID, so it can be
+    // stored in the transition while the detector goes elsewhere
+    let sees_tag_3 = detector.breaker_for(&[3]);
+    let transition = MovingTransition::new(10.0)?.with_breaker(move || {
+        if sees_tag_3() {
+            BreakerResult::Bool(true)
+        } else {
+            BreakerResult::Placeholder
+        }
+    });
+
+    println!("Waiting up to 10 s for tag 3...");
+    let breaker = transition.breaker.as_deref().expect("breaker was just set");
+    match Botix::wait_with_breaker(transition.duration, transition.check_interval, breaker) {
+        BreakerResult::Bool(true) => println!("Tag 3 in view, transition broken"),
+        _ => println!("Timed out without seeing tag 3"),
+    }
+
+    detector.apriltag_detect_end_join()?;
+    Ok(())
+}
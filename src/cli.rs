@@ -102,7 +102,8 @@ pub enum Commands {
         team_color: Option<String>,
     },
 
-    /// Test hardware devices: mot, adc, io, mpu, cam, pow, all
+    /// Test hardware devices: mot, adc, io, mpu, cam, pow, all, or `self`
+    /// for the end-to-end startup self-test (pulses every motor)
     Check {
         /// Devices to test (default: all)
         #[arg(default_value = "all")]
@@ -115,6 +116,14 @@ pub enum Commands {
         /// Camera ID for camera test
         #[arg(short = 'c', long)]
         camera: Option<i32>,
+
+        /// ID of the fixture tag the self-test expects in view
+        #[arg(long, default_value = "0")]
+        fixture_tag: i32,
+
+        /// Save the self-test's camera snapshot to this path
+        #[arg(long)]
+        snapshot: Option<PathBuf>,
     },
 
     /// Read sensor data continuously
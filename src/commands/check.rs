@@ -1,24 +1,31 @@
 use crate::config::AppConfig;
+use crate::self_test::{SelfTestConfig, SelfTestHandles, self_test};
+use bdmc_rs::controller::{CloseLoopController, Direction, MotorInfo};
+use std::path::PathBuf;
 
 pub fn cmd_check(
     app_config: AppConfig,
     devices: Vec<String>,
     port: Option<String>,
-    _camera: Option<i32>,
+    camera: Option<i32>,
+    fixture_tag: i32,
+    snapshot: Option<PathBuf>,
 ) {
-    use bdmc_rs::controller::CloseLoopController;
-
     let test_all = devices.iter().any(|d| d == "all");
     let test_mot = test_all || devices.iter().any(|d| d == "mot");
     let test_adc = test_all || devices.iter().any(|d| d == "adc");
     let test_io = test_all || devices.iter().any(|d| d == "io");
     let test_mpu = test_all || devices.iter().any(|d| d == "mpu");
+    // Moves the motors, so it only runs when asked for by name
+    let test_self = devices.iter().any(|d| d == "self");
 
     println!("{:=^40}", " Hardware Check ");
     let mut all_ok = true;
 
     if test_mot {
-        let port_name = port.unwrap_or_else(|| app_config.motion.port.clone());
+        let port_name = port
+            .clone()
+            .unwrap_or_else(|| app_config.motion.port.clone());
         print!("  MOTOR  ({:30}) ... ", port_name);
         match CloseLoopController::new(None, None, None, Some(&port_name)) {
             Ok(mut c) => {
@@ -47,5 +54,73 @@ pub fn cmd_check(
         println!("SKIP (no uptechstar hardware on this host)");
     }
 
+    if test_self {
+        println!("{:-^40}", " Self-test ");
+        all_ok &= run_self_test(&app_config, port, camera, fixture_tag, snapshot);
+    }
+
     println!("{:=^40}", if all_ok { " ALL OK " } else { " FAILURES " });
 }
+
+/// Run the startup self-test on the configured motors and camera.
+fn run_self_test(
+    app_config: &AppConfig,
+    port: Option<String>,
+    camera: Option<i32>,
+    fixture_tag: i32,
+    snapshot: Option<PathBuf>,
+) -> bool {
+    let motion = &app_config.motion;
+    let motor_infos = [
+        motion.motor_fr,
+        motion.motor_fl,
+        motion.motor_rr,
+        motion.motor_rl,
+    ]
+    .iter()
+    .map(|&(code_sign, direction)| MotorInfo::new(code_sign, direction as Direction))
+    .collect();
+    let port_name = port.unwrap_or_else(|| motion.port.clone());
+    let mut controller =
+        match CloseLoopController::new(Some(motor_infos), None, None, Some(&port_name)) {
+            Ok(c) => Some(c),
+            Err(e) => {
+                println!("  MOTOR  ({:30}) ... FAIL ({})", port_name, e);
+                None
+            }
+        };
+
+    #[cfg(feature = "vision")]
+    let mut detector = match upic_rs::TagDetector::new(camera, None) {
+        Ok(d) => Some(d),
+        Err(e) => {
+            println!("  CAMERA                   ... FAIL ({})", e);
+            None
+        }
+    };
+    #[cfg(not(feature = "vision"))]
+    let _ = camera;
+
+    let handles = SelfTestHandles {
+        #[cfg(feature = "vision")]
+        detector: detector.as_mut(),
+        // Blocked on: ADC/IO samplers via uptechstar-rs.
+        sensors: None,
+        controller: controller.as_mut(),
+    };
+    let config = SelfTestConfig {
+        fixture_tag,
+        snapshot,
+        ..SelfTestConfig::default()
+    };
+    let report = self_test(handles, config);
+    print!("{}", report);
+
+    if let Some(c) = controller.as_mut() {
+        c.close();
+    }
+    let opened = controller.is_some();
+    #[cfg(feature = "vision")]
+    let opened = opened && detector.is_some();
+    opened && report.passed()
+}
@@ -104,6 +104,8 @@ pub fn cmd_run(
 
     match Botix::build_full(controller, all_states, all_transitions) {
         Ok(mut botix) => {
+            // The stage-check schemas loop back to the stage check.
+            botix.set_allow_cycles(true);
             info!("Mission built. Executing...");
             match botix.execute() {
                 Ok(()) => info!("Mission complete."),
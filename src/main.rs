@@ -10,6 +10,7 @@ mod compile;
 mod config;
 mod constant;
 mod judgers;
+mod self_test;
 mod signal_light;
 mod static_utils;
 use clap::Parser;
@@ -57,7 +58,9 @@ fn main() {
             device,
             port,
             camera,
-        } => commands::cmd_check(app_config, device, port, camera),
+            fixture_tag,
+            snapshot,
+        } => commands::cmd_check(app_config, device, port, camera, fixture_tag, snapshot),
         Commands::Read {
             devices,
             use_screen,
@@ -0,0 +1,580 @@
+//! Startup self-test — one pass over camera, detection, judge and motors.
+//!
+//! Meant to run right before a match: every stage runs even when an earlier
+//! one failed, and the [`SelfTestReport`] keeps per-stage timings and the
+//! evidence each stage collected (snapshot path, decision margin, sensor
+//! samples, wire commands).
+
+use crate::judgers::SensorData;
+use bdmc_rs::controller::CloseLoopController;
+use log::{info, warn};
+use mentabotix_rs::transition::BreakerResult;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+#[cfg(feature = "vision")]
+use upic_rs::TagDetector;
+
+/// Thresholds and timings of [`self_test`].
+#[derive(Debug, Clone)]
+// The camera and detection fields are only read with the vision feature
+#[cfg_attr(not(feature = "vision"), allow(dead_code))]
+pub struct SelfTestConfig {
+    /// ID of the fixture tag mounted inside the camera's view.
+    pub fixture_tag: i32,
+    /// Darkest accepted mean frame brightness (0–255).
+    pub min_brightness: f64,
+    /// Brightest accepted mean frame brightness (0–255).
+    pub max_brightness: f64,
+    /// Slowest accepted average frame time.
+    pub max_frame_time: Duration,
+    /// How long to wait for the camera's first frame.
+    pub frame_timeout: Duration,
+    /// How long to wait for the fixture tag.
+    pub detect_timeout: Duration,
+    /// Speed each motor is pulsed with, before its direction is applied.
+    pub pulse_speed: f64,
+    /// How long each motor is pulsed.
+    pub pulse: Duration,
+    /// Smallest measured velocity that counts as the motor turning.
+    pub min_velocity: f64,
+    /// Where to save a camera snapshot, if anywhere.
+    pub snapshot: Option<PathBuf>,
+}
+
+impl Default for SelfTestConfig {
+    fn default() -> Self {
+        Self {
+            fixture_tag: 0,
+            min_brightness: 20.0,
+            max_brightness: 250.0,
+            max_frame_time: Duration::from_millis(100),
+            frame_timeout: Duration::from_secs(3),
+            detect_timeout: Duration::from_secs(2),
+            pulse_speed: 500.0,
+            pulse: Duration::from_millis(200),
+            min_velocity: 50.0,
+            snapshot: None,
+        }
+    }
+}
+
+/// The hardware [`self_test`] exercises; `None` skips the matching stages.
+pub struct SelfTestHandles<'a> {
+    /// Camera and detector; detection is started if it isn't running and
+    /// stopped again afterwards.
+    #[cfg(feature = "vision")]
+    pub detector: Option<&'a mut TagDetector>,
+    /// Live sensor samplers the judge stage reads.
+    pub sensors: Option<Arc<dyn SensorData>>,
+    /// Motor controller with its serial port open.
+    pub controller: Option<&'a mut CloseLoopController>,
+}
+
+/// Outcome of one stage.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StageStatus {
+    Passed,
+    Failed(String),
+    /// The stage had nothing to test, e.g. no camera was given.
+    Skipped(String),
+}
+
+/// One stage of a [`SelfTestReport`].
+#[derive(Debug, Clone)]
+pub struct StageReport {
+    pub name: &'static str,
+    pub status: StageStatus,
+    pub elapsed: Duration,
+    /// What the stage observed, one `key: value` line per item.
+    pub evidence: Vec<String>,
+}
+
+/// Result of [`self_test`], one entry per stage in the order they ran.
+#[derive(Debug, Clone, Default)]
+pub struct SelfTestReport {
+    pub stages: Vec<StageReport>,
+}
+
+impl SelfTestReport {
+    /// Whether no stage failed; skipped stages don't count as failures.
+    pub fn passed(&self) -> bool {
+        !self
+            .stages
+            .iter()
+            .any(|s| matches!(s.status, StageStatus::Failed(_)))
+    }
+
+    /// The stage named `name`, if it ran.
+    #[allow(dead_code)]
+    pub fn stage(&self, name: &str) -> Option<&StageReport> {
+        self.stages.iter().find(|s| s.name == name)
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for stage in &self.stages {
+            let status = match &stage.status {
+                StageStatus::Passed => "OK".to_string(),
+                StageStatus::Failed(reason) => format!("FAIL ({})", reason),
+                StageStatus::Skipped(reason) => format!("SKIP ({})", reason),
+            };
+            writeln!(
+                f,
+                "  {:<9} {:>7.3}s ... {}",
+                stage.name.to_uppercase(),
+                stage.elapsed.as_secs_f64(),
+                status
+            )?;
+            for line in &stage.evidence {
+                writeln!(f, "      {}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Collects the evidence of a stage while it runs.
+struct Stage {
+    name: &'static str,
+    started: Instant,
+    evidence: Vec<String>,
+}
+
+impl Stage {
+    fn start(name: &'static str) -> Self {
+        info!("Self-test stage {} started", name);
+        Self {
+            name,
+            started: Instant::now(),
+            evidence: Vec::new(),
+        }
+    }
+
+    fn note(&mut self, line: String) {
+        self.evidence.push(line);
+    }
+
+    fn finish(self, status: StageStatus) -> StageReport {
+        match &status {
+            StageStatus::Failed(reason) => {
+                warn!("Self-test stage {} failed: {}", self.name, reason)
+            }
+            _ => info!("Self-test stage {}: {:?}", self.name, status),
+        }
+        StageReport {
+            name: self.name,
+            status,
+            elapsed: self.started.elapsed(),
+            evidence: self.evidence,
+        }
+    }
+}
+
+/// Run the camera, detection, judge and motor stages in that order.
+///
+/// A failing stage never stops the later ones; read the outcome from
+/// [`SelfTestReport::passed`] and the per-stage entries.
+pub fn self_test(handles: SelfTestHandles<'_>, config: SelfTestConfig) -> SelfTestReport {
+    let mut report = SelfTestReport::default();
+
+    #[cfg(feature = "vision")]
+    {
+        let (camera, detection) = vision_stages(handles.detector, &config);
+        report.stages.push(camera);
+        report.stages.push(detection);
+    }
+    #[cfg(not(feature = "vision"))]
+    {
+        let reason = || StageStatus::Skipped("built without the vision feature".to_string());
+        report.stages.push(Stage::start("camera").finish(reason()));
+        report
+            .stages
+            .push(Stage::start("detection").finish(reason()));
+    }
+
+    report.stages.push(judge_stage(handles.sensors));
+    report.stages.push(motor_stage(handles.controller, &config));
+    report
+}
+
+#[cfg(feature = "vision")]
+fn vision_stages(
+    detector: Option<&mut TagDetector>,
+    config: &SelfTestConfig,
+) -> (StageReport, StageReport) {
+    let Some(detector) = detector else {
+        let skipped = || StageStatus::Skipped("no camera".to_string());
+        return (
+            Stage::start("camera").finish(skipped()),
+            Stage::start("detection").finish(skipped()),
+        );
+    };
+
+    let started_here = !detector.is_detecting();
+    let camera = camera_stage(detector, config);
+    let detection = detection_stage(detector, config);
+    if started_here && let Err(e) = detector.apriltag_detect_end_join() {
+        warn!("Stopping detection after the self-test failed: {}", e);
+    }
+    (camera, detection)
+}
+
+#[cfg(feature = "vision")]
+fn camera_stage(detector: &mut TagDetector, config: &SelfTestConfig) -> StageReport {
+    let mut stage = Stage::start("camera");
+    if !detector.is_detecting()
+        && let Err(e) = detector.apriltag_detect_start()
+    {
+        return stage.finish(StageStatus::Failed(format!("camera not ready: {}", e)));
+    }
+
+    let deadline = Instant::now() + config.frame_timeout;
+    while detector.stats().frames_processed == 0 {
+        if Instant::now() >= deadline {
+            return stage.finish(StageStatus::Failed(format!(
+                "no frame within {:?}",
+                config.frame_timeout
+            )));
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let mut problems = Vec::new();
+    match detector.frame_brightness() {
+        Ok(brightness) => {
+            stage.note(format!("brightness: {:.1}", brightness));
+            if !(config.min_brightness..=config.max_brightness).contains(&brightness) {
+                problems.push(format!(
+                    "brightness {:.1} outside {}..={}",
+                    brightness, config.min_brightness, config.max_brightness
+                ));
+            }
+        }
+        Err(e) => problems.push(format!("no frame to measure: {}", e)),
+    }
+
+    let frame_time = detector.stats().avg_frame_time;
+    stage.note(format!("frame time: {:?}", frame_time));
+    if frame_time > config.max_frame_time {
+        problems.push(format!(
+            "frame time {:?} above {:?}",
+            frame_time, config.max_frame_time
+        ));
+    }
+
+    if let Some(path) = &config.snapshot {
+        match detector.capture_frame_to(path) {
+            Ok(()) => stage.note(format!("snapshot: {}", path.display())),
+            Err(e) => problems.push(format!("snapshot {} failed: {}", path.display(), e)),
+        }
+    }
+
+    stage.finish(status_of(problems))
+}
+
+#[cfg(feature = "vision")]
+fn detection_stage(detector: &TagDetector, config: &SelfTestConfig) -> StageReport {
+    let mut stage = Stage::start("detection");
+    if !detector.is_detecting() {
+        return stage.finish(StageStatus::Failed("detection not running".to_string()));
+    }
+    if !detector.wait_for_tag_id(config.fixture_tag, config.detect_timeout) {
+        return stage.finish(StageStatus::Failed(format!(
+            "fixture tag {} not seen within {:?}",
+            config.fixture_tag, config.detect_timeout
+        )));
+    }
+
+    stage.note(format!("fixture tag: {}", config.fixture_tag));
+    let detection = detector
+        .all_detections()
+        .into_iter()
+        .chain(detector.latest_detection())
+        .find(|d| d.id == config.fixture_tag);
+    if let Some(detection) = detection {
+        stage.note(format!("decision margin: {:.1}", detection.decision_margin));
+        stage.note(format!(
+            "center: ({:.0}, {:.0})",
+            detection.center[0], detection.center[1]
+        ));
+    }
+    stage.finish(StageStatus::Passed)
+}
+
+fn judge_stage(sensors: Option<Arc<dyn SensorData>>) -> StageReport {
+    let mut stage = Stage::start("judge");
+    let Some(sensors) = sensors else {
+        return stage.finish(StageStatus::Skipped("no sensors".to_string()));
+    };
+
+    // A judge as trivial as a breaker gets: any IO channel triggered
+    let judge: Arc<dyn Fn() -> BreakerResult + Send + Sync> = {
+        let sensors = sensors.clone();
+        Arc::new(move || BreakerResult::Bool(sensors.io_all().iter().any(|&v| v != 0.0)))
+    };
+
+    let adc = sensors.adc_all();
+    let io = sensors.io_all();
+    stage.note(format!("adc: {:?}", adc));
+    stage.note(format!("io: {:?}", io));
+
+    let mut problems = Vec::new();
+    if adc.len() != 10 {
+        problems.push(format!("expected 10 ADC channels, got {}", adc.len()));
+    }
+    if let Some(v) = adc.iter().find(|v| !(0.0..=4095.0).contains(*v)) {
+        problems.push(format!("ADC value {} outside 0..=4095", v));
+    }
+    if io.len() != 8 {
+        problems.push(format!("expected 8 IO channels, got {}", io.len()));
+    }
+    if let Some(v) = io.iter().find(|&&v| v != 0.0 && v != 1.0) {
+        problems.push(format!("IO value {} is not 0 or 1", v));
+    }
+
+    let judged = Instant::now();
+    let result = judge();
+    stage.note(format!("judge: {} in {:?}", result, judged.elapsed()));
+    stage.finish(status_of(problems))
+}
+
+fn motor_stage(
+    controller: Option<&mut CloseLoopController>,
+    config: &SelfTestConfig,
+) -> StageReport {
+    let mut stage = Stage::start("motors");
+    let Some(controller) = controller else {
+        return stage.finish(StageStatus::Skipped("no motor controller".to_string()));
+    };
+    if controller.serial().is_none() {
+        return stage.finish(StageStatus::Failed("no serial port open".to_string()));
+    }
+
+    let infos = controller.motor_infos().clone();
+    let mut problems = Vec::new();
+    for (i, info) in infos.iter().enumerate() {
+        let mut speeds = vec![0.0; infos.len()];
+        speeds[i] = config.pulse_speed;
+
+        let sent = Instant::now();
+        if let Err(e) = controller.set_motors_speed(&speeds) {
+            problems.push(format!("motor {} command rejected: {}", info.code_sign, e));
+            continue;
+        }
+        // The history holds exactly what went out on the wire
+        match controller.history(1).pop() {
+            Some((at, accepted)) if at >= sent => {
+                let wire: String = infos
+                    .iter()
+                    .zip(&accepted)
+                    .map(|(m, &speed)| format!("{}v{}\\r", m.code_sign, speed * m.direction as i32))
+                    .collect();
+                stage.note(format!("motor {} wire: {}", info.code_sign, wire));
+            }
+            _ => {
+                problems.push(format!("motor {} command not sent", info.code_sign));
+                continue;
+            }
+        }
+
+        thread::sleep(config.pulse);
+        let expected = (config.pulse_speed * info.direction as f64).signum();
+        match controller.query_velocity(info.code_sign) {
+            Ok(velocity) => {
+                stage.note(format!("motor {} velocity: {}", info.code_sign, velocity));
+                if velocity.abs() < config.min_velocity || velocity.signum() != expected {
+                    problems.push(format!(
+                        "motor {} measured {} after a pulse of {}",
+                        info.code_sign, velocity, speeds[i]
+                    ));
+                }
+            }
+            // Not every driver answers GN; the accepted command is the fallback
+            Err(e) => stage.note(format!(
+                "motor {} velocity: unverified ({}), command accepted",
+                info.code_sign, e
+            )),
+        }
+    }
+
+    if let Err(e) = controller.set_motors_speed(&vec![0.0; infos.len()]) {
+        problems.push(format!("stopping the motors failed: {}", e));
+    }
+    stage.finish(status_of(problems))
+}
+
+fn status_of(problems: Vec<String>) -> StageStatus {
+    if problems.is_empty() {
+        StageStatus::Passed
+    } else {
+        StageStatus::Failed(problems.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::judgers::NullSensor;
+    use bdmc_rs::controller::MotorInfo;
+    use bdmc_rs::sim::{SimConfig, SimulatedDriver};
+
+    fn config() -> SelfTestConfig {
+        SelfTestConfig {
+            pulse: Duration::from_millis(50),
+            ..SelfTestConfig::default()
+        }
+    }
+
+    fn sim_controller(driver: SimulatedDriver) -> CloseLoopController {
+        let infos = vec![MotorInfo::new(1, 1), MotorInfo::new(2, -1)];
+        let mut controller = CloseLoopController::new(Some(infos), None, None, None).unwrap();
+        controller.attach_serial(Box::new(driver));
+        controller
+    }
+
+    fn handles(controller: Option<&mut CloseLoopController>) -> SelfTestHandles<'_> {
+        SelfTestHandles {
+            #[cfg(feature = "vision")]
+            detector: None,
+            sensors: Some(Arc::new(NullSensor)),
+            controller,
+        }
+    }
+
+    #[test]
+    fn test_motor_pulses_against_simulated_driver() {
+        let sim = SimConfig {
+            time_constant: Duration::from_millis(5),
+            ..SimConfig::default()
+        };
+        let driver = SimulatedDriver::new(&[1, 2], sim);
+        let handle = driver.handle();
+        let mut controller = sim_controller(driver);
+
+        let report = self_test(handles(Some(&mut controller)), config());
+        assert!(report.passed(), "{}", report);
+        let motors = report.stage("motors").unwrap();
+        assert_eq!(motors.status, StageStatus::Passed);
+        assert!(
+            motors
+                .evidence
+                .contains(&"motor 2 wire: 1v0\\r2v-500\\r".to_string())
+        );
+        assert!(
+            motors
+                .evidence
+                .contains(&"motor 2 velocity: -500".to_string())
+        );
+        assert_eq!(handle.motor(1).unwrap().target, 0.0);
+        assert_eq!(handle.motor(2).unwrap().target, 0.0);
+
+        let judge = report.stage("judge").unwrap();
+        assert_eq!(judge.status, StageStatus::Passed);
+        assert!(judge.evidence.iter().any(|e| e.starts_with("judge: false")));
+    }
+
+    #[test]
+    fn test_failing_stage_does_not_stop_later_ones() {
+        let driver = SimulatedDriver::new(&[1, 2], SimConfig::default());
+        let handle = driver.handle();
+        handle.set_fault(1, Some(3));
+        let mut controller = sim_controller(driver);
+
+        let report = self_test(handles(Some(&mut controller)), config());
+        assert!(!report.passed());
+        let names: Vec<&str> = report.stages.iter().map(|s| s.name).collect();
+        assert_eq!(names, ["camera", "detection", "judge", "motors"]);
+        let StageStatus::Failed(reason) = &report.stage("motors").unwrap().status else {
+            panic!("{}", report);
+        };
+        assert!(reason.contains("motor 1 measured 0"), "{}", reason);
+        assert!(!reason.contains("motor 2"), "{}", reason);
+        assert_eq!(handle.motor(2).unwrap().target, 0.0);
+
+        // Without hardware every stage is skipped, which is not a failure
+        let empty = SelfTestHandles {
+            #[cfg(feature = "vision")]
+            detector: None,
+            sensors: None,
+            controller: None,
+        };
+        let report = self_test(empty, config());
+        assert!(report.passed());
+        assert!(
+            report
+                .stages
+                .iter()
+                .all(|s| matches!(s.status, StageStatus::Skipped(_)))
+        );
+
+        // A controller without a serial port fails its stage
+        let mut closed = CloseLoopController::new(None, None, None, None).unwrap();
+        let report = self_test(handles(Some(&mut closed)), config());
+        assert_eq!(
+            report.stage("motors").unwrap().status,
+            StageStatus::Failed("no serial port open".to_string())
+        );
+    }
+
+    #[cfg(feature = "testing")]
+    #[test]
+    fn test_camera_and_fixture_tag_from_rendered_frames() {
+        use upic_rs::tag_detector::{MockFrameSource, TagFamily};
+        use upic_rs::testing::render_tag_frame;
+
+        let frame =
+            render_tag_frame(TagFamily::Tag36h11, 7, 120, [320.0, 240.0], (640, 480), 0.0).unwrap();
+        let source = MockFrameSource::new(vec![frame]);
+        let mut detector = TagDetector::with_source(Box::new(source)).unwrap();
+        let snapshot = std::env::temp_dir().join("kazu_self_test_snapshot.png");
+
+        let config = SelfTestConfig {
+            fixture_tag: 7,
+            max_brightness: 255.0,
+            max_frame_time: Duration::from_secs(1),
+            snapshot: Some(snapshot.clone()),
+            ..config()
+        };
+        let report = self_test(
+            SelfTestHandles {
+                detector: Some(&mut detector),
+                sensors: None,
+                controller: None,
+            },
+            config.clone(),
+        );
+        assert_eq!(report.stage("camera").unwrap().status, StageStatus::Passed);
+        assert_eq!(
+            report.stage("detection").unwrap().status,
+            StageStatus::Passed
+        );
+        assert!(snapshot.exists());
+        // Detection was started by the self-test, so it is stopped again
+        assert!(!detector.is_detecting());
+        std::fs::remove_file(&snapshot).unwrap();
+
+        // A fixture tag that isn't in view fails only the detection stage
+        let report = self_test(
+            SelfTestHandles {
+                detector: Some(&mut detector),
+                sensors: None,
+                controller: None,
+            },
+            SelfTestConfig {
+                fixture_tag: 8,
+                detect_timeout: Duration::from_millis(300),
+                snapshot: None,
+                ..config
+            },
+        );
+        assert_eq!(report.stage("camera").unwrap().status, StageStatus::Passed);
+        assert!(matches!(
+            report.stage("detection").unwrap().status,
+            StageStatus::Failed(_)
+        ));
+    }
+}